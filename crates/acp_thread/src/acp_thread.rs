@@ -2261,14 +2261,18 @@ impl AcpThread {
             }),
             None => Task::ready(None).shared(),
         };
-        let env = cx.spawn(async move |_, _| {
-            let mut env = env.await.unwrap_or_default();
-            // Disables paging for `git` and hopefully other commands
-            env.insert("PAGER".into(), "".into());
-            for var in extra_env {
-                env.insert(var.name, var.value);
+        // Disables paging for `git` and hopefully other commands
+        let mut task_env = collections::HashMap::from_iter([("PAGER".into(), "".into())]);
+        for var in extra_env {
+            task_env.insert(var.name, var.value);
+        }
+        let env = cx.spawn({
+            let task_env = task_env.clone();
+            async move |_, _| {
+                let mut env = env.await.unwrap_or_default();
+                env.extend(task_env);
+                env
             }
-            env
         });
 
         let project = self.project.clone();
@@ -2299,6 +2303,7 @@ impl AcpThread {
                                 args: task_args,
                                 cwd: cwd.clone(),
                                 env,
+                                task_env,
                                 ..Default::default()
                             },
                             cx,
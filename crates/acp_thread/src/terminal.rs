@@ -187,11 +187,17 @@ pub async fn create_terminal_entity(
         Default::default()
     };
 
-    // Disable pagers so agent/terminal commands don't hang behind interactive UIs
-    env.insert("PAGER".into(), "".into());
-    // Override user core.pager (e.g. delta) which Git prefers over PAGER
-    env.insert("GIT_PAGER".into(), "cat".into());
-    env.extend(env_vars);
+    let task_env = collections::HashMap::from_iter(
+        [
+            // Disable pagers so agent/terminal commands don't hang behind interactive UIs
+            ("PAGER".into(), "".into()),
+            // Override user core.pager (e.g. delta) which Git prefers over PAGER
+            ("GIT_PAGER".into(), "cat".into()),
+        ]
+        .into_iter()
+        .chain(env_vars),
+    );
+    env.extend(task_env.clone());
 
     // Use remote shell or default system shell, as appropriate
     let shell = project
@@ -217,6 +223,7 @@ pub async fn create_terminal_entity(
                     args: task_args,
                     cwd,
                     env,
+                    task_env,
                     ..Default::default()
                 },
                 cx,
@@ -1,6 +1,7 @@
 use collections::HashMap;
 pub use ipc_channel::ipc;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Serialize, Deserialize)]
 pub struct IpcHandshake {
@@ -14,13 +15,52 @@ pub enum CliRequest {
         paths: Vec<String>,
         urls: Vec<String>,
         diff_paths: Vec<[String; 2]>,
+        /// The local, base and remote versions of a file, and the path to write their merge to.
+        merge_paths: Option<[String; 4]>,
         wsl: Option<String>,
         wait: bool,
         open_new_workspace: Option<bool>,
         reuse: bool,
+        /// Brings the window that has the paths open to the front, or the frontmost window without paths, without
+        /// opening anything.
+        focus: bool,
         env: Option<HashMap<String, String>>,
         user_data_dir: Option<String>,
+        /// Quit once the last window closes, even when configured to run in the background.
+        no_daemon: bool,
     },
+    /// Manages the installed extensions, without opening windows.
+    Extensions { command: ExtensionCommand },
+    /// Manages the sessions saved under a name.
+    Sessions { command: SessionCommand },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ExtensionCommand {
+    /// Installs the extension in a directory, or with an ID.
+    Install {
+        id_or_path: String,
+    },
+    Uninstall {
+        id: String,
+    },
+    List,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SessionCommand {
+    /// Saves the workspaces of the open windows under a name.
+    Save {
+        name: String,
+    },
+    /// Opens the workspaces of the session saved under a name.
+    Restore {
+        name: String,
+    },
+    Delete {
+        name: String,
+    },
+    List,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,3 +74,26 @@ pub enum CliResponse {
 /// When Vector starts not as an *.app but as a binary (e.g. local development),
 /// there's a possibility to tell it to behave "regularly".
 pub const FORCE_CLI_MODE_ENV_VAR_NAME: &str = "VECTOR_FORCE_CLI_MODE";
+
+/// The prefix of the URLs opening a file as it was at a git revision, `vector://git/file/<revision>?path=<path>`.
+pub const GIT_FILE_URL_PREFIX: &str = "vector://git/file/";
+
+/// Returns the URL opening a file as it was at a git revision, when `argument` is `path@revision` and the path is
+/// an existing file. Paths that exist as given, `@` included, are never parsed as revisions.
+pub fn git_file_url(argument: &str) -> Option<String> {
+    if Path::new(argument).exists() {
+        return None;
+    }
+    // Revisions may contain `@` themselves, like `HEAD@{1}`, so the path ends at the first `@` after an existing file.
+    argument.match_indices('@').find_map(|(ix, _)| {
+        let revision = &argument[ix + 1..];
+        let path = Path::new(&argument[..ix]).canonicalize().ok()?;
+        (!revision.is_empty() && path.is_file()).then(|| {
+            format!(
+                "{GIT_FILE_URL_PREFIX}{}?path={}",
+                urlencoding::encode(revision),
+                urlencoding::encode(&path.to_string_lossy())
+            )
+        })
+    })
+}
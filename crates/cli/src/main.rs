@@ -8,8 +8,14 @@
 )]
 
 use anyhow::{Context as _, Result};
-use clap::Parser;
-use cli::{CliRequest, CliResponse, IpcHandshake, ipc::IpcOneShotServer};
+use clap::{CommandFactory as _, Parser};
+use clap_complete::engine::{
+    ArgValueCandidates, ArgValueCompleter, CompletionCandidate, PathCompleter, ValueCompleter as _,
+};
+use clap_complete::env::EnvCompleter;
+use cli::{
+    CliRequest, CliResponse, ExtensionCommand, IpcHandshake, SessionCommand, ipc::IpcOneShotServer,
+};
 use parking_lot::Mutex;
 use std::{
     env,
@@ -26,7 +32,17 @@ use util::paths::PathWithPosition;
 #[cfg(any(target_os = "linux", target_os = "freebsd"))]
 use std::io::IsTerminal;
 
-const URL_PREFIX: [&'static str; 5] = ["zed://", "http://", "https://", "file://", "ssh://"];
+const URL_PREFIX: [&'static str; 6] = [
+    "zed://",
+    "vector://",
+    "http://",
+    "https://",
+    "file://",
+    "ssh://",
+];
+
+/// The environment variable the completion scripts set when calling the CLI to complete a command.
+const COMPLETE_ENV_VAR: &str = "VECTOR_COMPLETE";
 
 struct Detect;
 
@@ -68,11 +84,19 @@ struct Args {
     #[arg(short, long, overrides_with_all = ["new", "reuse"])]
     add: bool,
     /// Create a new workspace
-    #[arg(short, long, overrides_with_all = ["add", "reuse"])]
+    #[arg(short, long, alias = "new-window", overrides_with_all = ["add", "reuse"])]
     new: bool,
-    /// Reuse an existing window, replacing its workspace
-    #[arg(short, long, overrides_with_all = ["add", "new"])]
+    /// Reuse the frontmost window, replacing its workspace
+    #[arg(short, long, alias = "reuse-window", overrides_with_all = ["add", "new"])]
     reuse: bool,
+    /// Bring the window that has the given paths open to the front, or the frontmost window when
+    /// no paths are given, without opening anything.
+    #[arg(long, conflicts_with_all = ["add", "new", "reuse", "wait", "goto"])]
+    focus: bool,
+    /// Open a file at a line and column in the window whose workspace contains it, or in the
+    /// frontmost window, e.g. `--goto src/main.rs:12:5`.
+    #[arg(long, value_name = "PATH:LINE:COL", conflicts_with_all = ["new", "reuse"])]
+    goto: Option<String>,
     /// Sets a custom directory for all user data (e.g., database, extensions, logs).
     /// This overrides the default platform-specific data directory location:
     #[cfg_attr(target_os = "macos", doc = "`~/Library/Application Support/Zed`.")]
@@ -83,16 +107,34 @@ struct Args {
     )]
     #[arg(long, value_name = "DIR")]
     user_data_dir: Option<String>,
+    /// Uses the named profile, which has its own settings, extensions and sessions, and runs its
+    /// own instance of Vector. Profiles are created when first used.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with = "user_data_dir",
+        add = ArgValueCandidates::new(profile_candidates)
+    )]
+    profile: Option<String>,
     /// The paths to open in Vector (space-separated).
     ///
     /// Use `path:line:column` syntax to open a file at the given line and column.
+    #[arg(add = ArgValueCompleter::new(complete_paths_to_open))]
     paths_with_position: Vec<String>,
     /// Print Vector's version and the app path.
     #[arg(short, long)]
     version: bool,
+    /// Print the script completing the arguments of `vector` in a shell, to source in its
+    /// startup file, e.g. `source <(vector --completions bash)` in `~/.bashrc`.
+    #[arg(long, value_name = "SHELL", exclusive = true)]
+    completions: Option<CompletionShell>,
     /// Run Vector in the foreground (useful for debugging)
     #[arg(long)]
     foreground: bool,
+    /// Quit Vector once the windows this opens are closed along with all other windows,
+    /// even when `on_last_window_closed` is `run_in_background`.
+    #[arg(long)]
+    no_daemon: bool,
     /// Custom path to Vector.app or the vector binary
     #[arg(long, alias = "zed")]
     vector: Option<PathBuf>,
@@ -118,6 +160,35 @@ struct Args {
     /// Pairs of file paths to diff. Can be specified multiple times.
     #[arg(long, action = clap::ArgAction::Append, num_args = 2, value_names = ["OLD_PATH", "NEW_PATH"])]
     diff: Vec<String>,
+    /// Merges the changes made to a base file in local and remote versions of it, and opens the
+    /// merge, with conflict markers around the conflicting changes, to resolve the conflicts.
+    ///
+    /// Waits until the merge is closed, and exits with a non-zero status if conflicts remain,
+    /// for use as a git mergetool.
+    #[arg(long, num_args = 4, value_names = ["LOCAL", "BASE", "REMOTE", "MERGED"])]
+    merge: Vec<String>,
+    /// Installs the extension in a directory, without opening windows.
+    #[arg(long, value_name = "ID|PATH", group = "extensions")]
+    install_extension: Option<String>,
+    /// Uninstalls the extension with an ID, without opening windows.
+    #[arg(long, value_name = "ID", group = "extensions")]
+    uninstall_extension: Option<String>,
+    /// Lists the installed extensions, without opening windows.
+    #[arg(long, group = "extensions")]
+    list_extensions: bool,
+    /// Opens the workspaces of the session saved under a name.
+    #[arg(long, value_name = "NAME", group = "sessions")]
+    session: Option<String>,
+    /// Saves the workspaces of the open windows under a name, to restore them later with
+    /// `--session`.
+    #[arg(long, value_name = "NAME", group = "sessions")]
+    save_session: Option<String>,
+    /// Deletes the session saved under a name.
+    #[arg(long, value_name = "NAME", group = "sessions")]
+    delete_session: Option<String>,
+    /// Lists the sessions saved under a name.
+    #[arg(long, group = "sessions")]
+    list_sessions: bool,
     /// Uninstall Zed from user system
     #[cfg(all(
         any(target_os = "linux", target_os = "macos"),
@@ -132,6 +203,56 @@ struct Args {
     askpass: Option<String>,
 }
 
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "pwsh")]
+    Powershell,
+}
+
+impl CompletionShell {
+    fn completer(self) -> &'static dyn EnvCompleter {
+        match self {
+            CompletionShell::Bash => &clap_complete::env::Bash,
+            CompletionShell::Zsh => &clap_complete::env::Zsh,
+            CompletionShell::Fish => &clap_complete::env::Fish,
+            CompletionShell::Powershell => &clap_complete::env::Powershell,
+        }
+    }
+}
+
+fn profile_candidates() -> Vec<CompletionCandidate> {
+    paths::profile_names()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Completes the paths to open with the recently opened workspaces, followed by the paths on disk.
+fn complete_paths_to_open(current: &OsStr) -> Vec<CompletionCandidate> {
+    let on_disk = PathCompleter::any().complete(current);
+    let Some(current) = current.to_str() else {
+        return on_disk;
+    };
+    let recent_workspaces = fs::read_to_string(paths::recent_workspaces_file()).unwrap_or_default();
+    let mut candidates = recent_workspaces
+        .lines()
+        .filter(|path| path.starts_with(current) && Path::new(path).exists())
+        .map(|path| CompletionCandidate::new(path).help(Some("recent workspace".into())))
+        .collect::<Vec<_>>();
+    for candidate in on_disk {
+        if !candidates
+            .iter()
+            .any(|recent| recent.get_value() == candidate.get_value())
+        {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
 /// Parses a path containing a position (e.g. `path:line:column`)
 /// and returns its canonicalized string representation.
 ///
@@ -299,6 +420,60 @@ mod tests {
         .unwrap();
         assert_eq!(result, expected);
     }
+
+    fn complete(args: &[&str], current_dir: &Path) -> Vec<String> {
+        let mut command = Args::command();
+        let args = args.iter().map(Into::into).collect::<Vec<_>>();
+        let arg_index = args.len() - 1;
+        clap_complete::engine::complete(&mut command, args, arg_index, Some(current_dir))
+            .unwrap()
+            .into_iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_completions() {
+        let temp_tree = TempTree::new(json!({
+            "src": { "main.rs": "" },
+            "README.md": "",
+        }));
+
+        let flags = complete(&["vector", "--comp"], temp_tree.path());
+        assert_eq!(flags, vec!["--completions"]);
+
+        let shells = complete(&["vector", "--completions", ""], temp_tree.path());
+        assert_eq!(shells, vec!["bash", "zsh", "fish", "powershell"]);
+
+        // Paths are completed relative to the working directory of the shell.
+        let paths = with_cwd(temp_tree.path(), || {
+            Ok(complete(&["vector", "s"], temp_tree.path()))
+        })
+        .unwrap();
+        assert!(
+            paths.iter().any(|path| path.starts_with("src")),
+            "expected src in {paths:?}"
+        );
+        assert!(!paths.iter().any(|path| path.starts_with("README")));
+    }
+
+    #[test]
+    fn test_completion_registration() {
+        let mut script = Vec::new();
+        CompletionShell::Bash
+            .completer()
+            .write_registration(
+                COMPLETE_ENV_VAR,
+                "vector",
+                "vector",
+                "/usr/bin/vector",
+                &mut script,
+            )
+            .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains(COMPLETE_ENV_VAR));
+        assert!(script.contains("/usr/bin/vector"));
+    }
 }
 
 fn parse_path_in_wsl(source: &str, wsl: &str) -> Result<String> {
@@ -347,6 +522,11 @@ fn parse_path_in_wsl(source: &str, wsl: &str) -> Result<String> {
 }
 
 fn main() -> Result<()> {
+    // Completes the command when called by the scripts printed by `--completions`.
+    clap_complete::CompleteEnv::with_factory(Args::command)
+        .var(COMPLETE_ENV_VAR)
+        .complete();
+
     #[cfg(unix)]
     util::prevent_root_execution();
 
@@ -375,9 +555,29 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    if let Some(shell) = args.completions {
+        let completer = env::current_exe().context("retrieving the CLI path")?;
+        shell.completer().write_registration(
+            COMPLETE_ENV_VAR,
+            "vector",
+            "vector",
+            &completer.to_string_lossy(),
+            &mut io::stdout(),
+        )?;
+        return Ok(());
+    }
+
     // Set custom data directory before any path operations
-    let user_data_dir = args.user_data_dir.clone();
-    if let Some(dir) = &user_data_dir {
+    let user_data_dir = if let Some(profile) = &args.profile {
+        anyhow::ensure!(
+            paths::is_valid_profile_name(profile),
+            "invalid profile name {profile:?}: profile names can only contain ASCII letters, digits, '-' and '_'"
+        );
+        Some(paths::set_profile(profile).to_string_lossy().into_owned())
+    } else {
+        args.user_data_dir.clone()
+    };
+    if let Some(dir) = &args.user_data_dir {
         paths::set_custom_data_dir(dir);
     }
 
@@ -420,7 +620,7 @@ fn main() -> Result<()> {
 
     let open_new_workspace = if args.new {
         Some(true)
-    } else if args.add {
+    } else if args.add || args.goto.is_some() {
         Some(false)
     } else {
         None
@@ -472,11 +672,52 @@ fn main() -> Result<()> {
         ]);
     }
 
+    let merge_paths = match args.merge.as_slice() {
+        [local, base, remote, merged] => Some([
+            parse_path_with_position(local)?,
+            parse_path_with_position(base)?,
+            parse_path_with_position(remote)?,
+            parse_path_with_position(merged)?,
+        ]),
+        _ => None,
+    };
+
+    let extension_command = if let Some(id_or_path) = &args.install_extension {
+        // Extensions in the current directory can be installed with a relative path.
+        let id_or_path = match Path::new(id_or_path).canonicalize() {
+            Ok(path) => path.to_string_lossy().into_owned(),
+            Err(_) => id_or_path.clone(),
+        };
+        Some(ExtensionCommand::Install { id_or_path })
+    } else if let Some(id) = &args.uninstall_extension {
+        Some(ExtensionCommand::Uninstall { id: id.clone() })
+    } else if args.list_extensions {
+        Some(ExtensionCommand::List)
+    } else {
+        None
+    };
+
+    let session_command = if let Some(name) = &args.session {
+        Some(SessionCommand::Restore { name: name.clone() })
+    } else if let Some(name) = &args.save_session {
+        Some(SessionCommand::Save { name: name.clone() })
+    } else if let Some(name) = &args.delete_session {
+        Some(SessionCommand::Delete { name: name.clone() })
+    } else if args.list_sessions {
+        Some(SessionCommand::List)
+    } else {
+        None
+    };
+
     #[cfg(target_os = "windows")]
     let wsl = args.wsl.as_ref();
     #[cfg(not(target_os = "windows"))]
     let wsl = None;
 
+    if let Some(goto) = &args.goto {
+        paths.push(parse_path_with_position(goto)?);
+    }
+
     for path in args.paths_with_position.iter() {
         if URL_PREFIX.iter().any(|&prefix| path.starts_with(prefix)) {
             urls.push(path.to_string());
@@ -492,6 +733,8 @@ fn main() -> Result<()> {
             anonymous_fd_tmp_files.push((file, tmp_file));
         } else if let Some(wsl) = wsl {
             urls.push(format!("file://{}", parse_path_in_wsl(path, wsl)?));
+        } else if let Some(url) = cli::git_file_url(path) {
+            urls.push(url);
         } else {
             paths.push(parse_path_with_position(path)?);
         }
@@ -523,17 +766,26 @@ fn main() -> Result<()> {
                 #[cfg(not(target_os = "windows"))]
                 let wsl = None;
 
-                tx.send(CliRequest::Open {
-                    paths,
-                    urls,
-                    diff_paths,
-                    wsl,
-                    wait: args.wait,
-                    open_new_workspace,
-                    reuse: args.reuse,
-                    env,
-                    user_data_dir: user_data_dir_for_thread,
-                })?;
+                if let Some(command) = extension_command {
+                    tx.send(CliRequest::Extensions { command })?;
+                } else if let Some(command) = session_command {
+                    tx.send(CliRequest::Sessions { command })?;
+                } else {
+                    tx.send(CliRequest::Open {
+                        paths,
+                        urls,
+                        diff_paths,
+                        wsl,
+                        wait: args.wait || merge_paths.is_some(),
+                        merge_paths,
+                        open_new_workspace,
+                        reuse: args.reuse,
+                        focus: args.focus,
+                        env,
+                        user_data_dir: user_data_dir_for_thread,
+                        no_daemon: args.no_daemon,
+                    })?;
+                }
 
                 while let Ok(response) = rx.recv() {
                     match response {
@@ -1,5 +1,6 @@
 use crate::{
     adapters::DebugAdapterBinary,
+    recording::DapRecorder,
     transport::{IoKind, LogKind, TransportDelegate},
 };
 use anyhow::Result;
@@ -97,15 +98,55 @@ impl DebugAdapterClient {
     /// Note: This function will block until a response is sent back from the adapter
     pub async fn request<R: Request>(&self, arguments: R::Arguments) -> Result<R::Response> {
         let serialized_arguments = serde_json::to_value(arguments)?;
+        let response = self
+            .send_request(R::COMMAND.to_string(), serialized_arguments)
+            .await?;
 
+        match response.success {
+            true => {
+                if let Some(json) = response.body {
+                    Ok(serde_json::from_value(json)?)
+                // Note: dap types configure themselves to return `None` when an empty object is received,
+                // which then fails here...
+                } else if let Ok(result) =
+                    serde_json::from_value(serde_json::Value::Object(Default::default()))
+                {
+                    Ok(result)
+                } else {
+                    Ok(serde_json::from_value(Default::default())?)
+                }
+            }
+            false => anyhow::bail!("Request failed: {}", response.message.unwrap_or_default()),
+        }
+    }
+
+    /// Sends a request that is not part of the protocol, such as an adapter-specific extension, returning the body of
+    /// the response.
+    pub async fn custom_request(
+        &self,
+        command: &str,
+        arguments: serde_json::Value,
+    ) -> Result<Option<serde_json::Value>> {
+        let response = self.send_request(command.to_string(), arguments).await?;
+        match response.success {
+            true => Ok(response.body),
+            false => anyhow::bail!("Request failed: {}", response.message.unwrap_or_default()),
+        }
+    }
+
+    async fn send_request(
+        &self,
+        command: String,
+        arguments: serde_json::Value,
+    ) -> Result<Response> {
         let (callback_tx, callback_rx) = oneshot::channel::<Result<Response>>();
 
         let sequence_id = self.next_sequence_id();
 
         let request = crate::messages::Request {
             seq: sequence_id,
-            command: R::COMMAND.to_string(),
-            arguments: Some(serialized_arguments),
+            command: command.clone(),
+            arguments: Some(arguments),
         };
         self.transport_delegate
             .pending_requests
@@ -115,15 +156,13 @@ impl DebugAdapterClient {
         log::debug!(
             "Client {} send `{}` request with sequence_id: {}",
             self.id.0,
-            R::COMMAND,
+            command,
             sequence_id
         );
         log::debug!("  request: {request:?}");
 
         self.send_message(Message::Request(request)).await?;
 
-        let command = R::COMMAND.to_string();
-
         let response = callback_rx.await??;
         log::debug!(
             "Client {} received response for: `{}` sequence_id: {}",
@@ -133,22 +172,7 @@ impl DebugAdapterClient {
         );
         log::debug!("  response: {response:?}");
 
-        match response.success {
-            true => {
-                if let Some(json) = response.body {
-                    Ok(serde_json::from_value(json)?)
-                // Note: dap types configure themselves to return `None` when an empty object is received,
-                // which then fails here...
-                } else if let Ok(result) =
-                    serde_json::from_value(serde_json::Value::Object(Default::default()))
-                {
-                    Ok(result)
-                } else {
-                    Ok(serde_json::from_value(Default::default())?)
-                }
-            }
-            false => anyhow::bail!("Request failed: {}", response.message.unwrap_or_default()),
-        }
+        Ok(response)
     }
 
     pub async fn send_message(&self, message: Message) -> Result<()> {
@@ -185,6 +209,11 @@ impl DebugAdapterClient {
         self.transport_delegate.add_log_handler(f, kind);
     }
 
+    /// Records the messages exchanged with the adapter, whether or not DAP logging is enabled.
+    pub fn record_to(&self, recorder: DapRecorder) {
+        self.transport_delegate.record_to(recorder);
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     pub fn on_request<R: dap_types::requests::Request, F>(&self, mut handler: F)
     where
@@ -203,6 +232,16 @@ impl DebugAdapterClient {
             });
     }
 
+    /// Answers all further requests from `recording` instead of the registered handlers.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn replay(&self, recording: crate::recording::DapRecording) {
+        self.transport_delegate
+            .transport
+            .lock()
+            .as_fake()
+            .replay(recording);
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     pub fn on_request_ext<R: dap_types::requests::Request, F>(&self, handler: F)
     where
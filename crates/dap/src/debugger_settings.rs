@@ -27,6 +27,11 @@ pub struct DebuggerSettings {
     ///
     /// Default: true
     pub format_dap_log_messages: bool,
+    /// Whether to record the messages exchanged with debug adapters to disk, so that sessions can be replayed.
+    /// Recording doesn't depend on `log_dap_communications`, and values that look like secrets are redacted.
+    ///
+    /// Default: false
+    pub record_dap_sessions: bool,
     /// The dock position of the debug panel
     ///
     /// Default: Bottom
@@ -45,6 +50,7 @@ impl Settings for DebuggerSettings {
             timeout: content.timeout.unwrap(),
             log_dap_communications: content.log_dap_communications.unwrap(),
             format_dap_log_messages: content.format_dap_log_messages.unwrap(),
+            record_dap_sessions: content.record_dap_sessions.unwrap(),
             dock: content.dock.unwrap(),
         }
     }
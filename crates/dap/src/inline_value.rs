@@ -1,3 +1,9 @@
+use std::{ops::Range, sync::OnceLock};
+
+use collections::HashSet;
+use language::{BufferSnapshot, DebuggerTextObject, Point};
+use tree_sitter::{Query, QueryCursor, StreamingIterator as _};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VariableLookupKind {
     Variable,
@@ -18,3 +24,155 @@ pub struct InlineValueLocation {
     pub row: usize,
     pub column: usize,
 }
+
+/// Finds the variables whose values should be shown inline while a debug session is stopped.
+///
+/// Languages get one from their `debugger.scm` query by default; a provider registered in the
+/// [`crate::DapRegistry`] for a language takes precedence over it.
+pub trait InlineValueProvider: Send + Sync {
+    /// Returns the variables visible on or before `max_row`, the row the debuggee is stopped on.
+    fn provide(&self, snapshot: &BufferSnapshot, max_row: usize) -> Vec<InlineValueLocation>;
+}
+
+/// An [`InlineValueProvider`] backed by a tree-sitter query with `@debug-variable` and `@debug-scope` captures,
+/// using the same conventions as a language's `debugger.scm`.
+pub struct QueryInlineValueProvider {
+    source: String,
+    query: OnceLock<Option<Query>>,
+}
+
+impl QueryInlineValueProvider {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            query: OnceLock::new(),
+        }
+    }
+
+    fn query(&self, language: &tree_sitter::Language) -> Option<&Query> {
+        self.query
+            .get_or_init(|| match Query::new(language, &self.source) {
+                Ok(query) => Some(query),
+                Err(error) => {
+                    log::error!("invalid inline values query: {error}");
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+impl InlineValueProvider for QueryInlineValueProvider {
+    fn provide(&self, snapshot: &BufferSnapshot, max_row: usize) -> Vec<InlineValueLocation> {
+        let Some(language) = snapshot.language() else {
+            return Vec::new();
+        };
+        let Some(query) = language
+            .grammar()
+            .and_then(|grammar| self.query(&grammar.ts_language))
+        else {
+            return Vec::new();
+        };
+        let objects_by_capture_ix = query
+            .capture_names()
+            .iter()
+            .map(|name| DebuggerTextObject::from_capture_name(name))
+            .collect::<Vec<_>>();
+
+        let end_offset =
+            snapshot.point_to_offset(Point::new(max_row as u32 + 1, 0).min(snapshot.max_point()));
+        let mut captures = Vec::new();
+        for layer in snapshot
+            .syntax_layers()
+            .filter(|layer| layer.language.name() == language.name())
+        {
+            let mut cursor = QueryCursor::new();
+            cursor.set_byte_range(0..end_offset);
+            // Read the matched nodes straight from the rope instead of copying the whole buffer per stop.
+            let mut matches = cursor.captures(query, layer.node(), |node: tree_sitter::Node| {
+                snapshot
+                    .text_for_range(node.byte_range())
+                    .map(str::as_bytes)
+            });
+            while let Some((query_match, capture_ix)) = matches.next() {
+                let capture = query_match.captures[*capture_ix];
+                if let Some(Some(object)) = objects_by_capture_ix.get(capture.index as usize) {
+                    captures.push((capture.node.byte_range(), *object));
+                }
+            }
+        }
+        captures.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        captures.dedup_by(|(a, a_object), (b, b_object)| a == b && a_object == b_object);
+
+        inline_value_locations(captures.into_iter(), snapshot, max_row)
+    }
+}
+
+/// Resolves the variables and scopes captured from a buffer, in document order, into the locations of the
+/// variables to evaluate.
+pub fn inline_value_locations(
+    captures: impl Iterator<Item = (Range<usize>, DebuggerTextObject)>,
+    snapshot: &BufferSnapshot,
+    max_row: usize,
+) -> Vec<InlineValueLocation> {
+    let mut variables = Vec::new();
+    let mut variable_position = HashSet::default();
+    let mut scopes = Vec::new();
+
+    let active_debug_line_offset = snapshot.point_to_offset(Point::new(max_row as u32, 0));
+
+    for (capture_range, capture_kind) in captures {
+        match capture_kind {
+            DebuggerTextObject::Variable => {
+                let variable_name = snapshot
+                    .text_for_range(capture_range.clone())
+                    .collect::<String>();
+                let point = snapshot.offset_to_point(capture_range.end);
+
+                while scopes
+                    .last()
+                    .is_some_and(|scope: &Range<_>| !scope.contains(&capture_range.start))
+                {
+                    scopes.pop();
+                }
+
+                if point.row as usize > max_row {
+                    break;
+                }
+
+                let scope = if scopes
+                    .last()
+                    .is_none_or(|scope| !scope.contains(&active_debug_line_offset))
+                {
+                    VariableScope::Global
+                } else {
+                    VariableScope::Local
+                };
+
+                if variable_position.insert(capture_range.end) {
+                    variables.push(InlineValueLocation {
+                        variable_name,
+                        scope,
+                        lookup: VariableLookupKind::Variable,
+                        row: point.row as usize,
+                        column: point.column as usize,
+                    });
+                }
+            }
+            DebuggerTextObject::Scope => {
+                while scopes.last().map_or_else(
+                    || false,
+                    |scope: &Range<usize>| {
+                        !(scope.contains(&capture_range.start)
+                            && scope.contains(&capture_range.end))
+                    },
+                ) {
+                    scopes.pop();
+                }
+                scopes.push(capture_range);
+            }
+        }
+    }
+
+    variables
+}
@@ -0,0 +1,317 @@
+//! Recording of the messages exchanged with a debug adapter, and their replay.
+//!
+//! A recording is a JSON Lines file with one message per line, in the order they were sent or received. Replaying it
+//! stands in for the adapter: each message the client sends is matched against the recording, and the adapter messages
+//! that followed it are sent back. This makes adapter integration bugs reproducible without the adapter itself.
+//!
+//! Values of fields that look like they hold secrets, such as tokens passed to the debuggee through its environment,
+//! are redacted from recordings so that they can be shared.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write as _},
+    path::Path,
+};
+
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::transport::IoKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageDirection {
+    ToAdapter,
+    FromAdapter,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub direction: MessageDirection,
+    pub message: Value,
+}
+
+const REDACTED: &str = "<redacted>";
+
+/// Parts of field names that mark their values as secrets.
+const SECRET_KEY_PARTS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "credential",
+    "authorization",
+    "private_key",
+];
+
+/// Writes the messages of a session to disk as they are exchanged. Writes are buffered, and flushed once the adapter
+/// reports that the debuggee ended, or when the recorder is dropped.
+pub struct DapRecorder {
+    writer: BufWriter<File>,
+}
+
+impl DapRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {parent:?}"))?;
+        }
+        let file = File::create(path).with_context(|| format!("creating recording {path:?}"))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Records an RPC message, as reported to a [`crate::transport::LogKind::Rpc`] log handler.
+    pub fn record(&mut self, io_kind: IoKind, message: &str) -> Result<()> {
+        let direction = match io_kind {
+            IoKind::StdIn => MessageDirection::ToAdapter,
+            IoKind::StdOut => MessageDirection::FromAdapter,
+            IoKind::StdErr => return Ok(()),
+        };
+        let mut message = serde_json::from_str::<Value>(message).context("parsing DAP message")?;
+        redact_secrets(&mut message);
+        let ended = message["type"] == "event"
+            && (message["event"] == "terminated" || message["event"] == "exited");
+        serde_json::to_writer(&mut self.writer, &RecordedMessage { direction, message })?;
+        self.writer.write_all(b"\n")?;
+        if ended {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn redact_secrets(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if is_secret_key(key) && !value.is_object() && !value.is_array() && !value.is_null()
+                {
+                    *value = Value::from(REDACTED);
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEY_PARTS.iter().any(|part| key.contains(part))
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DapRecording {
+    messages: Vec<RecordedMessage>,
+}
+
+impl DapRecording {
+    pub fn new(messages: Vec<RecordedMessage>) -> Self {
+        Self { messages }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("reading recording {path:?}"))?;
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let messages = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(ix, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("invalid recorded message on line {}", ix + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { messages })
+    }
+
+    pub fn messages(&self) -> &[RecordedMessage] {
+        &self.messages
+    }
+}
+
+/// Plays the adapter's side of a [`DapRecording`].
+///
+/// Requests are matched to recorded ones by command, in order, so the client is free to send concurrent requests in a
+/// different order than they were recorded in. Adapter messages are sent in their recorded order: responses once their
+/// request was received, everything else once all the client messages that preceded it were received.
+pub struct DapReplay {
+    messages: Vec<RecordedMessage>,
+    handled: Vec<bool>,
+    /// Maps the sequence numbers of recorded requests to the ones the client used when replaying them.
+    request_seqs: HashMap<u64, u64>,
+}
+
+impl DapReplay {
+    pub fn new(recording: DapRecording) -> Self {
+        Self {
+            // Events are only ever sent by adapters; the ones sent to it were injected by tests.
+            handled: recording
+                .messages
+                .iter()
+                .map(|recorded| {
+                    recorded.direction == MessageDirection::ToAdapter
+                        && recorded.message["type"] == "event"
+                })
+                .collect(),
+            messages: recording.messages,
+            request_seqs: HashMap::default(),
+        }
+    }
+
+    /// Handles a request sent by the client, returning the adapter messages to send back.
+    pub fn handle_request(&mut self, command: &str, seq: u64) -> Vec<Value> {
+        let Some(ix) = self.next_client_message("request", command) else {
+            log::error!("replayed request `{command}` is not in the recording");
+            return vec![serde_json::json!({
+                "seq": 0,
+                "type": "response",
+                "request_seq": seq,
+                "success": false,
+                "command": command,
+                "message": "request not in recording",
+            })];
+        };
+        self.handled[ix] = true;
+        if let Some(recorded_seq) = self.messages[ix].message["seq"].as_u64() {
+            self.request_seqs.insert(recorded_seq, seq);
+        }
+        self.adapter_messages()
+    }
+
+    /// Handles a response to a reverse request, returning the adapter messages to send back.
+    pub fn handle_response(&mut self, command: &str) -> Vec<Value> {
+        if let Some(ix) = self.next_client_message("response", command) {
+            self.handled[ix] = true;
+        } else {
+            log::error!("replayed response to `{command}` is not in the recording");
+        }
+        self.adapter_messages()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handled.iter().all(|handled| *handled)
+    }
+
+    fn next_client_message(&self, kind: &str, command: &str) -> Option<usize> {
+        self.messages.iter().enumerate().position(|(ix, recorded)| {
+            !self.handled[ix]
+                && recorded.direction == MessageDirection::ToAdapter
+                && recorded.message["type"] == kind
+                && recorded.message["command"] == command
+        })
+    }
+
+    fn adapter_messages(&mut self) -> Vec<Value> {
+        let mut outgoing = Vec::new();
+        let mut client_caught_up = true;
+        for (ix, recorded) in self.messages.iter().enumerate() {
+            if self.handled[ix] {
+                continue;
+            }
+            if recorded.direction == MessageDirection::ToAdapter {
+                client_caught_up = false;
+                continue;
+            }
+
+            let mut message = recorded.message.clone();
+            if message["type"] == "response" {
+                let Some(seq) = message["request_seq"]
+                    .as_u64()
+                    .and_then(|recorded_seq| self.request_seqs.get(&recorded_seq))
+                else {
+                    break;
+                };
+                message["request_seq"] = Value::from(*seq);
+            } else if !client_caught_up {
+                break;
+            }
+            self.handled[ix] = true;
+            outgoing.push(message);
+        }
+        outgoing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut message = json!({
+            "type": "request",
+            "command": "launch",
+            "arguments": {
+                "program": "server",
+                "env": {"PATH": "/usr/bin", "GITHUB_TOKEN": "ghp_123", "DB_PASSWORD": "hunter2"},
+                "auth": {"apiKey": "abc", "user": "me"},
+                "tokens": ["a", "b"],
+            },
+        });
+        redact_secrets(&mut message);
+        assert_eq!(
+            message["arguments"],
+            json!({
+                "program": "server",
+                "env": {"PATH": "/usr/bin", "GITHUB_TOKEN": REDACTED, "DB_PASSWORD": REDACTED},
+                "auth": {"apiKey": REDACTED, "user": "me"},
+                "tokens": ["a", "b"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let recording = DapRecording::parse(
+            &[
+                json!({"direction": "to_adapter", "message": {"seq": 1, "type": "request", "command": "initialize"}}),
+                json!({"direction": "from_adapter", "message": {"seq": 1, "type": "response", "request_seq": 1, "success": true, "command": "initialize"}}),
+                json!({"direction": "from_adapter", "message": {"seq": 2, "type": "event", "event": "initialized"}}),
+                json!({"direction": "to_adapter", "message": {"seq": 2, "type": "request", "command": "launch"}}),
+                json!({"direction": "to_adapter", "message": {"seq": 3, "type": "request", "command": "configurationDone"}}),
+                json!({"direction": "from_adapter", "message": {"seq": 3, "type": "response", "request_seq": 3, "success": true, "command": "configurationDone"}}),
+                json!({"direction": "from_adapter", "message": {"seq": 4, "type": "response", "request_seq": 2, "success": true, "command": "launch"}}),
+                json!({"direction": "from_adapter", "message": {"seq": 5, "type": "event", "event": "stopped"}}),
+            ]
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        )
+        .unwrap();
+        let mut replay = DapReplay::new(recording);
+
+        let outgoing = replay.handle_request("initialize", 10);
+        assert_eq!(outgoing.len(), 2);
+        assert_eq!(outgoing[0]["request_seq"], 10);
+        assert_eq!(outgoing[1]["event"], "initialized");
+
+        let outgoing = replay.handle_request("configurationDone", 12);
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0]["request_seq"], 12);
+
+        let outgoing = replay.handle_request("launch", 11);
+        assert_eq!(outgoing.len(), 2);
+        assert_eq!(outgoing[0]["request_seq"], 11);
+        assert_eq!(outgoing[1]["event"], "stopped");
+        assert!(replay.is_finished());
+
+        let outgoing = replay.handle_request("threads", 13);
+        assert_eq!(outgoing[0]["success"], false);
+        assert_eq!(outgoing[0]["request_seq"], 13);
+    }
+}
@@ -9,6 +9,7 @@ use task::{
 };
 
 use crate::adapters::{DebugAdapter, DebugAdapterName};
+use crate::inline_value::InlineValueProvider;
 use std::{collections::BTreeMap, sync::Arc};
 
 /// Given a user build configuration, locator creates a fill-in debug target ([DebugScenario]) on behalf of the user.
@@ -30,6 +31,7 @@ pub trait DapLocator: Send + Sync {
 struct DapRegistryState {
     adapters: BTreeMap<DebugAdapterName, Arc<dyn DebugAdapter>>,
     locators: FxHashMap<SharedString, Arc<dyn DapLocator>>,
+    inline_value_providers: FxHashMap<LanguageName, Arc<dyn InlineValueProvider>>,
 }
 
 #[derive(Clone, Default)]
@@ -51,6 +53,17 @@ impl DapRegistry {
         self.0.write().locators.insert(locator.name(), locator);
     }
 
+    pub fn add_inline_value_provider(
+        &self,
+        language: LanguageName,
+        provider: Arc<dyn InlineValueProvider>,
+    ) {
+        self.0
+            .write()
+            .inline_value_providers
+            .insert(language, provider);
+    }
+
     pub fn remove_adapter(&self, name: &str) {
         self.0.write().adapters.remove(name);
     }
@@ -59,6 +72,10 @@ impl DapRegistry {
         self.0.write().locators.remove(locator);
     }
 
+    pub fn remove_inline_value_provider(&self, language: &LanguageName) {
+        self.0.write().inline_value_providers.remove(language);
+    }
+
     pub fn adapter_language(&self, adapter_name: &str) -> Option<LanguageName> {
         self.adapter(adapter_name)
             .and_then(|adapter| adapter.adapter_language_name())
@@ -83,6 +100,13 @@ impl DapRegistry {
         self.0.read().locators.clone()
     }
 
+    pub fn inline_value_provider(
+        &self,
+        language: &LanguageName,
+    ) -> Option<Arc<dyn InlineValueProvider>> {
+        self.0.read().inline_value_providers.get(language).cloned()
+    }
+
     pub fn adapter(&self, name: &str) -> Option<Arc<dyn DebugAdapter>> {
         self.0.read().adapters.get(name).cloned()
     }
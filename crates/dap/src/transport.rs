@@ -24,12 +24,15 @@ use std::{
     time::Duration,
 };
 use task::TcpArgumentsTemplate;
-use util::ConnectionResult;
+use util::{ConnectionResult, ResultExt as _};
 
+#[cfg(any(test, feature = "test-support"))]
+use crate::recording::{DapRecording, DapReplay};
 use crate::{
     adapters::{DebugAdapterBinary, TcpArguments},
     client::DapMessageHandler,
     debugger_settings::DebuggerSettings,
+    recording::DapRecorder,
 };
 
 pub(crate) type IoMessage = str;
@@ -56,6 +59,7 @@ pub enum RequestHandling<T> {
 }
 
 type LogHandlers = Arc<Mutex<SmallVec<[(LogKind, IoHandler); 2]>>>;
+type Recorder = Arc<Mutex<Option<DapRecorder>>>;
 
 pub trait Transport: Send + Sync {
     fn has_adapter_logs(&self) -> bool;
@@ -150,6 +154,8 @@ impl PendingRequests {
 
 pub(crate) struct TransportDelegate {
     log_handlers: LogHandlers,
+    /// Records the RPC messages, independently of whether they're logged.
+    recorder: Recorder,
     pub(crate) pending_requests: Arc<Mutex<PendingRequests>>,
     pub(crate) transport: Mutex<Box<dyn Transport>>,
     pub(crate) server_tx: smol::lock::Mutex<Option<Sender<Message>>>,
@@ -163,6 +169,7 @@ impl TransportDelegate {
         Ok(Self {
             transport: Mutex::new(transport),
             log_handlers,
+            recorder: Default::default(),
             server_tx: Default::default(),
             pending_requests: Arc::new(Mutex::new(PendingRequests::new())),
             tasks: Default::default(),
@@ -193,6 +200,8 @@ impl TransportDelegate {
 
         let pending_requests = self.pending_requests.clone();
         let output_log_handler = log_handler.clone();
+        let output_recorder = self.recorder.clone();
+        let input_recorder = self.recorder.clone();
         {
             let mut tasks = self.tasks.lock();
             tasks.push(cx.background_spawn(async move {
@@ -201,6 +210,7 @@ impl TransportDelegate {
                     message_handler,
                     pending_requests.clone(),
                     output_log_handler,
+                    output_recorder,
                 )
                 .await
                 {
@@ -216,7 +226,7 @@ impl TransportDelegate {
             }));
 
             tasks.push(cx.background_spawn(async move {
-                match Self::send_to_server(input, client_rx, log_handler).await {
+                match Self::send_to_server(input, client_rx, log_handler, input_recorder).await {
                     Ok(()) => {}
                     Err(e) => log::error!("Error handling debugger input: {e}"),
                 }
@@ -284,6 +294,7 @@ impl TransportDelegate {
         mut server_stdin: Stdin,
         client_rx: Receiver<Message>,
         log_handlers: Option<LogHandlers>,
+        recorder: Recorder,
     ) -> Result<()>
     where
         Stdin: AsyncWrite + Unpin + Send + 'static,
@@ -309,6 +320,9 @@ impl TransportDelegate {
                             }
                         }
                     }
+                    if let Some(recorder) = recorder.lock().as_mut() {
+                        recorder.record(IoKind::StdIn, &message).log_err();
+                    }
 
                     if let Err(e) = server_stdin
                         .write_all(Self::build_rpc_message(message).as_bytes())
@@ -335,6 +349,7 @@ impl TransportDelegate {
         mut message_handler: DapMessageHandler,
         pending_requests: Arc<Mutex<PendingRequests>>,
         log_handlers: Option<LogHandlers>,
+        recorder: Recorder,
     ) -> Result<()>
     where
         Stdout: AsyncRead + Unpin + Send + 'static,
@@ -343,9 +358,13 @@ impl TransportDelegate {
         let mut reader = BufReader::new(server_stdout);
 
         let result = loop {
-            let result =
-                Self::receive_server_message(&mut reader, &mut recv_buffer, log_handlers.as_ref())
-                    .await;
+            let result = Self::receive_server_message(
+                &mut reader,
+                &mut recv_buffer,
+                log_handlers.as_ref(),
+                Some(&recorder),
+            )
+            .await;
             match result {
                 ConnectionResult::Timeout => anyhow::bail!("Timed out when connecting to debugger"),
                 ConnectionResult::ConnectionReset => {
@@ -397,6 +416,7 @@ impl TransportDelegate {
         reader: &mut BufReader<Stdout>,
         buffer: &mut String,
         log_handlers: Option<&LogHandlers>,
+        recorder: Option<&Recorder>,
     ) -> ConnectionResult<Message>
     where
         Stdout: AsyncRead + Unpin + Send + 'static,
@@ -457,6 +477,11 @@ impl TransportDelegate {
                 }
             }
         }
+        if let Some(recorder) = recorder
+            && let Some(recorder) = recorder.lock().as_mut()
+        {
+            recorder.record(IoKind::StdOut, message_str).log_err();
+        }
 
         ConnectionResult::Result(message)
     }
@@ -472,6 +497,10 @@ impl TransportDelegate {
         let mut log_handlers = self.log_handlers.lock();
         log_handlers.push((kind, Box::new(f)));
     }
+
+    pub fn record_to(&self, recorder: DapRecorder) {
+        *self.recorder.lock() = Some(recorder);
+    }
 }
 
 pub struct TcpTransport {
@@ -747,6 +776,8 @@ pub struct FakeTransport {
     request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
     // for reverse request responses
     response_handlers: Arc<Mutex<HashMap<&'static str, ResponseHandler>>>,
+    // answers requests from a recording instead of the handlers above, when set
+    replay: Arc<Mutex<Option<DapReplay>>>,
     message_handler: Option<Task<Result<()>>>,
     kind: FakeTransportKind,
 }
@@ -810,10 +841,16 @@ impl FakeTransport {
             .insert(R::COMMAND, Box::new(handler));
     }
 
+    /// Answers all further requests from `recording`, as the adapter that was recorded did.
+    pub fn replay(&self, recording: DapRecording) {
+        *self.replay.lock() = Some(DapReplay::new(recording));
+    }
+
     async fn start_tcp(connection: TcpArguments, cx: &mut AsyncApp) -> Result<Self> {
         Ok(Self {
             request_handlers: Arc::new(Mutex::new(HashMap::default())),
             response_handlers: Arc::new(Mutex::new(HashMap::default())),
+            replay: Arc::new(Mutex::new(None)),
             message_handler: None,
             kind: FakeTransportKind::Tcp {
                 connection,
@@ -825,6 +862,7 @@ impl FakeTransport {
     async fn handle_messages(
         request_handlers: Arc<Mutex<HashMap<&'static str, RequestHandler>>>,
         response_handlers: Arc<Mutex<HashMap<&'static str, ResponseHandler>>>,
+        replay: Arc<Mutex<Option<DapReplay>>>,
         stdin_reader: PipeReader,
         stdout_writer: PipeWriter,
     ) -> Result<()> {
@@ -836,7 +874,9 @@ impl FakeTransport {
         let mut buffer = String::new();
 
         loop {
-            match TransportDelegate::receive_server_message(&mut reader, &mut buffer, None).await {
+            match TransportDelegate::receive_server_message(&mut reader, &mut buffer, None, None)
+                .await
+            {
                 ConnectionResult::Timeout => {
                     anyhow::bail!("Timed out when connecting to debugger");
                 }
@@ -846,6 +886,27 @@ impl FakeTransport {
                 }
                 ConnectionResult::Result(Err(e)) => break Err(e),
                 ConnectionResult::Result(Ok(message)) => {
+                    let replayed = replay.lock().as_mut().map(|replay| match &message {
+                        Message::Request(request) => {
+                            replay.handle_request(&request.command, request.seq)
+                        }
+                        Message::Response(response) => replay.handle_response(&response.command),
+                        Message::Event(_) => Vec::new(),
+                    });
+                    if let Some(replayed) = replayed {
+                        let mut writer = stdout_writer.lock().await;
+                        for message in replayed {
+                            writer
+                                .write_all(
+                                    TransportDelegate::build_rpc_message(message.to_string())
+                                        .as_bytes(),
+                                )
+                                .await?;
+                        }
+                        writer.flush().await?;
+                        continue;
+                    }
+
                     match message {
                         Message::Request(request) => {
                             // redirect reverse requests to stdout writer/reader
@@ -946,16 +1007,19 @@ impl FakeTransport {
         let mut this = Self {
             request_handlers: Arc::new(Mutex::new(HashMap::default())),
             response_handlers: Arc::new(Mutex::new(HashMap::default())),
+            replay: Arc::new(Mutex::new(None)),
             message_handler: None,
             kind,
         };
 
         let request_handlers = this.request_handlers.clone();
         let response_handlers = this.response_handlers.clone();
+        let replay = this.replay.clone();
 
         this.message_handler = Some(cx.background_spawn(Self::handle_messages(
             request_handlers,
             response_handlers,
+            replay,
             stdin_reader,
             stdout_writer,
         )));
@@ -997,10 +1061,12 @@ impl Transport for FakeTransport {
 
                 let request_handlers = self.request_handlers.clone();
                 let response_handlers = self.response_handlers.clone();
+                let replay = self.replay.clone();
 
                 self.message_handler = Some(executor.spawn(Self::handle_messages(
                     request_handlers,
                     response_handlers,
+                    replay,
                     stdin_reader,
                     stdout_writer,
                 )));
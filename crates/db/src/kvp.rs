@@ -69,6 +69,29 @@ impl KeyValueStore {
             DELETE FROM kv_store WHERE key = (?)
         }
     }
+
+    /// Replaces the value of `key` with the one `update` returns given the current value, in a single write, so that
+    /// concurrent updates of the key don't undo each other. Returns whether `update` returned a value to write.
+    pub async fn update_kvp(
+        &self,
+        key: String,
+        update: impl 'static + Send + FnOnce(Option<String>) -> anyhow::Result<Option<String>>,
+    ) -> anyhow::Result<bool> {
+        log::debug!("Updating key-value pair for key {key}");
+        self.write(move |connection| {
+            let value = connection.select_row_bound::<&str, String>(sql!(
+                SELECT value FROM kv_store WHERE key = (?)
+            ))?(key.as_str())?;
+            let Some(value) = update(value)? else {
+                return Ok(false);
+            };
+            connection.exec_bound::<(&str, String)>(sql!(
+                INSERT OR REPLACE INTO kv_store(key, value) VALUES ((?), (?))
+            ))?((key.as_str(), value))?;
+            Ok(true)
+        })
+        .await
+    }
 }
 
 #[cfg(test)]
@@ -98,6 +121,19 @@ mod tests {
 
         db.delete_kvp("key-1".to_string()).await.unwrap();
         assert_eq!(db.read_kvp("key-1").unwrap(), None);
+
+        let updated = db
+            .update_kvp("key-2".to_string(), |value| {
+                Ok(value.map(|value| format!("{value}-2")))
+            })
+            .await
+            .unwrap();
+        assert!(updated);
+        assert_eq!(db.read_kvp("key-2").unwrap(), Some("two-2".to_string()));
+
+        let updated = db.update_kvp("key-1".to_string(), Ok).await.unwrap();
+        assert!(!updated);
+        assert_eq!(db.read_kvp("key-1").unwrap(), None);
     }
 }
 
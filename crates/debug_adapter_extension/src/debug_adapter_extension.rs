@@ -3,10 +3,12 @@ mod extension_locator_adapter;
 
 use std::{path::Path, sync::Arc};
 
-use dap::DapRegistry;
+use anyhow::Context as _;
+use dap::{DapRegistry, inline_value::QueryInlineValueProvider};
 use extension::{ExtensionDebugAdapterProviderProxy, ExtensionHostProxy};
 use extension_dap_adapter::ExtensionDapAdapter;
 use gpui::App;
+use language::LanguageName;
 use util::ResultExt;
 
 use crate::extension_locator_adapter::ExtensionLocatorAdapter;
@@ -55,6 +57,20 @@ impl ExtensionDebugAdapterProviderProxy for DebugAdapterRegistryProxy {
             )));
     }
 
+    fn register_debug_inline_values(&self, language: LanguageName, query_path: &Path) {
+        let query = std::fs::read_to_string(query_path).with_context(|| {
+            format!(
+                "Failed to read inline values query for {language} (from path: `{query_path:?}`)"
+            )
+        });
+        if let Some(query) = query.log_err() {
+            self.debug_adapter_registry.add_inline_value_provider(
+                language,
+                Arc::new(QueryInlineValueProvider::new(query)),
+            );
+        }
+    }
+
     fn unregister_debug_adapter(&self, debug_adapter_name: Arc<str>) {
         self.debug_adapter_registry
             .remove_adapter(&debug_adapter_name);
@@ -63,4 +79,9 @@ impl ExtensionDebugAdapterProviderProxy for DebugAdapterRegistryProxy {
     fn unregister_debug_locator(&self, locator_name: Arc<str>) {
         self.debug_adapter_registry.remove_locator(&locator_name);
     }
+
+    fn unregister_debug_inline_values(&self, language: LanguageName) {
+        self.debug_adapter_registry
+            .remove_inline_value_provider(&language);
+    }
 }
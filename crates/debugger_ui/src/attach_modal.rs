@@ -1,4 +1,4 @@
-use dap::{DapRegistry, DebugRequest};
+use dap::DebugRequest;
 use futures::channel::oneshot;
 use fuzzy::{StringMatch, StringMatchCandidate};
 use gpui::{AppContext, DismissEvent, Entity, EventEmitter, Focusable, Render, Task};
@@ -10,7 +10,6 @@ use util::debug_panic;
 
 use std::sync::Arc;
 
-use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind};
 use ui::{Context, Tooltip, prelude::*};
 use ui::{ListItem, ListItemSpacing};
 use workspace::{ModalView, Workspace};
@@ -237,34 +236,31 @@ impl PickerDelegate for AttachModalDelegate {
                     return cx.emit(DismissEvent);
                 };
 
-                match &mut definition.request {
-                    DebugRequest::Attach(config) => {
-                        config.process_id = Some(candidate.pid);
-                    }
-                    DebugRequest::Launch(_) => {
-                        debug_panic!("Debugger attach modal used on launch debug config");
-                        return;
-                    }
+                if let DebugRequest::Launch(_) = &definition.request {
+                    debug_panic!("Debugger attach modal used on launch debug config");
+                    return;
                 }
 
                 let workspace = self.workspace.clone();
-                let Some(panel) = workspace
-                    .update(cx, |workspace, cx| workspace.panel::<DebugPanel>(cx))
+                let Some((panel, dap_store)) = workspace
+                    .update(cx, |workspace, cx| {
+                        Some((
+                            workspace.panel::<DebugPanel>(cx)?,
+                            workspace.project().read(cx).dap_store(),
+                        ))
+                    })
                     .ok()
                     .flatten()
                 else {
                     return;
                 };
 
-                let Some(adapter) = cx.read_global::<DapRegistry, _>(|registry, _| {
-                    registry.adapter(&definition.adapter)
-                }) else {
-                    return;
-                };
-
-                let definition = definition.clone();
+                let scenario =
+                    dap_store
+                        .read(cx)
+                        .attach_scenario(definition.clone(), candidate.pid, cx);
                 cx.spawn_in(window, async move |this, cx| {
-                    let Ok(scenario) = adapter.config_from_zed_format(definition).await else {
+                    let Ok(scenario) = scenario.await else {
                         return;
                     };
 
@@ -356,31 +352,23 @@ impl PickerDelegate for AttachModalDelegate {
     }
 }
 
-fn get_processes_for_project(_project: &Entity<Project>, _cx: &mut App) -> Task<Arc<[Candidate]>> {
-    let refresh_kind = RefreshKind::nothing().with_processes(
-        ProcessRefreshKind::nothing()
-            .without_tasks()
-            .with_cmd(UpdateKind::Always),
-    );
-    let mut processes: Box<[_]> = System::new_with_specifics(refresh_kind)
-        .processes()
-        .values()
-        .map(|process| {
-            let name = process.name().to_string_lossy().into_owned();
-            Candidate {
-                name: name.into(),
-                pid: process.pid().as_u32(),
-                command: process
-                    .cmd()
-                    .iter()
-                    .map(|s| s.to_string_lossy().into_owned())
-                    .collect::<Vec<_>>(),
-            }
-        })
-        .collect();
-    processes.sort_by_key(|k| k.name.clone());
-    let processes = processes.into_iter().collect();
-    Task::ready(processes)
+fn get_processes_for_project(project: &Entity<Project>, cx: &mut App) -> Task<Arc<[Candidate]>> {
+    let processes = project
+        .read(cx)
+        .dap_store()
+        .read(cx)
+        .attachable_processes(cx);
+    cx.background_spawn(async move {
+        processes
+            .await
+            .into_iter()
+            .map(|process| Candidate {
+                pid: process.pid,
+                name: process.name,
+                command: process.command,
+            })
+            .collect()
+    })
 }
 
 #[cfg(test)]
@@ -6,8 +6,9 @@ use crate::session::running::breakpoint_list::BreakpointList;
 use crate::{
     ClearAllBreakpoints, Continue, CopyDebugAdapterArguments, Detach, FocusBreakpointList,
     FocusConsole, FocusFrames, FocusLoadedSources, FocusModules, FocusTerminal, FocusVariables,
-    NewProcessModal, NewProcessMode, Pause, RerunSession, StepInto, StepOut, StepOver, Stop,
-    ToggleExpandItem, ToggleSessionPicker, ToggleThreadPicker, persistence, spawn_task_or_modal,
+    NewProcessModal, NewProcessMode, Pause, RerunSession, ReverseContinue, StepBack, StepInto,
+    StepOut, StepOver, Stop, ToggleExpandItem, ToggleSessionPicker, ToggleThreadPicker,
+    persistence, spawn_task_or_modal,
 };
 use anyhow::{Context as _, Result, anyhow};
 use collections::IndexMap;
@@ -24,10 +25,12 @@ use gpui::{
 
 use itertools::Itertools as _;
 use language::Buffer;
+use project::debugger::dap_store::SessionGroupId;
 use project::debugger::session::{Session, SessionQuirks, SessionState, SessionStateEvent};
 use project::{DebugScenarioContext, Fs, ProjectPath, TaskSourceKind, WorktreeId};
 use project::{Project, debugger::session::ThreadStatus};
 use settings::Settings;
+use std::path::Path;
 use std::sync::{Arc, LazyLock};
 use task::{DebugScenario, TaskContext};
 use tree_sitter::{Query, StreamingIterator as _};
@@ -52,6 +55,14 @@ impl FeatureFlag for DebuggerHistoryFeatureFlag {
 
 const DEBUG_PANEL_KEY: &str = "DebugPanel";
 
+/// A scenario launched as part of a compound configuration.
+pub struct CompoundScenario {
+    pub scenario: DebugScenario,
+    /// The directories whose breakpoints are sent to the scenario's session, or `None` to send
+    /// it all breakpoints.
+    pub breakpoint_scope: Option<Vec<Arc<Path>>>,
+}
+
 pub struct DebugPanel {
     size: Pixels,
     active_session: Option<Entity<DebugSession>>,
@@ -181,10 +192,73 @@ impl DebugPanel {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        self.launch_session(
+            scenario,
+            task_context,
+            active_buffer,
+            worktree_id,
+            window,
+            cx,
+        );
+    }
+
+    /// Launches the scenarios of a compound configuration, e.g. a client and the server it talks
+    /// to, as one group of sessions. With `stop_all`, stopping any of the sessions stops them all.
+    pub fn start_compound_session(
+        &mut self,
+        label: SharedString,
+        scenarios: Vec<CompoundScenario>,
+        stop_all: bool,
+        task_context: TaskContext,
+        worktree_id: Option<WorktreeId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> SessionGroupId {
         let dap_store = self.project.read(cx).dap_store();
-        let Some(adapter) = DapRegistry::global(cx).adapter(&scenario.adapter) else {
-            return;
-        };
+        let group_id = dap_store.update(cx, |dap_store, _| {
+            dap_store.new_session_group(label, stop_all)
+        });
+        for CompoundScenario {
+            scenario,
+            breakpoint_scope,
+        } in scenarios
+        {
+            let Some(session) = self.launch_session(
+                scenario,
+                task_context.clone(),
+                None,
+                worktree_id,
+                window,
+                cx,
+            ) else {
+                continue;
+            };
+            // The session is still booting, so the scope applies to the first breakpoints it's sent.
+            session.update(cx, |session, _| {
+                session.set_breakpoint_scope(breakpoint_scope)
+            });
+            let session_id = session.read(cx).session_id();
+            dap_store.update(cx, |dap_store, _| {
+                dap_store
+                    .add_session_to_group(group_id, session_id)
+                    .log_err();
+            });
+        }
+        group_id
+    }
+
+    /// Starts a session for the scenario, returning it unless it couldn't be started.
+    fn launch_session(
+        &mut self,
+        scenario: DebugScenario,
+        task_context: TaskContext,
+        active_buffer: Option<Entity<Buffer>>,
+        worktree_id: Option<WorktreeId>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<Entity<Session>> {
+        let dap_store = self.project.read(cx).dap_store();
+        let adapter = DapRegistry::global(cx).adapter(&scenario.adapter)?;
         let quirks = SessionQuirks {
             compact: adapter.compact_child_session(),
             prefer_thread_name: adapter.prefer_thread_name(),
@@ -211,7 +285,7 @@ impl DebugPanel {
             .or_else(|| self.project.read(cx).visible_worktrees(cx).next())
         else {
             log::debug!("Could not find a worktree to spawn the debug session in");
-            return;
+            return None;
         };
 
         self.debug_scenario_scheduled_last = true;
@@ -300,6 +374,7 @@ impl DebugPanel {
                 debug_panic!("Session state should be in building because we are just starting it");
             }
         });
+        Some(session)
     }
 
     pub(crate) fn rerun_last_session(
@@ -696,6 +771,11 @@ impl DebugPanel {
                                     let capabilities = running_state.read(cx).capabilities(cx);
                                     let supports_detach =
                                         running_state.read(cx).session().read(cx).is_attached();
+                                    let supports_step_back = running_state
+                                        .read(cx)
+                                        .session()
+                                        .read(cx)
+                                        .supports_step_back();
 
                                     this.map(|this| {
                                         if thread_status == ThreadStatus::Running {
@@ -815,6 +895,55 @@ impl DebugPanel {
                                                 }
                                             }),
                                     )
+                                    .when(supports_step_back, |this| {
+                                        this.child(
+                                            IconButton::new("step-back", IconName::Undo)
+                                                .icon_size(IconSize::Small)
+                                                .on_click(window.listener_for(
+                                                    running_state,
+                                                    |this, _, _window, cx| {
+                                                        this.step_back(cx);
+                                                    },
+                                                ))
+                                                .disabled(thread_status != ThreadStatus::Stopped)
+                                                .tooltip({
+                                                    let focus_handle = focus_handle.clone();
+                                                    move |_window, cx| {
+                                                        Tooltip::for_action_in(
+                                                            "Step Back",
+                                                            &StepBack,
+                                                            &focus_handle,
+                                                            cx,
+                                                        )
+                                                    }
+                                                }),
+                                        )
+                                        .child(
+                                            IconButton::new(
+                                                "reverse-continue",
+                                                IconName::ArrowLeft,
+                                            )
+                                            .icon_size(IconSize::Small)
+                                            .on_click(window.listener_for(
+                                                running_state,
+                                                |this, _, _window, cx| {
+                                                    this.reverse_continue(cx);
+                                                },
+                                            ))
+                                            .disabled(thread_status != ThreadStatus::Stopped)
+                                            .tooltip({
+                                                let focus_handle = focus_handle.clone();
+                                                move |_window, cx| {
+                                                    Tooltip::for_action_in(
+                                                        "Reverse Continue",
+                                                        &ReverseContinue,
+                                                        &focus_handle,
+                                                        cx,
+                                                    )
+                                                }
+                                            }),
+                                        )
+                                    })
                                     .child(Divider::vertical())
                                     .child(
                                         IconButton::new("debug-restart", IconName::RotateCcw)
@@ -48,6 +48,8 @@ actions!(
         StepOut,
         /// Steps back to the previous statement.
         StepBack,
+        /// Runs the program backwards until the previous breakpoint.
+        ReverseContinue,
         /// Stops the debugging session.
         Stop,
         /// Toggles whether to ignore all breakpoints.
@@ -152,8 +154,7 @@ pub fn init(cx: &mut App) {
                     return div;
                 }
 
-                let caps = running_state.capabilities(cx);
-                let supports_step_back = caps.supports_step_back.unwrap_or_default();
+                let supports_step_back = running_state.session().read(cx).supports_step_back();
                 let supports_detach = running_state.session().read(cx).is_attached();
                 let status = running_state.thread_status(cx);
 
@@ -187,8 +188,16 @@ pub fn init(cx: &mut App) {
                     })
                     .when(supports_step_back, |div| {
                         let active_item = active_item.clone();
-                        div.on_action(move |_: &StepBack, _, cx| {
-                            active_item.update(cx, |item, cx| item.step_back(cx)).ok();
+                        div.on_action({
+                            let active_item = active_item.clone();
+                            move |_: &StepBack, _, cx| {
+                                active_item.update(cx, |item, cx| item.step_back(cx)).ok();
+                            }
+                        })
+                        .on_action(move |_: &ReverseContinue, _, cx| {
+                            active_item
+                                .update(cx, |item, cx| item.reverse_continue(cx))
+                                .ok();
                         })
                     })
                     .on_action({
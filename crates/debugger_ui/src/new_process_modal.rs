@@ -1020,6 +1020,7 @@ impl DebugDelegate {
                 Some(format!("LSP: {language_name}"))
             }
             Some(TaskSourceKind::Language { name }) => Some(format!("Language: {name}")),
+            Some(TaskSourceKind::Extension { provider }) => Some(format!("Extension: {provider}")),
             _ => context.clone().and_then(|ctx| {
                 ctx.task_context
                     .task_variables
@@ -1079,14 +1080,30 @@ impl DebugDelegate {
             })
         });
 
+        let dap_store_and_worktree = self.debug_panel.upgrade().and_then(|debug_panel| {
+            let project = debug_panel.read(cx).project().read(cx);
+            let worktree = task_contexts
+                .worktree()
+                .and_then(|worktree_id| project.worktree_for_id(worktree_id, cx))?;
+            Some((project.dap_store(), worktree))
+        });
+
         let valid_adapters: HashSet<_> = cx.global::<DapRegistry>().enumerate_adapters();
 
         cx.spawn(async move |this, cx| {
-            let (recent, scenarios) = if let Some(task) = task {
+            let (recent, mut scenarios) = if let Some(task) = task {
                 task.await
             } else {
                 (Vec::new(), Vec::new())
             };
+            if let Some((dap_store, worktree)) = dap_store_and_worktree {
+                let Ok(task) = dap_store.update(cx, |dap_store, cx| {
+                    dap_store.with_generated_debug_scenarios(&worktree, scenarios, cx)
+                }) else {
+                    return;
+                };
+                scenarios = task.await;
+            }
 
             this.update(cx, |this, cx| {
                 if !recent.is_empty() {
@@ -1489,6 +1506,8 @@ impl PickerDelegate for DebugDelegate {
             Some(TaskSourceKind::UserInput) => (Some(Icon::new(IconName::Terminal)), None),
             Some(TaskSourceKind::AbsPath { .. }) => (Some(Icon::new(IconName::Settings)), None),
             Some(TaskSourceKind::Worktree { .. }) => (Some(Icon::new(IconName::FileTree)), None),
+            Some(TaskSourceKind::BuildFile { .. }) => (Some(Icon::new(IconName::ToolHammer)), None),
+            Some(TaskSourceKind::Extension { .. }) => (Some(Icon::new(IconName::Blocks)), None),
             Some(TaskSourceKind::Lsp { language_name, .. }) => (
                 file_icons::FileIcons::get(cx)
                     .get_icon_for_type(&language_name.to_lowercase(), cx)
@@ -10,7 +10,7 @@ use std::{
     ops::ControlFlow,
     path::PathBuf,
     sync::{Arc, LazyLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -40,7 +40,7 @@ use language::Buffer;
 use loaded_source_list::LoadedSourceList;
 use module_list::ModuleList;
 use project::{
-    DebugScenarioContext, Project, WorktreeId,
+    DebugScenarioContext, Project, TaskSourceKind, WorktreeId,
     debugger::session::{self, Session, SessionEvent, SessionStateEvent, ThreadId, ThreadStatus},
 };
 use serde_json::Value;
@@ -1100,6 +1100,7 @@ impl RunningState {
                     args,
                     ..task.resolved.clone()
                 };
+                let started_at = Instant::now();
                 let terminal = project
                     .update(cx, |project, cx| {
                         project.create_terminal_task(
@@ -1127,13 +1128,57 @@ impl RunningState {
                     });
                 })?;
 
-                let exit_status = terminal
-                    .read_with(cx, |terminal, cx| terminal.wait_for_completed_task(cx))?
-                    .await
-                    .context("Failed to wait for completed task")?;
+                if task.resolved.is_background {
+                    let task_exited = terminal
+                        .read_with(cx, |terminal, cx| terminal.wait_for_completed_task(cx))?;
+                    let ready = task_store.update(cx, |task_store, cx| {
+                        task_store.watch_background_task(&task.resolved, &terminal, cx);
+                        task_store.wait_until_ready(&task.resolved.id, cx)
+                    })?;
+                    cx.spawn({
+                        let task_store = task_store.clone();
+                        let task = task.clone();
+                        async move |cx| {
+                            let exit_status = task_exited.await;
+                            // Build tasks of debug sessions are not restarted, so they stay stopped once they exit.
+                            task_store
+                                .update(cx, |task_store, cx| {
+                                    task_store.background_task_exited(&task.resolved.id, false, cx);
+                                    if let Some(exit_status) = exit_status {
+                                        task_store.task_run_finished(
+                                            &TaskSourceKind::UserInput,
+                                            &task,
+                                            exit_status.code(),
+                                            started_at.elapsed(),
+                                            cx,
+                                        );
+                                    }
+                                })
+                                .ok();
+                        }
+                    })
+                    .detach();
+                    ready.await.context("Background build task did not get ready")?;
+                } else {
+                    let exit_status = terminal
+                        .read_with(cx, |terminal, cx| terminal.wait_for_completed_task(cx))?
+                        .await
+                        .context("Failed to wait for completed task")?;
+                    task_store
+                        .update(cx, |task_store, cx| {
+                            task_store.task_run_finished(
+                                &TaskSourceKind::UserInput,
+                                &task,
+                                exit_status.code(),
+                                started_at.elapsed(),
+                                cx,
+                            )
+                        })
+                        .ok();
 
-                if !exit_status.success() {
-                    anyhow::bail!("Build failed");
+                    if !exit_status.success() {
+                        anyhow::bail!("Build failed");
+                    }
                 }
                 Some((task.resolved.clone(), locator_name, extra_config))
             } else {
@@ -1259,7 +1304,8 @@ impl RunningState {
             args,
             command_label: title,
             cwd,
-            env: envs,
+            env: envs.clone(),
+            task_env: envs,
             use_new_terminal: true,
             allow_concurrent_runs: true,
             reveal: task::RevealStrategy::NoFocus,
@@ -1269,6 +1315,10 @@ impl RunningState {
             show_summary: false,
             show_command: false,
             show_rerun: false,
+            problem_matchers: Vec::new(),
+            is_background: false,
+            ready_pattern: None,
+            execution_target: None,
         };
 
         let workspace = self.workspace.clone();
@@ -1675,6 +1725,16 @@ impl RunningState {
         });
     }
 
+    pub(crate) fn reverse_continue(&mut self, cx: &mut Context<Self>) {
+        let Some(thread_id) = self.thread_id else {
+            return;
+        };
+
+        self.session().update(cx, |state, cx| {
+            state.reverse_continue(thread_id, cx);
+        });
+    }
+
     pub fn rerun_session(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if let Some((scenario, context)) = self.scenario.take().zip(self.scenario_context.take())
             && scenario.build.is_some()
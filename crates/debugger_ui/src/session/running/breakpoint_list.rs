@@ -515,10 +515,10 @@ impl BreakpointList {
                 let name = session.adapter().0;
                 Self::kvp_key(&name)
             };
-            let settings = self.dap_store.update(cx, |this, cx| {
-                this.sync_adapter_options(session, cx);
-            });
-            let value = serde_json::to_string(&settings);
+            let settings = self
+                .dap_store
+                .update(cx, |this, cx| this.sync_adapter_options(session, cx));
+            let value = serde_json::to_string(settings.as_ref());
 
             cx.background_executor()
                 .spawn(async move { KEY_VALUE_STORE.write_kvp(key, value?).await })
@@ -411,7 +411,7 @@ impl VariableList {
             StackFrameListEvent::SelectedStackFrameChanged(stack_frame_id) => {
                 self.selected_stack_frame_id = Some(*stack_frame_id);
                 self.session.update(cx, |session, cx| {
-                    session.refresh_watchers(*stack_frame_id, cx);
+                    session.select_watch_frame(*stack_frame_id, cx);
                 });
                 self.build_entries(cx);
             }
@@ -965,8 +965,8 @@ impl VariableList {
             return;
         };
 
-        self.session.update(cx, |session, _| {
-            session.remove_watcher(watcher.expression.clone());
+        self.session.update(cx, |session, cx| {
+            session.remove_watcher(watcher.expression.clone(), cx);
         });
         self.build_entries(cx);
     }
@@ -1,17 +1,21 @@
 use crate::{
+    debugger_panel::CompoundScenario,
     persistence::DebuggerPaneItem,
     tests::{start_debug_session, start_debug_session_with},
     *,
 };
+use collections::HashSet;
 use dap::{
     ErrorResponse, Message, RunInTerminalRequestArguments, SourceBreakpoint,
     StartDebuggingRequestArguments, StartDebuggingRequestArgumentsRequest,
     adapters::DebugTaskDefinition,
     client::SessionId,
+    recording::{DapRecording, MessageDirection, RecordedMessage},
     requests::{
-        Continue, Disconnect, Launch, Next, RunInTerminal, SetBreakpoints, StackTrace,
-        StartDebugging, StepBack, StepIn, StepOut, Threads,
+        Continue, Disassemble, Disconnect, Launch, Next, ReverseContinue, RunInTerminal,
+        SetBreakpoints, StackTrace, StartDebugging, StepBack, StepIn, StepOut, Threads,
     },
+    transport::{IoKind, LogKind},
 };
 use editor::{
     ActiveDebugLine, Editor, EditorMode, MultiBuffer,
@@ -20,18 +24,24 @@ use editor::{
 use gpui::{BackgroundExecutor, TestAppContext, VisualTestContext};
 use project::{
     FakeFs, Project,
-    debugger::session::{ThreadId, ThreadStatus},
+    debugger::{
+        breakpoint_store::{
+            Breakpoint, BreakpointEditAction, BreakpointSessionState, BreakpointWithPosition,
+        },
+        session::{ThreadId, ThreadStatus},
+    },
 };
 use serde_json::json;
 use std::{
     path::Path,
     sync::{
         Arc,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
     },
 };
 use terminal_view::terminal_panel::TerminalPanel;
 use tests::{active_debug_session_panel, init_test, init_test_workspace};
+use text::Point;
 use util::{path, rel_path::rel_path};
 use workspace::item::SaveOptions;
 use workspace::{Item, dock::Panel};
@@ -886,6 +896,413 @@ async fn test_shutdown_parent_session_if_all_children_are_shutdown(
     });
 }
 
+#[gpui::test]
+async fn test_compound_sessions_route_breakpoints_and_stop_together(
+    executor: BackgroundExecutor,
+    cx: &mut TestAppContext,
+) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "client": {
+                "main.rs": "First line\nSecond line\nThird line\nFourth line",
+            },
+            "server": {
+                "main.rs": "First line\nSecond line\nThird line\nFourth line",
+            },
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let dap_store = project.update(cx, |project, _| project.dap_store());
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let worktree_id = workspace
+        .update(cx, |workspace, _, cx| {
+            workspace
+                .project()
+                .read(cx)
+                .worktrees(cx)
+                .next()
+                .unwrap()
+                .read(cx)
+                .id()
+        })
+        .unwrap();
+
+    let client_session = start_debug_session(&workspace, cx, |_| {}).unwrap();
+    let server_session = start_debug_session(&workspace, cx, |_| {}).unwrap();
+
+    let mut set_breakpoints_calls = Vec::new();
+    for (session, scope) in [
+        (&client_session, path!("/project/client")),
+        (&server_session, path!("/project/server")),
+    ] {
+        session.update(cx, |session, _| {
+            session.set_breakpoint_scope(Some(vec![Path::new(scope).into()]))
+        });
+        let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+        let calls = Arc::new(AtomicUsize::new(0));
+        client.on_request::<SetBreakpoints, _>({
+            let calls = calls.clone();
+            move |_, args| {
+                assert!(args.source.path.unwrap().starts_with(scope));
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(dap::SetBreakpointsResponse {
+                    breakpoints: Vec::default(),
+                })
+            }
+        });
+        client.on_request::<Disconnect, _>(move |_, _| Ok(()));
+        set_breakpoints_calls.push(calls);
+    }
+
+    let group_id = dap_store.update(cx, |dap_store, cx| {
+        let group_id = dap_store.new_session_group("Client + Server".into(), true);
+        for session in [&client_session, &server_session] {
+            dap_store
+                .add_session_to_group(group_id, session.read(cx).session_id())
+                .unwrap();
+        }
+        group_id
+    });
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_buffer((worktree_id, rel_path("client/main.rs")), cx)
+        })
+        .await
+        .unwrap();
+
+    let (editor, cx) = cx.add_window_view(|window, cx| {
+        Editor::new(
+            EditorMode::full(),
+            MultiBuffer::build_from_buffer(buffer, cx),
+            Some(project.clone()),
+            window,
+            cx,
+        )
+    });
+
+    editor.update_in(cx, |editor, window, cx| {
+        editor.toggle_breakpoint(&actions::ToggleBreakpoint, window, cx);
+    });
+
+    cx.run_until_parked();
+
+    assert_eq!(set_breakpoints_calls[0].load(Ordering::SeqCst), 1);
+    assert_eq!(
+        set_breakpoints_calls[1].load(Ordering::SeqCst),
+        0,
+        "Breakpoints outside the server's scope must not be sent to it"
+    );
+
+    dap_store
+        .update(cx, |dap_store, cx| {
+            dap_store.shutdown_session(client_session.read(cx).session_id(), cx)
+        })
+        .await
+        .unwrap();
+
+    dap_store.update(cx, |dap_store, cx| {
+        assert!(
+            dap_store
+                .session_by_id(server_session.read(cx).session_id())
+                .is_none(),
+            "Stopping one session of a stop-all group should stop the others"
+        );
+        assert!(dap_store.session_group(group_id).is_none());
+    });
+}
+
+#[gpui::test]
+async fn test_replaying_recorded_session(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+    fs.insert_tree(path!("/project"), json!({ "main.rs": "fn main() {}" }))
+        .await;
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+
+    let recorded = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let session = start_debug_session(&workspace, cx, {
+        let recorded = recorded.clone();
+        move |client| {
+            client.on_request::<Threads, _>(move |_, _| {
+                Ok(dap::ThreadsResponse {
+                    threads: vec![dap::Thread {
+                        id: 1,
+                        name: "main".into(),
+                    }],
+                })
+            });
+            client.on_request::<StackTrace, _>(move |_, _| {
+                Ok(dap::StackTraceResponse {
+                    stack_frames: Vec::default(),
+                    total_frames: None,
+                })
+            });
+
+            let recorded = recorded.clone();
+            client.add_log_handler(
+                move |io_kind, _, message| {
+                    let direction = match io_kind {
+                        IoKind::StdIn => MessageDirection::ToAdapter,
+                        IoKind::StdOut => MessageDirection::FromAdapter,
+                        IoKind::StdErr => return,
+                    };
+                    recorded.lock().unwrap().push(RecordedMessage {
+                        direction,
+                        message: serde_json::from_str(message).unwrap(),
+                    });
+                },
+                LogKind::Rpc,
+            );
+        }
+    })
+    .unwrap();
+    let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+    client
+        .fake_event(dap::messages::Events::Stopped(dap::StoppedEvent {
+            reason: dap::StoppedEventReason::Pause,
+            description: None,
+            thread_id: Some(1),
+            preserve_focus_hint: None,
+            text: None,
+            all_threads_stopped: None,
+            hit_breakpoint_ids: None,
+        }))
+        .await;
+    cx.run_until_parked();
+
+    let threads = session.update(cx, |session, cx| session.threads(cx));
+    assert_eq!(
+        threads
+            .iter()
+            .map(|(thread, status)| (thread.name.clone(), *status))
+            .collect::<Vec<_>>(),
+        vec![("main".to_string(), ThreadStatus::Stopped)]
+    );
+    cx.run_until_parked();
+
+    let recording = DapRecording::new(recorded.lock().unwrap().clone());
+    assert!(
+        recording
+            .messages()
+            .iter()
+            .any(|recorded| recorded.message["command"] == "initialize")
+    );
+
+    // The replayed adapter reports the same thread and stops it, without any handlers or injected events.
+    let replayed_session = start_debug_session(&workspace, cx, move |client| {
+        client.replay(recording.clone());
+    })
+    .unwrap();
+    cx.run_until_parked();
+
+    let replayed_threads = replayed_session.update(cx, |session, cx| session.threads(cx));
+    assert_eq!(
+        replayed_threads
+            .iter()
+            .map(|(thread, status)| (thread.name.clone(), *status))
+            .collect::<Vec<_>>(),
+        vec![("main".to_string(), ThreadStatus::Stopped)]
+    );
+}
+
+#[gpui::test]
+async fn test_restart_on_save(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "main.rs": "fn main() {}",
+            "README.md": "# Project",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+
+    let restarted = Arc::new(AtomicUsize::new(0));
+    let _session = start_debug_session_with(
+        &workspace,
+        cx,
+        DebugTaskDefinition {
+            adapter: "fake-adapter".into(),
+            label: "test".into(),
+            config: json!({
+                "request": "launch",
+                "restartOnSave": ["**/*.rs"],
+            }),
+            tcp_connection: None,
+        },
+        {
+            let restarted = restarted.clone();
+            move |client| {
+                client.on_request::<dap::requests::Initialize, _>(move |_, _| {
+                    Ok(dap::Capabilities {
+                        supports_restart_request: Some(true),
+                        ..Default::default()
+                    })
+                });
+                let restarted = restarted.clone();
+                client.on_request::<dap::requests::Restart, _>(move |_, _| {
+                    restarted.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                });
+            }
+        },
+    )
+    .unwrap();
+    cx.run_until_parked();
+
+    for (path, expected_restarts) in [("README.md", 0), ("main.rs", 1)] {
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_buffer((worktree_id, rel_path(path)), cx)
+            })
+            .await
+            .unwrap();
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+        project
+            .update(cx, |project, cx| project.save_buffer(buffer, cx))
+            .await
+            .unwrap();
+        cx.run_until_parked();
+
+        assert_eq!(
+            restarted.load(Ordering::SeqCst),
+            expected_restarts,
+            "unexpected restart count after saving {path}"
+        );
+    }
+
+    // Autosaves don't restart the session.
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_buffer((worktree_id, rel_path("main.rs")), cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "\n")], None, cx));
+    project
+        .update(cx, |project, cx| {
+            project.autosave_buffers(HashSet::from_iter([buffer.clone()]), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    assert_eq!(restarted.load(Ordering::SeqCst), 1);
+}
+
+#[gpui::test]
+async fn test_start_compound_session(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "client": { "main.rs": "First line" },
+            "server": { "main.rs": "First line" },
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let dap_store = project.update(cx, |project, _| project.dap_store());
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let _subscription = project::debugger::test::intercept_debug_sessions(cx, |client| {
+        client.on_request::<Disconnect, _>(move |_, _| Ok(()));
+    });
+
+    let scopes = [path!("/project/client"), path!("/project/server")];
+    let group_id = workspace
+        .update(cx, |workspace, window, cx| {
+            let scenarios = scopes
+                .iter()
+                .map(|scope| CompoundScenario {
+                    scenario: DebugTaskDefinition {
+                        adapter: "fake-adapter".into(),
+                        label: Path::new(scope)
+                            .file_name()
+                            .unwrap()
+                            .to_string_lossy()
+                            .into(),
+                        config: json!({ "request": "launch" }),
+                        tcp_connection: None,
+                    }
+                    .to_scenario(),
+                    breakpoint_scope: Some(vec![Path::new(scope).into()]),
+                })
+                .collect();
+            workspace
+                .panel::<DebugPanel>(cx)
+                .unwrap()
+                .update(cx, |panel, cx| {
+                    panel.start_compound_session(
+                        "Client + Server".into(),
+                        scenarios,
+                        true,
+                        task::TaskContext::default(),
+                        None,
+                        window,
+                        cx,
+                    )
+                })
+        })
+        .unwrap();
+    cx.run_until_parked();
+
+    let sessions = dap_store.read_with(cx, |dap_store, cx| {
+        let group = dap_store.session_group(group_id).unwrap();
+        let sessions = group
+            .session_ids()
+            .iter()
+            .map(|session_id| dap_store.session_by_id(session_id).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sessions.len(), 2);
+        for (session, scope) in sessions.iter().zip(scopes) {
+            assert_eq!(
+                session.read(cx).breakpoint_scope(),
+                Some([Arc::<Path>::from(Path::new(scope))].as_slice())
+            );
+        }
+        sessions
+    });
+
+    dap_store
+        .update(cx, |dap_store, cx| {
+            dap_store.shutdown_session(sessions[0].read(cx).session_id(), cx)
+        })
+        .await
+        .unwrap();
+    dap_store.read_with(cx, |dap_store, cx| {
+        assert!(
+            dap_store
+                .session_by_id(sessions[1].read(cx).session_id())
+                .is_none()
+        );
+        assert!(dap_store.session_group(group_id).is_none());
+    });
+}
+
 #[gpui::test]
 async fn test_debug_panel_item_thread_status_reset_on_failure(
     executor: BackgroundExecutor,
@@ -994,6 +1411,20 @@ async fn test_debug_panel_item_thread_status_reset_on_failure(
         })
     });
 
+    client.on_request::<ReverseContinue, _>(move |_, _| {
+        Err(ErrorResponse {
+            error: Some(Message {
+                id: 1,
+                format: "error".into(),
+                variables: None,
+                send_telemetry: None,
+                show_user: None,
+                url: None,
+                url_label: None,
+            }),
+        })
+    });
+
     client.on_request::<Continue, _>(move |_, _| {
         Err(ErrorResponse {
             error: Some(Message {
@@ -1024,6 +1455,7 @@ async fn test_debug_panel_item_thread_status_reset_on_failure(
 
     let running_state = active_debug_session_panel(workspace, cx)
         .read_with(cx, |item, _| item.running_state().clone());
+    assert!(session.read_with(cx, |session, _| session.supports_step_back()));
 
     cx.run_until_parked();
     let thread_id = ThreadId(1);
@@ -1032,6 +1464,7 @@ async fn test_debug_panel_item_thread_status_reset_on_failure(
         "step_over",
         "continue_thread",
         "step_back",
+        "reverse_continue",
         "step_in",
         "step_out",
     ] {
@@ -1039,6 +1472,7 @@ async fn test_debug_panel_item_thread_status_reset_on_failure(
             "step_over" => running_state.step_over(cx),
             "continue_thread" => running_state.continue_thread(cx),
             "step_back" => running_state.step_back(cx),
+            "reverse_continue" => running_state.reverse_continue(cx),
             "step_in" => running_state.step_in(cx),
             "step_out" => running_state.step_out(cx),
             _ => unreachable!(),
@@ -1051,7 +1485,7 @@ async fn test_debug_panel_item_thread_status_reset_on_failure(
                     .thread_status(cx)
                     .expect("There should be an active thread selected"),
                 match *operation {
-                    "continue_thread" => ThreadStatus::Running,
+                    "continue_thread" | "reverse_continue" => ThreadStatus::Running,
                     _ => ThreadStatus::Stepping,
                 },
                 "Thread status was not set to correct intermediate state after {} request",
@@ -1242,6 +1676,336 @@ async fn test_send_breakpoints_when_editor_has_been_saved(
     );
 }
 
+#[gpui::test]
+async fn test_breakpoint_validation_is_reflected_in_breakpoint_store(
+    executor: BackgroundExecutor,
+    cx: &mut TestAppContext,
+) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "main.rs": "First line\nSecond line\nThird line\nFourth line",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let worktree_id = workspace
+        .update(cx, |workspace, _, cx| {
+            workspace
+                .project()
+                .read(cx)
+                .worktrees(cx)
+                .next()
+                .unwrap()
+                .read(cx)
+                .id()
+        })
+        .unwrap();
+
+    let session = start_debug_session(&workspace, cx, |_| {}).unwrap();
+    let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+    let session_id = session.update(cx, |session, _| session.session_id());
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_buffer((worktree_id, rel_path("main.rs")), cx)
+        })
+        .await
+        .unwrap();
+
+    client.on_request::<SetBreakpoints, _>(move |_, args| {
+        assert_eq!(
+            json!(vec![
+                SourceBreakpoint {
+                    line: 2,
+                    column: None,
+                    condition: Some("x > 1".into()),
+                    hit_condition: Some("3".into()),
+                    log_message: None,
+                    mode: None
+                },
+                SourceBreakpoint {
+                    line: 4,
+                    column: None,
+                    condition: None,
+                    hit_condition: None,
+                    log_message: Some("x = {x}".into()),
+                    mode: None
+                }
+            ]),
+            json!(args.breakpoints.unwrap())
+        );
+
+        Ok(dap::SetBreakpointsResponse {
+            breakpoints: vec![
+                dap::Breakpoint {
+                    id: Some(1),
+                    verified: true,
+                    line: Some(3),
+                    ..Default::default()
+                },
+                dap::Breakpoint {
+                    id: None,
+                    verified: false,
+                    line: Some(4),
+                    ..Default::default()
+                },
+            ],
+        })
+    });
+
+    let breakpoint_store = project.read_with(cx, |project, _| project.breakpoint_store());
+    breakpoint_store.update(cx, |store, cx| {
+        let snapshot = buffer.read(cx).snapshot();
+        for (row, bp) in [
+            (
+                1,
+                Breakpoint {
+                    condition: Some("x > 1".into()),
+                    ..Breakpoint::new_condition("3")
+                },
+            ),
+            (3, Breakpoint::new_log("x = {x}")),
+        ] {
+            store.toggle_breakpoint(
+                buffer.clone(),
+                BreakpointWithPosition {
+                    position: snapshot.anchor_before(Point::new(row, 0)),
+                    bp,
+                },
+                BreakpointEditAction::Toggle,
+                cx,
+            );
+        }
+    });
+
+    cx.run_until_parked();
+
+    let states = breakpoint_store.read_with(cx, |store, _| {
+        store
+            .session_breakpoints(Path::new(path!("/project/main.rs")), session_id)
+            .into_iter()
+            .map(|(_, state)| state)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(
+        states,
+        vec![
+            Some(BreakpointSessionState {
+                id: Some(1),
+                verified: true,
+                row: Some(2),
+            }),
+            Some(BreakpointSessionState {
+                id: None,
+                verified: false,
+                row: None,
+            }),
+        ]
+    );
+
+    client
+        .fake_event(dap::messages::Events::Breakpoint(dap::BreakpointEvent {
+            reason: dap::BreakpointEventReason::Changed,
+            breakpoint: dap::Breakpoint {
+                id: Some(1),
+                verified: false,
+                line: Some(2),
+                ..Default::default()
+            },
+        }))
+        .await;
+
+    cx.run_until_parked();
+
+    let state = breakpoint_store.read_with(cx, |store, _| {
+        store.session_breakpoints(Path::new(path!("/project/main.rs")), session_id)[0].1
+    });
+    assert_eq!(
+        state,
+        Some(BreakpointSessionState {
+            id: Some(1),
+            verified: false,
+            row: None,
+        })
+    );
+}
+
+#[gpui::test]
+async fn test_path_mappings_are_applied_to_breakpoints_and_stack_frames(
+    executor: BackgroundExecutor,
+    cx: &mut TestAppContext,
+) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "main.rs": "First line\nSecond line\nThird line\nFourth line",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+    let worktree_id = workspace
+        .update(cx, |workspace, _, cx| {
+            workspace
+                .project()
+                .read(cx)
+                .worktrees(cx)
+                .next()
+                .unwrap()
+                .read(cx)
+                .id()
+        })
+        .unwrap();
+
+    let session = start_debug_session_with(
+        &workspace,
+        cx,
+        DebugTaskDefinition {
+            adapter: "fake-adapter".into(),
+            label: "remote".into(),
+            config: json!({
+                "request": "launch",
+                "remote": {
+                    "pathMappings": [
+                        { "localRoot": path!("/project"), "remoteRoot": "/srv/app" },
+                    ],
+                },
+            }),
+            tcp_connection: None,
+        },
+        |_| {},
+    )
+    .unwrap();
+    let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+
+    let called_set_breakpoints = Arc::new(AtomicBool::new(false));
+    client.on_request::<SetBreakpoints, _>({
+        let called_set_breakpoints = called_set_breakpoints.clone();
+        move |_, args| {
+            assert_eq!(Some("/srv/app/main.rs".into()), args.source.path);
+            called_set_breakpoints.store(true, Ordering::SeqCst);
+            Ok(dap::SetBreakpointsResponse {
+                breakpoints: Vec::default(),
+            })
+        }
+    });
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_buffer((worktree_id, rel_path("main.rs")), cx)
+        })
+        .await
+        .unwrap();
+
+    let (editor, cx) = cx.add_window_view(|window, cx| {
+        Editor::new(
+            EditorMode::full(),
+            MultiBuffer::build_from_buffer(buffer, cx),
+            Some(project.clone()),
+            window,
+            cx,
+        )
+    });
+
+    editor.update_in(cx, |editor, window, cx| {
+        editor.toggle_breakpoint(&actions::ToggleBreakpoint, window, cx);
+    });
+
+    cx.run_until_parked();
+
+    assert!(
+        called_set_breakpoints.load(Ordering::SeqCst),
+        "SetBreakpoints request must be called with the remote path"
+    );
+
+    client.on_request::<Threads, _>(move |_, _| {
+        Ok(dap::ThreadsResponse {
+            threads: vec![dap::Thread {
+                id: 1,
+                name: "Thread 1".into(),
+            }],
+        })
+    });
+
+    client.on_request::<StackTrace, _>(move |_, _| {
+        Ok(dap::StackTraceResponse {
+            stack_frames: vec![dap::StackFrame {
+                id: 1,
+                name: "main".into(),
+                source: Some(dap::Source {
+                    name: Some("main.rs".into()),
+                    path: Some("/srv/app/main.rs".into()),
+                    source_reference: None,
+                    presentation_hint: None,
+                    origin: None,
+                    sources: None,
+                    adapter_data: None,
+                    checksums: None,
+                }),
+                line: 1,
+                column: 1,
+                end_line: None,
+                end_column: None,
+                can_restart: None,
+                instruction_pointer_reference: None,
+                module_id: None,
+                presentation_hint: None,
+            }],
+            total_frames: None,
+        })
+    });
+
+    client
+        .fake_event(dap::messages::Events::Stopped(dap::StoppedEvent {
+            reason: dap::StoppedEventReason::Pause,
+            description: None,
+            thread_id: Some(1),
+            preserve_focus_hint: None,
+            text: None,
+            all_threads_stopped: None,
+            hit_breakpoint_ids: None,
+        }))
+        .await;
+
+    cx.run_until_parked();
+
+    session.update(cx, |session, cx| {
+        session.threads(cx);
+    });
+
+    cx.run_until_parked();
+
+    session.update(cx, |session, cx| {
+        session.stack_frames(ThreadId(1), cx).ok();
+    });
+
+    cx.run_until_parked();
+
+    let paths = session.update(cx, |session, cx| {
+        session
+            .stack_frames(ThreadId(1), cx)
+            .unwrap()
+            .into_iter()
+            .filter_map(|frame| frame.dap.source?.path)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(paths, vec![path!("/project/main.rs").to_string()]);
+}
+
 #[gpui::test]
 async fn test_unsetting_breakpoints_on_clear_breakpoint_action(
     executor: BackgroundExecutor,
@@ -1360,6 +2124,94 @@ async fn test_unsetting_breakpoints_on_clear_breakpoint_action(
     cx.run_until_parked();
 }
 
+#[gpui::test]
+async fn test_disassembly_is_cached_until_stopped(
+    executor: BackgroundExecutor,
+    cx: &mut TestAppContext,
+) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+    fs.insert_tree(path!("/project"), json!({ "main.rs": "" }))
+        .await;
+
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+
+    let session = start_debug_session(&workspace, cx, |client| {
+        client.on_request::<dap::requests::Initialize, _>(move |_, _| {
+            Ok(dap::Capabilities {
+                supports_disassemble_request: Some(true),
+                ..Default::default()
+            })
+        });
+    })
+    .unwrap();
+    let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+
+    client.on_request::<StackTrace, _>(move |_, _| {
+        Ok(dap::StackTraceResponse {
+            stack_frames: Vec::default(),
+            total_frames: None,
+        })
+    });
+
+    let disassemble_count = Arc::new(AtomicUsize::new(0));
+    client.on_request::<Disassemble, _>({
+        let disassemble_count = disassemble_count.clone();
+        move |_, args| {
+            assert_eq!("0x1000", args.memory_reference);
+            assert_eq!(Some(-64), args.instruction_offset);
+            assert_eq!(64, args.instruction_count);
+            disassemble_count.fetch_add(1, Ordering::SeqCst);
+
+            Ok(serde_json::from_value(json!({
+                "instructions": [{ "address": "0x0fc0", "instruction": "nop" }]
+            }))
+            .unwrap())
+        }
+    });
+
+    let page = session.update(cx, |session, cx| {
+        session.disassembly_page("0x1000".into(), -1, cx)
+    });
+    assert!(page.is_none());
+    cx.run_until_parked();
+
+    let page = session
+        .update(cx, |session, cx| {
+            session.disassembly_page("0x1000".into(), -1, cx)
+        })
+        .unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].instruction, "nop");
+    assert_eq!(disassemble_count.load(Ordering::SeqCst), 1);
+
+    client
+        .fake_event(dap::messages::Events::Stopped(dap::StoppedEvent {
+            reason: dap::StoppedEventReason::Pause,
+            description: None,
+            thread_id: Some(1),
+            preserve_focus_hint: None,
+            text: None,
+            all_threads_stopped: None,
+            hit_breakpoint_ids: None,
+        }))
+        .await;
+    cx.run_until_parked();
+
+    let page = session.update(cx, |session, cx| {
+        session.disassembly_page("0x1000".into(), -1, cx)
+    });
+    assert!(
+        page.is_none(),
+        "Disassembly should be refetched after a stop"
+    );
+    cx.run_until_parked();
+    assert_eq!(disassemble_count.load(Ordering::SeqCst), 2);
+}
+
 #[gpui::test]
 async fn test_debug_session_is_shutdown_when_attach_and_launch_request_fails(
     executor: BackgroundExecutor,
@@ -1,6 +1,9 @@
 use std::{path::Path, sync::Arc};
 
-use dap::{Scope, StackFrame, Variable, requests::Variables};
+use dap::{
+    DapRegistry, Scope, StackFrame, Variable, inline_value::QueryInlineValueProvider,
+    requests::Variables,
+};
 use editor::{Editor, EditorMode, MultiBuffer};
 use gpui::{BackgroundExecutor, TestAppContext, VisualTestContext};
 use language::{
@@ -2230,6 +2233,74 @@ func main() {
     .await;
 }
 
+#[gpui::test]
+async fn test_registered_inline_value_provider(
+    executor: BackgroundExecutor,
+    cx: &mut TestAppContext,
+) {
+    let variables = [("x", "42"), ("y", "hello")];
+
+    let before = r#"
+package main
+
+var globalCounter int = 100
+
+func main() {
+    x := 42
+    y := "hello"
+    z := x + 10
+    println(x, y, z)
+}
+"#
+    .unindent();
+
+    let after = r#"
+package main
+
+var globalCounter: 100 int = 100
+
+func main() {
+    x: 42 := 42
+    y := "hello"
+    z := x + 10
+    println(x, y, z)
+}
+"#
+    .unindent();
+
+    let language = Arc::new(Language::new(
+        LanguageConfig {
+            name: "Go".into(),
+            matcher: LanguageMatcher {
+                path_suffixes: vec!["go".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Some(tree_sitter_go::LANGUAGE.into()),
+    ));
+    cx.update(|cx| {
+        DapRegistry::global(cx).add_inline_value_provider(
+            language.name(),
+            Arc::new(QueryInlineValueProvider::new(include_str!(
+                "../../../languages/src/go/debugger.scm"
+            ))),
+        );
+    });
+
+    test_inline_values_util(
+        &variables,
+        &[("globalCounter", "100")],
+        &before,
+        &after,
+        None,
+        language,
+        executor,
+        cx,
+    )
+    .await;
+}
+
 #[gpui::test]
 async fn test_trim_multi_line_inline_value(executor: BackgroundExecutor, cx: &mut TestAppContext) {
     let variables = [("y", "hello\n world")];
@@ -1,6 +1,6 @@
 use std::sync::{
     Arc,
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 
 use crate::{
@@ -16,9 +16,9 @@ use dap::{
     Scope, StackFrame, Variable,
     requests::{Evaluate, Initialize, Launch, Scopes, StackTrace, Variables},
 };
-use gpui::{BackgroundExecutor, TestAppContext, VisualTestContext};
+use gpui::{BackgroundExecutor, Entity, TestAppContext, VisualTestContext};
 use menu::{SelectFirst, SelectNext, SelectPrevious};
-use project::{FakeFs, Project};
+use project::{FakeFs, Project, debugger::session::Session};
 use serde_json::json;
 use ui::SharedString;
 use unindent::Unindent as _;
@@ -2338,5 +2338,132 @@ async fn test_refresh_watchers(executor: BackgroundExecutor, cx: &mut TestAppCon
         assert_eq!("value updated", watcher.value.to_string());
         assert_eq!("variable1", watcher.expression.to_string());
         assert_eq!(3, watcher.variables_reference);
+        assert!(watcher.changed);
     });
 }
+
+#[gpui::test]
+async fn test_persisted_watchers(executor: BackgroundExecutor, cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(executor.clone());
+    // A root no other test uses, since tests share the same database.
+    fs.insert_tree(
+        path!("/persisted-watchers"),
+        json!({
+           "main.js": "let x = 1;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs, [path!("/persisted-watchers").as_ref()], cx).await;
+    project.update(cx, |project, cx| {
+        project.dap_store().update(cx, |dap_store, _| {
+            dap_store.persist_watch_expressions_for_test()
+        })
+    });
+    let workspace = init_test_workspace(&project, cx).await;
+    let cx = &mut VisualTestContext::from_window(*workspace, cx);
+
+    let evaluations = Arc::new(AtomicUsize::new(0));
+    let fake_stopped_thread = |session: &Entity<Session>, cx: &mut VisualTestContext| {
+        let client = session.update(cx, |session, _| session.adapter_client().unwrap());
+        client.on_request::<dap::requests::Threads, _>(move |_, _| {
+            Ok(dap::ThreadsResponse {
+                threads: vec![dap::Thread {
+                    id: 1,
+                    name: "Thread 1".into(),
+                }],
+            })
+        });
+        client.on_request::<StackTrace, _>(move |_, _| {
+            Ok(dap::StackTraceResponse {
+                stack_frames: vec![StackFrame {
+                    id: 1,
+                    name: "main".into(),
+                    source: None,
+                    line: 1,
+                    column: 1,
+                    end_line: None,
+                    end_column: None,
+                    can_restart: None,
+                    instruction_pointer_reference: None,
+                    module_id: None,
+                    presentation_hint: None,
+                }],
+                total_frames: None,
+            })
+        });
+        client.on_request::<Scopes, _>(move |_, _| Ok(dap::ScopesResponse { scopes: vec![] }));
+        client.on_request::<Evaluate, _>({
+            let evaluations = evaluations.clone();
+            move |_, args| {
+                let evaluation = evaluations.fetch_add(1, Ordering::SeqCst) + 1;
+                Ok(dap::EvaluateResponse {
+                    result: format!("{} = {evaluation}", args.expression),
+                    type_: None,
+                    presentation_hint: None,
+                    variables_reference: 0,
+                    named_variables: None,
+                    indexed_variables: None,
+                    memory_reference: None,
+                    value_location_reference: None,
+                })
+            }
+        });
+        client
+    };
+    let stopped_event = || {
+        dap::messages::Events::Stopped(dap::StoppedEvent {
+            reason: dap::StoppedEventReason::Pause,
+            description: None,
+            thread_id: Some(1),
+            preserve_focus_hint: None,
+            text: None,
+            all_threads_stopped: None,
+            hit_breakpoint_ids: None,
+        })
+    };
+    let watched_values = |session: &Entity<Session>, cx: &mut VisualTestContext| {
+        session.read_with(cx, |session, _| {
+            session
+                .watchers()
+                .values()
+                .map(|watcher| (watcher.expression.to_string(), watcher.value.to_string()))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let session = start_debug_session(&workspace, cx, |_| {}).unwrap();
+    let client = fake_stopped_thread(&session, cx);
+    session
+        .update(cx, |session, cx| session.add_watcher("x".into(), 1, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        watched_values(&session, cx),
+        [("x".to_string(), "x = 1".to_string())]
+    );
+
+    client.fake_event(stopped_event()).await;
+    cx.run_until_parked();
+    assert_eq!(
+        watched_values(&session, cx),
+        [("x".to_string(), "x = 2".to_string())],
+        "the session should re-evaluate its watchers once when the debuggee stops"
+    );
+    assert_eq!(evaluations.load(Ordering::SeqCst), 2);
+
+    let restarted_session = start_debug_session(&workspace, cx, |_| {}).unwrap();
+    assert_eq!(
+        watched_values(&restarted_session, cx),
+        [("x".to_string(), String::new())],
+        "a session of the same scenario should restore the persisted watchers"
+    );
+    let client = fake_stopped_thread(&restarted_session, cx);
+    client.fake_event(stopped_event()).await;
+    cx.run_until_parked();
+    assert_eq!(
+        watched_values(&restarted_session, cx),
+        [("x".to_string(), "x = 3".to_string())]
+    );
+}
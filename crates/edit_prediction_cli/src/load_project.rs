@@ -180,6 +180,7 @@ async fn setup_project(
             app_state.fs.clone(),
             None,
             false,
+            false,
             cx,
         )
     })?;
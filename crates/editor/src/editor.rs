@@ -17215,11 +17215,7 @@ impl Editor {
             let result = find_file(&buffer, project, buffer_position, cx).await;
 
             if let Some((_, path)) = result {
-                workspace
-                    .update_in(cx, |workspace, window, cx| {
-                        workspace.open_resolved_path(path, window, cx)
-                    })?
-                    .await?;
+                hover_links::open_resolved_path(&workspace, path, cx).await?;
             }
             anyhow::Ok(())
         })
@@ -17356,11 +17352,7 @@ impl Editor {
                         let Some(workspace) = workspace else {
                             return Ok(Navigated::No);
                         };
-                        workspace
-                            .update_in(cx, |workspace, window, cx| {
-                                workspace.open_resolved_path(path, window, cx)
-                            })?
-                            .await?;
+                        hover_links::open_resolved_path(&workspace, path, cx).await?;
                         Ok(Navigated::Yes)
                     }
                     None => Ok(Navigated::No),
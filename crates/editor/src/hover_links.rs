@@ -8,13 +8,14 @@ use gpui::{App, AsyncWindowContext, Context, Entity, Modifiers, Task, Window, px
 use language::{Bias, ToOffset};
 use linkify::{LinkFinder, LinkKind};
 use lsp::LanguageServerId;
-use project::{InlayId, LocationLink, Project, ResolvedPath};
+use project::{InlayId, LocationLink, Project, ResolvedPathWithPosition};
 use regex::Regex;
 use settings::Settings;
 use std::{ops::Range, sync::LazyLock};
 use text::OffsetRangeExt;
 use theme::ActiveTheme as _;
 use util::{ResultExt, TryFutureExt as _, maybe};
+use workspace::Workspace;
 
 #[derive(Debug)]
 pub struct HoveredLinkState {
@@ -63,7 +64,7 @@ impl RangeInEditor {
 #[derive(Debug, Clone)]
 pub enum HoverLink {
     Url(String),
-    File(ResolvedPath),
+    File(ResolvedPathWithPosition),
     Text(LocationLink),
     InlayHint(lsp::Location, LanguageServerId),
 }
@@ -593,7 +594,7 @@ pub(crate) async fn find_file(
     project: Option<Entity<Project>>,
     position: text::Anchor,
     cx: &mut AsyncWindowContext,
-) -> Option<(Range<text::Anchor>, ResolvedPath)> {
+) -> Option<(Range<text::Anchor>, ResolvedPathWithPosition)> {
     let project = project?;
     let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot()).ok()?;
     let scope = snapshot.language_scope_at(position);
@@ -605,14 +606,14 @@ pub(crate) async fn find_file(
         project: &Entity<Project>,
         buffer: &Entity<language::Buffer>,
         cx: &mut AsyncWindowContext,
-    ) -> Option<ResolvedPath> {
+    ) -> Option<ResolvedPathWithPosition> {
         project
             .update(cx, |project, cx| {
                 project.resolve_path_in_buffer(candidate_file_path, buffer, cx)
             })
             .ok()?
             .await
-            .filter(|s| s.is_file())
+            .filter(|s| s.path.is_file())
     }
 
     let pattern_candidates = link_pattern_file_candidates(&candidate_file_path);
@@ -653,6 +654,28 @@ pub(crate) async fn find_file(
     None
 }
 
+/// Opens a file found in the text, moving the cursor to the position that followed its path.
+pub(crate) async fn open_resolved_path(
+    workspace: &Entity<Workspace>,
+    path: ResolvedPathWithPosition,
+    cx: &mut AsyncWindowContext,
+) -> anyhow::Result<()> {
+    let point = path.point();
+    let item = workspace
+        .update_in(cx, |workspace, window, cx| {
+            workspace.open_resolved_path(path.path, window, cx)
+        })?
+        .await?;
+    if let Some(point) = point
+        && let Some(editor) = item.downcast::<Editor>()
+    {
+        editor.update_in(cx, |editor, window, cx| {
+            editor.go_to_singleton_buffer_point(point, window, cx)
+        })?;
+    }
+    Ok(())
+}
+
 // Tries to capture potentially inlined links, like those found in markdown,
 // e.g. [LinkTitle](link_file.txt)
 // Since files can have parens, we should always return the full string
@@ -866,7 +866,11 @@ impl Item for Editor {
             if !buffers_to_save.is_empty() {
                 project
                     .update(cx, |project, cx| {
-                        project.save_buffers(buffers_to_save.clone(), cx)
+                        if options.autosave {
+                            project.autosave_buffers(buffers_to_save.clone(), cx)
+                        } else {
+                            project.save_buffers(buffers_to_save.clone(), cx)
+                        }
                     })?
                     .await?;
             }
@@ -1,10 +1,14 @@
 mod download_file_capability;
+mod network_capability;
 mod npm_install_package_capability;
 mod process_exec_capability;
+mod read_file_capability;
 
 pub use download_file_capability::*;
+pub use network_capability::*;
 pub use npm_install_package_capability::*;
 pub use process_exec_capability::*;
+pub use read_file_capability::*;
 
 use serde::{Deserialize, Serialize};
 
@@ -17,4 +21,31 @@ pub enum ExtensionCapability {
     DownloadFile(DownloadFileCapability),
     #[serde(rename = "npm:install")]
     NpmInstallPackage(NpmInstallPackageCapability),
+    #[serde(rename = "network:fetch")]
+    Network(NetworkCapability),
+    #[serde(rename = "fs:read")]
+    ReadFile(ReadFileCapability),
+}
+
+impl ExtensionCapability {
+    /// Describes what the capability allows, for asking the user to grant it.
+    pub fn description(&self) -> String {
+        match self {
+            Self::ProcessExec(capability) => {
+                format!("run `{} {}`", capability.command, capability.args.join(" "))
+            }
+            Self::DownloadFile(capability) => format!(
+                "download files from {}/{}",
+                capability.host,
+                capability.path.join("/")
+            ),
+            Self::NpmInstallPackage(capability) => {
+                format!("install the npm package {}", capability.package)
+            }
+            Self::Network(capability) => format!("send requests to {}", capability.host),
+            Self::ReadFile(capability) => {
+                format!("read {} in the workspace", capability.path.join("/"))
+            }
+        }
+    }
 }
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct NetworkCapability {
+    /// The host to send requests to. Use `*` for any host, or `*.example.com` for the subdomains of a host.
+    pub host: String,
+}
+
+impl NetworkCapability {
+    /// Returns whether the capability allows sending requests to the given URL.
+    pub fn allows(&self, url: &Url) -> bool {
+        let Some(desired_host) = url.host_str() else {
+            return false;
+        };
+
+        if self.host == "*" || self.host == desired_host {
+            return true;
+        }
+
+        self.host
+            .strip_prefix("*.")
+            .and_then(|domain| desired_host.strip_suffix(domain))
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let capability = NetworkCapability {
+            host: "*".to_string(),
+        };
+        assert_eq!(
+            capability.allows(&"https://example.com/some/path".parse().unwrap()),
+            true
+        );
+
+        let capability = NetworkCapability {
+            host: "api.github.com".to_string(),
+        };
+        assert_eq!(
+            capability.allows(&"https://api.github.com/repos".parse().unwrap()),
+            true
+        );
+        assert_eq!(
+            capability.allows(&"https://github.com/some-owner".parse().unwrap()),
+            false
+        );
+
+        let capability = NetworkCapability {
+            host: "*.example.com".to_string(),
+        };
+        assert_eq!(
+            capability.allows(&"https://api.example.com/path".parse().unwrap()),
+            true
+        );
+        assert_eq!(
+            capability.allows(&"https://example.com/path".parse().unwrap()),
+            false
+        );
+        assert_eq!(
+            capability.allows(&"https://fake-example.com/path".parse().unwrap()),
+            false
+        );
+    }
+}
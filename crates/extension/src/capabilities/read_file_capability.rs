@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use util::rel_path::RelPath;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ReadFileCapability {
+    /// The components of the paths of the files to read, relative to the root of a worktree. Use `*` for a single
+    /// wildcard component. If the last element is `**`, then any trailing components are allowed.
+    pub path: Vec<String>,
+}
+
+impl ReadFileCapability {
+    /// Returns whether the capability allows reading the file at the given path of a worktree.
+    pub fn allows(&self, path: &RelPath) -> bool {
+        let desired_path = path.components().collect::<Vec<_>>();
+
+        for (ix, path_component) in self.path.iter().enumerate() {
+            if path_component == "**" {
+                return true;
+            }
+
+            if ix >= desired_path.len() {
+                return false;
+            }
+
+            if path_component != "*" && path_component != desired_path[ix] {
+                return false;
+            }
+        }
+
+        if self.path.len() < desired_path.len() {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn rel_path(path: &str) -> &RelPath {
+        RelPath::unix(path).unwrap()
+    }
+
+    #[test]
+    fn test_allows() {
+        let capability = ReadFileCapability {
+            path: vec!["**".to_string()],
+        };
+        assert_eq!(capability.allows(rel_path("src/main.rs")), true);
+
+        let capability = ReadFileCapability {
+            path: vec!["package.json".to_string()],
+        };
+        assert_eq!(capability.allows(rel_path("package.json")), true);
+        assert_eq!(capability.allows(rel_path("web/package.json")), false);
+        assert_eq!(capability.allows(rel_path(".env")), false);
+
+        let capability = ReadFileCapability {
+            path: vec!["*".to_string(), "Cargo.toml".to_string()],
+        };
+        assert_eq!(capability.allows(rel_path("crates/Cargo.toml")), true);
+        assert_eq!(capability.allows(rel_path("Cargo.toml")), false);
+        assert_eq!(capability.allows(rel_path("crates/cli/Cargo.toml")), false);
+    }
+}
@@ -16,7 +16,7 @@ use fs::normalize_path;
 use gpui::{App, Task};
 use language::LanguageName;
 use semver::Version;
-use task::{SpawnInTerminal, VectorDebugConfig};
+use task::{ProblemMatcher, SpawnInTerminal, TaskTemplate, VectorDebugConfig};
 use util::rel_path::RelPath;
 
 pub use crate::capabilities::*;
@@ -169,6 +169,12 @@ pub trait Extension: Send + Sync + 'static {
         locator_name: String,
         config: SpawnInTerminal,
     ) -> Result<DebugRequest>;
+
+    /// Returns the task templates of the given task provider.
+    async fn task_templates(&self, provider_name: Arc<str>) -> Result<Vec<TaskTemplate>>;
+
+    /// Returns the matchers of the given problem matcher.
+    async fn problem_matchers(&self, matcher_name: Arc<str>) -> Result<Vec<ProblemMatcher>>;
 }
 
 pub fn parse_wasm_extension_version(extension_id: &str, wasm_bytes: &[u8]) -> Result<Version> {
@@ -1,14 +1,16 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use fs::Fs;
 use gpui::{App, Global, ReadGlobal, SharedString, Task};
 use language::{BinaryStatus, LanguageMatcher, LanguageName, LoadedLanguage};
 use lsp::LanguageServerName;
 use parking_lot::RwLock;
 
-use crate::{Extension, SlashCommand};
+use crate::{
+    ContextServerManifestEntry, Extension, ExtensionCapability, ExtensionManifest, SlashCommand,
+};
 
 #[derive(Default)]
 struct GlobalExtensionHostProxy(Arc<ExtensionHostProxy>);
@@ -32,7 +34,10 @@ pub struct ExtensionHostProxy {
     slash_command_proxy: RwLock<Option<Arc<dyn ExtensionSlashCommandProxy>>>,
     context_server_proxy: RwLock<Option<Arc<dyn ExtensionContextServerProxy>>>,
     debug_adapter_provider_proxy: RwLock<Option<Arc<dyn ExtensionDebugAdapterProviderProxy>>>,
+    task_provider_proxy: RwLock<Option<Arc<dyn ExtensionTaskProviderProxy>>>,
     language_model_provider_proxy: RwLock<Option<Arc<dyn ExtensionLanguageModelProviderProxy>>>,
+    permission_proxy: RwLock<Option<Arc<dyn ExtensionPermissionProxy>>>,
+    capability_proxy: RwLock<Option<Arc<dyn ExtensionCapabilityProxy>>>,
 }
 
 impl ExtensionHostProxy {
@@ -58,7 +63,10 @@ impl ExtensionHostProxy {
             slash_command_proxy: RwLock::default(),
             context_server_proxy: RwLock::default(),
             debug_adapter_provider_proxy: RwLock::default(),
+            task_provider_proxy: RwLock::default(),
             language_model_provider_proxy: RwLock::default(),
+            permission_proxy: RwLock::default(),
+            capability_proxy: RwLock::default(),
         }
     }
 
@@ -96,6 +104,10 @@ impl ExtensionHostProxy {
             .replace(Arc::new(proxy));
     }
 
+    pub fn register_task_provider_proxy(&self, proxy: impl ExtensionTaskProviderProxy) {
+        self.task_provider_proxy.write().replace(Arc::new(proxy));
+    }
+
     pub fn register_language_model_provider_proxy(
         &self,
         proxy: impl ExtensionLanguageModelProviderProxy,
@@ -104,6 +116,14 @@ impl ExtensionHostProxy {
             .write()
             .replace(Arc::new(proxy));
     }
+
+    pub fn register_permission_proxy(&self, proxy: impl ExtensionPermissionProxy) {
+        self.permission_proxy.write().replace(Arc::new(proxy));
+    }
+
+    pub fn register_capability_proxy(&self, proxy: impl ExtensionCapabilityProxy) {
+        self.capability_proxy.write().replace(Arc::new(proxy));
+    }
 }
 
 pub trait ExtensionThemeProxy: Send + Sync + 'static {
@@ -386,6 +406,15 @@ pub trait ExtensionContextServerProxy: Send + Sync + 'static {
         cx: &mut App,
     );
 
+    /// Registers a context server whose manifest entry declares how to launch it.
+    fn register_declared_context_server(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        server_id: Arc<str>,
+        entry: ContextServerManifestEntry,
+        cx: &mut App,
+    );
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App);
 }
 
@@ -403,6 +432,20 @@ impl ExtensionContextServerProxy for ExtensionHostProxy {
         proxy.register_context_server(extension, server_id, cx)
     }
 
+    fn register_declared_context_server(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        server_id: Arc<str>,
+        entry: ContextServerManifestEntry,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.context_server_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_declared_context_server(manifest, server_id, entry, cx)
+    }
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App) {
         let Some(proxy) = self.context_server_proxy.read().clone() else {
             return;
@@ -420,8 +463,10 @@ pub trait ExtensionDebugAdapterProviderProxy: Send + Sync + 'static {
         schema_path: &Path,
     );
     fn register_debug_locator(&self, extension: Arc<dyn Extension>, locator_name: Arc<str>);
+    fn register_debug_inline_values(&self, language: LanguageName, query_path: &Path);
     fn unregister_debug_adapter(&self, debug_adapter_name: Arc<str>);
     fn unregister_debug_locator(&self, locator_name: Arc<str>);
+    fn unregister_debug_inline_values(&self, language: LanguageName);
 }
 
 impl ExtensionDebugAdapterProviderProxy for ExtensionHostProxy {
@@ -445,6 +490,14 @@ impl ExtensionDebugAdapterProviderProxy for ExtensionHostProxy {
 
         proxy.register_debug_locator(extension, locator_name)
     }
+
+    fn register_debug_inline_values(&self, language: LanguageName, query_path: &Path) {
+        let Some(proxy) = self.debug_adapter_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_debug_inline_values(language, query_path)
+    }
     fn unregister_debug_adapter(&self, debug_adapter_name: Arc<str>) {
         let Some(proxy) = self.debug_adapter_provider_proxy.read().clone() else {
             return;
@@ -459,6 +512,97 @@ impl ExtensionDebugAdapterProviderProxy for ExtensionHostProxy {
 
         proxy.unregister_debug_locator(locator_name)
     }
+    fn unregister_debug_inline_values(&self, language: LanguageName) {
+        let Some(proxy) = self.debug_adapter_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.unregister_debug_inline_values(language)
+    }
+}
+
+pub trait ExtensionTaskProviderProxy: Send + Sync + 'static {
+    fn register_task_provider(
+        &self,
+        extension: Arc<dyn Extension>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    );
+
+    fn unregister_task_provider(
+        &self,
+        extension_id: Arc<str>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    );
+
+    fn register_problem_matcher(
+        &self,
+        extension: Arc<dyn Extension>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    );
+
+    fn unregister_problem_matcher(
+        &self,
+        extension_id: Arc<str>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    );
+}
+
+impl ExtensionTaskProviderProxy for ExtensionHostProxy {
+    fn register_task_provider(
+        &self,
+        extension: Arc<dyn Extension>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.task_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_task_provider(extension, provider_name, cx)
+    }
+
+    fn unregister_task_provider(
+        &self,
+        extension_id: Arc<str>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.task_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.unregister_task_provider(extension_id, provider_name, cx)
+    }
+
+    fn register_problem_matcher(
+        &self,
+        extension: Arc<dyn Extension>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.task_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.register_problem_matcher(extension, matcher_name, cx)
+    }
+
+    fn unregister_problem_matcher(
+        &self,
+        extension_id: Arc<str>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        let Some(proxy) = self.task_provider_proxy.read().clone() else {
+            return;
+        };
+
+        proxy.unregister_problem_matcher(extension_id, matcher_name, cx)
+    }
 }
 
 pub trait ExtensionLanguageModelProviderProxy: Send + Sync + 'static {
@@ -494,3 +638,87 @@ impl ExtensionLanguageModelProviderProxy for ExtensionHostProxy {
         proxy.unregister_language_model_provider(provider_id, cx)
     }
 }
+
+/// The answer of the user when asked to grant a capability to an extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapabilityGrant {
+    /// Grants the capability until Vector quits.
+    AllowOnce,
+    /// Grants the capability, and remembers it in the settings.
+    AllowAlways,
+    Deny,
+}
+
+pub trait ExtensionPermissionProxy: Send + Sync + 'static {
+    /// Asks the user whether to grant a capability declared in the manifest of an extension, the first time the
+    /// extension uses it.
+    fn request_capability(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        capability: ExtensionCapability,
+        cx: &mut App,
+    ) -> Task<Result<CapabilityGrant>>;
+}
+
+impl ExtensionPermissionProxy for ExtensionHostProxy {
+    fn request_capability(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        capability: ExtensionCapability,
+        cx: &mut App,
+    ) -> Task<Result<CapabilityGrant>> {
+        let Some(proxy) = self.permission_proxy.read().clone() else {
+            return Task::ready(Ok(CapabilityGrant::Deny));
+        };
+
+        proxy.request_capability(manifest, capability, cx)
+    }
+}
+
+pub trait ExtensionCapabilityProxy: Send + Sync + 'static {
+    /// Checks that an extension may download a file outside of its WASM library, asking the user the first time.
+    fn ensure_download_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        url: String,
+        cx: &mut App,
+    ) -> Task<Result<()>>;
+
+    /// Checks that an extension may run a command outside of its WASM library, asking the user the first time.
+    fn ensure_exec_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        command: String,
+        args: Vec<String>,
+        cx: &mut App,
+    ) -> Task<Result<()>>;
+}
+
+impl ExtensionCapabilityProxy for ExtensionHostProxy {
+    fn ensure_download_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        url: String,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        let Some(proxy) = self.capability_proxy.read().clone() else {
+            return Task::ready(Err(anyhow!("no extension host to grant capabilities")));
+        };
+
+        proxy.ensure_download_granted(manifest, url, cx)
+    }
+
+    fn ensure_exec_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        command: String,
+        args: Vec<String>,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        let Some(proxy) = self.capability_proxy.read().clone() else {
+            return Task::ready(Err(anyhow!("no extension host to grant capabilities")));
+        };
+
+        proxy.ensure_exec_granted(manifest, command, args, cx)
+    }
+}
@@ -94,6 +94,12 @@ pub struct ExtensionManifest {
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub debug_locators: BTreeMap<Arc<str>, DebugLocatorManifestEntry>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub debug_inline_values: BTreeMap<LanguageName, DebugInlineValuesManifestEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub task_providers: BTreeMap<Arc<str>, TaskProviderManifestEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub problem_matchers: BTreeMap<Arc<str>, ProblemMatcherManifestEntry>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub language_model_providers: BTreeMap<Arc<str>, LanguageModelProviderManifestEntry>,
 }
 
@@ -123,6 +129,7 @@ impl ExtensionManifest {
         !self.language_servers.is_empty()
             || !self.debug_adapters.is_empty()
             || !self.debug_locators.is_empty()
+            || !self.debug_inline_values.is_empty()
     }
 }
 
@@ -136,6 +143,15 @@ pub fn build_debug_adapter_schema_path(
     })
 }
 
+pub fn build_debug_inline_values_query_path(
+    language: &LanguageName,
+    meta: &DebugInlineValuesManifestEntry,
+) -> PathBuf {
+    meta.query_path.clone().unwrap_or_else(|| {
+        Path::new("debug_inline_values").join(Path::new(language.as_ref()).with_extension("scm"))
+    })
+}
+
 #[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct LibManifestEntry {
     pub kind: Option<ExtensionLibraryKind>,
@@ -273,8 +289,28 @@ impl LanguageServerManifestEntry {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
-pub struct ContextServerManifestEntry {}
+/// A context server the extension provides.
+///
+/// The extension launches the server from its WASM library, unless the manifest declares the `targets` to download
+/// the server from, like for agent servers. The arguments and environment variables of the targets can refer to the
+/// settings of the server as `${settings.<key>}`.
+#[derive(Clone, Default, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct ContextServerManifestEntry {
+    /// Environment variables to set when launching the server.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Per-target configuration for archive-based installation, keyed by "{os}-{arch}" like
+    /// [`AgentServerManifestEntry::targets`].
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub targets: HashMap<String, TargetConfig>,
+}
+
+impl ContextServerManifestEntry {
+    /// Whether the manifest declares how to launch the server, rather than the WASM library of the extension.
+    pub fn is_declared(&self) -> bool {
+        !self.targets.is_empty()
+    }
+}
 
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct SlashCommandManifestEntry {
@@ -290,6 +326,21 @@ pub struct DebugAdapterManifestEntry {
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct DebugLocatorManifestEntry {}
 
+/// A tree-sitter query with `@debug-variable` and `@debug-scope` captures, used to find the variables whose values
+/// are shown inline while debugging files of a language.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct DebugInlineValuesManifestEntry {
+    pub query_path: Option<PathBuf>,
+}
+
+/// A provider of task templates, which are listed with the global tasks.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct TaskProviderManifestEntry {}
+
+/// A problem matcher that tasks can refer to by its name.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+pub struct ProblemMatcherManifestEntry {}
+
 /// Manifest entry for a language model provider.
 #[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
 pub struct LanguageModelProviderManifestEntry {
@@ -370,6 +421,9 @@ fn manifest_from_old_manifest(
         capabilities: Vec::new(),
         debug_adapters: Default::default(),
         debug_locators: Default::default(),
+        debug_inline_values: Default::default(),
+        task_providers: Default::default(),
+        problem_matchers: Default::default(),
         language_model_providers: Default::default(),
     }
 }
@@ -404,6 +458,9 @@ mod tests {
             capabilities: vec![],
             debug_adapters: Default::default(),
             debug_locators: Default::default(),
+            debug_inline_values: Default::default(),
+            task_providers: Default::default(),
+            problem_matchers: Default::default(),
             language_model_providers: BTreeMap::default(),
         }
     }
@@ -431,6 +488,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_debug_inline_values_query_path() {
+        let language = LanguageName::new("Zig");
+        let entry = DebugInlineValuesManifestEntry { query_path: None };
+        assert_eq!(
+            build_debug_inline_values_query_path(&language, &entry),
+            PathBuf::from("debug_inline_values").join("Zig.scm")
+        );
+
+        let entry = DebugInlineValuesManifestEntry {
+            query_path: Some(PathBuf::from("queries/zig/debugger.scm")),
+        };
+        assert_eq!(
+            build_debug_inline_values_query_path(&language, &entry),
+            PathBuf::from("queries/zig/debugger.scm")
+        );
+    }
+
     #[test]
     fn test_allow_exec_exact_match() {
         let manifest = ExtensionManifest {
@@ -533,4 +608,33 @@ args = ["--serve"]
         assert_eq!(target.cmd, "./agent");
         assert_eq!(target.args, vec!["--serve"]);
     }
+
+    #[test]
+    fn parse_manifest_with_declared_context_server() {
+        let toml_src = r#"
+id = "example.context-server-ext"
+name = "Context Server Example"
+version = "1.0.0"
+schema_version = 1
+
+[context_servers.wasm]
+
+[context_servers.docs]
+env = { DOCS_LOG = "info" }
+
+[context_servers.docs.targets.darwin-aarch64]
+archive = "https://example.com/docs-server-darwin-arm64.tar.gz"
+cmd = "./docs-server"
+args = ["--token", "${settings.token}"]
+"#;
+
+        let manifest: ExtensionManifest = toml::from_str(toml_src).expect("manifest should parse");
+        assert!(!manifest.context_servers["wasm"].is_declared());
+        let entry = &manifest.context_servers["docs"];
+        assert!(entry.is_declared());
+        assert_eq!(entry.env.get("DOCS_LOG").map(String::as_str), Some("info"));
+        let target = &entry.targets["darwin-aarch64"];
+        assert_eq!(target.cmd, "./docs-server");
+        assert_eq!(target.args, vec!["--token", "${settings.token}"]);
+    }
 }
@@ -37,6 +37,9 @@ pub use wit::{
     vector::extension::slash_command::{
         SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput, SlashCommandOutputSection,
     },
+    vector::extension::task_provider::{
+        ProblemMatcher, ProblemMatcherDefinition, ProblemSeverity, TaskDefinition,
+    },
 };
 
 // Undocumented WIT re-exports.
@@ -259,6 +262,17 @@ pub trait Extension: Send + Sync {
     ) -> Result<DebugRequest, String> {
         Err("`run_dap_locator` not implemented".to_string())
     }
+
+    /// Returns the tasks of the given task provider, which are listed with the global tasks. They are requested once,
+    /// when the extension is loaded.
+    fn task_templates(&mut self, _provider_name: String) -> Result<Vec<TaskDefinition>, String> {
+        Err("`task_templates` not implemented".to_string())
+    }
+
+    /// Returns the matchers of the given problem matcher, which tasks can refer to by its name.
+    fn problem_matchers(&mut self, _matcher_name: String) -> Result<Vec<ProblemMatcher>, String> {
+        Err("`problem_matchers` not implemented".to_string())
+    }
 }
 
 /// Registers the provided type as a Vector extension.
@@ -516,6 +530,14 @@ impl wit::Guest for Component {
     ) -> Result<DebugRequest, String> {
         extension().run_dap_locator(locator_name, build_task)
     }
+
+    fn task_templates(provider_name: String) -> Result<Vec<TaskDefinition>, String> {
+        extension().task_templates(provider_name)
+    }
+
+    fn problem_matchers(matcher_name: String) -> Result<Vec<ProblemMatcher>, String> {
+        extension().problem_matchers(matcher_name)
+    }
 }
 
 /// The ID of a language server.
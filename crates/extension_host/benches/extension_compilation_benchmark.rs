@@ -148,6 +148,9 @@ fn manifest() -> ExtensionManifest {
         )],
         debug_adapters: Default::default(),
         debug_locators: Default::default(),
+        debug_inline_values: Default::default(),
+        task_providers: Default::default(),
+        problem_matchers: Default::default(),
         language_model_providers: BTreeMap::default(),
     }
 }
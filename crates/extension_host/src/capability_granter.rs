@@ -1,86 +1,192 @@
 use std::sync::Arc;
 
 use anyhow::{Result, bail};
-use extension::{ExtensionCapability, ExtensionManifest};
+use extension::{ExtensionCapability, ExtensionManifest, NetworkCapability, ReadFileCapability};
 use url::Url;
+use util::rel_path::RelPath;
 
 pub struct CapabilityGranter {
     granted_capabilities: Vec<ExtensionCapability>,
+    denied_capabilities: Vec<ExtensionCapability>,
     manifest: Arc<ExtensionManifest>,
 }
 
+/// Whether an extension may use a capability.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CapabilityCheck {
+    Granted,
+    /// The extension declares a capability allowing the use in its manifest, or predates declaring uses of its
+    /// kind, and the user needs to grant it first.
+    NeedsGrant(ExtensionCapability),
+}
+
 impl CapabilityGranter {
     pub fn new(
         granted_capabilities: Vec<ExtensionCapability>,
+        denied_capabilities: Vec<ExtensionCapability>,
         manifest: Arc<ExtensionManifest>,
     ) -> Self {
         Self {
             granted_capabilities,
+            denied_capabilities,
             manifest,
         }
     }
 
+    /// Grants a capability that the user allowed.
+    pub fn grant(&mut self, capability: ExtensionCapability) {
+        self.granted_capabilities.push(capability);
+    }
+
+    /// Denies a capability that the user refused, so that they aren't asked for it again.
+    pub fn deny(&mut self, capability: ExtensionCapability) {
+        self.denied_capabilities.push(capability);
+    }
+
     pub fn grant_exec(
         &self,
         desired_command: &str,
         desired_args: &[impl AsRef<str> + std::fmt::Debug],
-    ) -> Result<()> {
+    ) -> Result<CapabilityCheck> {
         self.manifest.allow_exec(desired_command, desired_args)?;
 
-        let is_allowed = self
-            .granted_capabilities
-            .iter()
-            .any(|capability| match capability {
+        self.check(
+            || format!("process:exec {desired_command} {desired_args:?}"),
+            |capability| match capability {
                 ExtensionCapability::ProcessExec(capability) => {
                     capability.allows(desired_command, desired_args)
                 }
                 _ => false,
-            });
-
-        if !is_allowed {
-            bail!(
-                "capability for process:exec {desired_command} {desired_args:?} is not granted by the extension host",
-            );
-        }
-
-        Ok(())
+            },
+        )
     }
 
-    pub fn grant_download_file(&self, desired_url: &Url) -> Result<()> {
-        let is_allowed = self
-            .granted_capabilities
-            .iter()
-            .any(|capability| match capability {
+    pub fn grant_download_file(&self, desired_url: &Url) -> Result<CapabilityCheck> {
+        self.check(
+            || format!("download_file {desired_url}"),
+            |capability| match capability {
                 ExtensionCapability::DownloadFile(capability) => capability.allows(desired_url),
                 _ => false,
-            });
-
-        if !is_allowed {
-            bail!(
-                "capability for download_file {desired_url} is not granted by the extension host",
-            );
-        }
-
-        Ok(())
+            },
+        )
     }
 
-    pub fn grant_npm_install_package(&self, package_name: &str) -> Result<()> {
-        let is_allowed = self
-            .granted_capabilities
-            .iter()
-            .any(|capability| match capability {
+    pub fn grant_npm_install_package(&self, package_name: &str) -> Result<CapabilityCheck> {
+        self.check(
+            || format!("npm:install {package_name}"),
+            |capability| match capability {
                 ExtensionCapability::NpmInstallPackage(capability) => {
                     capability.allows(package_name)
                 }
                 _ => false,
-            });
+            },
+        )
+    }
 
-        if !is_allowed {
-            bail!("capability for npm:install {package_name} is not granted by the extension host",);
+    /// Sending requests needs to be declared in the manifest, so that extensions can't send the workspace anywhere.
+    pub fn grant_network(&self, desired_url: &Url) -> Result<CapabilityCheck> {
+        let is_allowed = |capability: &ExtensionCapability| match capability {
+            ExtensionCapability::Network(capability) => capability.allows(desired_url),
+            _ => false,
+        };
+        let is_network = |capability: &ExtensionCapability| {
+            matches!(capability, ExtensionCapability::Network(_))
+        };
+        if !self.manifest.capabilities.iter().any(is_network) {
+            let host = desired_url.host_str().unwrap_or_default().to_string();
+            return self.check_undeclared(
+                ExtensionCapability::Network(NetworkCapability { host }),
+                is_allowed,
+            );
         }
+        self.ensure_declared(|| format!("network:fetch {desired_url}"), is_allowed)?;
+        self.check(|| format!("network:fetch {desired_url}"), is_allowed)
+    }
 
+    /// Reading the files of worktrees needs to be declared in the manifest, like sending requests.
+    pub fn grant_read_file(&self, desired_path: &RelPath) -> Result<CapabilityCheck> {
+        let is_allowed = |capability: &ExtensionCapability| match capability {
+            ExtensionCapability::ReadFile(capability) => capability.allows(desired_path),
+            _ => false,
+        };
+        let is_read_file = |capability: &ExtensionCapability| {
+            matches!(capability, ExtensionCapability::ReadFile(_))
+        };
+        if !self.manifest.capabilities.iter().any(is_read_file) {
+            return self.check_undeclared(
+                ExtensionCapability::ReadFile(ReadFileCapability {
+                    path: vec![desired_path.as_unix_str().to_string()],
+                }),
+                is_allowed,
+            );
+        }
+        self.ensure_declared(
+            || format!("fs:read {}", desired_path.as_unix_str()),
+            is_allowed,
+        )?;
+        self.check(
+            || format!("fs:read {}", desired_path.as_unix_str()),
+            is_allowed,
+        )
+    }
+
+    fn ensure_declared(
+        &self,
+        description: impl FnOnce() -> String,
+        is_allowed: impl Fn(&ExtensionCapability) -> bool,
+    ) -> Result<()> {
+        if !self.manifest.capabilities.iter().any(is_allowed) {
+            bail!(
+                "capability for {} was not listed in the extension manifest",
+                description()
+            );
+        }
         Ok(())
     }
+
+    /// Checks a use by an extension whose manifest predates declaring uses of its kind, and so declares none of
+    /// them: rather than being refused, the use is asked for like a declared one.
+    fn check_undeclared(
+        &self,
+        capability: ExtensionCapability,
+        is_allowed: impl Fn(&ExtensionCapability) -> bool,
+    ) -> Result<CapabilityCheck> {
+        if self.granted_capabilities.iter().any(&is_allowed) {
+            return Ok(CapabilityCheck::Granted);
+        }
+        if self.denied_capabilities.iter().any(&is_allowed) {
+            bail!(
+                "the permission to {} was denied to the extension",
+                capability.description()
+            );
+        }
+        Ok(CapabilityCheck::NeedsGrant(capability))
+    }
+
+    /// Checks whether the use is granted, or else whether the manifest declares a capability allowing it, to ask the
+    /// user for.
+    fn check(
+        &self,
+        description: impl FnOnce() -> String,
+        is_allowed: impl Fn(&ExtensionCapability) -> bool,
+    ) -> Result<CapabilityCheck> {
+        if self.granted_capabilities.iter().any(&is_allowed) {
+            return Ok(CapabilityCheck::Granted);
+        }
+        if let Some(declared) = self.manifest.capabilities.iter().find(|c| is_allowed(c)) {
+            if self.denied_capabilities.contains(declared) {
+                bail!(
+                    "the permission to {} was denied to the extension",
+                    declared.description()
+                );
+            }
+            return Ok(CapabilityCheck::NeedsGrant(declared.clone()));
+        }
+        bail!(
+            "capability for {} is not granted by the extension host",
+            description()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -113,6 +219,9 @@ mod tests {
             capabilities: vec![],
             debug_adapters: Default::default(),
             debug_locators: Default::default(),
+            debug_inline_values: Default::default(),
+            task_providers: Default::default(),
+            problem_matchers: Default::default(),
             language_model_providers: BTreeMap::default(),
         }
     }
@@ -127,9 +236,20 @@ mod tests {
             ..extension_manifest()
         });
 
-        // It returns an error when the extension host has no granted capabilities.
-        let granter = CapabilityGranter::new(Vec::new(), manifest.clone());
-        assert!(granter.grant_exec("ls", &["-la"]).is_err());
+        // It asks for the capability of the manifest when the extension host has no granted capabilities.
+        let mut granter = CapabilityGranter::new(Vec::new(), Vec::new(), manifest.clone());
+        assert_eq!(
+            granter.grant_exec("ls", &["-la"]).unwrap(),
+            CapabilityCheck::NeedsGrant(manifest.capabilities[0].clone())
+        );
+        granter.grant(manifest.capabilities[0].clone());
+        assert_eq!(
+            granter.grant_exec("ls", &["-la"]).unwrap(),
+            CapabilityCheck::Granted
+        );
+
+        // It returns an error when the manifest doesn't list the capability.
+        assert!(granter.grant_exec("rm", &["-rf"]).is_err());
 
         // It succeeds when the extension host has the exact capability.
         let granter = CapabilityGranter::new(
@@ -137,6 +257,7 @@ mod tests {
                 command: "ls".to_string(),
                 args: vec!["-la".to_string()],
             })],
+            Vec::new(),
             manifest.clone(),
         );
         assert!(granter.grant_exec("ls", &["-la"]).is_ok());
@@ -147,8 +268,102 @@ mod tests {
                 command: "*".to_string(),
                 args: vec!["**".to_string()],
             })],
+            Vec::new(),
             manifest,
         );
         assert!(granter.grant_exec("ls", &["-la"]).is_ok());
     }
+
+    #[test]
+    fn test_grant_network_and_read_file() {
+        let manifest = Arc::new(ExtensionManifest {
+            capabilities: vec![
+                ExtensionCapability::Network(NetworkCapability {
+                    host: "api.example.com".to_string(),
+                }),
+                ExtensionCapability::ReadFile(ReadFileCapability {
+                    path: vec!["package.json".to_string()],
+                }),
+            ],
+            ..extension_manifest()
+        });
+        let all_granted = vec![
+            ExtensionCapability::Network(NetworkCapability {
+                host: "*".to_string(),
+            }),
+            ExtensionCapability::ReadFile(ReadFileCapability {
+                path: vec!["**".to_string()],
+            }),
+        ];
+
+        // Uses that the manifest doesn't list are refused, even when the extension host grants them.
+        let granter = CapabilityGranter::new(all_granted, Vec::new(), manifest.clone());
+        let url = "https://api.example.com/v1".parse().unwrap();
+        assert_eq!(
+            granter.grant_network(&url).unwrap(),
+            CapabilityCheck::Granted
+        );
+        assert!(
+            granter
+                .grant_network(&"https://elsewhere.com/upload".parse().unwrap())
+                .is_err()
+        );
+        let path = RelPath::unix("package.json").unwrap();
+        assert_eq!(
+            granter.grant_read_file(path).unwrap(),
+            CapabilityCheck::Granted
+        );
+        assert!(
+            granter
+                .grant_read_file(RelPath::unix(".env").unwrap())
+                .is_err()
+        );
+
+        // Uses that the manifest lists need to be granted first.
+        let granter = CapabilityGranter::new(Vec::new(), Vec::new(), manifest.clone());
+        assert_eq!(
+            granter.grant_network(&url).unwrap(),
+            CapabilityCheck::NeedsGrant(manifest.capabilities[0].clone())
+        );
+        assert_eq!(
+            granter.grant_read_file(path).unwrap(),
+            CapabilityCheck::NeedsGrant(manifest.capabilities[1].clone())
+        );
+
+        // Denied uses are refused without asking again.
+        let mut granter = granter;
+        granter.deny(manifest.capabilities[0].clone());
+        assert!(granter.grant_network(&url).is_err());
+    }
+
+    #[test]
+    fn test_grant_network_and_read_file_without_declarations() {
+        // Manifests written before the kinds could be declared ask for each use instead of refusing it.
+        let manifest = Arc::new(extension_manifest());
+        let mut granter = CapabilityGranter::new(Vec::new(), Vec::new(), manifest);
+        let url = "https://api.github.com/repos".parse().unwrap();
+        let network = ExtensionCapability::Network(NetworkCapability {
+            host: "api.github.com".to_string(),
+        });
+        assert_eq!(
+            granter.grant_network(&url).unwrap(),
+            CapabilityCheck::NeedsGrant(network.clone())
+        );
+        let path = RelPath::unix("Cargo.toml").unwrap();
+        let read_file = ExtensionCapability::ReadFile(ReadFileCapability {
+            path: vec!["Cargo.toml".to_string()],
+        });
+        assert_eq!(
+            granter.grant_read_file(path).unwrap(),
+            CapabilityCheck::NeedsGrant(read_file.clone())
+        );
+
+        granter.grant(network);
+        assert_eq!(
+            granter.grant_network(&url).unwrap(),
+            CapabilityCheck::Granted
+        );
+        granter.deny(read_file);
+        assert!(granter.grant_read_file(path).is_err());
+    }
 }
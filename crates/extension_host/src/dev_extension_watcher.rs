@@ -0,0 +1,222 @@
+//! Reloading dev extensions when their files change.
+//!
+//! The source directory of every installed dev extension is watched. Changing the sources of its WASM library
+//! rebuilds the extension before reloading it, while changing any other file of the extension only reloads it.
+//! Either way, only that extension is reloaded, without restarting Vector, and the result is reported with
+//! [`Event::DevExtensionReloaded`].
+
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use extension::ExtensionManifest;
+use extension::extension_builder::CompileExtensionOptions;
+use futures::StreamExt as _;
+use gpui::{Context, SharedString, Task};
+
+use crate::{Event, ExtensionOperation, ExtensionStore, FS_WATCH_LATENCY};
+
+/// The result of reloading a dev extension after its files changed, with the error to show when it failed.
+pub type DevExtensionReloadResult = Result<(), SharedString>;
+
+/// How a dev extension has to be reloaded after some of its files changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum DevExtensionChange {
+    /// Only files that are read when the extension is loaded changed.
+    Assets,
+    /// The sources of the WASM library changed, so the extension has to be rebuilt.
+    Sources,
+}
+
+/// Classifies a change to the file at `relative_path` of a dev extension, or returns `None` for the outputs of its
+/// build, which change whenever the extension is rebuilt.
+pub(crate) fn change_kind(relative_path: &Path) -> Option<DevExtensionChange> {
+    let mut components = relative_path.components();
+    let Some(Component::Normal(first)) = components.next() else {
+        return None;
+    };
+    let is_file = components.next().is_none();
+    match first.to_str()? {
+        "target" | "grammars" | ".git" => None,
+        "extension.wasm" | "Cargo.lock" if is_file => None,
+        "Cargo.toml" | "extension.toml" if is_file => Some(DevExtensionChange::Sources),
+        "src" if !is_file => Some(DevExtensionChange::Sources),
+        _ => Some(DevExtensionChange::Assets),
+    }
+}
+
+impl ExtensionStore {
+    /// Starts watching the dev extensions of the index that aren't watched yet, and stops watching the ones that
+    /// were uninstalled.
+    pub(crate) fn watch_dev_extensions(&mut self, cx: &mut Context<Self>) {
+        let dev_extension_ids = self
+            .dev_extensions()
+            .map(|manifest| manifest.id.clone())
+            .collect::<Vec<_>>();
+        self.dev_extension_watchers
+            .retain(|extension_id, _| dev_extension_ids.contains(extension_id));
+        self.pending_dev_extension_reloads
+            .retain(|extension_id, _| dev_extension_ids.contains(extension_id));
+        for extension_id in dev_extension_ids {
+            if !self.dev_extension_watchers.contains_key(&extension_id) {
+                let watcher = self.watch_dev_extension(extension_id.clone(), cx);
+                self.dev_extension_watchers.insert(extension_id, watcher);
+            }
+        }
+    }
+
+    fn watch_dev_extension(&self, extension_id: Arc<str>, cx: &mut Context<Self>) -> Task<()> {
+        let fs = self.fs.clone();
+        let extension_dir = self.installed_dir.join(extension_id.as_ref());
+        cx.spawn(async move |this, cx| {
+            // Dev extensions are symlinks to their source directories, which is where the changes happen.
+            let source_dir = match fs.canonicalize(&extension_dir).await {
+                Ok(source_dir) => source_dir,
+                Err(error) => {
+                    log::error!("failed to watch dev extension {extension_id}: {error:#}");
+                    return;
+                }
+            };
+            let (mut events, _watcher) = fs.watch(&source_dir, FS_WATCH_LATENCY).await;
+            while let Some(events) = events.next().await {
+                let change = events
+                    .iter()
+                    .filter_map(|event| event.path.strip_prefix(&source_dir).ok())
+                    .filter_map(change_kind)
+                    .max();
+                if let Some(change) = change {
+                    let reloaded = this.update(cx, |this, cx| {
+                        this.reload_dev_extension(extension_id.clone(), change, cx)
+                    });
+                    if reloaded.is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Reloads a dev extension after its files changed, rebuilding it first when its sources changed.
+    ///
+    /// When the extension is already being built or reloaded, it's reloaded again once that finishes.
+    pub(crate) fn reload_dev_extension(
+        &mut self,
+        extension_id: Arc<str>,
+        change: DevExtensionChange,
+        cx: &mut Context<Self>,
+    ) {
+        if self.outstanding_operations.contains_key(&extension_id) {
+            let pending = self
+                .pending_dev_extension_reloads
+                .entry(extension_id)
+                .or_insert(change);
+            *pending = (*pending).max(change);
+            return;
+        }
+        self.outstanding_operations
+            .insert(extension_id.clone(), ExtensionOperation::Upgrade);
+        cx.notify();
+
+        let compile = (change == DevExtensionChange::Sources).then(|| {
+            let path = self.installed_dir.join(extension_id.as_ref());
+            let builder = self.builder.clone();
+            let fs = self.fs.clone();
+            cx.background_spawn(async move {
+                let mut manifest = ExtensionManifest::load(fs.clone(), &path).await?;
+                builder
+                    .compile_extension(
+                        &path,
+                        &mut manifest,
+                        CompileExtensionOptions { release: false },
+                        fs,
+                    )
+                    .await
+            })
+        });
+
+        cx.spawn(async move |this, cx| {
+            let compiled = match compile {
+                Some(compile) => compile.await.context("failed to rebuild the extension"),
+                None => Ok(()),
+            };
+            if compiled.is_ok() {
+                this.update(cx, |this, cx| this.reload(Some(extension_id.clone()), cx))?
+                    .await;
+            }
+
+            this.update(cx, |this, cx| {
+                this.outstanding_operations.remove(&extension_id);
+                let result = match compiled {
+                    Ok(()) => this.dev_extension_load_result(&extension_id),
+                    Err(error) => Err(format!("{error:#}").into()),
+                };
+                if let Err(error) = &result {
+                    log::error!("failed to reload dev extension {extension_id}: {error}");
+                }
+                cx.emit(Event::DevExtensionReloaded {
+                    extension_id: extension_id.clone(),
+                    result,
+                });
+                cx.notify();
+
+                if let Some(change) = this.pending_dev_extension_reloads.remove(&extension_id) {
+                    this.reload_dev_extension(extension_id, change, cx);
+                }
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn dev_extension_load_result(&self, extension_id: &Arc<str>) -> DevExtensionReloadResult {
+        let Some(entry) = self.extension_index.extensions.get(extension_id) else {
+            return Err("the extension is no longer installed, or its manifest is invalid".into());
+        };
+        if entry.manifest.lib.kind.is_some()
+            && !self
+                .wasm_extensions
+                .iter()
+                .any(|(manifest, _)| &manifest.id == extension_id)
+        {
+            return Err("failed to load the WASM library of the extension".into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dev_extension_change_kind() {
+        assert_eq!(
+            change_kind(Path::new("src/lib.rs")),
+            Some(DevExtensionChange::Sources)
+        );
+        assert_eq!(
+            change_kind(Path::new("Cargo.toml")),
+            Some(DevExtensionChange::Sources)
+        );
+        assert_eq!(
+            change_kind(Path::new("extension.toml")),
+            Some(DevExtensionChange::Sources)
+        );
+        assert_eq!(
+            change_kind(Path::new("languages/rust/highlights.scm")),
+            Some(DevExtensionChange::Assets)
+        );
+        assert_eq!(
+            change_kind(Path::new("themes/dark.json")),
+            Some(DevExtensionChange::Assets)
+        );
+        assert_eq!(change_kind(Path::new("extension.wasm")), None);
+        assert_eq!(change_kind(Path::new("Cargo.lock")), None);
+        assert_eq!(change_kind(Path::new("grammars/rust.wasm")), None);
+        assert_eq!(
+            change_kind(Path::new("target/wasm32-wasip2/debug/x.wasm")),
+            None
+        );
+        assert_eq!(change_kind(Path::new(".git/index")), None);
+        assert_eq!(change_kind(Path::new("")), None);
+    }
+}
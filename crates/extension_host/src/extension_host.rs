@@ -1,4 +1,5 @@
 mod capability_granter;
+mod dev_extension_watcher;
 pub mod extension_settings;
 pub mod wasm_host;
 
@@ -6,7 +7,8 @@ pub mod wasm_host;
 mod extension_store_test;
 
 use anyhow::{Context as _, Result, anyhow, bail};
-use collections::{BTreeMap, BTreeSet, HashSet, btree_map};
+use collections::{BTreeMap, BTreeSet, HashMap, HashSet, btree_map};
+use dev_extension_watcher::DevExtensionChange;
 pub use extension::ExtensionManifest;
 use extension::api::{ExtensionApiManifest, ExtensionMetadata, ExtensionProvides};
 use extension::extension_builder::{CompileExtensionOptions, ExtensionBuilder};
@@ -14,7 +16,7 @@ use extension::{
     ExtensionContextServerProxy, ExtensionDebugAdapterProviderProxy, ExtensionEvents,
     ExtensionGrammarProxy, ExtensionHostProxy, ExtensionLanguageProxy,
     ExtensionLanguageServerProxy, ExtensionSlashCommandProxy, ExtensionSnippetProxy,
-    ExtensionThemeProxy,
+    ExtensionTaskProviderProxy, ExtensionThemeProxy,
 };
 use fs::{Fs, RemoveOptions};
 use futures::{
@@ -23,6 +25,7 @@ use futures::{
         mpsc::{UnboundedSender, unbounded},
         oneshot,
     },
+    future::Shared,
     select_biased,
 };
 use gpui::{App, AppContext as _, Context, Entity, EventEmitter, Global, Task, actions};
@@ -46,8 +49,11 @@ use std::{
     time::{Duration, Instant},
 };
 use util::ResultExt;
-use wasm_host::{WasmExtension, WasmHost, wit::is_supported_wasm_api_version};
+use wasm_host::{
+    WasmExtension, WasmHost, WasmHostCapabilityProxy, wit::is_supported_wasm_api_version,
+};
 
+pub use dev_extension_watcher::DevExtensionReloadResult;
 pub use extension::{
     ExtensionLibraryKind, GrammarManifestEntry, OldExtensionManifest, SchemaVersion,
 };
@@ -102,7 +108,55 @@ pub struct ExtensionStore {
     pub modified_extensions: HashSet<Arc<str>>,
     pub wasm_host: Arc<WasmHost>,
     pub wasm_extensions: Vec<(Arc<ExtensionManifest>, WasmExtension)>,
+    pub disabled_extensions: DisabledExtensions,
     pub tasks: Vec<Task<()>>,
+    /// The watchers of the source directories of the dev extensions, by extension ID.
+    dev_extension_watchers: HashMap<Arc<str>, Task<()>>,
+    /// The dev extensions whose files changed while they were being built or reloaded.
+    pending_dev_extension_reloads: HashMap<Arc<str>, DevExtensionChange>,
+    /// Completes once the extensions installed when the store was created are loaded.
+    initial_load: Shared<Task<()>>,
+}
+
+/// The installed extensions that aren't loaded, to start Vector without the extensions that break it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DisabledExtensions {
+    #[default]
+    None,
+    /// Loads no installed extension, in safe mode.
+    All,
+    /// Loads every installed extension except these.
+    Some(HashSet<Arc<str>>),
+    /// Loads only these installed extensions.
+    AllExcept(HashSet<Arc<str>>),
+}
+
+impl DisabledExtensions {
+    pub fn contains(&self, extension_id: &str) -> bool {
+        match self {
+            Self::None => false,
+            Self::All => true,
+            Self::Some(disabled) => disabled.contains(extension_id),
+            Self::AllExcept(enabled) => !enabled.contains(extension_id),
+        }
+    }
+
+    /// Removes the disabled extensions, and the resources they provide, from `index`.
+    fn remove_from(&self, index: &mut ExtensionIndex) {
+        if *self == Self::None {
+            return;
+        }
+        index.extensions.retain(|id, _| !self.contains(id));
+        index
+            .themes
+            .retain(|_, theme| !self.contains(&theme.extension));
+        index
+            .icon_themes
+            .retain(|_, icon_theme| !self.contains(&icon_theme.extension));
+        index
+            .languages
+            .retain(|_, language| !self.contains(&language.extension));
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -118,6 +172,11 @@ pub enum Event {
     StartedReloading,
     ExtensionInstalled(Arc<str>),
     ExtensionFailedToLoad(Arc<str>),
+    /// A dev extension was reloaded after its files changed.
+    DevExtensionReloaded {
+        extension_id: Arc<str>,
+        result: DevExtensionReloadResult,
+    },
 }
 
 impl EventEmitter<Event> for ExtensionStore {}
@@ -169,6 +228,7 @@ pub fn init(
     fs: Arc<dyn Fs>,
     http_client: Arc<HttpClientWithUrl>,
     node_runtime: NodeRuntime,
+    disabled_extensions: DisabledExtensions,
     cx: &mut App,
 ) {
     ExtensionSettings::register(cx);
@@ -182,6 +242,7 @@ pub fn init(
             http_client.clone(),
             http_client.clone(),
             node_runtime,
+            disabled_extensions,
             cx,
         )
     });
@@ -212,6 +273,7 @@ impl ExtensionStore {
         http_client: Arc<HttpClientWithUrl>,
         builder_client: Arc<dyn HttpClient>,
         node_runtime: NodeRuntime,
+        disabled_extensions: DisabledExtensions,
         cx: &mut Context<Self>,
     ) -> Self {
         let work_dir = extensions_dir.join("work");
@@ -238,11 +300,17 @@ impl ExtensionStore {
                 cx,
             ),
             wasm_extensions: Vec::new(),
+            disabled_extensions,
             fs,
             http_client,
             reload_tx,
             tasks: Vec::new(),
+            dev_extension_watchers: HashMap::default(),
+            pending_dev_extension_reloads: HashMap::default(),
+            initial_load: Task::ready(()).shared(),
         };
+        this.proxy
+            .register_capability_proxy(WasmHostCapabilityProxy(this.wasm_host.clone()));
 
         // The extensions store maintains an index file, which contains a complete
         // list of the installed extensions and the resources that they provide.
@@ -285,13 +353,16 @@ impl ExtensionStore {
             reload_future = Some(this.reload(None, cx));
         }
 
-        cx.spawn(async move |_, _| {
-            if let Some(future) = reload_future {
-                future.await;
-            }
-            // Vector fork: remote extension marketplace/downloads are disabled.
-        })
-        .detach();
+        let (initial_extensions_loaded_tx, initial_extensions_loaded_rx) = oneshot::channel();
+        this.initial_load = cx
+            .spawn(async move |_, _| {
+                initial_extensions_loaded_rx.await.ok();
+                if let Some(future) = reload_future {
+                    future.await;
+                }
+                // Vector fork: remote extension marketplace/downloads are disabled.
+            })
+            .shared();
 
         // Perform all extension loading in a single task to ensure that we
         // never attempt to simultaneously load/unload extensions from multiple
@@ -299,6 +370,7 @@ impl ExtensionStore {
         this.tasks.push(cx.spawn(async move |this, cx| {
             async move {
                 load_initial_extensions.await;
+                initial_extensions_loaded_tx.send(()).ok();
 
                 let mut index_changed = false;
                 let mut debounce_timer = cx.background_spawn(futures::future::pending()).fuse();
@@ -382,6 +454,12 @@ impl ExtensionStore {
         }
     }
 
+    /// Waits for the extensions installed when the store was created to be loaded, along with the rebuild of their index
+    /// when it was out of date.
+    pub fn wait_for_initial_load(&self) -> impl Future<Output = ()> + use<> {
+        self.initial_load.clone()
+    }
+
     fn extensions_dir(&self) -> PathBuf {
         self.installed_dir.clone()
     }
@@ -554,13 +632,21 @@ impl ExtensionStore {
         }
     }
 
-    pub fn uninstall_extension(&mut self, extension_id: Arc<str>, cx: &mut Context<Self>) {
+    pub fn uninstall_extension(
+        &mut self,
+        extension_id: Arc<str>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
         let extension_dir = self.installed_dir.join(extension_id.as_ref());
         let work_dir = self.wasm_host.work_dir.join(extension_id.as_ref());
         let fs = self.fs.clone();
 
         match self.outstanding_operations.entry(extension_id.clone()) {
-            btree_map::Entry::Occupied(_) => return,
+            btree_map::Entry::Occupied(_) => {
+                return Task::ready(Err(anyhow!(
+                    "extension {extension_id} is already being installed, upgraded or removed"
+                )));
+            }
             btree_map::Entry::Vacant(e) => e.insert(ExtensionOperation::Remove),
         };
 
@@ -597,7 +683,6 @@ impl ExtensionStore {
 
             anyhow::Ok(())
         })
-        .detach_and_log_err(cx)
     }
 
     pub fn install_dev_extension(
@@ -671,7 +756,12 @@ impl ExtensionStore {
             fs.create_symlink(output_path, extension_source_path)
                 .await?;
 
-            this.update(cx, |this, cx| this.reload(None, cx))?.await;
+            this.update(cx, |this, cx| {
+                // The extension may have been installed from another directory before, which is still being watched.
+                this.dev_extension_watchers.remove(&extension_id);
+                this.reload(None, cx)
+            })?
+            .await;
             this.update(cx, |this, cx| {
                 cx.emit(Event::ExtensionInstalled(extension_id.clone()));
                 if let Some(events) = ExtensionEvents::try_global(cx) {
@@ -739,6 +829,7 @@ impl ExtensionStore {
         mut new_index: ExtensionIndex,
         cx: &mut Context<Self>,
     ) -> Task<()> {
+        self.disabled_extensions.remove_from(&mut new_index);
         let old_index = &self.extension_index;
 
         // Determine which extensions need to be loaded and unloaded, based
@@ -843,6 +934,11 @@ impl ExtensionStore {
                 }
             }
 
+            for (slash_command_name, _) in extension.manifest.slash_commands.iter() {
+                self.proxy
+                    .unregister_slash_command(slash_command_name.clone());
+            }
+
             for (server_id, _) in extension.manifest.context_servers.iter() {
                 self.proxy.unregister_context_server(server_id.clone(), cx);
             }
@@ -855,6 +951,26 @@ impl ExtensionStore {
             for (locator_name, _) in extension.manifest.debug_locators.iter() {
                 self.proxy.unregister_debug_locator(locator_name.clone());
             }
+
+            for (language, _) in extension.manifest.debug_inline_values.iter() {
+                self.proxy.unregister_debug_inline_values(language.clone());
+            }
+
+            for (provider_name, _) in extension.manifest.task_providers.iter() {
+                self.proxy.unregister_task_provider(
+                    extension_id.clone(),
+                    provider_name.clone(),
+                    cx,
+                );
+            }
+
+            for (matcher_name, _) in extension.manifest.problem_matchers.iter() {
+                self.proxy.unregister_problem_matcher(
+                    extension_id.clone(),
+                    matcher_name.clone(),
+                    cx,
+                );
+            }
         }
 
         self.wasm_extensions
@@ -901,6 +1017,17 @@ impl ExtensionStore {
                 path.extend([Path::new(extension_id.as_ref()), snippets_path.as_path()]);
                 path
             }));
+            // Declared context servers are launched from the archives in the manifest, with or without a WASM library.
+            for (server_id, entry) in &extension.manifest.context_servers {
+                if entry.is_declared() {
+                    self.proxy.register_declared_context_server(
+                        extension.manifest.clone(),
+                        server_id.clone(),
+                        entry.clone(),
+                        cx,
+                    );
+                }
+            }
         }
 
         self.proxy.register_grammars(grammars_to_add);
@@ -953,6 +1080,7 @@ impl ExtensionStore {
             .filter_map(|name| new_index.extensions.get(name).cloned())
             .collect::<Vec<_>>();
         self.extension_index = new_index;
+        self.watch_dev_extensions(cx);
         cx.notify();
         cx.emit(Event::ExtensionsUpdated);
 
@@ -1041,9 +1169,11 @@ impl ExtensionStore {
                         );
                     }
 
-                    for (id, _context_server_entry) in &manifest.context_servers {
-                        this.proxy
-                            .register_context_server(extension.clone(), id.clone(), cx);
+                    for (id, context_server_entry) in &manifest.context_servers {
+                        if !context_server_entry.is_declared() {
+                            this.proxy
+                                .register_context_server(extension.clone(), id.clone(), cx);
+                        }
                     }
 
                     for (debug_adapter_name, meta) in &manifest.debug_adapters {
@@ -1061,6 +1191,30 @@ impl ExtensionStore {
                         this.proxy
                             .register_debug_locator(extension.clone(), locator_name.clone());
                     }
+
+                    for (language, meta) in &manifest.debug_inline_values {
+                        let query_path = root_dir.join(manifest.id.as_ref()).join(
+                            extension::build_debug_inline_values_query_path(language, meta),
+                        );
+                        this.proxy
+                            .register_debug_inline_values(language.clone(), query_path.as_path());
+                    }
+
+                    for (provider_name, _) in &manifest.task_providers {
+                        this.proxy.register_task_provider(
+                            extension.clone(),
+                            provider_name.clone(),
+                            cx,
+                        );
+                    }
+
+                    for (matcher_name, _) in &manifest.problem_matchers {
+                        this.proxy.register_problem_matcher(
+                            extension.clone(),
+                            matcher_name.clone(),
+                            cx,
+                        );
+                    }
                 }
 
                 this.wasm_extensions.extend(wasm_extensions);
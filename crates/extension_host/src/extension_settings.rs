@@ -1,7 +1,10 @@
 use collections::HashMap;
 use extension::{
-    DownloadFileCapability, ExtensionCapability, NpmInstallPackageCapability, ProcessExecCapability,
+    DownloadFileCapability, ExtensionCapability, NetworkCapability, NpmInstallPackageCapability,
+    ProcessExecCapability, ReadFileCapability,
 };
+use fs::Fs;
+use gpui::App;
 use settings::{RegisterSetting, Settings};
 use std::sync::Arc;
 
@@ -16,6 +19,8 @@ pub struct ExtensionSettings {
     pub auto_install_extensions: HashMap<Arc<str>, bool>,
     pub auto_update_extensions: HashMap<Arc<str>, bool>,
     pub granted_capabilities: Vec<ExtensionCapability>,
+    /// The capabilities granted to individual extensions, in addition to [`Self::granted_capabilities`].
+    pub capability_grants: HashMap<Arc<str>, Vec<ExtensionCapability>>,
 }
 
 impl ExtensionSettings {
@@ -33,6 +38,23 @@ impl ExtensionSettings {
             .copied()
             .unwrap_or(true)
     }
+
+    /// Returns the capabilities granted to the extension.
+    pub fn capabilities_granted_to(&self, extension_id: &str) -> Vec<ExtensionCapability> {
+        let mut capabilities = self.granted_capabilities.clone();
+        capabilities.extend(self.capabilities_granted_only_to(extension_id));
+        capabilities
+    }
+
+    /// Returns the capabilities granted to the extension itself, leaving out those granted to every extension.
+    pub fn capabilities_granted_only_to(&self, extension_id: &str) -> Vec<ExtensionCapability> {
+        self.capability_grants
+            .get(extension_id)
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
 }
 
 impl Settings for ExtensionSettings {
@@ -46,20 +68,81 @@ impl Settings for ExtensionSettings {
                 .clone()
                 .unwrap_or_default()
                 .into_iter()
-                .map(|capability| match capability {
-                    settings::ExtensionCapabilityContent::ProcessExec { command, args } => {
-                        ExtensionCapability::ProcessExec(ProcessExecCapability { command, args })
-                    }
-                    settings::ExtensionCapabilityContent::DownloadFile { host, path } => {
-                        ExtensionCapability::DownloadFile(DownloadFileCapability { host, path })
-                    }
-                    settings::ExtensionCapabilityContent::NpmInstallPackage { package } => {
-                        ExtensionCapability::NpmInstallPackage(NpmInstallPackageCapability {
-                            package,
-                        })
-                    }
+                .map(capability_from_content)
+                .collect(),
+            capability_grants: content
+                .extension
+                .extension_capability_grants
+                .iter()
+                .map(|(extension_id, capabilities)| {
+                    (
+                        extension_id.clone(),
+                        capabilities
+                            .iter()
+                            .cloned()
+                            .map(capability_from_content)
+                            .collect(),
+                    )
                 })
                 .collect(),
         }
     }
 }
+
+fn capability_from_content(
+    capability: settings::ExtensionCapabilityContent,
+) -> ExtensionCapability {
+    match capability {
+        settings::ExtensionCapabilityContent::ProcessExec { command, args } => {
+            ExtensionCapability::ProcessExec(ProcessExecCapability { command, args })
+        }
+        settings::ExtensionCapabilityContent::DownloadFile { host, path } => {
+            ExtensionCapability::DownloadFile(DownloadFileCapability { host, path })
+        }
+        settings::ExtensionCapabilityContent::NpmInstallPackage { package } => {
+            ExtensionCapability::NpmInstallPackage(NpmInstallPackageCapability { package })
+        }
+        settings::ExtensionCapabilityContent::Network { host } => {
+            ExtensionCapability::Network(NetworkCapability { host })
+        }
+        settings::ExtensionCapabilityContent::ReadFile { path } => {
+            ExtensionCapability::ReadFile(ReadFileCapability { path })
+        }
+    }
+}
+
+/// Remembers in the settings that the capability is granted to the extension.
+pub fn save_capability_grant(
+    extension_id: Arc<str>,
+    capability: ExtensionCapability,
+    fs: Arc<dyn Fs>,
+    cx: &App,
+) {
+    let capability = match capability {
+        ExtensionCapability::ProcessExec(ProcessExecCapability { command, args }) => {
+            settings::ExtensionCapabilityContent::ProcessExec { command, args }
+        }
+        ExtensionCapability::DownloadFile(DownloadFileCapability { host, path }) => {
+            settings::ExtensionCapabilityContent::DownloadFile { host, path }
+        }
+        ExtensionCapability::NpmInstallPackage(NpmInstallPackageCapability { package }) => {
+            settings::ExtensionCapabilityContent::NpmInstallPackage { package }
+        }
+        ExtensionCapability::Network(NetworkCapability { host }) => {
+            settings::ExtensionCapabilityContent::Network { host }
+        }
+        ExtensionCapability::ReadFile(ReadFileCapability { path }) => {
+            settings::ExtensionCapabilityContent::ReadFile { path }
+        }
+    };
+    settings::update_settings_file(fs, cx, move |content, _| {
+        let grants = content
+            .extension
+            .extension_capability_grants
+            .entry(extension_id)
+            .or_default();
+        if !grants.contains(&capability) {
+            grants.push(capability);
+        }
+    });
+}
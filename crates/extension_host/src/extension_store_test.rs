@@ -1,14 +1,18 @@
 use crate::{
-    Event, ExtensionIndex, ExtensionIndexEntry, ExtensionIndexLanguageEntry,
+    DisabledExtensions, Event, ExtensionIndex, ExtensionIndexEntry, ExtensionIndexLanguageEntry,
     ExtensionIndexThemeEntry, ExtensionManifest, ExtensionStore, GrammarManifestEntry,
-    RELOAD_DEBOUNCE_DURATION, SchemaVersion,
+    RELOAD_DEBOUNCE_DURATION, SchemaVersion, dev_extension_watcher::DevExtensionChange,
 };
 use async_compression::futures::bufread::GzipEncoder;
 use collections::{BTreeMap, HashSet};
-use extension::ExtensionHostProxy;
+use extension::{
+    CapabilityGrant, ContextServerManifestEntry, Extension, ExtensionCapability,
+    ExtensionCapabilityProxy, ExtensionContextServerProxy, ExtensionHostProxy,
+    ExtensionPermissionProxy, NetworkCapability,
+};
 use fs::{FakeFs, Fs, RealFs};
 use futures::{AsyncReadExt, StreamExt, io::BufReader};
-use gpui::{AppContext as _, TestAppContext};
+use gpui::{App, AppContext as _, Task, TestAppContext};
 use http_client::{FakeHttpClient, Response};
 use language::{BinaryStatus, LanguageMatcher, LanguageName, LanguageRegistry};
 use language_extension::LspAccess;
@@ -165,6 +169,9 @@ async fn test_extension_store(cx: &mut TestAppContext) {
                         capabilities: Vec::new(),
                         debug_adapters: Default::default(),
                         debug_locators: Default::default(),
+                        debug_inline_values: Default::default(),
+                        task_providers: Default::default(),
+                        problem_matchers: Default::default(),
                         language_model_providers: BTreeMap::default(),
                     }),
                     dev: false,
@@ -197,6 +204,9 @@ async fn test_extension_store(cx: &mut TestAppContext) {
                         capabilities: Vec::new(),
                         debug_adapters: Default::default(),
                         debug_locators: Default::default(),
+                        debug_inline_values: Default::default(),
+                        task_providers: Default::default(),
+                        problem_matchers: Default::default(),
                         language_model_providers: BTreeMap::default(),
                     }),
                     dev: false,
@@ -286,6 +296,7 @@ async fn test_extension_store(cx: &mut TestAppContext) {
             http_client.clone(),
             http_client.clone(),
             node_runtime.clone(),
+            DisabledExtensions::None,
             cx,
         )
     });
@@ -377,6 +388,9 @@ async fn test_extension_store(cx: &mut TestAppContext) {
                 capabilities: Vec::new(),
                 debug_adapters: Default::default(),
                 debug_locators: Default::default(),
+                debug_inline_values: Default::default(),
+                task_providers: Default::default(),
+                problem_matchers: Default::default(),
                 language_model_providers: BTreeMap::default(),
             }),
             dev: false,
@@ -436,6 +450,7 @@ async fn test_extension_store(cx: &mut TestAppContext) {
             http_client.clone(),
             http_client.clone(),
             node_runtime.clone(),
+            DisabledExtensions::None,
             cx,
         )
     });
@@ -491,9 +506,17 @@ async fn test_extension_store(cx: &mut TestAppContext) {
         assert_eq!(fs.metadata_call_count(), prev_fs_metadata_call_count + 2);
     });
 
-    store.update(cx, |store, cx| {
-        store.uninstall_extension("zed-ruby".into(), cx);
+    let concurrent_uninstall = store.update(cx, |store, cx| {
+        store
+            .uninstall_extension("zed-ruby".into(), cx)
+            .detach_and_log_err(cx);
+        store.uninstall_extension("zed-ruby".into(), cx)
     });
+    // Only one operation runs on an extension at a time.
+    assert_eq!(
+        concurrent_uninstall.await.unwrap_err().to_string(),
+        "extension zed-ruby is already being installed, upgraded or removed"
+    );
 
     cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
     expected_index.extensions.remove("vector-ruby");
@@ -528,6 +551,310 @@ async fn test_extension_store(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_disabled_extensions(cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    let http_client = FakeHttpClient::with_200_response();
+    let theme_extension = |id: &str, theme_name: &str| {
+        json!({
+            "extension.json": format!(
+                r#"{{"id": "{id}", "name": "{id}", "version": "1.0.0", "themes": {{"{theme_name}": "themes/theme.json"}}}}"#
+            ),
+            "themes": {
+                "theme.json": format!(
+                    r#"{{"name": "{theme_name}", "author": "Someone", "themes": [{{"name": "{theme_name}", "appearance": "dark", "style": {{}}}}]}}"#
+                ),
+            },
+        })
+    };
+    fs.insert_tree(
+        "/the-extension-dir",
+        json!({
+            "installed": {
+                "vector-gruvbox": theme_extension("vector-gruvbox", "Gruvbox"),
+                "vector-monokai": theme_extension("vector-monokai", "Monokai"),
+            }
+        }),
+    )
+    .await;
+
+    let proxy = Arc::new(ExtensionHostProxy::new());
+    let theme_registry = Arc::new(ThemeRegistry::new(Box::new(())));
+    theme_extension::init(proxy.clone(), theme_registry.clone(), cx.executor());
+    let store = cx.new(|cx| {
+        ExtensionStore::new(
+            PathBuf::from("/the-extension-dir"),
+            None,
+            proxy,
+            fs.clone(),
+            http_client.clone(),
+            http_client,
+            NodeRuntime::unavailable(),
+            DisabledExtensions::Some(HashSet::from_iter(["vector-monokai".into()])),
+            cx,
+        )
+    });
+
+    cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+    cx.executor().run_until_parked();
+    store.read_with(cx, |store, _| {
+        assert_eq!(
+            store.installed_extensions().keys().collect::<Vec<_>>(),
+            [&Arc::<str>::from("vector-gruvbox")]
+        );
+        assert_eq!(store.extension_index.themes.len(), 1);
+        assert_eq!(theme_registry.list_names(), ["Gruvbox", "One Dark"]);
+    });
+
+    // The index on disk still lists the disabled extensions, for the next launch.
+    let index = fs
+        .load(Path::new("/the-extension-dir/index.json"))
+        .await
+        .unwrap();
+    let index: ExtensionIndex = serde_json::from_str(&index).unwrap();
+    assert_eq!(index.extensions.len(), 2);
+}
+
+/// Records the context servers registered by extensions, and whether their manifest declares how to launch them.
+#[derive(Default)]
+struct RecordingContextServerProxy {
+    registered_servers: Arc<Mutex<Vec<(Arc<str>, bool)>>>,
+}
+
+impl ExtensionContextServerProxy for RecordingContextServerProxy {
+    fn register_context_server(
+        &self,
+        _extension: Arc<dyn Extension>,
+        server_id: Arc<str>,
+        _cx: &mut App,
+    ) {
+        self.registered_servers.lock().push((server_id, false));
+    }
+
+    fn register_declared_context_server(
+        &self,
+        _manifest: Arc<ExtensionManifest>,
+        server_id: Arc<str>,
+        _entry: ContextServerManifestEntry,
+        _cx: &mut App,
+    ) {
+        self.registered_servers.lock().push((server_id, true));
+    }
+
+    fn unregister_context_server(&self, server_id: Arc<str>, _cx: &mut App) {
+        self.registered_servers
+            .lock()
+            .retain(|(registered_id, _)| *registered_id != server_id);
+    }
+}
+
+#[gpui::test]
+async fn test_declared_context_servers(cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    let http_client = FakeHttpClient::with_200_response();
+    fs.insert_tree(
+        "/the-extension-dir",
+        json!({
+            "installed": {
+                "vector-context": {
+                    "extension.json": r#"{
+                        "id": "vector-context",
+                        "name": "Vector Context",
+                        "version": "1.0.0",
+                        "context_servers": {
+                            "declared-server": {
+                                "targets": {
+                                    "linux-x86_64": {
+                                        "archive": "https://example.com/declared-server.tar.gz",
+                                        "cmd": "./declared-server"
+                                    }
+                                }
+                            }
+                        },
+                        "capabilities": [
+                            {"kind": "process:exec", "command": "./declared-server", "args": []}
+                        ]
+                    }"#,
+                }
+            }
+        }),
+    )
+    .await;
+
+    struct RecordingPermissionProxy {
+        requested_capabilities: Arc<Mutex<Vec<ExtensionCapability>>>,
+    }
+
+    impl ExtensionPermissionProxy for RecordingPermissionProxy {
+        fn request_capability(
+            &self,
+            _manifest: Arc<ExtensionManifest>,
+            capability: ExtensionCapability,
+            _cx: &mut App,
+        ) -> Task<anyhow::Result<CapabilityGrant>> {
+            self.requested_capabilities.lock().push(capability);
+            Task::ready(Ok(CapabilityGrant::AllowOnce))
+        }
+    }
+
+    let proxy = Arc::new(ExtensionHostProxy::new());
+    let context_server_proxy = RecordingContextServerProxy::default();
+    let registered_servers = context_server_proxy.registered_servers.clone();
+    proxy.register_context_server_proxy(context_server_proxy);
+    let requested_capabilities = Arc::new(Mutex::new(Vec::new()));
+    proxy.register_permission_proxy(RecordingPermissionProxy {
+        requested_capabilities: requested_capabilities.clone(),
+    });
+    let store = cx.new(|cx| {
+        ExtensionStore::new(
+            PathBuf::from("/the-extension-dir"),
+            None,
+            proxy.clone(),
+            fs.clone(),
+            http_client.clone(),
+            http_client,
+            NodeRuntime::unavailable(),
+            DisabledExtensions::None,
+            cx,
+        )
+    });
+
+    cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+    cx.executor().run_until_parked();
+    // The declared server is registered without a WASM library to launch it.
+    assert_eq!(
+        *registered_servers.lock(),
+        [(Arc::<str>::from("declared-server"), true)]
+    );
+
+    // Launching the declared server asks the user the first time, even though the settings grant every command to
+    // every extension.
+    let manifest = store.read_with(cx, |store, _| {
+        store.extension_index.extensions["vector-context"]
+            .manifest
+            .clone()
+    });
+    for _ in 0..2 {
+        cx.update(|cx| {
+            proxy.ensure_exec_granted(manifest.clone(), "./declared-server".into(), Vec::new(), cx)
+        })
+        .await
+        .unwrap();
+    }
+    assert_eq!(requested_capabilities.lock().len(), 1);
+
+    // A command that the manifest doesn't declare is never launched.
+    cx.update(|cx| {
+        proxy.ensure_exec_granted(manifest.clone(), "./other-server".into(), Vec::new(), cx)
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(requested_capabilities.lock().len(), 1);
+
+    store.update(cx, |store, cx| {
+        store
+            .uninstall_extension("vector-context".into(), cx)
+            .detach_and_log_err(cx);
+    });
+    cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+    cx.executor().run_until_parked();
+    assert_eq!(*registered_servers.lock(), []);
+}
+
+#[gpui::test]
+async fn test_reloading_dev_extensions(cx: &mut TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    let http_client = FakeHttpClient::with_200_response();
+    fs.insert_tree("/the-extension-dir", json!({ "installed": {} }))
+        .await;
+    fs.insert_tree(
+        "/dev-extension",
+        json!({
+            "extension.json": r#"{"id": "vector-dev", "name": "Vector Dev", "version": "1.0.0"}"#,
+            "README.md": "",
+        }),
+    )
+    .await;
+    fs.insert_symlink(
+        "/the-extension-dir/installed/vector-dev",
+        PathBuf::from("/dev-extension"),
+    )
+    .await;
+
+    let store = cx.new(|cx| {
+        ExtensionStore::new(
+            PathBuf::from("/the-extension-dir"),
+            None,
+            Arc::new(ExtensionHostProxy::new()),
+            fs.clone(),
+            http_client.clone(),
+            http_client,
+            NodeRuntime::unavailable(),
+            DisabledExtensions::None,
+            cx,
+        )
+    });
+    let reloads = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cx.update(|cx| {
+        let reloads = reloads.clone();
+        cx.subscribe(&store, move |_, event, _| {
+            if let Event::DevExtensionReloaded {
+                extension_id,
+                result,
+            } = event
+            {
+                reloads.lock().push((extension_id.clone(), result.clone()));
+            }
+        })
+    });
+    cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+    cx.executor().run_until_parked();
+    store.read_with(cx, |store, _| {
+        assert!(store.extension_index.extensions["vector-dev"].dev);
+        assert!(store.dev_extension_watchers.contains_key("vector-dev"));
+    });
+
+    // Changes made while the extension is reloaded are coalesced into a single reload once it's done.
+    store.update(cx, |store, cx| {
+        for _ in 0..3 {
+            store.reload_dev_extension("vector-dev".into(), DevExtensionChange::Assets, cx);
+        }
+        assert!(store.outstanding_operations.contains_key("vector-dev"));
+        assert_eq!(
+            store.pending_dev_extension_reloads.get("vector-dev"),
+            Some(&DevExtensionChange::Assets)
+        );
+    });
+    for _ in 0..2 {
+        cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+        cx.executor().run_until_parked();
+    }
+    assert_eq!(
+        *reloads.lock(),
+        vec![(Arc::<str>::from("vector-dev"), Ok(())); 2]
+    );
+    store.read_with(cx, |store, _| {
+        assert!(store.pending_dev_extension_reloads.is_empty());
+        assert!(!store.outstanding_operations.contains_key("vector-dev"));
+    });
+
+    // Changing a file of the extension reloads it.
+    reloads.lock().clear();
+    fs.insert_file("/dev-extension/README.md", b"changed".to_vec())
+        .await;
+    for _ in 0..2 {
+        cx.executor().advance_clock(RELOAD_DEBOUNCE_DURATION);
+        cx.executor().run_until_parked();
+    }
+    assert_eq!(*reloads.lock(), [(Arc::<str>::from("vector-dev"), Ok(()))]);
+}
+
 #[gpui::test]
 async fn test_extension_store_with_test_extension(cx: &mut TestAppContext) {
     log::info!("Initializing test");
@@ -562,6 +889,30 @@ async fn test_extension_store_with_test_extension(cx: &mut TestAppContext) {
     let proxy = Arc::new(ExtensionHostProxy::new());
     let theme_registry = Arc::new(ThemeRegistry::new(Box::new(())));
     theme_extension::init(proxy.clone(), theme_registry.clone(), cx.executor());
+
+    struct AllowOncePermissionProxy {
+        requested_capabilities: Arc<Mutex<Vec<ExtensionCapability>>>,
+    }
+
+    impl ExtensionPermissionProxy for AllowOncePermissionProxy {
+        fn request_capability(
+            &self,
+            _manifest: Arc<ExtensionManifest>,
+            capability: ExtensionCapability,
+            _cx: &mut App,
+        ) -> Task<anyhow::Result<CapabilityGrant>> {
+            self.requested_capabilities.lock().push(capability);
+            Task::ready(Ok(CapabilityGrant::AllowOnce))
+        }
+    }
+
+    let requested_capabilities = Arc::new(Mutex::new(Vec::new()));
+    proxy.register_permission_proxy(AllowOncePermissionProxy {
+        requested_capabilities: requested_capabilities.clone(),
+    });
+    let context_server_proxy = RecordingContextServerProxy::default();
+    let registered_context_servers = context_server_proxy.registered_servers.clone();
+    proxy.register_context_server_proxy(context_server_proxy);
     let language_registry = project.read_with(cx, |project, _cx| project.languages().clone());
     language_extension::init(
         LspAccess::ViaLspStore(project.update(cx, |project, _| project.lsp_store())),
@@ -670,6 +1021,7 @@ async fn test_extension_store_with_test_extension(cx: &mut TestAppContext) {
             extension_client.clone(),
             builder_client,
             node_runtime,
+            DisabledExtensions::None,
             cx,
         )
     });
@@ -858,6 +1210,21 @@ async fn test_extension_store_with_test_extension(cx: &mut TestAppContext) {
 
     // The old language server directory has been cleaned up.
     assert!(fs.metadata(&expected_server_path).await.unwrap().is_none());
+
+    // The context server declared in the manifest isn't registered again for the WASM library to launch.
+    assert_eq!(
+        *registered_context_servers.lock(),
+        [(Arc::<str>::from("test-declared-server"), true)]
+    );
+
+    // The user was asked once for the capability the extension declared to look up releases, which stayed granted
+    // when the extension was reloaded.
+    assert_eq!(
+        *requested_capabilities.lock(),
+        [ExtensionCapability::Network(NetworkCapability {
+            host: "api.github.com".into()
+        })]
+    );
 }
 
 fn init_test(cx: &mut TestAppContext) {
@@ -1,15 +1,17 @@
 pub mod wit;
 
-use crate::capability_granter::CapabilityGranter;
+use crate::capability_granter::{CapabilityCheck, CapabilityGranter};
+use crate::extension_settings::save_capability_grant;
 use crate::{ExtensionManifest, ExtensionSettings};
 use anyhow::{Context as _, Result, anyhow, bail};
 use async_trait::async_trait;
+use collections::HashMap;
 use dap::{DebugRequest, StartDebuggingRequestArgumentsRequest};
 use extension::{
-    CodeLabel, Command, Completion, ContextServerConfiguration, DebugAdapterBinary,
-    DebugTaskDefinition, ExtensionCapability, ExtensionHostProxy, KeyValueStoreDelegate,
-    ProjectDelegate, SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput, Symbol,
-    WorktreeDelegate,
+    CapabilityGrant, CodeLabel, Command, Completion, ContextServerConfiguration,
+    DebugAdapterBinary, DebugTaskDefinition, ExtensionCapability, ExtensionCapabilityProxy,
+    ExtensionHostProxy, ExtensionPermissionProxy as _, KeyValueStoreDelegate, ProjectDelegate,
+    SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput, Symbol, WorktreeDelegate,
 };
 use fs::{Fs, normalize_path};
 use futures::future::LocalBoxFuture;
@@ -27,6 +29,7 @@ use language::LanguageName;
 use lsp::LanguageServerName;
 use moka::sync::Cache;
 use node_runtime::NodeRuntime;
+use parking_lot::Mutex;
 use release_channel::ReleaseChannel;
 use semver::Version;
 use settings::Settings;
@@ -39,8 +42,10 @@ use std::{
     },
     time::Duration,
 };
-use task::{DebugScenario, SpawnInTerminal, TaskTemplate, VectorDebugConfig};
+use task::{DebugScenario, ProblemMatcher, SpawnInTerminal, TaskTemplate, VectorDebugConfig};
+use url::Url;
 use util::paths::SanitizedPath;
+use util::rel_path::RelPath;
 use wasmtime::{
     CacheStore, Engine, Store,
     component::{Component, ResourceTable},
@@ -56,8 +61,10 @@ pub struct WasmHost {
     pub(crate) proxy: Arc<ExtensionHostProxy>,
     fs: Arc<dyn Fs>,
     pub work_dir: PathBuf,
-    /// The capabilities granted to extensions running on the host.
-    pub(crate) granted_capabilities: Vec<ExtensionCapability>,
+    /// The capabilities the user allowed once, by extension ID, which stay granted when extensions are reloaded.
+    capabilities_allowed_once: Mutex<HashMap<Arc<str>, Vec<ExtensionCapability>>>,
+    /// The capabilities the user denied, by extension ID, which they aren't asked for again until Vector quits.
+    capabilities_denied: Mutex<HashMap<Arc<str>, Vec<ExtensionCapability>>>,
     _main_thread_message_task: Task<()>,
     main_thread_message_tx: mpsc::UnboundedSender<MainThreadCall>,
 }
@@ -488,6 +495,74 @@ impl extension::Extension for WasmExtension {
         })
         .await?
     }
+
+    async fn task_templates(&self, provider_name: Arc<str>) -> Result<Vec<TaskTemplate>> {
+        self.call(|extension, store| {
+            async move {
+                extension
+                    .call_task_templates(store, &provider_name)
+                    .await?
+                    .map_err(|err| store.data().extension_error(err))
+            }
+            .boxed()
+        })
+        .await?
+    }
+
+    async fn problem_matchers(&self, matcher_name: Arc<str>) -> Result<Vec<ProblemMatcher>> {
+        self.call(|extension, store| {
+            async move {
+                extension
+                    .call_problem_matchers(store, &matcher_name)
+                    .await?
+                    .map_err(|err| store.data().extension_error(err))
+            }
+            .boxed()
+        })
+        .await?
+    }
+}
+
+/// Checks the capabilities that extensions use outside of their WASM library, like launching the context servers
+/// their manifest declares.
+pub(crate) struct WasmHostCapabilityProxy(pub Arc<WasmHost>);
+
+impl ExtensionCapabilityProxy for WasmHostCapabilityProxy {
+    fn ensure_download_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        url: String,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        let granted_capabilities =
+            ExtensionSettings::get_global(cx).capabilities_granted_to(&manifest.id);
+        let check = Url::parse(&url)
+            .map_err(anyhow::Error::from)
+            .and_then(|url| {
+                self.0
+                    .capability_granter(&manifest, granted_capabilities)
+                    .grant_download_file(&url)
+            });
+        self.0.ensure_granted(manifest, check, cx)
+    }
+
+    fn ensure_exec_granted(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        command: String,
+        args: Vec<String>,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        // Unlike the commands the WASM library runs, launching a declared server isn't covered by the capabilities
+        // granted to every extension, so that the user is asked before an installed extension first runs it.
+        let granted_capabilities =
+            ExtensionSettings::get_global(cx).capabilities_granted_only_to(&manifest.id);
+        let check = self
+            .0
+            .capability_granter(&manifest, granted_capabilities)
+            .grant_exec(&command, &args);
+        self.0.ensure_granted(manifest, check, cx)
+    }
 }
 
 pub struct WasmState {
@@ -579,8 +654,6 @@ impl WasmHost {
             }
         });
 
-        let extension_settings = ExtensionSettings::get_global(cx);
-
         Arc::new(Self {
             engine: wasm_engine(cx.background_executor()),
             fs,
@@ -589,12 +662,93 @@ impl WasmHost {
             node_runtime,
             proxy,
             release_channel: ReleaseChannel::global(cx),
-            granted_capabilities: extension_settings.granted_capabilities.clone(),
+            capabilities_allowed_once: Mutex::default(),
+            capabilities_denied: Mutex::default(),
             _main_thread_message_task: task,
             main_thread_message_tx: tx,
         })
     }
 
+    /// Returns the granter of the capabilities of an extension, from the given grants and the answers the user gave
+    /// since Vector started.
+    fn capability_granter(
+        &self,
+        manifest: &Arc<ExtensionManifest>,
+        mut granted_capabilities: Vec<ExtensionCapability>,
+    ) -> CapabilityGranter {
+        if let Some(allowed_once) = self.capabilities_allowed_once.lock().get(&manifest.id) {
+            granted_capabilities.extend(allowed_once.iter().cloned());
+        }
+        let denied_capabilities = self
+            .capabilities_denied
+            .lock()
+            .get(&manifest.id)
+            .cloned()
+            .unwrap_or_default();
+        CapabilityGranter::new(granted_capabilities, denied_capabilities, manifest.clone())
+    }
+
+    /// Asks the user whether to grant a capability to an extension, and remembers the answer.
+    async fn request_capability(
+        self: Arc<Self>,
+        manifest: Arc<ExtensionManifest>,
+        capability: ExtensionCapability,
+        cx: &mut AsyncApp,
+    ) -> Result<CapabilityGrant> {
+        let grant = cx
+            .update(|cx| {
+                self.proxy
+                    .request_capability(manifest.clone(), capability.clone(), cx)
+            })?
+            .await?;
+        match grant {
+            CapabilityGrant::AllowAlways => {
+                let fs = self.fs.clone();
+                cx.update(|cx| save_capability_grant(manifest.id.clone(), capability, fs, cx))?;
+            }
+            CapabilityGrant::AllowOnce => self
+                .capabilities_allowed_once
+                .lock()
+                .entry(manifest.id.clone())
+                .or_default()
+                .push(capability),
+            CapabilityGrant::Deny => self
+                .capabilities_denied
+                .lock()
+                .entry(manifest.id.clone())
+                .or_default()
+                .push(capability),
+        }
+        Ok(grant)
+    }
+
+    /// Grants a capability checked outside of the WASM library of an extension, asking the user if needed.
+    fn ensure_granted(
+        self: &Arc<Self>,
+        manifest: Arc<ExtensionManifest>,
+        check: Result<CapabilityCheck>,
+        cx: &mut App,
+    ) -> Task<Result<()>> {
+        let capability = match check {
+            Ok(CapabilityCheck::Granted) => return Task::ready(Ok(())),
+            Ok(CapabilityCheck::NeedsGrant(capability)) => capability,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let this = self.clone();
+        cx.spawn(async move |cx| {
+            let grant = this
+                .request_capability(manifest, capability.clone(), cx)
+                .await?;
+            if grant == CapabilityGrant::Deny {
+                bail!(
+                    "the permission to {} was denied to the extension",
+                    capability.description()
+                );
+            }
+            Ok(())
+        })
+    }
+
     pub fn load_extension(
         self: &Arc<Self>,
         wasm_bytes: Vec<u8>,
@@ -604,8 +758,16 @@ impl WasmHost {
         let this = self.clone();
         let manifest = manifest.clone();
         let executor = cx.background_executor().clone();
+        // The grants are read when loading the extension, so that reloading it applies the grants saved since.
+        let capability_granter = match cx
+            .update(|cx| ExtensionSettings::get_global(cx).capabilities_granted_to(&manifest.id))
+        {
+            Ok(granted_capabilities) => this.capability_granter(&manifest, granted_capabilities),
+            Err(error) => return Task::ready(Err(error)),
+        };
         let load_extension_task = async move {
             let zed_api_version = parse_wasm_extension_version(&manifest.id, &wasm_bytes)?;
+            wit::negotiate_wasm_api_version(this.release_channel, &manifest, &zed_api_version)?;
 
             let component = Component::from_binary(&this.engine, &wasm_bytes)
                 .context("failed to compile wasm component")?;
@@ -616,10 +778,7 @@ impl WasmHost {
                     manifest: manifest.clone(),
                     table: ResourceTable::new(),
                     host: this.clone(),
-                    capability_granter: CapabilityGranter::new(
-                        this.granted_capabilities.clone(),
-                        manifest.clone(),
-                    ),
+                    capability_granter,
                 },
             );
             // Store will yield after 1 tick, and get a new deadline of 1 tick after each yield.
@@ -842,6 +1001,44 @@ impl WasmState {
         }
     }
 
+    /// Asks the user to grant the capability the extension declared for a use, the first time it's used.
+    async fn ensure_granted(&mut self, check: CapabilityCheck) -> Result<()> {
+        let CapabilityCheck::NeedsGrant(capability) = check else {
+            return Ok(());
+        };
+        let grant = self
+            .on_main_thread({
+                let host = self.host.clone();
+                let manifest = self.manifest.clone();
+                let capability = capability.clone();
+                move |cx| {
+                    host.request_capability(manifest, capability, cx)
+                        .boxed_local()
+                }
+            })
+            .await?;
+        if grant == CapabilityGrant::Deny {
+            self.capability_granter.deny(capability.clone());
+            bail!(
+                "the permission to {} was denied to the extension",
+                capability.description()
+            );
+        }
+        self.capability_granter.grant(capability);
+        Ok(())
+    }
+
+    async fn ensure_network_granted(&mut self, url: &str) -> Result<()> {
+        let url = Url::parse(url)?;
+        let check = self.capability_granter.grant_network(&url)?;
+        self.ensure_granted(check).await
+    }
+
+    async fn ensure_read_file_granted(&mut self, path: &RelPath) -> Result<()> {
+        let check = self.capability_granter.grant_read_file(path)?;
+        self.ensure_granted(check).await
+    }
+
     fn work_dir(&self) -> PathBuf {
         self.host.work_dir.join(self.manifest.id.as_ref())
     }
@@ -9,12 +9,12 @@ mod since_v0_5_0;
 mod since_v0_6_0;
 mod since_v0_8_0;
 use dap::DebugRequest;
-use extension::{DebugTaskDefinition, KeyValueStoreDelegate, WorktreeDelegate};
+use extension::{DebugTaskDefinition, ExtensionManifest, KeyValueStoreDelegate, WorktreeDelegate};
 use gpui::BackgroundExecutor;
 use language::LanguageName;
 use lsp::LanguageServerName;
 use release_channel::ReleaseChannel;
-use task::{DebugScenario, SpawnInTerminal, TaskTemplate, VectorDebugConfig};
+use task::{DebugScenario, ProblemMatcher, SpawnInTerminal, TaskTemplate, VectorDebugConfig};
 
 use crate::wasm_host::wit::since_v0_6_0::dap::StartDebuggingRequestArgumentsRequest;
 
@@ -23,6 +23,7 @@ use anyhow::{Context as _, Result, anyhow};
 use semver::Version;
 use since_v0_8_0 as latest;
 use std::{ops::RangeInclusive, path::PathBuf, sync::Arc};
+use thiserror::Error;
 use wasmtime::{
     Store,
     component::{Component, Linker, Resource},
@@ -95,6 +96,115 @@ pub fn authorize_access_to_unreleased_wasm_api_version(
     Ok(())
 }
 
+/// Why the Wasm host refuses to run an extension built against a version of the Wasm API.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExtensionApiVersionError {
+    #[error(
+        "the extension was built against extension API v{version}, but this build of Vector supports up to v{max_version}. Update Vector to use it"
+    )]
+    Newer {
+        version: Version,
+        max_version: Version,
+    },
+    #[error(
+        "the extension was built against extension API v{version}, but Vector supports v{min_version} and later"
+    )]
+    Older {
+        version: Version,
+        min_version: Version,
+    },
+    #[error(
+        "the extension was built against the unreleased extension API v{version}, which can only be used on development builds of Vector"
+    )]
+    Unreleased { version: Version },
+    #[error(
+        "the extension provides {contribution}, which needs extension API v{required_version}, but it was built against v{version}"
+    )]
+    MissingContribution {
+        contribution: &'static str,
+        version: Version,
+        required_version: Version,
+    },
+}
+
+/// Checks that the Wasm host can run an extension built against `version` of the Wasm API, before instantiating it.
+///
+/// Extensions built against older versions run with the bindings of their version, which shim them to the current
+/// API. Extensions built against newer versions, or that provide things their version can't, are refused here
+/// rather than failing when called.
+pub fn negotiate_wasm_api_version(
+    release_channel: ReleaseChannel,
+    manifest: &ExtensionManifest,
+    version: &Version,
+) -> Result<(), ExtensionApiVersionError> {
+    let supported = wasm_api_version_range(release_channel);
+    if version < supported.start() {
+        return Err(ExtensionApiVersionError::Older {
+            version: version.clone(),
+            min_version: supported.start().clone(),
+        });
+    }
+    if version > &latest::MAX_VERSION {
+        return Err(ExtensionApiVersionError::Newer {
+            version: version.clone(),
+            max_version: supported.end().clone(),
+        });
+    }
+    if version > supported.end()
+        && authorize_access_to_unreleased_wasm_api_version(release_channel).is_err()
+    {
+        return Err(ExtensionApiVersionError::Unreleased {
+            version: version.clone(),
+        });
+    }
+
+    let required_versions = [
+        (
+            "slash commands",
+            !manifest.slash_commands.is_empty(),
+            since_v0_1_0::MIN_VERSION,
+        ),
+        (
+            "context servers",
+            manifest
+                .context_servers
+                .values()
+                .any(|entry| !entry.is_declared()),
+            since_v0_2_0::MIN_VERSION,
+        ),
+        (
+            "debug adapters",
+            !manifest.debug_adapters.is_empty(),
+            since_v0_6_0::MIN_VERSION,
+        ),
+        (
+            "debug locators",
+            !manifest.debug_locators.is_empty(),
+            since_v0_6_0::MIN_VERSION,
+        ),
+        (
+            "task providers",
+            !manifest.task_providers.is_empty(),
+            since_v0_8_0::MIN_VERSION,
+        ),
+        (
+            "problem matchers",
+            !manifest.problem_matchers.is_empty(),
+            since_v0_8_0::MIN_VERSION,
+        ),
+    ];
+    for (contribution, provided, required_version) in required_versions {
+        if provided && version < &required_version {
+            return Err(ExtensionApiVersionError::MissingContribution {
+                contribution,
+                version: version.clone(),
+                required_version,
+            });
+        }
+    }
+    Ok(())
+}
+
 pub enum Extension {
     V0_8_0(since_v0_8_0::Extension),
     V0_6_0(since_v0_6_0::Extension),
@@ -1111,6 +1221,34 @@ impl Extension {
             _ => anyhow::bail!("`dap_locator_create_scenario` not available prior to v0.6.0"),
         }
     }
+
+    pub async fn call_task_templates(
+        &self,
+        store: &mut Store<WasmState>,
+        provider_name: &str,
+    ) -> Result<Result<Vec<TaskTemplate>, String>> {
+        match self {
+            Extension::V0_8_0(ext) => Ok(ext
+                .call_task_templates(store, provider_name)
+                .await?
+                .map(|templates| templates.into_iter().map(Into::into).collect())),
+            _ => anyhow::bail!("`task_templates` not available prior to v0.8.0"),
+        }
+    }
+
+    pub async fn call_problem_matchers(
+        &self,
+        store: &mut Store<WasmState>,
+        matcher_name: &str,
+    ) -> Result<Result<Vec<ProblemMatcher>, String>> {
+        match self {
+            Extension::V0_8_0(ext) => Ok(ext
+                .call_problem_matchers(store, matcher_name)
+                .await?
+                .map(|matchers| matchers.into_iter().map(Into::into).collect())),
+            _ => anyhow::bail!("`problem_matchers` not available prior to v0.8.0"),
+        }
+    }
 }
 
 trait ToWasmtimeResult<T> {
@@ -1122,3 +1260,79 @@ impl<T> ToWasmtimeResult<T> for Result<T> {
         Ok(self.map_err(|error| format!("{error:?}")))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_wasm_api_version() {
+        let mut manifest = ExtensionManifest {
+            id: "test".into(),
+            name: "Test".to_string(),
+            version: "1.0.0".into(),
+            schema_version: extension::SchemaVersion(1),
+            description: None,
+            repository: None,
+            authors: Vec::new(),
+            lib: Default::default(),
+            themes: Vec::new(),
+            icon_themes: Vec::new(),
+            languages: Vec::new(),
+            grammars: Default::default(),
+            language_servers: Default::default(),
+            context_servers: Default::default(),
+            agent_servers: Default::default(),
+            slash_commands: Default::default(),
+            snippets: None,
+            capabilities: Vec::new(),
+            debug_adapters: Default::default(),
+            debug_locators: Default::default(),
+            debug_inline_values: Default::default(),
+            task_providers: Default::default(),
+            problem_matchers: Default::default(),
+            language_model_providers: Default::default(),
+        };
+
+        for version in [
+            Version::new(0, 0, 1),
+            Version::new(0, 6, 0),
+            latest::MAX_VERSION,
+        ] {
+            assert_eq!(
+                negotiate_wasm_api_version(ReleaseChannel::Dev, &manifest, &version),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            negotiate_wasm_api_version(ReleaseChannel::Dev, &manifest, &Version::new(0, 0, 0)),
+            Err(ExtensionApiVersionError::Older {
+                version: Version::new(0, 0, 0),
+                min_version: Version::new(0, 0, 1),
+            })
+        );
+        assert_eq!(
+            negotiate_wasm_api_version(ReleaseChannel::Stable, &manifest, &Version::new(1, 0, 0)),
+            Err(ExtensionApiVersionError::Newer {
+                version: Version::new(1, 0, 0),
+                max_version: since_v0_6_0::MAX_VERSION,
+            })
+        );
+
+        manifest
+            .task_providers
+            .insert("cargo".into(), extension::TaskProviderManifestEntry {});
+        assert_eq!(
+            negotiate_wasm_api_version(ReleaseChannel::Dev, &manifest, &Version::new(0, 6, 0)),
+            Err(ExtensionApiVersionError::MissingContribution {
+                contribution: "task providers",
+                version: Version::new(0, 6, 0),
+                required_version: Version::new(0, 8, 0),
+            })
+        );
+        assert_eq!(
+            negotiate_wasm_api_version(ReleaseChannel::Dev, &manifest, &latest::MAX_VERSION),
+            Ok(())
+        );
+    }
+}
@@ -324,6 +324,50 @@ impl TryFrom<SpawnInTerminal> for ResolvedTask {
     }
 }
 
+impl From<task_provider::TaskDefinition> for task::TaskTemplate {
+    fn from(value: task_provider::TaskDefinition) -> Self {
+        Self {
+            label: value.label,
+            command: value.command,
+            args: value.args,
+            env: value.env.into_iter().collect(),
+            cwd: value.cwd,
+            tags: value.tags,
+            problem_matchers: value.problem_matchers.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<task_provider::ProblemMatcherDefinition> for task::ProblemMatcherDefinition {
+    fn from(value: task_provider::ProblemMatcherDefinition) -> Self {
+        match value {
+            task_provider::ProblemMatcherDefinition::Named(name) => {
+                serde_json::from_value(serde_json::Value::String(name.clone()))
+                    .map_or(Self::Named(name), Self::BuiltIn)
+            }
+            task_provider::ProblemMatcherDefinition::Custom(matcher) => {
+                Self::Custom(matcher.into())
+            }
+        }
+    }
+}
+
+impl From<task_provider::ProblemMatcher> for task::ProblemMatcher {
+    fn from(value: task_provider::ProblemMatcher) -> Self {
+        Self {
+            pattern: value.pattern,
+            file_pattern: value.file_pattern,
+            severity: match value.severity {
+                task_provider::ProblemSeverity::Error => task::ProblemSeverity::Error,
+                task_provider::ProblemSeverity::Warning => task::ProblemSeverity::Warning,
+                task_provider::ProblemSeverity::Info => task::ProblemSeverity::Info,
+                task_provider::ProblemSeverity::Hint => task::ProblemSeverity::Hint,
+            },
+        }
+    }
+}
+
 impl From<CodeLabel> for extension::CodeLabel {
     fn from(value: CodeLabel) -> Self {
         Self {
@@ -564,9 +608,13 @@ impl HostWorktree for WasmState {
         delegate: Resource<Arc<dyn WorktreeDelegate>>,
         path: String,
     ) -> wasmtime::Result<Result<String, String>> {
+        let path = RelPath::new(Path::new(&path), PathStyle::Posix)?;
+        if let Err(error) = self.ensure_read_file_granted(&path).await {
+            return Ok(Err(error.to_string()));
+        }
         let delegate = self.table.get(&delegate)?;
         Ok(delegate
-            .read_text_file(&RelPath::new(Path::new(&path), PathStyle::Posix)?)
+            .read_text_file(&path)
             .await
             .map_err(|error| error.to_string()))
     }
@@ -603,6 +651,7 @@ impl http_client::Host for WasmState {
     ) -> wasmtime::Result<Result<http_client::HttpResponse, String>> {
         maybe!(async {
             let url = &request.url;
+            self.ensure_network_granted(url).await?;
             let request = convert_request(&request)?;
             let mut response = self.host.http_client.send(request).await?;
 
@@ -619,10 +668,10 @@ impl http_client::Host for WasmState {
         &mut self,
         request: http_client::HttpRequest,
     ) -> wasmtime::Result<Result<Resource<ExtensionHttpResponseStream>, String>> {
-        let request = convert_request(&request)?;
-        let response = self.host.http_client.send(request);
         maybe!(async {
-            let response = response.await?;
+            self.ensure_network_granted(&request.url).await?;
+            let request = convert_request(&request)?;
+            let response = self.host.http_client.send(request).await?;
             let stream = Arc::new(Mutex::new(response));
             let resource = self.table.push(stream)?;
             Ok(resource)
@@ -757,8 +806,10 @@ impl nodejs::Host for WasmState {
         package_name: String,
         version: String,
     ) -> wasmtime::Result<Result<(), String>> {
-        self.capability_granter
+        let check = self
+            .capability_granter
             .grant_npm_install_package(&package_name)?;
+        self.ensure_granted(check).await?;
 
         self.host
             .node_runtime
@@ -789,6 +840,11 @@ impl From<::http_client::github::GithubReleaseAsset> for github::GithubReleaseAs
     }
 }
 
+/// The URL of the GitHub API that looking up the releases of a repository sends requests to.
+fn github_api_url(repo: &str) -> String {
+    format!("https://api.github.com/repos/{repo}/releases")
+}
+
 impl github::Host for WasmState {
     async fn latest_github_release(
         &mut self,
@@ -796,6 +852,7 @@ impl github::Host for WasmState {
         options: github::GithubReleaseOptions,
     ) -> wasmtime::Result<Result<github::GithubRelease, String>> {
         maybe!(async {
+            self.ensure_network_granted(&github_api_url(&repo)).await?;
             let release = ::http_client::github::latest_github_release(
                 &repo,
                 options.require_assets,
@@ -815,6 +872,7 @@ impl github::Host for WasmState {
         tag: String,
     ) -> wasmtime::Result<Result<github::GithubRelease, String>> {
         maybe!(async {
+            self.ensure_network_granted(&github_api_url(&repo)).await?;
             let release = ::http_client::github::get_release_by_tag_name(
                 &repo,
                 &tag,
@@ -863,8 +921,10 @@ impl process::Host for WasmState {
         command: process::Command,
     ) -> wasmtime::Result<Result<process::Output, String>> {
         maybe!(async {
-            self.capability_granter
+            let check = self
+                .capability_granter
                 .grant_exec(&command.command, &command.args)?;
+            self.ensure_granted(check).await?;
 
             let output = util::command::new_smol_command(command.command.as_str())
                 .args(&command.args)
@@ -885,6 +945,8 @@ impl slash_command::Host for WasmState {}
 #[async_trait]
 impl context_server::Host for WasmState {}
 
+impl task_provider::Host for WasmState {}
+
 impl dap::Host for WasmState {
     async fn resolve_tcp_template(
         &mut self,
@@ -1035,7 +1097,8 @@ impl ExtensionImports for WasmState {
     ) -> wasmtime::Result<Result<(), String>> {
         maybe!(async {
             let parsed_url = Url::parse(&url)?;
-            self.capability_granter.grant_download_file(&parsed_url)?;
+            let check = self.capability_granter.grant_download_file(&parsed_url)?;
+            self.ensure_granted(check).await?;
 
             let path = PathBuf::from(path);
             let extension_work_dir = self.host.work_dir.join(self.manifest.id.as_ref());
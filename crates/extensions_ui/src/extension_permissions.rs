@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use extension::{
+    CapabilityGrant, ExtensionCapability, ExtensionHostProxy, ExtensionManifest,
+    ExtensionPermissionProxy,
+};
+use gpui::{App, PromptLevel, Task};
+
+pub(crate) fn init(cx: &mut App) {
+    ExtensionHostProxy::default_global(cx).register_permission_proxy(PermissionPromptProxy);
+}
+
+/// Asks for the capabilities of extensions in the frontmost window.
+struct PermissionPromptProxy;
+
+impl ExtensionPermissionProxy for PermissionPromptProxy {
+    fn request_capability(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        capability: ExtensionCapability,
+        cx: &mut App,
+    ) -> Task<Result<CapabilityGrant>> {
+        // Without a window to ask in, the use fails without remembering a denial, so that the user is asked when
+        // the extension uses the capability again.
+        let Some(window) = workspace::frontmost_local_workspace_window(cx) else {
+            return Task::ready(Err(anyhow!(
+                "no window to ask for the permission to {} in",
+                capability.description()
+            )));
+        };
+        let detail = format!(
+            "The extension wants to {}. Allow it?",
+            capability.description()
+        );
+        let answer = window.update(cx, |_, window, cx| {
+            window.prompt(
+                PromptLevel::Warning,
+                &format!("{} requests a permission", manifest.name),
+                Some(&detail),
+                &["Allow Once", "Always Allow", "Deny"],
+                cx,
+            )
+        });
+        cx.spawn(async move |_| {
+            Ok(match answer?.await? {
+                0 => CapabilityGrant::AllowOnce,
+                1 => CapabilityGrant::AllowAlways,
+                _ => CapabilityGrant::Deny,
+            })
+        })
+    }
+}
@@ -1,12 +1,15 @@
 mod components;
+mod extension_permissions;
 
 use std::time::Duration;
 use std::{ops::Range, sync::Arc};
 
-use collections::BTreeSet;
+use collections::{BTreeSet, HashMap};
 use editor::{Editor, EditorElement, EditorStyle};
 use extension::api::{ExtensionMetadata, ExtensionProvides};
-use extension_host::{ExtensionManifest, ExtensionOperation, ExtensionStore};
+use extension_host::{
+    DevExtensionReloadResult, ExtensionManifest, ExtensionOperation, ExtensionStore,
+};
 use fuzzy::{StringMatchCandidate, match_strings};
 use gpui::{
     Action, App, ClipboardItem, Context, Entity, EventEmitter, Flatten, Focusable,
@@ -30,6 +33,7 @@ use crate::components::ExtensionCard;
 actions!(vector, [InstallDevExtension]);
 
 pub fn init(cx: &mut App) {
+    extension_permissions::init(cx);
     cx.observe_new(move |workspace: &mut Workspace, window, _| {
         if window.is_none() {
             return;
@@ -185,6 +189,8 @@ pub struct ExtensionsPage {
     is_fetching_extensions: bool,
     remote_extension_entries: Vec<ExtensionMetadata>,
     dev_extension_entries: Vec<Arc<ExtensionManifest>>,
+    /// The result of the last reload of each dev extension after its files changed.
+    dev_extension_reload_results: HashMap<Arc<str>, DevExtensionReloadResult>,
     query_editor: Entity<Editor>,
     query_contains_error: bool,
     provides_filter: Option<ExtensionProvides>,
@@ -218,6 +224,14 @@ impl ExtensionsPage {
                                 window,
                                 cx,
                             ),
+                        extension_host::Event::DevExtensionReloaded {
+                            extension_id,
+                            result,
+                        } => {
+                            this.dev_extension_reload_results
+                                .insert(extension_id.clone(), result.clone());
+                            cx.notify();
+                        }
                         _ => {}
                     },
                 ),
@@ -236,6 +250,7 @@ impl ExtensionsPage {
                 list: scroll_handle,
                 is_fetching_extensions: false,
                 dev_extension_entries: Vec::new(),
+                dev_extension_reload_results: HashMap::default(),
                 remote_extension_entries: Vec::new(),
                 query_contains_error: false,
                 provides_filter,
@@ -462,7 +477,9 @@ impl ExtensionsPage {
                                         let extension_id = extension.id.clone();
                                         move |_, _, cx| {
                                             ExtensionStore::global(cx).update(cx, |store, cx| {
-                                                store.uninstall_extension(extension_id.clone(), cx)
+                                                store
+                                                    .uninstall_extension(extension_id.clone(), cx)
+                                                    .detach_and_log_err(cx)
                                             });
                                         }
                                     })
@@ -546,6 +563,20 @@ impl ExtensionsPage {
                         .tooltip(Tooltip::text(repository_url))
                     })),
             )
+            .children(
+                self.dev_extension_reload_results
+                    .get(&extension.id)
+                    .map(|result| match result {
+                        Ok(()) => Label::new("Reloaded after its files changed")
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                            .truncate(),
+                        Err(error) => Label::new(format!("Failed to reload: {error}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Error)
+                            .truncate(),
+                    }),
+            )
     }
 
     fn render_remote_extension(
@@ -793,7 +824,9 @@ impl ExtensionsPage {
                     let extension_id = extension.id.clone();
                     move |_, _, cx| {
                         ExtensionStore::global(cx).update(cx, |store, cx| {
-                            store.uninstall_extension(extension_id.clone(), cx)
+                            store
+                                .uninstall_extension(extension_id.clone(), cx)
+                                .detach_and_log_err(cx)
                         });
                     }
                 }),
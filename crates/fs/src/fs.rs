@@ -3,6 +3,9 @@ mod mac_watcher;
 
 #[cfg(not(target_os = "macos"))]
 pub mod fs_watcher;
+mod memory_fs;
+#[cfg(target_os = "windows")]
+mod wsl_watcher;
 
 use parking_lot::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -38,6 +41,7 @@ use serde::{Deserialize, Serialize};
 use smol::io::AsyncWriteExt;
 use std::{
     io::{self, Write},
+    ops::Range,
     path::{Component, Path, PathBuf},
     pin::Pin,
     sync::Arc,
@@ -66,6 +70,8 @@ use std::ffi::OsStr;
 #[cfg(any(test, feature = "test-support"))]
 pub use fake_git_repo::{LOAD_HEAD_TEXT_TASK, LOAD_INDEX_TEXT_TASK};
 
+pub use memory_fs::MemoryFs;
+
 pub trait Watcher: Send + Sync {
     fn add(&self, path: &Path) -> Result<()>;
     fn remove(&self, path: &Path) -> Result<()>;
@@ -121,9 +127,25 @@ pub trait Fs: Send + Sync {
         Ok(String::from_utf8(self.load_bytes(path).await?)?)
     }
     async fn load_bytes(&self, path: &Path) -> Result<Vec<u8>>;
+    /// Loads the bytes of a file within the given range, which is clamped to the file's length.
+    async fn load_bytes_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        use io::Read as _;
+
+        let mut file = self.open_sync(path).await?;
+        io::copy(&mut (&mut file).take(range.start), &mut io::sink())?;
+        let mut bytes = Vec::new();
+        file.take(range.end.saturating_sub(range.start))
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
     async fn atomic_write(&self, path: PathBuf, text: String) -> Result<()>;
     async fn save(&self, path: &Path, text: &Rope, line_ending: LineEnding) -> Result<()>;
     async fn write(&self, path: &Path, content: &[u8]) -> Result<()>;
+    /// Writes a file with elevated privileges, prompting the user to authenticate, for files
+    /// that the current user isn't allowed to write.
+    async fn write_elevated(&self, path: &Path, _content: &[u8]) -> Result<()> {
+        anyhow::bail!("saving {path:?} with elevated privileges is not supported on this platform")
+    }
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf>;
     async fn is_file(&self, path: &Path) -> bool;
     async fn is_dir(&self, path: &Path) -> bool;
@@ -212,6 +234,7 @@ pub struct Metadata {
     pub len: u64,
     pub is_fifo: bool,
     pub is_executable: bool,
+    pub is_read_only: bool,
 }
 
 /// Filesystem modification time. The purpose of this newtype is to discourage use of operations
@@ -767,6 +790,24 @@ impl Fs for RealFs {
         Ok(bytes)
     }
 
+    async fn load_bytes_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        use io::{Read as _, Seek as _};
+
+        let path = path.to_path_buf();
+        let bytes = self
+            .executor
+            .spawn(async move {
+                let mut file = std::fs::File::open(path)?;
+                file.seek(io::SeekFrom::Start(range.start))?;
+                let mut bytes = Vec::new();
+                file.take(range.end.saturating_sub(range.start))
+                    .read_to_end(&mut bytes)?;
+                io::Result::Ok(bytes)
+            })
+            .await?;
+        Ok(bytes)
+    }
+
     #[cfg(not(target_os = "windows"))]
     async fn atomic_write(&self, path: PathBuf, data: String) -> Result<()> {
         smol::unblock(move || {
@@ -841,6 +882,49 @@ impl Fs for RealFs {
             .await
     }
 
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "macos"))]
+    async fn write_elevated(&self, path: &Path, content: &[u8]) -> Result<()> {
+        // Write the contents to a temporary file as the current user, then copy it over the
+        // destination as an administrator. Copying onto the existing file keeps its owner and
+        // permissions.
+        let temp_file = tempfile::NamedTempFile::new_in(paths::temp_dir())?;
+        smol::fs::write(temp_file.path(), content).await?;
+
+        #[cfg(target_os = "macos")]
+        let output = {
+            fn quoted(path: &Path) -> String {
+                let path = path.to_string_lossy();
+                format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            let script = format!(
+                "do shell script \"cp \" & quoted form of {} & \" \" & quoted form of {} \
+                 with administrator privileges",
+                quoted(temp_file.path()),
+                quoted(path),
+            );
+            new_smol_command("osascript")
+                .args(["-e", &script])
+                .output()
+                .await?
+        };
+
+        #[cfg(not(target_os = "macos"))]
+        let output = new_smol_command("pkexec")
+            .arg("cp")
+            .arg("--")
+            .arg(temp_file.path())
+            .arg(path)
+            .output()
+            .await?;
+
+        anyhow::ensure!(
+            output.status.success(),
+            "failed to save {path:?} with elevated privileges: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        Ok(())
+    }
+
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
         let path = path.to_owned();
         self.executor
@@ -923,9 +1007,14 @@ impl Fs for RealFs {
         let is_fifo = metadata.file_type().is_fifo();
 
         let path_buf = path.to_path_buf();
-        let is_executable = self
+        let is_dir = metadata.file_type().is_dir();
+        let permissions = metadata.permissions();
+        let (is_executable, is_read_only) = self
             .executor
-            .spawn(async move { path_buf.is_executable() })
+            .spawn(async move {
+                let is_read_only = !is_dir && is_read_only(&path_buf, &permissions);
+                (path_buf.is_executable(), is_read_only)
+            })
             .await;
 
         Ok(Some(Metadata {
@@ -933,9 +1022,10 @@ impl Fs for RealFs {
             mtime: MTime(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)),
             len: metadata.len(),
             is_symlink,
-            is_dir: metadata.file_type().is_dir(),
+            is_dir,
             is_fifo,
             is_executable,
+            is_read_only,
         }))
     }
 
@@ -1033,7 +1123,16 @@ impl Fs for RealFs {
 
         let (tx, rx) = smol::channel::unbounded();
         let pending_paths: Arc<Mutex<Vec<PathEvent>>> = Default::default();
-        let watcher = Arc::new(fs_watcher::FsWatcher::new(tx, pending_paths.clone()));
+        #[cfg(target_os = "windows")]
+        let watcher: Arc<dyn Watcher> = match util::paths::WslPath::from_path(path) {
+            Some(wsl_path) if wsl_watcher::WslWatcher::is_available(&wsl_path).await => {
+                Arc::new(wsl_watcher::WslWatcher::new(tx, pending_paths.clone()))
+            }
+            _ => Arc::new(fs_watcher::FsWatcher::new(tx, pending_paths.clone())),
+        };
+        #[cfg(not(target_os = "windows"))]
+        let watcher: Arc<dyn Watcher> =
+            Arc::new(fs_watcher::FsWatcher::new(tx, pending_paths.clone()));
 
         // If the path doesn't exist yet (e.g. settings.json), watch the parent dir to learn when it's created.
         if let Err(e) = watcher.add(path)
@@ -1224,11 +1323,14 @@ struct FakeFsState {
     next_mtime: SystemTime,
     git_event_tx: smol::channel::Sender<PathBuf>,
     event_txs: Vec<(PathBuf, smol::channel::Sender<Vec<PathEvent>>)>,
+    /// The directories that watchers were asked to watch individually.
+    watched_dirs: std::collections::BTreeSet<PathBuf>,
     events_paused: bool,
     buffered_events: Vec<PathEvent>,
     metadata_call_count: usize,
     read_dir_call_count: usize,
     path_write_counts: std::collections::HashMap<PathBuf, usize>,
+    read_only_paths: std::collections::HashSet<PathBuf>,
     moves: std::collections::HashMap<u64, PathBuf>,
     job_event_subscribers: Arc<Mutex<Vec<JobEventSender>>>,
 }
@@ -1509,11 +1611,13 @@ impl FakeFs {
                 next_mtime: UNIX_EPOCH + Self::SYSTEMTIME_INTERVAL,
                 next_inode: 1,
                 event_txs: Default::default(),
+                watched_dirs: Default::default(),
                 buffered_events: Vec::new(),
                 events_paused: false,
                 read_dir_call_count: 0,
                 metadata_call_count: 0,
                 path_write_counts: Default::default(),
+                read_only_paths: Default::default(),
                 moves: Default::default(),
                 job_event_subscribers: Arc::new(Mutex::new(Vec::new())),
             })),
@@ -1666,6 +1770,18 @@ impl FakeFs {
         entry.file_content(&path).cloned()
     }
 
+    /// Marks a file as read-only, so that writing it fails unless it's written with elevated
+    /// privileges.
+    pub fn set_read_only(&self, path: impl AsRef<Path>, read_only: bool) {
+        let path = normalize_path(path.as_ref());
+        let mut state = self.state.lock();
+        if read_only {
+            state.read_only_paths.insert(path);
+        } else {
+            state.read_only_paths.remove(&path);
+        }
+    }
+
     pub fn pause_events(&self) {
         self.state.lock().events_paused = true;
     }
@@ -2171,6 +2287,12 @@ impl FakeFs {
         self.state.lock().metadata_call_count
     }
 
+    /// The directories that are watched individually, as opposed to recursively along with
+    /// the watched root.
+    pub fn watched_dirs(&self) -> Vec<PathBuf> {
+        self.state.lock().watched_dirs.iter().cloned().collect()
+    }
+
     /// How many write operations have been issued for a specific path.
     pub fn write_count_for_path(&self, path: impl AsRef<Path>) -> usize {
         let path = path.as_ref().to_path_buf();
@@ -2229,19 +2351,18 @@ struct FakeWatcher {
 #[cfg(any(test, feature = "test-support"))]
 impl Watcher for FakeWatcher {
     fn add(&self, path: &Path) -> Result<()> {
+        let mut fs_state = self.fs_state.try_lock().unwrap();
+        fs_state.watched_dirs.insert(path.to_owned());
         if path.starts_with(&self.original_path) {
             return Ok(());
         }
-        self.fs_state
-            .try_lock()
-            .unwrap()
-            .event_txs
-            .push((path.to_owned(), self.tx.clone()));
+        fs_state.event_txs.push((path.to_owned(), self.tx.clone()));
         self.prefixes.lock().push(path.to_owned());
         Ok(())
     }
 
-    fn remove(&self, _: &Path) -> Result<()> {
+    fn remove(&self, path: &Path) -> Result<()> {
+        self.fs_state.try_lock().unwrap().watched_dirs.remove(path);
         Ok(())
     }
 }
@@ -2585,6 +2706,10 @@ impl Fs for FakeFs {
         self.simulate_random_delay().await;
         let path = normalize_path(path);
         let content = text::chunks_with_line_ending(text, line_ending).collect::<String>();
+        anyhow::ensure!(
+            !self.state.lock().read_only_paths.contains(&path),
+            "permission denied writing {path:?}"
+        );
         if let Some(path) = path.parent() {
             self.create_dir(path).await?;
         }
@@ -2595,6 +2720,10 @@ impl Fs for FakeFs {
     async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
         self.simulate_random_delay().await;
         let path = normalize_path(path);
+        anyhow::ensure!(
+            !self.state.lock().read_only_paths.contains(&path),
+            "permission denied writing {path:?}"
+        );
         if let Some(path) = path.parent() {
             self.create_dir(path).await?;
         }
@@ -2602,6 +2731,13 @@ impl Fs for FakeFs {
         Ok(())
     }
 
+    async fn write_elevated(&self, path: &Path, content: &[u8]) -> Result<()> {
+        self.simulate_random_delay().await;
+        let path = normalize_path(path);
+        self.write_file_internal(path, content.to_vec(), false)?;
+        Ok(())
+    }
+
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
         let path = normalize_path(path);
         self.simulate_random_delay().await;
@@ -2634,6 +2770,7 @@ impl Fs for FakeFs {
         let path = normalize_path(path);
         let mut state = self.state.lock();
         state.metadata_call_count += 1;
+        let is_read_only = state.read_only_paths.contains(&path);
         if let Some((mut entry, _)) = state.try_entry(&path, false) {
             let is_symlink = entry.is_symlink();
             if is_symlink {
@@ -2655,6 +2792,7 @@ impl Fs for FakeFs {
                     is_symlink,
                     is_fifo: false,
                     is_executable: false,
+                    is_read_only,
                 },
                 FakeFsEntry::Dir {
                     inode, mtime, len, ..
@@ -2666,6 +2804,7 @@ impl Fs for FakeFs {
                     is_symlink,
                     is_fifo: false,
                     is_executable: false,
+                    is_read_only,
                 },
                 FakeFsEntry::Symlink { .. } => unreachable!(),
             }))
@@ -2802,6 +2941,24 @@ impl Fs for FakeFs {
     }
 }
 
+/// Whether the current user isn't allowed to write the file. Unlike [`std::fs::Permissions::readonly`],
+/// this takes the owner and group of the file into account, along with read-only mounts.
+#[cfg(unix)]
+fn is_read_only(path: &Path, _: &std::fs::Permissions) -> bool {
+    use std::os::unix::ffi::OsStrExt as _;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a valid, NUL-terminated string.
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) != 0 }
+}
+
+#[cfg(not(unix))]
+fn is_read_only(_: &Path, permissions: &std::fs::Permissions) -> bool {
+    permissions.readonly()
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = path.components().peekable();
     let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
@@ -0,0 +1,558 @@
+//! A file system that only exists in memory, for worktrees that aren't backed by the disk.
+
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context as _, Result, anyhow, bail};
+use async_tar::Archive;
+use futures::{AsyncRead, AsyncReadExt as _, Stream, StreamExt as _};
+use git::repository::GitRepository;
+use parking_lot::Mutex;
+use rope::Rope;
+use text::LineEnding;
+
+use crate::{
+    CopyOptions, CreateOptions, FileHandle, Fs, JobEventReceiver, MTime, Metadata, PathEvent,
+    PathEventKind, RemoveOptions, RenameOptions, Watcher, normalize_path,
+};
+
+/// An [`Fs`] that keeps its files in memory. Paths outside of it don't exist.
+pub struct MemoryFs {
+    state: Mutex<MemoryFsState>,
+}
+
+#[derive(Default)]
+struct MemoryFsState {
+    entries: BTreeMap<PathBuf, MemoryEntry>,
+    next_inode: u64,
+    event_txs: Vec<smol::channel::Sender<Vec<PathEvent>>>,
+}
+
+#[derive(Clone)]
+struct MemoryEntry {
+    inode: u64,
+    mtime: MTime,
+    content: Option<Vec<u8>>,
+}
+
+impl MemoryEntry {
+    fn is_dir(&self) -> bool {
+        self.content.is_none()
+    }
+}
+
+#[derive(Debug)]
+struct MemoryHandle {
+    path: PathBuf,
+}
+
+impl FileHandle for MemoryHandle {
+    fn current_path(&self, _: &Arc<dyn Fs>) -> Result<PathBuf> {
+        Ok(self.path.clone())
+    }
+}
+
+struct MemoryWatcher;
+
+impl Watcher for MemoryWatcher {
+    fn add(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn not_found(path: &Path) -> anyhow::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{path:?} does not exist")).into()
+}
+
+impl MemoryFs {
+    /// Creates a file system containing only the empty directory at `root` and its ancestors.
+    pub fn new(root: &Path) -> Arc<Self> {
+        let this = Self {
+            state: Default::default(),
+        };
+        this.state.lock().create_dir_all(&normalize_path(root));
+        Arc::new(this)
+    }
+}
+
+impl MemoryFsState {
+    fn insert(&mut self, path: PathBuf, content: Option<Vec<u8>>) {
+        self.next_inode += 1;
+        let entry = MemoryEntry {
+            inode: self.next_inode,
+            mtime: MTime(SystemTime::now()),
+            content,
+        };
+        self.entries.insert(path, entry);
+    }
+
+    fn create_dir_all(&mut self, path: &Path) -> Vec<PathBuf> {
+        let mut created = Vec::new();
+        for ancestor in path.ancestors() {
+            if self.entries.contains_key(ancestor) {
+                break;
+            }
+            created.push(ancestor.to_path_buf());
+        }
+        created.reverse();
+        for path in &created {
+            self.insert(path.clone(), None);
+        }
+        created
+    }
+
+    fn ensure_parent_dir(&self, path: &Path) -> Result<()> {
+        let parent = path
+            .parent()
+            .with_context(|| format!("{path:?} has no parent"))?;
+        match self.entries.get(parent) {
+            Some(entry) if entry.is_dir() => Ok(()),
+            Some(_) => bail!("{parent:?} is not a directory"),
+            None => Err(not_found(parent)),
+        }
+    }
+
+    fn write_file(&mut self, path: &Path, content: Vec<u8>) -> Result<()> {
+        let kind = match self.entries.get_mut(path) {
+            Some(entry) if entry.is_dir() => bail!("{path:?} is a directory"),
+            Some(entry) => {
+                entry.content = Some(content);
+                entry.mtime = MTime(SystemTime::now());
+                PathEventKind::Changed
+            }
+            None => {
+                self.ensure_parent_dir(path)?;
+                self.insert(path.to_path_buf(), Some(content));
+                PathEventKind::Created
+            }
+        };
+        self.emit_events([(path.to_path_buf(), kind)]);
+        Ok(())
+    }
+
+    /// The paths of the entry at `path` and of all of its descendants.
+    fn subtree(&self, path: &Path) -> Vec<PathBuf> {
+        self.entries
+            .range(path.to_path_buf()..)
+            .map(|(entry_path, _)| entry_path)
+            .take_while(|entry_path| entry_path.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    fn emit_events(&mut self, events: impl IntoIterator<Item = (PathBuf, PathEventKind)>) {
+        let events = events
+            .into_iter()
+            .map(|(path, kind)| PathEvent {
+                path,
+                kind: Some(kind),
+            })
+            .collect::<Vec<_>>();
+        if events.is_empty() {
+            return;
+        }
+        self.event_txs
+            .retain(|tx| tx.try_send(events.clone()).is_ok());
+    }
+}
+
+#[async_trait::async_trait]
+impl Fs for MemoryFs {
+    async fn create_dir(&self, path: &Path) -> Result<()> {
+        let mut state = self.state.lock();
+        let path = normalize_path(path);
+        if state
+            .entries
+            .get(&path)
+            .is_some_and(|entry| !entry.is_dir())
+        {
+            bail!("{path:?} is a file");
+        }
+        let created = state.create_dir_all(&path);
+        state.emit_events(
+            created
+                .into_iter()
+                .map(|path| (path, PathEventKind::Created)),
+        );
+        Ok(())
+    }
+
+    async fn create_symlink(&self, path: &Path, _: PathBuf) -> Result<()> {
+        bail!("cannot create symlink {path:?} in memory")
+    }
+
+    async fn create_file(&self, path: &Path, options: CreateOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        let path = normalize_path(path);
+        if state.entries.contains_key(&path) {
+            if options.ignore_if_exists {
+                return Ok(());
+            } else if !options.overwrite {
+                bail!("{path:?} already exists");
+            }
+        }
+        state.write_file(&path, Vec::new())
+    }
+
+    async fn create_file_with(
+        &self,
+        path: &Path,
+        mut content: Pin<&mut (dyn AsyncRead + Send)>,
+    ) -> Result<()> {
+        let mut bytes = Vec::new();
+        content.read_to_end(&mut bytes).await?;
+        self.write(path, &bytes).await
+    }
+
+    async fn extract_tar_file(
+        &self,
+        path: &Path,
+        content: Archive<Pin<&mut (dyn AsyncRead + Send)>>,
+    ) -> Result<()> {
+        let mut entries = content.entries()?;
+        while let Some(entry) = entries.next().await {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_file() {
+                let path = path.join(entry.path()?.as_ref());
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes).await?;
+                if let Some(parent) = path.parent() {
+                    self.create_dir(parent).await?;
+                }
+                self.write(&path, &bytes).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn copy_file(&self, source: &Path, target: &Path, options: CopyOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        let (source, target) = (normalize_path(source), normalize_path(target));
+        let content = state
+            .entries
+            .get(&source)
+            .ok_or_else(|| not_found(&source))?
+            .content
+            .clone()
+            .with_context(|| format!("{source:?} is a directory"))?;
+        if state.entries.contains_key(&target) {
+            if options.ignore_if_exists {
+                return Ok(());
+            } else if !options.overwrite {
+                bail!("{target:?} already exists");
+            }
+        }
+        state.write_file(&target, content)
+    }
+
+    async fn rename(&self, source: &Path, target: &Path, options: RenameOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        let (source, target) = (normalize_path(source), normalize_path(target));
+        if !state.entries.contains_key(&source) {
+            return Err(not_found(&source));
+        }
+        if target.starts_with(&source) && target != source {
+            bail!("cannot move {source:?} into itself");
+        }
+        if state.entries.contains_key(&target) && source != target {
+            if options.ignore_if_exists {
+                return Ok(());
+            } else if !options.overwrite {
+                bail!("{target:?} already exists");
+            }
+            for path in state.subtree(&target) {
+                state.entries.remove(&path);
+            }
+        }
+        if options.create_parents
+            && let Some(parent) = target.parent()
+        {
+            state.create_dir_all(parent);
+        }
+        state.ensure_parent_dir(&target)?;
+
+        let mut moved = Vec::new();
+        for path in state.subtree(&source) {
+            let entry = state.entries.remove(&path).unwrap();
+            let new_path = target.join(path.strip_prefix(&source).unwrap());
+            moved.push((new_path, entry));
+        }
+        state.entries.extend(moved);
+        state.emit_events([
+            (source, PathEventKind::Removed),
+            (target, PathEventKind::Created),
+        ]);
+        Ok(())
+    }
+
+    async fn remove_dir(&self, path: &Path, options: RemoveOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        let path = normalize_path(path);
+        match state.entries.get(&path) {
+            None if options.ignore_if_not_exists => return Ok(()),
+            None => return Err(not_found(&path)),
+            Some(entry) if !entry.is_dir() => bail!("{path:?} is not a directory"),
+            Some(_) => {}
+        }
+        let subtree = state.subtree(&path);
+        if subtree.len() > 1 && !options.recursive {
+            bail!("{path:?} is not empty");
+        }
+        for path in &subtree {
+            state.entries.remove(path);
+        }
+        state.emit_events([(path, PathEventKind::Removed)]);
+        Ok(())
+    }
+
+    async fn remove_file(&self, path: &Path, options: RemoveOptions) -> Result<()> {
+        let mut state = self.state.lock();
+        let path = normalize_path(path);
+        match state.entries.get(&path) {
+            None if options.ignore_if_not_exists => return Ok(()),
+            None => return Err(not_found(&path)),
+            Some(entry) if entry.is_dir() => bail!("{path:?} is a directory"),
+            Some(_) => {}
+        }
+        state.entries.remove(&path);
+        state.emit_events([(path, PathEventKind::Removed)]);
+        Ok(())
+    }
+
+    async fn open_handle(&self, path: &Path) -> Result<Arc<dyn FileHandle>> {
+        let path = normalize_path(path);
+        if !self.state.lock().entries.contains_key(&path) {
+            return Err(not_found(&path));
+        }
+        Ok(Arc::new(MemoryHandle { path }))
+    }
+
+    async fn open_sync(&self, path: &Path) -> Result<Box<dyn io::Read + Send + Sync>> {
+        Ok(Box::new(io::Cursor::new(self.load_bytes(path).await?)))
+    }
+
+    async fn load_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        let path = normalize_path(path);
+        self.state
+            .lock()
+            .entries
+            .get(&path)
+            .ok_or_else(|| not_found(&path))?
+            .content
+            .clone()
+            .with_context(|| format!("{path:?} is a directory"))
+    }
+
+    async fn atomic_write(&self, path: PathBuf, text: String) -> Result<()> {
+        self.write(&path, text.as_bytes()).await
+    }
+
+    async fn save(&self, path: &Path, text: &Rope, line_ending: LineEnding) -> Result<()> {
+        let content = text::chunks_with_line_ending(text, line_ending).collect::<String>();
+        if let Some(parent) = path.parent() {
+            self.create_dir(parent).await?;
+        }
+        self.write(path, content.as_bytes()).await
+    }
+
+    async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        self.state
+            .lock()
+            .write_file(&normalize_path(path), content.to_vec())
+    }
+
+    async fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        let path = normalize_path(path);
+        if self.state.lock().entries.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(not_found(&path))
+        }
+    }
+
+    async fn is_file(&self, path: &Path) -> bool {
+        self.state
+            .lock()
+            .entries
+            .get(&normalize_path(path))
+            .is_some_and(|entry| !entry.is_dir())
+    }
+
+    async fn is_dir(&self, path: &Path) -> bool {
+        self.state
+            .lock()
+            .entries
+            .get(&normalize_path(path))
+            .is_some_and(|entry| entry.is_dir())
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<Option<Metadata>> {
+        Ok(self
+            .state
+            .lock()
+            .entries
+            .get(&normalize_path(path))
+            .map(|entry| Metadata {
+                inode: entry.inode,
+                mtime: entry.mtime,
+                is_symlink: false,
+                is_dir: entry.is_dir(),
+                len: entry
+                    .content
+                    .as_ref()
+                    .map_or(0, |content| content.len() as u64),
+                is_fifo: false,
+                is_executable: false,
+                is_read_only: false,
+            }))
+    }
+
+    async fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        Err(anyhow!("{path:?} is not a symlink"))
+    }
+
+    async fn read_dir(
+        &self,
+        path: &Path,
+    ) -> Result<Pin<Box<dyn Send + Stream<Item = Result<PathBuf>>>>> {
+        let state = self.state.lock();
+        let path = normalize_path(path);
+        match state.entries.get(&path) {
+            Some(entry) if entry.is_dir() => {}
+            Some(_) => bail!("{path:?} is not a directory"),
+            None => return Err(not_found(&path)),
+        }
+        let children = state
+            .subtree(&path)
+            .into_iter()
+            .filter(|child| child.parent() == Some(path.as_path()))
+            .map(Ok)
+            .collect::<Vec<_>>();
+        Ok(Box::pin(futures::stream::iter(children)))
+    }
+
+    async fn watch(
+        &self,
+        _: &Path,
+        _: Duration,
+    ) -> (
+        Pin<Box<dyn Send + Stream<Item = Vec<PathEvent>>>>,
+        Arc<dyn Watcher>,
+    ) {
+        let (tx, rx) = smol::channel::unbounded();
+        self.state.lock().event_txs.push(tx);
+        (Box::pin(rx), Arc::new(MemoryWatcher))
+    }
+
+    fn open_repo(
+        &self,
+        _abs_dot_git: &Path,
+        _system_git_binary_path: Option<&Path>,
+    ) -> Option<Arc<dyn GitRepository>> {
+        None
+    }
+
+    async fn git_init(&self, abs_work_directory: &Path, _: String) -> Result<()> {
+        bail!("cannot initialize a git repository in memory at {abs_work_directory:?}")
+    }
+
+    async fn git_clone(&self, _: &str, abs_work_directory: &Path) -> Result<()> {
+        bail!("cannot clone a git repository in memory at {abs_work_directory:?}")
+    }
+
+    fn is_fake(&self) -> bool {
+        false
+    }
+
+    async fn is_case_sensitive(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn subscribe_to_jobs(&self) -> JobEventReceiver {
+        futures::channel::mpsc::unbounded().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::path;
+
+    #[gpui::test]
+    async fn test_memory_fs() {
+        let fs = MemoryFs::new(Path::new(path!("/scratch/sketch")));
+        let root = Path::new(path!("/scratch/sketch"));
+        assert!(fs.is_dir(root).await);
+
+        fs.create_dir(&root.join("src/bin")).await.unwrap();
+        fs.write(&root.join("src/main.rs"), b"fn main() {}")
+            .await
+            .unwrap();
+        assert!(
+            fs.create_file(&root.join("missing/lib.rs"), Default::default())
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            fs.load(&root.join("src/main.rs")).await.unwrap(),
+            "fn main() {}"
+        );
+
+        fs.rename(
+            &root.join("src"),
+            &root.join("crates/app/src"),
+            RenameOptions {
+                create_parents: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!fs.is_dir(&root.join("src")).await);
+        assert_eq!(
+            fs.load(&root.join("crates/app/src/main.rs")).await.unwrap(),
+            "fn main() {}"
+        );
+        let children = fs
+            .read_dir(&root.join("crates/app/src"))
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            children,
+            [
+                root.join("crates/app/src/bin"),
+                root.join("crates/app/src/main.rs")
+            ]
+        );
+
+        assert!(
+            fs.remove_dir(&root.join("crates"), Default::default())
+                .await
+                .is_err()
+        );
+        fs.remove_dir(
+            &root.join("crates"),
+            RemoveOptions {
+                recursive: true,
+                ignore_if_not_exists: false,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!fs.is_file(&root.join("crates/app/src/main.rs")).await);
+        assert!(fs.metadata(&root.join("crates")).await.unwrap().is_none());
+    }
+}
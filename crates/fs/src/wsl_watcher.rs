@@ -0,0 +1,175 @@
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use parking_lot::Mutex;
+use std::{
+    io::{BufRead as _, BufReader},
+    path::{Path, PathBuf},
+    process::{Child, Stdio},
+    sync::Arc,
+};
+use util::{
+    ResultExt as _,
+    command::{new_smol_command, new_std_command},
+    paths::WslPath,
+};
+
+use crate::{PathEvent, PathEventKind, Watcher};
+
+/// Watches paths inside of a WSL distro by running `inotifywait` on the Linux side.
+///
+/// File system notifications are not delivered over the `\\wsl$` 9P share, so the regular
+/// Windows watcher never sees changes made from inside of the distro.
+pub struct WslWatcher {
+    tx: smol::channel::Sender<()>,
+    pending_path_events: Arc<Mutex<Vec<PathEvent>>>,
+    processes: Mutex<HashMap<PathBuf, Child>>,
+}
+
+impl WslWatcher {
+    pub fn new(
+        tx: smol::channel::Sender<()>,
+        pending_path_events: Arc<Mutex<Vec<PathEvent>>>,
+    ) -> Self {
+        Self {
+            tx,
+            pending_path_events,
+            processes: Default::default(),
+        }
+    }
+
+    /// Returns whether `inotifywait` can be used inside of the given path's distro.
+    pub async fn is_available(wsl_path: &WslPath) -> bool {
+        new_smol_command("wsl.exe")
+            .args(["--distribution", &wsl_path.distro, "--exec", "which"])
+            .arg("inotifywait")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .is_ok_and(|status| status.success())
+    }
+}
+
+impl Drop for WslWatcher {
+    fn drop(&mut self) {
+        for (_, mut process) in self.processes.lock().drain() {
+            process.kill().log_err();
+        }
+    }
+}
+
+impl Watcher for WslWatcher {
+    fn add(&self, path: &Path) -> Result<()> {
+        let wsl_path = WslPath::from_path(path).context("path is not inside of WSL")?;
+        if self.processes.lock().contains_key(path) {
+            return Ok(());
+        }
+
+        let mut process = new_std_command("wsl.exe")
+            .args(["--distribution", &wsl_path.distro, "--exec"])
+            .args(["inotifywait", "--monitor", "--recursive", "--quiet"])
+            .args(["--event", "create,delete,modify,move,attrib"])
+            .args(["--format", "%e|%w%f"])
+            .arg(&wsl_path.path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("spawning inotifywait inside of WSL")?;
+        let stdout = process
+            .stdout
+            .take()
+            .context("missing inotifywait stdout")?;
+
+        let tx = self.tx.clone();
+        let pending_paths = self.pending_path_events.clone();
+        let root = path.to_path_buf();
+        let linux_root = wsl_path.path.to_string_lossy().into_owned();
+        std::thread::Builder::new()
+            .name("WslWatcher".to_string())
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    let Some(event) = parse_inotify_line(&root, &linux_root, &line) else {
+                        continue;
+                    };
+                    log::trace!("wsl watcher received event: {event:?}");
+                    let mut pending_paths = pending_paths.lock();
+                    if pending_paths.is_empty() {
+                        tx.try_send(()).ok();
+                    }
+                    util::extend_sorted(&mut *pending_paths, [event], usize::MAX, |a, b| {
+                        a.path.cmp(&b.path)
+                    });
+                }
+            })?;
+
+        self.processes.lock().insert(path.to_path_buf(), process);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        if let Some(mut process) = self.processes.lock().remove(path) {
+            process.kill()?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an `inotifywait` event line, mapping the Linux path back onto the watched Windows
+/// path so that events keep the same `\\wsl$` or `\\wsl.localhost` prefix as the worktree.
+fn parse_inotify_line(root: &Path, linux_root: &str, line: &str) -> Option<PathEvent> {
+    let (events, linux_path) = line.split_once('|')?;
+    let relative_path = linux_path.strip_prefix(linux_root)?;
+    if !relative_path.is_empty() && !relative_path.starts_with('/') {
+        return None;
+    }
+    let kind = events.split(',').find_map(|event| match event {
+        "CREATE" | "MOVED_TO" => Some(PathEventKind::Created),
+        "DELETE" | "MOVED_FROM" => Some(PathEventKind::Removed),
+        "MODIFY" | "ATTRIB" => Some(PathEventKind::Changed),
+        _ => None,
+    });
+    let mut path = root.to_path_buf();
+    path.extend(
+        relative_path
+            .split('/')
+            .filter(|component| !component.is_empty()),
+    );
+    Some(PathEvent { path, kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inotify_line() {
+        let root = Path::new(r"\\wsl$\Ubuntu\home\user\project");
+        let linux_root = "/home/user/project";
+        assert_eq!(
+            parse_inotify_line(root, linux_root, "CREATE,ISDIR|/home/user/project/src"),
+            Some(PathEvent {
+                path: PathBuf::from(r"\\wsl$\Ubuntu\home\user\project\src"),
+                kind: Some(PathEventKind::Created),
+            })
+        );
+        assert_eq!(
+            parse_inotify_line(root, linux_root, "MOVED_FROM|/home/user/project/a.txt"),
+            Some(PathEvent {
+                path: PathBuf::from(r"\\wsl$\Ubuntu\home\user\project\a.txt"),
+                kind: Some(PathEventKind::Removed),
+            })
+        );
+        assert_eq!(parse_inotify_line(root, linux_root, "garbage"), None);
+        assert_eq!(
+            parse_inotify_line(root, linux_root, "MODIFY|/etc/hosts"),
+            None
+        );
+        assert_eq!(
+            parse_inotify_line(root, linux_root, "MODIFY|/home/user/project2/a.txt"),
+            None
+        );
+    }
+}
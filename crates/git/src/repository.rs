@@ -28,7 +28,7 @@ use std::{
 use sum_tree::MapSeekTarget;
 use thiserror::Error;
 use util::command::new_smol_command;
-use util::paths::PathStyle;
+use util::paths::{PathStyle, WslPath};
 use util::rel_path::RelPath;
 use util::{ResultExt, paths};
 use uuid::Uuid;
@@ -2559,6 +2559,10 @@ impl GitBinary {
     where
         S: AsRef<OsStr>,
     {
+        if let Some(wsl_path) = WslPath::from_path(&self.working_directory) {
+            return self.build_wsl_command(wsl_path, args);
+        }
+
         let mut command = new_smol_command(&self.git_binary_path);
         command.current_dir(&self.working_directory);
         command.args(args);
@@ -2568,6 +2572,34 @@ impl GitBinary {
         command.envs(&self.envs);
         command
     }
+
+    /// Runs git inside of the WSL distro owning the working directory, since running a Windows
+    /// git over the `\\wsl$` 9P share is slow and reports wrong file modes.
+    fn build_wsl_command<S>(
+        &self,
+        wsl_path: WslPath,
+        args: impl IntoIterator<Item = S>,
+    ) -> smol::process::Command
+    where
+        S: AsRef<OsStr>,
+    {
+        let mut command = new_smol_command("wsl.exe");
+        command.args(wsl_path.exec_args());
+        command.arg("env");
+        if let Some(index_file_path) = self.index_file_path.as_ref()
+            && let Some(index_file_path) = WslPath::from_path(index_file_path)
+        {
+            let mut assignment = OsString::from("GIT_INDEX_FILE=");
+            assignment.push(&index_file_path.path);
+            command.arg(assignment);
+        }
+        for (key, value) in &self.envs {
+            command.arg(format!("{key}={value}"));
+        }
+        command.arg("git");
+        command.args(args);
+        command
+    }
 }
 
 #[derive(Error, Debug)]
@@ -754,6 +754,75 @@ impl language::File for GitBlob {
     }
 }
 
+/// Opens a read-only editor with the file at `abs_path` as it was at `revision`.
+pub fn open_file_at_revision(
+    revision: String,
+    abs_path: PathBuf,
+    workspace: &Workspace,
+    window: &mut Window,
+    cx: &mut App,
+) -> Task<Result<()>> {
+    let project = workspace.project().clone();
+    let workspace = workspace.weak_handle();
+    window.spawn(cx, async move |cx| {
+        let (worktree, _) = project
+            .update(cx, |project, cx| {
+                project.find_or_create_worktree(&abs_path, false, cx)
+            })?
+            .await?;
+        // The repositories of a worktree are found while scanning it.
+        let scan_complete = worktree.read_with(cx, |worktree, _| {
+            worktree.as_local().map(|worktree| worktree.scan_complete())
+        })?;
+        if let Some(scan_complete) = scan_complete {
+            scan_complete.await;
+        }
+
+        let (repository, repo_path, worktree_id, language_registry) =
+            project.read_with(cx, |project, cx| {
+                let project_path = project
+                    .find_project_path(&abs_path, cx)
+                    .with_context(|| format!("{abs_path:?} isn't in the project"))?;
+                let (repository, repo_path) = project
+                    .git_store()
+                    .read(cx)
+                    .repository_and_path_for_project_path(&project_path, cx)
+                    .with_context(|| format!("{abs_path:?} isn't in a git repository"))?;
+                anyhow::Ok((
+                    repository,
+                    repo_path,
+                    project_path.worktree_id,
+                    project.languages().clone(),
+                ))
+            })??;
+        let text = repository
+            .update(cx, |repository, _| {
+                repository.load_text_at_revision(revision.clone(), repo_path.clone())
+            })?
+            .await??
+            .with_context(|| format!("{abs_path:?} doesn't exist at revision {revision}"))?;
+
+        let file_name = repo_path
+            .file_name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| repo_path.display(PathStyle::local()).to_string());
+        let file = Arc::new(GitBlob {
+            path: repo_path,
+            worktree_id,
+            is_deleted: false,
+            display_name: format!("{file_name} @ {revision}"),
+        }) as Arc<dyn File>;
+        let buffer = build_buffer(text, file, &language_registry, cx).await?;
+        buffer.update(cx, |buffer, cx| {
+            buffer.set_capability(Capability::ReadOnly, cx)
+        })?;
+        workspace.update_in(cx, |workspace, window, cx| {
+            let editor = cx.new(|cx| Editor::for_buffer(buffer, Some(project), window, cx));
+            workspace.add_item_to_active_pane(Box::new(editor), None, true, window, cx);
+        })
+    })
+}
+
 async fn build_buffer(
     mut text: String,
     blob: Arc<dyn File>,
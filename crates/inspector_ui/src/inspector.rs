@@ -33,6 +33,7 @@ pub fn init(app_state: Arc<AppState>, cx: &mut App) {
         app_state.fs.clone(),
         None,
         false,
+        false,
         cx,
     );
 
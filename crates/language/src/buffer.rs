@@ -13,7 +13,7 @@ use crate::{
         SyntaxMapMatch, SyntaxMapMatches, SyntaxSnapshot, ToTreeSitterPoint,
     },
     task_context::RunnableRange,
-    text_diff::text_diff,
+    text_diff::{text_diff, three_way_merge},
     unified_diff,
 };
 pub use crate::{
@@ -46,6 +46,7 @@ use std::{
     cell::Cell,
     cmp::{self, Ordering, Reverse},
     collections::{BTreeMap, BTreeSet},
+    fmt,
     future::Future,
     iter::{self, Iterator, Peekable},
     mem,
@@ -89,6 +90,35 @@ pub enum Capability {
     ReadOnly,
 }
 
+/// Why edits to a [`Buffer`] are rejected.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BufferLock {
+    /// The buffer is a read-only replica.
+    Replica,
+    /// The buffer's file can't be written by the current user.
+    FileReadOnly,
+    /// The buffer was locked with [`Buffer::set_locked`].
+    Locked,
+}
+
+/// The error of editing a buffer that is read-only or locked.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct BufferLockedError {
+    pub lock: BufferLock,
+}
+
+impl fmt::Display for BufferLockedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lock {
+            BufferLock::Replica => write!(f, "buffer is read-only"),
+            BufferLock::FileReadOnly => write!(f, "file is read-only"),
+            BufferLock::Locked => write!(f, "buffer is locked"),
+        }
+    }
+}
+
+impl std::error::Error for BufferLockedError {}
+
 pub type BufferRow = u32;
 
 /// An in-memory representation of a source code file, including its text,
@@ -107,6 +137,9 @@ pub struct Buffer {
     preview_version: clock::Global,
     transaction_depth: usize,
     was_dirty_before_starting_transaction: Option<bool>,
+    /// For transactions that converted the buffer's line endings, the line ending that undoing
+    /// or redoing the transaction switches the buffer to.
+    line_ending_transactions: HashMap<TransactionId, LineEnding>,
     reload_task: Option<Task<Result<()>>>,
     language: Option<Arc<Language>>,
     autoindent_requests: Vec<Arc<AutoindentRequest>>,
@@ -125,7 +158,14 @@ pub struct Buffer {
     completion_triggers_timestamp: clock::Lamport,
     deferred_ops: OperationQueue<Operation>,
     capability: Capability,
+    /// Whether the buffer's file can't be written by the current user.
+    file_read_only: bool,
+    locked: bool,
     has_conflict: bool,
+    /// The contents of the file when they were last merged into the buffer, in place of the
+    /// contents at the saved version.
+    merge_base: Option<Rope>,
+    merge_conflicts: Vec<MergeConflict>,
     /// Memoize calls to has_changes_since(saved_version).
     /// The contents of a cell are (self.version, has_changes) at the time of a last call.
     has_unsaved_edits: Cell<(clock::Global, bool)>,
@@ -363,6 +403,11 @@ pub enum BufferEvent {
     Reloaded,
     /// The buffer is in need of a reload
     ReloadNeeded,
+    /// The buffer has unsaved changes and its file changed on disk, so the changes on disk need
+    /// to be merged into it.
+    MergeNeeded,
+    /// The buffer's merge conflicts were updated.
+    MergeConflictsChanged,
     /// The buffer's language was changed.
     /// The boolean indicates whether this buffer did not have a language before, but does now.
     LanguageChanged(bool),
@@ -374,6 +419,22 @@ pub enum BufferEvent {
     CapabilityChanged,
 }
 
+/// A region changed both in a buffer and in its file on disk since the buffer was last saved or
+/// reloaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+    /// The region of the buffer, containing the buffer's own changes.
+    pub range: Range<Anchor>,
+    /// The text of the file on disk in place of the region.
+    pub disk_text: Arc<str>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeConflictResolution {
+    KeepBuffer,
+    KeepDisk,
+}
+
 /// The file associated with a buffer.
 pub trait File: Send + Sync + Any {
     /// Returns the [`LocalFile`] associated with this file, if the
@@ -1079,7 +1140,39 @@ impl Buffer {
 
     /// Whether this buffer can only be read.
     pub fn read_only(&self) -> bool {
-        self.capability == Capability::ReadOnly
+        self.lock().is_some()
+    }
+
+    /// Returns why edits to this buffer are rejected, if they are.
+    pub fn lock(&self) -> Option<BufferLock> {
+        if self.capability == Capability::ReadOnly {
+            Some(BufferLock::Replica)
+        } else if self.locked {
+            Some(BufferLock::Locked)
+        } else if self.file_read_only {
+            Some(BufferLock::FileReadOnly)
+        } else {
+            None
+        }
+    }
+
+    /// Locks or unlocks the buffer. Locked buffers reject edits until they're unlocked.
+    pub fn set_locked(&mut self, locked: bool, cx: &mut Context<Self>) {
+        let was_read_only = self.read_only();
+        self.locked = locked;
+        if self.read_only() != was_read_only {
+            cx.emit(BufferEvent::CapabilityChanged);
+        }
+    }
+
+    /// Records whether the buffer's file can't be written by the current user, in which case
+    /// the buffer rejects edits.
+    pub fn set_file_read_only(&mut self, file_read_only: bool, cx: &mut Context<Self>) {
+        let was_read_only = self.read_only();
+        self.file_read_only = file_read_only;
+        if self.read_only() != was_read_only {
+            cx.emit(BufferEvent::CapabilityChanged);
+        }
     }
 
     /// Builds a [`Buffer`] with the given underlying [`TextBuffer`], diff base, [`File`] and [`Capability`].
@@ -1096,6 +1189,7 @@ impl Buffer {
             reload_task: None,
             transaction_depth: 0,
             was_dirty_before_starting_transaction: None,
+            line_ending_transactions: HashMap::default(),
             has_unsaved_edits: Cell::new((buffer.version(), false)),
             text: buffer,
             branch_state: None,
@@ -1117,7 +1211,11 @@ impl Buffer {
             completion_triggers_per_language_server: Default::default(),
             completion_triggers_timestamp: Lamport::MIN,
             deferred_ops: OperationQueue::new(),
+            file_read_only: false,
+            locked: false,
             has_conflict: false,
+            merge_base: None,
+            merge_conflicts: Vec::new(),
             change_bits: Default::default(),
             _subscriptions: Vec::new(),
             encoding: encoding_rs::UTF_8,
@@ -1478,6 +1576,35 @@ impl Buffer {
         );
     }
 
+    /// Converts the buffer to the given line ending in a transaction of its own, so that undoing
+    /// the transaction restores the previous line ending.
+    pub fn convert_line_ending(
+        &mut self,
+        line_ending: LineEnding,
+        cx: &mut Context<Self>,
+    ) -> Option<TransactionId> {
+        let previous_line_ending = self.line_ending();
+        if previous_line_ending == line_ending {
+            return None;
+        }
+        self.finalize_last_transaction();
+        let transaction_id = self.push_empty_transaction(Instant::now());
+        self.finalize_last_transaction();
+        self.line_ending_transactions
+            .insert(transaction_id, previous_line_ending);
+        self.set_line_ending(line_ending, cx);
+        Some(transaction_id)
+    }
+
+    /// Switches the line ending back and forth when a transaction that converted it is undone
+    /// or redone.
+    fn did_undo_or_redo(&mut self, transaction_id: TransactionId, cx: &mut Context<Self>) {
+        if let Some(line_ending) = self.line_ending_transactions.get_mut(&transaction_id) {
+            let line_ending = mem::replace(line_ending, self.text.line_ending());
+            self.set_line_ending(line_ending, cx);
+        }
+    }
+
     /// Assign the buffer a new [`Capability`].
     pub fn set_capability(&mut self, capability: Capability, cx: &mut Context<Self>) {
         if self.capability != capability {
@@ -1486,6 +1613,24 @@ impl Buffer {
         }
     }
 
+    /// Applies the given edits like [`Buffer::edit`], unless the buffer is read-only or locked.
+    pub fn try_edit<I, S, T>(
+        &mut self,
+        edits_iter: I,
+        autoindent_mode: Option<AutoindentMode>,
+        cx: &mut Context<Self>,
+    ) -> Result<Option<clock::Lamport>, BufferLockedError>
+    where
+        I: IntoIterator<Item = (Range<S>, T)>,
+        S: ToOffset,
+        T: Into<Arc<str>>,
+    {
+        if let Some(lock) = self.lock() {
+            return Err(BufferLockedError { lock });
+        }
+        Ok(self.edit(edits_iter, autoindent_mode, cx))
+    }
+
     /// This method is called to signal that the buffer has been saved.
     pub fn did_save(
         &mut self,
@@ -1497,6 +1642,7 @@ impl Buffer {
         self.has_unsaved_edits.set((version, false));
         self.has_conflict = false;
         self.saved_mtime = mtime;
+        self.clear_merge_state(cx);
         self.was_changed();
         cx.emit(BufferEvent::Saved);
         cx.notify();
@@ -1504,23 +1650,30 @@ impl Buffer {
 
     /// Reloads the contents of the buffer from disk.
     pub fn reload(&mut self, cx: &Context<Self>) -> oneshot::Receiver<Option<Transaction>> {
+        self.reload_with_encoding(self.encoding, cx)
+    }
+
+    /// Reloads the contents of the buffer from disk, decoding them with the given encoding
+    /// instead of the buffer's current one.
+    pub fn reload_with_encoding(
+        &mut self,
+        encoding: &'static Encoding,
+        cx: &Context<Self>,
+    ) -> oneshot::Receiver<Option<Transaction>> {
         let (tx, rx) = futures::channel::oneshot::channel();
         let prev_version = self.text.version();
         self.reload_task = Some(cx.spawn(async move |this, cx| {
-            let Some((new_mtime, load_bytes_task, encoding)) = this.update(cx, |this, cx| {
+            let Some((new_mtime, load_bytes_task)) = this.update(cx, |this, cx| {
                 let file = this.file.as_ref()?.as_local()?;
-                Some((
-                    file.disk_state().mtime(),
-                    file.load_bytes(cx),
-                    this.encoding,
-                ))
+                Some((file.disk_state().mtime(), file.load_bytes(cx)))
             })?
             else {
                 return Ok(());
             };
 
             let bytes = load_bytes_task.await?;
-            let (cow, _encoding_used, _has_errors) = encoding.decode(&bytes);
+            let (cow, encoding, _has_errors) = encoding.decode(&bytes);
+            let has_bom = Encoding::for_bom(&bytes).is_some();
             let new_text = cow.into_owned();
 
             let diff = this.update(cx, |this, cx| this.diff(new_text, cx))?.await;
@@ -1530,6 +1683,8 @@ impl Buffer {
                     this.apply_diff(diff, cx);
                     tx.send(this.finalize_last_transaction().cloned()).ok();
                     this.has_conflict = false;
+                    this.encoding = encoding;
+                    this.has_bom = has_bom;
                     this.did_reload(this.version(), this.line_ending(), new_mtime, cx);
                 } else {
                     if !diff.edits.is_empty()
@@ -1563,10 +1718,129 @@ impl Buffer {
             .set((self.saved_version.clone(), false));
         self.text.set_line_ending(line_ending);
         self.saved_mtime = mtime;
+        self.clear_merge_state(cx);
         cx.emit(BufferEvent::Reloaded);
         cx.notify();
     }
 
+    /// Merges the changes made to the file on disk since the buffer was last saved or reloaded
+    /// into the buffer, keeping the buffer's own unsaved changes. The regions changed in both
+    /// are left unchanged and become the buffer's [`MergeConflict`]s.
+    ///
+    /// The receiver gets the merge's transaction, if there was anything to merge, and is canceled
+    /// when the file couldn't be loaded or a reload superseded the merge.
+    pub fn merge_with_disk(
+        &mut self,
+        cx: &Context<Self>,
+    ) -> oneshot::Receiver<Option<Transaction>> {
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let encoding = self.encoding;
+        self.reload_task = Some(cx.spawn(async move |this, cx| {
+            let Some((new_mtime, load_bytes_task)) = this.update(cx, |this, cx| {
+                let file = this.file.as_ref()?.as_local()?;
+                Some((file.disk_state().mtime(), file.load_bytes(cx)))
+            })?
+            else {
+                tx.send(None).ok();
+                return Ok(());
+            };
+
+            let bytes = load_bytes_task.await?;
+            let mut disk_text = encoding.decode(&bytes).0.into_owned();
+            LineEnding::normalize(&mut disk_text);
+
+            let (base_text, buffer_text, version) = this.read_with(cx, |this, _| {
+                let base_text = this
+                    .merge_base
+                    .clone()
+                    .unwrap_or_else(|| this.text.rope_for_version(&this.saved_version));
+                (base_text, this.as_rope().clone(), this.version())
+            })?;
+            let merge = cx
+                .background_spawn({
+                    let disk_text = disk_text.clone();
+                    async move {
+                        three_way_merge(
+                            &base_text.to_string(),
+                            &buffer_text.to_string(),
+                            &disk_text,
+                        )
+                    }
+                })
+                .await;
+
+            this.update(cx, |this, cx| {
+                this.reload_task.take();
+                if this.version() != version {
+                    this.has_conflict = true;
+                    cx.notify();
+                    tx.send(None).ok();
+                    return;
+                }
+
+                let conflicts = merge
+                    .conflicts
+                    .into_iter()
+                    .map(|(range, disk_text)| MergeConflict {
+                        range: this.anchor_before(range.start)..this.anchor_after(range.end),
+                        disk_text,
+                    })
+                    .collect::<Vec<_>>();
+                this.finalize_last_transaction();
+                this.edit(merge.edits, None, cx);
+                tx.send(this.finalize_last_transaction().cloned()).ok();
+
+                if conflicts.is_empty() && this.text() == disk_text {
+                    this.has_conflict = false;
+                    this.did_reload(this.version(), this.line_ending(), new_mtime, cx);
+                } else {
+                    this.merge_base = Some(Rope::from(disk_text.as_str()));
+                    this.saved_mtime = new_mtime;
+                    this.has_conflict = !conflicts.is_empty();
+                    this.merge_conflicts = conflicts;
+                    cx.emit(BufferEvent::MergeConflictsChanged);
+                    cx.notify();
+                }
+            })
+        }));
+        rx
+    }
+
+    /// The regions changed both in the buffer and on disk that were left unchanged when merging
+    /// the changes on disk into the buffer.
+    pub fn merge_conflicts(&self) -> &[MergeConflict] {
+        &self.merge_conflicts
+    }
+
+    /// Resolves a merge conflict by keeping either the buffer's or the disk's text of its region.
+    pub fn resolve_merge_conflict(
+        &mut self,
+        ix: usize,
+        resolution: MergeConflictResolution,
+        cx: &mut Context<Self>,
+    ) {
+        if ix >= self.merge_conflicts.len() {
+            return;
+        }
+        let conflict = self.merge_conflicts.remove(ix);
+        if resolution == MergeConflictResolution::KeepDisk {
+            self.edit([(conflict.range, conflict.disk_text)], None, cx);
+        }
+        if self.merge_conflicts.is_empty() {
+            self.has_conflict = false;
+        }
+        cx.emit(BufferEvent::MergeConflictsChanged);
+        cx.notify();
+    }
+
+    fn clear_merge_state(&mut self, cx: &mut Context<Self>) {
+        self.merge_base = None;
+        if !self.merge_conflicts.is_empty() {
+            self.merge_conflicts.clear();
+            cx.emit(BufferEvent::MergeConflictsChanged);
+        }
+    }
+
     /// Updates the [`File`] backing this buffer. This should be called when
     /// the file has changed or has been deleted.
     pub fn file_updated(&mut self, new_file: Arc<dyn File>, cx: &mut Context<Self>) {
@@ -1582,8 +1856,12 @@ impl Buffer {
             let new_state = new_file.disk_state();
             if old_state != new_state {
                 file_changed = true;
-                if !was_dirty && matches!(new_state, DiskState::Present { .. }) {
-                    cx.emit(BufferEvent::ReloadNeeded)
+                if matches!(new_state, DiskState::Present { .. }) {
+                    if was_dirty {
+                        cx.emit(BufferEvent::MergeNeeded)
+                    } else {
+                        cx.emit(BufferEvent::ReloadNeeded)
+                    }
                 }
             }
         } else {
@@ -2200,6 +2478,36 @@ impl Buffer {
         self.edit([(offset..len, "\n")], None, cx);
     }
 
+    /// Whether the buffer's text ends with a newline.
+    pub fn has_final_newline(&self) -> bool {
+        self.as_rope().reversed_chars_at(self.len()).next() == Some('\n')
+    }
+
+    /// Adds a newline to the end of the buffer if it doesn't end with one, or removes the
+    /// newlines at its end. Empty buffers are left empty.
+    pub fn set_final_newline(
+        &mut self,
+        final_newline: bool,
+        cx: &mut Context<Self>,
+    ) -> Option<TransactionId> {
+        let len = self.len();
+        if len == 0 || self.has_final_newline() == final_newline {
+            return None;
+        }
+        self.start_transaction();
+        if final_newline {
+            self.edit([(len..len, "\n")], None, cx);
+        } else {
+            let newlines_len = self
+                .as_rope()
+                .reversed_chars_at(len)
+                .take_while(|c| *c == '\n')
+                .count();
+            self.edit([(len - newlines_len..len, "")], None, cx);
+        }
+        self.end_transaction(cx)
+    }
+
     /// Applies a diff to the buffer. If the buffer has changed since the given diff was
     /// calculated, then adjust the diff to account for those changes, and discard any
     /// parts of the diff that conflict with those changes.
@@ -2991,6 +3299,7 @@ impl Buffer {
 
         if let Some((transaction_id, operation)) = self.text.undo() {
             self.send_operation(Operation::Buffer(operation), true, cx);
+            self.did_undo_or_redo(transaction_id, cx);
             self.did_edit(&old_version, was_dirty, cx);
             Some(transaction_id)
         } else {
@@ -3008,6 +3317,7 @@ impl Buffer {
         let old_version = self.version.clone();
         if let Some(operation) = self.text.undo_transaction(transaction_id) {
             self.send_operation(Operation::Buffer(operation), true, cx);
+            self.did_undo_or_redo(transaction_id, cx);
             self.did_edit(&old_version, was_dirty, cx);
             true
         } else {
@@ -3026,8 +3336,9 @@ impl Buffer {
 
         let operations = self.text.undo_to_transaction(transaction_id);
         let undone = !operations.is_empty();
-        for operation in operations {
+        for (transaction_id, operation) in operations {
             self.send_operation(Operation::Buffer(operation), true, cx);
+            self.did_undo_or_redo(transaction_id, cx);
         }
         if undone {
             self.did_edit(&old_version, was_dirty, cx)
@@ -3050,6 +3361,7 @@ impl Buffer {
 
         if let Some((transaction_id, operation)) = self.text.redo() {
             self.send_operation(Operation::Buffer(operation), true, cx);
+            self.did_undo_or_redo(transaction_id, cx);
             self.did_edit(&old_version, was_dirty, cx);
             Some(transaction_id)
         } else {
@@ -3068,8 +3380,9 @@ impl Buffer {
 
         let operations = self.text.redo_to_transaction(transaction_id);
         let redone = !operations.is_empty();
-        for operation in operations {
+        for (transaction_id, operation) in operations {
             self.send_operation(Operation::Buffer(operation), true, cx);
+            self.did_undo_or_redo(transaction_id, cx);
         }
         if redone {
             self.did_edit(&old_version, was_dirty, cx)
@@ -144,6 +144,60 @@ fn test_set_line_ending(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+fn test_set_final_newline(cx: &mut gpui::App) {
+    cx.new(|cx| {
+        let mut buffer = Buffer::local("one\ntwo", cx);
+        assert!(!buffer.has_final_newline());
+        assert_eq!(buffer.set_final_newline(false, cx), None);
+
+        let transaction_id = buffer.set_final_newline(true, cx);
+        assert!(transaction_id.is_some());
+        assert_eq!(buffer.text(), "one\ntwo\n");
+        assert!(buffer.has_final_newline());
+        assert_eq!(buffer.set_final_newline(true, cx), None);
+
+        buffer.edit([(buffer.len()..buffer.len(), "\n\n")], None, cx);
+        buffer.set_final_newline(false, cx);
+        assert_eq!(buffer.text(), "one\ntwo");
+        buffer
+    });
+
+    cx.new(|cx| {
+        let mut buffer = Buffer::local("", cx);
+        assert_eq!(buffer.set_final_newline(true, cx), None);
+        assert_eq!(buffer.text(), "");
+        buffer
+    });
+}
+
+#[gpui::test]
+fn test_convert_line_ending(cx: &mut gpui::App) {
+    cx.new(|cx| {
+        let mut buffer = Buffer::local("one\r\ntwo\r\n", cx);
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert_eq!(buffer.convert_line_ending(LineEnding::Windows, cx), None);
+
+        let transaction_id = buffer.convert_line_ending(LineEnding::Unix, cx).unwrap();
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+
+        // Edits made afterwards aren't grouped with the conversion.
+        buffer.edit([(0..0, "zero\n")], None, cx);
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "one\ntwo\n");
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+
+        buffer.undo(cx);
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        buffer.redo(cx);
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+
+        buffer.undo_transaction(transaction_id, cx);
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        buffer
+    });
+}
+
 #[gpui::test]
 fn test_select_language(cx: &mut App) {
     init_settings(cx, |_| {});
@@ -1,11 +1,15 @@
 //! Provides `language`-related settings.
 
-use crate::{File, Language, LanguageName, LanguageServerName};
+use crate::{File, Language, LanguageName, LanguageServerName, LineEnding};
 use collections::{FxHashMap, HashMap, HashSet};
 use ec4rs::{
     Properties as EditorconfigProperties,
-    property::{FinalNewline, IndentSize, IndentStyle, MaxLineLen, TabWidth, TrimTrailingWs},
+    property::{
+        Charset, EndOfLine, FinalNewline, IndentSize, IndentStyle, MaxLineLen, TabWidth,
+        TrimTrailingWs,
+    },
 };
+use encoding_rs::Encoding;
 use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
 use gpui::{App, Modifiers, SharedString};
 use itertools::{Either, Itertools};
@@ -90,6 +94,8 @@ pub struct LanguageSettings {
     /// Whether or not to ensure there's a single newline at the end of a buffer
     /// when saving it.
     pub ensure_final_newline_on_save: bool,
+    /// Whether or not buffers in this language are saved automatically.
+    pub autosave: bool,
     /// How to perform a buffer format.
     pub formatter: settings::FormatterList,
     /// Zed's Prettier integration settings.
@@ -157,6 +163,8 @@ pub struct LanguageSettings {
     pub completions: CompletionSettings,
     /// Preferred debuggers for this language.
     pub debuggers: Vec<String>,
+    /// The template for the contents of new files of this language.
+    pub file_template: Option<String>,
     /// Whether to enable word diff highlighting in the editor.
     ///
     /// When enabled, changed words within modified lines are highlighted
@@ -166,6 +174,11 @@ pub struct LanguageSettings {
     pub word_diff_enabled: bool,
     /// Whether to use tree-sitter bracket queries to detect and colorize the brackets in the editor.
     pub colorize_brackets: bool,
+    /// The line ending that files are saved with, from EditorConfig's `end_of_line` property.
+    pub line_ending: Option<LineEnding>,
+    /// The encoding that files are saved with, and whether they start with a byte order mark,
+    /// from EditorConfig's `charset` property.
+    pub charset: Option<(&'static Encoding, bool)>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -504,6 +517,18 @@ fn merge_with_editorconfig(settings: &mut LanguageSettings, cfg: &EditorconfigPr
             TrimTrailingWs::Value(b) => b,
         })
         .ok();
+    let line_ending = cfg.get::<EndOfLine>().ok().and_then(|v| match v {
+        EndOfLine::Lf => Some(LineEnding::Unix),
+        EndOfLine::CrLf => Some(LineEnding::Windows),
+        EndOfLine::Cr => None,
+    });
+    let charset = cfg.get::<Charset>().ok().map(|v| match v {
+        Charset::Utf8 => (encoding_rs::UTF_8, false),
+        Charset::Utf8Bom => (encoding_rs::UTF_8, true),
+        Charset::Latin1 => (encoding_rs::WINDOWS_1252, false),
+        Charset::Utf16Le => (encoding_rs::UTF_16LE, true),
+        Charset::Utf16Be => (encoding_rs::UTF_16BE, true),
+    });
     fn merge<T>(target: &mut T, value: Option<T>) {
         if let Some(value) = value {
             *target = value;
@@ -520,6 +545,12 @@ fn merge_with_editorconfig(settings: &mut LanguageSettings, cfg: &EditorconfigPr
         &mut settings.ensure_final_newline_on_save,
         ensure_final_newline_on_save,
     );
+    if line_ending.is_some() {
+        settings.line_ending = line_ending;
+    }
+    if charset.is_some() {
+        settings.charset = charset;
+    }
 }
 
 impl settings::Settings for AllLanguageSettings {
@@ -553,6 +584,7 @@ impl settings::Settings for AllLanguageSettings {
                     .remove_trailing_whitespace_on_save
                     .unwrap(),
                 ensure_final_newline_on_save: settings.ensure_final_newline_on_save.unwrap(),
+                autosave: settings.autosave.unwrap(),
                 formatter: settings.formatter.unwrap(),
                 prettier: PrettierSettings {
                     allowed: prettier.allowed.unwrap(),
@@ -611,7 +643,10 @@ impl settings::Settings for AllLanguageSettings {
                     lsp_insert_mode: completions.lsp_insert_mode.unwrap(),
                 },
                 debuggers: settings.debuggers.unwrap(),
+                file_template: settings.file_template,
                 word_diff_enabled: settings.word_diff_enabled.unwrap(),
+                line_ending: None,
+                charset: None,
             }
         }
 
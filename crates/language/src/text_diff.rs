@@ -275,6 +275,163 @@ pub fn text_diff_with_options(
     edits
 }
 
+/// The result of merging the changes made to a base text in two different texts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreeWayMerge {
+    /// The edits that apply the changes of `theirs` to `ours`, as byte ranges of `ours` and
+    /// replacement strings.
+    pub edits: Vec<(Range<usize>, Arc<str>)>,
+    /// The regions changed in both texts, as byte ranges of `ours` and the text of `theirs` in
+    /// their place.
+    pub conflicts: Vec<(Range<usize>, Arc<str>)>,
+}
+
+impl ThreeWayMerge {
+    /// Applies the merge to `ours`, the text it was computed for, surrounding the lines of each
+    /// conflict with git-style conflict markers labelled with `our_label` and `their_label`.
+    pub fn apply_with_conflict_markers(
+        &self,
+        ours: &str,
+        our_label: &str,
+        their_label: &str,
+    ) -> String {
+        let mut replacements = self
+            .edits
+            .iter()
+            .map(|(range, text)| (range, text, false))
+            .chain(
+                self.conflicts
+                    .iter()
+                    .map(|(range, text)| (range, text, true)),
+            )
+            .collect::<Vec<_>>();
+        replacements.sort_by_key(|(range, _, _)| (range.start, range.end));
+
+        // Apply the edits, keeping our side of the conflicts, whose ranges are then relative to
+        // the merged text.
+        let mut merged = String::with_capacity(ours.len());
+        let mut conflicts = Vec::new();
+        let mut offset = 0;
+        for (range, text, is_conflict) in replacements {
+            merged.push_str(&ours[offset..range.start]);
+            if is_conflict {
+                let start = merged.len();
+                merged.push_str(&ours[range.clone()]);
+                conflicts.push((start..merged.len(), text));
+            } else {
+                merged.push_str(text);
+            }
+            offset = range.end;
+        }
+        merged.push_str(&ours[offset..]);
+
+        let mut result = String::with_capacity(merged.len());
+        let mut offset = 0;
+        let mut conflicts = conflicts.into_iter().peekable();
+        while let Some((range, text)) = conflicts.next() {
+            let line_start = merged[..range.start].rfind('\n').map_or(0, |ix| ix + 1);
+            let line_end = |end: usize| {
+                merged[end..]
+                    .find('\n')
+                    .map_or(merged.len(), |ix| end + ix + 1)
+            };
+            let mut end = line_end(range.end);
+            let mut theirs = merged[line_start..range.start].to_string();
+            theirs.push_str(text);
+            let mut their_offset = range.end;
+            while let Some((range, text)) = conflicts.next_if(|(range, _)| range.start < end) {
+                theirs.push_str(&merged[their_offset..range.start]);
+                theirs.push_str(text);
+                their_offset = range.end;
+                end = line_end(range.end);
+            }
+            theirs.push_str(&merged[their_offset..end]);
+
+            result.push_str(&merged[offset..line_start]);
+            for (marker, side) in [
+                (format!("<<<<<<< {our_label}\n"), &merged[line_start..end]),
+                ("=======\n".to_string(), &theirs),
+            ] {
+                result.push_str(&marker);
+                result.push_str(side);
+                if !side.is_empty() && !side.ends_with('\n') {
+                    result.push('\n');
+                }
+            }
+            result.push_str(&format!(">>>>>>> {their_label}\n"));
+            offset = end;
+        }
+        result.push_str(&merged[offset..]);
+        result
+    }
+}
+
+/// Merges the changes made to `base` in `theirs` into `ours`. Changes that overlap or touch
+/// changes made in `ours` conflict with them, unless both texts made the same changes.
+pub fn three_way_merge(base: &str, ours: &str, theirs: &str) -> ThreeWayMerge {
+    let mut edits = text_diff(base, ours)
+        .into_iter()
+        .map(|(range, text)| (range, text, true))
+        .chain(
+            text_diff(base, theirs)
+                .into_iter()
+                .map(|(range, text)| (range, text, false)),
+        )
+        .collect::<Vec<_>>();
+    edits.sort_by_key(|(range, _, _)| (range.start, range.end));
+
+    let mut merge = ThreeWayMerge::default();
+    let mut our_delta = 0isize;
+    let mut their_delta = 0isize;
+    let mut edits = edits.into_iter().peekable();
+    while let Some(first_edit) = edits.next() {
+        let start = first_edit.0.start;
+        let mut end = first_edit.0.end;
+        let mut cluster = vec![first_edit];
+        while let Some(edit) = edits.next_if(|(range, _, _)| range.start <= end) {
+            end = end.max(edit.0.end);
+            cluster.push(edit);
+        }
+
+        let (our_edits, their_edits): (Vec<_>, Vec<_>) =
+            cluster.into_iter().partition(|(_, _, is_ours)| *is_ours);
+        let len_delta = |edits: &[(Range<usize>, Arc<str>, bool)]| {
+            edits
+                .iter()
+                .map(|(range, text, _)| text.len() as isize - range.len() as isize)
+                .sum::<isize>()
+        };
+        let our_len_delta = len_delta(&our_edits);
+        let their_len_delta = len_delta(&their_edits);
+
+        if our_edits.is_empty() {
+            merge
+                .edits
+                .extend(their_edits.into_iter().map(|(range, text, _)| {
+                    let start = (range.start as isize + our_delta) as usize;
+                    let end = (range.end as isize + our_delta) as usize;
+                    (start..end, text)
+                }));
+        } else if !their_edits.is_empty()
+            && !our_edits
+                .iter()
+                .map(|(range, text, _)| (range, text))
+                .eq(their_edits.iter().map(|(range, text, _)| (range, text)))
+        {
+            let our_range = (start as isize + our_delta) as usize
+                ..(end as isize + our_delta + our_len_delta) as usize;
+            let their_range = (start as isize + their_delta) as usize
+                ..(end as isize + their_delta + their_len_delta) as usize;
+            merge
+                .conflicts
+                .push((our_range, theirs[their_range].into()));
+        }
+        our_delta += our_len_delta;
+        their_delta += their_len_delta;
+    }
+    merge
+}
+
 pub fn apply_diff_patch(base_text: &str, patch: &str) -> Result<String, anyhow::Error> {
     let patch = diffy::Patch::from_str(patch).context("Failed to parse patch")?;
     let result = diffy::apply(base_text, &patch);
@@ -434,6 +591,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_three_way_merge() {
+        let base = "one\ntwo\nthree\nfour\nfive\n";
+        let ours = "one\nTWO\nthree\nfour\nfive\n";
+        let theirs = "one\ntwo\nthree\nfour\nFIVE\nsix\n";
+        let merge = three_way_merge(base, ours, theirs);
+        assert!(merge.conflicts.is_empty());
+        let mut merged = ours.to_string();
+        for (range, text) in merge.edits.iter().rev() {
+            merged.replace_range(range.clone(), text);
+        }
+        assert_eq!(merged, "one\nTWO\nthree\nfour\nFIVE\nsix\n");
+
+        // The same change made in both texts doesn't conflict.
+        let merge = three_way_merge(base, ours, ours);
+        assert_eq!(merge, ThreeWayMerge::default());
+
+        let theirs = "one\nTwo\nthree\nfour\nFIVE\n";
+        let merge = three_way_merge(base, ours, theirs);
+        assert_eq!(merge.conflicts, [(4..7, "Two".into())]);
+        assert_eq!(merge.edits, [(19..23, "FIVE".into())]);
+        assert_eq!(
+            merge.apply_with_conflict_markers(ours, "ours", "theirs"),
+            "one\n<<<<<<< ours\nTWO\n=======\nTwo\n>>>>>>> theirs\nthree\nfour\nFIVE\n"
+        );
+    }
+
     #[test]
     fn test_apply_diff_patch() {
         let old_text = "one two\nthree four five\nsix seven eight nine\nten\n";
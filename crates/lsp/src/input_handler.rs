@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::str;
 use std::sync::Arc;
 
@@ -13,8 +14,8 @@ use parking_lot::Mutex;
 use smol::io::BufReader;
 
 use crate::{
-    AnyResponse, CONTENT_LEN_HEADER, IoHandler, IoKind, NotificationOrRequest, RequestId,
-    ResponseHandler,
+    AnyResponse, CONTENT_LEN_HEADER, IoHandler, IoKind, NotificationOrRequest, PathMapping,
+    RequestId, ResponseHandler,
 };
 
 const HEADER_DELIMITER: &[u8; 4] = b"\r\n\r\n";
@@ -46,13 +47,20 @@ impl LspStdoutHandler {
         stdout: Input,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
         io_handlers: Arc<Mutex<HashMap<i32, IoHandler>>>,
+        path_mapping: Option<Arc<PathMapping>>,
         cx: BackgroundExecutor,
     ) -> Self
     where
         Input: AsyncRead + Unpin + Send + 'static,
     {
         let (tx, notifications_channel) = unbounded();
-        let loop_handle = cx.spawn(Self::handler(stdout, tx, response_handlers, io_handlers));
+        let loop_handle = cx.spawn(Self::handler(
+            stdout,
+            tx,
+            response_handlers,
+            io_handlers,
+            path_mapping,
+        ));
         Self {
             loop_handle,
             incoming_messages: notifications_channel,
@@ -64,6 +72,7 @@ impl LspStdoutHandler {
         notifications_sender: UnboundedSender<NotificationOrRequest>,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
         io_handlers: Arc<Mutex<HashMap<i32, IoHandler>>>,
+        path_mapping: Option<Arc<PathMapping>>,
     ) -> anyhow::Result<()>
     where
         Input: AsyncRead + Unpin + Send + 'static,
@@ -89,6 +98,12 @@ impl LspStdoutHandler {
 
             buffer.resize(message_len, 0);
             stdout.read_exact(&mut buffer).await?;
+            if let Some(path_mapping) = &path_mapping
+                && let Ok(message) = str::from_utf8(&buffer)
+                && let Cow::Owned(message) = path_mapping.to_local(message)
+            {
+                buffer = message.into_bytes();
+            }
 
             if let Ok(message) = str::from_utf8(&buffer) {
                 log::trace!("incoming message: {message}");
@@ -1,7 +1,9 @@
 mod input_handler;
+mod path_mapping;
 
 pub use lsp_types::request::*;
 pub use lsp_types::*;
+pub use path_mapping::PathMapping;
 
 /// Compatibility alias: older code refers to LSP URIs as `Url`.
 pub type Url = Uri;
@@ -29,6 +31,7 @@ use smol::{
 use url::Url as UrlUrl;
 
 use std::{
+    borrow::Cow,
     collections::BTreeSet,
     ffi::{OsStr, OsString},
     fmt,
@@ -331,6 +334,9 @@ pub struct AdapterServerCapabilities {
 
 impl LanguageServer {
     /// Starts a language server process.
+    ///
+    /// The paths in the messages exchanged with the server are translated with `path_mapping`, for servers that see
+    /// the project at a different location.
     pub fn new(
         stderr_capture: Arc<Mutex<Option<String>>>,
         server_id: LanguageServerId,
@@ -339,6 +345,7 @@ impl LanguageServer {
         root_path: &Path,
         code_action_kinds: Option<Vec<CodeActionKind>>,
         workspace_folders: Option<Arc<Mutex<BTreeSet<Uri>>>>,
+        path_mapping: Option<PathMapping>,
         cx: &mut AsyncApp,
     ) -> Result<Self> {
         let working_dir = if root_path.is_dir() {
@@ -384,6 +391,7 @@ impl LanguageServer {
             binary,
             root_uri,
             workspace_folders,
+            path_mapping,
             cx,
             move |notification| {
                 log::info!(
@@ -411,6 +419,7 @@ impl LanguageServer {
         binary: LanguageServerBinary,
         root_uri: Uri,
         workspace_folders: Option<Arc<Mutex<BTreeSet<Uri>>>>,
+        path_mapping: Option<PathMapping>,
         cx: &mut AsyncApp,
         on_unhandled_notification: F,
     ) -> Self
@@ -427,6 +436,7 @@ impl LanguageServer {
         let response_handlers =
             Arc::new(Mutex::new(Some(HashMap::<_, ResponseHandler>::default())));
         let io_handlers = Arc::new(Mutex::new(HashMap::default()));
+        let path_mapping = path_mapping.map(Arc::new);
 
         let stdout_input_task = cx.spawn({
             let unhandled_notification_wrapper = {
@@ -453,6 +463,7 @@ impl LanguageServer {
             let notification_handlers = notification_handlers.clone();
             let response_handlers = response_handlers.clone();
             let io_handlers = io_handlers.clone();
+            let path_mapping = path_mapping.clone();
             async move |cx| {
                 Self::handle_incoming_messages(
                     stdout,
@@ -460,6 +471,7 @@ impl LanguageServer {
                     notification_handlers,
                     response_handlers,
                     io_handlers,
+                    path_mapping,
                     cx,
                 )
                 .log_err()
@@ -488,6 +500,7 @@ impl LanguageServer {
                 output_done_tx,
                 response_handlers.clone(),
                 io_handlers.clone(),
+                path_mapping,
             )
             .log_err()
         });
@@ -550,6 +563,7 @@ impl LanguageServer {
         notification_handlers: Arc<Mutex<HashMap<&'static str, NotificationHandler>>>,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
         io_handlers: Arc<Mutex<HashMap<i32, IoHandler>>>,
+        path_mapping: Option<Arc<PathMapping>>,
         cx: &mut AsyncApp,
     ) -> anyhow::Result<()>
     where
@@ -567,6 +581,7 @@ impl LanguageServer {
             stdout,
             response_handlers,
             io_handlers,
+            path_mapping,
             cx.background_executor().clone(),
         );
 
@@ -632,6 +647,7 @@ impl LanguageServer {
         output_done_tx: barrier::Sender,
         response_handlers: Arc<Mutex<Option<HashMap<RequestId, ResponseHandler>>>>,
         io_handlers: Arc<Mutex<HashMap<i32, IoHandler>>>,
+        path_mapping: Option<Arc<PathMapping>>,
     ) -> anyhow::Result<()>
     where
         Stdin: AsyncWrite + Unpin + Send + 'static,
@@ -649,6 +665,10 @@ impl LanguageServer {
             for handler in io_handlers.lock().values_mut() {
                 handler(IoKind::StdIn, &message);
             }
+            let message = match &path_mapping {
+                Some(path_mapping) => path_mapping.to_server(&message),
+                None => Cow::Borrowed(message.as_str()),
+            };
 
             content_len_buffer.clear();
             write!(content_len_buffer, "{}", message.len()).unwrap();
@@ -1683,6 +1703,7 @@ impl FakeLanguageServer {
             binary.clone(),
             root,
             Some(workspace_folders.clone()),
+            None,
             cx,
             |_| false,
         );
@@ -1702,6 +1723,7 @@ impl FakeLanguageServer {
                     binary,
                     Self::root_path(),
                     Some(workspace_folders),
+                    None,
                     cx,
                     move |msg| {
                         notifications_tx
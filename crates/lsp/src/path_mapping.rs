@@ -0,0 +1,265 @@
+use std::{borrow::Cow, path::Path};
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::url_from_file_path;
+
+/// The fields whose values are `file://` URIs, such as `TextDocumentIdentifier.uri`,
+/// `LocationLink.targetUri` or `RenameFile.newUri`.
+const URI_FIELDS: &[&str] = &[
+    "uri",
+    "rootUri",
+    "targetUri",
+    "scopeUri",
+    "baseUri",
+    "oldUri",
+    "newUri",
+    "target",
+];
+/// The fields whose values are plain paths.
+const PATH_FIELDS: &[&str] = &["rootPath"];
+/// The fields whose values are objects keyed by URIs, like `WorkspaceEdit.changes`.
+const URI_KEYED_FIELDS: &[&str] = &["changes"];
+
+/// Translates the paths in the messages exchanged with a language server that sees the project at a different
+/// location, such as a server running inside of a container with the project bind-mounted into it.
+///
+/// Only the fields that hold URIs or paths are translated, so document contents and edits that happen to mention
+/// the project's folder are passed through as they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathMapping {
+    local_uri: String,
+    server_uri: String,
+    local_path: String,
+    server_path: String,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    ToServer,
+    ToLocal,
+}
+
+impl PathMapping {
+    /// Maps the local folder onto the POSIX path at which the server sees it.
+    pub fn new(local_folder: &Path, server_folder: &str) -> Result<Self> {
+        let local_uri = url_from_file_path(local_folder)
+            .map_err(|()| anyhow!("{local_folder:?} is not a valid URI"))?;
+        let server_folder = server_folder.trim_end_matches('/');
+        Ok(Self {
+            local_uri: local_uri.as_str().trim_end_matches('/').to_string(),
+            server_uri: format!("file://{server_folder}"),
+            local_path: local_folder.to_string_lossy().into_owned(),
+            server_path: server_folder.to_string(),
+        })
+    }
+
+    /// Translates the local paths in a JSON message sent to the server.
+    pub fn to_server<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        self.translate(message, Direction::ToServer)
+    }
+
+    /// Translates the server paths in a JSON message received from the server.
+    pub fn to_local<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        self.translate(message, Direction::ToLocal)
+    }
+
+    fn translate<'a>(&self, message: &'a str, direction: Direction) -> Cow<'a, str> {
+        let (from_uri, from_path) = match direction {
+            Direction::ToServer => (&self.local_uri, &self.local_path),
+            Direction::ToLocal => (&self.server_uri, &self.server_path),
+        };
+        if !message.contains(from_uri.as_str()) && !message.contains(&json_escape(from_path)) {
+            return Cow::Borrowed(message);
+        }
+        let Ok(mut value) = serde_json::from_str::<Value>(message) else {
+            return Cow::Borrowed(message);
+        };
+        if !self.translate_value(&mut value, direction) {
+            return Cow::Borrowed(message);
+        }
+        match serde_json::to_string(&value) {
+            Ok(message) => Cow::Owned(message),
+            Err(_) => Cow::Borrowed(message),
+        }
+    }
+
+    /// Translates the URI and path fields within the value, returning whether any of them changed.
+    fn translate_value(&self, value: &mut Value, direction: Direction) -> bool {
+        let mut changed = false;
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    changed |= self.translate_value(item, direction);
+                }
+            }
+            Value::Object(fields) => {
+                for (key, field) in fields.iter_mut() {
+                    let key = key.as_str();
+                    changed |= match field {
+                        Value::String(text) if URI_FIELDS.contains(&key) => {
+                            self.translate_string(text, true, direction)
+                        }
+                        Value::String(text) if PATH_FIELDS.contains(&key) => {
+                            self.translate_string(text, false, direction)
+                        }
+                        Value::Object(uri_keyed) if URI_KEYED_FIELDS.contains(&key) => {
+                            let mut changed = false;
+                            *uri_keyed = std::mem::take(uri_keyed)
+                                .into_iter()
+                                .map(|(mut uri, mut field)| {
+                                    changed |= self.translate_string(&mut uri, true, direction);
+                                    changed |= self.translate_value(&mut field, direction);
+                                    (uri, field)
+                                })
+                                .collect();
+                            changed
+                        }
+                        _ => self.translate_value(field, direction),
+                    };
+                }
+            }
+            _ => {}
+        }
+        changed
+    }
+
+    fn translate_string(&self, text: &mut String, is_uri: bool, direction: Direction) -> bool {
+        let (local, server) = if is_uri {
+            (&self.local_uri, &self.server_uri)
+        } else {
+            (&self.local_path, &self.server_path)
+        };
+        let (from, to) = match direction {
+            Direction::ToServer => (local, server),
+            Direction::ToLocal => (server, local),
+        };
+        match replace_prefix(text, from, to) {
+            Some(replaced) => {
+                *text = replaced;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    let quoted = serde_json::to_string(text).unwrap_or_default();
+    quoted.trim_matches('"').to_string()
+}
+
+/// Replaces `from` at the start of the text if it's the whole text or a folder containing it, leaving paths
+/// that only share a prefix with a sibling's name (like `/project` in `/project2`) alone.
+fn replace_prefix(text: &str, from: &str, to: &str) -> Option<String> {
+    let rest = text.strip_prefix(from)?;
+    if from.is_empty() || !matches!(rest.chars().next(), None | Some('/' | '\\')) {
+        return None;
+    }
+    Some(format!("{to}{rest}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use util::path;
+
+    #[test]
+    fn test_path_mapping() {
+        let mapping = PathMapping::new(
+            Path::new(path!("/home/user/project")),
+            "/workspaces/project/",
+        )
+        .unwrap();
+        let local_uri = url_from_file_path(path!("/home/user/project/src/main.rs")).unwrap();
+        let local_root_uri = url_from_file_path(path!("/home/user/project")).unwrap();
+        let sibling_uri = url_from_file_path(path!("/home/user/project2/src/main.rs")).unwrap();
+
+        let message = json!({
+            "rootUri": local_root_uri.as_str(),
+            "rootPath": path!("/home/user/project"),
+            "workspaceFolders": [{ "uri": local_root_uri.as_str(), "name": "project" }],
+            "other": { "uri": sibling_uri.as_str() },
+        })
+        .to_string();
+        let server_message = mapping.to_server(&message);
+        assert_eq!(
+            serde_json::from_str::<Value>(&server_message).unwrap(),
+            json!({
+                "rootUri": "file:///workspaces/project",
+                "rootPath": "/workspaces/project",
+                "workspaceFolders": [{ "uri": "file:///workspaces/project", "name": "project" }],
+                "other": { "uri": sibling_uri.as_str() },
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&mapping.to_local(&server_message)).unwrap(),
+            serde_json::from_str::<Value>(&message).unwrap()
+        );
+
+        let edit = json!({
+            "changes": {
+                "file:///workspaces/project/src/main.rs": [{
+                    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                    "newText": "// see file:///workspaces/project/src/lib.rs",
+                }],
+            },
+        })
+        .to_string();
+        assert_eq!(
+            serde_json::from_str::<Value>(&mapping.to_local(&edit)).unwrap(),
+            json!({
+                "changes": {
+                    local_uri.as_str(): [{
+                        "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+                        "newText": "// see file:///workspaces/project/src/lib.rs",
+                    }],
+                },
+            })
+        );
+
+        assert!(matches!(
+            mapping.to_server(r#"{"uri":"file:///tmp/file.rs"}"#),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_path_mapping_leaves_document_text_alone() {
+        let mapping = PathMapping::new(
+            Path::new(path!("/home/user/project")),
+            "/workspaces/project",
+        )
+        .unwrap();
+        let local_uri = url_from_file_path(path!("/home/user/project/src/main.rs")).unwrap();
+        let text = format!(
+            "// Built from {} and {}\n",
+            local_uri.as_str(),
+            path!("/home/user/project/src")
+        );
+
+        let message = json!({
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": {
+                    "uri": local_uri.as_str(),
+                    "languageId": "rust",
+                    "version": 0,
+                    "text": text,
+                },
+            },
+        })
+        .to_string();
+        let server_message = serde_json::from_str::<Value>(&mapping.to_server(&message)).unwrap();
+        assert_eq!(
+            server_message["params"]["textDocument"]["uri"],
+            "file:///workspaces/project/src/main.rs"
+        );
+        assert_eq!(server_message["params"]["textDocument"]["text"], text);
+
+        let message = json!({ "contents": { "kind": "markdown", "value": text } }).to_string();
+        assert!(matches!(mapping.to_server(&message), Cow::Borrowed(_)));
+    }
+}
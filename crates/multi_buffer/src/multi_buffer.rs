@@ -1069,7 +1069,7 @@ impl MultiBuffer {
 
     pub fn singleton(buffer: Entity<Buffer>, cx: &mut Context<Self>) -> Self {
         let mut this = Self::new_(
-            buffer.read(cx).capability(),
+            buffer_capability(buffer.read(cx)),
             MultiBufferSnapshot {
                 singleton: true,
                 ..MultiBufferSnapshot::default()
@@ -2302,10 +2302,13 @@ impl MultiBuffer {
             BufferEvent::Reparsed => Event::Reparsed(buffer_id),
             BufferEvent::DiagnosticsUpdated => Event::DiagnosticsUpdated,
             BufferEvent::CapabilityChanged => {
-                self.capability = buffer.read(cx).capability();
+                self.capability = buffer_capability(buffer.read(cx));
                 return;
             }
-            BufferEvent::Operation { .. } | BufferEvent::ReloadNeeded => return,
+            BufferEvent::Operation { .. }
+            | BufferEvent::ReloadNeeded
+            | BufferEvent::MergeNeeded
+            | BufferEvent::MergeConflictsChanged => return,
         });
     }
 
@@ -3560,6 +3563,16 @@ impl DiffTransform {
     }
 }
 
+/// The capability of a buffer's edits, which are rejected when it's locked or its file is
+/// read-only as well as when it's a read-only replica.
+fn buffer_capability(buffer: &Buffer) -> Capability {
+    if buffer.read_only() {
+        Capability::ReadOnly
+    } else {
+        Capability::ReadWrite
+    }
+}
+
 fn build_excerpt_ranges(
     ranges: impl IntoIterator<Item = Range<Point>>,
     context_line_count: u32,
@@ -107,25 +107,95 @@ pub fn data_dir() -> &'static PathBuf {
     CURRENT_DATA_DIR.get_or_init(|| {
         if let Some(custom_dir) = CUSTOM_DATA_DIR.get() {
             custom_dir.clone()
-        } else if cfg!(target_os = "macos") {
-            home_dir().join("Library/Application Support/Vector")
-        } else if cfg!(any(target_os = "linux", target_os = "freebsd")) {
-            if let Ok(flatpak_xdg_data) = std::env::var("FLATPAK_XDG_DATA_HOME") {
-                flatpak_xdg_data.into()
-            } else {
-                dirs::data_local_dir().expect("failed to determine XDG_DATA_HOME directory")
-            }
-            .join("vector")
-        } else if cfg!(target_os = "windows") {
-            dirs::data_local_dir()
-                .expect("failed to determine LocalAppData directory")
-                .join("Vector")
         } else {
-            config_dir().clone() // Fallback
+            default_data_dir()
         }
     })
 }
 
+/// Returns the platform's default data directory, ignoring the custom data directory.
+fn default_data_dir() -> PathBuf {
+    if cfg!(target_os = "macos") {
+        home_dir().join("Library/Application Support/Vector")
+    } else if cfg!(any(target_os = "linux", target_os = "freebsd")) {
+        if let Ok(flatpak_xdg_data) = std::env::var("FLATPAK_XDG_DATA_HOME") {
+            flatpak_xdg_data.into()
+        } else {
+            dirs::data_local_dir().expect("failed to determine XDG_DATA_HOME directory")
+        }
+        .join("vector")
+    } else if cfg!(target_os = "windows") {
+        dirs::data_local_dir()
+            .expect("failed to determine LocalAppData directory")
+            .join("Vector")
+    } else {
+        config_dir().clone() // Fallback
+    }
+}
+
+/// Returns the path to the directory containing the data directories of the named profiles.
+///
+/// Each profile has its own settings, extensions, and sessions, and runs its own instance of
+/// Vector. The default profile uses the default data directory.
+pub fn profiles_dir() -> PathBuf {
+    default_data_dir().join("profiles")
+}
+
+/// Returns whether `name` can name a profile: it must be made of ASCII letters, digits, `-` and
+/// `_`.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Uses the data directory of the profile named `name` as the custom data directory, creating it
+/// if it doesn't exist. See [`set_custom_data_dir`].
+///
+/// # Panics
+///
+/// Panics if the name isn't a valid profile name, or in the same cases as [`set_custom_data_dir`].
+pub fn set_profile(name: &str) -> &'static PathBuf {
+    assert!(is_valid_profile_name(name), "invalid profile name {name:?}");
+    set_custom_data_dir(&profiles_dir().join(name).to_string_lossy())
+}
+
+/// Returns the name of the profile in use, or `None` when using the default profile or a custom
+/// data directory outside of the profiles directory.
+pub fn profile() -> Option<&'static str> {
+    let name = CUSTOM_DATA_DIR
+        .get()?
+        .strip_prefix(profiles_dir())
+        .ok()?
+        .to_str()?;
+    is_valid_profile_name(name).then_some(name)
+}
+
+/// Returns the names of the existing profiles, sorted, not including the default profile.
+pub fn profile_names() -> Vec<String> {
+    profile_names_in(&profiles_dir())
+}
+
+fn profile_names_in(profiles_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(profiles_dir) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            entry.file_type().ok()?.is_dir().then_some(())?;
+            entry
+                .file_name()
+                .into_string()
+                .ok()
+                .filter(|name| is_valid_profile_name(name))
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
 /// Returns the path to the temp directory used by Zed.
 pub fn temp_dir() -> &'static PathBuf {
     static TEMP_DIR: OnceLock<PathBuf> = OnceLock::new();
@@ -191,6 +261,14 @@ pub fn database_dir() -> &'static PathBuf {
     DATABASE_DIR.get_or_init(|| data_dir().join("db"))
 }
 
+/// Returns the path to the file listing the paths of the recently opened local workspaces, one per
+/// line, most recent first. The CLI reads it to complete workspace paths without opening the
+/// database.
+pub fn recent_workspaces_file() -> &'static PathBuf {
+    static RECENT_WORKSPACES_FILE: OnceLock<PathBuf> = OnceLock::new();
+    RECENT_WORKSPACES_FILE.get_or_init(|| data_dir().join("recent_workspaces"))
+}
+
 /// Returns the path to the crashes directory, if it exists for the current platform.
 pub fn crashes_dir() -> &'static Option<PathBuf> {
     static CRASHES_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
@@ -370,6 +448,12 @@ pub fn debug_adapters_dir() -> &'static PathBuf {
     DEBUG_ADAPTERS_DIR.get_or_init(|| data_dir().join("debug_adapters"))
 }
 
+/// Returns the path to the directory debug adapter sessions are recorded to.
+pub fn debug_recordings_dir() -> &'static PathBuf {
+    static DEBUG_RECORDINGS_DIR: OnceLock<PathBuf> = OnceLock::new();
+    DEBUG_RECORDINGS_DIR.get_or_init(|| data_dir().join("debug_recordings"))
+}
+
 /// Returns the path to the external agents directory
 ///
 /// This is where agent servers are downloaded to
@@ -402,6 +486,45 @@ pub fn remote_servers_dir() -> &'static PathBuf {
     REMOTE_SERVERS_DIR.get_or_init(|| data_dir().join("remote_servers"))
 }
 
+/// Returns the path to the directory where archives opened as read-only worktrees are extracted.
+pub fn archives_dir() -> &'static PathBuf {
+    static ARCHIVES_DIR: OnceLock<PathBuf> = OnceLock::new();
+    ARCHIVES_DIR.get_or_init(|| temp_dir().join("archives"))
+}
+
+/// Returns the directory under which scratch worktrees get their paths. They only exist in memory
+/// until they are materialized, so nothing is written there.
+pub fn scratch_dir() -> &'static PathBuf {
+    static SCRATCH_DIR: OnceLock<PathBuf> = OnceLock::new();
+    SCRATCH_DIR.get_or_init(|| temp_dir().join("scratch"))
+}
+
+/// Returns the path to the directory where the trigram indices of project searches are stored.
+pub fn search_index_dir() -> &'static PathBuf {
+    static SEARCH_INDEX_DIR: OnceLock<PathBuf> = OnceLock::new();
+    SEARCH_INDEX_DIR.get_or_init(|| data_dir().join("search_index"))
+}
+
+/// Returns the path to the directory where the local history of edited files is stored.
+pub fn local_history_dir() -> &'static PathBuf {
+    static LOCAL_HISTORY_DIR: OnceLock<PathBuf> = OnceLock::new();
+    LOCAL_HISTORY_DIR.get_or_init(|| database_dir().join("local_history"))
+}
+
+/// Returns the path to the directory where the buffers locked in any running instance are
+/// recorded.
+pub fn buffer_locks_dir() -> &'static PathBuf {
+    static BUFFER_LOCKS_DIR: OnceLock<PathBuf> = OnceLock::new();
+    BUFFER_LOCKS_DIR.get_or_init(|| database_dir().join("buffer_locks"))
+}
+
+/// Returns the path to the directory where the contents of buffers with unsaved changes are
+/// journaled.
+pub fn unsaved_journal_dir() -> &'static PathBuf {
+    static UNSAVED_JOURNAL_DIR: OnceLock<PathBuf> = OnceLock::new();
+    UNSAVED_JOURNAL_DIR.get_or_init(|| database_dir().join("unsaved_journal"))
+}
+
 /// Returns the path to the directory where the devcontainer CLI is installed.
 pub fn devcontainer_dir() -> &'static PathBuf {
     static DEVCONTAINER_DIR: OnceLock<PathBuf> = OnceLock::new();
@@ -558,3 +681,37 @@ pub fn global_gitignore_path() -> Option<PathBuf> {
         .get_or_init(::ignore::gitignore::gitconfig_excludes_path)
         .clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_profile_name() {
+        assert!(is_valid_profile_name("work"));
+        assert!(is_valid_profile_name("Work-2_old"));
+        assert!(!is_valid_profile_name(""));
+        assert!(!is_valid_profile_name("a b"));
+        assert!(!is_valid_profile_name("../work"));
+        assert!(!is_valid_profile_name("work/old"));
+        assert!(!is_valid_profile_name("trabajo-año"));
+    }
+
+    #[test]
+    fn test_profile_names() {
+        let profiles_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            profile_names_in(&profiles_dir.path().join("missing")),
+            Vec::<String>::new()
+        );
+
+        for name in ["work", "Personal", "a-b_c", "not valid"] {
+            std::fs::create_dir(profiles_dir.path().join(name)).unwrap();
+        }
+        std::fs::write(profiles_dir.path().join("file"), "").unwrap();
+        assert_eq!(
+            profile_names_in(profiles_dir.path()),
+            ["Personal", "a-b_c", "work"]
+        );
+    }
+}
@@ -318,6 +318,7 @@ impl Prettier {
             &prettier_dir,
             None,
             Default::default(),
+            None,
             &mut cx,
         )
         .context("prettier server creation")?;
@@ -1608,6 +1608,162 @@ struct LocalExtensionArchiveAgent {
     env: HashMap<String, String>,
 }
 
+/// Returns the target of an extension archive for the current platform.
+///
+/// The targets are keyed by `{os}-{arch}`, as in the manifests of extensions providing agent or context servers.
+pub(crate) fn extension_archive_target(
+    targets: &HashMap<String, extension::TargetConfig>,
+) -> Result<&extension::TargetConfig> {
+    // Determine platform key
+    let os = if cfg!(target_os = "macos") {
+        "darwin"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else if cfg!(target_os = "windows") {
+        "windows"
+    } else {
+        anyhow::bail!("unsupported OS");
+    };
+
+    let arch = if cfg!(target_arch = "aarch64") {
+        "aarch64"
+    } else if cfg!(target_arch = "x86_64") {
+        "x86_64"
+    } else {
+        anyhow::bail!("unsupported architecture");
+    };
+
+    let platform_key = format!("{}-{}", os, arch);
+    targets.get(&platform_key).with_context(|| {
+        format!(
+            "no target specified for platform '{}'. Available platforms: {}",
+            platform_key,
+            targets
+                .keys()
+                .map(|k| k.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+/// Downloads the archive of the target for the current platform into a directory of `dir`, unless it's already
+/// there, returning that directory and the target.
+pub(crate) async fn download_extension_archive(
+    fs: &dyn Fs,
+    http_client: Arc<dyn HttpClient>,
+    dir: &Path,
+    targets: &HashMap<String, extension::TargetConfig>,
+) -> Result<(PathBuf, extension::TargetConfig)> {
+    let target_config = extension_archive_target(targets)?;
+    let archive_url = &target_config.archive;
+
+    // Use URL as version identifier for caching
+    // Hash the URL to get a stable directory name
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    archive_url.hash(&mut hasher);
+    let url_hash = hasher.finish();
+    let version_dir = dir.join(format!("v_{:x}", url_hash));
+
+    if !fs.is_dir(&version_dir).await {
+        // Determine SHA256 for verification
+        let sha256 = if let Some(provided_sha) = &target_config.sha256 {
+            // Use provided SHA256
+            Some(provided_sha.clone())
+        } else if archive_url.starts_with("https://github.com/") {
+            // Try to fetch SHA256 from GitHub API
+            // Parse URL to extract repo and tag/file info
+            // Format: https://github.com/owner/repo/releases/download/tag/file.zip
+            if let Some(caps) = archive_url.strip_prefix("https://github.com/") {
+                let parts: Vec<&str> = caps.split('/').collect();
+                if parts.len() >= 6 && parts[2] == "releases" && parts[3] == "download" {
+                    let repo = format!("{}/{}", parts[0], parts[1]);
+                    let tag = parts[4];
+                    let filename = parts[5..].join("/");
+
+                    // Try to get release info from GitHub
+                    if let Ok(release) = ::http_client::github::get_release_by_tag_name(
+                        &repo,
+                        tag,
+                        http_client.clone(),
+                    )
+                    .await
+                    {
+                        // Find matching asset
+                        if let Some(asset) = release.assets.iter().find(|a| a.name == filename) {
+                            // Strip "sha256:" prefix if present
+                            asset.digest.as_ref().and_then(|d| {
+                                d.strip_prefix("sha256:")
+                                    .map(|s| s.to_string())
+                                    .or_else(|| Some(d.clone()))
+                            })
+                        } else {
+                            None
+                        }
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        // Determine archive type from URL
+        let asset_kind = if archive_url.ends_with(".zip") {
+            AssetKind::Zip
+        } else if archive_url.ends_with(".tar.gz") || archive_url.ends_with(".tgz") {
+            AssetKind::TarGz
+        } else {
+            anyhow::bail!("unsupported archive type in URL: {}", archive_url);
+        };
+
+        // Download and extract
+        ::http_client::github_download::download_server_binary(
+            &*http_client,
+            archive_url,
+            sha256.as_deref(),
+            &version_dir,
+            asset_kind,
+        )
+        .await?;
+    }
+
+    Ok((version_dir, target_config.clone()))
+}
+
+/// Resolves the command of an extension archive target, which must be relative to the directory it was extracted
+/// into.
+pub(crate) async fn extension_archive_command_path(
+    fs: &dyn Fs,
+    version_dir: &Path,
+    cmd: &str,
+) -> Result<PathBuf> {
+    if cmd.contains("..") {
+        anyhow::bail!("command path cannot contain '..': {}", cmd);
+    }
+
+    if cmd.starts_with("./") || cmd.starts_with(".\\") {
+        // Relative to extraction directory
+        let cmd_path = version_dir.join(&cmd[2..]);
+        anyhow::ensure!(
+            fs.is_file(&cmd_path).await,
+            "Missing command {} after extraction",
+            cmd_path.to_string_lossy()
+        );
+        Ok(cmd_path)
+    } else {
+        // On PATH
+        anyhow::bail!("command must be relative (start with './'): {}", cmd);
+    }
+}
+
 struct LocalCustomAgent {
     project_environment: Entity<ProjectEnvironment>,
     command: AgentServerCommand,
@@ -1657,118 +1813,9 @@ impl ExternalAgentServer for LocalExtensionArchiveAgent {
             let dir = paths::external_agents_dir().join(&cache_key);
             fs.create_dir(&dir).await?;
 
-            // Determine platform key
-            let os = if cfg!(target_os = "macos") {
-                "darwin"
-            } else if cfg!(target_os = "linux") {
-                "linux"
-            } else if cfg!(target_os = "windows") {
-                "windows"
-            } else {
-                anyhow::bail!("unsupported OS");
-            };
-
-            let arch = if cfg!(target_arch = "aarch64") {
-                "aarch64"
-            } else if cfg!(target_arch = "x86_64") {
-                "x86_64"
-            } else {
-                anyhow::bail!("unsupported architecture");
-            };
-
-            let platform_key = format!("{}-{}", os, arch);
-            let target_config = targets.get(&platform_key).with_context(|| {
-                format!(
-                    "no target specified for platform '{}'. Available platforms: {}",
-                    platform_key,
-                    targets
-                        .keys()
-                        .map(|k| k.as_str())
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            })?;
-
-            let archive_url = &target_config.archive;
-
-            // Use URL as version identifier for caching
-            // Hash the URL to get a stable directory name
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            let mut hasher = DefaultHasher::new();
-            archive_url.hash(&mut hasher);
-            let url_hash = hasher.finish();
-            let version_dir = dir.join(format!("v_{:x}", url_hash));
-
-            if !fs.is_dir(&version_dir).await {
-                // Determine SHA256 for verification
-                let sha256 = if let Some(provided_sha) = &target_config.sha256 {
-                    // Use provided SHA256
-                    Some(provided_sha.clone())
-                } else if archive_url.starts_with("https://github.com/") {
-                    // Try to fetch SHA256 from GitHub API
-                    // Parse URL to extract repo and tag/file info
-                    // Format: https://github.com/owner/repo/releases/download/tag/file.zip
-                    if let Some(caps) = archive_url.strip_prefix("https://github.com/") {
-                        let parts: Vec<&str> = caps.split('/').collect();
-                        if parts.len() >= 6 && parts[2] == "releases" && parts[3] == "download" {
-                            let repo = format!("{}/{}", parts[0], parts[1]);
-                            let tag = parts[4];
-                            let filename = parts[5..].join("/");
-
-                            // Try to get release info from GitHub
-                            if let Ok(release) = ::http_client::github::get_release_by_tag_name(
-                                &repo,
-                                tag,
-                                http_client.clone(),
-                            )
-                            .await
-                            {
-                                // Find matching asset
-                                if let Some(asset) =
-                                    release.assets.iter().find(|a| a.name == filename)
-                                {
-                                    // Strip "sha256:" prefix if present
-                                    asset.digest.as_ref().and_then(|d| {
-                                        d.strip_prefix("sha256:")
-                                            .map(|s| s.to_string())
-                                            .or_else(|| Some(d.clone()))
-                                    })
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-
-                // Determine archive type from URL
-                let asset_kind = if archive_url.ends_with(".zip") {
-                    AssetKind::Zip
-                } else if archive_url.ends_with(".tar.gz") || archive_url.ends_with(".tgz") {
-                    AssetKind::TarGz
-                } else {
-                    anyhow::bail!("unsupported archive type in URL: {}", archive_url);
-                };
-
-                // Download and extract
-                ::http_client::github_download::download_server_binary(
-                    &*http_client,
-                    archive_url,
-                    sha256.as_deref(),
-                    &version_dir,
-                    asset_kind,
-                )
-                .await?;
-            }
+            let (version_dir, target_config) =
+                download_extension_archive(fs.as_ref(), http_client.clone(), &dir, &targets)
+                    .await?;
 
             // Validate and resolve cmd path
             let cmd = &target_config.cmd;
@@ -1777,23 +1824,7 @@ impl ExternalAgentServer for LocalExtensionArchiveAgent {
                 // Use Zed's managed Node.js runtime
                 node_runtime.binary_path().await?
             } else {
-                if cmd.contains("..") {
-                    anyhow::bail!("command path cannot contain '..': {}", cmd);
-                }
-
-                if cmd.starts_with("./") || cmd.starts_with(".\\") {
-                    // Relative to extraction directory
-                    let cmd_path = version_dir.join(&cmd[2..]);
-                    anyhow::ensure!(
-                        fs.is_file(&cmd_path).await,
-                        "Missing command {} after extraction",
-                        cmd_path.to_string_lossy()
-                    );
-                    cmd_path
-                } else {
-                    // On PATH
-                    anyhow::bail!("command must be relative (start with './'): {}", cmd);
-                }
+                extension_archive_command_path(fs.as_ref(), &version_dir, cmd).await?
             };
 
             let command = AgentServerCommand {
@@ -0,0 +1,66 @@
+//! Buffer locks that are shared between the instances running on this machine.
+//!
+//! Locking a buffer with [`crate::Project::set_buffer_locked`] records the lock in a file named
+//! after the path of the buffer's file, under [`paths::buffer_locks_dir`]. Local projects watch
+//! that directory and lock their buffers of the recorded files, so that a lock applies in every
+//! instance, and not only in the one that set it.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use fs::{Fs, RemoveOptions};
+use futures::StreamExt as _;
+use sha2::{Digest as _, Sha256};
+
+const WATCH_LATENCY: Duration = Duration::from_millis(100);
+
+/// Whether the file is locked in any instance.
+pub(crate) async fn is_locked(fs: &dyn Fs, abs_path: &Path) -> bool {
+    fs.load(&lock_file_path(abs_path))
+        .await
+        .is_ok_and(|locked_path| Path::new(&locked_path) == abs_path)
+}
+
+pub(crate) async fn set_locked(fs: &dyn Fs, abs_path: &Path, locked: bool) -> Result<()> {
+    let lock_file_path = lock_file_path(abs_path);
+    if locked {
+        fs.create_dir(paths::buffer_locks_dir()).await?;
+        fs.atomic_write(lock_file_path, abs_path.to_string_lossy().into_owned())
+            .await
+    } else {
+        fs.remove_file(
+            &lock_file_path,
+            RemoveOptions {
+                ignore_if_not_exists: true,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+}
+
+/// Calls `on_change` whenever files are locked or unlocked in any instance, until it returns
+/// `false`.
+pub(crate) async fn watch(fs: Arc<dyn Fs>, mut on_change: impl FnMut() -> bool) -> Result<()> {
+    let locks_dir = paths::buffer_locks_dir();
+    fs.create_dir(locks_dir).await?;
+    let (mut events, _watcher) = fs.watch(locks_dir, WATCH_LATENCY).await;
+    while events.next().await.is_some() {
+        if !on_change() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn lock_file_path(abs_path: &Path) -> PathBuf {
+    let digest = format!(
+        "{:x}",
+        Sha256::digest(abs_path.to_string_lossy().as_bytes())
+    );
+    paths::buffer_locks_dir().join(&digest[..16])
+}
@@ -1,12 +1,17 @@
 use crate::{
     ProjectPath,
+    hex_buffer::HexBuffer,
+    large_file::{LargeFile, large_file_threshold},
     lsp_store::OpenLspBufferHandle,
+    project_settings::ProjectSettings,
     worktree_store::{WorktreeStore, WorktreeStoreEvent},
 };
 use anyhow::{Context as _, Result, anyhow};
 #[cfg(feature = "collab")]
 use client::Client;
 use collections::{HashMap, HashSet, hash_map};
+use encoding_rs::Encoding;
+use fs::Fs;
 #[cfg(feature = "collab")]
 use futures::channel::oneshot;
 use futures::{Future, FutureExt as _, future::Shared};
@@ -18,7 +23,10 @@ use language::proto::{
     deserialize_line_ending, deserialize_version, serialize_line_ending, serialize_version,
     split_operations,
 };
-use language::{Buffer, BufferEvent, Capability, DiskState, File as _, Language};
+use language::{
+    Buffer, BufferEvent, Capability, DiskState, File as _, Language, LineEnding,
+    language_settings::{AllLanguageSettings, language_settings},
+};
 #[cfg(feature = "collab")]
 use rpc::{AnyProtoClient, TypedEnvelope};
 use rpc::{
@@ -26,16 +34,17 @@ use rpc::{
     proto::{self},
 };
 
+use settings::{AutosaveSetting, Settings as _, SettingsLocation};
 #[cfg(feature = "collab")]
 use std::time::Instant;
-use std::{io, sync::Arc};
+use std::{io, path::Path, sync::Arc};
 use text::{BufferId, ReplicaId};
 #[cfg(feature = "collab")]
 use util::maybe;
 use util::{ResultExt as _, debug_panic, rel_path::RelPath};
 #[cfg(feature = "collab")]
 use worktree::WorktreeId;
-use worktree::{File, PathChange, ProjectEntryId, Worktree};
+use worktree::{File, PathChange, ProjectEntryId, Worktree, WorktreeSettings};
 
 /// A set of open buffers.
 pub struct BufferStore {
@@ -49,6 +58,22 @@ pub struct BufferStore {
     downstream_client: Option<(AnyProtoClient, u64)>,
     shared_buffers: HashMap<proto::PeerId, HashMap<BufferId, SharedBuffer>>,
     non_searchable_buffers: HashSet<BufferId>,
+    large_files: HashMap<ProjectPath, WeakEntity<LargeFile>>,
+    binary_paths: HashSet<ProjectPath>,
+    hex_buffers: HashMap<ProjectPath, WeakEntity<HexBuffer>>,
+    autosaving_buffers: HashSet<BufferId>,
+    buffer_usage: HashMap<BufferId, BufferUsage>,
+    buffer_memory: usize,
+    usage_clock: usize,
+    visible_paths: HashSet<ProjectPath>,
+    evicted_buffers: HashSet<BufferId>,
+}
+
+/// The memory taken up by an open buffer, approximated by the length of its text, and when it
+/// was last used.
+struct BufferUsage {
+    size: usize,
+    last_used: usize,
 }
 
 #[derive(Hash, Eq, PartialEq, Clone)]
@@ -100,11 +125,51 @@ pub enum BufferStoreEvent {
         buffer: Entity<Buffer>,
         old_file: Option<Arc<dyn language::File>>,
     },
+    /// An autosave of the buffer was skipped because it conflicts with its file on disk.
+    AutosaveSkipped(Entity<Buffer>),
+    /// The buffer was evicted because open buffers exceeded the memory budget. Holders of the
+    /// buffer should release it, along with its language server registrations, and reopen its
+    /// path when they need it again.
+    BufferEvicted {
+        buffer_id: BufferId,
+        project_path: ProjectPath,
+    },
+}
+
+/// The occasions on which buffers are autosaved, depending on the `autosave` setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutosaveTrigger {
+    /// The buffer wasn't edited for the configured delay.
+    Delay,
+    /// Focus moved away from the buffer's editor.
+    FocusChange,
+    /// The buffer's window was deactivated.
+    WindowChange,
+}
+
+impl AutosaveTrigger {
+    pub fn is_enabled(self, setting: AutosaveSetting) -> bool {
+        match setting {
+            AutosaveSetting::Off => false,
+            AutosaveSetting::AfterDelay { .. } => self == Self::Delay,
+            AutosaveSetting::OnFocusChange => self != Self::Delay,
+            AutosaveSetting::OnWindowChange => self == Self::WindowChange,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct ProjectTransaction(pub HashMap<Entity<Buffer>, language::Transaction>);
 
+/// The line endings and final newline that buffers are converted to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineEndingNormalization {
+    /// The line ending to convert buffers to, if any.
+    pub line_ending: Option<LineEnding>,
+    /// Whether buffers should end with a newline, if that should be changed.
+    pub final_newline: Option<bool>,
+}
+
 impl PartialEq for ProjectTransaction {
     fn eq(&self, other: &Self) -> bool {
         self.0.len() == other.0.len()
@@ -388,8 +453,34 @@ impl LocalBufferStore {
         worktree: Entity<Worktree>,
         path: Arc<RelPath>,
         mut has_changed_file: bool,
+        elevated: bool,
         cx: &mut Context<BufferStore>,
     ) -> Task<Result<()>> {
+        // Honor the line ending and charset that EditorConfig sets for the saved path.
+        let (line_ending, charset) = {
+            let buffer = buffer_handle.read(cx);
+            let location = SettingsLocation {
+                worktree_id: worktree.read(cx).id(),
+                path: &path,
+            };
+            let settings = AllLanguageSettings::get(Some(location), cx).language(
+                Some(location),
+                buffer.language().map(|language| language.name()).as_ref(),
+                cx,
+            );
+            (settings.line_ending, settings.charset)
+        };
+        buffer_handle.update(cx, |buffer, cx| {
+            if let Some(line_ending) = line_ending
+                && buffer.line_ending() != line_ending
+            {
+                buffer.set_line_ending(line_ending, cx);
+            }
+            if let Some((encoding, has_bom)) = charset {
+                buffer.set_encoding(encoding);
+                buffer.set_has_bom(has_bom);
+            }
+        });
         let buffer = buffer_handle.read(cx);
 
         let text = buffer.as_rope().clone();
@@ -406,7 +497,11 @@ impl LocalBufferStore {
         }
 
         let save = worktree.update(cx, |worktree, cx| {
-            worktree.write_file(path, text, line_ending, encoding, has_bom, cx)
+            if elevated {
+                worktree.write_file_elevated(path, text, line_ending, encoding, has_bom, cx)
+            } else {
+                worktree.write_file(path, text, line_ending, encoding, has_bom, cx)
+            }
         });
 
         cx.spawn(async move |_, cx| {
@@ -444,6 +539,11 @@ impl LocalBufferStore {
     ) {
         let snapshot = worktree_handle.read(cx).snapshot();
         for (path, entry_id, _) in changes {
+            // The file may no longer be binary, so it's decoded again the next time it's opened.
+            this.binary_paths.remove(&ProjectPath {
+                worktree_id: snapshot.id(),
+                path: path.clone(),
+            });
             Self::local_worktree_entry_changed(
                 this,
                 *entry_id,
@@ -585,7 +685,7 @@ impl LocalBufferStore {
             return Task::ready(Err(anyhow!("buffer doesn't have a file")));
         };
         let worktree = file.worktree.clone();
-        self.save_local_buffer(buffer, worktree, file.path.clone(), false, cx)
+        self.save_local_buffer(buffer, worktree, file.path.clone(), false, false, cx)
     }
 
     fn save_buffer_as(
@@ -601,7 +701,7 @@ impl LocalBufferStore {
         else {
             return Task::ready(Err(anyhow!("no such worktree")));
         };
-        self.save_local_buffer(buffer, worktree, path.path, true, cx)
+        self.save_local_buffer(buffer, worktree, path.path, true, false, cx)
     }
 
     fn open_buffer(
@@ -610,11 +710,35 @@ impl LocalBufferStore {
         worktree: Entity<Worktree>,
         cx: &mut Context<BufferStore>,
     ) -> Task<Result<Entity<Buffer>>> {
+        let capability = if self
+            .worktree_store
+            .read(cx)
+            .is_read_only(worktree.read(cx).id())
+        {
+            Capability::ReadOnly
+        } else {
+            Capability::ReadWrite
+        };
+        // Make the buffer's directory visible in the project panel before the rest of a large
+        // worktree has been scanned.
+        if let Some(worktree) = worktree.read(cx).as_local()
+            && let Some(parent) = path.parent()
+        {
+            worktree.prioritize_path_for_scan(parent.into());
+        }
+        let fs_and_abs_path = worktree
+            .read(cx)
+            .as_local()
+            .map(|worktree| (worktree.fs().clone(), worktree.absolutize(&path)));
         let load_file = worktree.update(cx, |worktree, cx| worktree.load_file(path.as_ref(), cx));
         cx.spawn(async move |this, cx| {
             let path = path.clone();
             let buffer = match load_file.await {
                 Ok(loaded) => {
+                    let file_read_only = match &fs_and_abs_path {
+                        Some((fs, abs_path)) => is_file_read_only(fs.as_ref(), abs_path).await,
+                        None => false,
+                    };
                     let reservation = cx.reserve_entity::<Buffer>()?;
                     let buffer_id = BufferId::from(reservation.entity_id().as_non_zero_u64());
                     let text_buffer = cx
@@ -622,11 +746,11 @@ impl LocalBufferStore {
                             text::Buffer::new(ReplicaId::LOCAL, buffer_id, loaded.text)
                         })
                         .await;
-                    cx.insert_entity(reservation, |_| {
-                        let mut buffer =
-                            Buffer::build(text_buffer, Some(loaded.file), Capability::ReadWrite);
+                    cx.insert_entity(reservation, |cx| {
+                        let mut buffer = Buffer::build(text_buffer, Some(loaded.file), capability);
                         buffer.set_encoding(loaded.encoding);
                         buffer.set_has_bom(loaded.has_bom);
+                        buffer.set_file_read_only(file_read_only, cx);
                         buffer
                     })?
                 }
@@ -643,7 +767,7 @@ impl LocalBufferStore {
                             is_local: true,
                             is_private: false,
                         })),
-                        Capability::ReadWrite,
+                        capability,
                     )
                 })?,
                 Err(e) => return Err(e),
@@ -750,6 +874,15 @@ impl BufferStore {
             shared_buffers: Default::default(),
             loading_buffers: Default::default(),
             non_searchable_buffers: Default::default(),
+            large_files: Default::default(),
+            binary_paths: Default::default(),
+            hex_buffers: Default::default(),
+            autosaving_buffers: Default::default(),
+            buffer_usage: Default::default(),
+            buffer_memory: 0,
+            usage_clock: 0,
+            visible_paths: Default::default(),
+            evicted_buffers: Default::default(),
             worktree_store,
         }
     }
@@ -776,6 +909,15 @@ impl BufferStore {
             loading_buffers: Default::default(),
             shared_buffers: Default::default(),
             non_searchable_buffers: Default::default(),
+            large_files: Default::default(),
+            binary_paths: Default::default(),
+            hex_buffers: Default::default(),
+            autosaving_buffers: Default::default(),
+            buffer_usage: Default::default(),
+            buffer_memory: 0,
+            usage_clock: 0,
+            visible_paths: Default::default(),
+            evicted_buffers: Default::default(),
             worktree_store,
         }
     }
@@ -816,6 +958,7 @@ impl BufferStore {
         cx: &mut Context<Self>,
     ) -> Task<Result<Entity<Buffer>>> {
         if let Some(buffer) = self.get_by_path(&project_path) {
+            self.touch_buffer(buffer.read(cx).remote_id());
             cx.emit(BufferStoreEvent::BufferOpened {
                 buffer: buffer.clone(),
                 project_path,
@@ -849,6 +992,13 @@ impl BufferStore {
                             this.update(cx, |this, cx| {
                                 // Record the fact that the buffer is no longer loading.
                                 this.loading_buffers.remove(&project_path);
+                                if let Err(error) = &load_result
+                                    && error
+                                        .chain()
+                                        .any(|error| error.is::<worktree::BinaryFileError>())
+                                {
+                                    this.binary_paths.insert(project_path.clone());
+                                }
 
                                 let buffer = load_result.map_err(Arc::new)?;
                                 cx.emit(BufferStoreEvent::BufferOpened {
@@ -900,6 +1050,113 @@ impl BufferStore {
         }
     }
 
+    /// Autosaves the buffers, skipping the ones that are excluded from autosaving, aren't backed
+    /// by a file on disk or conflict with their file.
+    ///
+    /// Which edits and [`AutosaveTrigger`]s lead to an autosave is decided by the UI, so that
+    /// only the user's own edits are autosaved.
+    pub fn autosave_buffers(
+        &mut self,
+        buffers: impl IntoIterator<Item = Entity<Buffer>>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let mut saves = Vec::new();
+        for buffer in buffers {
+            let is_saveable = {
+                let buffer = buffer.read(cx);
+                buffer.is_dirty()
+                    && !buffer.read_only()
+                    && buffer
+                        .file()
+                        .is_some_and(|file| matches!(file.disk_state(), DiskState::Present { .. }))
+            };
+            if !is_saveable || self.is_autosave_excluded(buffer.read(cx), cx) {
+                continue;
+            }
+            if buffer.read(cx).has_conflict() {
+                cx.emit(BufferStoreEvent::AutosaveSkipped(buffer));
+                continue;
+            }
+            let buffer_id = buffer.read(cx).remote_id();
+            self.autosaving_buffers.insert(buffer_id);
+            let save = self.save_buffer(buffer, cx);
+            saves.push(cx.spawn(async move |this, cx| {
+                let result = save.await;
+                this.update(cx, |this, _| this.autosaving_buffers.remove(&buffer_id))
+                    .ok();
+                result
+            }));
+        }
+        cx.background_spawn(async move {
+            futures::future::try_join_all(saves).await?;
+            Ok(())
+        })
+    }
+
+    /// Whether the buffer is being saved by [`Self::autosave_buffers`], rather than by an
+    /// explicit save of the user.
+    pub fn is_autosaving(&self, buffer_id: BufferId) -> bool {
+        self.autosaving_buffers.contains(&buffer_id)
+    }
+
+    /// Whether the buffer's language or path is excluded from autosaving.
+    pub fn is_autosave_excluded(&self, buffer: &Buffer, cx: &App) -> bool {
+        let Some(file) = buffer.file() else {
+            return false;
+        };
+        let worktree_settings = WorktreeSettings::get(
+            Some(SettingsLocation {
+                worktree_id: file.worktree_id(cx),
+                path: file.path(),
+            }),
+            cx,
+        );
+        worktree_settings.is_path_autosave_excluded(file.path())
+            || !language_settings(buffer.language().map(|l| l.name()), Some(file), cx).autosave
+    }
+
+    /// Saves a buffer whose file the current user isn't allowed to write, with elevated
+    /// privileges, on platforms that support it.
+    pub fn save_buffer_elevated(
+        &mut self,
+        buffer: Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let BufferStoreState::Local(this) = &self.state else {
+            return Task::ready(Err(anyhow!(
+                "buffers can only be saved with elevated privileges in local projects"
+            )));
+        };
+        let Some(file) = File::from_dyn(buffer.read(cx).file()) else {
+            return Task::ready(Err(anyhow!("buffer doesn't have a file")));
+        };
+        let worktree = file.worktree.clone();
+        let path = file.path.clone();
+        this.save_local_buffer(buffer, worktree, path, false, true, cx)
+    }
+
+    /// Updates whether the buffer's file can't be written by the current user.
+    fn refresh_file_read_only(&self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let Some(file) = File::from_dyn(buffer.read(cx).file()) else {
+            return;
+        };
+        let Some(worktree) = file.worktree.read(cx).as_local() else {
+            return;
+        };
+        let fs = worktree.fs().clone();
+        let abs_path = worktree.absolutize(&file.path);
+        let buffer = buffer.downgrade();
+        cx.spawn(async move |_, cx| {
+            let file_read_only = is_file_read_only(fs.as_ref(), &abs_path).await;
+            buffer
+                .update(cx, |buffer, cx| {
+                    buffer.set_file_read_only(file_read_only, cx)
+                })
+                .ok();
+        })
+        .detach();
+    }
+
     pub fn save_buffer_as(
         &mut self,
         buffer: Entity<Buffer>,
@@ -944,7 +1201,8 @@ impl BufferStore {
         buffer_entity.update(cx, move |_, cx| {
             cx.on_release(move |buffer, cx| {
                 handle
-                    .update(cx, |_, cx| {
+                    .update(cx, |this, cx| {
+                        this.forget_buffer_usage(buffer.remote_id());
                         cx.emit(BufferStoreEvent::BufferDropped(buffer.remote_id()))
                     })
                     .ok();
@@ -970,10 +1228,121 @@ impl BufferStore {
         }
 
         cx.subscribe(&buffer_entity, Self::on_buffer_event).detach();
+        self.update_buffer_usage(&buffer_entity, cx);
         cx.emit(BufferStoreEvent::BufferAdded(buffer_entity));
+        self.evict_buffers_over_budget(cx);
         Ok(())
     }
 
+    /// The memory taken up by open buffers, approximated by the length of their text.
+    pub fn buffer_memory(&self) -> usize {
+        self.buffer_memory
+    }
+
+    /// Sets the paths of the buffers that are visible, which are never evicted.
+    pub fn set_visible_paths(&mut self, paths: HashSet<ProjectPath>, cx: &mut Context<Self>) {
+        for path in &paths {
+            if let Some(buffer_id) = self.path_to_buffer_id.get(path).copied() {
+                self.touch_buffer(buffer_id);
+            }
+        }
+        self.visible_paths = paths;
+        self.evict_buffers_over_budget(cx);
+    }
+
+    fn touch_buffer(&mut self, buffer_id: BufferId) {
+        self.usage_clock += 1;
+        if let Some(usage) = self.buffer_usage.get_mut(&buffer_id) {
+            usage.last_used = self.usage_clock;
+        }
+        self.evicted_buffers.remove(&buffer_id);
+    }
+
+    fn update_buffer_usage(&mut self, buffer: &Entity<Buffer>, cx: &App) {
+        let buffer = buffer.read(cx);
+        let size = buffer.len();
+        let usage = self
+            .buffer_usage
+            .entry(buffer.remote_id())
+            .or_insert(BufferUsage {
+                size: 0,
+                last_used: 0,
+            });
+        self.buffer_memory = self.buffer_memory - usage.size + size;
+        usage.size = size;
+        self.touch_buffer(buffer.remote_id());
+    }
+
+    fn forget_buffer_usage(&mut self, buffer_id: BufferId) {
+        if let Some(usage) = self.buffer_usage.remove(&buffer_id) {
+            self.buffer_memory -= usage.size;
+        }
+        self.evicted_buffers.remove(&buffer_id);
+    }
+
+    /// Evicts the least recently used clean buffers that aren't visible or shared until the
+    /// memory taken up by the remaining ones fits in the `buffer_memory_budget_mb` setting.
+    fn evict_buffers_over_budget(&mut self, cx: &mut Context<Self>) {
+        if !matches!(self.state, BufferStoreState::Local(_)) {
+            return;
+        }
+        let budget_mb = ProjectSettings::get_global(cx).buffer_memory_budget_mb;
+        if budget_mb == 0 {
+            return;
+        }
+        let budget = usize::try_from(budget_mb.saturating_mul(1024 * 1024)).unwrap_or(usize::MAX);
+        let mut memory = self.buffer_memory
+            - self
+                .evicted_buffers
+                .iter()
+                .filter_map(|buffer_id| self.buffer_usage.get(buffer_id))
+                .map(|usage| usage.size)
+                .sum::<usize>();
+        if memory <= budget {
+            return;
+        }
+
+        let mut candidates = self
+            .buffer_usage
+            .iter()
+            .filter(|(buffer_id, _)| {
+                !self.evicted_buffers.contains(buffer_id)
+                    && !self
+                        .shared_buffers
+                        .values()
+                        .any(|buffers| buffers.contains_key(buffer_id))
+            })
+            .filter_map(|(buffer_id, usage)| {
+                let buffer = self.get(*buffer_id)?;
+                let buffer = buffer.read(cx);
+                let file = File::from_dyn(buffer.file())?;
+                let project_path = ProjectPath {
+                    worktree_id: file.worktree_id(cx),
+                    path: file.path.clone(),
+                };
+                if buffer.is_dirty()
+                    || buffer.has_conflict()
+                    || self.visible_paths.contains(&project_path)
+                {
+                    return None;
+                }
+                Some((usage.last_used, usage.size, *buffer_id, project_path))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(last_used, ..)| *last_used);
+        for (_, size, buffer_id, project_path) in candidates {
+            if memory <= budget {
+                break;
+            }
+            memory -= size;
+            self.evicted_buffers.insert(buffer_id);
+            cx.emit(BufferStoreEvent::BufferEvicted {
+                buffer_id,
+                project_path,
+            });
+        }
+    }
+
     pub fn buffers(&self) -> impl '_ + Iterator<Item = Entity<Buffer>> {
         self.opened_buffers
             .values()
@@ -1121,7 +1490,12 @@ impl BufferStore {
         cx: &mut Context<Self>,
     ) {
         match event {
+            BufferEvent::Edited => {
+                self.update_buffer_usage(&buffer, cx);
+                self.evict_buffers_over_budget(cx);
+            }
             BufferEvent::FileHandleChanged => {
+                self.refresh_file_read_only(&buffer, cx);
                 self.buffer_changed_file(buffer, cx);
             }
             BufferEvent::Reloaded => {
@@ -1483,6 +1857,55 @@ impl BufferStore {
         })
     }
 
+    /// Converts the line endings and final newlines of the given buffers, skipping read-only
+    /// ones, in one transaction per buffer. The buffers that had no unsaved changes beforehand
+    /// are saved afterwards.
+    pub fn normalize_line_endings(
+        &mut self,
+        buffers: HashSet<Entity<Buffer>>,
+        normalization: LineEndingNormalization,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        let mut project_transaction = ProjectTransaction::default();
+        let mut saves = Vec::new();
+        for buffer in buffers {
+            let should_save = buffer.update(cx, |buffer, cx| {
+                if buffer.read_only() {
+                    return false;
+                }
+                let was_dirty = buffer.is_dirty();
+                let mut transaction_id = normalization
+                    .line_ending
+                    .and_then(|line_ending| buffer.convert_line_ending(line_ending, cx));
+                if let Some(final_newline) = normalization.final_newline
+                    && let Some(final_newline_transaction_id) =
+                        buffer.set_final_newline(final_newline, cx)
+                {
+                    match transaction_id {
+                        Some(transaction_id) => {
+                            buffer.merge_transactions(final_newline_transaction_id, transaction_id)
+                        }
+                        None => transaction_id = Some(final_newline_transaction_id),
+                    }
+                }
+                let Some(transaction) = transaction_id
+                    .and_then(|transaction_id| buffer.get_transaction(transaction_id).cloned())
+                else {
+                    return false;
+                };
+                project_transaction.0.insert(cx.entity(), transaction);
+                !was_dirty && buffer.file().is_some()
+            });
+            if should_save {
+                saves.push(self.save_buffer(buffer, cx));
+            }
+        }
+        cx.background_spawn(async move {
+            futures::future::try_join_all(saves).await?;
+            Ok(project_transaction)
+        })
+    }
+
     pub fn reload_buffers(
         &self,
         buffers: HashSet<Entity<Buffer>>,
@@ -1499,6 +1922,154 @@ impl BufferStore {
         }
     }
 
+    /// Opens a file too large to be loaded into a buffer, reading its contents in windows
+    /// instead.
+    pub fn open_large_file(
+        &mut self,
+        project_path: ProjectPath,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<LargeFile>>> {
+        if let Some(large_file) = self
+            .large_files
+            .get(&project_path)
+            .and_then(|large_file| large_file.upgrade())
+        {
+            return Task::ready(Ok(large_file));
+        }
+        let Some(worktree) = self
+            .worktree_store
+            .read(cx)
+            .worktree_for_id(project_path.worktree_id, cx)
+        else {
+            return Task::ready(Err(anyhow!("no such worktree")));
+        };
+        let Some(local_worktree) = worktree.read(cx).as_local() else {
+            return Task::ready(Err(anyhow!(
+                "large files can only be opened in local projects"
+            )));
+        };
+        let Some(entry) = local_worktree.entry_for_path(&project_path.path).cloned() else {
+            return Task::ready(Err(anyhow!("no such file {:?}", project_path.path)));
+        };
+        let fs = local_worktree.fs().clone();
+        let abs_path = local_worktree.absolutize(&project_path.path);
+        let file = worktree::File::for_entry(entry, worktree);
+        cx.spawn(async move |this, cx| {
+            let metadata = fs
+                .metadata(&abs_path)
+                .await?
+                .with_context(|| format!("file {abs_path:?} was removed"))?;
+            let large_file = cx.new(|_| LargeFile {
+                file,
+                abs_path,
+                fs,
+                size: metadata.len,
+            })?;
+            this.update(cx, |this, _| {
+                this.large_files
+                    .insert(project_path, large_file.downgrade());
+            })?;
+            Ok(large_file)
+        })
+    }
+
+    /// Whether the file at the given path is open as a hex buffer, or failed to open as a buffer
+    /// because it is binary and hasn't changed since.
+    pub fn is_binary_path(&self, project_path: &ProjectPath) -> bool {
+        self.binary_paths.contains(project_path)
+            || self
+                .hex_buffers
+                .get(project_path)
+                .is_some_and(|hex_buffer| hex_buffer.upgrade().is_some())
+    }
+
+    /// Opens a binary file as bytes to be shown and edited in a hex representation.
+    pub fn open_hex_buffer(
+        &mut self,
+        project_path: ProjectPath,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<HexBuffer>>> {
+        if let Some(hex_buffer) = self
+            .hex_buffers
+            .get(&project_path)
+            .and_then(|hex_buffer| hex_buffer.upgrade())
+        {
+            return Task::ready(Ok(hex_buffer));
+        }
+        let Some(worktree) = self
+            .worktree_store
+            .read(cx)
+            .worktree_for_id(project_path.worktree_id, cx)
+        else {
+            return Task::ready(Err(anyhow!("no such worktree")));
+        };
+        let Some(local_worktree) = worktree.read(cx).as_local() else {
+            return Task::ready(Err(anyhow!(
+                "binary files can only be opened in local projects"
+            )));
+        };
+        let Some(entry) = local_worktree.entry_for_path(&project_path.path).cloned() else {
+            return Task::ready(Err(anyhow!("no such file {:?}", project_path.path)));
+        };
+        if entry.size >= large_file_threshold(cx) {
+            return Task::ready(Err(anyhow!(
+                "file {:?} is too large to be edited",
+                project_path.path
+            )));
+        }
+        let fs = local_worktree.fs().clone();
+        let abs_path = local_worktree.absolutize(&project_path.path);
+        let file = worktree::File::for_entry(entry, worktree);
+        cx.spawn(async move |this, cx| {
+            let mtime = fs.metadata(&abs_path).await?.map(|metadata| metadata.mtime);
+            let bytes = fs.load_bytes(&abs_path).await?;
+            let hex_buffer = cx.new(|_| HexBuffer {
+                file,
+                abs_path,
+                fs,
+                bytes,
+                mtime,
+                is_dirty: false,
+            })?;
+            this.update(cx, |this, _| {
+                this.hex_buffers
+                    .insert(project_path, hex_buffer.downgrade());
+            })?;
+            Ok(hex_buffer)
+        })
+    }
+
+    /// Reloads a buffer from disk, decoding its file with the given encoding instead of the
+    /// detected one. The buffer is saved with that encoding afterwards.
+    pub fn reopen_with_encoding(
+        &self,
+        buffer: Entity<Buffer>,
+        encoding: &'static Encoding,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        match &self.state {
+            BufferStoreState::Local(_) => {}
+            #[cfg(feature = "collab")]
+            BufferStoreState::Remote(_) => {
+                return Task::ready(Err(anyhow!(
+                    "reopening buffers with an encoding is not supported in remote projects"
+                )));
+            }
+        }
+        let buffer_ref = buffer.read(cx);
+        if buffer_ref.file().and_then(|file| file.as_local()).is_none() {
+            return Task::ready(Err(anyhow!("buffer has no file to reopen")));
+        }
+        if buffer_ref.is_dirty() {
+            return Task::ready(Err(anyhow!("cannot reopen a buffer with unsaved changes")));
+        }
+        let reload = buffer.update(cx, |buffer, cx| buffer.reload_with_encoding(encoding, cx));
+        cx.background_spawn(async move {
+            reload.await?;
+            Ok(())
+        })
+    }
+
     #[cfg(feature = "collab")]
     async fn handle_reload_buffers(
         this: Entity<Self>,
@@ -1758,3 +2329,11 @@ fn is_not_found_error(error: &anyhow::Error) -> bool {
         .downcast_ref::<io::Error>()
         .is_some_and(|err| err.kind() == io::ErrorKind::NotFound)
 }
+
+async fn is_file_read_only(fs: &dyn Fs, abs_path: &Path) -> bool {
+    fs.metadata(abs_path)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|metadata| metadata.is_read_only)
+}
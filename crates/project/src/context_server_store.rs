@@ -105,6 +105,47 @@ pub enum ContextServerConfiguration {
     },
 }
 
+/// Replaces the `${settings.<key>}` placeholders in the arguments and environment of a context server with the
+/// values of its settings, where `<key>` is a dot-separated path into the settings.
+fn expand_settings_placeholders(
+    mut command: ContextServerCommand,
+    settings: &serde_json::Value,
+) -> Result<ContextServerCommand> {
+    fn expand(template: &str, settings: &serde_json::Value) -> Result<String> {
+        let mut expanded = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("${settings.") {
+            expanded.push_str(&rest[..start]);
+            let placeholder = &rest[start + "${settings.".len()..];
+            let end = placeholder
+                .find('}')
+                .with_context(|| format!("unterminated placeholder in {template:?}"))?;
+            let key = &placeholder[..end];
+            let value = key
+                .split('.')
+                .try_fold(settings, |value, segment| value.get(segment))
+                .with_context(|| format!("the setting {key:?} is not set"))?;
+            match value {
+                serde_json::Value::String(value) => expanded.push_str(value),
+                value => expanded.push_str(&value.to_string()),
+            }
+            rest = &placeholder[end + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    for arg in &mut command.args {
+        *arg = expand(arg, settings)?;
+    }
+    if let Some(env) = command.env.as_mut() {
+        for value in env.values_mut() {
+            *value = expand(value, settings)?;
+        }
+    }
+    Ok(command)
+}
+
 impl ContextServerConfiguration {
     pub fn command(&self) -> Option<&ContextServerCommand> {
         match self {
@@ -137,6 +178,9 @@ impl ContextServerConfiguration {
 
                 match descriptor.command(worktree_store, cx).await {
                     Ok(command) => {
+                        let command = expand_settings_placeholders(command, &settings)
+                            .with_context(|| format!("context server {}", id.0))
+                            .log_err()?;
                         Some(ContextServerConfiguration::Extension { command, settings })
                     }
                     Err(e) => {
@@ -468,6 +512,12 @@ impl ContextServerStore {
             .servers
             .remove(id)
             .context("Context server not found")?;
+        // Dropping the state of a starting server cancels its start, but the process may already be running.
+        if let ContextServerState::Starting { server, .. }
+        | ContextServerState::Running { server, .. } = &state
+        {
+            server.stop().log_err();
+        }
         drop(state);
         cx.emit(Event::ServerStatusChanged {
             server_id: id.clone(),
@@ -672,6 +722,33 @@ mod tests {
     use std::{cell::RefCell, path::PathBuf, rc::Rc};
     use util::path;
 
+    #[test]
+    fn test_expand_settings_placeholders() {
+        let settings = json!({"api_key": "secret", "server": {"port": 8080}});
+        let command = ContextServerCommand {
+            path: PathBuf::from("/bin/server"),
+            args: vec!["--port=${settings.server.port}".into(), "--verbose".into()],
+            env: Some(HashMap::from_iter([(
+                "API_KEY".into(),
+                "${settings.api_key}".into(),
+            )])),
+            timeout: None,
+        };
+
+        let expanded = expand_settings_placeholders(command.clone(), &settings).unwrap();
+        assert_eq!(expanded.args, vec!["--port=8080", "--verbose"]);
+        assert_eq!(
+            expanded.env.unwrap().get("API_KEY").map(String::as_str),
+            Some("secret")
+        );
+
+        let missing = ContextServerCommand {
+            args: vec!["${settings.token}".into()],
+            ..command
+        };
+        assert!(expand_settings_placeholders(missing, &settings).is_err());
+    }
+
     #[gpui::test]
     async fn test_context_server_status(cx: &mut TestAppContext) {
         const SERVER_1_ID: &str = "mcp-1";
@@ -3,12 +3,19 @@ use std::sync::Arc;
 use anyhow::Result;
 use context_server::ContextServerCommand;
 use extension::{
-    ContextServerConfiguration, Extension, ExtensionContextServerProxy, ExtensionHostProxy,
-    ProjectDelegate,
+    ContextServerConfiguration, ContextServerManifestEntry, Extension,
+    ExtensionCapabilityProxy as _, ExtensionContextServerProxy, ExtensionHostProxy,
+    ExtensionManifest, ProjectDelegate,
 };
+use fs::Fs;
 use gpui::{App, AsyncApp, Entity, Task};
 
-use crate::worktree_store::WorktreeStore;
+use crate::{
+    agent_server_store::{
+        download_extension_archive, extension_archive_command_path, extension_archive_target,
+    },
+    worktree_store::WorktreeStore,
+};
 
 use super::registry::{self, ContextServerDescriptorRegistry};
 
@@ -94,6 +101,84 @@ impl registry::ContextServerDescriptor for ContextServerDescriptor {
     }
 }
 
+/// A context server whose manifest entry declares the archives to download it from, rather than the WASM library of
+/// its extension launching it.
+///
+/// Downloading the archive and running its command are checked against the capabilities of the extension, as when
+/// its WASM library does either, so the user is asked before the first launch.
+struct DeclaredContextServerDescriptor {
+    manifest: Arc<ExtensionManifest>,
+    id: Arc<str>,
+    entry: ContextServerManifestEntry,
+}
+
+impl registry::ContextServerDescriptor for DeclaredContextServerDescriptor {
+    fn command(
+        &self,
+        _worktree_store: Entity<WorktreeStore>,
+        cx: &AsyncApp,
+    ) -> Task<Result<ContextServerCommand>> {
+        let id = self.id.clone();
+        let dir = paths::extensions_dir()
+            .join("work")
+            .join(self.manifest.id.as_ref())
+            .join("context_servers")
+            .join(self.id.as_ref());
+        let manifest = self.manifest.clone();
+        let entry = self.entry.clone();
+        cx.spawn(async move |cx| {
+            let target = extension_archive_target(&entry.targets)?.clone();
+            let (proxy, fs, http_client) = cx.update(|cx| {
+                (
+                    ExtensionHostProxy::global(cx),
+                    <dyn Fs>::global(cx),
+                    cx.http_client(),
+                )
+            })?;
+            cx.update(|cx| {
+                proxy.ensure_download_granted(manifest.clone(), target.archive.clone(), cx)
+            })?
+            .await?;
+            cx.update(|cx| {
+                proxy.ensure_exec_granted(
+                    manifest.clone(),
+                    target.cmd.clone(),
+                    target.args.clone(),
+                    cx,
+                )
+            })?
+            .await?;
+
+            fs.create_dir(&dir).await?;
+            let (version_dir, target) =
+                download_extension_archive(fs.as_ref(), http_client, &dir, &entry.targets).await?;
+            let path =
+                extension_archive_command_path(fs.as_ref(), &version_dir, &target.cmd).await?;
+
+            let mut env = entry.env;
+            env.extend(target.env);
+            let command = ContextServerCommand {
+                path,
+                args: target.args,
+                env: Some(env.into_iter().collect()),
+                timeout: None,
+            };
+
+            log::debug!("loaded declared command for context server {id}: {command:?}");
+
+            Ok(command)
+        })
+    }
+
+    fn configuration(
+        &self,
+        _worktree_store: Entity<WorktreeStore>,
+        _cx: &AsyncApp,
+    ) -> Task<Result<Option<ContextServerConfiguration>>> {
+        Task::ready(Ok(None))
+    }
+}
+
 struct ContextServerDescriptorRegistryProxy {
     context_server_factory_registry: Entity<ContextServerDescriptorRegistry>,
 }
@@ -111,6 +196,27 @@ impl ExtensionContextServerProxy for ContextServerDescriptorRegistryProxy {
             });
     }
 
+    fn register_declared_context_server(
+        &self,
+        manifest: Arc<ExtensionManifest>,
+        server_id: Arc<str>,
+        entry: ContextServerManifestEntry,
+        cx: &mut App,
+    ) {
+        self.context_server_factory_registry
+            .update(cx, |registry, cx| {
+                registry.register_context_server_descriptor(
+                    server_id.clone(),
+                    Arc::new(DeclaredContextServerDescriptor {
+                        manifest,
+                        id: server_id,
+                        entry,
+                    }) as Arc<dyn registry::ContextServerDescriptor>,
+                    cx,
+                )
+            });
+    }
+
     fn unregister_context_server(&self, server_id: Arc<str>, cx: &mut App) {
         self.context_server_factory_registry
             .update(cx, |registry, cx| {
@@ -14,9 +14,15 @@
 pub mod breakpoint_store;
 pub mod dap_command;
 pub mod dap_store;
+pub(crate) mod generators;
 pub mod locators;
 mod memory;
+pub mod processes;
+pub mod remote;
+pub mod repl;
+pub mod restart_on_save;
 pub mod session;
+pub mod watch_store;
 
 #[cfg(any(feature = "test-support", test))]
 pub mod test;
@@ -55,8 +55,24 @@ mod breakpoints_in_file {
     #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
     pub struct BreakpointSessionState {
         /// Session-specific identifier for the breakpoint, as assigned by Debug Adapter.
-        pub id: u64,
+        pub id: Option<u64>,
         pub verified: bool,
+        /// The row the Debug Adapter actually placed the breakpoint at, if it differs from the requested one.
+        pub row: Option<u32>,
+    }
+
+    impl BreakpointSessionState {
+        pub(crate) fn from_dap(breakpoint: &dap::Breakpoint, requested_row: Option<u32>) -> Self {
+            let row = breakpoint
+                .line
+                .and_then(|line| u32::try_from(line.checked_sub(1)?).ok())
+                .filter(|row| Some(*row) != requested_row);
+            Self {
+                id: breakpoint.id,
+                verified: breakpoint.verified,
+                row,
+            }
+        }
     }
     #[derive(Clone)]
     pub(super) struct BreakpointsInFile {
@@ -247,6 +263,7 @@ impl BreakpointStore {
                             let state = BreakpointSessionState {
                                 id: state.id,
                                 verified: state.verified,
+                                row: state.row,
                             };
                             (SessionId::from_proto(*session_id), state)
                         })
@@ -337,58 +354,92 @@ impl BreakpointStore {
     pub(crate) fn update_session_breakpoint(
         &mut self,
         session_id: SessionId,
-        _: dap::BreakpointEventReason,
+        reason: dap::BreakpointEventReason,
         breakpoint: dap::Breakpoint,
+        cx: &mut Context<Self>,
     ) {
-        maybe!({
+        let updated = maybe!({
             let event_id = breakpoint.id?;
 
-            let state = self
-                .breakpoints
-                .values_mut()
-                .find_map(|breakpoints_in_file| {
-                    breakpoints_in_file
-                        .breakpoints
-                        .iter_mut()
-                        .find_map(|state| {
-                            let state = state.session_state.get_mut(&session_id)?;
-
-                            if state.id == event_id {
-                                Some(state)
-                            } else {
-                                None
-                            }
-                        })
-                })?;
-
-            state.verified = breakpoint.verified;
+            let (snapshot, state) =
+                self.breakpoints
+                    .values_mut()
+                    .find_map(|breakpoints_in_file| {
+                        let snapshot = breakpoints_in_file.buffer.read(cx).text_snapshot();
+                        breakpoints_in_file
+                            .breakpoints
+                            .iter_mut()
+                            .find_map(|stateful| {
+                                let state = stateful.session_state.get(&session_id)?;
+                                (state.id == Some(event_id)).then_some(stateful)
+                            })
+                            .map(|stateful| (snapshot, stateful))
+                    })?;
+
+            if matches!(reason, dap::BreakpointEventReason::Removed) {
+                state.session_state.remove(&session_id);
+            } else {
+                let requested_row = snapshot
+                    .summary_for_anchor::<PointUtf16>(state.position())
+                    .row;
+                state.session_state.insert(
+                    session_id,
+                    BreakpointSessionState::from_dap(&breakpoint, Some(requested_row)),
+                );
+            }
             Some(())
         });
+
+        if updated.is_some() {
+            cx.notify();
+        }
     }
 
     pub(super) fn mark_breakpoints_verified(
         &mut self,
         session_id: SessionId,
         abs_path: &Path,
-
         it: impl Iterator<Item = (BreakpointWithPosition, BreakpointSessionState)>,
+        cx: &mut Context<Self>,
     ) {
-        maybe!({
-            let breakpoints = self.breakpoints.get_mut(abs_path)?;
-            for (breakpoint, state) in it {
-                if let Some(to_update) = breakpoints
-                    .breakpoints
-                    .iter_mut()
-                    .find(|bp| *bp.position() == breakpoint.position)
-                {
-                    to_update
-                        .session_state
-                        .entry(session_id)
-                        .insert_entry(state);
-                }
+        let Some(breakpoints) = self.breakpoints.get_mut(abs_path) else {
+            return;
+        };
+        // Breakpoints that were not part of the request (e.g. disabled ones) are no longer known to the session.
+        for breakpoint in &mut breakpoints.breakpoints {
+            breakpoint.session_state.remove(&session_id);
+        }
+        for (breakpoint, state) in it {
+            if let Some(to_update) = breakpoints
+                .breakpoints
+                .iter_mut()
+                .find(|bp| *bp.position() == breakpoint.position)
+            {
+                to_update
+                    .session_state
+                    .entry(session_id)
+                    .insert_entry(state);
             }
-            Some(())
-        });
+        }
+        cx.notify();
+    }
+
+    /// Returns the breakpoints in the given file, along with their state as reported by the given session.
+    pub fn session_breakpoints(
+        &self,
+        abs_path: &Path,
+        session_id: SessionId,
+    ) -> Vec<(BreakpointWithPosition, Option<BreakpointSessionState>)> {
+        self.breakpoints
+            .get(abs_path)
+            .map(|breakpoints| {
+                breakpoints
+                    .breakpoints
+                    .iter()
+                    .map(|bp| (bp.bp.clone(), bp.session_state.get(&session_id).copied()))
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     pub fn abs_path_from_buffer(buffer: &Entity<Buffer>, cx: &App) -> Option<Arc<Path>> {
@@ -987,6 +1038,7 @@ impl Breakpoint {
                         proto::BreakpointSessionState {
                             id: state.id,
                             verified: state.verified,
+                            row: state.row,
                         },
                     )
                 })
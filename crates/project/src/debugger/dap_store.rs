@@ -1,11 +1,14 @@
 use super::{
     breakpoint_store::BreakpointStore,
     dap_command::EvaluateCommand,
-    locators,
+    generators, locators,
+    processes::{self, ProcessInfo},
+    remote::RemoteDebugConfig,
+    restart_on_save::{RestartAction, RestartOnSave},
     session::{self, Session, SessionStateEvent},
 };
 use crate::{
-    InlayHint, InlayHintLabel, ProjectEnvironment, ResolveState,
+    InlayHint, InlayHintLabel, ProjectEnvironment, ResolveState, TaskSourceKind,
     debugger::session::SessionQuirks,
     project_settings::{DapBinary, ProjectSettings},
     worktree_store::WorktreeStore,
@@ -15,16 +18,20 @@ use async_trait::async_trait;
 use collections::HashMap;
 use dap::{
     Capabilities, DapRegistry, DebugRequest, EvaluateArgumentsContext, StackFrameId,
-    adapters::{DapDelegate, DebugAdapterBinary, DebugAdapterName, DebugTaskDefinition},
+    adapters::{
+        DapDelegate, DebugAdapterBinary, DebugAdapterName, DebugTaskDefinition, TcpArguments,
+    },
     client::SessionId,
     inline_value::VariableLookupKind,
     messages::Message,
+    transport::TcpTransport,
 };
 use fs::{Fs, RemoveOptions};
 use futures::{
-    TryStreamExt as _,
+    AsyncBufReadExt as _, StreamExt as _, TryStreamExt as _,
     channel::mpsc::{self, UnboundedSender},
     future::{Shared, join_all},
+    io::BufReader,
 };
 use gpui::{App, AppContext, Context, Entity, EventEmitter, SharedString, Task};
 use http_client::HttpClient;
@@ -33,17 +40,22 @@ use node_runtime::NodeRuntime;
 use serde::{Deserialize, Serialize};
 use settings::InlayHintKind;
 use settings::{Settings, SettingsLocation, WorktreeId};
+use smol::process::Child;
 use std::{
     borrow::Borrow,
     collections::BTreeMap,
     ffi::OsStr,
+    net::Ipv4Addr,
     path::{Path, PathBuf},
     sync::{Arc, Once},
 };
-use task::{DebugScenario, SpawnInTerminal, TaskContext, TaskTemplate};
+use task::{DebugScenario, SpawnInTerminal, TaskContext, TaskTemplate, VectorDebugConfig};
 use util::{ResultExt as _, rel_path::RelPath};
 use worktree::Worktree;
 
+/// How long to wait for a forwarded adapter port to accept connections, unless the configuration says otherwise.
+const SSH_TUNNEL_TIMEOUT_MS: u64 = 10_000;
+
 #[derive(Debug)]
 pub enum DapStoreEvent {
     DebugClientStarted(SessionId),
@@ -56,6 +68,24 @@ pub enum DapStoreEvent {
     Notification(String),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionGroupId(pub u32);
+
+/// Sessions launched together from a compound configuration, e.g. a client and the server it talks to.
+#[derive(Clone, Debug)]
+pub struct SessionGroup {
+    pub label: SharedString,
+    /// Whether all sessions of the group are stopped as soon as one of them shuts down.
+    pub stop_all: bool,
+    session_ids: Vec<SessionId>,
+}
+
+impl SessionGroup {
+    pub fn session_ids(&self) -> &[SessionId] {
+        &self.session_ids
+    }
+}
+
 enum DapStoreMode {
     Local(LocalDapStore),
 }
@@ -75,7 +105,12 @@ pub struct DapStore {
     worktree_store: Entity<WorktreeStore>,
     sessions: BTreeMap<SessionId, Entity<Session>>,
     next_session_id: u32,
+    session_groups: BTreeMap<SessionGroupId, SessionGroup>,
+    next_session_group_id: u32,
+    ssh_tunnels: HashMap<SessionId, Child>,
     adapter_options: BTreeMap<DebugAdapterName, Arc<PersistedAdapterOptions>>,
+    /// Whether sessions persist their watch expressions in the database.
+    persist_watch_expressions: bool,
 }
 
 impl EventEmitter<DapStoreEvent> for DapStore {}
@@ -113,6 +148,7 @@ impl DapStore {
         worktree_store: Entity<WorktreeStore>,
         breakpoint_store: Entity<BreakpointStore>,
         is_headless: bool,
+        persist_watch_expressions: bool,
         cx: &mut Context<Self>,
     ) -> Self {
         let mode = DapStoreMode::Local(LocalDapStore {
@@ -124,7 +160,14 @@ impl DapStore {
             is_headless,
         });
 
-        Self::new(mode, breakpoint_store, worktree_store, fs, cx)
+        Self::new(
+            mode,
+            breakpoint_store,
+            worktree_store,
+            fs,
+            persist_watch_expressions,
+            cx,
+        )
     }
 
     fn as_local(&self) -> Option<&LocalDapStore> {
@@ -138,6 +181,7 @@ impl DapStore {
         breakpoint_store: Entity<BreakpointStore>,
         worktree_store: Entity<WorktreeStore>,
         fs: Arc<dyn Fs>,
+        persist_watch_expressions: bool,
         cx: &mut Context<Self>,
     ) -> Self {
         cx.background_spawn(async move {
@@ -176,8 +220,43 @@ impl DapStore {
             breakpoint_store,
             worktree_store,
             sessions: Default::default(),
+            session_groups: Default::default(),
+            next_session_group_id: 0,
+            ssh_tunnels: HashMap::default(),
             adapter_options: Default::default(),
+            persist_watch_expressions,
+        }
+    }
+
+    /// Lists the processes that a debug session could attach to.
+    pub fn attachable_processes(&self, cx: &App) -> Task<Vec<ProcessInfo>> {
+        match &self.mode {
+            DapStoreMode::Local(_) => cx.background_spawn(async { processes::list_processes() }),
+        }
+    }
+
+    /// Fills the process to attach to into an attach configuration, and converts it into the adapter's own format.
+    pub fn attach_scenario(
+        &self,
+        mut definition: VectorDebugConfig,
+        process_id: u32,
+        cx: &App,
+    ) -> Task<Result<DebugScenario>> {
+        match &mut definition.request {
+            DebugRequest::Attach(attach) => attach.process_id = Some(process_id),
+            DebugRequest::Launch(_) => {
+                return Task::ready(Err(anyhow!(
+                    "Cannot attach to a process with a launch configuration"
+                )));
+            }
         }
+        let Some(adapter) = DapRegistry::global(cx).adapter(&definition.adapter) else {
+            return Task::ready(Err(anyhow!(
+                "{}: is not a valid adapter name",
+                definition.adapter
+            )));
+        };
+        cx.background_spawn(async move { adapter.config_from_zed_format(definition).await })
     }
 
     pub fn get_debug_adapter_binary(
@@ -266,6 +345,50 @@ impl DapStore {
         })
     }
 
+    /// Synthesizes debug configurations for the binaries, tests and scripts found in the worktree.
+    /// Adds the scenarios generated for the targets of the worktree to the configured ones, unless a configured
+    /// scenario already has the same label.
+    pub fn with_generated_debug_scenarios(
+        &self,
+        worktree: &Entity<Worktree>,
+        configured: Vec<(TaskSourceKind, DebugScenario)>,
+        cx: &mut App,
+    ) -> Task<Vec<(TaskSourceKind, DebugScenario)>> {
+        let generated = self.generated_debug_scenarios(worktree, cx);
+        cx.background_spawn(async move { generators::merge_scenarios(configured, generated.await) })
+    }
+
+    fn generated_debug_scenarios(
+        &self,
+        worktree: &Entity<Worktree>,
+        cx: &mut App,
+    ) -> Task<Vec<(TaskSourceKind, DebugScenario)>> {
+        let fs = self.local().fs.clone();
+        let snapshot = worktree.read(cx).snapshot();
+        let locators = DapRegistry::global(cx).locators();
+
+        cx.background_spawn(async move {
+            let mut scenarios = Vec::new();
+            for generator in generators::generators() {
+                let adapter = generator.adapter();
+                let kind = TaskSourceKind::Language {
+                    name: generator.language(),
+                };
+                for task in generator.tasks(fs.as_ref(), &snapshot).await {
+                    for locator in locators.values() {
+                        if let Some(scenario) =
+                            locator.create_scenario(&task, &task.label, &adapter).await
+                        {
+                            scenarios.push((kind.clone(), scenario));
+                            break;
+                        }
+                    }
+                }
+            }
+            scenarios
+        })
+    }
+
     pub fn run_debug_locator(
         &mut self,
         locator_name: &str,
@@ -361,15 +484,66 @@ impl DapStore {
     ) -> Task<Result<()>> {
         let dap_store = cx.weak_entity();
         let console = session.update(cx, |session, cx| session.console_output(cx));
+        let remote = match RemoteDebugConfig::from_config(&definition.config) {
+            Ok(remote) => remote,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let restart_on_save =
+            match RestartOnSave::from_config(&definition.config, worktree.read(cx).path_style()) {
+                Ok(restart_on_save) => restart_on_save,
+                Err(error) => return Task::ready(Err(error)),
+            };
+        session.update(cx, |session, _| {
+            session.set_path_mapper(remote.path_mapper);
+            session.set_restart_on_save(restart_on_save);
+        });
 
         cx.spawn({
             let session = session.clone();
             async move |this, cx| {
-                let binary = this
+                let mut binary = this
                     .update(cx, |this, cx| {
-                        this.get_debug_adapter_binary(definition.clone(), &worktree, console, cx)
+                        this.get_debug_adapter_binary(
+                            definition.clone(),
+                            &worktree,
+                            console.clone(),
+                            cx,
+                        )
                     })?
                     .await?;
+                if let Some(ssh) = remote.ssh {
+                    let local_port = TcpTransport::unused_port(Ipv4Addr::LOCALHOST).await?;
+                    let mut tunnel = ssh.start(local_port)?;
+                    if let Some(stderr) = tunnel.stderr.take() {
+                        // Surface why the tunnel couldn't be established, e.g. failed authentication.
+                        cx.background_spawn(async move {
+                            let mut lines = BufReader::new(stderr).lines();
+                            while let Some(line) = lines.next().await {
+                                let Some(line) = line.log_err() else {
+                                    break;
+                                };
+                                log::warn!("ssh: {line}");
+                                console.unbounded_send(format!("ssh: {line}")).ok();
+                            }
+                        })
+                        .detach();
+                    }
+                    let session_id = session.read_with(cx, |session, _| session.session_id())?;
+                    this.update(cx, |this, _| {
+                        this.ssh_tunnels.insert(session_id, tunnel);
+                    })?;
+                    // The adapter is already running on the remote host, we only need to connect to it.
+                    binary.command = None;
+                    binary.connection = Some(TcpArguments {
+                        host: Ipv4Addr::LOCALHOST,
+                        port: local_port,
+                        timeout: binary
+                            .connection
+                            .as_ref()
+                            .and_then(|connection| connection.timeout)
+                            .or(Some(SSH_TUNNEL_TIMEOUT_MS)),
+                    });
+                }
                 session
                     .update(cx, |session, cx| {
                         session.boot(binary, worktree, dap_store, cx)
@@ -379,6 +553,52 @@ impl DapStore {
         })
     }
 
+    /// Restarts the sessions whose `restartOnSave` globs match the saved buffer's file, or sends them their
+    /// hot-reload request.
+    pub fn buffer_saved(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let Some(file) = buffer.read(cx).file() else {
+            return;
+        };
+        let worktree_id = file.worktree_id(cx);
+        let path = file.path().clone();
+
+        let sessions = self.sessions.values().cloned().collect::<Vec<_>>();
+        for session in sessions {
+            let (restart_on_save, client) = {
+                let session = session.read(cx);
+                let Some(restart_on_save) = session.restart_on_save().cloned() else {
+                    continue;
+                };
+                if session.is_terminated()
+                    || session
+                        .worktree()
+                        .is_none_or(|worktree| worktree.read(cx).id() != worktree_id)
+                    || !restart_on_save.matches(&path)
+                {
+                    continue;
+                }
+                (restart_on_save, session.adapter_client())
+            };
+
+            match &restart_on_save.action {
+                RestartAction::Restart => {
+                    session.update(cx, |session, cx| session.restart(None, cx));
+                }
+                RestartAction::CustomRequest { command, arguments } => {
+                    let Some(client) = client else {
+                        continue;
+                    };
+                    let command = command.clone();
+                    let arguments = arguments.clone();
+                    cx.background_spawn(
+                        async move { client.custom_request(&command, arguments).await },
+                    )
+                    .detach_and_log_err(cx);
+                }
+            }
+        }
+    }
+
     pub fn session_by_id(
         &self,
         session_id: impl Borrow<SessionId>,
@@ -391,6 +611,71 @@ impl DapStore {
         self.sessions.values()
     }
 
+    pub fn new_session_group(&mut self, label: SharedString, stop_all: bool) -> SessionGroupId {
+        let group_id = SessionGroupId(util::post_inc(&mut self.next_session_group_id));
+        self.session_groups.insert(
+            group_id,
+            SessionGroup {
+                label,
+                stop_all,
+                session_ids: Vec::new(),
+            },
+        );
+        group_id
+    }
+
+    pub fn add_session_to_group(
+        &mut self,
+        group_id: SessionGroupId,
+        session_id: SessionId,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            self.sessions.contains_key(&session_id),
+            "Could not find session: {session_id:?}"
+        );
+        anyhow::ensure!(
+            self.group_for_session(session_id).is_none(),
+            "Session {session_id:?} already belongs to a group"
+        );
+        let group = self
+            .session_groups
+            .get_mut(&group_id)
+            .with_context(|| format!("Could not find session group: {group_id:?}"))?;
+        group.session_ids.push(session_id);
+        Ok(())
+    }
+
+    pub fn session_group(&self, group_id: SessionGroupId) -> Option<&SessionGroup> {
+        self.session_groups.get(&group_id)
+    }
+
+    pub fn group_for_session(&self, session_id: SessionId) -> Option<SessionGroupId> {
+        self.session_groups
+            .iter()
+            .find(|(_, group)| group.session_ids.contains(&session_id))
+            .map(|(group_id, _)| *group_id)
+    }
+
+    pub fn shutdown_session_group(
+        &mut self,
+        group_id: SessionGroupId,
+        cx: &mut Context<Self>,
+    ) -> Task<()> {
+        let Some(group) = self.session_groups.remove(&group_id) else {
+            return Task::ready(());
+        };
+        let tasks = group
+            .session_ids
+            .into_iter()
+            .filter(|session_id| self.sessions.contains_key(session_id))
+            .map(|session_id| self.shutdown_session(session_id, cx))
+            .collect::<Vec<_>>();
+
+        cx.background_spawn(async move {
+            join_all(tasks).await;
+        })
+    }
+
     pub fn capabilities_by_id(
         &self,
         session_id: impl Borrow<SessionId>,
@@ -559,6 +844,7 @@ impl DapStore {
         let Some(session) = self.sessions.remove(&session_id) else {
             return Task::ready(Err(anyhow!("Could not find session: {:?}", session_id)));
         };
+        let ssh_tunnel = self.ssh_tunnels.remove(&session_id);
 
         let shutdown_children = session
             .read(cx)
@@ -587,6 +873,19 @@ impl DapStore {
             None
         };
 
+        let shutdown_group_task = self.group_for_session(session_id).and_then(|group_id| {
+            let group = self.session_groups.get_mut(&group_id)?;
+            group.session_ids.retain(|id| *id != session_id);
+            if group.stop_all {
+                Some(self.shutdown_session_group(group_id, cx))
+            } else {
+                if group.session_ids.is_empty() {
+                    self.session_groups.remove(&group_id);
+                }
+                None
+            }
+        });
+
         let shutdown_task = session.update(cx, |this, cx| this.shutdown(cx));
 
         cx.emit(DapStoreEvent::DebugClientShutdown(session_id));
@@ -597,6 +896,11 @@ impl DapStore {
             }
 
             shutdown_task.await;
+            drop(ssh_tunnel);
+
+            if let Some(group_task) = shutdown_group_task {
+                group_task.await;
+            }
 
             if let Some(parent_task) = shutdown_parent_task {
                 parent_task.await?;
@@ -644,6 +948,29 @@ impl DapStore {
     pub fn all_adapter_options(&self) -> &BTreeMap<DebugAdapterName, Arc<PersistedAdapterOptions>> {
         &self.adapter_options
     }
+
+    /// Persists the watch expressions of sessions in a test project.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn persist_watch_expressions_for_test(&mut self) {
+        self.persist_watch_expressions = true;
+    }
+
+    /// Returns the database key a session persists its watch expressions under, which it shares with earlier
+    /// sessions of the same debug scenario in the same worktree.
+    pub(crate) fn watch_expressions_key(
+        &self,
+        worktree: &Worktree,
+        adapter: &DebugAdapterName,
+        label: Option<&str>,
+    ) -> Option<String> {
+        if !self.persist_watch_expressions {
+            return None;
+        }
+        // Paths and labels may contain any separator, so they are encoded as a JSON array.
+        let scenario =
+            serde_json::to_string(&(worktree.abs_path(), adapter.0.as_ref(), label?)).ok()?;
+        Some(format!("debug_session_watch_expressions-{scenario}"))
+    }
 }
 
 #[derive(Clone)]
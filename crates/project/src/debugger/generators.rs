@@ -0,0 +1,50 @@
+//! Generators synthesize debug configurations for the targets found in a worktree (binaries, tests, scripts), so that
+//! a project can be debugged without writing a `debug.json` first.
+//!
+//! Each generator lists the commands that run its targets; the locators then turn those into debug scenarios, exactly as
+//! they do for the tasks provided by languages.
+
+pub(crate) mod cargo;
+pub(crate) mod node;
+pub(crate) mod python;
+
+use async_trait::async_trait;
+use dap::adapters::DebugAdapterName;
+use fs::Fs;
+use gpui::SharedString;
+use task::{DebugScenario, TaskSourceKind, TaskTemplate};
+use worktree::Snapshot;
+
+#[async_trait]
+pub(crate) trait ScenarioGenerator: Send + Sync {
+    /// The language of the targets, used to group the generated scenarios.
+    fn language(&self) -> SharedString;
+    fn adapter(&self) -> DebugAdapterName;
+    /// Lists the commands that run the targets found in the worktree.
+    async fn tasks(&self, fs: &dyn Fs, snapshot: &Snapshot) -> Vec<TaskTemplate>;
+}
+
+pub(crate) fn generators() -> Vec<Box<dyn ScenarioGenerator>> {
+    vec![
+        Box::new(cargo::CargoGenerator),
+        Box::new(node::NodeGenerator),
+        Box::new(python::PytestGenerator),
+    ]
+}
+
+/// Appends the generated scenarios to the configured ones, skipping those whose label a configured scenario (or an
+/// earlier generated one) already uses.
+pub(crate) fn merge_scenarios(
+    mut configured: Vec<(TaskSourceKind, DebugScenario)>,
+    generated: Vec<(TaskSourceKind, DebugScenario)>,
+) -> Vec<(TaskSourceKind, DebugScenario)> {
+    for (kind, scenario) in generated {
+        if !configured
+            .iter()
+            .any(|(_, existing)| existing.label == scenario.label)
+        {
+            configured.push((kind, scenario));
+        }
+    }
+    configured
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dap::adapters::DebugAdapterName;
+use fs::Fs;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use gpui::SharedString;
+use serde::Deserialize;
+use task::{TaskTemplate, VariableName};
+use util::rel_path::RelPath;
+use worktree::Snapshot;
+
+use super::ScenarioGenerator;
+
+pub(crate) struct CargoGenerator;
+
+#[derive(Deserialize)]
+struct Manifest {
+    package: Option<Package>,
+    workspace: Option<Workspace>,
+    #[serde(default)]
+    bin: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+}
+
+impl CargoGenerator {
+    /// Lists the binaries of the package whose manifest is in the given directory of the worktree.
+    fn binaries(
+        manifest: Manifest,
+        dir: &RelPath,
+        snapshot: &Snapshot,
+    ) -> Option<(String, Vec<String>)> {
+        let package = manifest.package?;
+        let mut binaries = manifest
+            .bin
+            .into_iter()
+            .map(|target| target.name)
+            .collect::<Vec<_>>();
+        if snapshot
+            .entry_for_path(&dir.join(RelPath::unix("src/main.rs").ok()?))
+            .is_some()
+        {
+            binaries.push(package.name.clone());
+        }
+        if let Ok(bin_dir) = RelPath::unix("src/bin") {
+            let bin_dir = dir.join(bin_dir);
+            binaries.extend(
+                snapshot
+                    .traverse_from_path(true, false, false, &bin_dir)
+                    .take_while(|entry| entry.path.starts_with(&bin_dir))
+                    .filter(|entry| entry.path.parent() == Some(bin_dir.as_ref()))
+                    .filter(|entry| entry.path.extension() == Some("rs"))
+                    .filter_map(|entry| entry.path.file_stem().map(ToOwned::to_owned)),
+            );
+        }
+        binaries.sort();
+        binaries.dedup();
+        Some((package.name, binaries))
+    }
+
+    /// Lists the directories of the workspace members, expanding their globs the way Cargo does.
+    fn members(workspace: &Workspace, snapshot: &Snapshot) -> Vec<Arc<RelPath>> {
+        fn glob_set(patterns: &[String]) -> GlobSet {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                if let Ok(glob) = GlobBuilder::new(pattern.trim_end_matches('/'))
+                    .literal_separator(true)
+                    .build()
+                {
+                    builder.add(glob);
+                }
+            }
+            builder.build().unwrap_or_else(|_| GlobSet::empty())
+        }
+
+        let members = glob_set(&workspace.members);
+        let exclude = glob_set(&workspace.exclude);
+        snapshot
+            .directories(false, 0)
+            .filter(|entry| !entry.path.is_empty())
+            .filter(|entry| {
+                let path = entry.path.as_unix_str();
+                members.is_match(path) && !exclude.is_match(path)
+            })
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ScenarioGenerator for CargoGenerator {
+    fn language(&self) -> SharedString {
+        SharedString::new_static("Rust")
+    }
+
+    fn adapter(&self) -> DebugAdapterName {
+        DebugAdapterName(SharedString::new_static("CodeLLDB"))
+    }
+
+    async fn tasks(&self, fs: &dyn Fs, snapshot: &Snapshot) -> Vec<TaskTemplate> {
+        let Some(manifest) = fs
+            .load(&snapshot.abs_path().join("Cargo.toml"))
+            .await
+            .ok()
+            .and_then(|manifest| toml::from_str::<Manifest>(&manifest).ok())
+        else {
+            return Vec::new();
+        };
+
+        let members = manifest
+            .workspace
+            .as_ref()
+            .map(|workspace| Self::members(workspace, snapshot))
+            .unwrap_or_default();
+        let mut packages = Vec::new();
+        packages.extend(Self::binaries(manifest, RelPath::empty(), snapshot));
+        for member in members {
+            let manifest_path = snapshot
+                .abs_path()
+                .join(member.as_std_path())
+                .join("Cargo.toml");
+            let Some(manifest) = fs
+                .load(&manifest_path)
+                .await
+                .ok()
+                .and_then(|manifest| toml::from_str::<Manifest>(&manifest).ok())
+            else {
+                continue;
+            };
+            packages.extend(Self::binaries(manifest, &member, snapshot));
+        }
+
+        let cwd = Some(VariableName::WorktreeRoot.template_value());
+        packages
+            .into_iter()
+            .flat_map(|(package, binaries)| {
+                binaries
+                    .into_iter()
+                    .map({
+                        let package = package.clone();
+                        let cwd = cwd.clone();
+                        move |binary| TaskTemplate {
+                            label: format!("Debug binary '{binary}'"),
+                            command: "cargo".into(),
+                            args: vec![
+                                "run".into(),
+                                "--package".into(),
+                                package.clone(),
+                                "--bin".into(),
+                                binary,
+                            ],
+                            cwd: cwd.clone(),
+                            ..TaskTemplate::default()
+                        }
+                    })
+                    .chain(Some(TaskTemplate {
+                        label: format!("Debug tests in '{package}'"),
+                        command: "cargo".into(),
+                        args: vec!["test".into(), "--package".into(), package],
+                        cwd: cwd.clone(),
+                        ..TaskTemplate::default()
+                    }))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
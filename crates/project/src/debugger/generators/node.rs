@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use collections::BTreeMap;
+use dap::adapters::DebugAdapterName;
+use fs::Fs;
+use gpui::SharedString;
+use serde::Deserialize;
+use task::{TaskTemplate, VariableName};
+use worktree::Snapshot;
+
+use super::ScenarioGenerator;
+
+pub(crate) struct NodeGenerator;
+
+#[derive(Deserialize)]
+struct PackageJson {
+    #[serde(default)]
+    scripts: BTreeMap<String, String>,
+}
+
+#[async_trait]
+impl ScenarioGenerator for NodeGenerator {
+    fn language(&self) -> SharedString {
+        SharedString::new_static("JavaScript")
+    }
+
+    fn adapter(&self) -> DebugAdapterName {
+        DebugAdapterName(SharedString::new_static("JavaScript"))
+    }
+
+    async fn tasks(&self, fs: &dyn Fs, snapshot: &Snapshot) -> Vec<TaskTemplate> {
+        let Ok(package_json) = fs.load(&snapshot.abs_path().join("package.json")).await else {
+            return Vec::new();
+        };
+        let Ok(package_json) = serde_json::from_str::<PackageJson>(&package_json) else {
+            return Vec::new();
+        };
+
+        let cwd = Some(VariableName::WorktreeRoot.template_value());
+        package_json
+            .scripts
+            .into_keys()
+            .map(|script| TaskTemplate {
+                label: format!("Debug script '{script}'"),
+                command: "npm".into(),
+                args: vec!["run".into(), script],
+                cwd: cwd.clone(),
+                ..TaskTemplate::default()
+            })
+            .collect()
+    }
+}
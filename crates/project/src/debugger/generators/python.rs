@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+use dap::adapters::DebugAdapterName;
+use fs::Fs;
+use gpui::SharedString;
+use task::{TaskTemplate, VariableName};
+use worktree::Snapshot;
+
+use super::ScenarioGenerator;
+
+pub(crate) struct PytestGenerator;
+
+/// Follows pytest's default discovery rules for test modules.
+fn is_test_module(file_name: &str) -> bool {
+    file_name.ends_with(".py")
+        && (file_name.starts_with("test_") || file_name.ends_with("_test.py"))
+}
+
+#[async_trait]
+impl ScenarioGenerator for PytestGenerator {
+    fn language(&self) -> SharedString {
+        SharedString::new_static("Python")
+    }
+
+    fn adapter(&self) -> DebugAdapterName {
+        DebugAdapterName(SharedString::new_static("Debugpy"))
+    }
+
+    async fn tasks(&self, _: &dyn Fs, snapshot: &Snapshot) -> Vec<TaskTemplate> {
+        let cwd = Some(VariableName::WorktreeRoot.template_value());
+        snapshot
+            .files(false, 0)
+            .filter(|entry| entry.path.file_name().is_some_and(is_test_module))
+            .map(|entry| {
+                let path = entry.path.as_unix_str().to_owned();
+                TaskTemplate {
+                    label: format!("Debug tests in '{path}'"),
+                    command: "python3".into(),
+                    args: vec!["-m".into(), "pytest".into(), path],
+                    cwd: cwd.clone(),
+                    ..TaskTemplate::default()
+                }
+            })
+            .collect()
+    }
+}
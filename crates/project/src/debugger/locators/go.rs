@@ -266,6 +266,14 @@ mod tests {
             tags: vec![],
             show_summary: true,
             show_command: true,
+            depends_on: vec![],
+            depends_order: Default::default(),
+            on_dependency_failure: Default::default(),
+            problem_matchers: vec![],
+            is_background: false,
+            ready_pattern: None,
+            run_on_save: vec![],
+            execution_target: None,
         };
 
         let scenario = locator
@@ -293,6 +301,14 @@ mod tests {
             tags: vec![],
             show_summary: true,
             show_command: true,
+            depends_on: vec![],
+            depends_order: Default::default(),
+            on_dependency_failure: Default::default(),
+            problem_matchers: vec![],
+            is_background: false,
+            ready_pattern: None,
+            run_on_save: vec![],
+            execution_target: None,
         };
 
         let scenario = locator
@@ -431,6 +447,14 @@ mod tests {
             tags: vec![],
             show_summary: true,
             show_command: true,
+            depends_on: vec![],
+            depends_order: Default::default(),
+            on_dependency_failure: Default::default(),
+            problem_matchers: vec![],
+            is_background: false,
+            ready_pattern: None,
+            run_on_save: vec![],
+            execution_target: None,
         };
 
         let scenario = locator
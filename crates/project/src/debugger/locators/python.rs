@@ -119,6 +119,14 @@ mod test {
             shell: task::Shell::System,
             show_summary: false,
             show_command: false,
+            depends_on: vec![],
+            depends_order: Default::default(),
+            on_dependency_failure: Default::default(),
+            problem_matchers: vec![],
+            is_background: false,
+            ready_pattern: None,
+            run_on_save: vec![],
+            execution_target: None,
         };
 
         let expected_scenario = DebugScenario {
@@ -0,0 +1,63 @@
+//! Enumeration of the processes running on this machine, so that attach configurations can offer a process picker
+//! instead of requiring a hand-typed pid.
+
+use gpui::SharedString;
+use sysinfo::{ProcessRefreshKind, RefreshKind, System, UpdateKind, Users};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: SharedString,
+    /// The command line the process was started with, including the executable.
+    pub command: Vec<String>,
+    /// The name of the user the process runs as, if it could be determined.
+    pub user: Option<SharedString>,
+}
+
+/// Lists the running processes, sorted by name.
+///
+/// This queries the operating system synchronously, so it's best called from a background thread.
+pub fn list_processes() -> Vec<ProcessInfo> {
+    let refresh_kind = RefreshKind::nothing().with_processes(
+        ProcessRefreshKind::nothing()
+            .without_tasks()
+            .with_cmd(UpdateKind::Always)
+            .with_user(UpdateKind::Always),
+    );
+    let users = Users::new_with_refreshed_list();
+    let mut processes = System::new_with_specifics(refresh_kind)
+        .processes()
+        .values()
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            name: process.name().to_string_lossy().into_owned().into(),
+            command: process
+                .cmd()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            user: process
+                .user_id()
+                .and_then(|uid| users.get_user_by_id(uid))
+                .map(|user| SharedString::from(user.name().to_string())),
+        })
+        .collect::<Vec<_>>();
+    processes.sort_by(|a, b| a.name.cmp(&b.name).then(a.pid.cmp(&b.pid)));
+    processes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_processes_includes_current_process() {
+        let pid = std::process::id();
+        let processes = list_processes();
+        let current = processes
+            .iter()
+            .find(|process| process.pid == pid)
+            .expect("current process should be listed");
+        assert!(!current.name.is_empty());
+    }
+}
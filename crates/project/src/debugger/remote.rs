@@ -0,0 +1,260 @@
+//! Debugging a program that runs on another machine.
+//!
+//! A debug configuration can ask, under its `remote` key, for the adapter's TCP port on a remote host to be forwarded over
+//! SSH, so that the session connects to `127.0.0.1` on our side. Since the debuggee sees the remote file system, source
+//! paths have to be translated between the local and the remote roots, in both directions: when sending breakpoints, and
+//! when reading the sources of stack frames, breakpoints, output and loaded sources.
+//!
+//! The mappings live under `remote` rather than in `pathMappings` or `localRoot`/`remoteRoot`, which some adapters
+//! interpret themselves; mapping those paths on our side too would translate them twice.
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+use smol::process::{Child, Stdio};
+use std::{
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+use util::command::new_smol_command;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshTunnel {
+    pub host: String,
+    pub user: Option<String>,
+    /// The port of the SSH server.
+    pub port: Option<u16>,
+    /// The port the debug adapter listens on, on the remote host.
+    pub remote_port: u16,
+    /// The interface the debug adapter listens on, as seen from the remote host.
+    ///
+    /// Default: 127.0.0.1
+    pub remote_host: Option<String>,
+}
+
+impl SshTunnel {
+    /// Starts forwarding `local_port` on the loopback interface to the remote adapter.
+    ///
+    /// SSH runs in batch mode, since there's no terminal to answer its prompts; its errors are written to the returned
+    /// process's stderr. The tunnel is torn down when the process is dropped.
+    pub fn start(&self, local_port: u16) -> Result<Child> {
+        let destination = match &self.user {
+            Some(user) => format!("{user}@{}", self.host),
+            None => self.host.clone(),
+        };
+        let mut command = new_smol_command("ssh");
+        command
+            .arg("-N")
+            .args(["-o", "BatchMode=yes"])
+            .args(["-o", "ExitOnForwardFailure=yes"])
+            .arg("-L")
+            .arg(format!(
+                "{}:{local_port}:{}:{}",
+                Ipv4Addr::LOCALHOST,
+                self.remote_host.as_deref().unwrap_or("127.0.0.1"),
+                self.remote_port
+            ));
+        if let Some(port) = self.port {
+            command.arg("-p").arg(port.to_string());
+        }
+        command
+            .arg(destination)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("starting SSH tunnel to {}", self.host))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathMapping {
+    pub local_root: PathBuf,
+    pub remote_root: PathBuf,
+}
+
+/// Translates source paths between this machine and the one the debuggee runs on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathMapper {
+    mappings: Vec<PathMapping>,
+}
+
+impl PathMapper {
+    pub fn new(mappings: Vec<PathMapping>) -> Self {
+        Self { mappings }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    pub fn to_remote(&self, local_path: &Path) -> PathBuf {
+        Self::map(
+            local_path,
+            self.mappings
+                .iter()
+                .map(|mapping| (&mapping.local_root, &mapping.remote_root)),
+        )
+    }
+
+    pub fn to_local(&self, remote_path: &Path) -> PathBuf {
+        Self::map(
+            remote_path,
+            self.mappings
+                .iter()
+                .map(|mapping| (&mapping.remote_root, &mapping.local_root)),
+        )
+    }
+
+    /// Translates the path of a source reported by the adapter, and those of the sources it's made of, to local paths.
+    pub fn source_to_local(&self, source: &mut dap::Source) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(path) = source.path.as_mut() {
+            *path = self
+                .to_local(Path::new(path))
+                .to_string_lossy()
+                .into_owned();
+        }
+        for source in source.sources.iter_mut().flatten() {
+            self.source_to_local(source);
+        }
+    }
+
+    /// Translates the sources of stack frames reported by the adapter to local paths.
+    pub fn stack_frames_to_local(
+        &self,
+        mut stack_frames: Vec<dap::StackFrame>,
+    ) -> Vec<dap::StackFrame> {
+        for source in stack_frames
+            .iter_mut()
+            .filter_map(|frame| frame.source.as_mut())
+        {
+            self.source_to_local(source);
+        }
+        stack_frames
+    }
+
+    /// Rewrites the root of `path` using the most specific mapping that applies to it.
+    fn map<'a>(path: &Path, mappings: impl Iterator<Item = (&'a PathBuf, &'a PathBuf)>) -> PathBuf {
+        mappings
+            .filter_map(|(from, to)| {
+                let suffix = path.strip_prefix(from).ok()?;
+                Some((from.components().count(), to.join(suffix)))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, mapped)| mapped)
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteDebugContent {
+    ssh: Option<SshTunnel>,
+    #[serde(default)]
+    path_mappings: Vec<PathMapping>,
+}
+
+/// The remote debugging options of a debug configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteDebugConfig {
+    pub ssh: Option<SshTunnel>,
+    pub path_mapper: PathMapper,
+}
+
+impl RemoteDebugConfig {
+    /// Reads the `remote` key of an adapter configuration, which holds the `ssh` tunnel and the `pathMappings`.
+    pub fn from_config(config: &serde_json::Value) -> Result<Self> {
+        let content = config
+            .get("remote")
+            .map(|remote| {
+                RemoteDebugContent::deserialize(remote).context("invalid `remote` configuration")
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            ssh: content.ssh,
+            path_mapper: PathMapper::new(content.path_mappings),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_remote_debug_config() {
+        let config = RemoteDebugConfig::from_config(&json!({
+            "request": "attach",
+            "remote": {
+                "ssh": { "host": "build-box", "user": "dev", "remotePort": 5678 },
+                "pathMappings": [
+                    { "localRoot": "/home/me/app", "remoteRoot": "/srv/app" },
+                    { "localRoot": "/home/me/vendor/lib", "remoteRoot": "/srv/app/lib" },
+                ],
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.ssh,
+            Some(SshTunnel {
+                host: "build-box".into(),
+                user: Some("dev".into()),
+                port: None,
+                remote_port: 5678,
+                remote_host: None,
+            })
+        );
+
+        let mapper = &config.path_mapper;
+        assert_eq!(
+            mapper.to_remote(Path::new("/home/me/app/src/main.py")),
+            Path::new("/srv/app/src/main.py")
+        );
+        assert_eq!(
+            mapper.to_local(Path::new("/srv/app/lib/util.py")),
+            Path::new("/home/me/vendor/lib/util.py")
+        );
+        assert_eq!(
+            mapper.to_local(Path::new("/usr/lib/python3/os.py")),
+            Path::new("/usr/lib/python3/os.py")
+        );
+
+        // The adapter's own mappings are left for the adapter to apply.
+        let config = RemoteDebugConfig::from_config(&json!({
+            "localRoot": "/home/me/app",
+            "remoteRoot": "/srv/app",
+            "pathMappings": [{ "localRoot": "/home/me/app", "remoteRoot": "/srv/app" }],
+        }))
+        .unwrap();
+        assert_eq!(config, RemoteDebugConfig::default());
+
+        let remote_source = |path: &str, sources: Option<Vec<dap::Source>>| dap::Source {
+            name: None,
+            path: Some(path.into()),
+            source_reference: None,
+            presentation_hint: None,
+            origin: None,
+            sources,
+            adapter_data: None,
+            checksums: None,
+        };
+        let mut source = remote_source(
+            "/srv/app/lib/util.py",
+            Some(vec![remote_source("/srv/app/main.py", None)]),
+        );
+        mapper.source_to_local(&mut source);
+        assert_eq!(source.path.as_deref(), Some("/home/me/vendor/lib/util.py"));
+        assert_eq!(
+            source.sources.unwrap()[0].path.as_deref(),
+            Some("/home/me/app/main.py")
+        );
+    }
+}
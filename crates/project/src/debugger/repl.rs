@@ -0,0 +1,146 @@
+//! Debug console support: turns the stream of output events sent by a debug adapter into entries a console can display.
+//!
+//! Adapters forward the output of the debuggee as it arrives, so a single line can be split across multiple events; output can
+//! also be nested in (possibly collapsed) groups. We join fragments of the same line and resolve the group nesting, so that
+//! each console doesn't have to.
+
+use dap::{OutputEvent, OutputEventCategory, OutputEventGroup};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplEntry {
+    pub category: Option<OutputEventCategory>,
+    pub text: String,
+    /// How many groups this entry is nested in.
+    pub depth: usize,
+    /// Set when this entry is the header of a group; the value tells whether the group starts collapsed.
+    pub starts_group: Option<bool>,
+    /// The structured value of the entry, if any, to be expanded via `variables` requests.
+    pub variables_reference: Option<u64>,
+}
+
+impl ReplEntry {
+    fn is_complete(&self) -> bool {
+        self.starts_group.is_some()
+            || self.variables_reference.is_some()
+            || self.text.ends_with('\n')
+    }
+}
+
+pub fn aggregate_output<'a>(events: impl IntoIterator<Item = &'a OutputEvent>) -> Vec<ReplEntry> {
+    let mut entries: Vec<ReplEntry> = Vec::new();
+    let mut depth = 0;
+    for event in events {
+        let variables_reference = event.variables_reference.filter(|reference| *reference > 0);
+        match event.group {
+            Some(OutputEventGroup::Start) | Some(OutputEventGroup::StartCollapsed) => {
+                entries.push(ReplEntry {
+                    category: event.category.clone(),
+                    text: event.output.clone(),
+                    depth,
+                    starts_group: Some(matches!(
+                        event.group,
+                        Some(OutputEventGroup::StartCollapsed)
+                    )),
+                    variables_reference,
+                });
+                depth += 1;
+                continue;
+            }
+            Some(OutputEventGroup::End) => {
+                depth = depth.saturating_sub(1);
+                if event.output.is_empty() {
+                    continue;
+                }
+            }
+            None => {}
+        }
+
+        let is_program_output = matches!(
+            event.category,
+            Some(OutputEventCategory::Stdout | OutputEventCategory::Stderr)
+        );
+        if is_program_output
+            && variables_reference.is_none()
+            && let Some(last) = entries.last_mut()
+            && !last.is_complete()
+            && last.depth == depth
+            && last.category == event.category
+        {
+            last.text.push_str(&event.output);
+            continue;
+        }
+
+        entries.push(ReplEntry {
+            category: event.category.clone(),
+            text: event.output.clone(),
+            depth,
+            starts_group: None,
+            variables_reference,
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(
+        text: &str,
+        category: OutputEventCategory,
+        group: Option<OutputEventGroup>,
+    ) -> OutputEvent {
+        OutputEvent {
+            category: Some(category),
+            output: text.to_string(),
+            group,
+            variables_reference: None,
+            source: None,
+            line: None,
+            column: None,
+            data: None,
+            location_reference: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_output() {
+        let events = [
+            output("Hello, ", OutputEventCategory::Stdout, None),
+            output("world!\n", OutputEventCategory::Stdout, None),
+            output("oops", OutputEventCategory::Stderr, None),
+            output(
+                "Tests",
+                OutputEventCategory::Console,
+                Some(OutputEventGroup::StartCollapsed),
+            ),
+            output("passed: ", OutputEventCategory::Stdout, None),
+            output("2\n", OutputEventCategory::Stdout, None),
+            output("> x", OutputEventCategory::Console, None),
+            output("< 1", OutputEventCategory::Console, None),
+            output(
+                "",
+                OutputEventCategory::Console,
+                Some(OutputEventGroup::End),
+            ),
+            output("done\n", OutputEventCategory::Console, None),
+        ];
+
+        let entries = aggregate_output(&events)
+            .into_iter()
+            .map(|entry| (entry.text, entry.depth, entry.starts_group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("Hello, world!\n".to_string(), 0, None),
+                ("oops".to_string(), 0, None),
+                ("Tests".to_string(), 0, Some(true)),
+                ("passed: 2\n".to_string(), 1, None),
+                ("> x".to_string(), 1, None),
+                ("< 1".to_string(), 1, None),
+                ("done\n".to_string(), 0, None),
+            ]
+        );
+    }
+}
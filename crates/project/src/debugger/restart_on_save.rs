@@ -0,0 +1,127 @@
+//! Restarting the debuggee when the files it's built from are saved.
+//!
+//! A debug configuration lists the globs (relative to the worktree root) of the files that should trigger a restart,
+//! either as a plain array or together with an adapter-specific request to send instead of restarting, for adapters
+//! that can hot-reload code:
+//!
+//! ```json
+//! "restartOnSave": { "globs": ["lib/**/*.dart"], "customRequest": "hotReload" }
+//! ```
+
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use util::{
+    paths::{PathMatcher, PathStyle},
+    rel_path::RelPath,
+};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RestartOnSaveContent {
+    Globs(Vec<String>),
+    #[serde(rename_all = "camelCase")]
+    Options {
+        globs: Vec<String>,
+        custom_request: Option<String>,
+        arguments: Option<Value>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestartAction {
+    Restart,
+    CustomRequest { command: String, arguments: Value },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartOnSave {
+    matcher: PathMatcher,
+    pub action: RestartAction,
+}
+
+impl RestartOnSave {
+    /// Reads the `restartOnSave` key of an adapter configuration.
+    pub fn from_config(config: &Value, path_style: PathStyle) -> Result<Option<Self>> {
+        let Some(content) = config.get("restartOnSave") else {
+            return Ok(None);
+        };
+        let content = RestartOnSaveContent::deserialize(content)
+            .context("invalid `restartOnSave` configuration")?;
+        let (globs, action) = match content {
+            RestartOnSaveContent::Globs(globs) => (globs, RestartAction::Restart),
+            RestartOnSaveContent::Options {
+                globs,
+                custom_request,
+                arguments,
+            } => (
+                globs,
+                match custom_request {
+                    Some(command) => RestartAction::CustomRequest {
+                        command,
+                        arguments: arguments.unwrap_or(Value::Null),
+                    },
+                    None => RestartAction::Restart,
+                },
+            ),
+        };
+        let matcher = PathMatcher::new(&globs, path_style)
+            .context("invalid glob in `restartOnSave` configuration")?;
+        Ok(Some(Self { matcher, action }))
+    }
+
+    pub fn matches(&self, path: &RelPath) -> bool {
+        self.matcher.is_match(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use util::rel_path::rel_path;
+
+    #[test]
+    fn test_restart_on_save_config() {
+        assert_eq!(
+            RestartOnSave::from_config(&json!({ "request": "launch" }), PathStyle::Posix).unwrap(),
+            None
+        );
+
+        let restart = RestartOnSave::from_config(
+            &json!({ "restartOnSave": ["src/**/*.py"] }),
+            PathStyle::Posix,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(restart.action, RestartAction::Restart);
+        assert!(restart.matches(rel_path("src/app/main.py")));
+        assert!(!restart.matches(rel_path("tests/test_main.py")));
+
+        let hot_reload = RestartOnSave::from_config(
+            &json!({
+                "restartOnSave": {
+                    "globs": ["lib/**/*.dart"],
+                    "customRequest": "hotReload",
+                    "arguments": { "reason": "save" },
+                }
+            }),
+            PathStyle::Posix,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            hot_reload.action,
+            RestartAction::CustomRequest {
+                command: "hotReload".into(),
+                arguments: json!({ "reason": "save" }),
+            }
+        );
+        assert!(hot_reload.matches(rel_path("lib/main.dart")));
+
+        assert!(
+            RestartOnSave::from_config(&json!({ "restartOnSave": "src" }), PathStyle::Posix)
+                .is_err()
+        );
+    }
+}
@@ -5,19 +5,26 @@ use super::dap_command::{
     self, Attach, ConfigurationDone, ContinueCommand, DataBreakpointInfoCommand, DisconnectCommand,
     EvaluateCommand, Initialize, Launch, LoadedSourcesCommand, LocalDapCommand, LocationsCommand,
     ModulesCommand, NextCommand, PauseCommand, RestartCommand, RestartStackFrameCommand,
-    ScopesCommand, SetDataBreakpointsCommand, SetExceptionBreakpoints, SetVariableValueCommand,
-    StackTraceCommand, StepBackCommand, StepCommand, StepInCommand, StepOutCommand,
-    TerminateCommand, TerminateThreadsCommand, ThreadsCommand, VariablesCommand,
+    ReverseContinueCommand, ScopesCommand, SetDataBreakpointsCommand, SetExceptionBreakpoints,
+    SetVariableValueCommand, StackTraceCommand, StepBackCommand, StepCommand, StepInCommand,
+    StepOutCommand, TerminateCommand, TerminateThreadsCommand, ThreadsCommand, VariablesCommand,
 };
 use super::dap_store::DapStore;
 use crate::debugger::breakpoint_store::BreakpointSessionState;
-use crate::debugger::dap_command::{DataBreakpointContext, ReadMemory};
+use crate::debugger::dap_command::{DataBreakpointContext, DisassembleCommand, ReadMemory};
 use crate::debugger::memory::{self, Memory, MemoryIterator, MemoryPageBuilder, PageAddress};
+use crate::debugger::remote::PathMapper;
+use crate::debugger::repl::{self, ReplEntry};
+use crate::debugger::restart_on_save::RestartOnSave;
+use crate::debugger::watch_store::WatchStore;
+pub use crate::debugger::watch_store::Watcher;
 use anyhow::{Context as _, Result, anyhow};
 use base64::Engine;
 use collections::{HashMap, HashSet, IndexMap};
 use dap::adapters::{DebugAdapterBinary, DebugAdapterName};
+use dap::debugger_settings::DebuggerSettings;
 use dap::messages::Response;
+use dap::recording::DapRecorder;
 use dap::requests::{Request, RunInTerminal, StartDebugging};
 #[cfg(any())]
 use dap::transport::TcpTransport;
@@ -30,8 +37,9 @@ use dap::{
 use dap::{
     ExceptionBreakpointsFilter, ExceptionFilterOptions, OutputEvent, OutputEventCategory,
     RunInTerminalRequestArguments, StackFramePresentationHint, StartDebuggingRequestArguments,
-    StartDebuggingRequestArgumentsRequest, VariablePresentationHint, WriteMemoryArguments,
+    StartDebuggingRequestArgumentsRequest, WriteMemoryArguments,
 };
+use db::kvp::KEY_VALUE_STORE;
 use futures::channel::mpsc::UnboundedSender;
 use futures::channel::{mpsc, oneshot};
 #[cfg(any())]
@@ -47,6 +55,7 @@ use gpui::{
 #[cfg(any())]
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use settings::Settings as _;
 use std::any::TypeId;
 use std::collections::{BTreeMap, VecDeque};
 use std::ops::RangeInclusive;
@@ -67,6 +76,8 @@ use worktree::Worktree;
 
 const MAX_TRACKED_OUTPUT_EVENTS: usize = 5000;
 const DEBUG_HISTORY_LIMIT: usize = 10;
+/// The number of instructions fetched by a single `disassemble` request.
+pub const DISASSEMBLY_PAGE_LEN: u64 = 64;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Ord, Eq)]
 #[repr(transparent)]
@@ -134,14 +145,6 @@ impl From<dap::Thread> for Thread {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Watcher {
-    pub expression: SharedString,
-    pub value: SharedString,
-    pub variables_reference: u64,
-    pub presentation_hint: Option<VariablePresentationHint>,
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct DataBreakpointState {
     pub dap: dap::DataBreakpoint,
@@ -167,6 +170,8 @@ pub struct RunningMode {
     is_started: bool,
     has_ever_stopped: bool,
     messages_tx: UnboundedSender<Message>,
+    breakpoint_scope: Option<Arc<[Arc<Path>]>>,
+    path_mapper: Arc<PathMapper>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
@@ -190,6 +195,13 @@ fn client_source(abs_path: &Path) -> dap::Source {
     }
 }
 
+fn requested_rows(breakpoints: &[dap::SourceBreakpoint]) -> Vec<u32> {
+    breakpoints
+        .iter()
+        .map(|breakpoint| breakpoint.line.saturating_sub(1) as u32)
+        .collect()
+}
+
 impl RunningMode {
     async fn new(
         session_id: SessionId,
@@ -217,6 +229,19 @@ impl RunningMode {
             DebugAdapterClient::start(session_id, binary.clone(), message_handler, cx).await?
         };
 
+        if cx.update(|cx| DebuggerSettings::get_global(cx).record_dap_sessions)? {
+            let file_name = format!(
+                "{}-{}.jsonl",
+                chrono::Local::now().format("%Y-%m-%d-%H-%M-%S"),
+                session_id.0
+            );
+            if let Some(recorder) =
+                DapRecorder::create(&paths::debug_recordings_dir().join(file_name)).log_err()
+            {
+                client.record_to(recorder);
+            }
+        }
+
         Ok(Self {
             client: Arc::new(client),
             worktree,
@@ -226,6 +251,8 @@ impl RunningMode {
             is_started: false,
             has_ever_stopped: false,
             messages_tx,
+            breakpoint_scope: None,
+            path_mapper: Arc::default(),
         })
     }
 
@@ -233,12 +260,24 @@ impl RunningMode {
         &self.worktree
     }
 
+    fn source(&self, abs_path: &Path) -> dap::Source {
+        client_source(&self.path_mapper.to_remote(abs_path))
+    }
+
+    fn routes_breakpoints_for(&self, abs_path: &Path) -> bool {
+        self.breakpoint_scope.as_ref().is_none_or(|scope| {
+            scope
+                .iter()
+                .any(|directory| abs_path.starts_with(directory))
+        })
+    }
+
     fn unset_breakpoints_from_paths(&self, paths: &Vec<Arc<Path>>, cx: &mut App) -> Task<()> {
         let tasks: Vec<_> = paths
             .iter()
             .map(|path| {
                 self.request(dap_command::SetBreakpoints {
-                    source: client_source(path),
+                    source: self.source(path),
                     source_modified: None,
                     breakpoints: vec![],
                 })
@@ -265,7 +304,11 @@ impl RunningMode {
         breakpoint_store: &Entity<BreakpointStore>,
         cx: &mut App,
     ) -> Task<()> {
-        let breakpoints =
+        if !self.routes_breakpoints_for(&abs_path) {
+            return Task::ready(());
+        }
+
+        let breakpoints: Vec<dap::SourceBreakpoint> =
             breakpoint_store
                 .read(cx)
                 .source_breakpoints_from_path(&abs_path, cx)
@@ -276,6 +319,7 @@ impl RunningMode {
                 }))
                 .map(Into::into)
                 .collect();
+        let requested_rows = requested_rows(&breakpoints);
 
         let raw_breakpoints = breakpoint_store
             .read(cx)
@@ -285,7 +329,7 @@ impl RunningMode {
             .collect::<Vec<_>>();
 
         let task = self.request(dap_command::SetBreakpoints {
-            source: client_source(&abs_path),
+            source: self.source(&abs_path),
             source_modified: Some(matches!(reason, BreakpointUpdatedReason::FileSaved)),
             breakpoints,
         });
@@ -293,22 +337,19 @@ impl RunningMode {
         let breakpoint_store = breakpoint_store.downgrade();
         cx.spawn(async move |cx| match cx.background_spawn(task).await {
             Ok(breakpoints) => {
-                let breakpoints =
-                    breakpoints
-                        .into_iter()
-                        .zip(raw_breakpoints)
-                        .filter_map(|(dap_bp, zed_bp)| {
-                            Some((
-                                zed_bp,
-                                BreakpointSessionState {
-                                    id: dap_bp.id?,
-                                    verified: dap_bp.verified,
-                                },
-                            ))
-                        });
+                let breakpoints = breakpoints
+                    .into_iter()
+                    .zip(requested_rows)
+                    .zip(raw_breakpoints)
+                    .map(|((dap_bp, requested_row), zed_bp)| {
+                        (
+                            zed_bp,
+                            BreakpointSessionState::from_dap(&dap_bp, Some(requested_row)),
+                        )
+                    });
                 breakpoint_store
-                    .update(cx, |this, _| {
-                        this.mark_breakpoints_verified(session_id, &abs_path, breakpoints);
+                    .update(cx, |this, cx| {
+                        this.mark_breakpoints_verified(session_id, &abs_path, breakpoints, cx);
                     })
                     .ok();
             }
@@ -352,7 +393,11 @@ impl RunningMode {
         debug_assert_eq!(raw_breakpoints.len(), breakpoints.len());
         let session_id = self.client.id();
         for (path, breakpoints) in breakpoints {
-            let breakpoints = if ignore_breakpoints {
+            if !self.routes_breakpoints_for(&path) {
+                continue;
+            }
+
+            let breakpoints: Vec<dap::SourceBreakpoint> = if ignore_breakpoints {
                 vec![]
             } else {
                 breakpoints
@@ -361,6 +406,7 @@ impl RunningMode {
                     .map(Into::into)
                     .collect()
             };
+            let requested_rows = requested_rows(&breakpoints);
 
             let raw_breakpoints = raw_breakpoints
                 .remove(&path)
@@ -370,7 +416,7 @@ impl RunningMode {
             let error_path = path.clone();
             let send_request = self
                 .request(dap_command::SetBreakpoints {
-                    source: client_source(&path),
+                    source: self.source(&path),
                     source_modified: Some(false),
                     breakpoints,
                 })
@@ -381,20 +427,19 @@ impl RunningMode {
                 async move |cx| {
                     let breakpoints = cx.background_spawn(send_request).await?;
 
-                    let breakpoints = breakpoints.into_iter().zip(raw_breakpoints).filter_map(
-                        |(dap_bp, zed_bp)| {
-                            Some((
+                    let breakpoints = breakpoints
+                        .into_iter()
+                        .zip(requested_rows)
+                        .zip(raw_breakpoints)
+                        .map(|((dap_bp, requested_row), zed_bp)| {
+                            (
                                 zed_bp,
-                                BreakpointSessionState {
-                                    id: dap_bp.id?,
-                                    verified: dap_bp.verified,
-                                },
-                            ))
-                        },
-                    );
+                                BreakpointSessionState::from_dap(&dap_bp, Some(requested_row)),
+                            )
+                        });
                     breakpoint_store
-                        .update(cx, |this, _| {
-                            this.mark_breakpoints_verified(session_id, &path, breakpoints);
+                        .update(cx, |this, cx| {
+                            this.mark_breakpoints_verified(session_id, &path, breakpoints, cx);
                         })
                         .ok();
 
@@ -461,6 +506,21 @@ impl RunningMode {
                             dap_store.adapter_options(&adapter_name),
                         )
                     })?;
+                let watch_expressions_key = dap_store.read_with(cx, |dap_store, cx| {
+                    let worktree = worktree.upgrade()?;
+                    let session = session.upgrade()?;
+                    let session = session.read(cx);
+                    dap_store.watch_expressions_key(
+                        worktree.read(cx),
+                        &session.adapter,
+                        session.label.as_deref(),
+                    )
+                })?;
+                if let Some(key) = watch_expressions_key {
+                    session
+                        .update(cx, |session, cx| session.load_watch_expressions(key, cx))?
+                        .await;
+                }
                 initialized_rx.await?;
                 let errors_by_path = cx
                     .update(|cx| this.send_source_breakpoints(false, &breakpoint_store, cx))?
@@ -700,7 +760,9 @@ pub struct Session {
     parent_session: Option<Entity<Session>>,
     output_token: OutputToken,
     output: Box<circular_buffer::CircularBuffer<MAX_TRACKED_OUTPUT_EVENTS, dap::OutputEvent>>,
-    watchers: HashMap<SharedString, Watcher>,
+    watch_store: WatchStore,
+    /// The database key the watch expressions are persisted under, if they are.
+    watch_expressions_key: Option<String>,
     is_session_terminated: bool,
     requests: HashMap<TypeId, HashMap<RequestSlot, Shared<Task<Option<()>>>>>,
     pub(crate) breakpoint_store: Entity<BreakpointStore>,
@@ -711,6 +773,10 @@ pub struct Session {
     restart_task: Option<Task<()>>,
     task_context: TaskContext,
     memory: memory::Memory,
+    disassembly: HashMap<(Arc<str>, i64), Arc<[dap::DisassembledInstruction]>>,
+    breakpoint_scope: Option<Arc<[Arc<Path>]>>,
+    path_mapper: Arc<PathMapper>,
+    restart_on_save: Option<Arc<RestartOnSave>>,
     quirks: SessionQuirks,
 }
 
@@ -792,6 +858,7 @@ pub enum SessionEvent {
     StackTrace,
     Variables,
     Watchers,
+    Disassembly,
     Threads,
     InvalidateInlineValue,
     CapabilitiesLoaded,
@@ -864,7 +931,8 @@ impl Session {
                 child_session_ids: HashSet::default(),
                 parent_session,
                 capabilities: Capabilities::default(),
-                watchers: HashMap::default(),
+                watch_store: WatchStore::default(),
+                watch_expressions_key: None,
                 output_token: OutputToken(0),
                 output: circular_buffer::CircularBuffer::boxed(),
                 requests: HashMap::default(),
@@ -879,6 +947,10 @@ impl Session {
                 adapter,
                 task_context,
                 memory: memory::Memory::new(),
+                disassembly: HashMap::default(),
+                breakpoint_scope: None,
+                path_mapper: Arc::default(),
+                restart_on_save: None,
                 quirks,
             }
         })
@@ -940,7 +1012,7 @@ impl Session {
         let parent_session = self.parent_session.clone();
 
         cx.spawn(async move |this, cx| {
-            let mode = RunningMode::new(
+            let mut mode = RunningMode::new(
                 id,
                 parent_session,
                 worktree.downgrade(),
@@ -950,6 +1022,8 @@ impl Session {
             )
             .await?;
             this.update(cx, |this, cx| {
+                mode.breakpoint_scope = this.breakpoint_scope.clone();
+                mode.path_mapper = this.path_mapper.clone();
                 match &mut this.state {
                     SessionState::Booting(task) if task.is_some() => {
                         task.take().unwrap().detach_and_log_err(cx);
@@ -1050,6 +1124,11 @@ impl Session {
         &self.capabilities
     }
 
+    /// Whether the adapter can run the debuggee backwards, e.g. because it replays a recording.
+    pub fn supports_step_back(&self) -> bool {
+        self.capabilities.supports_step_back.unwrap_or_default()
+    }
+
     pub fn binary(&self) -> Option<&DebugAdapterBinary> {
         match &self.state {
             SessionState::Booting(_) => None,
@@ -1450,6 +1529,10 @@ impl Session {
 
     fn handle_stopped_event(&mut self, event: StoppedEvent, cx: &mut Context<Self>) {
         self.push_to_history();
+        self.watch_store.stopped();
+        if let Some(thread_id) = event.thread_id {
+            self.refresh_watchers_on_stop(thread_id, cx);
+        }
 
         self.state.stopped();
         // todo(debugger): Find a clean way to get around the clone
@@ -1552,7 +1635,7 @@ impl Session {
                 self.invalidate_state(&ThreadsCommand.into());
                 cx.notify();
             }
-            Events::Output(event) => {
+            Events::Output(mut event) => {
                 if event
                     .category
                     .as_ref()
@@ -1561,12 +1644,25 @@ impl Session {
                     return;
                 }
 
+                if let Some(source) = event.source.as_mut() {
+                    self.path_mapper.source_to_local(source);
+                }
                 self.push_output(event);
                 cx.notify();
             }
-            Events::Breakpoint(event) => self.breakpoint_store.update(cx, |store, _| {
-                store.update_session_breakpoint(self.session_id(), event.reason, event.breakpoint);
-            }),
+            Events::Breakpoint(mut event) => {
+                if let Some(source) = event.breakpoint.source.as_mut() {
+                    self.path_mapper.source_to_local(source);
+                }
+                self.breakpoint_store.update(cx, |store, cx| {
+                    store.update_session_breakpoint(
+                        self.session_id(),
+                        event.reason,
+                        event.breakpoint,
+                        cx,
+                    );
+                })
+            }
             Events::Module(event) => {
                 match event.reason {
                     dap::ModuleEventReason::New => {
@@ -1750,6 +1846,7 @@ impl Session {
         self.invalidate_command_type::<ThreadsCommand>();
         self.invalidate_command_type::<DataBreakpointInfoCommand>();
         self.invalidate_command_type::<ReadMemory>();
+        self.invalidate_disassembly();
         let executor = self.as_running().map(|running| running.executor.clone());
         if let Some(executor) = executor {
             self.memory.clear(&executor);
@@ -1890,7 +1987,9 @@ impl Session {
                 this.memory.clear(cx.background_executor());
                 this.invalidate_command_type::<ReadMemory>();
                 this.invalidate_command_type::<VariablesCommand>();
+                this.invalidate_disassembly();
                 cx.emit(SessionEvent::Variables);
+                cx.emit(SessionEvent::Disassembly);
                 response.ok()
             },
             cx,
@@ -1959,10 +2058,83 @@ impl Session {
         );
     }
 
+    /// Returns a page of [`DISASSEMBLY_PAGE_LEN`] instructions relative to the given memory reference, with negative pages preceding it.
+    /// Pages that were not fetched yet are requested, and [`SessionEvent::Disassembly`] is emitted once they arrive.
+    pub fn disassembly_page(
+        &mut self,
+        memory_reference: Arc<str>,
+        page: i64,
+        cx: &mut Context<Self>,
+    ) -> Option<Arc<[dap::DisassembledInstruction]>> {
+        let key = (memory_reference.clone(), page);
+        if let Some(instructions) = self.disassembly.get(&key) {
+            return Some(instructions.clone());
+        }
+
+        self.fetch(
+            DisassembleCommand {
+                memory_reference,
+                instruction_offset: page * DISASSEMBLY_PAGE_LEN as i64,
+                instruction_count: DISASSEMBLY_PAGE_LEN,
+            },
+            move |this, instructions, cx| {
+                let Some(instructions) = instructions.log_err() else {
+                    return;
+                };
+                this.disassembly.insert(key, instructions);
+                cx.emit(SessionEvent::Disassembly);
+                cx.notify();
+            },
+            cx,
+        );
+        None
+    }
+
+    fn invalidate_disassembly(&mut self) {
+        self.invalidate_command_type::<DisassembleCommand>();
+        self.disassembly.clear();
+    }
+
     pub fn ignore_breakpoints(&self) -> bool {
         self.ignore_breakpoints
     }
 
+    pub fn path_mapper(&self) -> &Arc<PathMapper> {
+        &self.path_mapper
+    }
+
+    /// Sets how source paths are translated for a debuggee that runs on another machine.
+    pub fn set_path_mapper(&mut self, path_mapper: PathMapper) {
+        self.path_mapper = Arc::new(path_mapper);
+        if let SessionState::Running(running) = &mut self.state {
+            running.path_mapper = self.path_mapper.clone();
+        }
+    }
+
+    pub fn restart_on_save(&self) -> Option<&Arc<RestartOnSave>> {
+        self.restart_on_save.as_ref()
+    }
+
+    /// Sets which saved files restart the debuggee, and how.
+    pub fn set_restart_on_save(&mut self, restart_on_save: Option<RestartOnSave>) {
+        self.restart_on_save = restart_on_save.map(Arc::new);
+    }
+
+    pub fn breakpoint_scope(&self) -> Option<&[Arc<Path>]> {
+        self.breakpoint_scope.as_deref()
+    }
+
+    /// Restricts the source breakpoints sent to this session's adapter to the files under the given directories.
+    ///
+    /// In a compound launch (e.g. a client and a server), this keeps each adapter from being asked to bind breakpoints
+    /// in sources it doesn't own. Breakpoints that were already sent are left as they are.
+    pub fn set_breakpoint_scope(&mut self, scope: Option<Vec<Arc<Path>>>) {
+        self.breakpoint_scope = scope.map(Arc::from);
+        if let SessionState::Running(running) = &mut self.state {
+            running.breakpoint_scope = self.breakpoint_scope.clone();
+        }
+    }
+
     pub fn toggle_ignore_breakpoints(
         &mut self,
         cx: &mut App,
@@ -2073,9 +2245,12 @@ impl Session {
         self.fetch(
             dap_command::LoadedSourcesCommand,
             |this, result, cx| {
-                let Some(result) = result.log_err() else {
+                let Some(mut result) = result.log_err() else {
                     return;
                 };
+                for source in &mut result {
+                    this.path_mapper.source_to_local(source);
+                }
                 this.active_snapshot.loaded_sources = result;
                 cx.emit(SessionEvent::LoadedSources);
                 cx.notify();
@@ -2274,6 +2449,27 @@ impl Session {
         .detach();
     }
 
+    /// Runs the thread backwards until it reaches a breakpoint or the start of the recording.
+    pub fn reverse_continue(&mut self, thread_id: ThreadId, cx: &mut Context<Self>) {
+        if !self.supports_step_back() {
+            return;
+        }
+        self.select_historic_snapshot(None, cx);
+
+        self.active_snapshot
+            .thread_states
+            .continue_thread(thread_id);
+        self.request(
+            ReverseContinueCommand {
+                thread_id: thread_id.0,
+                single_thread: self.capabilities.supports_single_thread_execution_requests,
+            },
+            Self::on_step_response::<ReverseContinueCommand>(thread_id),
+            cx,
+        )
+        .detach();
+    }
+
     pub fn adapter_client(&self) -> Option<Arc<DebugAdapterClient>> {
         match self.state {
             SessionState::Running(ref local) => Some(local.client.clone()),
@@ -2434,6 +2630,8 @@ impl Session {
                     levels: None,
                 },
                 move |this, stack_frames, cx| {
+                    let stack_frames = stack_frames
+                        .map(|stack_frames| this.path_mapper.stack_frames_to_local(stack_frames));
                     let entry =
                         this.active_snapshot
                             .threads
@@ -2561,8 +2759,8 @@ impl Session {
             .collect()
     }
 
-    pub fn watchers(&self) -> &HashMap<SharedString, Watcher> {
-        &self.watchers
+    pub fn watchers(&self) -> &IndexMap<SharedString, Watcher> {
+        self.watch_store.watchers()
     }
 
     pub fn add_watcher(
@@ -2582,30 +2780,134 @@ impl Session {
             let response = request.await?;
 
             this.update(cx, |session, cx| {
-                session.watchers.insert(
-                    expression.clone(),
-                    Watcher {
-                        expression,
-                        value: response.result.into(),
-                        variables_reference: response.variables_reference,
-                        presentation_hint: response.presentation_hint,
-                    },
+                session.watch_store.set_value(
+                    expression,
+                    response.result.into(),
+                    response.variables_reference,
+                    response.presentation_hint,
                 );
+                session.save_watch_expressions(cx);
                 cx.emit(SessionEvent::Watchers);
             })
         })
     }
 
+    /// Evaluates all watch expressions against the given stack frame, e.g. when the user selects it, unless they
+    /// were evaluated against it since the debuggee last stopped.
+    pub fn select_watch_frame(&mut self, frame_id: u64, cx: &mut Context<Self>) {
+        if self.watch_store.frame_id() != Some(frame_id) {
+            self.refresh_watchers(frame_id, cx);
+        }
+    }
+
+    /// Evaluates all watch expressions against the top stack frame of the thread that stopped.
+    fn refresh_watchers_on_stop(&mut self, thread_id: i64, cx: &mut Context<Self>) {
+        if self.watch_store.is_empty() {
+            return;
+        }
+        let request = self.state.request_dap(StackTraceCommand {
+            thread_id,
+            start_frame: None,
+            levels: Some(1),
+        });
+        cx.spawn(async move |this, cx| {
+            let Some(stack_frame) = request
+                .await
+                .log_err()
+                .and_then(|stack_frames| stack_frames.into_iter().next())
+            else {
+                return;
+            };
+            this.update(cx, |session, cx| {
+                session.select_watch_frame(stack_frame.id, cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    /// Re-evaluates all watch expressions against the given stack frame, e.g. after a variable was set.
+    /// Unlike [`Self::add_watcher`], expressions that fail to evaluate are kept, with the error as their value.
     pub fn refresh_watchers(&mut self, frame_id: u64, cx: &mut Context<Self>) {
-        let watches = self.watchers.clone();
-        for (_, watch) in watches.into_iter() {
-            self.add_watcher(watch.expression.clone(), frame_id, cx)
-                .detach();
+        self.watch_store.set_frame_id(frame_id);
+        let expressions = self.watch_store.expressions().cloned().collect::<Vec<_>>();
+        for expression in expressions {
+            let request = self.state.request_dap(EvaluateCommand {
+                expression: expression.to_string(),
+                context: Some(EvaluateArgumentsContext::Watch),
+                frame_id: Some(frame_id),
+                source: None,
+            });
+
+            cx.spawn(async move |this, cx| {
+                let response = request.await;
+                this.update(cx, |session, cx| {
+                    if !session.watch_store.contains(&expression) {
+                        return;
+                    }
+                    match response {
+                        Ok(response) => session.watch_store.set_value(
+                            expression,
+                            response.result.into(),
+                            response.variables_reference,
+                            response.presentation_hint,
+                        ),
+                        Err(error) => session
+                            .watch_store
+                            .set_error(expression, error.to_string().into()),
+                    }
+                    cx.emit(SessionEvent::Watchers);
+                })
+                .ok();
+            })
+            .detach();
         }
     }
 
-    pub fn remove_watcher(&mut self, expression: SharedString) {
-        self.watchers.remove(&expression);
+    pub fn remove_watcher(&mut self, expression: SharedString, cx: &mut Context<Self>) {
+        self.watch_store.remove(&expression);
+        self.save_watch_expressions(cx);
+        cx.emit(SessionEvent::Watchers);
+    }
+
+    /// Restores the watch expressions that earlier sessions persisted under the given key, and persists the
+    /// expressions of this session under it from now on.
+    fn load_watch_expressions(&mut self, key: String, cx: &mut Context<Self>) -> Task<()> {
+        self.watch_expressions_key = Some(key.clone());
+        cx.spawn(async move |this, cx| {
+            let expressions = cx
+                .background_spawn(async move { KEY_VALUE_STORE.read_kvp(&key) })
+                .await
+                .log_err()
+                .flatten()
+                .and_then(|value| serde_json::from_str::<Vec<String>>(&value).log_err());
+            if let Some(expressions) = expressions {
+                this.update(cx, |session, cx| {
+                    session
+                        .watch_store
+                        .restore(expressions.into_iter().map(SharedString::from));
+                    cx.emit(SessionEvent::Watchers);
+                })
+                .ok();
+            }
+        })
+    }
+
+    fn save_watch_expressions(&self, cx: &mut Context<Self>) {
+        let Some(key) = self.watch_expressions_key.clone() else {
+            return;
+        };
+        let expressions = self
+            .watch_store
+            .expressions()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let Some(value) = serde_json::to_string(&expressions).log_err() else {
+            return;
+        };
+        db::write_and_log(cx, move || async move {
+            KEY_VALUE_STORE.write_kvp(key, value).await
+        });
     }
 
     pub fn variables(
@@ -2694,6 +2996,60 @@ impl Session {
         source: Option<Source>,
         cx: &mut Context<Self>,
     ) -> Task<()> {
+        let task = self.evaluate_with_output(expression, context, frame_id, source, cx);
+        cx.background_spawn(async move {
+            task.await.ok();
+        })
+    }
+
+    /// Evaluates an expression typed into a debug console, echoing both the expression and its result to the output.
+    pub fn evaluate_repl(
+        &mut self,
+        expression: String,
+        frame_id: Option<u64>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<dap::EvaluateResponse>> {
+        self.evaluate_with_output(
+            expression,
+            Some(EvaluateArgumentsContext::Repl),
+            frame_id,
+            None,
+            cx,
+        )
+    }
+
+    /// Fetches completions for the text of a debug console, with `column` being the (1-based) position of the cursor in it.
+    pub fn repl_completions(
+        &mut self,
+        text: String,
+        column: u64,
+        frame_id: Option<u64>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Vec<dap::CompletionItem>>> {
+        self.completions(
+            CompletionsQuery {
+                query: text,
+                column,
+                line: None,
+                frame_id,
+            },
+            cx,
+        )
+    }
+
+    /// All of the retained output, with fragments of the same line joined and groups resolved.
+    pub fn repl_output(&self) -> Vec<ReplEntry> {
+        repl::aggregate_output(self.output.iter())
+    }
+
+    fn evaluate_with_output(
+        &mut self,
+        expression: String,
+        context: Option<EvaluateArgumentsContext>,
+        frame_id: Option<u64>,
+        source: Option<Source>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<dap::EvaluateResponse>> {
         let event = dap::OutputEvent {
             category: None,
             output: format!("> {expression}"),
@@ -2719,7 +3075,7 @@ impl Session {
                 this.invalidate_command_type::<ReadMemory>();
                 this.invalidate_command_type::<VariablesCommand>();
                 cx.emit(SessionEvent::Variables);
-                match response {
+                match &response {
                     Ok(response) => {
                         let event = dap::OutputEvent {
                             category: None,
@@ -2750,8 +3106,8 @@ impl Session {
                     }
                 };
                 cx.notify();
-            })
-            .ok();
+            })?;
+            response
         })
     }
 
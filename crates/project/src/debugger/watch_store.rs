@@ -0,0 +1,177 @@
+//! Watch expressions of a debug session.
+//!
+//! The user maintains a list of expressions which are re-evaluated against the selected stack frame whenever the debuggee stops.
+//! We remember the values each expression had before the latest stop, so that the UI can highlight the ones that changed.
+
+use collections::{HashMap, IndexMap};
+use dap::VariablePresentationHint;
+use gpui::SharedString;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watcher {
+    pub expression: SharedString,
+    pub value: SharedString,
+    pub variables_reference: u64,
+    pub presentation_hint: Option<VariablePresentationHint>,
+    /// Whether the value differs from the one the expression had before the latest stop.
+    pub changed: bool,
+    /// Whether the value is an error message, e.g. because the expression can't be evaluated in the active stack frame.
+    pub is_error: bool,
+}
+
+impl Watcher {
+    fn pending(expression: SharedString) -> Self {
+        Self {
+            expression,
+            value: SharedString::default(),
+            variables_reference: 0,
+            presentation_hint: None,
+            changed: false,
+            is_error: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WatchStore {
+    watchers: IndexMap<SharedString, Watcher>,
+    values_before_stop: HashMap<SharedString, SharedString>,
+    /// The stack frame the expressions were last evaluated against since the debuggee stopped.
+    frame_id: Option<u64>,
+}
+
+impl WatchStore {
+    pub fn watchers(&self) -> &IndexMap<SharedString, Watcher> {
+        &self.watchers
+    }
+
+    pub fn expressions(&self) -> impl Iterator<Item = &SharedString> {
+        self.watchers.keys()
+    }
+
+    pub fn contains(&self, expression: &SharedString) -> bool {
+        self.watchers.contains_key(expression)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watchers.is_empty()
+    }
+
+    pub fn frame_id(&self) -> Option<u64> {
+        self.frame_id
+    }
+
+    pub(crate) fn set_frame_id(&mut self, frame_id: u64) {
+        self.frame_id = Some(frame_id);
+    }
+
+    /// Adds expressions that have not been evaluated yet, e.g. the ones persisted from a previous session.
+    pub(crate) fn restore(&mut self, expressions: impl IntoIterator<Item = SharedString>) {
+        for expression in expressions {
+            self.watchers
+                .entry(expression.clone())
+                .or_insert_with(|| Watcher::pending(expression));
+        }
+    }
+
+    pub(crate) fn set_value(
+        &mut self,
+        expression: SharedString,
+        value: SharedString,
+        variables_reference: u64,
+        presentation_hint: Option<VariablePresentationHint>,
+    ) {
+        let changed = self
+            .values_before_stop
+            .get(&expression)
+            .is_some_and(|previous| *previous != value);
+        self.watchers.insert(
+            expression.clone(),
+            Watcher {
+                expression,
+                value,
+                variables_reference,
+                presentation_hint,
+                changed,
+                is_error: false,
+            },
+        );
+    }
+
+    pub(crate) fn set_error(&mut self, expression: SharedString, error: SharedString) {
+        self.watchers.insert(
+            expression.clone(),
+            Watcher {
+                value: error,
+                is_error: true,
+                ..Watcher::pending(expression)
+            },
+        );
+    }
+
+    pub(crate) fn remove(&mut self, expression: &SharedString) {
+        self.watchers.shift_remove(expression);
+        self.values_before_stop.remove(expression);
+    }
+
+    /// Records the current values as the baseline that values evaluated after this stop are compared against.
+    pub(crate) fn stopped(&mut self) {
+        self.frame_id = None;
+        self.values_before_stop = self
+            .watchers
+            .values()
+            .filter(|watcher| !watcher.is_error && !watcher.value.is_empty())
+            .map(|watcher| (watcher.expression.clone(), watcher.value.clone()))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_watchers() {
+        let mut store = WatchStore::default();
+        store.restore(["a".into(), "b".into(), "c".into()]);
+        assert_eq!(
+            store
+                .expressions()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["a", "b", "c"]
+        );
+
+        store.set_value("a".into(), "1".into(), 0, None);
+        store.set_value("b".into(), "2".into(), 0, None);
+        store.set_error("c".into(), "not in scope".into());
+        assert!(store.watchers().values().all(|watcher| !watcher.changed));
+
+        store.stopped();
+        store.set_value("a".into(), "1".into(), 0, None);
+        store.set_value("b".into(), "3".into(), 0, None);
+        store.set_value("c".into(), "4".into(), 0, None);
+        let changed = store
+            .watchers()
+            .values()
+            .map(|watcher| (watcher.expression.to_string(), watcher.changed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            vec![
+                ("a".to_string(), false),
+                ("b".to_string(), true),
+                ("c".to_string(), false)
+            ]
+        );
+
+        store.remove(&"b".into());
+        assert_eq!(
+            store
+                .expressions()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["a", "c"]
+        );
+    }
+}
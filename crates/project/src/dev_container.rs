@@ -0,0 +1,632 @@
+//! Support for running project processes inside of a [Dev Container](https://containers.dev).
+//!
+//! When enabled, the worktree is bind-mounted into a container started with the `devcontainer`
+//! CLI, and terminals, tasks and language servers are executed through `docker exec` with their
+//! paths translated between the host and the container.
+
+use anyhow::{Context as _, Result, anyhow, bail};
+use collections::HashMap;
+use lsp::{LanguageServerBinary, PathMapping};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use task::SpawnInTerminal;
+use util::{ResultExt as _, command::new_smol_command};
+
+/// Locations of the Dev Container configuration, relative to the workspace folder, in the order
+/// the `devcontainer` CLI looks them up.
+pub const DEV_CONTAINER_CONFIG_PATHS: &[&str] =
+    &[".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevContainerUp {
+    outcome: String,
+    container_id: Option<String>,
+    remote_user: Option<String>,
+    remote_workspace_folder: Option<String>,
+    message: Option<String>,
+}
+
+/// A running Dev Container with a host folder bind-mounted into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevContainer {
+    pub container_id: String,
+    pub remote_user: Option<String>,
+    pub host_workspace_folder: Arc<Path>,
+    pub container_workspace_folder: PathBuf,
+}
+
+impl DevContainer {
+    /// Translates a host path into the corresponding path inside of the container.
+    /// Returns `None` for paths outside of the bind-mounted workspace folder.
+    pub fn to_container_path(&self, host_path: &Path) -> Option<PathBuf> {
+        let relative = host_path.strip_prefix(&self.host_workspace_folder).ok()?;
+        Some(join_posix(&self.container_workspace_folder, relative))
+    }
+
+    /// Translates a path inside of the container into the corresponding host path.
+    /// Returns `None` for paths outside of the bind-mounted workspace folder.
+    pub fn to_host_path(&self, container_path: &Path) -> Option<PathBuf> {
+        let relative = container_path
+            .strip_prefix(&self.container_workspace_folder)
+            .ok()?;
+        Some(self.host_workspace_folder.join(relative))
+    }
+
+    /// Wraps a command so that it runs inside of the container via `docker exec`.
+    ///
+    /// The working directory is translated into the container, falling back to the workspace
+    /// folder when it is not inside of the bind mount.
+    pub fn wrap_command(
+        &self,
+        program: &str,
+        args: &[String],
+        cwd: Option<&Path>,
+        env: &HashMap<String, String>,
+        interactive: bool,
+    ) -> (String, Vec<String>) {
+        let mut docker_args = vec!["exec".to_string()];
+        if interactive {
+            docker_args.push("-it".to_string());
+        } else {
+            docker_args.push("-i".to_string());
+        }
+        if let Some(user) = &self.remote_user {
+            docker_args.push("-u".to_string());
+            docker_args.push(user.clone());
+        }
+        let container_cwd = cwd
+            .and_then(|cwd| self.to_container_path(cwd))
+            .unwrap_or_else(|| self.container_workspace_folder.clone());
+        docker_args.push("-w".to_string());
+        docker_args.push(container_cwd.to_string_lossy().into_owned());
+
+        let mut env = env.iter().collect::<Vec<_>>();
+        env.sort();
+        for (key, value) in env {
+            docker_args.push("-e".to_string());
+            docker_args.push(format!("{key}={value}"));
+        }
+
+        docker_args.push(self.container_id.clone());
+        docker_args.push(program.to_string());
+        docker_args.extend(args.iter().cloned());
+        ("docker".to_string(), docker_args)
+    }
+
+    /// Wraps a task so that it runs inside of the container via `docker exec`, with the host paths in its command,
+    /// arguments and environment translated into the container.
+    ///
+    /// Rather than the project environment of the host, the task gets `container_env` with its own
+    /// [`task_env`](SpawnInTerminal::task_env) on top, and runs after the POSIX shell commands of
+    /// `activation_script`, which activate a toolchain of the container.
+    pub fn wrap_task(
+        &self,
+        command: &str,
+        task: &SpawnInTerminal,
+        cwd: Option<&Path>,
+        container_env: &HashMap<String, String>,
+        activation_script: &[String],
+        interactive: bool,
+    ) -> (String, Vec<String>) {
+        let mut env = container_env.clone();
+        env.extend(
+            task.task_env
+                .iter()
+                .map(|(key, value)| (key.clone(), self.translate_host_paths(value))),
+        );
+        let args = task
+            .args
+            .iter()
+            .map(|arg| self.translate_host_paths(arg))
+            .collect::<Vec<_>>();
+        let (program, args) =
+            with_activation_script(self.translate_host_paths(command), args, activation_script);
+        self.wrap_command(&program, &args, cwd, &env, interactive)
+    }
+
+    /// Returns the POSIX shell commands activating a toolchain inside of the container, or `None` when the
+    /// toolchain isn't in the bind-mounted workspace folder, and so is only on the host.
+    ///
+    /// `source` is replaced with `.`, since the `/bin/sh` of many images (like Debian's dash) doesn't have it.
+    pub fn toolchain_activation_script(
+        &self,
+        toolchain_path: &Path,
+        activation_script: Vec<String>,
+    ) -> Option<Vec<String>> {
+        self.to_container_path(toolchain_path)?;
+        Some(
+            activation_script
+                .iter()
+                .map(|command| {
+                    let command = self.translate_host_paths(command);
+                    match command.strip_prefix("source ") {
+                        Some(script) => format!(". {script}"),
+                        None => command,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Replaces the paths inside of the bind-mounted workspace folder found in `text` with the corresponding
+    /// container paths, leaving the rest of it as is.
+    pub fn translate_host_paths(&self, text: &str) -> String {
+        let host_folder = self.host_workspace_folder.to_string_lossy();
+        let mut translated = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(host_folder.as_ref()) {
+            let path_start = &rest[start..];
+            let path_len = path_start[host_folder.len()..]
+                .find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ':' | ';'))
+                .map_or(path_start.len(), |len| host_folder.len() + len);
+            let (path, after_path) = path_start.split_at(path_len);
+            translated.push_str(&rest[..start]);
+            match self.to_container_path(Path::new(path)) {
+                Some(container_path) => translated.push_str(&container_path.to_string_lossy()),
+                None => translated.push_str(path),
+            }
+            rest = after_path;
+        }
+        translated.push_str(rest);
+        translated
+    }
+
+    /// Wraps a language server resolved on the host so that it runs inside of the container via `docker exec`,
+    /// returning the mapping of the paths in the messages exchanged with it along with the wrapped binary.
+    ///
+    /// Returns `None` when the server can't run in the container: its program has to be in the workspace folder or
+    /// installed in the container, and its arguments can't refer to host files outside of the workspace folder, such
+    /// as the scripts of the servers that Vector installs.
+    pub async fn wrap_language_server(
+        &self,
+        binary: &LanguageServerBinary,
+        container_env: &HashMap<String, String>,
+    ) -> Option<(LanguageServerBinary, PathMapping)> {
+        let mut args = Vec::with_capacity(binary.arguments.len());
+        for arg in &binary.arguments {
+            let arg = arg.to_string_lossy();
+            let path = Path::new(arg.as_ref());
+            if path.is_absolute() && self.to_container_path(path).is_none() {
+                return None;
+            }
+            args.push(self.translate_host_paths(&arg));
+        }
+        let program = match self.to_container_path(&binary.path) {
+            Some(program) => program.to_string_lossy().into_owned(),
+            None => {
+                self.which(&binary.path.file_name()?.to_string_lossy())
+                    .await?
+            }
+        };
+        let path_mapping = PathMapping::new(
+            &self.host_workspace_folder,
+            &self.container_workspace_folder.to_string_lossy(),
+        )
+        .log_err()?;
+        let (docker, docker_args) = self.wrap_command(&program, &args, None, container_env, false);
+        let binary = LanguageServerBinary {
+            path: docker.into(),
+            arguments: docker_args.into_iter().map(Into::into).collect(),
+            env: None,
+        };
+        Some((binary, path_mapping))
+    }
+
+    /// Finds a program on the `PATH` of the remote user's login shell inside of the container.
+    pub async fn which(&self, program: &str) -> Option<String> {
+        let (docker, args) = self.wrap_command(
+            "sh",
+            &[
+                "-lc".to_string(),
+                "command -v \"$1\"".to_string(),
+                "sh".to_string(),
+                program.to_string(),
+            ],
+            None,
+            &HashMap::default(),
+            false,
+        );
+        let output = new_smol_command(docker)
+            .args(args)
+            .output()
+            .await
+            .context("running docker exec")
+            .log_err()?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !path.is_empty()).then_some(path)
+    }
+
+    /// Captures the environment of the remote user's login shell inside of the container.
+    pub async fn capture_environment(&self) -> Result<HashMap<String, String>> {
+        let (program, args) = self.wrap_command(
+            "sh",
+            &["-lc".to_string(), "env -0".to_string()],
+            None,
+            &HashMap::default(),
+            false,
+        );
+        let output = new_smol_command(program)
+            .args(args)
+            .output()
+            .await
+            .context("running docker exec")?;
+        if !output.status.success() {
+            bail!(
+                "capturing Dev Container environment failed ({}), stderr: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(parse_null_separated_env(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+}
+
+/// Runs a program after the given POSIX shell commands, so that it inherits the environment they set up. The
+/// program doesn't run if any of the commands fail.
+pub fn with_activation_script(
+    program: String,
+    args: Vec<String>,
+    activation_script: &[String],
+) -> (String, Vec<String>) {
+    if activation_script.is_empty() {
+        return (program, args);
+    }
+    let script = format!("{} && exec \"$0\" \"$@\"", activation_script.join(" && "));
+    (
+        "sh".to_string(),
+        ["-c".to_string(), script, program]
+            .into_iter()
+            .chain(args)
+            .collect(),
+    )
+}
+
+/// Returns the Dev Container configuration file for the given workspace folder, if there is one.
+pub async fn find_dev_container_config(workspace_folder: &Path) -> Option<PathBuf> {
+    for relative_path in DEV_CONTAINER_CONFIG_PATHS {
+        let path = workspace_folder.join(relative_path);
+        if smol::fs::metadata(&path)
+            .await
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Builds (if needed) and starts the Dev Container for the given workspace folder, with the
+/// folder bind-mounted into the container.
+pub async fn start_dev_container(
+    cli_path: Option<PathBuf>,
+    workspace_folder: Arc<Path>,
+) -> Result<DevContainer> {
+    let cli_path = match cli_path {
+        Some(cli_path) => cli_path,
+        None => find_dev_container_cli()?,
+    };
+    let output = new_smol_command(&cli_path)
+        .arg("up")
+        .arg("--workspace-folder")
+        .arg(workspace_folder.as_ref())
+        .output()
+        .await
+        .with_context(|| format!("running {cli_path:?} up"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // The CLI logs progress to stdout before the final JSON result line.
+    let result_line = stdout
+        .lines()
+        .rev()
+        .find(|line| line.trim_start().starts_with('{'))
+        .with_context(|| {
+            format!(
+                "no result from devcontainer up ({}), stderr: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            )
+        })?;
+    let up: DevContainerUp =
+        serde_json::from_str(result_line).context("parsing devcontainer up result")?;
+    dev_container_from_up(up, workspace_folder)
+}
+
+fn dev_container_from_up(up: DevContainerUp, workspace_folder: Arc<Path>) -> Result<DevContainer> {
+    if up.outcome != "success" {
+        return Err(anyhow!(
+            "devcontainer up failed: {}",
+            up.message.unwrap_or(up.outcome)
+        ));
+    }
+    Ok(DevContainer {
+        container_id: up.container_id.context("missing container id")?,
+        remote_user: up.remote_user,
+        container_workspace_folder: up
+            .remote_workspace_folder
+            .context("missing remote workspace folder")?
+            .into(),
+        host_workspace_folder: workspace_folder,
+    })
+}
+
+fn find_dev_container_cli() -> Result<PathBuf> {
+    let cli_name = if cfg!(target_os = "windows") {
+        "devcontainer.cmd"
+    } else {
+        "devcontainer"
+    };
+    if let Ok(path) = which::which(cli_name) {
+        return Ok(path);
+    }
+    let installed_path = paths::devcontainer_dir()
+        .join("node_modules")
+        .join(".bin")
+        .join(cli_name);
+    if installed_path.is_file() {
+        Ok(installed_path)
+    } else {
+        bail!("could not find the devcontainer CLI in $PATH or {installed_path:?}")
+    }
+}
+
+/// Joins a host-relative path onto a POSIX container path, so that Windows hosts
+/// don't produce backslash-separated paths inside of the container.
+fn join_posix(base: &Path, relative: &Path) -> PathBuf {
+    let mut joined = base.to_string_lossy().trim_end_matches('/').to_string();
+    for component in relative.components() {
+        joined.push('/');
+        joined.push_str(&component.as_os_str().to_string_lossy());
+    }
+    if joined.is_empty() {
+        joined.push('/');
+    }
+    PathBuf::from(joined)
+}
+
+pub(crate) fn parse_null_separated_env(output: &str) -> HashMap<String, String> {
+    output
+        .split('\0')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('=')?;
+            (!key.is_empty()).then(|| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::path;
+
+    fn test_container() -> DevContainer {
+        DevContainer {
+            container_id: "abc123".to_string(),
+            remote_user: Some("vscode".to_string()),
+            host_workspace_folder: Path::new(path!("/home/user/project")).into(),
+            container_workspace_folder: PathBuf::from("/workspaces/project"),
+        }
+    }
+
+    #[test]
+    fn test_parse_dev_container_up() {
+        let json = r#"{"outcome":"success","containerId":"826abcac45af","remoteUser":"vscode","remoteWorkspaceFolder":"/workspaces/vector"}"#;
+        let up: DevContainerUp = serde_json::from_str(json).unwrap();
+        let container = dev_container_from_up(up, Path::new("/src/vector").into()).unwrap();
+        assert_eq!(container.container_id, "826abcac45af");
+        assert_eq!(container.remote_user.as_deref(), Some("vscode"));
+        assert_eq!(
+            container.container_workspace_folder,
+            PathBuf::from("/workspaces/vector")
+        );
+
+        let json = r#"{"outcome":"error","message":"Command failed: docker build"}"#;
+        let up: DevContainerUp = serde_json::from_str(json).unwrap();
+        assert!(dev_container_from_up(up, Path::new("/src/vector").into()).is_err());
+    }
+
+    #[test]
+    fn test_path_translation() {
+        let container = test_container();
+        assert_eq!(
+            container.to_container_path(Path::new(path!("/home/user/project/src/main.rs"))),
+            Some(PathBuf::from("/workspaces/project/src/main.rs"))
+        );
+        assert_eq!(
+            container.to_container_path(Path::new(path!("/home/user/other"))),
+            None
+        );
+        assert_eq!(
+            container.to_host_path(Path::new("/workspaces/project/src/main.rs")),
+            Some(
+                Path::new(path!("/home/user/project"))
+                    .join("src")
+                    .join("main.rs")
+            )
+        );
+        assert_eq!(container.to_host_path(Path::new("/usr/bin/cargo")), None);
+    }
+
+    #[test]
+    fn test_wrap_command() {
+        let container = test_container();
+        let env = HashMap::from_iter([("RUST_LOG".to_string(), "info".to_string())]);
+        let (program, args) = container.wrap_command(
+            "cargo",
+            &["test".to_string()],
+            Some(Path::new(path!("/home/user/project/crates"))),
+            &env,
+            false,
+        );
+        assert_eq!(program, "docker");
+        assert_eq!(
+            args,
+            [
+                "exec",
+                "-i",
+                "-u",
+                "vscode",
+                "-w",
+                "/workspaces/project/crates",
+                "-e",
+                "RUST_LOG=info",
+                "abc123",
+                "cargo",
+                "test"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_task() {
+        let container = test_container();
+        let host_file = Path::new(path!("/home/user/project/src/main.rs"));
+        let task = SpawnInTerminal {
+            command: Some("cargo".to_string()),
+            args: vec![
+                "run".to_string(),
+                "--manifest-path".to_string(),
+                Path::new(path!("/home/user/project/Cargo.toml"))
+                    .to_string_lossy()
+                    .into_owned(),
+            ],
+            env: HashMap::from_iter([
+                ("PATH".to_string(), "/host/bin".to_string()),
+                (
+                    "VECTOR_FILE".to_string(),
+                    host_file.to_string_lossy().into_owned(),
+                ),
+            ]),
+            task_env: HashMap::from_iter([(
+                "VECTOR_FILE".to_string(),
+                host_file.to_string_lossy().into_owned(),
+            )]),
+            ..SpawnInTerminal::default()
+        };
+        let container_env = HashMap::from_iter([("PATH".to_string(), "/usr/bin".to_string())]);
+        let (program, args) = container.wrap_task("cargo", &task, None, &container_env, &[], false);
+        assert_eq!(program, "docker");
+        assert_eq!(
+            args,
+            [
+                "exec",
+                "-i",
+                "-u",
+                "vscode",
+                "-w",
+                "/workspaces/project",
+                "-e",
+                "PATH=/usr/bin",
+                "-e",
+                "VECTOR_FILE=/workspaces/project/src/main.rs",
+                "abc123",
+                "cargo",
+                "run",
+                "--manifest-path",
+                "/workspaces/project/Cargo.toml"
+            ]
+        );
+
+        let venv = Path::new(path!("/home/user/project/.venv"));
+        let activation_script = container
+            .toolchain_activation_script(
+                venv,
+                vec![format!("source {}", venv.join("bin/activate").display())],
+            )
+            .unwrap();
+        let (_, args) = container.wrap_task(
+            "pytest",
+            &SpawnInTerminal::default(),
+            None,
+            &HashMap::default(),
+            &activation_script,
+            false,
+        );
+        assert_eq!(
+            args[args.len() - 4..],
+            [
+                "sh",
+                "-c",
+                ". /workspaces/project/.venv/bin/activate && exec \"$0\" \"$@\"",
+                "pytest"
+            ]
+        );
+        assert_eq!(
+            container.toolchain_activation_script(
+                Path::new(path!("/usr/lib/venv")),
+                vec!["source /usr/lib/venv/bin/activate".to_string()]
+            ),
+            None
+        );
+
+        let host_folder = Path::new(path!("/home/user/project")).to_string_lossy();
+        assert_eq!(
+            container
+                .translate_host_paths(&format!("--out-dir={host_folder} {host_folder}2 /tmp/file")),
+            "--out-dir=/workspaces/project /home/user/project2 /tmp/file"
+                .replace("/home/user/project", &host_folder)
+        );
+    }
+
+    #[gpui::test]
+    async fn test_wrap_language_server() {
+        let container = test_container();
+        let env = HashMap::from_iter([("PATH".to_string(), "/usr/bin".to_string())]);
+        let binary = LanguageServerBinary {
+            path: Path::new(path!("/home/user/project/node_modules/.bin/server")).into(),
+            arguments: vec![
+                "--stdio".into(),
+                Path::new(path!("/home/user/project/server.toml")).into(),
+            ],
+            env: Some(HashMap::default()),
+        };
+        let (wrapped, path_mapping) = container.wrap_language_server(&binary, &env).await.unwrap();
+        assert_eq!(wrapped.path, PathBuf::from("docker"));
+        assert_eq!(
+            wrapped.arguments,
+            [
+                "exec",
+                "-i",
+                "-u",
+                "vscode",
+                "-w",
+                "/workspaces/project",
+                "-e",
+                "PATH=/usr/bin",
+                "abc123",
+                "/workspaces/project/node_modules/.bin/server",
+                "--stdio",
+                "/workspaces/project/server.toml"
+            ]
+            .map(std::ffi::OsString::from)
+        );
+        assert_eq!(
+            path_mapping,
+            PathMapping::new(&container.host_workspace_folder, "/workspaces/project").unwrap()
+        );
+
+        // Servers whose scripts are on the host keep running there.
+        let binary = LanguageServerBinary {
+            arguments: vec![Path::new(path!("/home/user/.local/share/server.js")).into()],
+            ..binary
+        };
+        assert!(
+            container
+                .wrap_language_server(&binary, &env)
+                .await
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_null_separated_env() {
+        let env = parse_null_separated_env("PATH=/usr/bin\0MULTI=a\nb\0EMPTY=\0");
+        assert_eq!(env.get("PATH").map(String::as_str), Some("/usr/bin"));
+        assert_eq!(env.get("MULTI").map(String::as_str), Some("a\nb"));
+        assert_eq!(env.get("EMPTY").map(String::as_str), Some(""));
+    }
+}
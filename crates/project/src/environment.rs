@@ -1,10 +1,14 @@
 use anyhow::{Context as _, bail};
 use futures::{FutureExt, StreamExt as _, channel::mpsc, future::Shared};
 use language::Buffer;
-use std::{collections::VecDeque, path::Path, sync::Arc};
-use task::Shell;
-use terminal::terminal_settings::TerminalSettings;
-use util::{ResultExt, command::new_smol_command, rel_path::RelPath};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    sync::Arc,
+};
+use task::{ExecutionTarget, Shell};
+use terminal::terminal_settings::{TerminalEnvProfile, TerminalSettings};
+use util::{ResultExt, command::new_smol_command, paths::WslPath, rel_path::RelPath};
 use worktree::Worktree;
 
 use collections::HashMap;
@@ -12,13 +16,20 @@ use gpui::{App, AppContext as _, Context, Entity, EventEmitter, Task, WeakEntity
 use settings::Settings as _;
 
 use crate::{
+    dev_container::{self, DevContainer},
     project_settings::{DirenvSettings, ProjectSettings},
+    trusted_worktrees::TrustedWorktrees,
     worktree_store::WorktreeStore,
 };
 
 pub struct ProjectEnvironment {
     cli_environment: Option<HashMap<String, String>>,
+    /// Variables set on top of the environments of the project.
+    overrides: BTreeMap<String, String>,
     local_environments: HashMap<(Shell, Arc<Path>), Shared<Task<Option<HashMap<String, String>>>>>,
+    dev_containers: HashMap<Arc<Path>, Shared<Task<Option<Arc<DevContainer>>>>>,
+    /// The login shell environments of the Dev Containers, by container id.
+    dev_container_environments: HashMap<String, Shared<Task<Option<HashMap<String, String>>>>>,
     environment_error_messages: VecDeque<String>,
     environment_error_messages_tx: mpsc::UnboundedSender<String>,
     worktree_store: WeakEntity<WorktreeStore>,
@@ -49,7 +60,10 @@ impl ProjectEnvironment {
         });
         Self {
             cli_environment,
+            overrides: BTreeMap::new(),
             local_environments: Default::default(),
+            dev_containers: Default::default(),
+            dev_container_environments: Default::default(),
             environment_error_messages: Default::default(),
             environment_error_messages_tx: tx,
             worktree_store,
@@ -57,13 +71,29 @@ impl ProjectEnvironment {
         }
     }
 
+    pub fn overrides(&self) -> &BTreeMap<String, String> {
+        &self.overrides
+    }
+
+    /// Sets the variables applied on top of the environments of the project, reloading the
+    /// environments of directories.
+    pub fn set_overrides(&mut self, overrides: BTreeMap<String, String>) {
+        if overrides != self.overrides {
+            self.overrides = overrides;
+            self.local_environments.clear();
+        }
+    }
+
     /// Returns the inherited CLI environment, if this project was opened from the Vector CLI.
     pub(crate) fn get_cli_environment(&self) -> Option<HashMap<String, String>> {
         if cfg!(any(test, feature = "test-support")) {
-            return Some(HashMap::default());
+            let mut env = HashMap::default();
+            apply_overrides(&mut env, &self.overrides);
+            return Some(env);
         }
         if let Some(mut env) = self.cli_environment.clone() {
             set_origin_marker(&mut env, EnvironmentOrigin::Cli);
+            apply_overrides(&mut env, &self.overrides);
             Some(env)
         } else {
             None
@@ -171,6 +201,7 @@ impl ProjectEnvironment {
             .entry((shell.clone(), abs_path.clone()))
             .or_insert_with(|| {
                 let load_direnv = ProjectSettings::get_global(cx).load_direnv.clone();
+                let overrides = self.overrides.clone();
                 let shell = shell.clone();
                 let tx = self.environment_error_messages_tx.clone();
                 cx.spawn(async move |cx| {
@@ -204,6 +235,7 @@ impl ProjectEnvironment {
                         );
 
                         set_origin_marker(shell_env, EnvironmentOrigin::WorktreeShell);
+                        apply_overrides(shell_env, &overrides);
                     }
 
                     shell_env
@@ -213,6 +245,142 @@ impl ProjectEnvironment {
             .clone()
     }
 
+    /// Returns the Dev Container that processes started in the given directory should run in.
+    ///
+    /// The container is started lazily, once per worktree, when the worktree has a Dev Container
+    /// configuration and the `dev_container.enabled` setting is on for it.
+    pub fn dev_container(
+        &mut self,
+        abs_path: Arc<Path>,
+        cx: &mut App,
+    ) -> Shared<Task<Option<Arc<DevContainer>>>> {
+        self.dev_container_for_path(abs_path, false, cx)
+    }
+
+    /// Returns the Dev Container that a task started in the given directory should run in: the one of the project,
+    /// unless the task's execution target says otherwise. A task targeting the Dev Container starts it even when the
+    /// `dev_container.enabled` setting is off.
+    pub fn task_dev_container(
+        &mut self,
+        abs_path: Arc<Path>,
+        execution_target: Option<ExecutionTarget>,
+        cx: &mut App,
+    ) -> Shared<Task<Option<Arc<DevContainer>>>> {
+        match execution_target {
+            None => self.dev_container_for_path(abs_path, false, cx),
+            Some(ExecutionTarget::Host) => Task::ready(None).shared(),
+            Some(ExecutionTarget::DevContainer) => self.dev_container_for_path(abs_path, true, cx),
+        }
+    }
+
+    /// Returns the environment of the container user's login shell in the given Dev Container, which processes run
+    /// in the container get instead of the project environment of the host.
+    pub fn dev_container_environment(
+        &mut self,
+        container: Arc<DevContainer>,
+        cx: &mut App,
+    ) -> Shared<Task<Option<HashMap<String, String>>>> {
+        self.dev_container_environments
+            .entry(container.container_id.clone())
+            .or_insert_with(|| {
+                cx.background_spawn(async move {
+                    container
+                        .capture_environment()
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "capturing the environment of Dev Container {}",
+                                container.container_id
+                            )
+                        })
+                        .log_err()
+                })
+                .shared()
+            })
+            .clone()
+    }
+
+    fn dev_container_for_path(
+        &mut self,
+        abs_path: Arc<Path>,
+        required: bool,
+        cx: &mut App,
+    ) -> Shared<Task<Option<Arc<DevContainer>>>> {
+        let Some(worktree_store) = self.worktree_store.upgrade() else {
+            return Task::ready(None).shared();
+        };
+        let Some((worktree, _)) = worktree_store.read(cx).find_worktree(&abs_path, cx) else {
+            return Task::ready(None).shared();
+        };
+        let worktree = worktree.read(cx);
+        if !worktree.is_local() || worktree.is_single_file() {
+            return Task::ready(None).shared();
+        }
+        let worktree_id = worktree.id();
+        let workspace_folder = worktree.abs_path();
+        let settings = ProjectSettings::get(
+            Some(settings::SettingsLocation {
+                worktree_id,
+                path: RelPath::empty(),
+            }),
+            cx,
+        )
+        .dev_container
+        .clone();
+        if !settings.enabled && !required {
+            return Task::ready(None).shared();
+        }
+        // Starting a Dev Container runs the commands of the project's configuration, with a CLI that the project's
+        // settings can pick, so it waits until the worktree is trusted.
+        if let Some(trusted_worktrees) = TrustedWorktrees::try_get_global(cx)
+            && !trusted_worktrees.update(cx, |trusted_worktrees, cx| {
+                trusted_worktrees.can_trust(&worktree_store, worktree_id, cx)
+            })
+        {
+            return Task::ready(None).shared();
+        }
+
+        // Look the container up again when it failed to start or there was no configuration, so that fixing the
+        // configuration doesn't require reopening the project.
+        if let Some(dev_container) = self.dev_containers.get(&workspace_folder)
+            && !matches!(dev_container.peek(), Some(None))
+        {
+            return dev_container.clone();
+        }
+        let tx = self.environment_error_messages_tx.clone();
+        let dev_container = cx
+            .background_spawn({
+                let workspace_folder = workspace_folder.clone();
+                async move {
+                    dev_container::find_dev_container_config(&workspace_folder).await?;
+                    log::info!("starting Dev Container for {workspace_folder:?}");
+                    match dev_container::start_dev_container(
+                        settings.cli_path,
+                        workspace_folder.clone(),
+                    )
+                    .await
+                    {
+                        Ok(container) => Some(Arc::new(container)),
+                        Err(e) => {
+                            log::error!(
+                                "Failed to start Dev Container for {workspace_folder:?}: {e:#}"
+                            );
+                            tx.unbounded_send(format!(
+                                "Failed to start Dev Container for {}",
+                                workspace_folder.display()
+                            ))
+                            .ok();
+                            None
+                        }
+                    }
+                }
+            })
+            .shared();
+        self.dev_containers
+            .insert(workspace_folder, dev_container.clone());
+        dev_container
+    }
+
     pub fn peek_environment_error(&self) -> Option<&String> {
         self.environment_error_messages.front()
     }
@@ -220,12 +388,62 @@ impl ProjectEnvironment {
     pub fn pop_environment_error(&mut self) -> Option<String> {
         self.environment_error_messages.pop_front()
     }
+
+    /// Applies a terminal env profile to the environment of a terminal started in `cwd`, setting the profile's
+    /// variables and prepending its directories to `PATH`. Relative directories are resolved against `cwd`.
+    ///
+    /// When `env` has no `PATH`, the directories are prepended to the one Vector runs with, which the terminal would
+    /// otherwise inherit.
+    pub fn apply_terminal_env_profile(
+        profile: &TerminalEnvProfile,
+        cwd: Option<&Path>,
+        is_windows: bool,
+        env: &mut HashMap<String, String>,
+    ) {
+        env.extend(profile.env.clone());
+        if profile.path_prepend.is_empty() {
+            return;
+        }
+
+        let separator = if is_windows { ";" } else { ":" };
+        let mut path = profile
+            .path_prepend
+            .iter()
+            .map(|dir| {
+                let dir = shellexpand::tilde(dir);
+                match cwd {
+                    Some(cwd) if Path::new(dir.as_ref()).is_relative() => {
+                        cwd.join(dir.as_ref()).to_string_lossy().into_owned()
+                    }
+                    _ => dir.into_owned(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(separator);
+        let inherited_path = env
+            .get("PATH")
+            .cloned()
+            .or_else(|| std::env::var("PATH").ok());
+        if let Some(inherited_path) = inherited_path.filter(|path| !path.is_empty()) {
+            path.push_str(separator);
+            path.push_str(&inherited_path);
+        }
+        env.insert("PATH".to_owned(), path);
+    }
 }
 
 fn set_origin_marker(env: &mut HashMap<String, String>, origin: EnvironmentOrigin) {
     env.insert(VECTOR_ENVIRONMENT_ORIGIN_MARKER.to_string(), origin.into());
 }
 
+fn apply_overrides(env: &mut HashMap<String, String>, overrides: &BTreeMap<String, String>) {
+    env.extend(
+        overrides
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+}
+
 const VECTOR_ENVIRONMENT_ORIGIN_MARKER: &str = "VECTOR_ENVIRONMENT";
 
 enum EnvironmentOrigin {
@@ -271,6 +489,14 @@ async fn load_directory_shell_environment(
             .into()
     };
 
+    if let Some(wsl_path) = WslPath::from_path(&dir) {
+        return load_wsl_shell_environment(wsl_path).await.with_context(|| {
+            tx.unbounded_send("Failed to load WSL environment variables".into())
+                .ok();
+            format!("capturing WSL shell environment in {dir:?}")
+        });
+    }
+
     let (shell, args) = shell.program_and_args();
     let mut envs = util::shell_env::capture(shell.clone(), args, abs_path)
         .await
@@ -319,6 +545,27 @@ async fn load_directory_shell_environment(
     Ok(envs)
 }
 
+/// Captures the environment of the user's login shell inside of the WSL distro, so that
+/// processes started inside of the distro see the same `PATH` as a WSL terminal would.
+async fn load_wsl_shell_environment(wsl_path: WslPath) -> anyhow::Result<HashMap<String, String>> {
+    let output = new_smol_command("wsl.exe")
+        .args(wsl_path.exec_args())
+        .args(["sh", "-c", "exec \"${SHELL:-sh}\" -l -c 'env -0'"])
+        .output()
+        .await
+        .context("running wsl.exe")?;
+    if !output.status.success() {
+        bail!(
+            "Loading WSL environment failed ({}), stderr: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(dev_container::parse_null_separated_env(
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
 async fn load_direnv_environment(
     env: &HashMap<String, String>,
     dir: &Path,
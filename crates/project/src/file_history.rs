@@ -0,0 +1,201 @@
+//! The files opened in a project across sessions, ranked by how recently and how often they
+//! were opened.
+
+use std::{
+    cmp::Reverse,
+    path::{Path, PathBuf},
+};
+
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of files remembered, beyond which the least frecent ones are forgotten.
+const MAX_FILE_COUNT: usize = 500;
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileVisits {
+    pub open_count: u32,
+    /// When the file was last opened, in seconds since the Unix epoch.
+    pub last_opened: u64,
+    /// When the file was last closed, in seconds since the Unix epoch.
+    pub last_closed: Option<u64>,
+}
+
+impl FileVisits {
+    /// Weighs the number of times the file was opened by how long ago it was last opened.
+    fn frecency(&self, now: u64) -> u64 {
+        let age = now.saturating_sub(self.last_opened);
+        let weight = if age < 4 * DAY {
+            100
+        } else if age < 14 * DAY {
+            70
+        } else if age < 31 * DAY {
+            50
+        } else if age < 90 * DAY {
+            30
+        } else {
+            10
+        };
+        weight * u64::from(self.open_count)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.last_closed
+            .is_some_and(|last_closed| last_closed >= self.last_opened)
+    }
+}
+
+/// The visits of the files of a project, keyed by their absolute paths.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileHistory {
+    files: HashMap<PathBuf, FileVisits>,
+}
+
+impl FileHistory {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn visits(&self, abs_path: &Path) -> Option<&FileVisits> {
+        self.files.get(abs_path)
+    }
+
+    pub fn record_open(&mut self, abs_path: PathBuf, now: u64) {
+        let visits = self.files.entry(abs_path).or_default();
+        visits.open_count = visits.open_count.saturating_add(1);
+        visits.last_opened = now;
+        if self.files.len() > MAX_FILE_COUNT
+            && let Some(least_frecent) = self
+                .files
+                .iter()
+                .min_by_key(|(_, visits)| (visits.frecency(now), visits.last_opened))
+                .map(|(path, _)| path.clone())
+        {
+            self.files.remove(&least_frecent);
+        }
+    }
+
+    /// Records that a file was closed, returning whether it was in the history.
+    pub fn record_close(&mut self, abs_path: &Path, now: u64) -> bool {
+        let Some(visits) = self.files.get_mut(abs_path) else {
+            return false;
+        };
+        visits.last_closed = Some(now);
+        true
+    }
+
+    /// Returns the files ordered by when they were last opened, most recent first.
+    pub fn recent(&self) -> Vec<&Path> {
+        self.sorted_by_key(|visits| Some(visits.last_opened))
+    }
+
+    /// Returns the files that were closed since they were last opened, most recently closed
+    /// first.
+    pub fn recently_closed(&self) -> Vec<&Path> {
+        self.sorted_by_key(|visits| visits.is_closed().then_some(visits.last_closed).flatten())
+    }
+
+    /// Returns the files ordered by frecency, most frecent first.
+    pub fn frecent(&self, now: u64) -> Vec<&Path> {
+        self.sorted_by_key(|visits| Some(visits.frecency(now)))
+    }
+
+    /// Merges the history of a previous session into this one.
+    pub fn restore(&mut self, previous: FileHistory) {
+        for (abs_path, previous) in previous.files {
+            let visits = self.files.entry(abs_path).or_default();
+            visits.open_count = visits.open_count.saturating_add(previous.open_count);
+            visits.last_opened = visits.last_opened.max(previous.last_opened);
+            visits.last_closed = visits.last_closed.max(previous.last_closed);
+        }
+    }
+
+    fn sorted_by_key(&self, key: impl Fn(&FileVisits) -> Option<u64>) -> Vec<&Path> {
+        let mut files = self
+            .files
+            .iter()
+            .filter_map(|(path, visits)| Some((key(visits)?, visits.last_opened, path.as_path())))
+            .collect::<Vec<_>>();
+        files.sort_unstable_by_key(|(key, last_opened, path)| {
+            (Reverse(*key), Reverse(*last_opened), *path)
+        });
+        files.into_iter().map(|(_, _, path)| path).collect()
+    }
+}
+
+/// Returns the database key of the file history of a project with the given worktree roots.
+pub(crate) fn file_history_key<'a>(roots: impl IntoIterator<Item = &'a Path>) -> Option<String> {
+    let mut roots = roots
+        .into_iter()
+        .map(|root| root.to_string_lossy())
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        return None;
+    }
+    roots.sort_unstable();
+    Some(format!("project_file_history-{}", roots.join(":")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_history() {
+        let now = 100 * DAY;
+        let mut history = FileHistory::default();
+        history.record_open(PathBuf::from("/root/a.rs"), now - 50 * DAY);
+        history.record_open(PathBuf::from("/root/a.rs"), now - 40 * DAY);
+        history.record_open(PathBuf::from("/root/a.rs"), now - 40 * DAY);
+        history.record_open(PathBuf::from("/root/b.rs"), now - DAY);
+        history.record_open(PathBuf::from("/root/c.rs"), now - 2 * DAY);
+        assert!(history.record_close(Path::new("/root/c.rs"), now - DAY));
+        assert!(history.record_close(Path::new("/root/a.rs"), now - 30 * DAY));
+        assert!(!history.record_close(Path::new("/root/d.rs"), now));
+
+        assert_eq!(
+            history.recent(),
+            [
+                Path::new("/root/b.rs"),
+                Path::new("/root/c.rs"),
+                Path::new("/root/a.rs")
+            ]
+        );
+        assert_eq!(
+            history.recently_closed(),
+            [Path::new("/root/c.rs"), Path::new("/root/a.rs")]
+        );
+        // Older visits weigh less than recent ones, until there are enough of them.
+        assert_eq!(
+            history.frecent(now),
+            [
+                Path::new("/root/b.rs"),
+                Path::new("/root/c.rs"),
+                Path::new("/root/a.rs")
+            ]
+        );
+        history.record_open(PathBuf::from("/root/a.rs"), now - 20 * DAY);
+        assert_eq!(history.frecent(now)[0], Path::new("/root/a.rs"));
+
+        let mut restored = FileHistory::default();
+        restored.record_open(PathBuf::from("/root/b.rs"), now);
+        restored.restore(history.clone());
+        assert_eq!(
+            restored.visits(Path::new("/root/b.rs")),
+            Some(&FileVisits {
+                open_count: 2,
+                last_opened: now,
+                last_closed: None,
+            })
+        );
+        assert_eq!(restored.recent().len(), 3);
+
+        assert_eq!(
+            file_history_key([Path::new("/b"), Path::new("/a")]),
+            file_history_key([Path::new("/a"), Path::new("/b")]),
+        );
+        assert_eq!(file_history_key([]), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! Templates for the contents of newly created files.
+
+use chrono::NaiveDate;
+use util::rel_path::RelPath;
+
+/// The template to fill a newly created file with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EntryTemplate {
+    /// The template with the given name from the `file_templates` setting.
+    Named(String),
+    /// The `file_template` setting of the language that the new file belongs to.
+    Language,
+}
+
+/// File stems that name a module after their parent directory rather than themselves.
+const MODULE_INDEX_STEMS: &[&str] = &["mod", "index", "__init__", "lib", "main"];
+
+/// Replaces the `${filename}`, `${module}` and `${date}` variables of a template for the file
+/// at the given path.
+pub(crate) fn expand_file_template(template: &str, path: &RelPath, date: NaiveDate) -> String {
+    let file_name = path.file_name().unwrap_or_default();
+    let stem = path.file_stem().unwrap_or(file_name);
+    let module = if MODULE_INDEX_STEMS.contains(&stem) {
+        path.parent()
+            .and_then(|parent| parent.file_name())
+            .unwrap_or(stem)
+    } else {
+        stem
+    };
+    template
+        .replace("${filename}", file_name)
+        .replace("${module}", module)
+        .replace("${date}", &date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::rel_path::rel_path;
+
+    #[test]
+    fn test_expand_file_template() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+        assert_eq!(
+            expand_file_template(
+                "// ${filename}, created ${date}\npub struct ${module};\n",
+                rel_path("src/parser.rs"),
+                date
+            ),
+            "// parser.rs, created 2024-03-09\npub struct parser;\n"
+        );
+        assert_eq!(
+            expand_file_template(
+                "export * from './${module}';",
+                rel_path("ui/button/index.tsx"),
+                date
+            ),
+            "export * from './button';"
+        );
+        assert_eq!(
+            expand_file_template("${module}", rel_path("Makefile"), date),
+            "Makefile"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Batches of file system operations on project entries, applied in order and rolled back as
+//! a whole when any of them fails.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context as _, Result, bail};
+use fs::{CopyOptions, CreateOptions, Fs, RemoveOptions, RenameOptions, copy_recursive};
+use util::ResultExt as _;
+
+use crate::ProjectPath;
+
+/// A single operation of a [`crate::Project::apply_fs_transaction`] batch.
+///
+/// Paths refer to the state of the project after all of the preceding operations in the
+/// batch have been applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsOperation {
+    CreateFile(ProjectPath),
+    CreateDirectory(ProjectPath),
+    Rename {
+        old_path: ProjectPath,
+        new_path: ProjectPath,
+    },
+    Copy {
+        source: ProjectPath,
+        destination: ProjectPath,
+    },
+    Delete(ProjectPath),
+}
+
+/// An [`FsOperation`] with its project paths resolved to absolute paths.
+#[derive(Debug)]
+pub(crate) enum ResolvedFsOperation {
+    CreateFile(PathBuf),
+    CreateDirectory(PathBuf),
+    Rename {
+        old_path: PathBuf,
+        new_path: PathBuf,
+    },
+    Copy {
+        source: PathBuf,
+        destination: PathBuf,
+    },
+    Delete(PathBuf),
+}
+
+enum UndoOperation {
+    Remove(PathBuf),
+    Rename {
+        old_path: PathBuf,
+        new_path: PathBuf,
+    },
+}
+
+/// Applies the operations in order. When one of them fails, the already applied operations
+/// are reverted in reverse order and the error is returned.
+///
+/// Deleted entries are moved into a backup directory until the whole batch has succeeded, so
+/// that they can be restored.
+pub(crate) async fn apply_fs_operations(
+    fs: &dyn Fs,
+    operations: &[ResolvedFsOperation],
+) -> Result<()> {
+    static NEXT_TRANSACTION_ID: AtomicUsize = AtomicUsize::new(0);
+    let backup_dir = paths::temp_dir().join("fs-transactions").join(format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_TRANSACTION_ID.fetch_add(1, Ordering::SeqCst)
+    ));
+
+    let mut undo_log = Vec::new();
+    let mut result = Ok(());
+    for (ix, operation) in operations.iter().enumerate() {
+        if let Err(error) = apply_fs_operation(
+            fs,
+            operation,
+            &backup_dir.join(ix.to_string()),
+            &mut undo_log,
+        )
+        .await
+        {
+            result = Err(error.context(format!("applying {operation:?}")));
+            for undo in undo_log.drain(..).rev() {
+                revert_fs_operation(fs, undo).await.log_err();
+            }
+            break;
+        }
+    }
+
+    fs.remove_dir(
+        &backup_dir,
+        RemoveOptions {
+            recursive: true,
+            ignore_if_not_exists: true,
+        },
+    )
+    .await
+    .log_err();
+    result
+}
+
+async fn apply_fs_operation(
+    fs: &dyn Fs,
+    operation: &ResolvedFsOperation,
+    backup_path: &Path,
+    undo_log: &mut Vec<UndoOperation>,
+) -> Result<()> {
+    match operation {
+        ResolvedFsOperation::CreateFile(path) => {
+            ensure_does_not_exist(fs, path).await?;
+            fs.create_file(path, CreateOptions::default()).await?;
+            undo_log.push(UndoOperation::Remove(path.clone()));
+        }
+        ResolvedFsOperation::CreateDirectory(path) => {
+            ensure_does_not_exist(fs, path).await?;
+            fs.create_dir(path).await?;
+            undo_log.push(UndoOperation::Remove(path.clone()));
+        }
+        ResolvedFsOperation::Rename { old_path, new_path } => {
+            ensure_does_not_exist(fs, new_path).await?;
+            fs.rename(old_path, new_path, RenameOptions::default())
+                .await?;
+            undo_log.push(UndoOperation::Rename {
+                old_path: new_path.clone(),
+                new_path: old_path.clone(),
+            });
+        }
+        ResolvedFsOperation::Copy {
+            source,
+            destination,
+        } => {
+            ensure_does_not_exist(fs, destination).await?;
+            // Record the undo first, so that a partially copied directory is removed as well.
+            undo_log.push(UndoOperation::Remove(destination.clone()));
+            copy_recursive(fs, source, destination, CopyOptions::default()).await?;
+        }
+        ResolvedFsOperation::Delete(path) => {
+            if fs.metadata(path).await?.is_none() {
+                bail!("{path:?} does not exist");
+            }
+            move_path(fs, path, backup_path).await?;
+            undo_log.push(UndoOperation::Rename {
+                old_path: backup_path.to_path_buf(),
+                new_path: path.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn revert_fs_operation(fs: &dyn Fs, undo: UndoOperation) -> Result<()> {
+    match undo {
+        UndoOperation::Remove(path) => remove_path(fs, &path).await,
+        UndoOperation::Rename { old_path, new_path } => move_path(fs, &old_path, &new_path).await,
+    }
+}
+
+async fn ensure_does_not_exist(fs: &dyn Fs, path: &Path) -> Result<()> {
+    if fs.metadata(path).await?.is_some() {
+        bail!("{path:?} already exists");
+    }
+    Ok(())
+}
+
+/// Moves a file or directory, falling back to copying it when renaming fails, e.g. because
+/// the destination is on another device.
+async fn move_path(fs: &dyn Fs, source: &Path, destination: &Path) -> Result<()> {
+    let options = RenameOptions {
+        create_parents: true,
+        ..RenameOptions::default()
+    };
+    if fs.rename(source, destination, options).await.is_ok() {
+        return Ok(());
+    }
+    if let Some(parent) = destination.parent() {
+        fs.create_dir(parent).await?;
+    }
+    copy_recursive(fs, source, destination, CopyOptions::default())
+        .await
+        .with_context(|| format!("copying {source:?} to {destination:?}"))?;
+    remove_path(fs, source).await
+}
+
+async fn remove_path(fs: &dyn Fs, path: &Path) -> Result<()> {
+    let options = RemoveOptions {
+        recursive: true,
+        ignore_if_not_exists: true,
+    };
+    if fs.is_dir(path).await {
+        fs.remove_dir(path, options).await
+    } else {
+        fs.remove_file(path, options).await
+    }
+}
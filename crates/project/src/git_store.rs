@@ -4086,6 +4086,28 @@ impl Repository {
         })
     }
 
+    /// Loads the text of the file at `path` as it was at `revision`, or `None` if the file didn't exist then.
+    pub fn load_text_at_revision(
+        &mut self,
+        revision: String,
+        path: RepoPath,
+    ) -> oneshot::Receiver<Result<Option<String>>> {
+        self.send_job(None, move |git_repo, _cx| async move {
+            match git_repo {
+                RepositoryState::Local(LocalRepositoryState { backend, .. }) => {
+                    let object = format!("{revision}:{}", path.as_unix_str());
+                    let Some(Some(oid)) = backend.revparse_batch(vec![object]).await?.pop() else {
+                        return Ok(None);
+                    };
+                    backend.load_blob_content(oid.parse()?).await.map(Some)
+                }
+                RepositoryState::Remote(_) => {
+                    bail!("loading files at a revision isn't supported in remote projects")
+                }
+            }
+        })
+    }
+
     pub fn load_commit_diff(&mut self, commit: String) -> oneshot::Receiver<Result<CommitDiff>> {
         let id = self.id;
         self.send_job(None, move |git_repo, cx| async move {
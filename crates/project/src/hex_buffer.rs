@@ -0,0 +1,221 @@
+//! Binary files, opened as bytes that are shown and edited in a hex representation instead of
+//! being decoded into buffers.
+
+use std::{
+    fmt::Write as _,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Result, bail};
+use fs::{Fs, MTime};
+use gpui::{App, Context, Entity, EventEmitter, Task};
+use worktree::ProjectEntryId;
+
+use crate::{Project, ProjectItem, ProjectPath, large_file::opens_as_large_file};
+
+/// The number of bytes in each row of the hex representation.
+pub const HEX_ROW_LEN: usize = 16;
+
+/// Extensions of files that are opened as [`HexBuffer`]s without first trying to decode them.
+const BINARY_FILE_EXTENSIONS: &[&str] = &[
+    "a", "bin", "class", "dat", "dll", "dylib", "exe", "lib", "o", "obj", "pyc", "so", "wasm",
+];
+
+/// Whether the file at the path is opened as a [`HexBuffer`] rather than a buffer: either it has
+/// a binary extension, or it was found to be binary when it was last opened.
+pub(crate) fn opens_as_hex_buffer(project: &Project, path: &ProjectPath, cx: &App) -> bool {
+    if !project.is_local() || opens_as_large_file(project, path, cx) {
+        return false;
+    }
+    let has_binary_extension = path
+        .path
+        .extension()
+        .is_some_and(|extension| BINARY_FILE_EXTENSIONS.contains(&extension));
+    project
+        .entry_for_path(path, cx)
+        .is_some_and(|entry| entry.is_file())
+        && (has_binary_extension || project.buffer_store().read(cx).is_binary_path(path))
+}
+
+pub enum HexBufferEvent {
+    Edited,
+    DirtyChanged,
+    Saved,
+}
+
+/// The bytes of a file detected as binary when it was opened as a buffer.
+pub struct HexBuffer {
+    pub file: Arc<worktree::File>,
+    pub(crate) abs_path: PathBuf,
+    pub(crate) fs: Arc<dyn Fs>,
+    pub(crate) bytes: Vec<u8>,
+    /// The modification time of the file when its bytes were last read or written.
+    pub(crate) mtime: Option<MTime>,
+    pub(crate) is_dirty: bool,
+}
+
+impl EventEmitter<HexBufferEvent> for HexBuffer {}
+
+impl HexBuffer {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn abs_path(&self) -> &Path {
+        &self.abs_path
+    }
+
+    pub fn project_path(&self, cx: &App) -> ProjectPath {
+        ProjectPath {
+            worktree_id: self.file.worktree_id(cx),
+            path: self.file.path().clone(),
+        }
+    }
+
+    /// The number of rows in the hex representation.
+    pub fn row_count(&self) -> usize {
+        self.bytes.len().div_ceil(HEX_ROW_LEN)
+    }
+
+    /// Returns a row of the hex representation: the offset of its first byte, the bytes in
+    /// hexadecimal, and the printable ASCII characters among them.
+    pub fn hex_row(&self, row: usize) -> Option<String> {
+        let start = row.checked_mul(HEX_ROW_LEN)?;
+        let bytes = self.bytes.get(start..)?;
+        if bytes.is_empty() {
+            return None;
+        }
+        Some(format_hex_row(
+            start,
+            &bytes[..bytes.len().min(HEX_ROW_LEN)],
+        ))
+    }
+
+    /// Replaces the bytes in the given range. The range is clamped to the length of the file.
+    pub fn edit(&mut self, range: Range<usize>, new_bytes: &[u8], cx: &mut Context<Self>) {
+        let end = range.end.min(self.bytes.len());
+        let start = range.start.min(end);
+        if self.bytes[start..end] == *new_bytes {
+            return;
+        }
+        self.bytes.splice(start..end, new_bytes.iter().copied());
+        if !self.is_dirty {
+            self.is_dirty = true;
+            cx.emit(HexBufferEvent::DirtyChanged);
+        }
+        cx.emit(HexBufferEvent::Edited);
+        cx.notify();
+    }
+
+    /// Writes the bytes to the file, unless it was changed on disk since they were read.
+    pub fn save(&mut self, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let bytes = self.bytes.clone();
+        let mtime = self.mtime;
+        cx.spawn(async move |this, cx| {
+            if let Some(metadata) = fs.metadata(&abs_path).await?
+                && Some(metadata.mtime) != mtime
+            {
+                bail!("{abs_path:?} has changed on disk since it was opened");
+            }
+            fs.write(&abs_path, &bytes).await?;
+            let mtime = fs.metadata(&abs_path).await?.map(|metadata| metadata.mtime);
+            this.update(cx, |this, cx| {
+                this.mtime = mtime;
+                this.is_dirty = false;
+                cx.emit(HexBufferEvent::DirtyChanged);
+                cx.emit(HexBufferEvent::Saved);
+                cx.notify();
+            })
+        })
+    }
+
+    /// Reads the bytes from the file again, discarding any edits.
+    pub fn reload(&mut self, cx: &mut Context<Self>) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        cx.spawn(async move |this, cx| {
+            let mtime = fs.metadata(&abs_path).await?.map(|metadata| metadata.mtime);
+            let bytes = fs.load_bytes(&abs_path).await?;
+            this.update(cx, |this, cx| {
+                this.bytes = bytes;
+                this.mtime = mtime;
+                if this.is_dirty {
+                    this.is_dirty = false;
+                    cx.emit(HexBufferEvent::DirtyChanged);
+                }
+                cx.emit(HexBufferEvent::Edited);
+                cx.notify();
+            })
+        })
+    }
+}
+
+impl ProjectItem for HexBuffer {
+    fn try_open(
+        project: &Entity<Project>,
+        path: &ProjectPath,
+        cx: &mut App,
+    ) -> Option<Task<Result<Entity<Self>>>> {
+        if !opens_as_hex_buffer(project.read(cx), path, cx) {
+            return None;
+        }
+        Some(project.update(cx, |project, cx| project.open_hex_buffer(path.clone(), cx)))
+    }
+
+    fn entry_id(&self, _: &App) -> Option<ProjectEntryId> {
+        self.file.entry_id
+    }
+
+    fn project_path(&self, cx: &App) -> Option<ProjectPath> {
+        Some(self.project_path(cx))
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+}
+
+fn format_hex_row(offset: usize, bytes: &[u8]) -> String {
+    let mut row = format!("{offset:08x} ");
+    for ix in 0..HEX_ROW_LEN {
+        if ix % 8 == 0 {
+            row.push(' ');
+        }
+        if let Some(byte) = bytes.get(ix) {
+            write!(row, "{byte:02x} ").ok();
+        } else {
+            row.push_str("   ");
+        }
+    }
+    row.push('|');
+    row.extend(bytes.iter().map(|&byte| {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            byte as char
+        } else {
+            '.'
+        }
+    }));
+    row.push('|');
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_hex_row() {
+        assert_eq!(
+            format_hex_row(0x20, b"Hello,\x00world!\xff\n"),
+            "00000020  48 65 6c 6c 6f 2c 00 77  6f 72 6c 64 21 ff 0a    |Hello,.world!..|"
+        );
+        assert_eq!(
+            format_hex_row(0, b"ab"),
+            "00000000  61 62                                            |ab|"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Files too large to be loaded into buffers, whose contents are read in windows and searched
+//! line by line instead.
+
+use std::{
+    io::{BufRead as _, BufReader},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use fs::Fs;
+use gpui::{App, Entity, Task};
+use settings::Settings as _;
+use worktree::ProjectEntryId;
+
+use crate::{
+    Project, ProjectItem, ProjectPath, project_settings::ProjectSettings, search::SearchQuery,
+};
+
+/// The number of bytes read from a large file at a time.
+pub const LARGE_FILE_WINDOW_SIZE: u64 = 1024 * 1024;
+
+const LINES_PER_YIELD: usize = 1000;
+
+/// The size, in bytes, from which files are opened as [`LargeFile`]s rather than buffers, set by
+/// the `large_file_threshold_mb` setting.
+pub fn large_file_threshold(cx: &App) -> u64 {
+    ProjectSettings::get_global(cx)
+        .large_file_threshold_mb
+        .saturating_mul(1024 * 1024)
+}
+
+/// Whether the file at the path is opened as a [`LargeFile`] rather than a buffer. Large files
+/// can only be opened in local projects.
+pub(crate) fn opens_as_large_file(project: &Project, path: &ProjectPath, cx: &App) -> bool {
+    project.is_local()
+        && project
+            .entry_for_path(path, cx)
+            .is_some_and(|entry| entry.is_file() && entry.size >= large_file_threshold(cx))
+}
+
+/// A read-only view of a file too large to be loaded into a buffer. Large files aren't
+/// registered with language servers; they can still be opened as buffers to edit them.
+pub struct LargeFile {
+    pub file: Arc<worktree::File>,
+    pub(crate) abs_path: PathBuf,
+    pub(crate) fs: Arc<dyn Fs>,
+    pub(crate) size: u64,
+}
+
+/// Text read from a window of a [`LargeFile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LargeFileWindow {
+    /// The byte range of the file that the text was read from.
+    pub range: Range<u64>,
+    pub text: String,
+}
+
+impl LargeFile {
+    /// The size of the file in bytes when it was opened.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn abs_path(&self) -> &Path {
+        &self.abs_path
+    }
+
+    pub fn project_path(&self, cx: &App) -> ProjectPath {
+        ProjectPath {
+            worktree_id: self.file.worktree_id(cx),
+            path: self.file.path().clone(),
+        }
+    }
+
+    /// Reads a window of the file starting at the given byte offset. The window is trimmed to
+    /// whole characters, so its range may start and end a few bytes from the requested one.
+    pub fn read_window(&self, offset: u64, cx: &App) -> Task<Result<LargeFileWindow>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        cx.background_spawn(async move {
+            let range = offset..offset.saturating_add(LARGE_FILE_WINDOW_SIZE);
+            let bytes = fs.load_bytes_range(&abs_path, range).await?;
+            Ok(decode_window(offset, &bytes))
+        })
+    }
+
+    /// Searches the file line by line, returning the byte ranges of the matches. Lines that
+    /// aren't valid UTF-8 are skipped, and multiline queries aren't supported.
+    pub fn search(&self, query: SearchQuery, cx: &App) -> Task<Result<Vec<Range<u64>>>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        cx.background_spawn(async move {
+            let mut reader = BufReader::new(fs.open_sync(&abs_path).await?);
+            let max_ranges = query.limits().max_ranges;
+            let mut matches = Vec::new();
+            let mut line = Vec::new();
+            let mut offset = 0;
+            for line_ix in 1.. {
+                line.clear();
+                let len = reader.read_until(b'\n', &mut line)?;
+                if len == 0 || query.cancellation().is_cancelled() {
+                    break;
+                }
+                if let Ok(text) = std::str::from_utf8(&line) {
+                    for range in query.find_in_line(text.trim_end_matches(['\n', '\r']))? {
+                        matches.push(offset + range.start as u64..offset + range.end as u64);
+                        if matches.len() >= max_ranges {
+                            return Ok(matches);
+                        }
+                    }
+                }
+                offset += len as u64;
+                if line_ix % LINES_PER_YIELD == 0 {
+                    smol::future::yield_now().await;
+                }
+            }
+            Ok(matches)
+        })
+    }
+}
+
+impl ProjectItem for LargeFile {
+    fn try_open(
+        project: &Entity<Project>,
+        path: &ProjectPath,
+        cx: &mut App,
+    ) -> Option<Task<Result<Entity<Self>>>> {
+        if !opens_as_large_file(project.read(cx), path, cx) {
+            return None;
+        }
+        Some(project.update(cx, |project, cx| project.open_large_file(path.clone(), cx)))
+    }
+
+    fn entry_id(&self, _: &App) -> Option<ProjectEntryId> {
+        self.file.entry_id
+    }
+
+    fn project_path(&self, cx: &App) -> Option<ProjectPath> {
+        Some(self.project_path(cx))
+    }
+
+    fn is_dirty(&self) -> bool {
+        false
+    }
+}
+
+fn decode_window(offset: u64, bytes: &[u8]) -> LargeFileWindow {
+    let start = bytes
+        .iter()
+        .take(3)
+        .take_while(|byte| is_continuation_byte(**byte))
+        .count();
+    let mut end = bytes.len();
+    if let Some(lead_ix) = bytes[start..]
+        .iter()
+        .rposition(|byte| !is_continuation_byte(*byte))
+        .map(|ix| start + ix)
+        && lead_ix + utf8_char_len(bytes[lead_ix]) > end
+    {
+        end = lead_ix;
+    }
+    LargeFileWindow {
+        range: offset + start as u64..offset + end as u64,
+        text: String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+    }
+}
+
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+fn utf8_char_len(lead_byte: u8) -> usize {
+    match lead_byte {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_window() {
+        let text = "aé€😀b";
+        let bytes = text.as_bytes();
+        assert_eq!(
+            decode_window(0, bytes),
+            LargeFileWindow {
+                range: 0..bytes.len() as u64,
+                text: text.to_string(),
+            }
+        );
+        // A window starting and ending in the middle of characters is trimmed to whole ones.
+        assert_eq!(
+            decode_window(2, &bytes[2..8]),
+            LargeFileWindow {
+                range: 3..6,
+                text: "€".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Snapshots of the contents of local files, taken when they're saved and periodically while
+//! they have unsaved changes, so that earlier versions can be listed, compared and restored
+//! independently of version control.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use fs::{Fs, RemoveOptions};
+use futures::StreamExt as _;
+use gpui::{App, AppContext as _, Context, Entity, Subscription, Task};
+use language::{Buffer, BufferEvent, Diff};
+use settings::{Settings as _, SettingsLocation, SettingsStore};
+use sha2::{Digest as _, Sha256};
+use text::BufferId;
+use util::{ResultExt as _, time::unix_millis};
+
+use crate::{
+    buffer_store::{BufferStore, BufferStoreEvent},
+    project_settings::{LocalHistorySettings, ProjectSettings},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LocalHistoryTrigger {
+    /// The file was saved.
+    Save,
+    /// The file had unsaved changes when the snapshot interval elapsed.
+    Interval,
+}
+
+impl LocalHistoryTrigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Save => "save",
+            Self::Interval => "interval",
+        }
+    }
+
+    fn parse(trigger: &str) -> Option<Self> {
+        match trigger {
+            "save" => Some(Self::Save),
+            "interval" => Some(Self::Interval),
+            _ => None,
+        }
+    }
+}
+
+/// A snapshot of the contents of a file in its local history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalHistoryVersion {
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub trigger: LocalHistoryTrigger,
+    path: PathBuf,
+}
+
+impl LocalHistoryVersion {
+    fn file_name(timestamp: u64, trigger: LocalHistoryTrigger) -> String {
+        format!("{timestamp}-{}", trigger.as_str())
+    }
+
+    fn parse(path: PathBuf) -> Option<Self> {
+        let (timestamp, trigger) = path.file_name()?.to_str()?.split_once('-')?;
+        Some(Self {
+            timestamp: timestamp.parse().ok()?,
+            trigger: LocalHistoryTrigger::parse(trigger)?,
+            path,
+        })
+    }
+}
+
+/// Keeps the local history of the project's local files while the `local_history.enabled`
+/// setting is on for them. Private files are never snapshotted.
+pub struct LocalHistory {
+    fs: Arc<dyn Fs>,
+    buffer_store: Entity<BufferStore>,
+    /// How often dirty buffers are snapshotted. Project settings can only turn interval snapshots
+    /// off, since a single timer is shared by all files.
+    snapshot_interval: Option<Duration>,
+    /// The version of each buffer whose contents were last snapshotted.
+    snapshotted_versions: HashMap<BufferId, clock::Global>,
+    buffer_subscriptions: HashMap<BufferId, Subscription>,
+    /// The timestamp of the last snapshot, which later ones are kept distinct from.
+    last_timestamp: u64,
+    interval_task: Option<Task<()>>,
+    _subscriptions: [Subscription; 2],
+}
+
+impl LocalHistory {
+    pub fn new(fs: Arc<dyn Fs>, buffer_store: Entity<BufferStore>, cx: &mut Context<Self>) -> Self {
+        let mut this = Self {
+            fs,
+            snapshot_interval: ProjectSettings::get_global(cx)
+                .local_history
+                .snapshot_interval,
+            snapshotted_versions: HashMap::default(),
+            buffer_subscriptions: HashMap::default(),
+            last_timestamp: 0,
+            interval_task: None,
+            _subscriptions: [
+                cx.subscribe(&buffer_store, Self::on_buffer_store_event),
+                cx.observe_global::<SettingsStore>(Self::on_settings_changed),
+            ],
+            buffer_store: buffer_store.clone(),
+        };
+        for buffer in buffer_store.read(cx).buffers().collect::<Vec<_>>() {
+            this.subscribe_to_buffer(&buffer, cx);
+        }
+        this.restart_interval_task(cx);
+        this
+    }
+
+    /// Returns the versions of the file at the given path, most recent first.
+    pub fn versions(&self, abs_path: &Path, cx: &App) -> Task<Result<Vec<LocalHistoryVersion>>> {
+        let fs = self.fs.clone();
+        let history_dir = file_history_dir(abs_path);
+        cx.background_spawn(async move { load_versions(fs.as_ref(), &history_dir).await })
+    }
+
+    pub fn load_version(&self, version: &LocalHistoryVersion, cx: &App) -> Task<Result<String>> {
+        let fs = self.fs.clone();
+        let path = version.path.clone();
+        cx.background_spawn(async move { fs.load(&path).await })
+    }
+
+    /// Computes the edits that turn the contents of the buffer into the given version.
+    pub fn diff_version(
+        &self,
+        buffer: Entity<Buffer>,
+        version: &LocalHistoryVersion,
+        cx: &App,
+    ) -> Task<Result<Diff>> {
+        let load = self.load_version(version, cx);
+        cx.spawn(async move |cx| {
+            let text = load.await?;
+            let diff = buffer.read_with(cx, |buffer, cx| buffer.diff(text, cx))?;
+            Ok(diff.await)
+        })
+    }
+
+    /// Replaces the contents of the buffer with the given version, as an edit that can be
+    /// undone.
+    pub fn restore_version(
+        &self,
+        buffer: Entity<Buffer>,
+        version: &LocalHistoryVersion,
+        cx: &App,
+    ) -> Task<Result<()>> {
+        let diff = self.diff_version(buffer.clone(), version, cx);
+        cx.spawn(async move |cx| {
+            let diff = diff.await?;
+            buffer.update(cx, |buffer, cx| {
+                buffer.finalize_last_transaction();
+                buffer.apply_diff(diff, cx);
+                buffer.finalize_last_transaction();
+            })
+        })
+    }
+
+    fn on_settings_changed(&mut self, cx: &mut Context<Self>) {
+        let snapshot_interval = ProjectSettings::get_global(cx)
+            .local_history
+            .snapshot_interval;
+        if snapshot_interval != self.snapshot_interval {
+            self.snapshot_interval = snapshot_interval;
+            self.restart_interval_task(cx);
+        }
+    }
+
+    fn restart_interval_task(&mut self, cx: &mut Context<Self>) {
+        self.interval_task = None;
+        let Some(interval) = self.snapshot_interval else {
+            return;
+        };
+        self.interval_task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(interval).await;
+                if this
+                    .update(cx, |this, cx| this.snapshot_dirty_buffers(cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }));
+    }
+
+    fn on_buffer_store_event(
+        &mut self,
+        _: Entity<BufferStore>,
+        event: &BufferStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            BufferStoreEvent::BufferAdded(buffer) => self.subscribe_to_buffer(buffer, cx),
+            BufferStoreEvent::BufferDropped(buffer_id) => {
+                self.buffer_subscriptions.remove(buffer_id);
+                self.snapshotted_versions.remove(buffer_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn subscribe_to_buffer(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let subscription = cx.subscribe(buffer, |this, buffer, event, cx| {
+            if let BufferEvent::Saved = event {
+                this.snapshot(&buffer, LocalHistoryTrigger::Save, cx);
+            }
+        });
+        self.buffer_subscriptions
+            .insert(buffer.read(cx).remote_id(), subscription);
+    }
+
+    fn snapshot_dirty_buffers(&mut self, cx: &mut Context<Self>) {
+        let buffers = self
+            .buffer_store
+            .read(cx)
+            .buffers()
+            .filter(|buffer| {
+                let buffer = buffer.read(cx);
+                buffer.is_dirty()
+                    && buffer_settings(buffer, cx)
+                        .is_some_and(|settings| settings.snapshot_interval.is_some())
+            })
+            .collect::<Vec<_>>();
+        for buffer in buffers {
+            self.snapshot(&buffer, LocalHistoryTrigger::Interval, cx);
+        }
+    }
+
+    fn snapshot(
+        &mut self,
+        buffer: &Entity<Buffer>,
+        trigger: LocalHistoryTrigger,
+        cx: &mut Context<Self>,
+    ) {
+        let buffer = buffer.read(cx);
+        let Some(file) = buffer.file().and_then(|file| file.as_local()) else {
+            return;
+        };
+        let Some(settings) = buffer_settings(buffer, cx) else {
+            return;
+        };
+        if !settings.enabled || file.is_private() {
+            return;
+        }
+        let version = buffer.version();
+        if self.snapshotted_versions.get(&buffer.remote_id()) == Some(&version) {
+            return;
+        }
+        self.snapshotted_versions
+            .insert(buffer.remote_id(), version);
+
+        let timestamp = unix_millis(SystemTime::now()).max(self.last_timestamp + 1);
+        self.last_timestamp = timestamp;
+
+        let fs = self.fs.clone();
+        let history_dir = file_history_dir(&file.abs_path(cx));
+        let text = buffer.as_rope().clone();
+        cx.background_spawn(async move {
+            fs.create_dir(&history_dir).await?;
+            fs.write(
+                &history_dir.join(LocalHistoryVersion::file_name(timestamp, trigger)),
+                text.to_string().as_bytes(),
+            )
+            .await?;
+            prune_versions(fs.as_ref(), &history_dir, &settings, timestamp).await
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+/// The local history settings of the project that the buffer's file belongs to.
+fn buffer_settings(buffer: &Buffer, cx: &App) -> Option<LocalHistorySettings> {
+    let file = buffer.file()?;
+    let location = SettingsLocation {
+        worktree_id: file.worktree_id(cx),
+        path: file.path(),
+    };
+    Some(ProjectSettings::get(Some(location), cx).local_history)
+}
+
+async fn load_versions(fs: &dyn Fs, history_dir: &Path) -> Result<Vec<LocalHistoryVersion>> {
+    if !fs.is_dir(history_dir).await {
+        return Ok(Vec::new());
+    }
+    let mut entries = fs
+        .read_dir(history_dir)
+        .await
+        .with_context(|| format!("reading local history {history_dir:?}"))?;
+    let mut versions = Vec::new();
+    while let Some(path) = entries.next().await {
+        if let Some(version) = path.log_err().and_then(LocalHistoryVersion::parse) {
+            versions.push(version);
+        }
+    }
+    versions.sort_unstable_by_key(|version| std::cmp::Reverse(version.timestamp));
+    Ok(versions)
+}
+
+/// Deletes the versions beyond the ones that the retention settings keep.
+async fn prune_versions(
+    fs: &dyn Fs,
+    history_dir: &Path,
+    settings: &LocalHistorySettings,
+    now: u64,
+) -> Result<()> {
+    let min_timestamp = now.saturating_sub(settings.max_age.as_millis() as u64);
+    let versions = load_versions(fs, history_dir).await?;
+    for (ix, version) in versions.iter().enumerate() {
+        if ix >= settings.max_versions_per_file || version.timestamp < min_timestamp {
+            fs.remove_file(&version.path, RemoveOptions::default())
+                .await
+                .log_err();
+        }
+    }
+    Ok(())
+}
+
+fn file_history_dir(abs_path: &Path) -> PathBuf {
+    let digest = format!(
+        "{:x}",
+        Sha256::digest(abs_path.to_string_lossy().as_bytes())
+    );
+    paths::local_history_dir().join(&digest[..16])
+}
@@ -147,6 +147,9 @@ const SERVER_LAUNCHING_BEFORE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5
 pub const SERVER_PROGRESS_THROTTLE_TIMEOUT: Duration = Duration::from_millis(100);
 const WORKSPACE_DIAGNOSTICS_TOKEN_START: &str = "id:";
 const SERVER_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// The server id that diagnostics extracted from task output are reported under, as no language server produced them.
+pub const TASK_DIAGNOSTICS_SERVER_ID: LanguageServerId = LanguageServerId(usize::MAX);
+const TASK_DIAGNOSTICS_SOURCE: &str = "task";
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub enum ProgressToken {
@@ -414,6 +417,10 @@ impl LocalLspStore {
             });
         let update_binary_status = untrusted_worktree_task.is_none();
 
+        let settings_env = settings
+            .binary
+            .as_ref()
+            .and_then(|binary| binary.env.clone());
         let binary = self.get_language_server_binary(
             worktree_abs_path.clone(),
             adapter.clone(),
@@ -433,6 +440,7 @@ impl LocalLspStore {
             #[cfg(any(test, feature = "test-support"))]
             let lsp_store = self.weak.clone();
             let pending_workspace_folders = pending_workspace_folders.clone();
+            let environment = self.environment.clone();
             async move |cx| {
                 let binary = binary.await?;
                 #[cfg(any(test, feature = "test-support"))]
@@ -451,6 +459,16 @@ impl LocalLspStore {
                     return Ok(server);
                 }
 
+                // The worktree is trusted by now, as resolving the binary waits for it.
+                let (binary, path_mapping) = Self::run_in_dev_container(
+                    binary,
+                    &server_name,
+                    worktree_abs_path.clone(),
+                    settings_env,
+                    environment,
+                    cx,
+                )
+                .await?;
                 let code_action_kinds = adapter.code_action_kinds();
                 lsp::LanguageServer::new(
                     stderr_capture,
@@ -460,6 +478,7 @@ impl LocalLspStore {
                     &worktree_abs_path,
                     code_action_kinds,
                     Some(pending_workspace_folders),
+                    path_mapping,
                     cx,
                 )
             }
@@ -611,6 +630,46 @@ impl LocalLspStore {
         server_id
     }
 
+    /// Wraps the binary of a language server for a worktree in a Dev Container so that it runs in the container,
+    /// along with the mapping of the paths in the messages exchanged with it. Servers that can't run in the container
+    /// keep running on the host.
+    async fn run_in_dev_container(
+        binary: LanguageServerBinary,
+        server_name: &LanguageServerName,
+        worktree_abs_path: Arc<Path>,
+        settings_env: Option<HashMap<String, String>>,
+        environment: Entity<ProjectEnvironment>,
+        cx: &mut AsyncApp,
+    ) -> Result<(LanguageServerBinary, Option<lsp::PathMapping>)> {
+        let dev_container = environment
+            .update(cx, |environment, cx| {
+                environment.dev_container(worktree_abs_path.clone(), cx)
+            })?
+            .await;
+        let Some(dev_container) = dev_container else {
+            return Ok((binary, None));
+        };
+        let mut container_env = environment
+            .update(cx, |environment, cx| {
+                environment.dev_container_environment(dev_container.clone(), cx)
+            })?
+            .await
+            .unwrap_or_default();
+        container_env.extend(settings_env.unwrap_or_default());
+        match dev_container
+            .wrap_language_server(&binary, &container_env)
+            .await
+        {
+            Some((binary, path_mapping)) => Ok((binary, Some(path_mapping))),
+            None => {
+                log::warn!(
+                    "running language server {server_name} on the host, as it can't run in the Dev Container of {worktree_abs_path:?}"
+                );
+                Ok((binary, None))
+            }
+        }
+    }
+
     fn get_language_server_binary(
         &self,
         worktree_abs_path: Arc<Path>,
@@ -4200,7 +4259,9 @@ impl LspStore {
                         this.update_local_worktree_language_servers(&worktree, changes, cx);
                     }
                     worktree::Event::UpdatedGitRepositories(_)
-                    | worktree::Event::DeletedEntry(_) => {}
+                    | worktree::Event::DeletedEntry(_)
+                    | worktree::Event::ScanProgress(_)
+                    | worktree::Event::SymlinkEscapedRoot { .. } => {}
                 })
                 .detach()
             }
@@ -4212,7 +4273,9 @@ impl LspStore {
             | WorktreeStoreEvent::WorktreeOrderChanged
             | WorktreeStoreEvent::WorktreeUpdatedEntries(..)
             | WorktreeStoreEvent::WorktreeUpdatedGitRepositories(..)
-            | WorktreeStoreEvent::WorktreeDeletedEntry(..) => {}
+            | WorktreeStoreEvent::WorktreeDeletedEntry(..)
+            | WorktreeStoreEvent::WorktreeScanProgress(..)
+            | WorktreeStoreEvent::WorktreeSymlinkEscapedRoot(..) => {}
         }
     }
 
@@ -8383,6 +8446,93 @@ impl LspStore {
             .collect();
     }
 
+    /// Replaces the diagnostics extracted from the previous run of the task labeled `task_label` with `problems`.
+    ///
+    /// Relative problem paths are resolved against `cwd`; problems outside of the project's worktrees are dropped.
+    pub fn update_task_diagnostics(
+        &mut self,
+        task_label: &str,
+        cwd: Option<&Path>,
+        problems: Vec<task::Problem>,
+        cx: &mut Context<Self>,
+    ) -> anyhow::Result<()> {
+        let task_data = serde_json::json!({ "task": task_label });
+        let worktree_store = self.worktree_store.read(cx);
+        let mut diagnostics_by_path = HashMap::<PathBuf, Vec<_>>::default();
+        for (worktree_id, summaries) in &self.diagnostic_summaries {
+            let Some(worktree) = worktree_store.worktree_for_id(*worktree_id, cx) else {
+                continue;
+            };
+            for (path, summaries_by_server) in summaries {
+                if summaries_by_server.contains_key(&TASK_DIAGNOSTICS_SERVER_ID) {
+                    diagnostics_by_path
+                        .entry(worktree.read(cx).absolutize(path))
+                        .or_default();
+                }
+            }
+        }
+
+        let local = self
+            .as_local_mut()
+            .context("cannot update task diagnostics on a remote LspStore")?;
+        for problem in problems {
+            let abs_path = match cwd {
+                Some(cwd) if problem.path.is_relative() => cwd.join(&problem.path),
+                _ => problem.path,
+            };
+            if !abs_path.is_absolute() {
+                continue;
+            }
+            let severity = match problem.severity {
+                task::ProblemSeverity::Error => DiagnosticSeverity::ERROR,
+                task::ProblemSeverity::Warning => DiagnosticSeverity::WARNING,
+                task::ProblemSeverity::Info => DiagnosticSeverity::INFORMATION,
+                task::ProblemSeverity::Hint => DiagnosticSeverity::HINT,
+            };
+            let position = Unclipped(PointUtf16::new(problem.row, problem.column));
+            diagnostics_by_path
+                .entry(abs_path)
+                .or_default()
+                .push(DiagnosticEntry {
+                    range: position..position,
+                    diagnostic: Diagnostic {
+                        source: Some(TASK_DIAGNOSTICS_SOURCE.to_string()),
+                        severity,
+                        message: problem.message,
+                        group_id: post_inc(&mut local.next_diagnostic_group_id),
+                        is_primary: true,
+                        is_disk_based: true,
+                        data: Some(task_data.clone()),
+                        ..Diagnostic::default()
+                    },
+                });
+        }
+
+        let worktree_store = self.worktree_store.read(cx);
+        let updates = diagnostics_by_path
+            .into_iter()
+            .filter(|(abs_path, _)| worktree_store.find_worktree(abs_path, cx).is_some())
+            .map(
+                |(document_abs_path, diagnostics)| DocumentDiagnosticsUpdate {
+                    diagnostics: DocumentDiagnostics {
+                        diagnostics,
+                        document_abs_path,
+                        version: None,
+                    },
+                    result_id: None,
+                    registration_id: None,
+                    server_id: TASK_DIAGNOSTICS_SERVER_ID,
+                    disk_based_sources: Cow::Borrowed(&[]),
+                },
+            )
+            .collect::<Vec<_>>();
+        self.merge_diagnostic_entries(
+            updates,
+            move |_, diagnostic, _| diagnostic.data.as_ref() != Some(&task_data),
+            cx,
+        )
+    }
+
     #[cfg(test)]
     pub fn update_diagnostic_entries(
         &mut self,
@@ -9721,15 +9871,25 @@ impl LspStore {
         new_path: &Path,
         is_dir: bool,
     ) {
+        self.did_rename_entries(
+            worktree_id,
+            &[RenamedEntry {
+                old_path: old_path.to_path_buf(),
+                new_path: new_path.to_path_buf(),
+                is_dir,
+            }],
+        );
+    }
+
+    /// Notifies the worktree's language servers about renamed entries, sending a single
+    /// notification per server for all of them.
+    pub(super) fn did_rename_entries(&self, worktree_id: WorktreeId, renames: &[RenamedEntry]) {
         maybe!({
             let local_store = self.as_local()?;
-
-            let old_uri = lsp::Uri::from_file_path(old_path)
-                .ok()
-                .map(|uri| uri.to_string())?;
-            let new_uri = lsp::Uri::from_file_path(new_path)
-                .ok()
-                .map(|uri| uri.to_string())?;
+            let renames = renames
+                .iter()
+                .filter_map(RenamedEntry::file_rename)
+                .collect::<Vec<_>>();
 
             for language_server in local_store.language_servers_for_worktree(worktree_id) {
                 let Some(filter) = local_store
@@ -9739,14 +9899,16 @@ impl LspStore {
                     continue;
                 };
 
-                if filter.should_send_did_rename(&old_uri, is_dir) {
+                let files = renames
+                    .iter()
+                    .filter(|(rename, is_dir)| {
+                        filter.should_send_did_rename(&rename.old_uri, *is_dir)
+                    })
+                    .map(|(rename, _)| rename.clone())
+                    .collect::<Vec<_>>();
+                if !files.is_empty() {
                     language_server
-                        .notify::<DidRenameFiles>(RenameFilesParams {
-                            files: vec![FileRename {
-                                old_uri: old_uri.clone(),
-                                new_uri: new_uri.clone(),
-                            }],
-                        })
+                        .notify::<DidRenameFiles>(RenameFilesParams { files })
                         .ok();
                 }
             }
@@ -9762,18 +9924,34 @@ impl LspStore {
         is_dir: bool,
         cx: AsyncApp,
     ) -> Task<ProjectTransaction> {
-        let old_uri = lsp::Uri::from_file_path(old_path)
-            .ok()
-            .map(|uri| uri.to_string());
-        let new_uri = lsp::Uri::from_file_path(new_path)
-            .ok()
-            .map(|uri| uri.to_string());
+        Self::will_rename_entries(
+            this,
+            worktree_id,
+            vec![RenamedEntry {
+                old_path: old_path.to_path_buf(),
+                new_path: new_path.to_path_buf(),
+                is_dir,
+            }],
+            cx,
+        )
+    }
+
+    /// Asks the worktree's language servers for the edits to apply before renaming the given
+    /// entries, sending a single request per server for all of them.
+    pub(super) fn will_rename_entries(
+        this: WeakEntity<Self>,
+        worktree_id: WorktreeId,
+        renames: Vec<RenamedEntry>,
+        cx: AsyncApp,
+    ) -> Task<ProjectTransaction> {
+        let renames = renames
+            .iter()
+            .filter_map(RenamedEntry::file_rename)
+            .collect::<Vec<_>>();
         cx.spawn(async move |cx| {
             let mut tasks = vec![];
             this.update(cx, |this, cx| {
                 let local_store = this.as_local()?;
-                let old_uri = old_uri?;
-                let new_uri = new_uri?;
                 for language_server in local_store.language_servers_for_worktree(worktree_id) {
                     let Some(filter) = local_store
                         .language_server_paths_watched_for_rename
@@ -9782,16 +9960,19 @@ impl LspStore {
                         continue;
                     };
 
-                    if filter.should_send_will_rename(&old_uri, is_dir) {
+                    let files = renames
+                        .iter()
+                        .filter(|(rename, is_dir)| {
+                            filter.should_send_will_rename(&rename.old_uri, *is_dir)
+                        })
+                        .map(|(rename, _)| rename.clone())
+                        .collect::<Vec<_>>();
+                    if !files.is_empty() {
                         let apply_edit = cx.spawn({
-                            let old_uri = old_uri.clone();
-                            let new_uri = new_uri.clone();
                             let language_server = language_server.clone();
                             async move |this, cx| {
                                 let edit = language_server
-                                    .request::<WillRenameFiles>(RenameFilesParams {
-                                        files: vec![FileRename { old_uri, new_uri }],
-                                    })
+                                    .request::<WillRenameFiles>(RenameFilesParams { files })
                                     .await
                                     .into_response()
                                     .context("will rename files")
@@ -13374,6 +13555,22 @@ pub enum LanguageServerToQuery {
     Other(LanguageServerId),
 }
 
+/// A file or directory that is renamed, as reported to language servers.
+#[derive(Clone, Debug)]
+pub(crate) struct RenamedEntry {
+    pub old_path: PathBuf,
+    pub new_path: PathBuf,
+    pub is_dir: bool,
+}
+
+impl RenamedEntry {
+    fn file_rename(&self) -> Option<(FileRename, bool)> {
+        let old_uri = lsp::Uri::from_file_path(&self.old_path).ok()?.to_string();
+        let new_uri = lsp::Uri::from_file_path(&self.new_path).ok()?.to_string();
+        Some((FileRename { old_uri, new_uri }, self.is_dir))
+    }
+}
+
 #[derive(Default)]
 struct RenamePathsWatchedForServer {
     did_rename: Vec<RenameActionPredicate>,
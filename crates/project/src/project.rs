@@ -1,23 +1,44 @@
+mod buffer_locks;
 pub mod buffer_store;
 mod color_extractor;
 pub mod context_server_store;
 pub mod debounced_delay;
 pub mod debugger;
+pub mod dev_container;
+pub mod file_history;
+mod file_template;
+mod fs_transaction;
 pub mod git_store;
+pub mod hex_buffer;
 pub mod image_store;
+pub mod large_file;
+pub mod local_history;
 pub mod lsp_command;
 pub mod lsp_store;
 mod manifest_tree;
 pub mod prettier_store;
+mod project_descriptor;
+mod project_replace;
 mod project_search;
 pub mod project_settings;
+mod project_statistics;
 pub mod search;
+mod search_index;
+mod task_discovery;
+mod task_environment;
+mod task_history;
 mod task_inventory;
+mod task_output;
+mod task_plan;
+pub mod task_providers;
 pub mod task_store;
 pub mod telemetry_snapshot;
 pub mod terminals;
+pub mod todo_store;
 pub mod toolchain_store;
+mod trash;
 pub mod trusted_worktrees;
+pub mod unsaved_journal;
 pub mod worktree_store;
 
 #[cfg(all(test, feature = "remote"))]
@@ -27,13 +48,17 @@ mod environment;
 use buffer_diff::BufferDiff;
 use context_server_store::ContextServerStore;
 pub use environment::ProjectEnvironmentEvent;
+use file_history::FileHistory;
+pub use file_template::EntryTemplate;
+pub use fs_transaction::FsOperation;
+use fs_transaction::ResolvedFsOperation;
 #[cfg(feature = "collab")]
 use git::repository::get_git_committer;
 use git_store::{Repository, RepositoryId};
 pub mod search_history;
 mod yarn;
 
-use dap::inline_value::{InlineValueLocation, VariableLookupKind, VariableScope};
+use dap::{DapRegistry, inline_value::inline_value_locations};
 
 #[cfg(feature = "collab")]
 use crate::trusted_worktrees::{PathTrust, RemoteHostLocation, TrustedWorktrees};
@@ -47,15 +72,20 @@ pub use git_store::{
     git_traversal::{ChildEntriesGitIter, GitEntry, GitEntryRef, GitTraversal},
 };
 pub use manifest_tree::ManifestTree;
+pub use project_descriptor::{ProjectDescriptor, ToolchainDescriptor, WorktreeDescriptor};
+pub use project_replace::{ProjectReplace, ReplaceSummary};
 pub use project_search::{Search, SearchResults};
+pub use project_statistics::{ProjectStatistics, WorktreeStatistics};
+pub use trash::{TrashedEntry, TrashedEntryId};
 
-use anyhow::{Context as _, Result, anyhow};
+use anyhow::{Context as _, Result, anyhow, bail};
 use buffer_store::{BufferStore, BufferStoreEvent};
 use clock::ReplicaId;
 
 use dap::client::DebugAdapterClient;
 
 use collections::{BTreeSet, HashMap, HashSet, IndexSet};
+use db::kvp::KEY_VALUE_STORE;
 use debounced_delay::DebouncedDelay;
 pub use debugger::breakpoint_store::BreakpointWithPosition;
 use debugger::{
@@ -67,11 +97,20 @@ use encoding_rs;
 pub use environment::ProjectEnvironment;
 #[cfg(feature = "collab")]
 use futures::channel::mpsc::{self, UnboundedReceiver};
-use futures::{StreamExt, future::try_join_all};
+use futures::{
+    FutureExt, StreamExt,
+    future::{Shared, try_join_all},
+};
+pub use hex_buffer::HexBuffer;
 pub use image_store::{ImageItem, ImageStore};
 use image_store::{ImageItemEvent, ImageStoreEvent};
+pub use large_file::LargeFile;
+use local_history::LocalHistory;
 
-use ::git::{blame::Blame, status::FileStatus};
+use ::git::{
+    blame::Blame,
+    status::{FileStatus, GitSummary},
+};
 use gpui::{
     App, AppContext, AsyncApp, Context, Entity, EventEmitter, Hsla, SharedString, Task, WeakEntity,
     Window,
@@ -82,7 +121,8 @@ use language::proto::split_operations;
 use language::{
     Buffer, BufferEvent, Capability, CodeLabel, DiskState, Language, LanguageName,
     LanguageRegistry, PointUtf16, ToOffset, ToPointUtf16, Toolchain, ToolchainMetadata,
-    ToolchainScope, Transaction, Unclipped, language_settings::InlayHintKind,
+    ToolchainScope, Transaction, Unclipped,
+    language_settings::{AllLanguageSettings, InlayHintKind},
 };
 #[cfg(feature = "collab")]
 use lsp::MessageActionItem;
@@ -91,7 +131,7 @@ use lsp::{
     LanguageServerBinary, LanguageServerId, LanguageServerName, LanguageServerSelector,
 };
 use lsp_command::*;
-use lsp_store::{CompletionDocumentation, LspFormatTarget, OpenLspBufferHandle};
+use lsp_store::{CompletionDocumentation, LspFormatTarget, OpenLspBufferHandle, RenamedEntry};
 pub use manifest_tree::ManifestProvidersStore;
 use node_runtime::NodeRuntime;
 pub use prettier_store::PrettierStore;
@@ -100,7 +140,8 @@ use project_settings::{ProjectSettings, SettingsObserver, SettingsObserverEvent}
 use rpc::ErrorCode;
 use rpc::proto;
 use search::{SearchInputKind, SearchQuery, SearchResult};
-use search_history::SearchHistory;
+use search_history::{SearchHistory, SearchHistoryCursor, SerializedSearchHistories};
+use search_index::SearchIndex;
 use settings::{InvalidSettingsError, RegisterSetting, Settings, SettingsLocation, SettingsStore};
 use snippet::Snippet;
 pub use snippet_provider;
@@ -109,22 +150,26 @@ use std::{
     borrow::Cow,
     collections::BTreeMap,
     ffi::OsString,
+    iter,
     ops::Range,
     path::{Path, PathBuf},
     str::{self, FromStr},
     sync::Arc,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use task_store::TaskStore;
 use terminals::Terminals;
 use text::{Anchor, BufferId, OffsetRangeExt, Point, Rope};
+use todo_store::TodoStore;
 #[cfg(feature = "collab")]
 use toolchain_store::EmptyToolchainStore;
+use unsaved_journal::{RecoverableBuffer, UnsavedJournal, UnsavedJournalEvent};
 use util::{
     ResultExt as _, maybe,
-    paths::{PathStyle, SanitizedPath, is_absolute},
+    paths::{PathStyle, PathWithPosition, SanitizedPath, is_absolute},
     rel_path::RelPath,
+    time::unix_secs,
 };
 use worktree::{CreatedEntry, Snapshot, Traversal};
 pub use worktree::{
@@ -137,12 +182,17 @@ pub use fs::*;
 pub use language::Location;
 #[cfg(any(test, feature = "test-support"))]
 pub use prettier::FORMAT_SUFFIX as TEST_PRETTIER_FORMAT_SUFFIX;
+pub use task_discovery::{BuildFileKind, DiscoveredTask, discover_tasks};
+pub use task_environment::{TaskEnvironment, TaskEnvironmentLayer};
+pub use task_history::{TaskRun, TaskRunId};
 pub use task_inventory::{
     BasicContextProvider, ContextProviderWithTasks, DebugScenarioContext, Inventory, TaskContexts,
     TaskSourceKind,
 };
+pub use task_output::{TaskOutput, TaskOutputStream};
+pub use task_plan::{ExecutionPlan, PlanStep};
 
-pub use buffer_store::ProjectTransaction;
+pub use buffer_store::{LineEndingNormalization, ProjectTransaction};
 pub use lsp_store::{
     DiagnosticSummary, InvalidationStrategy, LanguageServerLogType, LanguageServerProgress,
     LanguageServerPromptRequest, LanguageServerStatus, LanguageServerToQuery, LspStore,
@@ -150,6 +200,10 @@ pub use lsp_store::{
 };
 pub use toolchain_store::{ToolchainStore, Toolchains};
 const MAX_PROJECT_SEARCH_HISTORY_SIZE: usize = 500;
+/// How long to wait for more changes to the file history before writing it.
+const FILE_HISTORY_SAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+/// How many files are opened and converted at a time when normalizing line endings.
+const NORMALIZE_LINE_ENDINGS_BATCH_SIZE: usize = 64;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ProjectId(pub u64);
@@ -204,6 +258,32 @@ pub struct Project {
     search_history: SearchHistory,
     search_included_history: SearchHistory,
     search_excluded_history: SearchHistory,
+    /// The database key the search histories are persisted under, derived from the roots of the
+    /// visible worktrees.
+    search_history_key: Option<String>,
+    /// Whether the search and file histories are persisted in the database, along with the watch
+    /// expressions of debug sessions.
+    persist_histories: bool,
+    file_history: FileHistory,
+    /// The database key the file history is persisted under.
+    file_history_key: Option<String>,
+    /// The absolute paths of the open buffers whose files are in the file history.
+    file_history_buffers: HashMap<BufferId, PathBuf>,
+    /// The paths passed to [`Project::open_buffer`] that are recorded in the file history once
+    /// their buffers are opened.
+    pending_file_history_opens: HashSet<ProjectPath>,
+    /// Writes the file history after a burst of changes, so that it isn't written for each of them.
+    file_history_save_task: Option<Task<()>>,
+    search_index: Option<Entity<SearchIndex>>,
+    /// The statistics of each worktree, along with the scan id they were computed at.
+    statistics_cache: HashMap<WorktreeId, (usize, Shared<Task<WorktreeStatistics>>)>,
+    todo_store: Option<Entity<TodoStore>>,
+    local_history: Option<Entity<LocalHistory>>,
+    /// Keeps the locks of the buffers in sync with the ones set in other instances.
+    _buffer_locks_task: Option<Task<()>>,
+    unsaved_journal: Option<Entity<UnsavedJournal>>,
+    /// The entries trashed in this session that can be restored, oldest first.
+    trashed_entries: Vec<TrashedEntry>,
     snippets: Entity<SnippetProvider>,
     environment: Entity<ProjectEnvironment>,
     #[allow(dead_code)]
@@ -244,6 +324,12 @@ pub enum Event {
     WorktreeOrderChanged,
     WorktreeRemoved(WorktreeId),
     WorktreeUpdatedEntries(WorktreeId, UpdatedEntriesSet),
+    /// A symlink within a worktree points outside of the worktree's root.
+    SymlinkEscapedWorktree {
+        worktree_id: WorktreeId,
+        path: Arc<RelPath>,
+        target: Arc<Path>,
+    },
     DiskBasedDiagnosticsStarted {
         language_server_id: LanguageServerId,
     },
@@ -276,6 +362,17 @@ pub enum Event {
     EntryRenamed(ProjectTransaction, ProjectPath, PathBuf),
     WorkspaceEditApplied(ProjectTransaction),
     AgentLocationChanged,
+    /// An autosave of the buffer was skipped because it conflicts with its file on disk.
+    AutosaveSkipped(Entity<Buffer>),
+    /// The buffer was evicted to stay within the `buffer_memory_budget_mb` setting. Holders
+    /// should release it and reopen the path when they need it again.
+    BufferEvicted {
+        buffer_id: BufferId,
+        project_path: ProjectPath,
+    },
+    /// Unsaved changes of buffers journaled by earlier sessions that ended abnormally can be
+    /// recovered with [`Project::recover_buffer`].
+    UnsavedBuffersRecoverable(usize),
 }
 
 pub enum DebugAdapterClientState {
@@ -958,6 +1055,7 @@ impl Project {
     pub fn init(cx: &mut App) {
         DapStore::init(cx);
         context_server_store::init(cx);
+        task_providers::init(cx);
     }
 
     pub fn local(
@@ -967,6 +1065,7 @@ impl Project {
         fs: Arc<dyn Fs>,
         env: Option<HashMap<String, String>>,
         init_worktree_trust: bool,
+        persist_histories: bool,
         cx: &mut App,
     ) -> Entity<Self> {
         cx.new(|cx: &mut Context<Self>| {
@@ -1013,6 +1112,7 @@ impl Project {
                     worktree_store.clone(),
                     breakpoint_store.clone(),
                     false,
+                    persist_histories,
                     cx,
                 )
             });
@@ -1032,9 +1132,20 @@ impl Project {
                 )
             });
 
+            let git_store = cx.new(|cx| {
+                GitStore::local(
+                    &worktree_store,
+                    buffer_store.clone(),
+                    environment.clone(),
+                    fs.clone(),
+                    cx,
+                )
+            });
+
             let task_store = cx.new(|cx| {
                 TaskStore::local(
                     worktree_store.clone(),
+                    git_store.clone(),
                     toolchain_store.read(cx).as_language_toolchain_store(),
                     environment.clone(),
                     cx,
@@ -1071,18 +1182,22 @@ impl Project {
                 )
             });
 
-            let git_store = cx.new(|cx| {
-                GitStore::local(
-                    &worktree_store,
-                    buffer_store.clone(),
-                    environment.clone(),
-                    fs.clone(),
-                    cx,
-                )
-            });
-
             cx.subscribe(&lsp_store, Self::on_lsp_store_event).detach();
 
+            let search_index =
+                cx.new(|cx| SearchIndex::new(fs.clone(), worktree_store.clone(), cx));
+            let todo_store = cx.new(|cx| {
+                TodoStore::new(fs.clone(), buffer_store.clone(), worktree_store.clone(), cx)
+            });
+            let local_history =
+                cx.new(|cx| LocalHistory::new(fs.clone(), buffer_store.clone(), cx));
+            let buffer_locks_task = Self::watch_buffer_locks(fs.clone(), cx);
+            let unsaved_journal = cx.new(|cx| {
+                UnsavedJournal::new(fs.clone(), buffer_store.clone(), worktree_store.clone(), cx)
+            });
+            cx.subscribe(&unsaved_journal, Self::on_unsaved_journal_event)
+                .detach();
+
             Self {
                 worktree_store,
                 buffer_store,
@@ -1090,7 +1205,20 @@ impl Project {
                 lsp_store,
                 context_server_store,
                 git_store,
-                _subscriptions: vec![cx.on_release(Self::release)],
+                _subscriptions: vec![
+                    cx.on_release(Self::release),
+                    cx.on_app_quit(|this, _| {
+                        let write = this
+                            .file_history_save_task
+                            .take()
+                            .and_then(|_| this.write_file_history());
+                        async move {
+                            if let Some(write) = write {
+                                write.await.log_err();
+                            }
+                        }
+                    }),
+                ],
                 active_entry: None,
                 snippets,
                 languages,
@@ -1111,6 +1239,20 @@ impl Project {
 
                 search_included_history: Self::new_search_history(),
                 search_excluded_history: Self::new_search_history(),
+                search_history_key: None,
+                persist_histories,
+                file_history: FileHistory::default(),
+                file_history_key: None,
+                file_history_buffers: HashMap::default(),
+                pending_file_history_opens: HashSet::default(),
+                file_history_save_task: None,
+                search_index: Some(search_index),
+                statistics_cache: HashMap::default(),
+                todo_store: Some(todo_store),
+                local_history: Some(local_history),
+                _buffer_locks_task: Some(buffer_locks_task),
+                unsaved_journal: Some(unsaved_journal),
+                trashed_entries: Vec::new(),
 
                 toolchain_store: Some(toolchain_store),
             }
@@ -1324,6 +1466,20 @@ impl Project {
 
                 search_included_history: Self::new_search_history(),
                 search_excluded_history: Self::new_search_history(),
+                search_history_key: None,
+                persist_histories: false,
+                file_history: FileHistory::default(),
+                file_history_key: None,
+                file_history_buffers: HashMap::default(),
+                pending_file_history_opens: HashSet::default(),
+                file_history_save_task: None,
+                search_index: None,
+                statistics_cache: HashMap::default(),
+                todo_store: None,
+                local_history: None,
+                _buffer_locks_task: None,
+                unsaved_journal: None,
+                trashed_entries: Vec::new(),
 
                 toolchain_store: Some(toolchain_store),
                 agent_location: None,
@@ -1582,6 +1738,20 @@ impl Project {
                 search_history: Self::new_search_history(),
                 search_included_history: Self::new_search_history(),
                 search_excluded_history: Self::new_search_history(),
+                search_history_key: None,
+                persist_histories: false,
+                file_history: FileHistory::default(),
+                file_history_key: None,
+                file_history_buffers: HashMap::default(),
+                pending_file_history_opens: HashSet::default(),
+                file_history_save_task: None,
+                search_index: None,
+                statistics_cache: HashMap::default(),
+                todo_store: None,
+                local_history: None,
+                _buffer_locks_task: None,
+                unsaved_journal: None,
+                trashed_entries: Vec::new(),
                 environment,
                 remotely_created_models: Arc::new(Mutex::new(RemotelyCreatedModels::default())),
                 toolchain_store: None,
@@ -1657,8 +1827,14 @@ impl Project {
         )
     }
 
-    fn release(&mut self, _cx: &mut App) {
+    fn release(&mut self, cx: &mut App) {
         // Strict offline build: no remote/collab shutdown needed.
+        if self.file_history_save_task.take().is_some()
+            && let Some(write) = self.write_file_history()
+        {
+            cx.background_spawn(async move { write.await.log_err() })
+                .detach();
+        }
     }
 
     #[cfg(any(test, feature = "test-support"))]
@@ -1678,6 +1854,7 @@ impl Project {
                     fs,
                     None,
                     false,
+                    false,
                     cx,
                 )
             })
@@ -1732,6 +1909,8 @@ impl Project {
                 fs,
                 None,
                 init_worktree_trust,
+                // Tests share the same database, so they only persist histories when asked to.
+                false,
                 cx,
             )
         });
@@ -1882,6 +2061,396 @@ impl Project {
         }
     }
 
+    /// The store of tagged comments across the project, for local projects.
+    pub fn todo_store(&self) -> Option<&Entity<TodoStore>> {
+        self.todo_store.as_ref()
+    }
+
+    /// The earlier versions of the project's files, for local projects.
+    pub fn local_history(&self) -> Option<&Entity<LocalHistory>> {
+        self.local_history.as_ref()
+    }
+
+    /// The journal of the unsaved changes of the project's buffers, for local projects.
+    pub fn unsaved_journal(&self) -> Option<&Entity<UnsavedJournal>> {
+        self.unsaved_journal.as_ref()
+    }
+
+    /// Opens the buffer whose unsaved contents were journaled by an earlier session, replacing
+    /// its contents with the journaled ones, and forgets the journaled contents.
+    pub fn recover_buffer(
+        &mut self,
+        recoverable: RecoverableBuffer,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Buffer>>> {
+        let Some(unsaved_journal) = self.unsaved_journal.clone() else {
+            return Task::ready(Err(anyhow!(
+                "unsaved changes can only be recovered in local projects"
+            )));
+        };
+        let open_buffer = match &recoverable.abs_path {
+            Some(abs_path) => self.open_local_buffer(abs_path, cx),
+            None => Task::ready(Ok(self.create_local_buffer("", None, true, cx))),
+        };
+        cx.spawn(async move |_, cx| {
+            let buffer = open_buffer.await?;
+            buffer.update(cx, |buffer, cx| {
+                buffer.set_text(recoverable.text.as_str(), cx);
+            })?;
+            unsaved_journal
+                .update(cx, |unsaved_journal, cx| {
+                    unsaved_journal.discard(&recoverable, cx)
+                })?
+                .await?;
+            Ok(buffer)
+        })
+    }
+
+    /// Computes the file counts, line counts by language, total size and git status summary of
+    /// each visible worktree in the background. The contents of worktrees are only rescanned
+    /// after they change.
+    pub fn statistics(&mut self, cx: &mut Context<Self>) -> Task<ProjectStatistics> {
+        let worktrees = self.visible_worktrees(cx).collect::<Vec<_>>();
+        self.statistics_cache.retain(|worktree_id, _| {
+            worktrees
+                .iter()
+                .any(|worktree| worktree.read(cx).id() == *worktree_id)
+        });
+
+        let repositories = self
+            .git_store
+            .read(cx)
+            .repositories()
+            .values()
+            .map(|repository| {
+                let repository = repository.read(cx);
+                (
+                    repository.work_directory_abs_path.clone(),
+                    repository.status_summary(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let tasks = worktrees
+            .into_iter()
+            .map(|worktree| {
+                let snapshot = worktree.read(cx).snapshot();
+                let scan_id = snapshot.scan_id();
+                let abs_path = snapshot.abs_path().clone();
+                let git_status = repositories
+                    .iter()
+                    .filter(|(work_directory, _)| {
+                        work_directory.starts_with(&abs_path)
+                            || abs_path.starts_with(work_directory)
+                    })
+                    .fold(GitSummary::UNCHANGED, |summary, (_, repository_summary)| {
+                        summary + *repository_summary
+                    });
+                let task = match self.statistics_cache.get(&snapshot.id()) {
+                    Some((cached_scan_id, task)) if *cached_scan_id == scan_id => task.clone(),
+                    _ => {
+                        let worktree_id = snapshot.id();
+                        let task = cx
+                            .background_spawn(project_statistics::scan_worktree(
+                                self.fs.clone(),
+                                self.languages.clone(),
+                                snapshot,
+                            ))
+                            .shared();
+                        self.statistics_cache
+                            .insert(worktree_id, (scan_id, task.clone()));
+                        task
+                    }
+                };
+                async move {
+                    WorktreeStatistics {
+                        git_status,
+                        ..task.await
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        cx.background_spawn(async move {
+            ProjectStatistics {
+                worktrees: futures::future::join_all(tasks).await,
+            }
+        })
+    }
+
+    /// Describes the visible worktrees of the project and their configuration, with the paths of
+    /// worktrees within the given directory relative to it.
+    pub fn descriptor(&self, dir: &Path, cx: &App) -> ProjectDescriptor {
+        let toolchains = self
+            .toolchain_store
+            .as_ref()
+            .map(|store| store.read(cx).active_toolchains(cx))
+            .unwrap_or_default();
+        let settings_observer = self.settings_observer.read(cx);
+        let worktrees = self
+            .visible_worktrees(cx)
+            .map(|worktree| {
+                let worktree = worktree.read(cx);
+                let worktree_id = worktree.id();
+                WorktreeDescriptor {
+                    path: project_descriptor::relativize(&worktree.abs_path(), dir),
+                    excluded_paths: settings_observer
+                        .worktree_settings_overlay(worktree_id, cx)
+                        .and_then(|overlay| overlay.worktree.file_scan_exclusions.clone())
+                        .unwrap_or_default(),
+                    toolchains: toolchains
+                        .iter()
+                        .filter(|(path, _)| path.worktree_id == worktree_id)
+                        .map(|(path, toolchain)| ToolchainDescriptor {
+                            language: toolchain.language_name.to_string(),
+                            directory: path.path.as_unix_str().to_string(),
+                            name: toolchain.name.to_string(),
+                            path: toolchain.path.to_string(),
+                            data: toolchain.as_json.clone(),
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        ProjectDescriptor {
+            worktrees,
+            environment: self.environment.read(cx).overrides().clone(),
+        }
+    }
+
+    /// Writes the descriptor of the project to a file, so that it can be shared and opened
+    /// with [`Project::open_from_descriptor`].
+    pub fn export_descriptor(&self, abs_path: PathBuf, cx: &App) -> Task<Result<()>> {
+        let Some(dir) = abs_path.parent() else {
+            return Task::ready(Err(anyhow!("invalid descriptor path {abs_path:?}")));
+        };
+        let json = match self.descriptor(dir, cx).to_json() {
+            Ok(json) => json,
+            Err(error) => return Task::ready(Err(error)),
+        };
+        let fs = self.fs.clone();
+        cx.background_spawn(async move { fs.atomic_write(abs_path, json).await })
+    }
+
+    /// Adds the worktrees of a descriptor file to the project, applying their excluded paths
+    /// and toolchains, along with the environment overrides of the descriptor.
+    pub fn open_from_descriptor(
+        &mut self,
+        abs_path: PathBuf,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        cx.spawn(async move |this, cx| {
+            let json = fs
+                .load(&abs_path)
+                .await
+                .with_context(|| format!("reading project descriptor {abs_path:?}"))?;
+            let descriptor = ProjectDescriptor::from_json(&json)?;
+            let dir = abs_path
+                .parent()
+                .with_context(|| format!("invalid descriptor path {abs_path:?}"))?;
+            this.update(cx, |this, cx| {
+                this.environment.update(cx, |environment, _| {
+                    environment.set_overrides(descriptor.environment)
+                })
+            })?;
+
+            for worktree in descriptor.worktrees {
+                let worktree_abs_path = project_descriptor::absolutize(&worktree.path, dir)?;
+                let (worktree_entity, _) = this
+                    .update(cx, |this, cx| {
+                        this.find_or_create_worktree(&worktree_abs_path, true, cx)
+                    })?
+                    .await?;
+                let worktree_id = worktree_entity.read_with(cx, |worktree, _| worktree.id())?;
+                if !worktree.excluded_paths.is_empty() {
+                    let overlay = serde_json::json!({
+                        "file_scan_exclusions": worktree.excluded_paths,
+                    })
+                    .to_string();
+                    this.update(cx, |this, cx| {
+                        this.settings_observer.update(cx, |settings_observer, cx| {
+                            settings_observer.set_worktree_settings_overlay(
+                                worktree_id,
+                                Some(overlay),
+                                cx,
+                            )
+                        })
+                    })??;
+                }
+                for toolchain in worktree.toolchains {
+                    let path = ProjectPath {
+                        worktree_id,
+                        path: RelPath::unix(&toolchain.directory)?.into_arc(),
+                    };
+                    let toolchain = Toolchain {
+                        name: toolchain.name.into(),
+                        path: toolchain.path.into(),
+                        language_name: LanguageName::new(&toolchain.language),
+                        as_json: toolchain.data,
+                    };
+                    this.update(cx, |this, cx| this.activate_toolchain(path, toolchain, cx))?
+                        .await;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Adds a query to the search history of the given kind, persisting the search histories.
+    pub fn add_to_search_history(
+        &mut self,
+        kind: SearchInputKind,
+        cursor: &mut SearchHistoryCursor,
+        query: String,
+        cx: &mut Context<Self>,
+    ) {
+        self.search_history_mut(kind).add(cursor, query);
+        self.save_search_history(cx);
+    }
+
+    fn save_search_history(&self, cx: &mut Context<Self>) {
+        let Some(key) = self.search_history_key.clone() else {
+            return;
+        };
+        let histories = SerializedSearchHistories {
+            query: self.search_history.entries().map(str::to_string).collect(),
+            include: self
+                .search_included_history
+                .entries()
+                .map(str::to_string)
+                .collect(),
+            exclude: self
+                .search_excluded_history
+                .entries()
+                .map(str::to_string)
+                .collect(),
+        };
+        let Some(value) = serde_json::to_string(&histories).log_err() else {
+            return;
+        };
+        db::write_and_log(cx, move || async move {
+            KEY_VALUE_STORE.write_kvp(key, value).await
+        });
+    }
+
+    /// Persists the search and file histories of a test project, restoring the histories of a
+    /// previous project with the same worktrees.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn persist_histories_for_test(&mut self, cx: &mut Context<Self>) {
+        self.persist_histories = true;
+        self.update_search_history_key(cx);
+    }
+
+    /// Points the search histories at the key of the current visible worktrees, restoring the
+    /// histories of a previous session when the first worktree is added.
+    fn update_search_history_key(&mut self, cx: &mut Context<Self>) {
+        if !self.is_local() || !self.persist_histories {
+            return;
+        }
+        // Scratch worktrees only exist in memory, so their histories aren't kept either.
+        let worktree_store = self.worktree_store.read(cx);
+        let roots = self
+            .visible_worktrees(cx)
+            .filter(|worktree| !worktree_store.is_scratch(worktree.read(cx).id()))
+            .map(|worktree| worktree.read(cx).abs_path())
+            .collect::<Vec<_>>();
+        let key = search_history::search_history_key(roots.iter().map(AsRef::as_ref));
+        let file_history_key = file_history::file_history_key(roots.iter().map(AsRef::as_ref));
+        let restore = self.search_history_key.is_none();
+        self.search_history_key = key.clone();
+        self.file_history_key = file_history_key.clone();
+        let (Some(key), Some(file_history_key)) = (key, file_history_key) else {
+            return;
+        };
+        if !restore {
+            return;
+        }
+        cx.spawn(async move |this, cx| {
+            let (histories, file_history) = cx
+                .background_spawn(async move {
+                    (
+                        KEY_VALUE_STORE.read_kvp(&key),
+                        KEY_VALUE_STORE.read_kvp(&file_history_key),
+                    )
+                })
+                .await;
+            let histories = histories.log_err().flatten().and_then(|value| {
+                serde_json::from_str::<SerializedSearchHistories>(&value).log_err()
+            });
+            let file_history = file_history
+                .log_err()
+                .flatten()
+                .and_then(|value| serde_json::from_str::<FileHistory>(&value).log_err());
+            this.update(cx, |this, _| {
+                if let Some(histories) = histories {
+                    this.search_history.restore(histories.query);
+                    this.search_included_history.restore(histories.include);
+                    this.search_excluded_history.restore(histories.exclude);
+                }
+                if let Some(file_history) = file_history {
+                    this.file_history.restore(file_history);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn save_file_history(&mut self, cx: &mut Context<Self>) {
+        if self.file_history_save_task.is_some() {
+            return;
+        }
+        self.file_history_save_task = Some(cx.spawn(async move |this, cx| {
+            cx.background_executor()
+                .timer(FILE_HISTORY_SAVE_DEBOUNCE)
+                .await;
+            let Ok(Some(write)) = this.update(cx, |this, _| {
+                this.file_history_save_task = None;
+                this.write_file_history()
+            }) else {
+                return;
+            };
+            cx.background_spawn(write).await.log_err();
+        }));
+    }
+
+    fn write_file_history(&self) -> Option<impl Future<Output = Result<()>> + Send + use<>> {
+        let key = self.file_history_key.clone()?;
+        let value = serde_json::to_string(&self.file_history).log_err()?;
+        Some(async move { KEY_VALUE_STORE.write_kvp(key, value).await })
+    }
+
+    /// Returns the files of the project that were opened, most recently opened first.
+    pub fn recent_paths(&self, cx: &App) -> Vec<ProjectPath> {
+        self.file_history_paths(self.file_history.recent(), cx)
+    }
+
+    /// Returns the files of the project that were closed and not reopened since, most recently
+    /// closed first.
+    pub fn recently_closed_paths(&self, cx: &App) -> Vec<ProjectPath> {
+        self.file_history_paths(self.file_history.recently_closed(), cx)
+    }
+
+    /// Returns the files of the project that were opened, ranked by how often and how recently
+    /// they were opened, so that file finders can suggest them before anything is typed.
+    pub fn frecent_paths(&self, cx: &App) -> Vec<ProjectPath> {
+        self.file_history_paths(self.file_history.frecent(unix_secs(SystemTime::now())), cx)
+    }
+
+    fn file_history_paths(&self, abs_paths: Vec<&Path>, cx: &App) -> Vec<ProjectPath> {
+        abs_paths
+            .into_iter()
+            .filter_map(|abs_path| {
+                let (worktree, path) = self.find_worktree(abs_path, cx)?;
+                let worktree = worktree.read(cx);
+                worktree.entry_for_path(&path)?;
+                Some(ProjectPath {
+                    worktree_id: worktree.id(),
+                    path,
+                })
+            })
+            .collect()
+    }
+
     #[inline]
     pub fn set_worktrees_reordered(&mut self, worktrees_reordered: bool, cx: &mut App) {
         self.worktree_store.update(cx, |store, _| {
@@ -2003,6 +2572,7 @@ impl Project {
         &mut self,
         project_path: impl Into<ProjectPath>,
         is_directory: bool,
+        template: Option<EntryTemplate>,
         cx: &mut Context<Self>,
     ) -> Task<Result<CreatedEntry>> {
         let project_path = project_path.into();
@@ -2011,11 +2581,68 @@ impl Project {
                 "No worktree for path {project_path:?}"
             ))));
         };
+        if self
+            .worktree_store
+            .read(cx)
+            .is_read_only(project_path.worktree_id)
+        {
+            return Task::ready(Err(anyhow!(
+                "Cannot create entries in a read-only worktree"
+            )));
+        }
+        let content = match template {
+            Some(template) if !is_directory => {
+                match self.resolve_file_template(&project_path, &template, cx) {
+                    Ok(content) => content.map(String::into_bytes),
+                    Err(error) => return Task::ready(Err(error)),
+                }
+            }
+            _ => None,
+        };
         worktree.update(cx, |worktree, cx| {
-            worktree.create_entry(project_path.path, is_directory, None, cx)
+            worktree.create_entry(project_path.path, is_directory, content, cx)
         })
     }
 
+    /// Returns the contents of a new file at the given path, with the template's variables
+    /// replaced, or `None` if its language has no template.
+    fn resolve_file_template(
+        &self,
+        project_path: &ProjectPath,
+        template: &EntryTemplate,
+        cx: &App,
+    ) -> Result<Option<String>> {
+        let location = SettingsLocation {
+            worktree_id: project_path.worktree_id,
+            path: &project_path.path,
+        };
+        let template = match template {
+            EntryTemplate::Named(name) => Some(
+                ProjectSettings::get(Some(location), cx)
+                    .file_templates
+                    .get(name)
+                    .cloned()
+                    .with_context(|| format!("No file template named {name:?}"))?,
+            ),
+            EntryTemplate::Language => self
+                .languages
+                .language_for_file_path(project_path.path.as_std_path())
+                .and_then(|language| {
+                    AllLanguageSettings::get(Some(location), cx)
+                        .language(Some(location), Some(&language.name()), cx)
+                        .file_template
+                        .clone()
+                }),
+        };
+        Ok(template.map(|template| {
+            file_template::expand_file_template(
+                &template,
+                &project_path.path,
+                chrono::Local::now().date_naive(),
+            )
+        }))
+    }
+
     #[inline]
     pub fn copy_entry(
         &mut self,
@@ -2028,6 +2655,17 @@ impl Project {
         })
     }
 
+    /// Copies the entry next to itself under a new name, recursively for directories.
+    pub fn duplicate_entry(
+        &mut self,
+        entry_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Option<Entry>>> {
+        self.worktree_store.update(cx, |worktree_store, cx| {
+            worktree_store.duplicate_entry(entry_id, cx)
+        })
+    }
+
     /// Renames the project entry with given `entry_id`.
     ///
     /// `new_path` is a relative path to worktree root.
@@ -2048,6 +2686,11 @@ impl Project {
         };
 
         let worktree_id = worktree.read(cx).id();
+        if worktree_store.read(cx).is_read_only(worktree_id) {
+            return Task::ready(Err(anyhow!(
+                "Cannot rename entries of a read-only worktree"
+            )));
+        }
         let is_root_entry = self.entry_is_worktree_root(entry_id, cx);
 
         let lsp_store = self.lsp_store().downgrade();
@@ -2099,6 +2742,201 @@ impl Project {
         })
     }
 
+    /// Applies a batch of create, rename, copy and delete operations in order. If any of them
+    /// fails, the ones that were already applied are reverted.
+    ///
+    /// Language servers are asked about and notified of all renames in the batch at once.
+    /// Returns the edits the language servers made in response to the renames.
+    pub fn apply_fs_transaction(
+        &mut self,
+        operations: Vec<FsOperation>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        let mut resolved_operations = Vec::with_capacity(operations.len());
+        let mut touched_paths = HashMap::<WorktreeId, Vec<Arc<RelPath>>>::default();
+        let mut renames = HashMap::<WorktreeId, Vec<RenamedEntry>>::default();
+        let mut renamed_paths = Vec::new();
+        let mut deleted_entries = Vec::new();
+        // The paths created by the preceding operations, with the paths they were renamed or
+        // copied from and whether they are directories.
+        let mut created_paths = Vec::<(ProjectPath, Option<ProjectPath>, bool)>::new();
+        for operation in &operations {
+            let resolved = maybe!({
+                let mut abs_path = |project_path: &ProjectPath, allow_root: bool| {
+                    if !allow_root && project_path.path.is_empty() {
+                        bail!("Cannot apply {operation:?} to a worktree root");
+                    }
+                    let worktree = self
+                        .worktree_for_id(project_path.worktree_id, cx)
+                        .with_context(|| format!("No worktree for path {project_path:?}"))?;
+                    if self
+                        .worktree_store
+                        .read(cx)
+                        .is_read_only(project_path.worktree_id)
+                    {
+                        bail!("Cannot modify entries of a read-only worktree");
+                    }
+                    touched_paths
+                        .entry(project_path.worktree_id)
+                        .or_default()
+                        .push(project_path.path.clone());
+                    anyhow::Ok(worktree.read(cx).absolutize(&project_path.path))
+                };
+                anyhow::Ok(match operation {
+                    FsOperation::CreateFile(path) => {
+                        let resolved = ResolvedFsOperation::CreateFile(abs_path(path, false)?);
+                        created_paths.push((path.clone(), None, false));
+                        resolved
+                    }
+                    FsOperation::CreateDirectory(path) => {
+                        let resolved = ResolvedFsOperation::CreateDirectory(abs_path(path, false)?);
+                        created_paths.push((path.clone(), None, true));
+                        resolved
+                    }
+                    FsOperation::Rename { old_path, new_path } => {
+                        let old_abs_path = abs_path(old_path, false)?;
+                        let new_abs_path = abs_path(new_path, false)?;
+                        let is_dir = self.is_dir_in_fs_transaction(old_path, &created_paths, cx);
+                        created_paths.push((new_path.clone(), Some(old_path.clone()), is_dir));
+                        renames
+                            .entry(old_path.worktree_id)
+                            .or_default()
+                            .push(RenamedEntry {
+                                old_path: old_abs_path.clone(),
+                                new_path: new_abs_path.clone(),
+                                is_dir,
+                            });
+                        renamed_paths.push((new_path.clone(), new_abs_path.clone()));
+                        ResolvedFsOperation::Rename {
+                            old_path: old_abs_path,
+                            new_path: new_abs_path,
+                        }
+                    }
+                    FsOperation::Copy {
+                        source,
+                        destination,
+                    } => {
+                        let resolved = ResolvedFsOperation::Copy {
+                            source: abs_path(source, true)?,
+                            destination: abs_path(destination, false)?,
+                        };
+                        let is_dir = self.is_dir_in_fs_transaction(source, &created_paths, cx);
+                        created_paths.push((destination.clone(), Some(source.clone()), is_dir));
+                        resolved
+                    }
+                    FsOperation::Delete(path) => {
+                        if let Some(entry) = self.entry_for_path(path, cx) {
+                            deleted_entries.push((path.worktree_id, entry.id));
+                        }
+                        ResolvedFsOperation::Delete(abs_path(path, false)?)
+                    }
+                })
+            });
+            match resolved {
+                Ok(resolved) => resolved_operations.push(resolved),
+                Err(error) => return Task::ready(Err(error)),
+            }
+        }
+
+        let fs = self.fs.clone();
+        let lsp_store = self.lsp_store().downgrade();
+        cx.spawn(async move |project, cx| {
+            let mut transaction = ProjectTransaction::default();
+            for (worktree_id, renames) in &renames {
+                let worktree_transaction = LspStore::will_rename_entries(
+                    lsp_store.clone(),
+                    *worktree_id,
+                    renames.clone(),
+                    cx.clone(),
+                )
+                .await;
+                transaction.0.extend(worktree_transaction.0);
+            }
+
+            let result = cx
+                .background_spawn(async move {
+                    fs_transaction::apply_fs_operations(fs.as_ref(), &resolved_operations).await
+                })
+                .await;
+            if let Err(error) = result {
+                // The language servers' edits assumed that the renames would happen.
+                project
+                    .update(cx, |project, cx| {
+                        project.undo_project_transaction(&transaction, cx)
+                    })
+                    .ok();
+                return Err(error);
+            }
+
+            let refreshes = project.update(cx, |project, cx| {
+                for (worktree_id, entry_id) in deleted_entries {
+                    cx.emit(Event::DeletedEntry(worktree_id, entry_id));
+                }
+                touched_paths
+                    .into_iter()
+                    .filter_map(|(worktree_id, mut paths)| {
+                        let worktree = project.worktree_for_id(worktree_id, cx)?;
+                        paths.sort();
+                        paths.dedup();
+                        let worktree = worktree.read(cx).as_local()?;
+                        Some(worktree.refresh_entries_for_paths(paths).into_future())
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+            futures::future::join_all(refreshes).await;
+
+            project.update(cx, |_, cx| {
+                for (ix, (new_path, new_abs_path)) in renamed_paths.into_iter().enumerate() {
+                    // Report the language servers' edits only once for the whole batch.
+                    let transaction = if ix == 0 {
+                        transaction.clone()
+                    } else {
+                        ProjectTransaction::default()
+                    };
+                    cx.emit(Event::EntryRenamed(transaction, new_path, new_abs_path));
+                }
+            })?;
+            lsp_store
+                .read_with(cx, |lsp_store, _| {
+                    for (worktree_id, renames) in &renames {
+                        lsp_store.did_rename_entries(*worktree_id, renames);
+                    }
+                })
+                .ok();
+            Ok(transaction)
+        })
+    }
+
+    /// Whether the path refers to a directory once the preceding operations of an
+    /// [`Self::apply_fs_transaction`] batch, which created `created_paths`, are applied.
+    fn is_dir_in_fs_transaction(
+        &self,
+        path: &ProjectPath,
+        created_paths: &[(ProjectPath, Option<ProjectPath>, bool)],
+        cx: &App,
+    ) -> bool {
+        let mut path = path.clone();
+        for (created_path, origin, is_dir) in created_paths.iter().rev() {
+            if created_path.worktree_id != path.worktree_id {
+                continue;
+            }
+            if created_path.path == path.path {
+                return *is_dir;
+            }
+            // Entries inside of a renamed or copied directory come from the original one.
+            if let Some(origin) = origin
+                && let Ok(suffix) = path.path.strip_prefix(&created_path.path)
+            {
+                path = ProjectPath {
+                    worktree_id: origin.worktree_id,
+                    path: origin.path.join(suffix),
+                };
+            }
+        }
+        self.entry_for_path(&path, cx)
+            .is_some_and(|entry| entry.is_dir())
+    }
+
     #[inline]
     pub fn delete_file(
         &mut self,
@@ -2118,12 +2956,182 @@ impl Project {
         cx: &mut Context<Self>,
     ) -> Option<Task<Result<()>>> {
         let worktree = self.worktree_for_entry(entry_id, cx)?;
+        if self
+            .worktree_store
+            .read(cx)
+            .is_read_only(worktree.read(cx).id())
+        {
+            return Some(Task::ready(Err(anyhow!(
+                "Cannot delete entries of a read-only worktree"
+            ))));
+        }
+        let restorable = if trash {
+            self.stash_trashed_entry(&worktree, entry_id, cx)
+        } else {
+            None
+        };
         cx.emit(Event::DeletedEntry(worktree.read(cx).id(), entry_id));
-        worktree.update(cx, |worktree, cx| {
-            worktree.delete_entry(entry_id, trash, cx)
+        let Some((trashed_entry, stash)) = restorable else {
+            return worktree.update(cx, |worktree, cx| {
+                worktree.delete_entry(entry_id, trash, cx)
+            });
+        };
+        Some(cx.spawn(async move |this, cx| {
+            let stashed = stash.await.log_err().is_some();
+            worktree
+                .update(cx, |worktree, cx| {
+                    worktree.delete_entry(entry_id, trash, cx)
+                })?
+                .context("entry not found")?
+                .await?;
+            if stashed {
+                this.update(cx, |this, cx| this.push_trashed_entry(trashed_entry, cx))?;
+            }
+            Ok(())
+        }))
+    }
+
+    /// Deletes several entries, reporting the result of each deletion.
+    pub fn delete_entries(
+        &mut self,
+        entry_ids: Vec<ProjectEntryId>,
+        trash: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Vec<(ProjectEntryId, Result<()>)>> {
+        let deletions = entry_ids
+            .into_iter()
+            .map(|entry_id| {
+                let task = self.delete_entry(entry_id, trash, cx);
+                async move {
+                    let result = match task {
+                        Some(task) => task.await,
+                        None => Err(anyhow!("entry {entry_id:?} not found")),
+                    };
+                    (entry_id, result)
+                }
+            })
+            .collect::<Vec<_>>();
+        cx.background_spawn(futures::future::join_all(deletions))
+    }
+
+    /// Returns the entries trashed in this session that can be restored, most recently deleted
+    /// first.
+    pub fn recently_deleted(&self) -> impl DoubleEndedIterator<Item = &TrashedEntry> {
+        self.trashed_entries.iter().rev()
+    }
+
+    /// Puts a trashed entry back at its original path, failing if something else was created
+    /// there in the meantime.
+    pub fn restore_entry(
+        &mut self,
+        id: TrashedEntryId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectPath>> {
+        let Some(ix) = self.trashed_entries.iter().position(|entry| entry.id == id) else {
+            return Task::ready(Err(anyhow!("no restorable entry with id {id:?}")));
+        };
+        let entry = self.trashed_entries.remove(ix);
+        let fs = self.fs.clone();
+        cx.spawn(async move |this, cx| {
+            let restore = async {
+                if fs.metadata(&entry.abs_path).await?.is_some() {
+                    bail!("{:?} already exists", entry.abs_path);
+                }
+                if let Some(parent) = entry.abs_path.parent() {
+                    fs.create_dir(parent).await?;
+                }
+                fs::copy_recursive(
+                    fs.as_ref(),
+                    &entry.stash_path,
+                    &entry.abs_path,
+                    CopyOptions::default(),
+                )
+                .await
+            };
+            if let Err(error) = restore.await {
+                this.update(cx, |this, _| {
+                    let ix = ix.min(this.trashed_entries.len());
+                    this.trashed_entries.insert(ix, entry);
+                })
+                .ok();
+                return Err(error);
+            }
+            remove_stash(fs.as_ref(), &entry).await.log_err();
+            Ok(ProjectPath {
+                worktree_id: entry.worktree_id,
+                path: entry.path,
+            })
         })
     }
 
+    /// Starts copying an entry of a local project before it's trashed, so that it can be
+    /// restored later.
+    fn stash_trashed_entry(
+        &self,
+        worktree: &Entity<Worktree>,
+        entry_id: ProjectEntryId,
+        cx: &App,
+    ) -> Option<(TrashedEntry, Task<Result<()>>)> {
+        if !self.is_local() {
+            return None;
+        }
+        let worktree = worktree.read(cx);
+        let entry = worktree.entry_for_id(entry_id)?;
+        let size = if entry.is_dir() {
+            worktree
+                .traverse_from_path(true, false, true, &entry.path)
+                .take_while(|descendant| descendant.path.starts_with(&entry.path))
+                .map(|descendant| descendant.size)
+                .sum()
+        } else {
+            entry.size
+        };
+        if size > trash::MAX_RESTORABLE_SIZE {
+            return None;
+        }
+        let id = TrashedEntryId::new();
+        let trashed_entry = TrashedEntry {
+            id,
+            worktree_id: worktree.id(),
+            path: entry.path.clone(),
+            abs_path: worktree.absolutize(&entry.path),
+            is_dir: entry.is_dir(),
+            size,
+            mtime: entry.mtime,
+            deleted_at: SystemTime::now(),
+            stash_path: trash::stash_path(id),
+        };
+        let fs = self.fs.clone();
+        let source = trashed_entry.abs_path.clone();
+        let target = trashed_entry.stash_path.clone();
+        let stash = cx.background_spawn(async move {
+            trash::remove_stale_stashes(fs.as_ref()).await.log_err();
+            if let Some(parent) = target.parent() {
+                fs.create_dir(parent).await?;
+            }
+            fs::copy_recursive(fs.as_ref(), &source, &target, CopyOptions::default()).await
+        });
+        Some((trashed_entry, stash))
+    }
+
+    fn push_trashed_entry(&mut self, entry: TrashedEntry, cx: &mut Context<Self>) {
+        self.trashed_entries.push(entry);
+        let forgotten_count = trash::entries_to_forget(&self.trashed_entries, SystemTime::now());
+        if forgotten_count > 0 {
+            let forgotten = self
+                .trashed_entries
+                .drain(..forgotten_count)
+                .collect::<Vec<_>>();
+            let fs = self.fs.clone();
+            cx.background_spawn(async move {
+                for entry in forgotten {
+                    remove_stash(fs.as_ref(), &entry).await.log_err();
+                }
+            })
+            .detach();
+        }
+    }
+
     #[inline]
     pub fn expand_entry(
         &mut self,
@@ -2508,9 +3516,11 @@ impl Project {
             return Task::ready(Err(anyhow!("Project is disconnected")));
         }
 
-        self.buffer_store.update(cx, |buffer_store, cx| {
-            buffer_store.open_buffer(path.into(), cx)
-        })
+        let path = path.into();
+        // The file is recorded in the history once its buffer is opened.
+        self.pending_file_history_opens.insert(path.clone());
+        self.buffer_store
+            .update(cx, |buffer_store, cx| buffer_store.open_buffer(path, cx))
     }
 
     #[cfg(any(test, feature = "test-support"))]
@@ -2564,38 +3574,124 @@ impl Project {
         })
     }
 
-    pub fn open_buffer_by_id(
-        &mut self,
-        id: BufferId,
-        cx: &mut Context<Self>,
-    ) -> Task<Result<Entity<Buffer>>> {
-        if let Some(buffer) = self.buffer_for_id(id, cx) {
-            Task::ready(Ok(buffer))
-        } else {
-            Task::ready(Err(anyhow!("buffer {id} does not exist")))
+    pub fn open_buffer_by_id(
+        &mut self,
+        id: BufferId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Buffer>>> {
+        if let Some(buffer) = self.buffer_for_id(id, cx) {
+            Task::ready(Ok(buffer))
+        } else {
+            Task::ready(Err(anyhow!("buffer {id} does not exist")))
+        }
+    }
+
+    pub fn save_buffers(
+        &self,
+        buffers: HashSet<Entity<Buffer>>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        cx.spawn(async move |this, cx| {
+            let save_tasks = buffers.into_iter().filter_map(|buffer| {
+                this.update(cx, |this, cx| this.save_buffer(buffer, cx))
+                    .ok()
+            });
+            try_join_all(save_tasks).await?;
+            Ok(())
+        })
+    }
+
+    pub fn save_buffer(&self, buffer: Entity<Buffer>, cx: &mut Context<Self>) -> Task<Result<()>> {
+        self.buffer_store
+            .update(cx, |buffer_store, cx| buffer_store.save_buffer(buffer, cx))
+    }
+
+    /// Saves a buffer whose file the current user isn't allowed to write, prompting them to
+    /// authenticate as an administrator.
+    pub fn save_buffer_elevated(
+        &self,
+        buffer: Entity<Buffer>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.save_buffer_elevated(buffer, cx)
+        })
+    }
+
+    /// Locks or unlocks a buffer. Locked buffers reject edits until they're unlocked.
+    ///
+    /// In local projects, the buffers of the same file are locked in the other running instances
+    /// too.
+    pub fn set_buffer_locked(
+        &self,
+        buffer: &Entity<Buffer>,
+        locked: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        buffer.update(cx, |buffer, cx| buffer.set_locked(locked, cx));
+        let abs_path = buffer
+            .read(cx)
+            .file()
+            .and_then(|file| Some(file.as_local()?.abs_path(cx)));
+        let Some(abs_path) = abs_path.filter(|_| self.is_local()) else {
+            return Task::ready(Ok(()));
+        };
+        let fs = self.fs.clone();
+        cx.background_spawn(async move {
+            buffer_locks::set_locked(fs.as_ref(), &abs_path, locked).await
+        })
+    }
+
+    fn watch_buffer_locks(fs: Arc<dyn Fs>, cx: &mut Context<Self>) -> Task<()> {
+        cx.spawn(async move |this, cx| {
+            buffer_locks::watch(fs, || {
+                this.update(cx, |this, cx| {
+                    let buffers = this.opened_buffers(cx);
+                    this.refresh_buffer_locks(buffers, cx);
+                })
+                .is_ok()
+            })
+            .await
+            .log_err();
+        })
+    }
+
+    /// Locks the buffers whose files are locked in any instance, and unlocks the others.
+    fn refresh_buffer_locks(&self, buffers: Vec<Entity<Buffer>>, cx: &mut Context<Self>) {
+        let buffers = buffers
+            .into_iter()
+            .filter_map(|buffer| {
+                let abs_path = buffer.read(cx).file()?.as_local()?.abs_path(cx);
+                Some((buffer.downgrade(), abs_path))
+            })
+            .collect::<Vec<_>>();
+        if buffers.is_empty() {
+            return;
         }
+        let fs = self.fs.clone();
+        cx.spawn(async move |_, cx| {
+            for (buffer, abs_path) in buffers {
+                let locked = buffer_locks::is_locked(fs.as_ref(), &abs_path).await;
+                buffer
+                    .update(cx, |buffer, cx| buffer.set_locked(locked, cx))
+                    .ok();
+            }
+        })
+        .detach();
     }
 
-    pub fn save_buffers(
+    /// Autosaves the buffers, skipping the ones that are excluded from autosaving or conflict
+    /// with their files.
+    pub fn autosave_buffers(
         &self,
         buffers: HashSet<Entity<Buffer>>,
         cx: &mut Context<Self>,
     ) -> Task<Result<()>> {
-        cx.spawn(async move |this, cx| {
-            let save_tasks = buffers.into_iter().filter_map(|buffer| {
-                this.update(cx, |this, cx| this.save_buffer(buffer, cx))
-                    .ok()
-            });
-            try_join_all(save_tasks).await?;
-            Ok(())
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.autosave_buffers(buffers, cx)
         })
     }
 
-    pub fn save_buffer(&self, buffer: Entity<Buffer>, cx: &mut Context<Self>) -> Task<Result<()>> {
-        self.buffer_store
-            .update(cx, |buffer_store, cx| buffer_store.save_buffer(buffer, cx))
-    }
-
     pub fn save_buffer_as(
         &mut self,
         buffer: Entity<Buffer>,
@@ -2622,6 +3718,34 @@ impl Project {
         Ok(())
     }
 
+    /// Opens a file too large to be loaded into a buffer. Large files are read-only, and
+    /// aren't registered with language servers.
+    pub fn open_large_file(
+        &mut self,
+        path: impl Into<ProjectPath>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<LargeFile>>> {
+        if self.is_disconnected(cx) {
+            return Task::ready(Err(anyhow!("Project is disconnected")));
+        }
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.open_large_file(path.into(), cx)
+        })
+    }
+
+    pub fn open_hex_buffer(
+        &mut self,
+        path: impl Into<ProjectPath>,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<HexBuffer>>> {
+        if self.is_disconnected(cx) {
+            return Task::ready(Err(anyhow!("Project is disconnected")));
+        }
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.open_hex_buffer(path.into(), cx)
+        })
+    }
+
     pub fn open_image(
         &mut self,
         path: impl Into<ProjectPath>,
@@ -2779,6 +3903,48 @@ impl Project {
         match event {
             BufferStoreEvent::BufferAdded(buffer) => {
                 self.register_buffer(buffer, cx).log_err();
+                if self.is_local() {
+                    self.refresh_buffer_locks(vec![buffer.clone()], cx);
+                }
+            }
+            BufferStoreEvent::BufferOpened {
+                buffer,
+                project_path,
+            } => {
+                let recorded = self.pending_file_history_opens.remove(project_path);
+                let Some(abs_path) = self.absolute_path(project_path, cx) else {
+                    return;
+                };
+                if recorded {
+                    self.file_history
+                        .record_open(abs_path.clone(), unix_secs(SystemTime::now()));
+                    self.save_file_history(cx);
+                }
+                if self.file_history.visits(&abs_path).is_some() {
+                    self.file_history_buffers
+                        .insert(buffer.read(cx).remote_id(), abs_path);
+                }
+            }
+            BufferStoreEvent::BufferDropped(buffer_id) => {
+                if let Some(abs_path) = self.file_history_buffers.remove(buffer_id)
+                    && self
+                        .file_history
+                        .record_close(&abs_path, unix_secs(SystemTime::now()))
+                {
+                    self.save_file_history(cx);
+                }
+            }
+            BufferStoreEvent::AutosaveSkipped(buffer) => {
+                cx.emit(Event::AutosaveSkipped(buffer.clone()));
+            }
+            BufferStoreEvent::BufferEvicted {
+                buffer_id,
+                project_path,
+            } => {
+                cx.emit(Event::BufferEvicted {
+                    buffer_id: *buffer_id,
+                    project_path: project_path.clone(),
+                });
             }
             _ => {}
         }
@@ -2814,6 +3980,20 @@ impl Project {
         }
     }
 
+    fn on_unsaved_journal_event(
+        &mut self,
+        unsaved_journal: Entity<UnsavedJournal>,
+        event: &UnsavedJournalEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            UnsavedJournalEvent::RecoverableBuffersFound => {
+                let count = unsaved_journal.read(cx).recoverable_buffers().len();
+                cx.emit(Event::UnsavedBuffersRecoverable(count));
+            }
+        }
+    }
+
     fn on_lsp_store_event(
         &mut self,
         _: Entity<LspStore>,
@@ -3028,6 +4208,7 @@ impl Project {
                 cx.emit(Event::WorktreeAdded(worktree.read(cx).id()));
             }
             WorktreeStoreEvent::WorktreeRemoved(_, id) => {
+                self.update_search_history_key(cx);
                 cx.emit(Event::WorktreeRemoved(*id));
             }
             WorktreeStoreEvent::WorktreeReleased(_, id) => {
@@ -3043,10 +4224,20 @@ impl Project {
             }
             // Listen to the GitStore instead.
             WorktreeStoreEvent::WorktreeUpdatedGitRepositories(_, _) => {}
+            WorktreeStoreEvent::WorktreeScanProgress(_, _) => {}
+            WorktreeStoreEvent::WorktreeSymlinkEscapedRoot(worktree_id, path, target) => {
+                cx.emit(Event::SymlinkEscapedWorktree {
+                    worktree_id: *worktree_id,
+                    path: path.clone(),
+                    target: target.clone(),
+                })
+            }
         }
     }
 
-    fn on_worktree_added(&mut self, _worktree: &Entity<Worktree>, _: &mut Context<Self>) {}
+    fn on_worktree_added(&mut self, _worktree: &Entity<Worktree>, cx: &mut Context<Self>) {
+        self.update_search_history_key(cx);
+    }
 
     fn on_worktree_released(&mut self, _id_to_remove: WorktreeId, _cx: &mut Context<Self>) {}
 
@@ -3065,6 +4256,31 @@ impl Project {
                 self.reload_buffers([buffer.clone()].into_iter().collect(), true, cx)
                     .detach_and_log_err(cx);
             }
+            BufferEvent::MergeNeeded => {
+                let merge = buffer.update(cx, |buffer, cx| buffer.merge_with_disk(cx));
+                let path = buffer.read(cx).file().map(|file| file.full_path(cx));
+                cx.background_spawn(async move {
+                    merge
+                        .await
+                        .with_context(|| format!("merging the changes on disk into {path:?}"))
+                        .log_err();
+                })
+                .detach();
+            }
+            BufferEvent::Saved => {
+                // Autosaves happen all the time while editing, so only explicit saves run tasks
+                // or restart debug sessions.
+                let is_autosave = self
+                    .buffer_store
+                    .read(cx)
+                    .is_autosaving(buffer.read(cx).remote_id());
+                if !is_autosave {
+                    self.dap_store
+                        .update(cx, |dap_store, cx| dap_store.buffer_saved(&buffer, cx));
+                    self.task_store
+                        .update(cx, |task_store, cx| task_store.buffer_saved(&buffer, cx));
+                }
+            }
 
             _ => {}
         }
@@ -3347,6 +4563,78 @@ impl Project {
         })
     }
 
+    /// Converts the line endings and final newlines of the files at the given paths, including
+    /// the files within directories among them.
+    pub fn normalize_line_endings(
+        &mut self,
+        paths: impl IntoIterator<Item = ProjectPath>,
+        normalization: LineEndingNormalization,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectTransaction>> {
+        let mut file_paths = HashSet::default();
+        for path in paths {
+            let Some(worktree) = self.worktree_for_id(path.worktree_id, cx) else {
+                continue;
+            };
+            let snapshot = worktree.read(cx).snapshot();
+            match snapshot.entry_for_path(&path.path) {
+                Some(entry) if entry.is_dir() => {
+                    file_paths.extend(
+                        snapshot
+                            .files(false, 0)
+                            .filter(|entry| entry.path.starts_with(&path.path))
+                            .map(|entry| ProjectPath {
+                                worktree_id: path.worktree_id,
+                                path: entry.path.clone(),
+                            }),
+                    );
+                }
+                Some(_) => {
+                    file_paths.insert(path);
+                }
+                None => {}
+            }
+        }
+        let file_paths = file_paths.into_iter().collect::<Vec<_>>();
+        cx.spawn(async move |this, cx| {
+            let mut project_transaction = ProjectTransaction::default();
+            for batch in file_paths.chunks(NORMALIZE_LINE_ENDINGS_BATCH_SIZE) {
+                let open_buffers = this.update(cx, |this, cx| {
+                    batch
+                        .iter()
+                        .map(|path| this.open_buffer(path.clone(), cx))
+                        .collect::<Vec<_>>()
+                })?;
+                // Files that can't be opened as buffers, such as binary ones, are skipped.
+                let buffers = futures::future::join_all(open_buffers)
+                    .await
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .collect();
+                let batch_transaction = this
+                    .update(cx, |this, cx| {
+                        this.buffer_store.update(cx, |buffer_store, cx| {
+                            buffer_store.normalize_line_endings(buffers, normalization, cx)
+                        })
+                    })?
+                    .await?;
+                project_transaction.0.extend(batch_transaction.0);
+            }
+            Ok(project_transaction)
+        })
+    }
+
+    pub fn reopen_buffer_with_encoding(
+        &self,
+        buffer: Entity<Buffer>,
+        encoding: &'static encoding_rs::Encoding,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.reopen_with_encoding(buffer, encoding, cx)
+        })
+    }
+
     pub fn reload_images(
         &self,
         images: HashSet<Entity<ImageItem>>,
@@ -3686,14 +4974,21 @@ impl Project {
     ) -> Task<anyhow::Result<Vec<InlayHint>>> {
         let snapshot = buffer_handle.read(cx).snapshot();
 
-        let captures =
-            snapshot.debug_variables_query(Anchor::min_for_buffer(snapshot.remote_id())..range.end);
-
         let row = snapshot
             .summary_for_anchor::<text::PointUtf16>(&range.end)
             .row as usize;
 
-        let inline_value_locations = provide_inline_values(captures, &snapshot, row);
+        let provider = snapshot
+            .language()
+            .and_then(|language| DapRegistry::global(cx).inline_value_provider(&language.name()));
+        let inline_value_locations = match provider {
+            Some(provider) => provider.provide(&snapshot, row),
+            None => {
+                let captures = snapshot
+                    .debug_variables_query(Anchor::min_for_buffer(snapshot.remote_id())..range.end);
+                inline_value_locations(captures, &snapshot, row)
+            }
+        };
 
         let stack_frame_id = active_stack_frame.stack_frame_id;
         cx.spawn(async move |this, cx| {
@@ -3717,18 +5012,25 @@ impl Project {
             project_search::Search::open_buffers_only(
                 self.buffer_store.clone(),
                 self.worktree_store.clone(),
-                project_search::Search::MAX_SEARCH_RESULT_FILES + 1,
+                query.limits().max_files + 1,
             )
         } else {
             project_search::Search::local(
-                self.fs.clone(),
                 self.buffer_store.clone(),
                 self.worktree_store.clone(),
-                project_search::Search::MAX_SEARCH_RESULT_FILES + 1,
+                self.search_index
+                    .as_ref()
+                    .and_then(|search_index| search_index.read(cx).index()),
+                query.limits().max_files + 1,
                 cx,
             )
         };
-        searcher.into_handle(query, cx)
+        let active_path = self
+            .active_entry
+            .and_then(|entry_id| self.path_for_entry(entry_id, cx));
+        searcher
+            .with_active_path(active_path)
+            .into_handle(query, cx)
     }
 
     pub fn search(
@@ -3739,6 +5041,72 @@ impl Project {
         self.search_impl(query, cx).results(cx)
     }
 
+    /// Replaces all matches of the query with its replacement, in both open buffers and files
+    /// that haven't been opened yet. The edited buffers are left unsaved.
+    pub fn replace_all(
+        &mut self,
+        query: SearchQuery,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<ProjectReplace>> {
+        if query.replacement().is_none() {
+            return Task::ready(Err(anyhow!("search query has no replacement")));
+        }
+        let results = self.search(query.clone(), cx);
+        cx.spawn(async move |_, cx| {
+            let mut matches = Vec::new();
+            let mut replace = ProjectReplace::default();
+            while let Ok(result) = results.rx.recv().await {
+                match result {
+                    SearchResult::Buffer { buffer, ranges, .. } => matches.push((buffer, ranges)),
+                    SearchResult::LimitReached { .. } => replace.summary.limit_reached = true,
+                    // Replacing only some of the matches would be worse than replacing none.
+                    SearchResult::BudgetExceeded { path, error } => {
+                        return Err(anyhow::Error::new(error).context(match path {
+                            Some(path) => format!("failed to search {:?}", path.path),
+                            None => "failed to search an open buffer".to_string(),
+                        }));
+                    }
+                }
+            }
+
+            for (buffer, ranges) in matches {
+                let edits = buffer.read_with(cx, |buffer, _| {
+                    project_replace::replacement_edits(&query, &buffer.snapshot(), &ranges)
+                })?;
+                if edits.is_empty() {
+                    continue;
+                }
+                let edit_count = edits.len();
+                let transaction = buffer.update(cx, |buffer, cx| {
+                    buffer.finalize_last_transaction();
+                    buffer.start_transaction();
+                    buffer.edit(edits, None, cx);
+                    buffer.end_transaction(cx)?;
+                    buffer.finalize_last_transaction().cloned()
+                })?;
+                if let Some(transaction) = transaction {
+                    replace.summary.files_changed += 1;
+                    replace.summary.replacements += edit_count;
+                    replace.transaction.0.insert(buffer, transaction);
+                }
+            }
+            Ok(replace)
+        })
+    }
+
+    /// Reverts the edits of a project transaction, e.g. one produced by [`Self::replace_all`].
+    pub fn undo_project_transaction(
+        &mut self,
+        transaction: &ProjectTransaction,
+        cx: &mut Context<Self>,
+    ) {
+        for (buffer, transaction) in &transaction.0 {
+            buffer.update(cx, |buffer, cx| {
+                buffer.undo_transaction(transaction.id, cx);
+            });
+        }
+    }
+
     pub fn request_lsp<R: LspCommand>(
         &mut self,
         buffer_handle: Entity<Buffer>,
@@ -3805,11 +5173,46 @@ impl Project {
     }
 
     /// Returns the resolved version of `path`, that was found in `buffer`, if it exists.
+    ///
+    /// `file://` URLs resolve to the paths they point to, and `path:row:column` or
+    /// `path(row,column)` suffixes are returned as the position within the resolved file.
     pub fn resolve_path_in_buffer(
         &self,
         path: &str,
         buffer: &Entity<Buffer>,
         cx: &mut Context<Self>,
+    ) -> Task<Option<ResolvedPathWithPosition>> {
+        let path = file_url_path(path).unwrap_or_else(|| path.to_string());
+        let with_position = PathWithPosition::parse_str(&path);
+        let stripped_path = with_position.path.to_string_lossy().into_owned();
+        let resolve_stripped = (stripped_path != path)
+            .then(|| self.resolve_path_without_position(&stripped_path, buffer, cx));
+        let buffer = buffer.clone();
+        cx.spawn(async move |this, cx| {
+            if let Some(resolve_stripped) = resolve_stripped
+                && let Some(resolved) = resolve_stripped.await
+            {
+                return Some(ResolvedPathWithPosition {
+                    path: resolved,
+                    row: with_position.row,
+                    column: with_position.column,
+                });
+            }
+            // The suffix may be part of the file name after all.
+            this.update(cx, |this, cx| {
+                this.resolve_path_without_position(&path, &buffer, cx)
+            })
+            .ok()?
+            .await
+            .map(ResolvedPathWithPosition::from)
+        })
+    }
+
+    fn resolve_path_without_position(
+        &self,
+        path: &str,
+        buffer: &Entity<Buffer>,
+        cx: &mut Context<Self>,
     ) -> Task<Option<ResolvedPath>> {
         if util::paths::is_absolute(path, self.path_style(cx)) || path.starts_with("~") {
             self.resolve_abs_path(path, cx)
@@ -3940,6 +5343,28 @@ impl Project {
         })
     }
 
+    pub fn create_scratch_worktree(
+        &mut self,
+        name: &str,
+        visible: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Worktree>>> {
+        self.worktree_store.update(cx, |worktree_store, cx| {
+            worktree_store.create_scratch_worktree(name, visible, cx)
+        })
+    }
+
+    pub fn materialize_scratch_worktree(
+        &mut self,
+        worktree_id: WorktreeId,
+        destination: PathBuf,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.worktree_store.update(cx, |worktree_store, cx| {
+            worktree_store.materialize_scratch_worktree(worktree_id, destination, cx)
+        })
+    }
+
     pub fn remove_worktree(&mut self, id_to_remove: WorktreeId, cx: &mut Context<Self>) {
         self.worktree_store.update(cx, |worktree_store, cx| {
             worktree_store.remove_worktree(id_to_remove, cx);
@@ -3961,6 +5386,18 @@ impl Project {
         }
     }
 
+    /// Sets the paths of the items visible in the workspace, whose buffers are never evicted.
+    pub fn set_visible_paths(
+        &mut self,
+        paths: impl IntoIterator<Item = ProjectPath>,
+        cx: &mut Context<Self>,
+    ) {
+        let paths = paths.into_iter().collect();
+        self.buffer_store.update(cx, |buffer_store, cx| {
+            buffer_store.set_visible_paths(paths, cx)
+        });
+    }
+
     pub fn language_servers_running_disk_based_diagnostics<'a>(
         &'a self,
         cx: &'a App,
@@ -4023,7 +5460,10 @@ impl Project {
     /// the first visible worktree that has an entry for that relative path.
     ///
     /// We use this to resolve edit steps, when there's a chance an LLM may omit the workree
-    /// root name from paths.
+    /// root name from paths. Paths that don't exist are matched case-insensitively on
+    /// case-insensitive file systems, as paths from LLMs and terminals often differ in case.
+    /// For paths to read, [`Self::find_existing_project_path`] also matches paths that omit
+    /// directories.
     ///
     /// # Arguments
     ///
@@ -4046,6 +5486,16 @@ impl Project {
                 if let Ok(relative_path) = path.strip_prefix(worktree_abs_path)
                     && let Ok(path) = RelPath::new(relative_path, path_style)
                 {
+                    if worktree.read(cx).entry_for_path(&path).is_none()
+                        && let Some(project_path) = self.find_project_path_fuzzy(
+                            &path,
+                            iter::once(worktree.clone()),
+                            true,
+                            cx,
+                        )
+                    {
+                        return Some(project_path);
+                    }
                     return Some(ProjectPath {
                         worktree_id: worktree.read(cx).id(),
                         path: path.into_arc(),
@@ -4076,11 +5526,105 @@ impl Project {
                     });
                 }
             }
+
+            if let Ok(path) = RelPath::new(path, path_style) {
+                return self.find_project_path_fuzzy(
+                    &path,
+                    worktree_store.visible_worktrees(cx),
+                    true,
+                    cx,
+                );
+            }
         }
 
         None
     }
 
+    /// Like [`Self::find_project_path`], but only finds existing entries, and falls back to the
+    /// entry sharing the longest suffix with a relative path, as long as it's the only one and
+    /// the suffix is longer than the file name, as paths from LLMs and terminals often omit
+    /// directories.
+    ///
+    /// This is only for paths to read: a path to create or write may resolve to another file.
+    pub fn find_existing_project_path(
+        &self,
+        path: impl AsRef<Path>,
+        cx: &App,
+    ) -> Option<ProjectPath> {
+        let path = path.as_ref();
+        if let Some(project_path) = self.find_project_path(path, cx)
+            && self.entry_for_path(&project_path, cx).is_some()
+        {
+            return Some(project_path);
+        }
+        let path_style = self.path_style(cx);
+        if is_absolute(&path.to_string_lossy(), path_style) {
+            return None;
+        }
+        let path = RelPath::new(path, path_style).ok()?;
+        self.find_project_path_fuzzy(&path, self.visible_worktrees(cx), false, cx)
+    }
+
+    /// Matches a relative path against the entries of the given worktrees, ignoring case on
+    /// case-insensitive file systems. Unless `exact` is set, a path that omits leading
+    /// directories matches too, as long as a single entry shares the longest suffix with it,
+    /// and that suffix has more components than the file name.
+    fn find_project_path_fuzzy(
+        &self,
+        path: &RelPath,
+        worktrees: impl Iterator<Item = Entity<Worktree>>,
+        exact: bool,
+        cx: &App,
+    ) -> Option<ProjectPath> {
+        let components = path.components().collect::<Vec<_>>();
+        // The number of trailing components of the closest matches, along with the match if
+        // it's the only one.
+        let mut closest_match: Option<(usize, Option<ProjectPath>)> = None;
+        for worktree in worktrees {
+            let worktree = worktree.read(cx);
+            let ignore_case = worktree
+                .as_local()
+                .is_some_and(|worktree| !worktree.fs_is_case_sensitive());
+            if exact && !ignore_case {
+                continue;
+            }
+            for entry in worktree.entries(false, 0) {
+                let matched = entry
+                    .path
+                    .components()
+                    .rev()
+                    .zip(components.iter().rev())
+                    .take_while(|(component, query)| {
+                        if ignore_case {
+                            component.to_lowercase() == query.to_lowercase()
+                        } else {
+                            component == *query
+                        }
+                    })
+                    .count();
+                if matched == 0 {
+                    continue;
+                }
+                let project_path = ProjectPath {
+                    worktree_id: worktree.id(),
+                    path: entry.path.clone(),
+                };
+                if matched == components.len() && entry.path.len() == components.len() {
+                    return Some(project_path);
+                }
+                if exact || matched < 2 {
+                    continue;
+                }
+                match &mut closest_match {
+                    Some((closest, unique)) if *closest == matched => *unique = None,
+                    Some((closest, _)) if *closest > matched => {}
+                    _ => closest_match = Some((matched, Some(project_path))),
+                }
+            }
+        }
+        closest_match?.1
+    }
+
     /// If there's only one visible worktree, returns the given worktree-relative path with no prefix.
     ///
     /// Otherwise, returns the full path for the project path (obtained by prefixing the worktree-relative path with the name of the worktree).
@@ -5101,6 +6645,43 @@ pub enum ResolvedPath {
     },
 }
 
+/// A resolved path, along with the 1-based row and column that followed it in the text it was
+/// resolved from.
+#[derive(Debug, Clone)]
+pub struct ResolvedPathWithPosition {
+    pub path: ResolvedPath,
+    pub row: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl ResolvedPathWithPosition {
+    /// Returns the zero-based point of the position, if there is one.
+    pub fn point(&self) -> Option<Point> {
+        let row = self.row?.saturating_sub(1);
+        let column = self.column.map_or(0, |column| column.saturating_sub(1));
+        Some(Point::new(row, column))
+    }
+}
+
+impl From<ResolvedPath> for ResolvedPathWithPosition {
+    fn from(path: ResolvedPath) -> Self {
+        Self {
+            path,
+            row: None,
+            column: None,
+        }
+    }
+}
+
+/// Returns the path of a `file://` URL.
+fn file_url_path(url: &str) -> Option<String> {
+    let url = url::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "file")?;
+    let path = url.to_file_path().ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
 impl ResolvedPath {
     pub fn abs_path(&self) -> Option<&str> {
         match self {
@@ -5141,6 +6722,13 @@ impl ProjectItem for Buffer {
         path: &ProjectPath,
         cx: &mut App,
     ) -> Option<Task<Result<Entity<Self>>>> {
+        // Files too large to be loaded into buffers are opened as `LargeFile`s, and binary files
+        // as `HexBuffer`s.
+        if large_file::opens_as_large_file(project.read(cx), path, cx)
+            || hex_buffer::opens_as_hex_buffer(project.read(cx), path, cx)
+        {
+            return None;
+        }
         Some(project.update(cx, |project, cx| project.open_buffer(path.clone(), cx)))
     }
 
@@ -5234,71 +6822,16 @@ fn proto_to_prompt(level: proto::language_server_prompt_request::Level) -> gpui:
     }
 }
 
-fn provide_inline_values(
-    captures: impl Iterator<Item = (Range<usize>, language::DebuggerTextObject)>,
-    snapshot: &language::BufferSnapshot,
-    max_row: usize,
-) -> Vec<InlineValueLocation> {
-    let mut variables = Vec::new();
-    let mut variable_position = HashSet::default();
-    let mut scopes = Vec::new();
-
-    let active_debug_line_offset = snapshot.point_to_offset(Point::new(max_row as u32, 0));
-
-    for (capture_range, capture_kind) in captures {
-        match capture_kind {
-            language::DebuggerTextObject::Variable => {
-                let variable_name = snapshot
-                    .text_for_range(capture_range.clone())
-                    .collect::<String>();
-                let point = snapshot.offset_to_point(capture_range.end);
-
-                while scopes
-                    .last()
-                    .is_some_and(|scope: &Range<_>| !scope.contains(&capture_range.start))
-                {
-                    scopes.pop();
-                }
-
-                if point.row as usize > max_row {
-                    break;
-                }
-
-                let scope = if scopes
-                    .last()
-                    .is_none_or(|scope| !scope.contains(&active_debug_line_offset))
-                {
-                    VariableScope::Global
-                } else {
-                    VariableScope::Local
-                };
-
-                if variable_position.insert(capture_range.end) {
-                    variables.push(InlineValueLocation {
-                        variable_name,
-                        scope,
-                        lookup: VariableLookupKind::Variable,
-                        row: point.row as usize,
-                        column: point.column as usize,
-                    });
-                }
-            }
-            language::DebuggerTextObject::Scope => {
-                while scopes.last().map_or_else(
-                    || false,
-                    |scope: &Range<usize>| {
-                        !(scope.contains(&capture_range.start)
-                            && scope.contains(&capture_range.end))
-                    },
-                ) {
-                    scopes.pop();
-                }
-                scopes.push(capture_range);
-            }
-        }
+async fn remove_stash(fs: &dyn Fs, entry: &TrashedEntry) -> Result<()> {
+    let options = RemoveOptions {
+        recursive: true,
+        ignore_if_not_exists: true,
+    };
+    if entry.is_dir {
+        fs.remove_dir(&entry.stash_path, options).await
+    } else {
+        fs.remove_file(&entry.stash_path, options).await
     }
-
-    variables
 }
 
 #[cfg(test)]
@@ -0,0 +1,117 @@
+//! A shareable definition of a multi-root project, stored as a JSON file that can be opened to
+//! recreate the project's worktrees and their configuration.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use util::paths::normalize_lexically;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectDescriptor {
+    pub worktrees: Vec<WorktreeDescriptor>,
+    /// Environment variables set for the processes started by the project, overriding the ones
+    /// of the shell environment.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorktreeDescriptor {
+    /// The root of the worktree, relative to the directory of the descriptor file when it's
+    /// within that directory.
+    pub path: PathBuf,
+    /// Globs of the paths excluded from the worktree, replacing the `file_scan_exclusions`
+    /// setting for it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toolchains: Vec<ToolchainDescriptor>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolchainDescriptor {
+    pub language: String,
+    /// The directory within the worktree that the toolchain is active for, empty for the root.
+    #[serde(default)]
+    pub directory: String,
+    pub name: String,
+    /// The path of the toolchain.
+    pub path: String,
+    /// The language-specific details of the toolchain.
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+impl ProjectDescriptor {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("invalid project descriptor")
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Returns the path relative to the given directory if it's within it, and the path itself
+/// otherwise.
+pub(crate) fn relativize(abs_path: &Path, dir: &Path) -> PathBuf {
+    match abs_path.strip_prefix(dir) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => abs_path.to_path_buf(),
+    }
+}
+
+/// Resolves a path of a descriptor against the directory of the descriptor file.
+pub(crate) fn absolutize(path: &Path, dir: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    normalize_lexically(&dir.join(path))
+        .with_context(|| format!("invalid worktree path {path:?} in project descriptor"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::path;
+
+    #[test]
+    fn test_descriptor_paths() {
+        let dir = Path::new(path!("/projects/app"));
+        for abs_path in [
+            path!("/projects/app"),
+            path!("/projects/app/crates/core"),
+            path!("/other/lib"),
+        ] {
+            let relative = relativize(Path::new(abs_path), dir);
+            assert_eq!(absolutize(&relative, dir).unwrap(), Path::new(abs_path));
+        }
+        assert_eq!(
+            relativize(Path::new(path!("/projects/app/crates/core")), dir),
+            Path::new("crates").join("core")
+        );
+        assert_eq!(
+            absolutize(Path::new("../lib"), dir).unwrap(),
+            Path::new(path!("/projects/lib"))
+        );
+
+        let descriptor = ProjectDescriptor::from_json(
+            r#"{
+                "worktrees": [{ "path": ".", "excluded_paths": ["target"] }],
+                "environment": { "RUST_LOG": "info" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(descriptor.worktrees[0].excluded_paths, ["target"]);
+        assert_eq!(
+            ProjectDescriptor::from_json(&descriptor.to_json().unwrap()).unwrap(),
+            descriptor
+        );
+        assert!(ProjectDescriptor::from_json(r#"{ "worktrees": 1 }"#).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Project-wide replacement of search matches.
+
+use std::ops::Range;
+
+use language::BufferSnapshot;
+use text::Anchor;
+
+use crate::{ProjectTransaction, search::SearchQuery};
+
+/// The result of [`crate::Project::replace_all`].
+///
+/// The edits are applied to the matched buffers without saving them, so that the transaction
+/// can be previewed before saving it, or reverted with
+/// [`crate::Project::undo_project_transaction`].
+#[derive(Debug, Default, Clone)]
+pub struct ProjectReplace {
+    pub transaction: ProjectTransaction,
+    pub summary: ReplaceSummary,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReplaceSummary {
+    pub files_changed: usize,
+    pub replacements: usize,
+    /// Whether the search stopped before visiting all files because too many of them matched,
+    /// leaving the remaining matches unreplaced.
+    pub limit_reached: bool,
+}
+
+/// Computes the edits replacing each of the given matches within the buffer, expanding regex
+/// capture groups in the query's replacement. Matches that would be left unchanged are skipped.
+pub(crate) fn replacement_edits(
+    query: &SearchQuery,
+    snapshot: &BufferSnapshot,
+    ranges: &[Range<Anchor>],
+) -> Vec<(Range<Anchor>, String)> {
+    ranges
+        .iter()
+        .filter_map(|range| {
+            let text = snapshot.text_for_range(range.clone()).collect::<String>();
+            let replacement = query.replacement_for(&text)?;
+            (replacement != text).then(|| (range.clone(), replacement.into_owned()))
+        })
+        .collect()
+}
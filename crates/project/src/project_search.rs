@@ -2,10 +2,12 @@ use std::{
     cell::LazyCell,
     collections::BTreeSet,
     io::{BufRead, BufReader},
-    ops::Range,
     path::{Path, PathBuf},
     pin::pin,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
 
 use anyhow::Context;
@@ -13,7 +15,7 @@ use collections::HashSet;
 use fs::Fs;
 use futures::{SinkExt, StreamExt, select_biased, stream::FuturesOrdered};
 use gpui::{App, AppContext, AsyncApp, Entity, Task};
-use language::{Buffer, BufferSnapshot};
+use language::{Buffer, BufferSnapshot, Point};
 use postage::oneshot;
 use smol::{
     channel::{Receiver, Sender, bounded, unbounded},
@@ -26,7 +28,8 @@ use worktree::{Entry, ProjectEntryId, Snapshot, Worktree, WorktreeSettings};
 use crate::{
     ProjectItem, ProjectPath,
     buffer_store::BufferStore,
-    search::{SearchQuery, SearchResult},
+    search::{RegexBudgetError, SearchQuery, SearchResult, relevance_score},
+    search_index::{self, TrigramIndex},
     worktree_store::WorktreeStore,
 };
 
@@ -35,14 +38,16 @@ pub struct Search {
     worktree_store: Entity<WorktreeStore>,
     limit: usize,
     kind: SearchKind,
+    /// The path of the file the user is working in, which results are scored by proximity to.
+    active_path: Option<ProjectPath>,
 }
 
 /// Represents search setup, before it is actually kicked off with Search::into_results
 enum SearchKind {
     /// Search for candidates by inspecting file contents on file system, avoiding loading the buffer unless we know that a given file contains a match.
     Local {
-        fs: Arc<dyn Fs>,
         worktrees: Vec<Entity<Worktree>>,
+        search_index: Option<TrigramIndex>,
     },
     /// Run search against a known set of candidates. Even when working with a remote host, this won't round-trip to host.
     OpenBuffersOnly,
@@ -80,7 +85,6 @@ impl SearchResultsHandle {
 #[derive(Clone)]
 enum FindSearchCandidates {
     Local {
-        fs: Arc<dyn Fs>,
         /// Start off with all paths in project and filter them based on:
         /// - Include filters
         /// - Exclude filters
@@ -92,24 +96,37 @@ enum FindSearchCandidates {
         /// based on disk contents of a buffer. This step is not performed for buffers we already have in memory.
         confirm_contents_will_match_tx: Sender<MatchingEntry>,
         confirm_contents_will_match_rx: Receiver<MatchingEntry>,
+        index_filter: Option<IndexFilter>,
     },
     OpenBuffersOnly,
 }
 
+/// Rules out files without reading them, when the search index knows that they don't contain
+/// all trigrams of the query.
+#[derive(Clone)]
+struct IndexFilter {
+    index: TrigramIndex,
+    trigrams: Arc<[u32]>,
+}
+
 impl Search {
     pub fn local(
-        fs: Arc<dyn Fs>,
         buffer_store: Entity<BufferStore>,
         worktree_store: Entity<WorktreeStore>,
+        search_index: Option<TrigramIndex>,
         limit: usize,
         cx: &mut App,
     ) -> Self {
         let worktrees = worktree_store.read(cx).visible_worktrees(cx).collect();
         Self {
-            kind: SearchKind::Local { fs, worktrees },
+            kind: SearchKind::Local {
+                worktrees,
+                search_index,
+            },
             buffer_store,
             worktree_store,
             limit,
+            active_path: None,
         }
     }
 
@@ -123,11 +140,15 @@ impl Search {
             buffer_store,
             worktree_store,
             limit,
+            active_path: None,
         }
     }
 
-    pub(crate) const MAX_SEARCH_RESULT_FILES: usize = 5_000;
-    pub(crate) const MAX_SEARCH_RESULT_RANGES: usize = 10_000;
+    pub fn with_active_path(mut self, active_path: Option<ProjectPath>) -> Self {
+        self.active_path = active_path;
+        self
+    }
+
     /// Prepares a project search run. The resulting [`SearchResultsHandle`] has to be used to specify whether you're interested in matching buffers
     /// or full search results.
     pub fn into_handle(mut self, query: SearchQuery, cx: &mut App) -> SearchResultsHandle {
@@ -147,6 +168,7 @@ impl Search {
             };
         }
         let open_buffers = Arc::new(open_buffers);
+        let unnamed_buffer_count = unnamed_buffers.len();
         let executor = cx.background_executor().clone();
         let (tx, rx) = unbounded();
         let (grab_buffer_snapshot_tx, grab_buffer_snapshot_rx) = unbounded();
@@ -160,6 +182,9 @@ impl Search {
                 let (find_all_matches_tx, find_all_matches_rx) =
                     bounded(MAX_CONCURRENT_BUFFER_OPENS);
                 let query = Arc::new(query);
+                let (mut total_files_tx, total_files_rx) = oneshot::channel();
+                // The number of files that were ruled out before being opened.
+                let rejected_files = Arc::new(AtomicUsize::new(0));
                 let (candidate_searcher, tasks) = match self.kind {
                     SearchKind::OpenBuffersOnly => {
                         let Ok(open_buffers) = cx.update(|cx| self.all_loaded_buffers(&query, cx))
@@ -168,6 +193,7 @@ impl Search {
                         };
                         let fill_requests = cx
                             .background_spawn(async move {
+                                _ = total_files_tx.send(open_buffers.len()).await;
                                 for buffer in open_buffers {
                                     if let Err(_) = grab_buffer_snapshot_tx.send(buffer).await {
                                         return;
@@ -178,9 +204,16 @@ impl Search {
                         (FindSearchCandidates::OpenBuffersOnly, vec![fill_requests])
                     }
                     SearchKind::Local {
-                        fs,
                         ref mut worktrees,
+                        ref mut search_index,
                     } => {
+                        let index_filter = search_index
+                            .take()
+                            .zip(search_index::query_trigrams(&query))
+                            .map(|(index, trigrams)| IndexFilter {
+                                index,
+                                trigrams: trigrams.into(),
+                            });
                         let (get_buffer_for_full_scan_tx, get_buffer_for_full_scan_rx) =
                             unbounded();
                         let (confirm_contents_will_match_tx, confirm_contents_will_match_rx) =
@@ -194,6 +227,7 @@ impl Search {
                                 query.clone(),
                                 input_paths_tx,
                                 sorted_search_results_tx,
+                                total_files_tx,
                             ))
                             .boxed_local(),
                             Self::open_buffers(
@@ -206,16 +240,17 @@ impl Search {
                             cx.background_spawn(Self::maintain_sorted_search_results(
                                 sorted_search_results_rx,
                                 get_buffer_for_full_scan_tx,
+                                rejected_files.clone(),
                                 self.limit,
                             ))
                             .boxed_local(),
                         ];
                         (
                             FindSearchCandidates::Local {
-                                fs,
                                 confirm_contents_will_match_tx,
                                 confirm_contents_will_match_rx,
                                 input_paths_rx,
+                                index_filter,
                             },
                             tasks,
                         )
@@ -225,6 +260,7 @@ impl Search {
                 let should_find_all_matches = !tx.is_closed();
 
                 let _executor = executor.clone();
+                let active_path = self.active_path.clone();
                 let worker_pool = executor.spawn(async move {
                     let num_cpus = _executor.num_cpus();
 
@@ -234,6 +270,7 @@ impl Search {
                             for _ in 0..num_cpus - 1 {
                                 let worker = Worker {
                                     query: query.clone(),
+                                    active_path: active_path.clone(),
                                     open_buffers: open_buffers.clone(),
                                     candidates: candidate_searcher.clone(),
                                     find_all_matches_rx: find_all_matches_rx.clone(),
@@ -260,6 +297,7 @@ impl Search {
                             grab_buffer_snapshot_rx,
                             find_all_matches_tx,
                             sorted_matches_tx,
+                            query.is_structural(),
                             cx.clone(),
                         )
                         .boxed_local(),
@@ -271,8 +309,15 @@ impl Search {
                 };
                 let ensure_matches_are_reported_in_order = if should_find_all_matches {
                     Some(
-                        Self::ensure_matched_ranges_are_reported_in_order(sorted_matches_rx, tx)
-                            .boxed_local(),
+                        Self::ensure_matched_ranges_are_reported_in_order(
+                            sorted_matches_rx,
+                            tx,
+                            query.clone(),
+                            unnamed_buffer_count,
+                            total_files_rx,
+                            rejected_files,
+                        )
+                        .boxed_local(),
                     )
                 } else {
                     drop(tx);
@@ -302,15 +347,19 @@ impl Search {
         query: Arc<SearchQuery>,
         tx: Sender<InputPath>,
         results: Sender<oneshot::Receiver<ProjectPath>>,
+        mut total_files_tx: oneshot::Sender<usize>,
     ) -> impl AsyncFnOnce(&mut AsyncApp) {
         async move |cx| {
             _ = maybe!(async move {
                 let gitignored_tracker = PathInclusionMatcher::new(query.clone());
                 let include_ignored = query.include_ignored();
-                for worktree in worktrees {
-                    let (mut snapshot, worktree_settings) = worktree
+                let mut total_files = 0;
+                let mut worktrees = worktrees.into_iter();
+                while let Some(worktree) = worktrees.next() {
+                    let (mut snapshot, worktree_settings, fs) = worktree
                         .read_with(cx, |this, _| {
-                            Some((this.snapshot(), this.as_local()?.settings()))
+                            let local = this.as_local()?;
+                            Some((this.snapshot(), local.settings(), local.fs().clone()))
                         })?
                         .context("The worktree is not local")?;
                     if query.include_ignored() {
@@ -341,29 +390,52 @@ impl Search {
                     }
                     let tx = tx.clone();
                     let results = results.clone();
+                    let query = query.clone();
 
-                    cx.background_executor()
+                    // The number of files of the worktree that were provided and, when the search
+                    // stops early, the number of files that weren't.
+                    let (provided_files, unprovided_files) = cx
+                        .background_executor()
                         .spawn(async move {
-                            for entry in snapshot.files(include_ignored, 0) {
+                            let mut provided_files = 0;
+                            let mut files = snapshot.files(include_ignored, 0);
+                            while let Some(entry) = files.next() {
+                                if query.cancellation().is_cancelled() {
+                                    return (provided_files, Some(1 + files.count()));
+                                }
                                 let (should_scan_tx, should_scan_rx) = oneshot::channel();
 
                                 let Ok(_) = tx
                                     .send(InputPath {
                                         entry: entry.clone(),
                                         snapshot: snapshot.clone(),
+                                        fs: fs.clone(),
                                         should_scan_tx,
                                     })
                                     .await
                                 else {
-                                    return;
+                                    return (provided_files, Some(1 + files.count()));
                                 };
                                 if results.send(should_scan_rx).await.is_err() {
-                                    return;
+                                    return (provided_files, Some(1 + files.count()));
                                 };
+                                provided_files += 1;
                             }
+                            (provided_files, None)
                         })
                         .await;
+                    total_files += provided_files;
+                    if let Some(unprovided_files) = unprovided_files {
+                        total_files += unprovided_files;
+                        for worktree in worktrees {
+                            total_files += worktree.read_with(cx, |this, _| {
+                                this.snapshot().files(include_ignored, 0).count()
+                            })?;
+                        }
+                        break;
+                    }
                 }
+                total_files_tx.send(total_files).await?;
                 anyhow::Ok(())
             })
             .await;
@@ -373,6 +445,7 @@ impl Search {
     async fn maintain_sorted_search_results(
         rx: Receiver<oneshot::Receiver<ProjectPath>>,
         paths_for_full_scan: Sender<ProjectPath>,
+        rejected_files: Arc<AtomicUsize>,
         limit: usize,
     ) {
         let mut rx = pin!(rx);
@@ -380,6 +453,7 @@ impl Search {
         while let Some(mut next_path_result) = rx.next().await {
             let Some(successful_path) = next_path_result.next().await else {
                 // This file did not produce a match, hence skip it.
+                rejected_files.fetch_add(1, Ordering::Relaxed);
                 continue;
             };
             if paths_for_full_scan.send(successful_path).await.is_err() {
@@ -422,19 +496,35 @@ impl Search {
 
     async fn grab_buffer_snapshots(
         rx: Receiver<Entity<Buffer>>,
-        find_all_matches_tx: Sender<(
-            Entity<Buffer>,
-            BufferSnapshot,
-            oneshot::Sender<(Entity<Buffer>, Vec<Range<language::Anchor>>)>,
-        )>,
-        results: Sender<oneshot::Receiver<(Entity<Buffer>, Vec<Range<language::Anchor>>)>>,
+        find_all_matches_tx: Sender<FindAllMatches>,
+        results: Sender<oneshot::Receiver<SearchResult>>,
+        wait_for_parsing: bool,
         mut cx: AsyncApp,
     ) {
         _ = maybe!(async move {
             while let Ok(buffer) = rx.recv().await {
-                let snapshot = buffer.read_with(&mut cx, |this, _| this.snapshot())?;
+                // Structural queries match against syntax trees, which freshly opened buffers
+                // don't have until they have been parsed.
+                if wait_for_parsing {
+                    buffer
+                        .read_with(&mut cx, |this, _| this.parsing_idle())?
+                        .await;
+                }
+                let (snapshot, path) = buffer.read_with(&mut cx, |this, cx| {
+                    let path = this
+                        .file()
+                        .map(|file| ProjectPath::from_file(file.as_ref(), cx));
+                    (this.snapshot(), path)
+                })?;
                 let (tx, rx) = oneshot::channel();
-                find_all_matches_tx.send((buffer, snapshot, tx)).await?;
+                find_all_matches_tx
+                    .send(FindAllMatches {
+                        buffer,
+                        snapshot,
+                        path,
+                        report_matches: tx,
+                    })
+                    .await?;
                 results.send(rx).await?;
             }
             debug_assert!(rx.is_empty());
@@ -444,28 +534,51 @@ impl Search {
     }
 
     async fn ensure_matched_ranges_are_reported_in_order(
-        rx: Receiver<oneshot::Receiver<(Entity<Buffer>, Vec<Range<language::Anchor>>)>>,
+        rx: Receiver<oneshot::Receiver<SearchResult>>,
         tx: Sender<SearchResult>,
+        query: Arc<SearchQuery>,
+        unnamed_buffer_count: usize,
+        mut total_files_rx: oneshot::Receiver<usize>,
+        rejected_files: Arc<AtomicUsize>,
     ) {
         use postage::stream::Stream;
         _ = maybe!(async move {
+            let limits = query.limits();
             let mut matched_buffers = 0;
             let mut matches = 0;
+            // The number of files whose search finished before the limits were reached.
+            let mut searched_files = 0;
             while let Ok(mut next_buffer_matches) = rx.recv().await {
-                let Some((buffer, ranges)) = next_buffer_matches.recv().await else {
+                if query.cancellation().is_cancelled() {
+                    break;
+                }
+                let Some(result) = next_buffer_matches.recv().await else {
+                    searched_files += 1;
+                    continue;
+                };
+                let SearchResult::Buffer { ranges, .. } = &result else {
+                    searched_files += 1;
+                    _ = tx.send(result).await?;
                     continue;
                 };
 
-                if matched_buffers > Search::MAX_SEARCH_RESULT_FILES
-                    || matches > Search::MAX_SEARCH_RESULT_RANGES
-                {
-                    _ = tx.send(SearchResult::LimitReached).await;
+                if matched_buffers >= limits.max_files || matches >= limits.max_ranges {
+                    // Stop the remaining stages of the pipeline, so that they report how many
+                    // files there are in total.
+                    drop(rx);
+                    drop(next_buffer_matches);
+                    let total_files =
+                        unnamed_buffer_count + total_files_rx.recv().await.unwrap_or(0);
+                    let searched_files = searched_files + rejected_files.load(Ordering::Relaxed);
+                    let skipped_files = total_files.saturating_sub(searched_files).max(1);
+                    _ = tx.send(SearchResult::LimitReached { skipped_files }).await;
                     break;
                 }
                 matched_buffers += 1;
                 matches += ranges.len();
+                searched_files += 1;
 
-                _ = tx.send(SearchResult::Buffer { buffer, ranges }).await?;
+                _ = tx.send(result).await?;
             }
             anyhow::Ok(())
         })
@@ -512,17 +625,22 @@ impl Search {
     }
 }
 
+/// A request to find all matches in a buffer snapshot, reported via `report_matches`.
+struct FindAllMatches {
+    buffer: Entity<Buffer>,
+    snapshot: BufferSnapshot,
+    path: Option<ProjectPath>,
+    report_matches: oneshot::Sender<SearchResult>,
+}
+
 struct Worker {
     query: Arc<SearchQuery>,
+    active_path: Option<ProjectPath>,
     open_buffers: Arc<HashSet<ProjectEntryId>>,
     candidates: FindSearchCandidates,
     /// Ok, we're back in background: run full scan & find all matches in a given buffer snapshot.
     /// Then, when you're done, share them via the channel you were given.
-    find_all_matches_rx: Receiver<(
-        Entity<Buffer>,
-        BufferSnapshot,
-        oneshot::Sender<(Entity<Buffer>, Vec<Range<language::Anchor>>)>,
-    )>,
+    find_all_matches_rx: Receiver<FindAllMatches>,
 }
 
 impl Worker {
@@ -531,18 +649,18 @@ impl Worker {
             input_paths_rx,
             confirm_contents_will_match_rx,
             mut confirm_contents_will_match_tx,
-            fs,
+            index_filter,
         ) = match self.candidates {
             FindSearchCandidates::Local {
-                fs,
                 input_paths_rx,
                 confirm_contents_will_match_rx,
                 confirm_contents_will_match_tx,
+                index_filter,
             } => (
                 input_paths_rx,
                 confirm_contents_will_match_rx,
                 confirm_contents_will_match_tx,
-                Some(fs),
+                index_filter,
             ),
             FindSearchCandidates::OpenBuffersOnly => {
                 (unbounded().1, unbounded().1, unbounded().0, None)
@@ -557,9 +675,10 @@ impl Worker {
         loop {
             let handler = RequestHandler {
                 query: &self.query,
+                active_path: self.active_path.as_ref(),
                 open_entries: &self.open_buffers,
-                fs: fs.as_deref(),
                 confirm_contents_will_match_tx: &confirm_contents_will_match_tx,
+                index_filter: index_filter.as_ref(),
             };
             // Whenever we notice that some step of a pipeline is closed, we don't want to close subsequent
             // steps straight away. Another worker might be about to produce a value that will
@@ -597,35 +716,79 @@ impl Worker {
 
 struct RequestHandler<'worker> {
     query: &'worker SearchQuery,
-    fs: Option<&'worker dyn Fs>,
+    active_path: Option<&'worker ProjectPath>,
     open_entries: &'worker HashSet<ProjectEntryId>,
     confirm_contents_will_match_tx: &'worker Sender<MatchingEntry>,
+    index_filter: Option<&'worker IndexFilter>,
 }
 
 impl RequestHandler<'_> {
     async fn handle_find_all_matches(
         &self,
-        (buffer, snapshot, mut report_matches): (
-            Entity<Buffer>,
-            BufferSnapshot,
-            oneshot::Sender<(Entity<Buffer>, Vec<Range<language::Anchor>>)>,
-        ),
+        FindAllMatches {
+            buffer,
+            snapshot,
+            path,
+            mut report_matches,
+        }: FindAllMatches,
     ) {
-        let ranges = self
-            .query
-            .search(&snapshot, None)
-            .await
+        let matches = match self.query.try_search(&snapshot, None).await {
+            Ok(matches) => matches,
+            Err(error) => {
+                _ = report_matches
+                    .send(SearchResult::BudgetExceeded { path, error })
+                    .await;
+                return;
+            }
+        };
+        let context_lines = self.query.context_lines();
+        let context = (context_lines > 0).then(|| {
+            let max_row = snapshot.max_point().row;
+            matches
+                .iter()
+                .map(|range| {
+                    let start_row = snapshot
+                        .offset_to_point(range.start)
+                        .row
+                        .saturating_sub(context_lines);
+                    let end_row = snapshot
+                        .offset_to_point(range.end)
+                        .row
+                        .saturating_add(context_lines)
+                        .min(max_row);
+                    snapshot.anchor_before(Point::new(start_row, 0))
+                        ..snapshot.anchor_after(Point::new(end_row, snapshot.line_len(end_row)))
+                })
+                .collect()
+        });
+        let score = relevance_score(
+            path.as_ref(),
+            self.active_path,
+            matches.len(),
+            snapshot.max_point().row + 1,
+        );
+        let ranges = matches
             .iter()
             .map(|range| snapshot.anchor_before(range.start)..snapshot.anchor_after(range.end))
-            .collect::<Vec<_>>();
-
-        _ = report_matches.send((buffer, ranges)).await;
+            .collect();
+
+        _ = report_matches
+            .send(SearchResult::Buffer {
+                buffer,
+                ranges,
+                context,
+                score,
+            })
+            .await;
     }
 
     async fn handle_find_first_match(&self, mut entry: MatchingEntry) {
+        if self.query.cancellation().is_cancelled() {
+            return;
+        }
         _=maybe!(async move {
             let abs_path = entry.worktree_root.join(entry.path.path.as_std_path());
-            let Some(file) = self.fs.context("Trying to query filesystem in remote project search")?.open_sync(&abs_path).await.log_err() else {
+            let Some(file) = entry.fs.open_sync(&abs_path).await.log_err() else {
                 return anyhow::Ok(());
             };
 
@@ -643,7 +806,12 @@ impl RequestHandler<'_> {
                 return Ok(());
             }
 
-            if self.query.detect(file).await.unwrap_or(false) {
+            let should_scan = match self.query.detect(file).await {
+                Ok(should_scan) => should_scan,
+                // Scan the whole file so that the error is reported along with its path.
+                Err(error) => error.is::<RegexBudgetError>(),
+            };
+            if should_scan {
                 // Yes, we should scan the whole file.
                 entry.should_scan_tx.send(entry.path).await?;
             }
@@ -652,10 +820,14 @@ impl RequestHandler<'_> {
     }
 
     async fn handle_scan_path(&self, req: InputPath) {
+        if self.query.cancellation().is_cancelled() {
+            return;
+        }
         _ = maybe!(async move {
             let InputPath {
                 entry,
                 snapshot,
+                fs,
                 mut should_scan_tx,
             } = req;
 
@@ -685,11 +857,16 @@ impl RequestHandler<'_> {
                         path: entry.path.clone(),
                     })
                     .await?;
-            } else {
+            } else if self.index_filter.is_none_or(|filter| {
+                filter
+                    .index
+                    .may_contain(snapshot.id(), &entry, &filter.trigrams)
+            }) {
                 self.confirm_contents_will_match_tx
                     .send(MatchingEntry {
                         should_scan_tx: should_scan_tx,
                         worktree_root: snapshot.abs_path().clone(),
+                        fs,
                         path: ProjectPath {
                             worktree_id: snapshot.id(),
                             path: entry.path.clone(),
@@ -707,11 +884,14 @@ impl RequestHandler<'_> {
 struct InputPath {
     entry: Entry,
     snapshot: Snapshot,
+    /// The file system of the entry's worktree, which is in memory for scratch worktrees.
+    fs: Arc<dyn Fs>,
     should_scan_tx: oneshot::Sender<ProjectPath>,
 }
 
 struct MatchingEntry {
     worktree_root: Arc<Path>,
+    fs: Arc<dyn Fs>,
     path: ProjectPath,
     should_scan_tx: oneshot::Sender<ProjectPath>,
 }
@@ -4,7 +4,7 @@ use context_server::ContextServerCommand;
 use dap::adapters::DebugAdapterName;
 use fs::Fs;
 use futures::StreamExt as _;
-use gpui::{AsyncApp, BorrowAppContext, Context, Entity, EventEmitter, Subscription, Task};
+use gpui::{App, AsyncApp, BorrowAppContext, Context, Entity, EventEmitter, Subscription, Task};
 use lsp::LanguageServerName;
 use paths::{
     EDITORCONFIG_NAME, local_debug_file_relative_path, local_settings_file_relative_path,
@@ -20,8 +20,9 @@ use serde::{Deserialize, Serialize};
 pub use settings::DirenvSettings;
 pub use settings::LspSettings;
 use settings::{
-    DapSettingsContent, InvalidSettingsError, LocalSettingsKind, RegisterSetting, Settings,
-    SettingsLocation, SettingsStore, parse_json_with_comments, watch_config_file,
+    DapSettingsContent, InvalidSettingsError, LocalSettingsKind, ProjectSettingsContent,
+    RegisterSetting, Settings, SettingsLocation, SettingsStore, parse_json_with_comments,
+    watch_config_file,
 };
 use std::{cell::OnceCell, collections::BTreeMap, path::PathBuf, sync::Arc, time::Duration};
 use task::{DebugTaskFile, TaskTemplates, VsCodeDebugTaskFile, VsCodeTaskFile};
@@ -71,6 +72,30 @@ pub struct ProjectSettings {
     /// Configuration for how direnv configuration should be loaded
     pub load_direnv: DirenvSettings,
 
+    /// Configuration for running project processes inside a Dev Container
+    pub dev_container: DevContainerSettings,
+
+    /// Named templates for the contents of new files
+    pub file_templates: HashMap<String, String>,
+
+    /// Whether to maintain a trigram index of worktree files for project searches
+    pub search_index: bool,
+
+    /// Whether to collect tagged comments across the project
+    pub todo_scanner: bool,
+
+    /// The tags that mark comments collected by the TODO scanner
+    pub todo_tags: Vec<String>,
+
+    /// Configuration for the local history of edited files
+    pub local_history: LocalHistorySettings,
+
+    /// The memory open buffers may take up before invisible clean ones are evicted
+    pub buffer_memory_budget_mb: u64,
+
+    /// The size from which files are opened as large files rather than buffers
+    pub large_file_threshold_mb: u64,
+
     /// Configuration for session-related features
     pub session: SessionSettings,
 }
@@ -92,6 +117,50 @@ pub struct SessionSettings {
     pub trust_all_worktrees: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalHistorySettings {
+    /// Whether to snapshot local files on save and periodically while they have unsaved changes.
+    pub enabled: bool,
+    /// How often to snapshot files with unsaved changes, if at all.
+    pub snapshot_interval: Option<Duration>,
+    /// The number of versions kept for each file.
+    pub max_versions_per_file: usize,
+    /// How long versions are kept for.
+    pub max_age: Duration,
+}
+
+impl From<settings::LocalHistorySettingsContent> for LocalHistorySettings {
+    fn from(settings: settings::LocalHistorySettingsContent) -> Self {
+        let snapshot_interval_secs = settings.snapshot_interval_secs.unwrap();
+        Self {
+            enabled: settings.enabled.unwrap(),
+            snapshot_interval: (snapshot_interval_secs > 0)
+                .then(|| Duration::from_secs(snapshot_interval_secs)),
+            max_versions_per_file: settings.max_versions_per_file.unwrap(),
+            max_age: Duration::from_secs(
+                settings.max_age_days.unwrap().saturating_mul(24 * 60 * 60),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DevContainerSettings {
+    /// Whether to start the Dev Container of a worktree and run terminals and tasks inside of it.
+    pub enabled: bool,
+    /// The path to the `devcontainer` CLI, if it should not be looked up automatically.
+    pub cli_path: Option<PathBuf>,
+}
+
+impl From<settings::DevContainerSettingsContent> for DevContainerSettings {
+    fn from(settings: settings::DevContainerSettingsContent) -> Self {
+        Self {
+            enabled: settings.enabled.unwrap_or(false),
+            cli_path: settings.cli_path.map(PathBuf::from),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct NodeBinarySettings {
     /// The path to the Node binary.
@@ -598,6 +667,14 @@ impl Settings for ProjectSettings {
             git: git_settings,
             node: content.node.clone().unwrap().into(),
             load_direnv: project.load_direnv.clone().unwrap(),
+            dev_container: project.dev_container.clone().unwrap_or_default().into(),
+            file_templates: project.file_templates.clone(),
+            search_index: project.search_index.unwrap(),
+            todo_scanner: project.todo_scanner.unwrap(),
+            todo_tags: project.todo_tags.clone().unwrap(),
+            local_history: project.local_history.clone().unwrap().into(),
+            buffer_memory_budget_mb: project.buffer_memory_budget_mb.unwrap(),
+            large_file_threshold_mb: project.large_file_threshold_mb.unwrap(),
             session: SessionSettings {
                 restore_unsaved_buffers: content.session.unwrap().restore_unsaved_buffers.unwrap(),
                 trust_all_worktrees: content.session.unwrap().trust_all_worktrees.unwrap(),
@@ -872,6 +949,30 @@ impl SettingsObserver {
         Ok(())
     }
 
+    /// Sets the settings overlay of a worktree from a JSON string, or removes it when `None`.
+    ///
+    /// Overlays let a worktree carry its own settings without a local settings file. They take
+    /// precedence over user and server settings, and are overridden by the worktree's
+    /// `.vector/settings.json` files.
+    pub fn set_worktree_settings_overlay(
+        &mut self,
+        worktree_id: WorktreeId,
+        settings_content: Option<String>,
+        cx: &mut Context<Self>,
+    ) -> Result<(), InvalidSettingsError> {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.set_worktree_overlay(worktree_id, settings_content.as_deref(), cx)
+        })
+    }
+
+    pub fn worktree_settings_overlay<'a>(
+        &self,
+        worktree_id: WorktreeId,
+        cx: &'a App,
+    ) -> Option<&'a ProjectSettingsContent> {
+        SettingsStore::global(cx).worktree_overlay(worktree_id)
+    }
+
     fn on_worktree_store_event(
         &mut self,
         _: Entity<WorktreeStore>,
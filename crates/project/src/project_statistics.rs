@@ -0,0 +1,95 @@
+//! An overview of the contents of a project's worktrees.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use fs::Fs;
+use git::status::GitSummary;
+use language::{LanguageName, LanguageRegistry};
+use worktree::{Snapshot, WorktreeId};
+
+/// Files larger than this are counted, but their lines are not.
+const MAX_LINE_COUNTED_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectStatistics {
+    pub worktrees: Vec<WorktreeStatistics>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorktreeStatistics {
+    pub worktree_id: WorktreeId,
+    pub root_name: String,
+    /// The number of files that are not ignored.
+    pub file_count: usize,
+    /// The total size of the files that are not ignored, in bytes.
+    pub total_size: u64,
+    /// The number of lines in the files of each language. Files whose language is unknown
+    /// are left out.
+    pub lines_by_language: BTreeMap<LanguageName, usize>,
+    /// The summary of the git statuses of the repositories within the worktree or containing it.
+    pub git_status: GitSummary,
+}
+
+impl ProjectStatistics {
+    pub fn file_count(&self) -> usize {
+        self.worktrees
+            .iter()
+            .map(|worktree| worktree.file_count)
+            .sum()
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.worktrees
+            .iter()
+            .map(|worktree| worktree.total_size)
+            .sum()
+    }
+
+    pub fn lines_by_language(&self) -> BTreeMap<LanguageName, usize> {
+        let mut lines_by_language = BTreeMap::new();
+        for worktree in &self.worktrees {
+            for (language, lines) in &worktree.lines_by_language {
+                *lines_by_language.entry(language.clone()).or_default() += lines;
+            }
+        }
+        lines_by_language
+    }
+}
+
+/// Counts the files of a worktree snapshot and the lines in them, reading files from the file
+/// system. The git status is left for the caller to fill in, as it changes independently of the
+/// worktree's contents.
+pub(crate) async fn scan_worktree(
+    fs: Arc<dyn Fs>,
+    languages: Arc<LanguageRegistry>,
+    snapshot: Snapshot,
+) -> WorktreeStatistics {
+    let mut statistics = WorktreeStatistics {
+        worktree_id: snapshot.id(),
+        root_name: snapshot.root_name_str().to_string(),
+        file_count: 0,
+        total_size: 0,
+        lines_by_language: BTreeMap::new(),
+        git_status: GitSummary::UNCHANGED,
+    };
+    for entry in snapshot.files(false, 0) {
+        statistics.file_count += 1;
+        statistics.total_size += entry.size;
+        if entry.size > MAX_LINE_COUNTED_FILE_SIZE {
+            continue;
+        }
+        let Some(language) = languages.language_for_file_path(entry.path.as_std_path()) else {
+            continue;
+        };
+        let abs_path = snapshot.absolutize(&entry.path);
+        let Ok(text) = fs.load(&abs_path).await else {
+            log::debug!("failed to read {abs_path:?} for project statistics");
+            continue;
+        };
+        *statistics
+            .lines_by_language
+            .entry(language.name())
+            .or_default() += text.lines().count();
+    }
+    statistics
+}
@@ -2,9 +2,11 @@
 
 use crate::{
     Event,
+    buffer_store::AutosaveTrigger,
     git_store::{GitStoreEvent, RepositoryEvent, StatusEntry, pending_op},
+    local_history::LocalHistoryTrigger,
     task_inventory::TaskContexts,
-    task_store::TaskSettingsLocation,
+    task_store::{BackgroundTaskStatus, TaskSettingsLocation, TaskStoreEvent, TriggeredTaskSlot},
     *,
 };
 use async_trait::async_trait;
@@ -23,10 +25,10 @@ use git2::RepositoryInitOptions;
 use gpui::{App, BackgroundExecutor, FutureExt, UpdateGlobal};
 use itertools::Itertools;
 use language::{
-    Diagnostic, DiagnosticEntry, DiagnosticEntryRef, DiagnosticSet, DiagnosticSourceKind,
-    DiskState, FakeLspAdapter, LanguageConfig, LanguageMatcher, LanguageName, LineEnding,
-    ManifestName, ManifestProvider, ManifestQuery, OffsetRangeExt, Point, ToPoint, ToolchainList,
-    ToolchainLister,
+    BufferLock, BufferLockedError, Diagnostic, DiagnosticEntry, DiagnosticEntryRef, DiagnosticSet,
+    DiagnosticSourceKind, DiskState, FakeLspAdapter, LanguageConfig, LanguageMatcher, LanguageName,
+    LineEnding, ManifestName, ManifestProvider, ManifestQuery, MergeConflictResolution,
+    OffsetRangeExt, Point, ToPoint, ToolchainList, ToolchainLister,
     language_settings::{LanguageSettingsContent, language_settings},
     rust_lang, tree_sitter_typescript,
 };
@@ -35,7 +37,7 @@ use lsp::{
     Uri, WillRenameFiles, notification::DidRenameFiles,
 };
 use parking_lot::Mutex;
-use paths::{config_dir, global_gitignore_path, tasks_file};
+use paths::{buffer_locks_dir, config_dir, global_gitignore_path, tasks_file};
 use postage::stream::Stream as _;
 use pretty_assertions::{assert_eq, assert_matches};
 use rand::{Rng as _, rngs::StdRng};
@@ -51,7 +53,10 @@ use std::{
     task::Poll,
 };
 use sum_tree::SumTree;
-use task::{ResolvedTask, ShellKind, TaskContext};
+use task::{
+    DebugScenario, ResolvedTask, ShellKind, SpawnInTerminal, TaskContext, TaskId, TaskTemplate,
+    TaskVariables, VariableName,
+};
 use unindent::Unindent as _;
 use util::{
     TryFutureExt as _, assert_set_eq, maybe, path,
@@ -229,6 +234,8 @@ async fn test_editorconfig_support(cx: &mut gpui::TestAppContext) {
         assert_eq!(settings_a.ensure_final_newline_on_save, true);
         assert_eq!(settings_a.remove_trailing_whitespace_on_save, true);
         assert_eq!(settings_a.preferred_line_length, 120);
+        assert_eq!(settings_a.line_ending, Some(LineEnding::Unix));
+        assert_eq!(settings_c.line_ending, None);
 
         // .editorconfig in b/ overrides .editorconfig in root
         assert_eq!(Some(settings_b.tab_size), NonZeroU32::new(2));
@@ -245,6 +252,58 @@ async fn test_editorconfig_support(cx: &mut gpui::TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_editorconfig_line_ending_and_charset(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".editorconfig": "root = true\n[*.txt]\nend_of_line = crlf\ncharset = utf-8-bom\n",
+            "a.txt": "one\ntwo\n",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    cx.run_until_parked();
+
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "zero ")], None, cx));
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load_bytes(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        b"\xEF\xBB\xBFzero one\r\ntwo\r\n"
+    );
+
+    // Changes to .editorconfig files are picked up.
+    fs.save(
+        path!("/dir/.editorconfig").as_ref(),
+        &"root = true\n[*.txt]\nend_of_line = lf\ncharset = utf-8\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.run_until_parked();
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..5, "")], None, cx));
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "one\ntwo\n"
+    );
+}
+
 #[gpui::test]
 async fn test_git_provider_project_setting(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -1543,7 +1602,9 @@ async fn test_reporting_fs_changes_to_language_servers(cx: &mut gpui::TestAppCon
 
     let mut new_watched_paths = fs.watched_paths();
     new_watched_paths.retain(|path| {
-        !path.starts_with(config_dir()) && !path.starts_with(global_gitignore_path().unwrap())
+        !path.starts_with(config_dir())
+            && !path.starts_with(global_gitignore_path().unwrap())
+            && !path.starts_with(buffer_locks_dir())
     });
     assert_eq!(
         &new_watched_paths,
@@ -2879,954 +2940,1238 @@ async fn test_diagnostics_from_multiple_language_servers(cx: &mut gpui::TestAppC
 }
 
 #[gpui::test]
-async fn test_edits_from_lsp2_with_past_version(cx: &mut gpui::TestAppContext) {
+async fn test_build_file_task_discovery(cx: &mut gpui::TestAppContext) {
     init_test(cx);
-
-    let text = "
-        fn a() {
-            f1();
-        }
-        fn b() {
-            f2();
-        }
-        fn c() {
-            f3();
-        }
-    "
-    .unindent();
+    TaskStore::init(None);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.rs": text.clone(),
+            "Makefile": "build:\n\tcc main.c\n",
+            "web": {
+                "package.json": r#"{ "scripts": { "dev": "vite" } }"#,
+            },
         }),
     )
     .await;
 
-    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.update(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let discovered_tasks = |cx: &mut gpui::TestAppContext| {
+        cx.executor().advance_clock(Duration::from_secs(1));
+        cx.executor().run_until_parked();
+        let tasks = project.update(cx, |project, cx| {
+            let inventory = project.task_store.read(cx).task_inventory().unwrap();
+            inventory
+                .read(cx)
+                .list_tasks(None, None, Some(worktree_id), cx)
+        });
+        cx.background_spawn(async move {
+            tasks
+                .await
+                .into_iter()
+                .filter_map(|(kind, template)| match kind {
+                    TaskSourceKind::BuildFile { path, row, .. } => Some((
+                        template.label,
+                        template.cwd,
+                        path.as_unix_str().to_string(),
+                        row,
+                    )),
+                    _ => None,
+                })
+                .sorted()
+                .collect::<Vec<_>>()
+        })
+    };
 
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(rust_lang());
-    let mut fake_servers = language_registry.register_fake_lsp("Rust", FakeLspAdapter::default());
+    assert_eq!(
+        discovered_tasks(cx).await,
+        vec![
+            ("make build".to_string(), None, "Makefile".to_string(), 0),
+            (
+                "npm run dev (web)".to_string(),
+                Some("$VECTOR_WORKTREE_ROOT/web".to_string()),
+                "web/package.json".to_string(),
+                0,
+            ),
+        ]
+    );
 
-    let (buffer, _handle) = project
-        .update(cx, |project, cx| {
-            project.open_local_buffer_with_lsp(path!("/dir/a.rs"), cx)
-        })
+    fs.insert_file(
+        path!("/dir/Makefile"),
+        "CC := gcc\n\ntest:\n\tcc test.c\n".into(),
+    )
+    .await;
+    fs.remove_file(path!("/dir/web/package.json").as_ref(), Default::default())
         .await
         .unwrap();
+    assert_eq!(
+        discovered_tasks(cx).await,
+        vec![("make test".to_string(), None, "Makefile".to_string(), 2)]
+    );
+}
 
-    let mut fake_server = fake_servers.next().await.unwrap();
-    let lsp_document_version = fake_server
-        .receive_notification::<lsp::notification::DidOpenTextDocument>()
-        .await
-        .text_document
-        .version;
-
-    // Simulate editing the buffer after the language server computes some edits.
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit(
-            [(
-                Point::new(0, 0)..Point::new(0, 0),
-                "// above first function\n",
-            )],
-            None,
-            cx,
-        );
-        buffer.edit(
-            [(
-                Point::new(2, 0)..Point::new(2, 0),
-                "    // inside first function\n",
-            )],
-            None,
-            cx,
-        );
-        buffer.edit(
-            [(
-                Point::new(6, 4)..Point::new(6, 4),
-                "// inside second function ",
-            )],
-            None,
-            cx,
-        );
-
-        assert_eq!(
-            buffer.text(),
-            "
-                // above first function
-                fn a() {
-                    // inside first function
-                    f1();
-                }
-                fn b() {
-                    // inside second function f2();
-                }
-                fn c() {
-                    f3();
-                }
-            "
-            .unindent()
-        );
-    });
+#[gpui::test]
+async fn test_execution_plan_with_language_tasks(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    TaskStore::init(None);
 
-    let edits = lsp_store
-        .update(cx, |lsp_store, cx| {
-            lsp_store.as_local_mut().unwrap().edits_from_lsp(
-                &buffer,
-                vec![
-                    // replace body of first function
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(3, 0)),
-                        new_text: "
-                            fn a() {
-                                f10();
-                            }
-                            "
-                        .unindent(),
-                    },
-                    // edit inside second function
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(4, 6), lsp::Position::new(4, 6)),
-                        new_text: "00".into(),
-                    },
-                    // edit inside third function via two distinct edits
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(7, 5), lsp::Position::new(7, 5)),
-                        new_text: "4000".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(7, 5), lsp::Position::new(7, 6)),
-                        new_text: "".into(),
-                    },
-                ],
-                LanguageServerId(0),
-                Some(lsp_document_version),
-                cx,
-            )
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "main.rs": "fn main() {}" }))
+        .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            matcher: LanguageMatcher {
+                path_suffixes: vec!["rs".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        None,
+    )
+    .with_context_provider(Some(Arc::new(ContextProviderWithTasks::new(
+        task::TaskTemplates(vec![TaskTemplate {
+            label: "build".to_string(),
+            command: "cargo build".to_string(),
+            ..TaskTemplate::default()
+        }]),
+    ))));
+    project.read_with(cx, |project, _| project.languages().add(Arc::new(language)));
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/main.rs"), cx)
         })
         .await
         .unwrap();
+    cx.executor().run_until_parked();
 
-    buffer.update(cx, |buffer, cx| {
-        for (range, new_text) in edits {
-            buffer.edit([(range, new_text)], None, cx);
-        }
-        assert_eq!(
-            buffer.text(),
-            "
-                // above first function
-                fn a() {
-                    // inside first function
-                    f10();
-                }
-                fn b() {
-                    // inside second function f200();
-                }
-                fn c() {
-                    f4000();
-                }
-                "
-            .unindent()
-        );
-    });
+    let test_task = TaskTemplate {
+        label: "test".to_string(),
+        command: "cargo test".to_string(),
+        depends_on: vec!["build".to_string()],
+        ..TaskTemplate::default()
+    }
+    .resolve_task("test", &TaskContext::default())
+    .unwrap();
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let execution_plan = |buffer: Option<Entity<Buffer>>, cx: &mut gpui::TestAppContext| {
+        task_store.read_with(cx, |task_store, cx| {
+            task_store.execution_plan(TaskSourceKind::UserInput, test_task.clone(), buffer, cx)
+        })
+    };
+
+    // The dependency is a task of the buffer's language, so it is only found with the buffer.
+    let error = execution_plan(None, cx).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "task `test` depends on unknown task `build`"
+    );
+    let plan = execution_plan(Some(buffer), cx).await.unwrap();
+    assert_eq!(
+        plan.steps
+            .iter()
+            .map(|step| step.task.original_task().label.as_str())
+            .collect::<Vec<_>>(),
+        ["build", "test"]
+    );
 }
 
 #[gpui::test]
-async fn test_edits_from_lsp2_with_edits_on_adjacent_lines(cx: &mut gpui::TestAppContext) {
+async fn test_run_on_save_triggers(cx: &mut gpui::TestAppContext) {
     init_test(cx);
-
-    let text = "
-        use a::b;
-        use a::c;
-
-        fn f() {
-            b();
-            c();
-        }
-    "
-    .unindent();
+    TaskStore::init(None);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.rs": text.clone(),
+            ".vector": {
+                "tasks.json": r#"[
+                    { "label": "lint", "command": "lint", "run_on_save": ["src/**/*.rs"] },
+                    { "label": "check", "command": "check", "run_on_save": ["**/*.rs"] },
+                    { "label": "format", "command": "format", "run_on_save": ["**/*.rs"] },
+                    { "label": "codegen", "command": "codegen", "run_on_save": ["schema/*.json"] },
+                    { "label": "build", "command": "build" }
+                ]"#,
+            },
+            "src": {
+                "main.rs": "fn main() {}",
+            },
         }),
     )
     .await;
 
-    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    cx.executor().run_until_parked();
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let triggered = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cx.update(|cx| {
+        let triggered = triggered.clone();
+        cx.subscribe(&task_store, move |_, event: &TaskStoreEvent, _| {
+            if let TaskStoreEvent::TaskTriggered { task, slot, .. } = event {
+                triggered.lock().push((task.clone(), slot.clone()));
+            }
+        })
+    });
+
     let buffer = project
         .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/a.rs"), cx)
+            project.open_local_buffer(path!("/dir/src/main.rs"), cx)
         })
         .await
         .unwrap();
+    for _ in 0..3 {
+        project
+            .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+            .await
+            .unwrap();
+        cx.executor().advance_clock(Duration::from_millis(100));
+    }
+    cx.executor().run_until_parked();
+    assert!(
+        triggered.lock().is_empty(),
+        "triggered tasks should wait for the saves to settle"
+    );
 
-    // Simulate the language server sending us a small edit in the form of a very large diff.
-    // Rust-analyzer does this when performing a merge-imports code action.
-    let edits = lsp_store
-        .update(cx, |lsp_store, cx| {
-            lsp_store.as_local_mut().unwrap().edits_from_lsp(
-                &buffer,
-                [
-                    // Replace the first use statement without editing the semicolon.
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 4), lsp::Position::new(0, 8)),
-                        new_text: "a::{b, c}".into(),
-                    },
-                    // Reinsert the remainder of the file between the semicolon and the final
-                    // newline of the file.
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
-                        new_text: "\n\n".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
-                        new_text: "
-                            fn f() {
-                                b();
-                                c();
-                            }"
-                        .unindent(),
-                    },
-                    // Delete everything after the first newline of the file.
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(7, 0)),
-                        new_text: "".into(),
-                    },
-                ],
-                LanguageServerId(0),
-                None,
-                cx,
-            )
-        })
-        .await
-        .unwrap();
-
-    buffer.update(cx, |buffer, cx| {
-        let edits = edits
-            .into_iter()
-            .map(|(range, text)| {
-                (
-                    range.start.to_point(buffer)..range.end.to_point(buffer),
-                    text,
-                )
-            })
-            .collect::<Vec<_>>();
-
-        assert_eq!(
-            edits,
-            [
-                (Point::new(0, 4)..Point::new(0, 8), "a::{b, c}".into()),
-                (Point::new(1, 0)..Point::new(2, 0), "".into())
-            ]
-        );
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    let labels = |triggered: &Mutex<Vec<(ResolvedTask, TriggeredTaskSlot)>>| {
+        triggered
+            .lock()
+            .iter()
+            .map(|(task, _)| task.original_task().label.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        labels(&triggered),
+        vec!["lint".to_string(), "check".to_string()],
+        "repeated saves should be coalesced, and no more than two tasks should run at once"
+    );
 
-        for (range, new_text) in edits {
-            buffer.edit([(range, new_text)], None, cx);
-        }
-        assert_eq!(
-            buffer.text(),
-            "
-                use a::{b, c};
+    // The slot of a triggered task is freed once whoever runs it drops it.
+    let (first_task, first_slot) = triggered.lock().remove(0);
+    drop(first_slot);
+    cx.executor().run_until_parked();
+    assert_eq!(
+        labels(&triggered),
+        vec!["check".to_string(), "format".to_string()],
+    );
+    assert_eq!(first_task.original_task().label, "lint");
 
-                fn f() {
-                    b();
-                    c();
-                }
-            "
-            .unindent()
-        );
-    });
+    // Autosaves don't trigger tasks.
+    triggered.lock().clear();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "// ")], None, cx));
+    project
+        .update(cx, |project, cx| {
+            project.autosave_buffers(HashSet::from_iter([buffer.clone()]), cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    assert!(labels(&triggered).is_empty());
 }
 
 #[gpui::test]
-async fn test_edits_from_lsp_with_replacement_followed_by_adjacent_insertion(
-    cx: &mut gpui::TestAppContext,
-) {
+async fn test_run_on_save_triggers_in_untrusted_worktree(cx: &mut gpui::TestAppContext) {
     init_test(cx);
-
-    let text = "Path()";
+    TaskStore::init(None);
+    cx.update(|cx| crate::trusted_worktrees::init(HashMap::default(), cx));
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.rs": text
+            ".vector": {
+                "tasks.json": r#"[{ "label": "lint", "command": "lint", "run_on_save": ["*.rs"] }]"#,
+            },
+            "main.rs": "fn main() {}",
         }),
     )
     .await;
 
-    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    cx.executor().run_until_parked();
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let triggered = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cx.update(|cx| {
+        let triggered = triggered.clone();
+        cx.subscribe(&task_store, move |_, event: &TaskStoreEvent, _| {
+            if let TaskStoreEvent::TaskTriggered { task, .. } = event {
+                triggered.lock().push(task.original_task().label.clone());
+            }
+        })
+    });
+
     let buffer = project
         .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/a.rs"), cx)
+            project.open_local_buffer(path!("/dir/main.rs"), cx)
         })
         .await
         .unwrap();
+    let save = |cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+    };
+    save(cx).await.unwrap();
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    assert!(
+        triggered.lock().is_empty(),
+        "tasks of untrusted worktrees should not run on save"
+    );
 
-    // Simulate the language server sending us a pair of edits at the same location,
-    // with an insertion following a replacement (which violates the LSP spec).
-    let edits = lsp_store
-        .update(cx, |lsp_store, cx| {
-            lsp_store.as_local_mut().unwrap().edits_from_lsp(
-                &buffer,
-                [
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 4)),
-                        new_text: "Path".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 0)),
-                        new_text: "from path import Path\n\n\n".into(),
-                    },
-                ],
-                LanguageServerId(0),
-                None,
+    let worktree_store = project.read_with(cx, |project, _| project.worktree_store());
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    cx.update(|cx| {
+        crate::trusted_worktrees::TrustedWorktrees::try_get_global(cx)
+            .unwrap()
+            .update(cx, |trusted, cx| {
+                trusted.trust(
+                    &worktree_store,
+                    HashSet::from_iter([crate::trusted_worktrees::PathTrust::Worktree(
+                        worktree_id,
+                    )]),
+                    cx,
+                )
+            })
+    });
+    save(cx).await.unwrap();
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    assert_eq!(*triggered.lock(), vec!["lint".to_string()]);
+}
+
+#[gpui::test]
+async fn test_task_history_rerun(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    TaskStore::init(None);
+
+    let fs = FakeFs::new(cx.executor());
+    let project = Project::test(fs, [], cx).await;
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let rerun_tasks = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cx.update(|cx| {
+        let rerun_tasks = rerun_tasks.clone();
+        cx.subscribe(&task_store, move |_, event: &TaskStoreEvent, _| {
+            if let TaskStoreEvent::RerunRequested { task, .. } = event {
+                rerun_tasks.lock().push(task.clone());
+            }
+        })
+    });
+
+    let template = TaskTemplate {
+        label: "test $VECTOR_STEM".to_string(),
+        command: "cargo".to_string(),
+        args: vec!["test".to_string(), VariableName::Stem.template_value()],
+        ..TaskTemplate::default()
+    };
+    let task_context = TaskContext {
+        cwd: Some(PathBuf::from(path!("/project"))),
+        task_variables: TaskVariables::from_iter([(VariableName::Stem, "parser".to_string())]),
+        project_env: HashMap::default(),
+    };
+    let task = template.resolve_task("test", &task_context).unwrap();
+    let id = task_store
+        .update(cx, |task_store, cx| {
+            task_store.task_run_finished(
+                &TaskSourceKind::UserInput,
+                &task,
+                Some(101),
+                Duration::from_secs(3),
                 cx,
             )
         })
-        .await
         .unwrap();
-
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit(edits, None, cx);
-        assert_eq!(buffer.text(), "from path import Path\n\n\nPath()")
+    task_store.read_with(cx, |task_store, _| {
+        let history = task_store.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, id);
+        assert_eq!(history[0].label, "test parser");
+        assert_eq!(history[0].command_label, "cargo test parser");
+        assert_eq!(history[0].cwd, Some(PathBuf::from(path!("/project"))));
+        assert_eq!(history[0].exit_code, Some(101));
+        assert_eq!(history[0].duration, Duration::from_secs(3));
     });
+
+    task_store
+        .update(cx, |task_store, cx| task_store.rerun(id, cx))
+        .await
+        .unwrap();
+    let rerun_tasks = rerun_tasks.lock();
+    assert_eq!(rerun_tasks.len(), 1);
+    assert_eq!(rerun_tasks[0].id, task.id);
+    assert_eq!(rerun_tasks[0].resolved, task.resolved);
+    drop(rerun_tasks);
+
+    let missing_run = TaskRunId(id.0 + 1);
+    let rerun_missing = task_store
+        .update(cx, |task_store, cx| task_store.rerun(missing_run, cx))
+        .await;
+    assert!(rerun_missing.is_err());
 }
 
+#[cfg(not(windows))]
 #[gpui::test]
-async fn test_invalid_edits_from_lsp2(cx: &mut gpui::TestAppContext) {
+async fn test_run_headless_task(cx: &mut gpui::TestAppContext) {
     init_test(cx);
-
-    let text = "
-        use a::b;
-        use a::c;
-
-        fn f() {
-            b();
-            c();
-        }
-    "
-    .unindent();
+    cx.executor().allow_parking();
+    TaskStore::init(None);
 
     let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(
-        path!("/dir"),
-        json!({
-            "a.rs": text.clone(),
-        }),
-    )
-    .await;
+    let project = Project::test(fs, [], cx).await;
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let streamed_lines = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cx.update(|cx| {
+        let streamed_lines = streamed_lines.clone();
+        cx.subscribe(&task_store, move |_, event: &TaskStoreEvent, _| {
+            if let TaskStoreEvent::HeadlessTaskOutput { stream, line, .. } = event {
+                streamed_lines.lock().push((*stream, line.clone()));
+            }
+        })
+    });
 
-    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
-    let buffer = project
-        .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/a.rs"), cx)
+    let template = TaskTemplate {
+        label: "greet".to_string(),
+        command: "echo hello $GREETING && echo oops 1>&2 && exit 3".to_string(),
+        env: HashMap::from_iter([("GREETING".to_string(), "world".to_string())]),
+        ..TaskTemplate::default()
+    };
+    let task = template
+        .resolve_task("test", &TaskContext::default())
+        .unwrap();
+    let output = task_store
+        .update(cx, |task_store, cx| {
+            task_store.run_headless(TaskSourceKind::UserInput, task, cx)
         })
         .await
         .unwrap();
+    assert_eq!(output.stdout, "hello world\n");
+    assert_eq!(output.stderr, "oops\n");
+    assert_eq!(output.exit_code, Some(3));
+    assert!(!output.succeeded());
 
-    // Simulate the language server sending us edits in a non-ordered fashion,
-    // with ranges sometimes being inverted or pointing to invalid locations.
-    let edits = lsp_store
-        .update(cx, |lsp_store, cx| {
-            lsp_store.as_local_mut().unwrap().edits_from_lsp(
-                &buffer,
-                [
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
-                        new_text: "\n\n".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 8), lsp::Position::new(0, 4)),
-                        new_text: "a::{b, c}".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(99, 0)),
-                        new_text: "".into(),
-                    },
-                    lsp::TextEdit {
-                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
-                        new_text: "
-                            fn f() {
-                                b();
-                                c();
-                            }"
-                        .unindent(),
-                    },
-                ],
-                LanguageServerId(0),
-                None,
+    let mut streamed_lines = streamed_lines.lock().clone();
+    streamed_lines.sort();
+    assert_eq!(
+        streamed_lines,
+        vec![
+            (TaskOutputStream::Stdout, "hello world".to_string()),
+            (TaskOutputStream::Stderr, "oops".to_string()),
+        ]
+    );
+    task_store.read_with(cx, |task_store, _| {
+        let history = task_store.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].label, "greet");
+        assert_eq!(history[0].exit_code, Some(3));
+    });
+}
+
+#[gpui::test]
+async fn test_export_terminal_scrollback(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/logs"), json!({})).await;
+    let project = Project::test(fs.clone(), [], cx).await;
+    let terminal = cx.new(|cx| {
+        terminal::TerminalBuilder::new_display_only(
+            terminal::terminal_settings::CursorShape::default(),
+            terminal::terminal_settings::AlternateScroll::On,
+            None,
+            0,
+        )
+        .unwrap()
+        .subscribe(cx)
+    });
+    terminal.update(cx, |terminal, cx| {
+        terminal.write_output(b"Compiling vector\r\nerror: build failed\r\n", cx);
+    });
+
+    project
+        .update(cx, |project, cx| {
+            project.export_terminal_scrollback(
+                &terminal,
+                PathBuf::from(path!("/logs/build.log")),
                 cx,
             )
         })
         .await
         .unwrap();
+    assert_eq!(
+        fs.load(path!("/logs/build.log").as_ref()).await.unwrap(),
+        "Compiling vector\nerror: build failed\n"
+    );
 
-    buffer.update(cx, |buffer, cx| {
-        let edits = edits
-            .into_iter()
-            .map(|(range, text)| {
-                (
-                    range.start.to_point(buffer)..range.end.to_point(buffer),
-                    text,
-                )
-            })
-            .collect::<Vec<_>>();
-
-        assert_eq!(
-            edits,
-            [
-                (Point::new(0, 4)..Point::new(0, 8), "a::{b, c}".into()),
-                (Point::new(1, 0)..Point::new(2, 0), "".into())
-            ]
-        );
-
-        for (range, new_text) in edits {
-            buffer.edit([(range, new_text)], None, cx);
-        }
-        assert_eq!(
-            buffer.text(),
-            "
-                use a::{b, c};
-
-                fn f() {
-                    b();
-                    c();
-                }
-            "
-            .unindent()
-        );
+    let buffer = project.update(cx, |project, cx| {
+        project.open_terminal_scrollback(&terminal, cx)
+    });
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "Compiling vector\nerror: build failed\n");
+        assert!(buffer.file().is_none());
     });
 }
 
-fn chunks_with_diagnostics<T: ToOffset + ToPoint>(
-    buffer: &Buffer,
-    range: Range<T>,
-) -> Vec<(String, Option<DiagnosticSeverity>)> {
-    let mut chunks: Vec<(String, Option<DiagnosticSeverity>)> = Vec::new();
-    for chunk in buffer.snapshot().chunks(range, true) {
-        if chunks
-            .last()
-            .is_some_and(|prev_chunk| prev_chunk.1 == chunk.diagnostic_severity)
-        {
-            chunks.last_mut().unwrap().0.push_str(chunk.text);
-        } else {
-            chunks.push((chunk.text.to_string(), chunk.diagnostic_severity));
-        }
-    }
-    chunks
-}
-
-#[gpui::test(iterations = 10)]
-async fn test_definition(cx: &mut gpui::TestAppContext) {
+#[gpui::test]
+async fn test_run_terminal_command_gating(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(
-        path!("/dir"),
-        json!({
-            "a.rs": "const fn a() { A }",
-            "b.rs": "const y: i32 = crate::a()",
-        }),
-    )
-    .await;
+    let project = Project::test(fs, [], cx).await;
+    let terminal = cx.new(|cx| {
+        terminal::TerminalBuilder::new_display_only(
+            terminal::terminal_settings::CursorShape::default(),
+            terminal::terminal_settings::AlternateScroll::On,
+            None,
+            0,
+        )
+        .unwrap()
+        .subscribe(cx)
+    });
 
-    let project = Project::test(fs, [path!("/dir/b.rs").as_ref()], cx).await;
+    let run = |command: &str, cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| {
+            project.run_terminal_command(&terminal, command, Duration::from_secs(60), cx)
+        })
+    };
+    let error = run("ls", cx).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "the terminal doesn't belong to this project"
+    );
 
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(rust_lang());
-    let mut fake_servers = language_registry.register_fake_lsp("Rust", FakeLspAdapter::default());
+    project.update(cx, |project, _| {
+        project.terminals.local_handles.push(terminal.downgrade());
+    });
+    let error = run("ls\nrm -rf target", cx).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "can't run `ls\nrm -rf target` in a terminal: it spans several lines"
+    );
+    let error = run("ls", cx).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "the terminal's shell has no shell integration"
+    );
+}
 
-    let (buffer, _handle) = project
+#[cfg(not(windows))]
+#[gpui::test]
+async fn test_terminal_env_profiles(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let profile = terminal::terminal_settings::TerminalEnvProfile {
+        env: HashMap::from_iter([("NODE_ENV".to_owned(), "development".to_owned())]),
+        path_prepend: vec!["node_modules/.bin".to_owned(), "/opt/tools/bin".to_owned()],
+        activation_script: vec!["nvm use".to_owned()],
+    };
+    let mut env = HashMap::from_iter([("PATH".to_owned(), "/usr/bin".to_owned())]);
+    ProjectEnvironment::apply_terminal_env_profile(
+        &profile,
+        Some(Path::new("/project")),
+        false,
+        &mut env,
+    );
+    assert_eq!(env["NODE_ENV"], "development");
+    assert_eq!(
+        env["PATH"],
+        "/project/node_modules/.bin:/opt/tools/bin:/usr/bin"
+    );
+
+    let fs = FakeFs::new(cx.executor());
+    let project = Project::test(fs, [], cx).await;
+    let error = project
         .update(cx, |project, cx| {
-            project.open_local_buffer_with_lsp(path!("/dir/b.rs"), cx)
+            project.create_terminal_shell_with_env_profile(None, Some("node".to_owned()), cx)
         })
         .await
-        .unwrap();
+        .unwrap_err();
+    assert_eq!(error.to_string(), "unknown terminal env profile `node`");
+}
 
-    let fake_server = fake_servers.next().await.unwrap();
-    fake_server.set_request_handler::<lsp::request::GotoDefinition, _, _>(|params, _| async move {
-        let params = params.text_document_position_params;
+#[gpui::test]
+async fn test_background_task_readiness(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    let project = Project::test(fs, [], cx).await;
+    let task_store = project.read_with(cx, |project, _| project.task_store.clone());
+    let task = SpawnInTerminal {
+        id: TaskId("watch".to_string()),
+        full_label: "watch".to_string(),
+        is_background: true,
+        ready_pattern: Some("Watching for file changes".to_string()),
+        ..SpawnInTerminal::default()
+    };
+
+    let ready = task_store.update(cx, |task_store, cx| {
+        task_store.background_task_started(&task, cx);
+        task_store.wait_until_ready(&task.id, cx)
+    });
+    task_store.update(cx, |task_store, cx| {
         assert_eq!(
-            params.text_document.uri.to_file_path().unwrap(),
-            Path::new(path!("/dir/b.rs")),
+            task_store.background_task_exited(&task.id, true, cx),
+            Some(Duration::from_secs(1))
         );
-        assert_eq!(params.position, lsp::Position::new(0, 22));
-
-        Ok(Some(lsp::GotoDefinitionResponse::Scalar(
-            lsp::Location::new(
-                lsp::Uri::from_file_path(path!("/dir/a.rs")).unwrap(),
-                lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 10)),
-            ),
-        )))
+        task_store.background_task_started(&task, cx);
+        assert_eq!(
+            task_store.background_task_exited(&task.id, true, cx),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            task_store.background_task_status(&task.id),
+            Some(BackgroundTaskStatus::Restarting { attempt: 2 })
+        );
+        task_store.background_task_started(&task, cx);
+        task_store.background_task_ready(&task.id, cx);
     });
-    let mut definitions = project
-        .update(cx, |project, cx| project.definitions(&buffer, 22, cx))
-        .await
-        .unwrap()
-        .unwrap();
-
-    // Assert no new language server started
-    cx.executor().run_until_parked();
-    assert!(fake_servers.try_next().is_err());
+    ready.await.unwrap();
 
-    assert_eq!(definitions.len(), 1);
-    let definition = definitions.pop().unwrap();
-    cx.update(|cx| {
-        let target_buffer = definition.target.buffer.read(cx);
+    // Getting ready resets the backoff, and a task that stops before getting ready fails its waiters.
+    let ready = task_store.update(cx, |task_store, cx| {
         assert_eq!(
-            target_buffer
-                .file()
-                .unwrap()
-                .as_local()
-                .unwrap()
-                .abs_path(cx),
-            Path::new(path!("/dir/a.rs")),
+            task_store.background_task_exited(&task.id, true, cx),
+            Some(Duration::from_secs(1))
         );
-        assert_eq!(definition.target.range.to_offset(target_buffer), 9..10);
+        task_store.background_task_started(&task, cx);
+        let ready = task_store.wait_until_ready(&task.id, cx);
+        assert_eq!(task_store.background_task_exited(&task.id, false, cx), None);
         assert_eq!(
-            list_worktrees(&project, cx),
-            [
-                (path!("/dir/a.rs").as_ref(), false),
-                (path!("/dir/b.rs").as_ref(), true)
-            ],
+            task_store.background_task_status(&task.id),
+            Some(BackgroundTaskStatus::Stopped)
         );
-
-        drop(definition);
+        ready
     });
-    cx.update(|cx| {
+    assert!(ready.await.is_err());
+}
+
+#[gpui::test]
+async fn test_task_diagnostics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({ "a.rs": "one two three", "b.rs": "four five" }),
+    )
+    .await;
+
+    let project = Project::test(fs, [Path::new(path!("/dir"))], cx).await;
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store.clone());
+    let problem = |path: &str, severity| task::Problem {
+        path: PathBuf::from(path),
+        row: 0,
+        column: 4,
+        severity,
+        message: "something is off".to_string(),
+    };
+
+    lsp_store.update(cx, |lsp_store, cx| {
+        lsp_store
+            .update_task_diagnostics(
+                "check",
+                Some(Path::new(path!("/dir"))),
+                vec![
+                    problem("a.rs", task::ProblemSeverity::Error),
+                    problem(path!("/dir/b.rs"), task::ProblemSeverity::Warning),
+                    problem(path!("/elsewhere/c.rs"), task::ProblemSeverity::Error),
+                ],
+                cx,
+            )
+            .unwrap();
+        lsp_store
+            .update_task_diagnostics(
+                "lint",
+                Some(Path::new(path!("/dir"))),
+                vec![problem("a.rs", task::ProblemSeverity::Warning)],
+                cx,
+            )
+            .unwrap();
         assert_eq!(
-            list_worktrees(&project, cx),
-            [(path!("/dir/b.rs").as_ref(), true)]
+            lsp_store.diagnostic_summary(false, cx),
+            DiagnosticSummary {
+                error_count: 1,
+                warning_count: 2,
+            }
         );
-    });
 
-    fn list_worktrees<'a>(project: &'a Entity<Project>, cx: &'a App) -> Vec<(&'a Path, bool)> {
-        project
-            .read(cx)
-            .worktrees(cx)
-            .map(|worktree| {
-                let worktree = worktree.read(cx);
-                (
-                    worktree.as_local().unwrap().abs_path().as_ref(),
-                    worktree.is_visible(),
-                )
-            })
-            .collect::<Vec<_>>()
-    }
+        // Rerunning a task replaces its diagnostics, but not those of other tasks.
+        lsp_store
+            .update_task_diagnostics("check", Some(Path::new(path!("/dir"))), Vec::new(), cx)
+            .unwrap();
+        assert_eq!(
+            lsp_store.diagnostic_summary(false, cx),
+            DiagnosticSummary {
+                error_count: 0,
+                warning_count: 1,
+            }
+        );
+    });
 }
 
 #[gpui::test]
-async fn test_completions_with_text_edit(cx: &mut gpui::TestAppContext) {
+async fn test_edits_from_lsp2_with_past_version(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
+    let text = "
+        fn a() {
+            f1();
+        }
+        fn b() {
+            f2();
+        }
+        fn c() {
+            f3();
+        }
+    "
+    .unindent();
+
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.ts": "",
+            "a.rs": text.clone(),
         }),
     )
     .await;
 
     let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
 
     let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(typescript_lang());
-    let mut fake_language_servers = language_registry.register_fake_lsp(
-        "TypeScript",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string()]),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp("Rust", FakeLspAdapter::default());
 
     let (buffer, _handle) = project
-        .update(cx, |p, cx| {
-            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/a.rs"), cx)
         })
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
-
-    // When text_edit exists, it takes precedence over insert_text and label
-    let text = "let a = obj.fqn";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
-    });
+    let mut fake_server = fake_servers.next().await.unwrap();
+    let lsp_document_version = fake_server
+        .receive_notification::<lsp::notification::DidOpenTextDocument>()
+        .await
+        .text_document
+        .version;
 
-    fake_server
-        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "labelText".into(),
-                    insert_text: Some("insertText".into()),
-                    text_edit: Some(lsp::CompletionTextEdit::Edit(lsp::TextEdit {
-                        range: lsp::Range::new(
-                            lsp::Position::new(0, text.len() as u32 - 3),
-                            lsp::Position::new(0, text.len() as u32),
-                        ),
-                        new_text: "textEditText".into(),
-                    })),
-                    ..Default::default()
-                },
-            ])))
-        })
-        .next()
-        .await;
+    // Simulate editing the buffer after the language server computes some edits.
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit(
+            [(
+                Point::new(0, 0)..Point::new(0, 0),
+                "// above first function\n",
+            )],
+            None,
+            cx,
+        );
+        buffer.edit(
+            [(
+                Point::new(2, 0)..Point::new(2, 0),
+                "    // inside first function\n",
+            )],
+            None,
+            cx,
+        );
+        buffer.edit(
+            [(
+                Point::new(6, 4)..Point::new(6, 4),
+                "// inside second function ",
+            )],
+            None,
+            cx,
+        );
 
-    let completions = completions
+        assert_eq!(
+            buffer.text(),
+            "
+                // above first function
+                fn a() {
+                    // inside first function
+                    f1();
+                }
+                fn b() {
+                    // inside second function f2();
+                }
+                fn c() {
+                    f3();
+                }
+            "
+            .unindent()
+        );
+    });
+
+    let edits = lsp_store
+        .update(cx, |lsp_store, cx| {
+            lsp_store.as_local_mut().unwrap().edits_from_lsp(
+                &buffer,
+                vec![
+                    // replace body of first function
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(3, 0)),
+                        new_text: "
+                            fn a() {
+                                f10();
+                            }
+                            "
+                        .unindent(),
+                    },
+                    // edit inside second function
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(4, 6), lsp::Position::new(4, 6)),
+                        new_text: "00".into(),
+                    },
+                    // edit inside third function via two distinct edits
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(7, 5), lsp::Position::new(7, 5)),
+                        new_text: "4000".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(7, 5), lsp::Position::new(7, 6)),
+                        new_text: "".into(),
+                    },
+                ],
+                LanguageServerId(0),
+                Some(lsp_document_version),
+                cx,
+            )
+        })
         .await
-        .unwrap()
-        .into_iter()
-        .flat_map(|response| response.completions)
-        .collect::<Vec<_>>();
-    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+        .unwrap();
 
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "textEditText");
-    assert_eq!(
-        completions[0].replace_range.to_offset(&snapshot),
-        text.len() - 3..text.len()
-    );
+    buffer.update(cx, |buffer, cx| {
+        for (range, new_text) in edits {
+            buffer.edit([(range, new_text)], None, cx);
+        }
+        assert_eq!(
+            buffer.text(),
+            "
+                // above first function
+                fn a() {
+                    // inside first function
+                    f10();
+                }
+                fn b() {
+                    // inside second function f200();
+                }
+                fn c() {
+                    f4000();
+                }
+                "
+            .unindent()
+        );
+    });
 }
 
 #[gpui::test]
-async fn test_completions_with_edit_ranges(cx: &mut gpui::TestAppContext) {
+async fn test_edits_from_lsp2_with_edits_on_adjacent_lines(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
+    let text = "
+        use a::b;
+        use a::c;
+
+        fn f() {
+            b();
+            c();
+        }
+    "
+    .unindent();
+
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.ts": "",
+            "a.rs": text.clone(),
         }),
     )
     .await;
 
     let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(typescript_lang());
-    let mut fake_language_servers = language_registry.register_fake_lsp(
-        "TypeScript",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string()]),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
-
-    let (buffer, _handle) = project
-        .update(cx, |p, cx| {
-            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.rs"), cx)
         })
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
-    let text = "let a = obj.fqn";
-
-    // Test 1: When text_edit is None but text_edit_text exists with default edit_range
-    {
-        buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-        let completions = project.update(cx, |project, cx| {
-            project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
-        });
-
-        fake_server
-            .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
-                Ok(Some(lsp::CompletionResponse::List(lsp::CompletionList {
-                    is_incomplete: false,
-                    item_defaults: Some(lsp::CompletionListItemDefaults {
-                        edit_range: Some(lsp::CompletionListItemDefaultsEditRange::Range(
-                            lsp::Range::new(
-                                lsp::Position::new(0, text.len() as u32 - 3),
-                                lsp::Position::new(0, text.len() as u32),
-                            ),
-                        )),
-                        ..Default::default()
-                    }),
-                    items: vec![lsp::CompletionItem {
-                        label: "labelText".into(),
-                        text_edit_text: Some("textEditText".into()),
-                        text_edit: None,
-                        ..Default::default()
-                    }],
-                })))
-            })
-            .next()
-            .await;
+    // Simulate the language server sending us a small edit in the form of a very large diff.
+    // Rust-analyzer does this when performing a merge-imports code action.
+    let edits = lsp_store
+        .update(cx, |lsp_store, cx| {
+            lsp_store.as_local_mut().unwrap().edits_from_lsp(
+                &buffer,
+                [
+                    // Replace the first use statement without editing the semicolon.
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 4), lsp::Position::new(0, 8)),
+                        new_text: "a::{b, c}".into(),
+                    },
+                    // Reinsert the remainder of the file between the semicolon and the final
+                    // newline of the file.
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
+                        new_text: "\n\n".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
+                        new_text: "
+                            fn f() {
+                                b();
+                                c();
+                            }"
+                        .unindent(),
+                    },
+                    // Delete everything after the first newline of the file.
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(7, 0)),
+                        new_text: "".into(),
+                    },
+                ],
+                LanguageServerId(0),
+                None,
+                cx,
+            )
+        })
+        .await
+        .unwrap();
 
-        let completions = completions
-            .await
-            .unwrap()
+    buffer.update(cx, |buffer, cx| {
+        let edits = edits
             .into_iter()
-            .flat_map(|response| response.completions)
+            .map(|(range, text)| {
+                (
+                    range.start.to_point(buffer)..range.end.to_point(buffer),
+                    text,
+                )
+            })
             .collect::<Vec<_>>();
-        let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
 
-        assert_eq!(completions.len(), 1);
-        assert_eq!(completions[0].new_text, "textEditText");
         assert_eq!(
-            completions[0].replace_range.to_offset(&snapshot),
-            text.len() - 3..text.len()
+            edits,
+            [
+                (Point::new(0, 4)..Point::new(0, 8), "a::{b, c}".into()),
+                (Point::new(1, 0)..Point::new(2, 0), "".into())
+            ]
         );
-    }
-
-    // Test 2: When both text_edit and text_edit_text are None with default edit_range
-    {
-        buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-        let completions = project.update(cx, |project, cx| {
-            project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
-        });
-
-        fake_server
-            .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
-                Ok(Some(lsp::CompletionResponse::List(lsp::CompletionList {
-                    is_incomplete: false,
-                    item_defaults: Some(lsp::CompletionListItemDefaults {
-                        edit_range: Some(lsp::CompletionListItemDefaultsEditRange::Range(
-                            lsp::Range::new(
-                                lsp::Position::new(0, text.len() as u32 - 3),
-                                lsp::Position::new(0, text.len() as u32),
-                            ),
-                        )),
-                        ..Default::default()
-                    }),
-                    items: vec![lsp::CompletionItem {
-                        label: "labelText".into(),
-                        text_edit_text: None,
-                        insert_text: Some("irrelevant".into()),
-                        text_edit: None,
-                        ..Default::default()
-                    }],
-                })))
-            })
-            .next()
-            .await;
-
-        let completions = completions
-            .await
-            .unwrap()
-            .into_iter()
-            .flat_map(|response| response.completions)
-            .collect::<Vec<_>>();
-        let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
 
-        assert_eq!(completions.len(), 1);
-        assert_eq!(completions[0].new_text, "labelText");
+        for (range, new_text) in edits {
+            buffer.edit([(range, new_text)], None, cx);
+        }
         assert_eq!(
-            completions[0].replace_range.to_offset(&snapshot),
-            text.len() - 3..text.len()
+            buffer.text(),
+            "
+                use a::{b, c};
+
+                fn f() {
+                    b();
+                    c();
+                }
+            "
+            .unindent()
         );
-    }
+    });
 }
 
 #[gpui::test]
-async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
+async fn test_edits_from_lsp_with_replacement_followed_by_adjacent_insertion(
+    cx: &mut gpui::TestAppContext,
+) {
     init_test(cx);
 
+    let text = "Path()";
+
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.ts": "",
+            "a.rs": text
         }),
     )
     .await;
 
     let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(typescript_lang());
-    let mut fake_language_servers = language_registry.register_fake_lsp(
-        "TypeScript",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![":".to_string()]),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
-
-    let (buffer, _handle) = project
-        .update(cx, |p, cx| {
-            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.rs"), cx)
         })
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
-
-    // Test 1: When text_edit is None but insert_text exists (no edit_range in defaults)
-    let text = "let a = b.fqn";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
-    });
-
-    fake_server
-        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "fullyQualifiedName?".into(),
-                    insert_text: Some("fullyQualifiedName".into()),
-                    ..Default::default()
-                },
-            ])))
+    // Simulate the language server sending us a pair of edits at the same location,
+    // with an insertion following a replacement (which violates the LSP spec).
+    let edits = lsp_store
+        .update(cx, |lsp_store, cx| {
+            lsp_store.as_local_mut().unwrap().edits_from_lsp(
+                &buffer,
+                [
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 4)),
+                        new_text: "Path".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 0), lsp::Position::new(0, 0)),
+                        new_text: "from path import Path\n\n\n".into(),
+                    },
+                ],
+                LanguageServerId(0),
+                None,
+                cx,
+            )
         })
-        .next()
-        .await;
-    let completions = completions
         .await
-        .unwrap()
-        .into_iter()
-        .flat_map(|response| response.completions)
-        .collect::<Vec<_>>();
-    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "fullyQualifiedName");
-    assert_eq!(
-        completions[0].replace_range.to_offset(&snapshot),
-        text.len() - 3..text.len()
-    );
+        .unwrap();
 
-    // Test 2: When both text_edit and insert_text are None (no edit_range in defaults)
-    let text = "let a = \"atoms/cmp\"";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len() - 1, DEFAULT_COMPLETION_CONTEXT, cx)
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit(edits, None, cx);
+        assert_eq!(buffer.text(), "from path import Path\n\n\nPath()")
     });
-
-    fake_server
-        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "component".into(),
-                    ..Default::default()
-                },
-            ])))
-        })
-        .next()
-        .await;
-    let completions = completions
-        .await
-        .unwrap()
-        .into_iter()
-        .flat_map(|response| response.completions)
-        .collect::<Vec<_>>();
-    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "component");
-    assert_eq!(
-        completions[0].replace_range.to_offset(&snapshot),
-        text.len() - 4..text.len() - 1
-    );
 }
 
 #[gpui::test]
-async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
+async fn test_invalid_edits_from_lsp2(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
+    let text = "
+        use a::b;
+        use a::c;
+
+        fn f() {
+            b();
+            c();
+        }
+    "
+    .unindent();
+
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.ts": "",
+            "a.rs": text.clone(),
         }),
     )
     .await;
 
     let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
-
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(typescript_lang());
-    let mut fake_language_servers = language_registry.register_fake_lsp(
-        "TypeScript",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![":".to_string()]),
-                    ..Default::default()
-                }),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
-
-    let (buffer, _handle) = project
-        .update(cx, |p, cx| {
-            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.rs"), cx)
         })
         .await
         .unwrap();
 
-    let fake_server = fake_language_servers.next().await.unwrap();
-
-    let text = "let a = b.fqn";
-    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
-    let completions = project.update(cx, |project, cx| {
-        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
-    });
-
-    fake_server
-        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
-            Ok(Some(lsp::CompletionResponse::Array(vec![
-                lsp::CompletionItem {
-                    label: "fullyQualifiedName?".into(),
-                    insert_text: Some("fully\rQualified\r\nName".into()),
-                    ..Default::default()
-                },
-            ])))
-        })
-        .next()
-        .await;
-    let completions = completions
-        .await
-        .unwrap()
-        .into_iter()
-        .flat_map(|response| response.completions)
-        .collect::<Vec<_>>();
-    assert_eq!(completions.len(), 1);
-    assert_eq!(completions[0].new_text, "fully\nQualified\nName");
-}
-
-#[gpui::test(iterations = 10)]
-async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
+    // Simulate the language server sending us edits in a non-ordered fashion,
+    // with ranges sometimes being inverted or pointing to invalid locations.
+    let edits = lsp_store
+        .update(cx, |lsp_store, cx| {
+            lsp_store.as_local_mut().unwrap().edits_from_lsp(
+                &buffer,
+                [
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
+                        new_text: "\n\n".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 8), lsp::Position::new(0, 4)),
+                        new_text: "a::{b, c}".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(1, 0), lsp::Position::new(99, 0)),
+                        new_text: "".into(),
+                    },
+                    lsp::TextEdit {
+                        range: lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 9)),
+                        new_text: "
+                            fn f() {
+                                b();
+                                c();
+                            }"
+                        .unindent(),
+                    },
+                ],
+                LanguageServerId(0),
+                None,
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+
+    buffer.update(cx, |buffer, cx| {
+        let edits = edits
+            .into_iter()
+            .map(|(range, text)| {
+                (
+                    range.start.to_point(buffer)..range.end.to_point(buffer),
+                    text,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            edits,
+            [
+                (Point::new(0, 4)..Point::new(0, 8), "a::{b, c}".into()),
+                (Point::new(1, 0)..Point::new(2, 0), "".into())
+            ]
+        );
+
+        for (range, new_text) in edits {
+            buffer.edit([(range, new_text)], None, cx);
+        }
+        assert_eq!(
+            buffer.text(),
+            "
+                use a::{b, c};
+
+                fn f() {
+                    b();
+                    c();
+                }
+            "
+            .unindent()
+        );
+    });
+}
+
+fn chunks_with_diagnostics<T: ToOffset + ToPoint>(
+    buffer: &Buffer,
+    range: Range<T>,
+) -> Vec<(String, Option<DiagnosticSeverity>)> {
+    let mut chunks: Vec<(String, Option<DiagnosticSeverity>)> = Vec::new();
+    for chunk in buffer.snapshot().chunks(range, true) {
+        if chunks
+            .last()
+            .is_some_and(|prev_chunk| prev_chunk.1 == chunk.diagnostic_severity)
+        {
+            chunks.last_mut().unwrap().0.push_str(chunk.text);
+        } else {
+            chunks.push((chunk.text.to_string(), chunk.diagnostic_severity));
+        }
+    }
+    chunks
+}
+
+#[gpui::test(iterations = 10)]
+async fn test_definition(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.ts": "a",
+            "a.rs": "const fn a() { A }",
+            "b.rs": "const y: i32 = crate::a()",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [path!("/dir/b.rs").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp("Rust", FakeLspAdapter::default());
+
+    let (buffer, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/b.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    fake_server.set_request_handler::<lsp::request::GotoDefinition, _, _>(|params, _| async move {
+        let params = params.text_document_position_params;
+        assert_eq!(
+            params.text_document.uri.to_file_path().unwrap(),
+            Path::new(path!("/dir/b.rs")),
+        );
+        assert_eq!(params.position, lsp::Position::new(0, 22));
+
+        Ok(Some(lsp::GotoDefinitionResponse::Scalar(
+            lsp::Location::new(
+                lsp::Uri::from_file_path(path!("/dir/a.rs")).unwrap(),
+                lsp::Range::new(lsp::Position::new(0, 9), lsp::Position::new(0, 10)),
+            ),
+        )))
+    });
+    let mut definitions = project
+        .update(cx, |project, cx| project.definitions(&buffer, 22, cx))
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Assert no new language server started
+    cx.executor().run_until_parked();
+    assert!(fake_servers.try_next().is_err());
+
+    assert_eq!(definitions.len(), 1);
+    let definition = definitions.pop().unwrap();
+    cx.update(|cx| {
+        let target_buffer = definition.target.buffer.read(cx);
+        assert_eq!(
+            target_buffer
+                .file()
+                .unwrap()
+                .as_local()
+                .unwrap()
+                .abs_path(cx),
+            Path::new(path!("/dir/a.rs")),
+        );
+        assert_eq!(definition.target.range.to_offset(target_buffer), 9..10);
+        assert_eq!(
+            list_worktrees(&project, cx),
+            [
+                (path!("/dir/a.rs").as_ref(), false),
+                (path!("/dir/b.rs").as_ref(), true)
+            ],
+        );
+
+        drop(definition);
+    });
+    cx.update(|cx| {
+        assert_eq!(
+            list_worktrees(&project, cx),
+            [(path!("/dir/b.rs").as_ref(), true)]
+        );
+    });
+
+    fn list_worktrees<'a>(project: &'a Entity<Project>, cx: &'a App) -> Vec<(&'a Path, bool)> {
+        project
+            .read(cx)
+            .worktrees(cx)
+            .map(|worktree| {
+                let worktree = worktree.read(cx);
+                (
+                    worktree.as_local().unwrap().abs_path().as_ref(),
+                    worktree.is_visible(),
+                )
+            })
+            .collect::<Vec<_>>()
+    }
+}
+
+#[gpui::test]
+async fn test_completions_with_text_edit(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.ts": "",
         }),
     )
     .await;
@@ -3839,19 +4184,13 @@ async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
         "TypeScript",
         FakeLspAdapter {
             capabilities: lsp::ServerCapabilities {
-                code_action_provider: Some(lsp::CodeActionProviderCapability::Options(
-                    lsp::CodeActionOptions {
-                        resolve_provider: Some(true),
-                        ..lsp::CodeActionOptions::default()
-                    },
-                )),
-                execute_command_provider: Some(lsp::ExecuteCommandOptions {
-                    commands: vec!["_the/command".to_string()],
-                    ..lsp::ExecuteCommandOptions::default()
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![".".to_string()]),
+                    ..Default::default()
                 }),
-                ..lsp::ServerCapabilities::default()
+                ..Default::default()
             },
-            ..FakeLspAdapter::default()
+            ..Default::default()
         },
     );
 
@@ -3864,2647 +4203,5191 @@ async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
 
     let fake_server = fake_language_servers.next().await.unwrap();
 
-    // Language server returns code actions that contain commands, and not edits.
-    let actions = project.update(cx, |project, cx| {
-        project.code_actions(&buffer, 0..0, None, cx)
+    // When text_edit exists, it takes precedence over insert_text and label
+    let text = "let a = obj.fqn";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
     });
+
     fake_server
-        .set_request_handler::<lsp::request::CodeActionRequest, _, _>(|_, _| async move {
-            Ok(Some(vec![
-                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
-                    title: "The code action".into(),
-                    data: Some(serde_json::json!({
-                        "command": "_the/command",
+        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "labelText".into(),
+                    insert_text: Some("insertText".into()),
+                    text_edit: Some(lsp::CompletionTextEdit::Edit(lsp::TextEdit {
+                        range: lsp::Range::new(
+                            lsp::Position::new(0, text.len() as u32 - 3),
+                            lsp::Position::new(0, text.len() as u32),
+                        ),
+                        new_text: "textEditText".into(),
                     })),
-                    ..lsp::CodeAction::default()
-                }),
-                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
-                    title: "two".into(),
-                    ..lsp::CodeAction::default()
-                }),
-            ]))
+                    ..Default::default()
+                },
+            ])))
         })
         .next()
         .await;
 
-    let action = actions.await.unwrap().unwrap()[0].clone();
-    let apply = project.update(cx, |project, cx| {
-        project.apply_code_action(buffer.clone(), action, true, cx)
-    });
-
-    // Resolving the code action does not populate its edits. In absence of
-    // edits, we must execute the given command.
-    fake_server.set_request_handler::<lsp::request::CodeActionResolveRequest, _, _>(
-        |mut action, _| async move {
-            if action.data.is_some() {
-                action.command = Some(lsp::Command {
-                    title: "The command".into(),
-                    command: "_the/command".into(),
-                    arguments: Some(vec![json!("the-argument")]),
-                });
-            }
-            Ok(action)
-        },
-    );
-
-    // While executing the command, the language server sends the editor
-    // a `workspaceEdit` request.
-    fake_server
-        .set_request_handler::<lsp::request::ExecuteCommand, _, _>({
-            let fake = fake_server.clone();
-            move |params, _| {
-                assert_eq!(params.command, "_the/command");
-                let fake = fake.clone();
-                async move {
-                    fake.server
-                        .request::<lsp::request::ApplyWorkspaceEdit>(
-                            lsp::ApplyWorkspaceEditParams {
-                                label: None,
-                                edit: lsp::WorkspaceEdit {
-                                    changes: Some(
-                                        [(
-                                            lsp::Uri::from_file_path(path!("/dir/a.ts")).unwrap(),
-                                            vec![lsp::TextEdit {
-                                                range: lsp::Range::new(
-                                                    lsp::Position::new(0, 0),
-                                                    lsp::Position::new(0, 0),
-                                                ),
-                                                new_text: "X".into(),
-                                            }],
-                                        )]
-                                        .into_iter()
-                                        .collect(),
-                                    ),
-                                    ..Default::default()
-                                },
-                            },
-                        )
-                        .await
-                        .into_response()
-                        .unwrap();
-                    Ok(Some(json!(null)))
-                }
-            }
-        })
-        .next()
-        .await;
-
-    // Applying the code action returns a project transaction containing the edits
-    // sent by the language server in its `workspaceEdit` request.
-    let transaction = apply.await.unwrap();
-    assert!(transaction.0.contains_key(&buffer));
-    buffer.update(cx, |buffer, cx| {
-        assert_eq!(buffer.text(), "Xa");
-        buffer.undo(cx);
-        assert_eq!(buffer.text(), "a");
-    });
-}
-
-#[gpui::test]
-async fn test_rename_file_to_new_directory(cx: &mut gpui::TestAppContext) {
-    init_test(cx);
-    let fs = FakeFs::new(cx.background_executor.clone());
-    let expected_contents = "content";
-    fs.as_fake()
-        .insert_tree(
-            "/root",
-            json!({
-                "test.txt": expected_contents
-            }),
-        )
-        .await;
-
-    let project = Project::test(fs, [path!("/root").as_ref()], cx).await;
-
-    let (worktree, entry_id) = project.read_with(cx, |project, cx| {
-        let worktree = project.worktrees(cx).next().unwrap();
-        let entry_id = worktree
-            .read(cx)
-            .entry_for_path(rel_path("test.txt"))
-            .unwrap()
-            .id;
-        (worktree, entry_id)
-    });
-    let worktree_id = worktree.read_with(cx, |worktree, _| worktree.id());
-    let _result = project
-        .update(cx, |project, cx| {
-            project.rename_entry(
-                entry_id,
-                (worktree_id, rel_path("dir1/dir2/dir3/test.txt")).into(),
-                cx,
-            )
-        })
+    let completions = completions
         .await
-        .unwrap();
-    worktree.read_with(cx, |worktree, _| {
-        assert!(
-            worktree.entry_for_path(rel_path("test.txt")).is_none(),
-            "Old file should have been removed"
-        );
-        assert!(
-            worktree
-                .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
-                .is_some(),
-            "Whole directory hierarchy and the new file should have been created"
-        );
-    });
-    assert_eq!(
-        worktree
-            .update(cx, |worktree, cx| {
-                worktree.load_file(rel_path("dir1/dir2/dir3/test.txt"), cx)
-            })
-            .await
-            .unwrap()
-            .text,
-        expected_contents,
-        "Moved file's contents should be preserved"
-    );
-
-    let entry_id = worktree.read_with(cx, |worktree, _| {
-        worktree
-            .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
-            .unwrap()
-            .id
-    });
+        .unwrap()
+        .into_iter()
+        .flat_map(|response| response.completions)
+        .collect::<Vec<_>>();
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
 
-    let _result = project
-        .update(cx, |project, cx| {
-            project.rename_entry(
-                entry_id,
-                (worktree_id, rel_path("dir1/dir2/test.txt")).into(),
-                cx,
-            )
-        })
-        .await
-        .unwrap();
-    worktree.read_with(cx, |worktree, _| {
-        assert!(
-            worktree.entry_for_path(rel_path("test.txt")).is_none(),
-            "First file should not reappear"
-        );
-        assert!(
-            worktree
-                .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
-                .is_none(),
-            "Old file should have been removed"
-        );
-        assert!(
-            worktree
-                .entry_for_path(rel_path("dir1/dir2/test.txt"))
-                .is_some(),
-            "No error should have occurred after moving into existing directory"
-        );
-    });
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "textEditText");
     assert_eq!(
-        worktree
-            .update(cx, |worktree, cx| {
-                worktree.load_file(rel_path("dir1/dir2/test.txt"), cx)
-            })
-            .await
-            .unwrap()
-            .text,
-        expected_contents,
-        "Moved file's contents should be preserved"
+        completions[0].replace_range.to_offset(&snapshot),
+        text.len() - 3..text.len()
     );
 }
 
-#[gpui::test(iterations = 10)]
-async fn test_save_file(cx: &mut gpui::TestAppContext) {
+#[gpui::test]
+async fn test_completions_with_edit_ranges(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "file1": "the old contents",
+            "a.ts": "",
         }),
     )
     .await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
-        .await
-        .unwrap();
-    buffer.update(cx, |buffer, cx| {
-        assert_eq!(buffer.text(), "the old contents");
-        buffer.edit([(0..0, "a line of text.\n".repeat(10 * 1024))], None, cx);
-    });
-
-    project
-        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
-        .await
-        .unwrap();
-
-    let new_text = fs
-        .load(Path::new(path!("/dir/file1")))
-        .await
-        .unwrap()
-        .replace("\r\n", "\n");
-    assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
-}
-
-#[gpui::test(iterations = 10)]
-async fn test_save_file_spawns_language_server(cx: &mut gpui::TestAppContext) {
-    // Issue: #24349
-    init_test(cx);
-
-    let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(path!("/dir"), json!({})).await;
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
     let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-
-    language_registry.add(rust_lang());
-    let mut fake_rust_servers = language_registry.register_fake_lsp(
-        "Rust",
+    language_registry.add(typescript_lang());
+    let mut fake_language_servers = language_registry.register_fake_lsp(
+        "TypeScript",
         FakeLspAdapter {
-            name: "the-rust-language-server",
             capabilities: lsp::ServerCapabilities {
                 completion_provider: Some(lsp::CompletionOptions {
-                    trigger_characters: Some(vec![".".to_string(), "::".to_string()]),
+                    trigger_characters: Some(vec![".".to_string()]),
                     ..Default::default()
                 }),
-                text_document_sync: Some(lsp::TextDocumentSyncCapability::Options(
-                    lsp::TextDocumentSyncOptions {
-                        save: Some(lsp::TextDocumentSyncSaveOptions::Supported(true)),
-                        ..Default::default()
-                    },
-                )),
                 ..Default::default()
             },
             ..Default::default()
         },
     );
 
-    let buffer = project
-        .update(cx, |this, cx| this.create_buffer(false, cx))
-        .unwrap()
-        .await;
-    project.update(cx, |this, cx| {
-        this.register_buffer_with_language_servers(&buffer, cx);
-        buffer.update(cx, |buffer, cx| {
-            assert!(!this.has_language_servers_for(buffer, cx));
-        })
-    });
-
-    project
-        .update(cx, |this, cx| {
-            let worktree_id = this.worktrees(cx).next().unwrap().read(cx).id();
-            this.save_buffer_as(
-                buffer.clone(),
-                ProjectPath {
-                    worktree_id,
-                    path: rel_path("file.rs").into(),
-                },
-                cx,
-            )
+    let (buffer, _handle) = project
+        .update(cx, |p, cx| {
+            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
         })
         .await
         .unwrap();
-    // A server is started up, and it is notified about Rust files.
-    let mut fake_rust_server = fake_rust_servers.next().await.unwrap();
-    assert_eq!(
-        fake_rust_server
-            .receive_notification::<lsp::notification::DidOpenTextDocument>()
+
+    let fake_server = fake_language_servers.next().await.unwrap();
+    let text = "let a = obj.fqn";
+
+    // Test 1: When text_edit is None but text_edit_text exists with default edit_range
+    {
+        buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+        let completions = project.update(cx, |project, cx| {
+            project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
+        });
+
+        fake_server
+            .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
+                Ok(Some(lsp::CompletionResponse::List(lsp::CompletionList {
+                    is_incomplete: false,
+                    item_defaults: Some(lsp::CompletionListItemDefaults {
+                        edit_range: Some(lsp::CompletionListItemDefaultsEditRange::Range(
+                            lsp::Range::new(
+                                lsp::Position::new(0, text.len() as u32 - 3),
+                                lsp::Position::new(0, text.len() as u32),
+                            ),
+                        )),
+                        ..Default::default()
+                    }),
+                    items: vec![lsp::CompletionItem {
+                        label: "labelText".into(),
+                        text_edit_text: Some("textEditText".into()),
+                        text_edit: None,
+                        ..Default::default()
+                    }],
+                })))
+            })
+            .next()
+            .await;
+
+        let completions = completions
             .await
-            .text_document,
-        lsp::TextDocumentItem {
-            uri: lsp::Uri::from_file_path(path!("/dir/file.rs")).unwrap(),
-            version: 0,
-            text: "".to_string(),
-            language_id: "rust".to_string(),
-        }
-    );
+            .unwrap()
+            .into_iter()
+            .flat_map(|response| response.completions)
+            .collect::<Vec<_>>();
+        let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
 
-    project.update(cx, |this, cx| {
-        buffer.update(cx, |buffer, cx| {
-            assert!(this.has_language_servers_for(buffer, cx));
-        })
-    });
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].new_text, "textEditText");
+        assert_eq!(
+            completions[0].replace_range.to_offset(&snapshot),
+            text.len() - 3..text.len()
+        );
+    }
+
+    // Test 2: When both text_edit and text_edit_text are None with default edit_range
+    {
+        buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+        let completions = project.update(cx, |project, cx| {
+            project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
+        });
+
+        fake_server
+            .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async {
+                Ok(Some(lsp::CompletionResponse::List(lsp::CompletionList {
+                    is_incomplete: false,
+                    item_defaults: Some(lsp::CompletionListItemDefaults {
+                        edit_range: Some(lsp::CompletionListItemDefaultsEditRange::Range(
+                            lsp::Range::new(
+                                lsp::Position::new(0, text.len() as u32 - 3),
+                                lsp::Position::new(0, text.len() as u32),
+                            ),
+                        )),
+                        ..Default::default()
+                    }),
+                    items: vec![lsp::CompletionItem {
+                        label: "labelText".into(),
+                        text_edit_text: None,
+                        insert_text: Some("irrelevant".into()),
+                        text_edit: None,
+                        ..Default::default()
+                    }],
+                })))
+            })
+            .next()
+            .await;
+
+        let completions = completions
+            .await
+            .unwrap()
+            .into_iter()
+            .flat_map(|response| response.completions)
+            .collect::<Vec<_>>();
+        let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].new_text, "labelText");
+        assert_eq!(
+            completions[0].replace_range.to_offset(&snapshot),
+            text.len() - 3..text.len()
+        );
+    }
 }
 
-#[gpui::test(iterations = 30)]
-async fn test_file_changes_multiple_times_on_disk(cx: &mut gpui::TestAppContext) {
+#[gpui::test]
+async fn test_completions_without_edit_ranges(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "file1": "the original contents",
+            "a.ts": "",
         }),
     )
     .await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let worktree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
-    let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(typescript_lang());
+    let mut fake_language_servers = language_registry.register_fake_lsp(
+        "TypeScript",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![":".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    let (buffer, _handle) = project
+        .update(cx, |p, cx| {
+            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
+        })
         .await
         .unwrap();
 
-    // Simulate buffer diffs being slow, so that they don't complete before
-    // the next file change occurs.
-    cx.executor().deprioritize(*language::BUFFER_DIFF_TASK);
+    let fake_server = fake_language_servers.next().await.unwrap();
 
-    // Change the buffer's file on disk, and then wait for the file change
-    // to be detected by the worktree, so that the buffer starts reloading.
-    fs.save(
-        path!("/dir/file1").as_ref(),
-        &"the first contents".into(),
-        Default::default(),
-    )
-    .await
-    .unwrap();
-    worktree.next_event(cx).await;
+    // Test 1: When text_edit is None but insert_text exists (no edit_range in defaults)
+    let text = "let a = b.fqn";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
+    });
 
-    // Change the buffer's file again. Depending on the random seed, the
-    // previous file change may still be in progress.
-    fs.save(
-        path!("/dir/file1").as_ref(),
-        &"the second contents".into(),
-        Default::default(),
-    )
-    .await
-    .unwrap();
-    worktree.next_event(cx).await;
+    fake_server
+        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "fullyQualifiedName?".into(),
+                    insert_text: Some("fullyQualifiedName".into()),
+                    ..Default::default()
+                },
+            ])))
+        })
+        .next()
+        .await;
+    let completions = completions
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|response| response.completions)
+        .collect::<Vec<_>>();
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "fullyQualifiedName");
+    assert_eq!(
+        completions[0].replace_range.to_offset(&snapshot),
+        text.len() - 3..text.len()
+    );
 
-    cx.executor().run_until_parked();
-    let on_disk_text = fs.load(Path::new(path!("/dir/file1"))).await.unwrap();
-    buffer.read_with(cx, |buffer, _| {
-        assert_eq!(buffer.text(), on_disk_text);
-        assert!(!buffer.is_dirty(), "buffer should not be dirty");
-        assert!(!buffer.has_conflict(), "buffer should not be dirty");
+    // Test 2: When both text_edit and insert_text are None (no edit_range in defaults)
+    let text = "let a = \"atoms/cmp\"";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len() - 1, DEFAULT_COMPLETION_CONTEXT, cx)
     });
+
+    fake_server
+        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "component".into(),
+                    ..Default::default()
+                },
+            ])))
+        })
+        .next()
+        .await;
+    let completions = completions
+        .await
+        .unwrap()
+        .into_iter()
+        .flat_map(|response| response.completions)
+        .collect::<Vec<_>>();
+    let snapshot = buffer.update(cx, |buffer, _| buffer.snapshot());
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "component");
+    assert_eq!(
+        completions[0].replace_range.to_offset(&snapshot),
+        text.len() - 4..text.len() - 1
+    );
 }
 
-#[gpui::test(iterations = 30)]
-async fn test_edit_buffer_while_it_reloads(cx: &mut gpui::TestAppContext) {
+#[gpui::test]
+async fn test_completions_with_carriage_returns(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "file1": "the original contents",
+            "a.ts": "",
         }),
     )
     .await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let worktree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
-    let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
-        .await
-        .unwrap();
-
-    // Simulate buffer diffs being slow, so that they don't complete before
-    // the next file change occurs.
-    cx.executor().deprioritize(*language::BUFFER_DIFF_TASK);
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
 
-    // Change the buffer's file on disk, and then wait for the file change
-    // to be detected by the worktree, so that the buffer starts reloading.
-    fs.save(
-        path!("/dir/file1").as_ref(),
-        &"the first contents".into(),
-        Default::default(),
-    )
-    .await
-    .unwrap();
-    worktree.next_event(cx).await;
-
-    cx.executor()
-        .spawn(cx.executor().simulate_random_delay())
-        .await;
-
-    // Perform a noop edit, causing the buffer's version to increase.
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit([(0..0, " ")], None, cx);
-        buffer.undo(cx);
-    });
-
-    cx.executor().run_until_parked();
-    let on_disk_text = fs.load(Path::new(path!("/dir/file1"))).await.unwrap();
-    buffer.read_with(cx, |buffer, _| {
-        let buffer_text = buffer.text();
-        if buffer_text == on_disk_text {
-            assert!(
-                !buffer.is_dirty() && !buffer.has_conflict(),
-                "buffer shouldn't be dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}",
-            );
-        }
-        // If the file change occurred while the buffer was processing the first
-        // change, the buffer will be in a conflicting state.
-        else {
-            assert!(buffer.is_dirty(), "buffer should report that it is dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}");
-            assert!(buffer.has_conflict(), "buffer should report that it is dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}");
-        }
-    });
-}
-
-#[gpui::test]
-async fn test_save_in_single_file_worktree(cx: &mut gpui::TestAppContext) {
-    init_test(cx);
-
-    let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(
-        path!("/dir"),
-        json!({
-            "file1": "the old contents",
-        }),
-    )
-    .await;
-
-    let project = Project::test(fs.clone(), [path!("/dir/file1").as_ref()], cx).await;
-    let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
-        .await
-        .unwrap();
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit([(0..0, "a line of text.\n".repeat(10 * 1024))], None, cx);
-    });
-
-    project
-        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
-        .await
-        .unwrap();
-
-    let new_text = fs
-        .load(Path::new(path!("/dir/file1")))
-        .await
-        .unwrap()
-        .replace("\r\n", "\n");
-    assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
-}
-
-#[gpui::test]
-async fn test_save_as(cx: &mut gpui::TestAppContext) {
-    init_test(cx);
-
-    let fs = FakeFs::new(cx.executor());
-    fs.insert_tree("/dir", json!({})).await;
-
-    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
-
-    let languages = project.update(cx, |project, _| project.languages().clone());
-    languages.add(rust_lang());
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(typescript_lang());
+    let mut fake_language_servers = language_registry.register_fake_lsp(
+        "TypeScript",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![":".to_string()]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
 
-    let buffer = project.update(cx, |project, cx| {
-        project.create_local_buffer("", None, false, cx)
-    });
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit([(0..0, "abc")], None, cx);
-        assert!(buffer.is_dirty());
-        assert!(!buffer.has_conflict());
-        assert_eq!(buffer.language().unwrap().name(), "Plain Text".into());
-    });
-    project
-        .update(cx, |project, cx| {
-            let worktree_id = project.worktrees(cx).next().unwrap().read(cx).id();
-            let path = ProjectPath {
-                worktree_id,
-                path: rel_path("file1.rs").into(),
-            };
-            project.save_buffer_as(buffer.clone(), path, cx)
+    let (buffer, _handle) = project
+        .update(cx, |p, cx| {
+            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
         })
         .await
         .unwrap();
-    assert_eq!(fs.load(Path::new("/dir/file1.rs")).await.unwrap(), "abc");
 
-    cx.executor().run_until_parked();
-    buffer.update(cx, |buffer, cx| {
-        assert_eq!(
-            buffer.file().unwrap().full_path(cx),
-            Path::new("dir/file1.rs")
-        );
-        assert!(!buffer.is_dirty());
-        assert!(!buffer.has_conflict());
-        assert_eq!(buffer.language().unwrap().name(), "Rust".into());
+    let fake_server = fake_language_servers.next().await.unwrap();
+
+    let text = "let a = b.fqn";
+    buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+    let completions = project.update(cx, |project, cx| {
+        project.completions(&buffer, text.len(), DEFAULT_COMPLETION_CONTEXT, cx)
     });
 
-    let opened_buffer = project
-        .update(cx, |project, cx| {
-            project.open_local_buffer("/dir/file1.rs", cx)
+    fake_server
+        .set_request_handler::<lsp::request::Completion, _, _>(|_, _| async move {
+            Ok(Some(lsp::CompletionResponse::Array(vec![
+                lsp::CompletionItem {
+                    label: "fullyQualifiedName?".into(),
+                    insert_text: Some("fully\rQualified\r\nName".into()),
+                    ..Default::default()
+                },
+            ])))
         })
+        .next()
+        .await;
+    let completions = completions
         .await
-        .unwrap();
-    assert_eq!(opened_buffer, buffer);
+        .unwrap()
+        .into_iter()
+        .flat_map(|response| response.completions)
+        .collect::<Vec<_>>();
+    assert_eq!(completions.len(), 1);
+    assert_eq!(completions[0].new_text, "fully\nQualified\nName");
 }
 
-#[gpui::test]
-async fn test_save_as_existing_file(cx: &mut gpui::TestAppContext) {
+#[gpui::test(iterations = 10)]
+async fn test_apply_code_actions_with_commands(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-
     fs.insert_tree(
         path!("/dir"),
         json!({
-                "data_a.txt": "data about a"
+            "a.ts": "a",
         }),
     )
     .await;
 
-    let buffer = project
-        .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/data_a.txt"), cx)
-        })
-        .await
-        .unwrap();
-
-    buffer.update(cx, |buffer, cx| {
-        buffer.edit([(11..12, "b")], None, cx);
-    });
+    let project = Project::test(fs, [path!("/dir").as_ref()], cx).await;
 
-    // Save buffer's contents as a new file and confirm that the buffer's now
-    // associated with `data_b.txt` instead of `data_a.txt`, confirming that the
-    // file associated with the buffer has now been updated to `data_b.txt`
-    project
-        .update(cx, |project, cx| {
-            let worktree_id = project.worktrees(cx).next().unwrap().read(cx).id();
-            let new_path = ProjectPath {
-                worktree_id,
-                path: rel_path("data_b.txt").into(),
-            };
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(typescript_lang());
+    let mut fake_language_servers = language_registry.register_fake_lsp(
+        "TypeScript",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                code_action_provider: Some(lsp::CodeActionProviderCapability::Options(
+                    lsp::CodeActionOptions {
+                        resolve_provider: Some(true),
+                        ..lsp::CodeActionOptions::default()
+                    },
+                )),
+                execute_command_provider: Some(lsp::ExecuteCommandOptions {
+                    commands: vec!["_the/command".to_string()],
+                    ..lsp::ExecuteCommandOptions::default()
+                }),
+                ..lsp::ServerCapabilities::default()
+            },
+            ..FakeLspAdapter::default()
+        },
+    );
 
-            project.save_buffer_as(buffer.clone(), new_path, cx)
+    let (buffer, _handle) = project
+        .update(cx, |p, cx| {
+            p.open_local_buffer_with_lsp(path!("/dir/a.ts"), cx)
         })
         .await
         .unwrap();
 
-    buffer.update(cx, |buffer, cx| {
-        assert_eq!(
-            buffer.file().unwrap().full_path(cx),
-            Path::new("dir/data_b.txt")
-        )
-    });
+    let fake_server = fake_language_servers.next().await.unwrap();
 
-    // Open the original `data_a.txt` file, confirming that its contents are
-    // unchanged and the resulting buffer's associated file is `data_a.txt`.
-    let original_buffer = project
-        .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/data_a.txt"), cx)
+    // Language server returns code actions that contain commands, and not edits.
+    let actions = project.update(cx, |project, cx| {
+        project.code_actions(&buffer, 0..0, None, cx)
+    });
+    fake_server
+        .set_request_handler::<lsp::request::CodeActionRequest, _, _>(|_, _| async move {
+            Ok(Some(vec![
+                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                    title: "The code action".into(),
+                    data: Some(serde_json::json!({
+                        "command": "_the/command",
+                    })),
+                    ..lsp::CodeAction::default()
+                }),
+                lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+                    title: "two".into(),
+                    ..lsp::CodeAction::default()
+                }),
+            ]))
         })
-        .await
-        .unwrap();
+        .next()
+        .await;
 
-    original_buffer.update(cx, |buffer, cx| {
-        assert_eq!(buffer.text(), "data about a");
-        assert_eq!(
-            buffer.file().unwrap().full_path(cx),
-            Path::new("dir/data_a.txt")
-        )
+    let action = actions.await.unwrap().unwrap()[0].clone();
+    let apply = project.update(cx, |project, cx| {
+        project.apply_code_action(buffer.clone(), action, true, cx)
     });
-}
-
-#[gpui::test(retries = 5)]
-async fn test_rescan_and_remote_updates(cx: &mut gpui::TestAppContext) {
-    use worktree::WorktreeModelHandle as _;
-
-    init_test(cx);
-    cx.executor().allow_parking();
 
-    let dir = TempTree::new(json!({
-        "a": {
-            "file1": "",
-            "file2": "",
-            "file3": "",
-        },
-        "b": {
-            "c": {
-                "file4": "",
-                "file5": "",
+    // Resolving the code action does not populate its edits. In absence of
+    // edits, we must execute the given command.
+    fake_server.set_request_handler::<lsp::request::CodeActionResolveRequest, _, _>(
+        |mut action, _| async move {
+            if action.data.is_some() {
+                action.command = Some(lsp::Command {
+                    title: "The command".into(),
+                    command: "_the/command".into(),
+                    arguments: Some(vec![json!("the-argument")]),
+                });
             }
-        }
-    }));
-
-    let project = Project::test(Arc::new(RealFs::new(None, cx.executor())), [dir.path()], cx).await;
+            Ok(action)
+        },
+    );
 
-    let buffer_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
-        let buffer = project.update(cx, |p, cx| p.open_local_buffer(dir.path().join(path), cx));
-        async move { buffer.await.unwrap() }
-    };
-    let id_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
-        project.update(cx, |project, cx| {
-            let tree = project.worktrees(cx).next().unwrap();
-            tree.read(cx)
-                .entry_for_path(rel_path(path))
-                .unwrap_or_else(|| panic!("no entry for path {}", path))
-                .id
+    // While executing the command, the language server sends the editor
+    // a `workspaceEdit` request.
+    fake_server
+        .set_request_handler::<lsp::request::ExecuteCommand, _, _>({
+            let fake = fake_server.clone();
+            move |params, _| {
+                assert_eq!(params.command, "_the/command");
+                let fake = fake.clone();
+                async move {
+                    fake.server
+                        .request::<lsp::request::ApplyWorkspaceEdit>(
+                            lsp::ApplyWorkspaceEditParams {
+                                label: None,
+                                edit: lsp::WorkspaceEdit {
+                                    changes: Some(
+                                        [(
+                                            lsp::Uri::from_file_path(path!("/dir/a.ts")).unwrap(),
+                                            vec![lsp::TextEdit {
+                                                range: lsp::Range::new(
+                                                    lsp::Position::new(0, 0),
+                                                    lsp::Position::new(0, 0),
+                                                ),
+                                                new_text: "X".into(),
+                                            }],
+                                        )]
+                                        .into_iter()
+                                        .collect(),
+                                    ),
+                                    ..Default::default()
+                                },
+                            },
+                        )
+                        .await
+                        .into_response()
+                        .unwrap();
+                    Ok(Some(json!(null)))
+                }
+            }
         })
-    };
-
-    let buffer2 = buffer_for_path("a/file2", cx).await;
-    let buffer3 = buffer_for_path("a/file3", cx).await;
-    let buffer4 = buffer_for_path("b/c/file4", cx).await;
-    let buffer5 = buffer_for_path("b/c/file5", cx).await;
-
-    let file2_id = id_for_path("a/file2", cx);
-    let file3_id = id_for_path("a/file3", cx);
-    let file4_id = id_for_path("b/c/file4", cx);
-
-    // Create a remote copy of this worktree.
-    let tree = project.update(cx, |project, cx| project.worktrees(cx).next().unwrap());
-    let metadata = tree.update(cx, |tree, _| tree.metadata_proto());
+        .next()
+        .await;
 
-    let updates = Arc::new(Mutex::new(Vec::new()));
-    tree.update(cx, |tree, cx| {
-        let updates = updates.clone();
-        tree.observe_updates(0, cx, move |update| {
-            updates.lock().push(update);
-            async { true }
-        });
+    // Applying the code action returns a project transaction containing the edits
+    // sent by the language server in its `workspaceEdit` request.
+    let transaction = apply.await.unwrap();
+    assert!(transaction.0.contains_key(&buffer));
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), "Xa");
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "a");
     });
+}
 
-    let remote = cx.update(|cx| {
-        Worktree::remote(
-            0,
-            ReplicaId::REMOTE_SERVER,
-            metadata,
-            project.read(cx).client().into(),
-            project.read(cx).path_style(cx),
-            cx,
+#[gpui::test]
+async fn test_rename_file_to_new_directory(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    let expected_contents = "content";
+    fs.as_fake()
+        .insert_tree(
+            "/root",
+            json!({
+                "test.txt": expected_contents
+            }),
         )
-    });
+        .await;
 
-    cx.executor().run_until_parked();
+    let project = Project::test(fs, [path!("/root").as_ref()], cx).await;
 
-    cx.update(|cx| {
-        assert!(!buffer2.read(cx).is_dirty());
-        assert!(!buffer3.read(cx).is_dirty());
-        assert!(!buffer4.read(cx).is_dirty());
-        assert!(!buffer5.read(cx).is_dirty());
+    let (worktree, entry_id) = project.read_with(cx, |project, cx| {
+        let worktree = project.worktrees(cx).next().unwrap();
+        let entry_id = worktree
+            .read(cx)
+            .entry_for_path(rel_path("test.txt"))
+            .unwrap()
+            .id;
+        (worktree, entry_id)
     });
-
-    // Rename and delete files and directories.
-    tree.flush_fs_events(cx).await;
-    std::fs::rename(dir.path().join("a/file3"), dir.path().join("b/c/file3")).unwrap();
-    std::fs::remove_file(dir.path().join("b/c/file5")).unwrap();
-    std::fs::rename(dir.path().join("b/c"), dir.path().join("d")).unwrap();
-    std::fs::rename(dir.path().join("a/file2"), dir.path().join("a/file2.new")).unwrap();
-    tree.flush_fs_events(cx).await;
-
-    cx.update(|app| {
-        assert_eq!(
-            tree.read(app).paths().collect::<Vec<_>>(),
-            vec![
-                rel_path("a"),
-                rel_path("a/file1"),
-                rel_path("a/file2.new"),
-                rel_path("b"),
-                rel_path("d"),
-                rel_path("d/file3"),
-                rel_path("d/file4"),
-            ]
+    let worktree_id = worktree.read_with(cx, |worktree, _| worktree.id());
+    let _result = project
+        .update(cx, |project, cx| {
+            project.rename_entry(
+                entry_id,
+                (worktree_id, rel_path("dir1/dir2/dir3/test.txt")).into(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    worktree.read_with(cx, |worktree, _| {
+        assert!(
+            worktree.entry_for_path(rel_path("test.txt")).is_none(),
+            "Old file should have been removed"
+        );
+        assert!(
+            worktree
+                .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
+                .is_some(),
+            "Whole directory hierarchy and the new file should have been created"
         );
     });
+    assert_eq!(
+        worktree
+            .update(cx, |worktree, cx| {
+                worktree.load_file(rel_path("dir1/dir2/dir3/test.txt"), cx)
+            })
+            .await
+            .unwrap()
+            .text,
+        expected_contents,
+        "Moved file's contents should be preserved"
+    );
 
-    assert_eq!(id_for_path("a/file2.new", cx), file2_id);
-    assert_eq!(id_for_path("d/file3", cx), file3_id);
-    assert_eq!(id_for_path("d/file4", cx), file4_id);
+    let entry_id = worktree.read_with(cx, |worktree, _| {
+        worktree
+            .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
+            .unwrap()
+            .id
+    });
 
-    cx.update(|cx| {
-        assert_eq!(
-            buffer2.read(cx).file().unwrap().path().as_ref(),
-            rel_path("a/file2.new")
-        );
-        assert_eq!(
-            buffer3.read(cx).file().unwrap().path().as_ref(),
-            rel_path("d/file3")
+    let _result = project
+        .update(cx, |project, cx| {
+            project.rename_entry(
+                entry_id,
+                (worktree_id, rel_path("dir1/dir2/test.txt")).into(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    worktree.read_with(cx, |worktree, _| {
+        assert!(
+            worktree.entry_for_path(rel_path("test.txt")).is_none(),
+            "First file should not reappear"
         );
-        assert_eq!(
-            buffer4.read(cx).file().unwrap().path().as_ref(),
-            rel_path("d/file4")
+        assert!(
+            worktree
+                .entry_for_path(rel_path("dir1/dir2/dir3/test.txt"))
+                .is_none(),
+            "Old file should have been removed"
         );
-        assert_eq!(
-            buffer5.read(cx).file().unwrap().path().as_ref(),
-            rel_path("b/c/file5")
-        );
-
-        assert_matches!(
-            buffer2.read(cx).file().unwrap().disk_state(),
-            DiskState::Present { .. }
-        );
-        assert_matches!(
-            buffer3.read(cx).file().unwrap().disk_state(),
-            DiskState::Present { .. }
-        );
-        assert_matches!(
-            buffer4.read(cx).file().unwrap().disk_state(),
-            DiskState::Present { .. }
-        );
-        assert_eq!(
-            buffer5.read(cx).file().unwrap().disk_state(),
-            DiskState::Deleted
-        );
-    });
-
-    // Update the remote worktree. Check that it becomes consistent with the
-    // local worktree.
-    cx.executor().run_until_parked();
-
-    remote.update(cx, |remote, _| {
-        for update in updates.lock().drain(..) {
-            remote.as_remote_mut().unwrap().update_from_remote(update);
-        }
-    });
-    cx.executor().run_until_parked();
-    remote.update(cx, |remote, _| {
-        assert_eq!(
-            remote.paths().collect::<Vec<_>>(),
-            vec![
-                rel_path("a"),
-                rel_path("a/file1"),
-                rel_path("a/file2.new"),
-                rel_path("b"),
-                rel_path("d"),
-                rel_path("d/file3"),
-                rel_path("d/file4"),
-            ]
+        assert!(
+            worktree
+                .entry_for_path(rel_path("dir1/dir2/test.txt"))
+                .is_some(),
+            "No error should have occurred after moving into existing directory"
         );
     });
+    assert_eq!(
+        worktree
+            .update(cx, |worktree, cx| {
+                worktree.load_file(rel_path("dir1/dir2/test.txt"), cx)
+            })
+            .await
+            .unwrap()
+            .text,
+        expected_contents,
+        "Moved file's contents should be preserved"
+    );
 }
 
 #[gpui::test(iterations = 10)]
-async fn test_buffer_identity_across_renames(cx: &mut gpui::TestAppContext) {
+async fn test_save_file(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a": {
-                "file1": "",
-            }
+            "file1": "the old contents",
         }),
     )
     .await;
 
-    let project = Project::test(fs, [Path::new(path!("/dir"))], cx).await;
-    let tree = project.update(cx, |project, cx| project.worktrees(cx).next().unwrap());
-    let tree_id = tree.update(cx, |tree, _| tree.id());
-
-    let id_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
-        project.update(cx, |project, cx| {
-            let tree = project.worktrees(cx).next().unwrap();
-            tree.read(cx)
-                .entry_for_path(rel_path(path))
-                .unwrap_or_else(|| panic!("no entry for path {}", path))
-                .id
-        })
-    };
-
-    let dir_id = id_for_path("a", cx);
-    let file_id = id_for_path("a/file1", cx);
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
     let buffer = project
-        .update(cx, |p, cx| {
-            p.open_buffer((tree_id, rel_path("a/file1")), cx)
-        })
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
         .await
         .unwrap();
-    buffer.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), "the old contents");
+        buffer.edit([(0..0, "a line of text.\n".repeat(10 * 1024))], None, cx);
+    });
 
     project
-        .update(cx, |project, cx| {
-            project.rename_entry(dir_id, (tree_id, rel_path("b")).into(), cx)
-        })
-        .unwrap()
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
         .await
-        .into_included()
         .unwrap();
-    cx.executor().run_until_parked();
 
-    assert_eq!(id_for_path("b", cx), dir_id);
-    assert_eq!(id_for_path("b/file1", cx), file_id);
-    buffer.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
+    let new_text = fs
+        .load(Path::new(path!("/dir/file1")))
+        .await
+        .unwrap()
+        .replace("\r\n", "\n");
+    assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
 }
 
-#[gpui::test]
-async fn test_buffer_deduping(cx: &mut gpui::TestAppContext) {
+#[gpui::test(iterations = 10)]
+async fn test_save_file_spawns_language_server(cx: &mut gpui::TestAppContext) {
+    // Issue: #24349
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(
-        "/dir",
-        json!({
-            "a.txt": "a-contents",
-            "b.txt": "b-contents",
-        }),
-    )
-    .await;
-
-    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    fs.insert_tree(path!("/dir"), json!({})).await;
 
-    // Spawn multiple tasks to open paths, repeating some paths.
-    let (buffer_a_1, buffer_b, buffer_a_2) = project.update(cx, |p, cx| {
-        (
-            p.open_local_buffer("/dir/a.txt", cx),
-            p.open_local_buffer("/dir/b.txt", cx),
-            p.open_local_buffer("/dir/a.txt", cx),
-        )
-    });
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
 
-    let buffer_a_1 = buffer_a_1.await.unwrap();
-    let buffer_a_2 = buffer_a_2.await.unwrap();
-    let buffer_b = buffer_b.await.unwrap();
-    assert_eq!(buffer_a_1.update(cx, |b, _| b.text()), "a-contents");
-    assert_eq!(buffer_b.update(cx, |b, _| b.text()), "b-contents");
+    language_registry.add(rust_lang());
+    let mut fake_rust_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            name: "the-rust-language-server",
+            capabilities: lsp::ServerCapabilities {
+                completion_provider: Some(lsp::CompletionOptions {
+                    trigger_characters: Some(vec![".".to_string(), "::".to_string()]),
+                    ..Default::default()
+                }),
+                text_document_sync: Some(lsp::TextDocumentSyncCapability::Options(
+                    lsp::TextDocumentSyncOptions {
+                        save: Some(lsp::TextDocumentSyncSaveOptions::Supported(true)),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
 
-    // There is only one buffer per path.
-    let buffer_a_id = buffer_a_1.entity_id();
-    assert_eq!(buffer_a_2.entity_id(), buffer_a_id);
+    let buffer = project
+        .update(cx, |this, cx| this.create_buffer(false, cx))
+        .unwrap()
+        .await;
+    project.update(cx, |this, cx| {
+        this.register_buffer_with_language_servers(&buffer, cx);
+        buffer.update(cx, |buffer, cx| {
+            assert!(!this.has_language_servers_for(buffer, cx));
+        })
+    });
 
-    // Open the same path again while it is still open.
-    drop(buffer_a_1);
-    let buffer_a_3 = project
-        .update(cx, |p, cx| p.open_local_buffer("/dir/a.txt", cx))
+    project
+        .update(cx, |this, cx| {
+            let worktree_id = this.worktrees(cx).next().unwrap().read(cx).id();
+            this.save_buffer_as(
+                buffer.clone(),
+                ProjectPath {
+                    worktree_id,
+                    path: rel_path("file.rs").into(),
+                },
+                cx,
+            )
+        })
         .await
         .unwrap();
+    // A server is started up, and it is notified about Rust files.
+    let mut fake_rust_server = fake_rust_servers.next().await.unwrap();
+    assert_eq!(
+        fake_rust_server
+            .receive_notification::<lsp::notification::DidOpenTextDocument>()
+            .await
+            .text_document,
+        lsp::TextDocumentItem {
+            uri: lsp::Uri::from_file_path(path!("/dir/file.rs")).unwrap(),
+            version: 0,
+            text: "".to_string(),
+            language_id: "rust".to_string(),
+        }
+    );
 
-    // There's still only one buffer per path.
-    assert_eq!(buffer_a_3.entity_id(), buffer_a_id);
+    project.update(cx, |this, cx| {
+        buffer.update(cx, |buffer, cx| {
+            assert!(this.has_language_servers_for(buffer, cx));
+        })
+    });
 }
 
-#[gpui::test]
-async fn test_buffer_is_dirty(cx: &mut gpui::TestAppContext) {
+#[gpui::test(iterations = 30)]
+async fn test_file_changes_multiple_times_on_disk(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "file1": "abc",
-            "file2": "def",
-            "file3": "ghi",
+            "file1": "the original contents",
         }),
     )
     .await;
 
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-
-    let buffer1 = project
+    let worktree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
+    let buffer = project
         .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
         .await
         .unwrap();
-    let events = Arc::new(Mutex::new(Vec::new()));
 
-    // initially, the buffer isn't dirty.
-    buffer1.update(cx, |buffer, cx| {
-        cx.subscribe(&buffer1, {
-            let events = events.clone();
-            move |_, _, event, _| match event {
-                BufferEvent::Operation { .. } => {}
-                _ => events.lock().push(event.clone()),
-            }
-        })
-        .detach();
+    // Simulate buffer diffs being slow, so that they don't complete before
+    // the next file change occurs.
+    cx.executor().deprioritize(*language::BUFFER_DIFF_TASK);
 
-        assert!(!buffer.is_dirty());
-        assert!(events.lock().is_empty());
+    // Change the buffer's file on disk, and then wait for the file change
+    // to be detected by the worktree, so that the buffer starts reloading.
+    fs.save(
+        path!("/dir/file1").as_ref(),
+        &"the first contents".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    worktree.next_event(cx).await;
 
-        buffer.edit([(1..2, "")], None, cx);
+    // Change the buffer's file again. Depending on the random seed, the
+    // previous file change may still be in progress.
+    fs.save(
+        path!("/dir/file1").as_ref(),
+        &"the second contents".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    worktree.next_event(cx).await;
+
+    cx.executor().run_until_parked();
+    let on_disk_text = fs.load(Path::new(path!("/dir/file1"))).await.unwrap();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), on_disk_text);
+        assert!(!buffer.is_dirty(), "buffer should not be dirty");
+        assert!(!buffer.has_conflict(), "buffer should not be dirty");
     });
+}
 
-    // after the first edit, the buffer is dirty, and emits a dirtied event.
-    buffer1.update(cx, |buffer, cx| {
-        assert!(buffer.text() == "ac");
-        assert!(buffer.is_dirty());
-        assert_eq!(
-            *events.lock(),
-            &[
-                language::BufferEvent::Edited,
-                language::BufferEvent::DirtyChanged
-            ]
-        );
-        events.lock().clear();
-        buffer.did_save(
-            buffer.version(),
-            buffer.file().unwrap().disk_state().mtime(),
-            cx,
-        );
-    });
-
-    // after saving, the buffer is not dirty, and emits a saved event.
-    buffer1.update(cx, |buffer, cx| {
-        assert!(!buffer.is_dirty());
-        assert_eq!(*events.lock(), &[language::BufferEvent::Saved]);
-        events.lock().clear();
-
-        buffer.edit([(1..1, "B")], None, cx);
-        buffer.edit([(2..2, "D")], None, cx);
-    });
-
-    // after editing again, the buffer is dirty, and emits another dirty event.
-    buffer1.update(cx, |buffer, cx| {
-        assert!(buffer.text() == "aBDc");
-        assert!(buffer.is_dirty());
-        assert_eq!(
-            *events.lock(),
-            &[
-                language::BufferEvent::Edited,
-                language::BufferEvent::DirtyChanged,
-                language::BufferEvent::Edited,
-            ],
-        );
-        events.lock().clear();
-
-        // After restoring the buffer to its previously-saved state,
-        // the buffer is not considered dirty anymore.
-        buffer.edit([(1..3, "")], None, cx);
-        assert!(buffer.text() == "ac");
-        assert!(!buffer.is_dirty());
-    });
-
-    assert_eq!(
-        *events.lock(),
-        &[
-            language::BufferEvent::Edited,
-            language::BufferEvent::DirtyChanged
-        ]
-    );
-
-    // When a file is deleted, it is not considered dirty.
-    let events = Arc::new(Mutex::new(Vec::new()));
-    let buffer2 = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file2"), cx))
-        .await
-        .unwrap();
-    buffer2.update(cx, |_, cx| {
-        cx.subscribe(&buffer2, {
-            let events = events.clone();
-            move |_, _, event, _| match event {
-                BufferEvent::Operation { .. } => {}
-                _ => events.lock().push(event.clone()),
-            }
-        })
-        .detach();
-    });
-
-    fs.remove_file(path!("/dir/file2").as_ref(), Default::default())
-        .await
-        .unwrap();
-    cx.executor().run_until_parked();
-    buffer2.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
-    assert_eq!(
-        mem::take(&mut *events.lock()),
-        &[language::BufferEvent::FileHandleChanged]
-    );
-
-    // Buffer becomes dirty when edited.
-    buffer2.update(cx, |buffer, cx| {
-        buffer.edit([(2..3, "")], None, cx);
-        assert_eq!(buffer.is_dirty(), true);
-    });
-    assert_eq!(
-        mem::take(&mut *events.lock()),
-        &[
-            language::BufferEvent::Edited,
-            language::BufferEvent::DirtyChanged
-        ]
-    );
-
-    // Buffer becomes clean again when all of its content is removed, because
-    // the file was deleted.
-    buffer2.update(cx, |buffer, cx| {
-        buffer.edit([(0..2, "")], None, cx);
-        assert_eq!(buffer.is_empty(), true);
-        assert_eq!(buffer.is_dirty(), false);
-    });
-    assert_eq!(
-        *events.lock(),
-        &[
-            language::BufferEvent::Edited,
-            language::BufferEvent::DirtyChanged
-        ]
-    );
-
-    // When a file is already dirty when deleted, we don't emit a Dirtied event.
-    let events = Arc::new(Mutex::new(Vec::new()));
-    let buffer3 = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file3"), cx))
-        .await
-        .unwrap();
-    buffer3.update(cx, |_, cx| {
-        cx.subscribe(&buffer3, {
-            let events = events.clone();
-            move |_, _, event, _| match event {
-                BufferEvent::Operation { .. } => {}
-                _ => events.lock().push(event.clone()),
-            }
-        })
-        .detach();
-    });
-
-    buffer3.update(cx, |buffer, cx| {
-        buffer.edit([(0..0, "x")], None, cx);
-    });
-    events.lock().clear();
-    fs.remove_file(path!("/dir/file3").as_ref(), Default::default())
-        .await
-        .unwrap();
-    cx.executor().run_until_parked();
-    assert_eq!(*events.lock(), &[language::BufferEvent::FileHandleChanged]);
-    cx.update(|cx| assert!(buffer3.read(cx).is_dirty()));
-}
-
-#[gpui::test]
-async fn test_buffer_file_changes_on_disk(cx: &mut gpui::TestAppContext) {
+#[gpui::test(iterations = 30)]
+async fn test_edit_buffer_while_it_reloads(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
-    let (initial_contents, initial_offsets) =
-        marked_text_offsets("one twoˇ\nthree ˇfourˇ five\nsixˇ seven\n");
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "the-file": initial_contents,
+            "file1": "the original contents",
         }),
     )
     .await;
+
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree = project.read_with(cx, |project, cx| project.worktrees(cx).next().unwrap());
     let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/the-file"), cx))
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
         .await
         .unwrap();
 
-    let anchors = initial_offsets
-        .iter()
-        .map(|offset| buffer.update(cx, |b, _| b.anchor_before(offset)))
-        .collect::<Vec<_>>();
-
-    // Change the file on disk, adding two new lines of text, and removing
-    // one line.
-    buffer.update(cx, |buffer, _| {
-        assert!(!buffer.is_dirty());
-        assert!(!buffer.has_conflict());
-    });
+    // Simulate buffer diffs being slow, so that they don't complete before
+    // the next file change occurs.
+    cx.executor().deprioritize(*language::BUFFER_DIFF_TASK);
 
-    let (new_contents, new_offsets) =
-        marked_text_offsets("oneˇ\nthree ˇFOURˇ five\nsixtyˇ seven\n");
+    // Change the buffer's file on disk, and then wait for the file change
+    // to be detected by the worktree, so that the buffer starts reloading.
     fs.save(
-        path!("/dir/the-file").as_ref(),
-        &new_contents.as_str().into(),
-        LineEnding::Unix,
+        path!("/dir/file1").as_ref(),
+        &"the first contents".into(),
+        Default::default(),
     )
     .await
     .unwrap();
+    worktree.next_event(cx).await;
 
-    // Because the buffer was not modified, it is reloaded from disk. Its
-    // contents are edited according to the diff between the old and new
-    // file contents.
-    cx.executor().run_until_parked();
-    buffer.update(cx, |buffer, _| {
-        assert_eq!(buffer.text(), new_contents);
-        assert!(!buffer.is_dirty());
-        assert!(!buffer.has_conflict());
-
-        let anchor_offsets = anchors
-            .iter()
-            .map(|anchor| anchor.to_offset(&*buffer))
-            .collect::<Vec<_>>();
-        assert_eq!(anchor_offsets, new_offsets);
-    });
+    cx.executor()
+        .spawn(cx.executor().simulate_random_delay())
+        .await;
 
-    // Modify the buffer
+    // Perform a noop edit, causing the buffer's version to increase.
     buffer.update(cx, |buffer, cx| {
         buffer.edit([(0..0, " ")], None, cx);
-        assert!(buffer.is_dirty());
-        assert!(!buffer.has_conflict());
+        buffer.undo(cx);
     });
 
-    // Change the file on disk again, adding blank lines to the beginning.
-    fs.save(
-        path!("/dir/the-file").as_ref(),
-        &"\n\n\nAAAA\naaa\nBB\nbbbbb\n".into(),
-        LineEnding::Unix,
-    )
-    .await
-    .unwrap();
-
-    // Because the buffer is modified, it doesn't reload from disk, but is
-    // marked as having a conflict.
     cx.executor().run_until_parked();
-    buffer.update(cx, |buffer, _| {
-        assert_eq!(buffer.text(), " ".to_string() + &new_contents);
-        assert!(buffer.has_conflict());
+    let on_disk_text = fs.load(Path::new(path!("/dir/file1"))).await.unwrap();
+    buffer.read_with(cx, |buffer, _| {
+        let buffer_text = buffer.text();
+        if buffer_text == on_disk_text {
+            assert!(
+                !buffer.is_dirty() && !buffer.has_conflict(),
+                "buffer shouldn't be dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}",
+            );
+        }
+        // If the file change occurred while the buffer was processing the first
+        // change, the buffer will be in a conflicting state.
+        else {
+            assert!(buffer.is_dirty(), "buffer should report that it is dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}");
+            assert!(buffer.has_conflict(), "buffer should report that it is dirty. text: {buffer_text:?}, disk text: {on_disk_text:?}");
+        }
     });
 }
 
 #[gpui::test]
-async fn test_buffer_line_endings(cx: &mut gpui::TestAppContext) {
+async fn test_save_in_single_file_worktree(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "file1": "a\nb\nc\n",
-            "file2": "one\r\ntwo\r\nthree\r\n",
+            "file1": "the old contents",
         }),
     )
     .await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let buffer1 = project
+    let project = Project::test(fs.clone(), [path!("/dir/file1").as_ref()], cx).await;
+    let buffer = project
         .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
         .await
         .unwrap();
-    let buffer2 = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file2"), cx))
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "a line of text.\n".repeat(10 * 1024))], None, cx);
+    });
+
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
         .await
         .unwrap();
 
-    buffer1.update(cx, |buffer, _| {
-        assert_eq!(buffer.text(), "a\nb\nc\n");
-        assert_eq!(buffer.line_ending(), LineEnding::Unix);
-    });
-    buffer2.update(cx, |buffer, _| {
-        assert_eq!(buffer.text(), "one\ntwo\nthree\n");
-        assert_eq!(buffer.line_ending(), LineEnding::Windows);
-    });
-
-    // Change a file's line endings on disk from unix to windows. The buffer's
-    // state updates correctly.
-    fs.save(
-        path!("/dir/file1").as_ref(),
-        &"aaa\nb\nc\n".into(),
-        LineEnding::Windows,
-    )
-    .await
-    .unwrap();
-    cx.executor().run_until_parked();
-    buffer1.update(cx, |buffer, _| {
-        assert_eq!(buffer.text(), "aaa\nb\nc\n");
-        assert_eq!(buffer.line_ending(), LineEnding::Windows);
-    });
+    let new_text = fs
+        .load(Path::new(path!("/dir/file1")))
+        .await
+        .unwrap()
+        .replace("\r\n", "\n");
+    assert_eq!(new_text, buffer.update(cx, |buffer, _| buffer.text()));
+}
 
-    // Save a file with windows line endings. The file is written correctly.
-    buffer2.update(cx, |buffer, cx| {
-        buffer.set_text("one\ntwo\nthree\nfour\n", cx);
+#[gpui::test]
+async fn test_save_as(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree("/dir", json!({})).await;
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+
+    let languages = project.update(cx, |project, _| project.languages().clone());
+    languages.add(rust_lang());
+
+    let buffer = project.update(cx, |project, cx| {
+        project.create_local_buffer("", None, false, cx)
+    });
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "abc")], None, cx);
+        assert!(buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+        assert_eq!(buffer.language().unwrap().name(), "Plain Text".into());
     });
     project
-        .update(cx, |project, cx| project.save_buffer(buffer2, cx))
+        .update(cx, |project, cx| {
+            let worktree_id = project.worktrees(cx).next().unwrap().read(cx).id();
+            let path = ProjectPath {
+                worktree_id,
+                path: rel_path("file1.rs").into(),
+            };
+            project.save_buffer_as(buffer.clone(), path, cx)
+        })
         .await
         .unwrap();
-    assert_eq!(
-        fs.load(path!("/dir/file2").as_ref()).await.unwrap(),
-        "one\r\ntwo\r\nthree\r\nfour\r\n",
-    );
+    assert_eq!(fs.load(Path::new("/dir/file1.rs")).await.unwrap(), "abc");
+
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(
+            buffer.file().unwrap().full_path(cx),
+            Path::new("dir/file1.rs")
+        );
+        assert!(!buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+        assert_eq!(buffer.language().unwrap().name(), "Rust".into());
+    });
+
+    let opened_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/file1.rs", cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(opened_buffer, buffer);
 }
 
 #[gpui::test]
-async fn test_grouped_diagnostics(cx: &mut gpui::TestAppContext) {
+async fn test_save_as_existing_file(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "a.rs": "
-                fn foo(mut v: Vec<usize>) {
-                    for x in &v {
-                        v.push(1);
-                    }
-                }
-            "
-            .unindent(),
+                "data_a.txt": "data about a"
         }),
     )
     .await;
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
     let buffer = project
-        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/a.rs"), cx))
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/data_a.txt"), cx)
+        })
         .await
         .unwrap();
 
-    let buffer_uri = Uri::from_file_path(path!("/dir/a.rs")).unwrap();
-    let message = lsp::PublishDiagnosticsParams {
-        uri: buffer_uri.clone(),
-        diagnostics: vec![
-            lsp::Diagnostic {
-                range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
-                severity: Some(DiagnosticSeverity::WARNING),
-                message: "error 1".to_string(),
-                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
-                    location: lsp::Location {
-                        uri: buffer_uri.clone(),
-                        range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
-                    },
-                    message: "error 1 hint 1".to_string(),
-                }]),
-                ..Default::default()
-            },
-            lsp::Diagnostic {
-                range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
-                severity: Some(DiagnosticSeverity::HINT),
-                message: "error 1 hint 1".to_string(),
-                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
-                    location: lsp::Location {
-                        uri: buffer_uri.clone(),
-                        range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
-                    },
-                    message: "original diagnostic".to_string(),
-                }]),
-                ..Default::default()
-            },
-            lsp::Diagnostic {
-                range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
-                severity: Some(DiagnosticSeverity::ERROR),
-                message: "error 2".to_string(),
-                related_information: Some(vec![
-                    lsp::DiagnosticRelatedInformation {
-                        location: lsp::Location {
-                            uri: buffer_uri.clone(),
-                            range: lsp::Range::new(
-                                lsp::Position::new(1, 13),
-                                lsp::Position::new(1, 15),
-                            ),
-                        },
-                        message: "error 2 hint 1".to_string(),
-                    },
-                    lsp::DiagnosticRelatedInformation {
-                        location: lsp::Location {
-                            uri: buffer_uri.clone(),
-                            range: lsp::Range::new(
-                                lsp::Position::new(1, 13),
-                                lsp::Position::new(1, 15),
-                            ),
-                        },
-                        message: "error 2 hint 2".to_string(),
-                    },
-                ]),
-                ..Default::default()
-            },
-            lsp::Diagnostic {
-                range: lsp::Range::new(lsp::Position::new(1, 13), lsp::Position::new(1, 15)),
-                severity: Some(DiagnosticSeverity::HINT),
-                message: "error 2 hint 1".to_string(),
-                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
-                    location: lsp::Location {
-                        uri: buffer_uri.clone(),
-                        range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
-                    },
-                    message: "original diagnostic".to_string(),
-                }]),
-                ..Default::default()
-            },
-            lsp::Diagnostic {
-                range: lsp::Range::new(lsp::Position::new(1, 13), lsp::Position::new(1, 15)),
-                severity: Some(DiagnosticSeverity::HINT),
-                message: "error 2 hint 2".to_string(),
-                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
-                    location: lsp::Location {
-                        uri: buffer_uri,
-                        range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
-                    },
-                    message: "original diagnostic".to_string(),
-                }]),
-                ..Default::default()
-            },
-        ],
-        version: None,
-    };
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(11..12, "b")], None, cx);
+    });
 
-    lsp_store
-        .update(cx, |lsp_store, cx| {
-            lsp_store.update_diagnostics(
-                LanguageServerId(0),
-                message,
-                None,
-                DiagnosticSourceKind::Pushed,
-                &[],
-                cx,
-            )
+    // Save buffer's contents as a new file and confirm that the buffer's now
+    // associated with `data_b.txt` instead of `data_a.txt`, confirming that the
+    // file associated with the buffer has now been updated to `data_b.txt`
+    project
+        .update(cx, |project, cx| {
+            let worktree_id = project.worktrees(cx).next().unwrap().read(cx).id();
+            let new_path = ProjectPath {
+                worktree_id,
+                path: rel_path("data_b.txt").into(),
+            };
+
+            project.save_buffer_as(buffer.clone(), new_path, cx)
         })
+        .await
         .unwrap();
-    let buffer = buffer.update(cx, |buffer, _| buffer.snapshot());
 
-    assert_eq!(
-        buffer
-            .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
-            .collect::<Vec<_>>(),
-        &[
-            DiagnosticEntry {
-                range: Point::new(1, 8)..Point::new(1, 9),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::WARNING,
-                    message: "error 1".to_string(),
-                    group_id: 1,
-                    is_primary: true,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(1, 8)..Point::new(1, 9),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 1 hint 1".to_string(),
-                    group_id: 1,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(1, 13)..Point::new(1, 15),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 2 hint 1".to_string(),
-                    group_id: 0,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(1, 13)..Point::new(1, 15),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 2 hint 2".to_string(),
-                    group_id: 0,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(
+            buffer.file().unwrap().full_path(cx),
+            Path::new("dir/data_b.txt")
+        )
+    });
+
+    // Open the original `data_a.txt` file, confirming that its contents are
+    // unchanged and the resulting buffer's associated file is `data_a.txt`.
+    let original_buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/data_a.txt"), cx)
+        })
+        .await
+        .unwrap();
+
+    original_buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), "data about a");
+        assert_eq!(
+            buffer.file().unwrap().full_path(cx),
+            Path::new("dir/data_a.txt")
+        )
+    });
+}
+
+#[gpui::test(retries = 5)]
+async fn test_rescan_and_remote_updates(cx: &mut gpui::TestAppContext) {
+    use worktree::WorktreeModelHandle as _;
+
+    init_test(cx);
+    cx.executor().allow_parking();
+
+    let dir = TempTree::new(json!({
+        "a": {
+            "file1": "",
+            "file2": "",
+            "file3": "",
+        },
+        "b": {
+            "c": {
+                "file4": "",
+                "file5": "",
+            }
+        }
+    }));
+
+    let project = Project::test(Arc::new(RealFs::new(None, cx.executor())), [dir.path()], cx).await;
+
+    let buffer_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
+        let buffer = project.update(cx, |p, cx| p.open_local_buffer(dir.path().join(path), cx));
+        async move { buffer.await.unwrap() }
+    };
+    let id_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| {
+            let tree = project.worktrees(cx).next().unwrap();
+            tree.read(cx)
+                .entry_for_path(rel_path(path))
+                .unwrap_or_else(|| panic!("no entry for path {}", path))
+                .id
+        })
+    };
+
+    let buffer2 = buffer_for_path("a/file2", cx).await;
+    let buffer3 = buffer_for_path("a/file3", cx).await;
+    let buffer4 = buffer_for_path("b/c/file4", cx).await;
+    let buffer5 = buffer_for_path("b/c/file5", cx).await;
+
+    let file2_id = id_for_path("a/file2", cx);
+    let file3_id = id_for_path("a/file3", cx);
+    let file4_id = id_for_path("b/c/file4", cx);
+
+    // Create a remote copy of this worktree.
+    let tree = project.update(cx, |project, cx| project.worktrees(cx).next().unwrap());
+    let metadata = tree.update(cx, |tree, _| tree.metadata_proto());
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    tree.update(cx, |tree, cx| {
+        let updates = updates.clone();
+        tree.observe_updates(0, cx, move |update| {
+            updates.lock().push(update);
+            async { true }
+        });
+    });
+
+    let remote = cx.update(|cx| {
+        Worktree::remote(
+            0,
+            ReplicaId::REMOTE_SERVER,
+            metadata,
+            project.read(cx).client().into(),
+            project.read(cx).path_style(cx),
+            cx,
+        )
+    });
+
+    cx.executor().run_until_parked();
+
+    cx.update(|cx| {
+        assert!(!buffer2.read(cx).is_dirty());
+        assert!(!buffer3.read(cx).is_dirty());
+        assert!(!buffer4.read(cx).is_dirty());
+        assert!(!buffer5.read(cx).is_dirty());
+    });
+
+    // Rename and delete files and directories.
+    tree.flush_fs_events(cx).await;
+    std::fs::rename(dir.path().join("a/file3"), dir.path().join("b/c/file3")).unwrap();
+    std::fs::remove_file(dir.path().join("b/c/file5")).unwrap();
+    std::fs::rename(dir.path().join("b/c"), dir.path().join("d")).unwrap();
+    std::fs::rename(dir.path().join("a/file2"), dir.path().join("a/file2.new")).unwrap();
+    tree.flush_fs_events(cx).await;
+
+    cx.update(|app| {
+        assert_eq!(
+            tree.read(app).paths().collect::<Vec<_>>(),
+            vec![
+                rel_path("a"),
+                rel_path("a/file1"),
+                rel_path("a/file2.new"),
+                rel_path("b"),
+                rel_path("d"),
+                rel_path("d/file3"),
+                rel_path("d/file4"),
+            ]
+        );
+    });
+
+    assert_eq!(id_for_path("a/file2.new", cx), file2_id);
+    assert_eq!(id_for_path("d/file3", cx), file3_id);
+    assert_eq!(id_for_path("d/file4", cx), file4_id);
+
+    cx.update(|cx| {
+        assert_eq!(
+            buffer2.read(cx).file().unwrap().path().as_ref(),
+            rel_path("a/file2.new")
+        );
+        assert_eq!(
+            buffer3.read(cx).file().unwrap().path().as_ref(),
+            rel_path("d/file3")
+        );
+        assert_eq!(
+            buffer4.read(cx).file().unwrap().path().as_ref(),
+            rel_path("d/file4")
+        );
+        assert_eq!(
+            buffer5.read(cx).file().unwrap().path().as_ref(),
+            rel_path("b/c/file5")
+        );
+
+        assert_matches!(
+            buffer2.read(cx).file().unwrap().disk_state(),
+            DiskState::Present { .. }
+        );
+        assert_matches!(
+            buffer3.read(cx).file().unwrap().disk_state(),
+            DiskState::Present { .. }
+        );
+        assert_matches!(
+            buffer4.read(cx).file().unwrap().disk_state(),
+            DiskState::Present { .. }
+        );
+        assert_eq!(
+            buffer5.read(cx).file().unwrap().disk_state(),
+            DiskState::Deleted
+        );
+    });
+
+    // Update the remote worktree. Check that it becomes consistent with the
+    // local worktree.
+    cx.executor().run_until_parked();
+
+    remote.update(cx, |remote, _| {
+        for update in updates.lock().drain(..) {
+            remote.as_remote_mut().unwrap().update_from_remote(update);
+        }
+    });
+    cx.executor().run_until_parked();
+    remote.update(cx, |remote, _| {
+        assert_eq!(
+            remote.paths().collect::<Vec<_>>(),
+            vec![
+                rel_path("a"),
+                rel_path("a/file1"),
+                rel_path("a/file2.new"),
+                rel_path("b"),
+                rel_path("d"),
+                rel_path("d/file3"),
+                rel_path("d/file4"),
+            ]
+        );
+    });
+}
+
+#[gpui::test(iterations = 10)]
+async fn test_buffer_identity_across_renames(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a": {
+                "file1": "",
+            }
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs, [Path::new(path!("/dir"))], cx).await;
+    let tree = project.update(cx, |project, cx| project.worktrees(cx).next().unwrap());
+    let tree_id = tree.update(cx, |tree, _| tree.id());
+
+    let id_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| {
+            let tree = project.worktrees(cx).next().unwrap();
+            tree.read(cx)
+                .entry_for_path(rel_path(path))
+                .unwrap_or_else(|| panic!("no entry for path {}", path))
+                .id
+        })
+    };
+
+    let dir_id = id_for_path("a", cx);
+    let file_id = id_for_path("a/file1", cx);
+    let buffer = project
+        .update(cx, |p, cx| {
+            p.open_buffer((tree_id, rel_path("a/file1")), cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
+
+    project
+        .update(cx, |project, cx| {
+            project.rename_entry(dir_id, (tree_id, rel_path("b")).into(), cx)
+        })
+        .unwrap()
+        .await
+        .into_included()
+        .unwrap();
+    cx.executor().run_until_parked();
+
+    assert_eq!(id_for_path("b", cx), dir_id);
+    assert_eq!(id_for_path("b/file1", cx), file_id);
+    buffer.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
+}
+
+#[gpui::test]
+async fn test_buffer_deduping(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            "a.txt": "a-contents",
+            "b.txt": "b-contents",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+
+    // Spawn multiple tasks to open paths, repeating some paths.
+    let (buffer_a_1, buffer_b, buffer_a_2) = project.update(cx, |p, cx| {
+        (
+            p.open_local_buffer("/dir/a.txt", cx),
+            p.open_local_buffer("/dir/b.txt", cx),
+            p.open_local_buffer("/dir/a.txt", cx),
+        )
+    });
+
+    let buffer_a_1 = buffer_a_1.await.unwrap();
+    let buffer_a_2 = buffer_a_2.await.unwrap();
+    let buffer_b = buffer_b.await.unwrap();
+    assert_eq!(buffer_a_1.update(cx, |b, _| b.text()), "a-contents");
+    assert_eq!(buffer_b.update(cx, |b, _| b.text()), "b-contents");
+
+    // There is only one buffer per path.
+    let buffer_a_id = buffer_a_1.entity_id();
+    assert_eq!(buffer_a_2.entity_id(), buffer_a_id);
+
+    // Open the same path again while it is still open.
+    drop(buffer_a_1);
+    let buffer_a_3 = project
+        .update(cx, |p, cx| p.open_local_buffer("/dir/a.txt", cx))
+        .await
+        .unwrap();
+
+    // There's still only one buffer per path.
+    assert_eq!(buffer_a_3.entity_id(), buffer_a_id);
+}
+
+#[gpui::test]
+async fn test_buffer_is_dirty(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "file1": "abc",
+            "file2": "def",
+            "file3": "ghi",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let buffer1 = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
+        .await
+        .unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+
+    // initially, the buffer isn't dirty.
+    buffer1.update(cx, |buffer, cx| {
+        cx.subscribe(&buffer1, {
+            let events = events.clone();
+            move |_, _, event, _| match event {
+                BufferEvent::Operation { .. } => {}
+                _ => events.lock().push(event.clone()),
+            }
+        })
+        .detach();
+
+        assert!(!buffer.is_dirty());
+        assert!(events.lock().is_empty());
+
+        buffer.edit([(1..2, "")], None, cx);
+    });
+
+    // after the first edit, the buffer is dirty, and emits a dirtied event.
+    buffer1.update(cx, |buffer, cx| {
+        assert!(buffer.text() == "ac");
+        assert!(buffer.is_dirty());
+        assert_eq!(
+            *events.lock(),
+            &[
+                language::BufferEvent::Edited,
+                language::BufferEvent::DirtyChanged
+            ]
+        );
+        events.lock().clear();
+        buffer.did_save(
+            buffer.version(),
+            buffer.file().unwrap().disk_state().mtime(),
+            cx,
+        );
+    });
+
+    // after saving, the buffer is not dirty, and emits a saved event.
+    buffer1.update(cx, |buffer, cx| {
+        assert!(!buffer.is_dirty());
+        assert_eq!(*events.lock(), &[language::BufferEvent::Saved]);
+        events.lock().clear();
+
+        buffer.edit([(1..1, "B")], None, cx);
+        buffer.edit([(2..2, "D")], None, cx);
+    });
+
+    // after editing again, the buffer is dirty, and emits another dirty event.
+    buffer1.update(cx, |buffer, cx| {
+        assert!(buffer.text() == "aBDc");
+        assert!(buffer.is_dirty());
+        assert_eq!(
+            *events.lock(),
+            &[
+                language::BufferEvent::Edited,
+                language::BufferEvent::DirtyChanged,
+                language::BufferEvent::Edited,
+            ],
+        );
+        events.lock().clear();
+
+        // After restoring the buffer to its previously-saved state,
+        // the buffer is not considered dirty anymore.
+        buffer.edit([(1..3, "")], None, cx);
+        assert!(buffer.text() == "ac");
+        assert!(!buffer.is_dirty());
+    });
+
+    assert_eq!(
+        *events.lock(),
+        &[
+            language::BufferEvent::Edited,
+            language::BufferEvent::DirtyChanged
+        ]
+    );
+
+    // When a file is deleted, it is not considered dirty.
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let buffer2 = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file2"), cx))
+        .await
+        .unwrap();
+    buffer2.update(cx, |_, cx| {
+        cx.subscribe(&buffer2, {
+            let events = events.clone();
+            move |_, _, event, _| match event {
+                BufferEvent::Operation { .. } => {}
+                _ => events.lock().push(event.clone()),
+            }
+        })
+        .detach();
+    });
+
+    fs.remove_file(path!("/dir/file2").as_ref(), Default::default())
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    buffer2.update(cx, |buffer, _| assert!(!buffer.is_dirty()));
+    assert_eq!(
+        mem::take(&mut *events.lock()),
+        &[language::BufferEvent::FileHandleChanged]
+    );
+
+    // Buffer becomes dirty when edited.
+    buffer2.update(cx, |buffer, cx| {
+        buffer.edit([(2..3, "")], None, cx);
+        assert_eq!(buffer.is_dirty(), true);
+    });
+    assert_eq!(
+        mem::take(&mut *events.lock()),
+        &[
+            language::BufferEvent::Edited,
+            language::BufferEvent::DirtyChanged
+        ]
+    );
+
+    // Buffer becomes clean again when all of its content is removed, because
+    // the file was deleted.
+    buffer2.update(cx, |buffer, cx| {
+        buffer.edit([(0..2, "")], None, cx);
+        assert_eq!(buffer.is_empty(), true);
+        assert_eq!(buffer.is_dirty(), false);
+    });
+    assert_eq!(
+        *events.lock(),
+        &[
+            language::BufferEvent::Edited,
+            language::BufferEvent::DirtyChanged
+        ]
+    );
+
+    // When a file is already dirty when deleted, we don't emit a Dirtied event.
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let buffer3 = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file3"), cx))
+        .await
+        .unwrap();
+    buffer3.update(cx, |_, cx| {
+        cx.subscribe(&buffer3, {
+            let events = events.clone();
+            move |_, _, event, _| match event {
+                BufferEvent::Operation { .. } => {}
+                _ => events.lock().push(event.clone()),
+            }
+        })
+        .detach();
+    });
+
+    buffer3.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, "x")], None, cx);
+    });
+    events.lock().clear();
+    fs.remove_file(path!("/dir/file3").as_ref(), Default::default())
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    assert_eq!(*events.lock(), &[language::BufferEvent::FileHandleChanged]);
+    cx.update(|cx| assert!(buffer3.read(cx).is_dirty()));
+}
+
+#[gpui::test]
+async fn test_buffer_file_changes_on_disk(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let (initial_contents, initial_offsets) =
+        marked_text_offsets("one twoˇ\nthree ˇfourˇ five\nsixˇ seven\n");
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "the-file": initial_contents,
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/the-file"), cx))
+        .await
+        .unwrap();
+
+    let anchors = initial_offsets
+        .iter()
+        .map(|offset| buffer.update(cx, |b, _| b.anchor_before(offset)))
+        .collect::<Vec<_>>();
+
+    // Change the file on disk, adding two new lines of text, and removing
+    // one line.
+    buffer.update(cx, |buffer, _| {
+        assert!(!buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+    });
+
+    let (new_contents, new_offsets) =
+        marked_text_offsets("oneˇ\nthree ˇFOURˇ five\nsixtyˇ seven\n");
+    fs.save(
+        path!("/dir/the-file").as_ref(),
+        &new_contents.as_str().into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+
+    // Because the buffer was not modified, it is reloaded from disk. Its
+    // contents are edited according to the diff between the old and new
+    // file contents.
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), new_contents);
+        assert!(!buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+
+        let anchor_offsets = anchors
+            .iter()
+            .map(|anchor| anchor.to_offset(&*buffer))
+            .collect::<Vec<_>>();
+        assert_eq!(anchor_offsets, new_offsets);
+    });
+
+    // Modify the buffer
+    buffer.update(cx, |buffer, cx| {
+        buffer.edit([(0..0, " ")], None, cx);
+        assert!(buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+    });
+
+    // Change the file on disk again, adding blank lines to the beginning.
+    fs.save(
+        path!("/dir/the-file").as_ref(),
+        &"\n\n\nAAAA\naaa\nBB\nbbbbb\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+
+    // Because the buffer is modified, it doesn't reload from disk. The changes
+    // on disk are merged into it instead, but they conflict with its own change.
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), " ".to_string() + &new_contents);
+        assert!(buffer.has_conflict());
+    });
+}
+
+#[gpui::test]
+async fn test_buffer_line_endings(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "file1": "a\nb\nc\n",
+            "file2": "one\r\ntwo\r\nthree\r\n",
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer1 = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file1"), cx))
+        .await
+        .unwrap();
+    let buffer2 = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/file2"), cx))
+        .await
+        .unwrap();
+
+    buffer1.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "a\nb\nc\n");
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+    });
+    buffer2.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "one\ntwo\nthree\n");
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+    });
+
+    // Change a file's line endings on disk from unix to windows. The buffer's
+    // state updates correctly.
+    fs.save(
+        path!("/dir/file1").as_ref(),
+        &"aaa\nb\nc\n".into(),
+        LineEnding::Windows,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    buffer1.update(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "aaa\nb\nc\n");
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+    });
+
+    // Save a file with windows line endings. The file is written correctly.
+    buffer2.update(cx, |buffer, cx| {
+        buffer.set_text("one\ntwo\nthree\nfour\n", cx);
+    });
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer2, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load(path!("/dir/file2").as_ref()).await.unwrap(),
+        "one\r\ntwo\r\nthree\r\nfour\r\n",
+    );
+}
+
+#[gpui::test]
+async fn test_grouped_diagnostics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.rs": "
+                fn foo(mut v: Vec<usize>) {
+                    for x in &v {
+                        v.push(1);
+                    }
+                }
+            "
+            .unindent(),
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let lsp_store = project.read_with(cx, |project, _| project.lsp_store());
+    let buffer = project
+        .update(cx, |p, cx| p.open_local_buffer(path!("/dir/a.rs"), cx))
+        .await
+        .unwrap();
+
+    let buffer_uri = Uri::from_file_path(path!("/dir/a.rs")).unwrap();
+    let message = lsp::PublishDiagnosticsParams {
+        uri: buffer_uri.clone(),
+        diagnostics: vec![
+            lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: "error 1".to_string(),
+                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: buffer_uri.clone(),
+                        range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
+                    },
+                    message: "error 1 hint 1".to_string(),
+                }]),
+                ..Default::default()
+            },
+            lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
+                severity: Some(DiagnosticSeverity::HINT),
+                message: "error 1 hint 1".to_string(),
+                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: buffer_uri.clone(),
+                        range: lsp::Range::new(lsp::Position::new(1, 8), lsp::Position::new(1, 9)),
+                    },
+                    message: "original diagnostic".to_string(),
+                }]),
+                ..Default::default()
+            },
+            lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
+                severity: Some(DiagnosticSeverity::ERROR),
+                message: "error 2".to_string(),
+                related_information: Some(vec![
+                    lsp::DiagnosticRelatedInformation {
+                        location: lsp::Location {
+                            uri: buffer_uri.clone(),
+                            range: lsp::Range::new(
+                                lsp::Position::new(1, 13),
+                                lsp::Position::new(1, 15),
+                            ),
+                        },
+                        message: "error 2 hint 1".to_string(),
+                    },
+                    lsp::DiagnosticRelatedInformation {
+                        location: lsp::Location {
+                            uri: buffer_uri.clone(),
+                            range: lsp::Range::new(
+                                lsp::Position::new(1, 13),
+                                lsp::Position::new(1, 15),
+                            ),
+                        },
+                        message: "error 2 hint 2".to_string(),
+                    },
+                ]),
+                ..Default::default()
+            },
+            lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(1, 13), lsp::Position::new(1, 15)),
+                severity: Some(DiagnosticSeverity::HINT),
+                message: "error 2 hint 1".to_string(),
+                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: buffer_uri.clone(),
+                        range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
+                    },
+                    message: "original diagnostic".to_string(),
+                }]),
+                ..Default::default()
+            },
+            lsp::Diagnostic {
+                range: lsp::Range::new(lsp::Position::new(1, 13), lsp::Position::new(1, 15)),
+                severity: Some(DiagnosticSeverity::HINT),
+                message: "error 2 hint 2".to_string(),
+                related_information: Some(vec![lsp::DiagnosticRelatedInformation {
+                    location: lsp::Location {
+                        uri: buffer_uri,
+                        range: lsp::Range::new(lsp::Position::new(2, 8), lsp::Position::new(2, 17)),
+                    },
+                    message: "original diagnostic".to_string(),
+                }]),
+                ..Default::default()
+            },
+        ],
+        version: None,
+    };
+
+    lsp_store
+        .update(cx, |lsp_store, cx| {
+            lsp_store.update_diagnostics(
+                LanguageServerId(0),
+                message,
+                None,
+                DiagnosticSourceKind::Pushed,
+                &[],
+                cx,
+            )
+        })
+        .unwrap();
+    let buffer = buffer.update(cx, |buffer, _| buffer.snapshot());
+
+    assert_eq!(
+        buffer
+            .diagnostics_in_range::<_, Point>(0..buffer.len(), false)
+            .collect::<Vec<_>>(),
+        &[
+            DiagnosticEntry {
+                range: Point::new(1, 8)..Point::new(1, 9),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::WARNING,
+                    message: "error 1".to_string(),
+                    group_id: 1,
+                    is_primary: true,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(1, 8)..Point::new(1, 9),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 1 hint 1".to_string(),
+                    group_id: 1,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(1, 13)..Point::new(1, 15),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 2 hint 1".to_string(),
+                    group_id: 0,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(1, 13)..Point::new(1, 15),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 2 hint 2".to_string(),
+                    group_id: 0,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(2, 8)..Point::new(2, 17),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::ERROR,
+                    message: "error 2".to_string(),
+                    group_id: 0,
+                    is_primary: true,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            }
+        ]
+    );
+
+    assert_eq!(
+        buffer.diagnostic_group::<Point>(0).collect::<Vec<_>>(),
+        &[
+            DiagnosticEntry {
+                range: Point::new(1, 13)..Point::new(1, 15),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 2 hint 1".to_string(),
+                    group_id: 0,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(1, 13)..Point::new(1, 15),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 2 hint 2".to_string(),
+                    group_id: 0,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(2, 8)..Point::new(2, 17),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::ERROR,
+                    message: "error 2".to_string(),
+                    group_id: 0,
+                    is_primary: true,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            }
+        ]
+    );
+
+    assert_eq!(
+        buffer.diagnostic_group::<Point>(1).collect::<Vec<_>>(),
+        &[
+            DiagnosticEntry {
+                range: Point::new(1, 8)..Point::new(1, 9),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::WARNING,
+                    message: "error 1".to_string(),
+                    group_id: 1,
+                    is_primary: true,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+            DiagnosticEntry {
+                range: Point::new(1, 8)..Point::new(1, 9),
+                diagnostic: Diagnostic {
+                    severity: DiagnosticSeverity::HINT,
+                    message: "error 1 hint 1".to_string(),
+                    group_id: 1,
+                    is_primary: false,
+                    source_kind: DiagnosticSourceKind::Pushed,
+                    ..Diagnostic::default()
+                }
+            },
+        ]
+    );
+}
+
+#[gpui::test]
+async fn test_lsp_rename_notifications(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two": {
+                "two.rs": "const TWO: usize = one::ONE + one::ONE;"
+            }
+
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let watched_paths = lsp::FileOperationRegistrationOptions {
+        filters: vec![
+            FileOperationFilter {
+                scheme: Some("file".to_owned()),
+                pattern: lsp::FileOperationPattern {
+                    glob: "**/*.rs".to_owned(),
+                    matches: Some(lsp::FileOperationPatternKind::File),
+                    options: None,
+                },
+            },
+            FileOperationFilter {
+                scheme: Some("file".to_owned()),
+                pattern: lsp::FileOperationPattern {
+                    glob: "**/**".to_owned(),
+                    matches: Some(lsp::FileOperationPatternKind::Folder),
+                    options: None,
+                },
+            },
+        ],
+    };
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                workspace: Some(lsp::WorkspaceServerCapabilities {
+                    workspace_folders: None,
+                    file_operations: Some(lsp::WorkspaceFileOperationsServerCapabilities {
+                        did_rename: Some(watched_paths.clone()),
+                        will_rename: Some(watched_paths),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    let _ = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+    let response = project.update(cx, |project, cx| {
+        let worktree = project.worktrees(cx).next().unwrap();
+        let entry = worktree
+            .read(cx)
+            .entry_for_path(rel_path("one.rs"))
+            .unwrap();
+        project.rename_entry(
+            entry.id,
+            (worktree.read(cx).id(), rel_path("three.rs")).into(),
+            cx,
+        )
+    });
+    let expected_edit = lsp::WorkspaceEdit {
+        changes: None,
+        document_changes: Some(DocumentChanges::Edits({
+            vec![TextDocumentEdit {
+                edits: vec![lsp::Edit::Plain(lsp::TextEdit {
+                    range: lsp::Range {
+                        start: lsp::Position {
+                            line: 0,
+                            character: 1,
+                        },
+                        end: lsp::Position {
+                            line: 0,
+                            character: 3,
+                        },
+                    },
+                    new_text: "This is not a drill".to_owned(),
+                })],
+                text_document: lsp::OptionalVersionedTextDocumentIdentifier {
+                    uri: Uri::from_str(uri!("file:///dir/two/two.rs")).unwrap(),
+                    version: Some(1337),
+                },
+            }]
+        })),
+        change_annotations: None,
+    };
+    let resolved_workspace_edit = Arc::new(OnceLock::new());
+    fake_server
+        .set_request_handler::<WillRenameFiles, _, _>({
+            let resolved_workspace_edit = resolved_workspace_edit.clone();
+            let expected_edit = expected_edit.clone();
+            move |params, _| {
+                let resolved_workspace_edit = resolved_workspace_edit.clone();
+                let expected_edit = expected_edit.clone();
+                async move {
+                    assert_eq!(params.files.len(), 1);
+                    assert_eq!(params.files[0].old_uri, uri!("file:///dir/one.rs"));
+                    assert_eq!(params.files[0].new_uri, uri!("file:///dir/three.rs"));
+                    resolved_workspace_edit.set(expected_edit.clone()).unwrap();
+                    Ok(Some(expected_edit))
                 }
+            }
+        })
+        .next()
+        .await
+        .unwrap();
+    let _ = response.await.unwrap();
+    fake_server
+        .handle_notification::<DidRenameFiles, _>(|params, _| {
+            assert_eq!(params.files.len(), 1);
+            assert_eq!(params.files[0].old_uri, uri!("file:///dir/one.rs"));
+            assert_eq!(params.files[0].new_uri, uri!("file:///dir/three.rs"));
+        })
+        .next()
+        .await
+        .unwrap();
+    assert_eq!(resolved_workspace_edit.get(), Some(&expected_edit));
+}
+
+#[gpui::test]
+async fn test_rename(cx: &mut gpui::TestAppContext) {
+    // hi
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;"
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                rename_provider: Some(lsp::OneOf::Right(lsp::RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    let (buffer, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let fake_server = fake_servers.next().await.unwrap();
+
+    let response = project.update(cx, |project, cx| {
+        project.prepare_rename(buffer.clone(), 7, cx)
+    });
+    fake_server
+        .set_request_handler::<lsp::request::PrepareRenameRequest, _, _>(|params, _| async move {
+            assert_eq!(
+                params.text_document.uri.as_str(),
+                uri!("file:///dir/one.rs")
+            );
+            assert_eq!(params.position, lsp::Position::new(0, 7));
+            Ok(Some(lsp::PrepareRenameResponse::Range(lsp::Range::new(
+                lsp::Position::new(0, 6),
+                lsp::Position::new(0, 9),
+            ))))
+        })
+        .next()
+        .await
+        .unwrap();
+    let response = response.await.unwrap();
+    let PrepareRenameResponse::Success(range) = response else {
+        panic!("{:?}", response);
+    };
+    let range = buffer.update(cx, |buffer, _| range.to_offset(buffer));
+    assert_eq!(range, 6..9);
+
+    let response = project.update(cx, |project, cx| {
+        project.perform_rename(buffer.clone(), 7, "THREE".to_string(), cx)
+    });
+    fake_server
+        .set_request_handler::<lsp::request::Rename, _, _>(|params, _| async move {
+            assert_eq!(
+                params.text_document_position.text_document.uri.as_str(),
+                uri!("file:///dir/one.rs")
+            );
+            assert_eq!(
+                params.text_document_position.position,
+                lsp::Position::new(0, 7)
+            );
+            assert_eq!(params.new_name, "THREE");
+            Ok(Some(lsp::WorkspaceEdit {
+                changes: Some(
+                    [
+                        (
+                            lsp::Uri::from_file_path(path!("/dir/one.rs")).unwrap(),
+                            vec![lsp::TextEdit::new(
+                                lsp::Range::new(lsp::Position::new(0, 6), lsp::Position::new(0, 9)),
+                                "THREE".to_string(),
+                            )],
+                        ),
+                        (
+                            lsp::Uri::from_file_path(path!("/dir/two.rs")).unwrap(),
+                            vec![
+                                lsp::TextEdit::new(
+                                    lsp::Range::new(
+                                        lsp::Position::new(0, 24),
+                                        lsp::Position::new(0, 27),
+                                    ),
+                                    "THREE".to_string(),
+                                ),
+                                lsp::TextEdit::new(
+                                    lsp::Range::new(
+                                        lsp::Position::new(0, 35),
+                                        lsp::Position::new(0, 38),
+                                    ),
+                                    "THREE".to_string(),
+                                ),
+                            ],
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            }))
+        })
+        .next()
+        .await
+        .unwrap();
+    let mut transaction = response.await.unwrap().0;
+    assert_eq!(transaction.len(), 2);
+    assert_eq!(
+        transaction
+            .remove_entry(&buffer)
+            .unwrap()
+            .0
+            .update(cx, |buffer, _| buffer.text()),
+        "const THREE: usize = 1;"
+    );
+    assert_eq!(
+        transaction
+            .into_keys()
+            .next()
+            .unwrap()
+            .update(cx, |buffer, _| buffer.text()),
+        "const TWO: usize = one::THREE + one::THREE;"
+    );
+}
+
+#[gpui::test]
+async fn test_search(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+            "three.rs": "const THREE: usize = one::ONE + two::TWO;",
+            "four.rs": "const FOUR: usize = one::ONE + three::THREE;",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "TWO",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                false,
+                None
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/two.rs").to_string(), vec![6..9]),
+            (path!("dir/three.rs").to_string(), vec![37..40])
+        ])
+    );
+
+    let buffer_4 = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/four.rs"), cx)
+        })
+        .await
+        .unwrap();
+    buffer_4.update(cx, |buffer, cx| {
+        let text = "two::TWO";
+        buffer.edit([(20..28, text), (31..43, text)], None, cx);
+    });
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "TWO",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/two.rs").to_string(), vec![6..9]),
+            (path!("dir/three.rs").to_string(), vec![37..40]),
+            (path!("dir/four.rs").to_string(), vec![25..28, 36..39])
+        ])
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let search_query = "file";
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": r#"// Rust file one"#,
+            "one.ts": r#"// TypeScript file one"#,
+            "two.rs": r#"// Rust file two"#,
+            "two.ts": r#"// TypeScript file two"#,
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
+                Default::default(),
+                false,
+                None
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "If no inclusions match, no files should be returned"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.rs".to_owned()], PathStyle::local()).unwrap(),
+                Default::default(),
+                false,
+                None
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+        ]),
+        "Rust only search should give only Rust files"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "TypeScript only search should give only TypeScript files, even if other inclusions don't match anything"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(
+                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
+                    PathStyle::local()
+                )
+                .unwrap(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+        ]),
+        "Rust and typescript search should give both Rust and TypeScript files, even if other inclusions don't match anything"
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_exclusions(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let search_query = "file";
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": r#"// Rust file one"#,
+            "one.ts": r#"// TypeScript file one"#,
+            "two.rs": r#"// Rust file two"#,
+            "two.ts": r#"// TypeScript file two"#,
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "If no exclusions match, all files should be returned"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.rs".to_owned()], PathStyle::local()).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "Rust exclusion search should give only TypeScript files"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+        ]),
+        "TypeScript exclusion search should give only Rust files, even if other exclusions don't match anything"
+    );
+
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(
+                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
+                    PathStyle::local(),
+                )
+                .unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "Rust and typescript exclusion should give no files, even if other exclusions don't match anything"
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_buffer_exclusions(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let search_query = "file";
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": r#"// Rust file one"#,
+            "one.ts": r#"// TypeScript file one"#,
+            "two.rs": r#"// Rust file two"#,
+            "two.ts": r#"// TypeScript file two"#,
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let path_style = PathStyle::local();
+    let _buffer = project.update(cx, |project, cx| {
+        project.create_local_buffer("file", None, false, cx)
+    });
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.odd".to_owned()], path_style).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "If no exclusions match, all files should be returned"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.rs".to_owned()], path_style).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "Rust exclusion search should give only TypeScript files"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], path_style).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![8..12]),
+            (path!("dir/two.rs").to_string(), vec![8..12]),
+        ]),
+        "TypeScript exclusion search should give only Rust files, even if other exclusions don't match anything"
+    );
+
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                Default::default(),
+                PathMatcher::new(
+                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
+                    PathStyle::local(),
+                )
+                .unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "Rust and typescript exclusion should give no files, even if other exclusions don't match anything"
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_exclusions_and_inclusions(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let search_query = "file";
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": r#"// Rust file one"#,
+            "one.ts": r#"// TypeScript file one"#,
+            "two.rs": r#"// Rust file two"#,
+            "two.ts": r#"// TypeScript file two"#,
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
+                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "If both no exclusions and inclusions match, exclusions should win and return nothing"
+    );
+
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.ts".to_owned()], PathStyle::local()).unwrap(),
+                PathMatcher::new(&["*.ts".to_owned()], PathStyle::local()).unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "If both TypeScript exclusions and inclusions match, exclusions should win and return nothing files."
+    );
+
+    assert!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap()
+        .is_empty(),
+        "Non-matching inclusions and exclusions should not change that."
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                search_query,
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                PathMatcher::new(&["*.rs".to_owned(), "*.odd".to_owned()], PathStyle::local())
+                    .unwrap(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.ts").to_string(), vec![14..18]),
+            (path!("dir/two.ts").to_string(), vec![14..18]),
+        ]),
+        "Non-intersecting TypeScript inclusions and Rust exclusions should return TypeScript files"
+    );
+}
+
+#[gpui::test]
+async fn test_search_multiple_worktrees_with_inclusions(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/worktree-a"),
+        json!({
+            "haystack.rs": r#"// NEEDLE"#,
+            "haystack.ts": r#"// NEEDLE"#,
+        }),
+    )
+    .await;
+    fs.insert_tree(
+        path!("/worktree-b"),
+        json!({
+            "haystack.rs": r#"// NEEDLE"#,
+            "haystack.ts": r#"// NEEDLE"#,
+        }),
+    )
+    .await;
+
+    let path_style = PathStyle::local();
+    let project = Project::test(
+        fs.clone(),
+        [path!("/worktree-a").as_ref(), path!("/worktree-b").as_ref()],
+        cx,
+    )
+    .await;
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "NEEDLE",
+                false,
+                true,
+                false,
+                PathMatcher::new(&["worktree-a/*.rs".to_owned()], path_style).unwrap(),
+                Default::default(),
+                true,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(path!("worktree-a/haystack.rs").to_string(), vec![3..9])]),
+        "should only return results from included worktree"
+    );
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "NEEDLE",
+                false,
+                true,
+                false,
+                PathMatcher::new(&["worktree-b/*.rs".to_owned()], path_style).unwrap(),
+                Default::default(),
+                true,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(path!("worktree-b/haystack.rs").to_string(), vec![3..9])]),
+        "should only return results from included worktree"
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "NEEDLE",
+                false,
+                true,
+                false,
+                PathMatcher::new(&["*.ts".to_owned()], path_style).unwrap(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("worktree-a/haystack.ts").to_string(), vec![3..9]),
+            (path!("worktree-b/haystack.ts").to_string(), vec![3..9])
+        ]),
+        "should return results from both worktrees"
+    );
+}
+
+#[gpui::test]
+async fn test_search_in_gitignored_dirs(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".git": {},
+            ".gitignore": "**/target\n/node_modules\n",
+            "target": {
+                "index.txt": "index_key:index_value"
+            },
+            "node_modules": {
+                "eslint": {
+                    "index.ts": "const eslint_key = 'eslint value'",
+                    "package.json": r#"{ "some_key": "some value" }"#,
+                },
+                "prettier": {
+                    "index.ts": "const prettier_key = 'prettier value'",
+                    "package.json": r#"{ "other_key": "other value" }"#,
+                },
+            },
+            "package.json": r#"{ "main_key": "main value" }"#,
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let query = "key";
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                query,
+                false,
+                false,
+                false,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(path!("dir/package.json").to_string(), vec![8..11])]),
+        "Only one non-ignored file should have the query"
+    );
+
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let path_style = PathStyle::local();
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                query,
+                false,
+                false,
+                true,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/package.json").to_string(), vec![8..11]),
+            (path!("dir/target/index.txt").to_string(), vec![6..9]),
+            (
+                path!("dir/node_modules/prettier/package.json").to_string(),
+                vec![9..12]
+            ),
+            (
+                path!("dir/node_modules/prettier/index.ts").to_string(),
+                vec![15..18]
+            ),
+            (
+                path!("dir/node_modules/eslint/index.ts").to_string(),
+                vec![13..16]
+            ),
+            (
+                path!("dir/node_modules/eslint/package.json").to_string(),
+                vec![8..11]
+            ),
+        ]),
+        "Unrestricted search with ignored directories should find every file with the query"
+    );
+
+    let files_to_include =
+        PathMatcher::new(&["node_modules/prettier/**".to_owned()], path_style).unwrap();
+    let files_to_exclude = PathMatcher::new(&["*.ts".to_owned()], path_style).unwrap();
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                query,
+                false,
+                false,
+                true,
+                files_to_include,
+                files_to_exclude,
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(
+            path!("dir/node_modules/prettier/package.json").to_string(),
+            vec![9..12]
+        )]),
+        "With search including ignored prettier directory and excluding TS files, only one file should be found"
+    );
+}
+
+#[gpui::test]
+async fn test_search_with_unicode(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "// ПРИВЕТ? привет!",
+            "two.rs": "// ПРИВЕТ.",
+            "three.rs": "// привет",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let unicode_case_sensitive_query = SearchQuery::text(
+        "привет",
+        false,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    );
+    assert_matches!(unicode_case_sensitive_query, Ok(SearchQuery::Text { .. }));
+    assert_eq!(
+        search(&project, unicode_case_sensitive_query.unwrap(), cx)
+            .await
+            .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![17..29]),
+            (path!("dir/three.rs").to_string(), vec![3..15]),
+        ])
+    );
+
+    let unicode_case_insensitive_query = SearchQuery::text(
+        "привет",
+        false,
+        false,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    );
+    assert_matches!(
+        unicode_case_insensitive_query,
+        Ok(SearchQuery::Regex { .. })
+    );
+    assert_eq!(
+        search(&project, unicode_case_insensitive_query.unwrap(), cx)
+            .await
+            .unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![3..15, 17..29]),
+            (path!("dir/two.rs").to_string(), vec![3..15]),
+            (path!("dir/three.rs").to_string(), vec![3..15]),
+        ])
+    );
+
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "привет.",
+                false,
+                false,
+                false,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(path!("dir/two.rs").to_string(), vec![3..16]),])
+    );
+}
+
+#[gpui::test]
+async fn test_create_entry(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/one/two",
+        json!({
+            "three": {
+                "a.txt": "",
+                "four": {}
+            },
+            "c.rs": ""
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), ["/one/two/three".as_ref()], cx).await;
+    project
+        .update(cx, |project, cx| {
+            let id = project.worktrees(cx).next().unwrap().read(cx).id();
+            project.create_entry((id, rel_path("b..")), true, None, cx)
+        })
+        .await
+        .unwrap()
+        .into_included()
+        .unwrap();
+
+    assert_eq!(
+        fs.paths(true),
+        vec![
+            PathBuf::from(path!("/")),
+            PathBuf::from(path!("/one")),
+            PathBuf::from(path!("/one/two")),
+            PathBuf::from(path!("/one/two/c.rs")),
+            PathBuf::from(path!("/one/two/three")),
+            PathBuf::from(path!("/one/two/three/a.txt")),
+            PathBuf::from(path!("/one/two/three/b..")),
+            PathBuf::from(path!("/one/two/three/four")),
+        ]
+    );
+}
+
+#[gpui::test]
+async fn test_materialize_scratch_worktree(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/projects"), json!({})).await;
+    let project = Project::test(fs.clone(), [], cx).await;
+
+    let worktree = project
+        .update(cx, |project, cx| {
+            project.create_scratch_worktree("sketch", true, cx)
+        })
+        .await
+        .unwrap();
+    let worktree_id = worktree.read_with(cx, |worktree, _| worktree.id());
+    project
+        .update(cx, |project, cx| {
+            project.create_entry((worktree_id, rel_path("src/main.rs")), false, None, cx)
+        })
+        .await
+        .unwrap();
+    let (scratch_fs, scratch_path) = worktree.read_with(cx, |worktree, _| {
+        (
+            worktree.as_local().unwrap().fs().clone(),
+            worktree.abs_path(),
+        )
+    });
+    scratch_fs
+        .write(&scratch_path.join("src/main.rs"), b"fn main() {}")
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    assert!(project.read_with(cx, |project, cx| {
+        project.worktree_store().read(cx).is_scratch(worktree_id)
+    }));
+    assert!(!fs.is_dir(&scratch_path).await);
+    assert_eq!(
+        search(
+            &project,
+            SearchQuery::text(
+                "main",
+                false,
+                true,
+                false,
+                Default::default(),
+                Default::default(),
+                false,
+                None,
+            )
+            .unwrap(),
+            cx,
+        )
+        .await
+        .unwrap(),
+        HashMap::from_iter([(path!("sketch/src/main.rs").to_string(), vec![3..7])])
+    );
+
+    project
+        .update(cx, |project, cx| {
+            project.materialize_scratch_worktree(
+                worktree_id,
+                PathBuf::from(path!("/projects/sketch")),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+
+    assert_eq!(
+        fs.load(Path::new(path!("/projects/sketch/src/main.rs")))
+            .await
+            .unwrap(),
+        "fn main() {}"
+    );
+    worktree.read_with(cx, |worktree, _| {
+        assert_eq!(
+            worktree.abs_path().as_ref(),
+            Path::new(path!("/projects/sketch"))
+        );
+        assert!(worktree.entry_for_path(rel_path("src/main.rs")).is_some());
+    });
+    assert!(!project.read_with(cx, |project, cx| {
+        project.worktree_store().read(cx).is_scratch(worktree_id)
+    }));
+}
+
+#[gpui::test]
+async fn test_apply_fs_transaction(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "src": {
+                "lib.rs": "mod a;",
+                "a.rs": "",
+            },
+            "old.txt": "",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    // Deleted entries are backed up outside of the project while the transaction is applied.
+    let project_paths = || {
+        fs.paths(false)
+            .into_iter()
+            .filter(|path| path.starts_with(path!("/dir")))
+            .collect::<Vec<_>>()
+    };
+    let project_path = |path: &str| ProjectPath {
+        worktree_id,
+        path: rel_path(path).into(),
+    };
+
+    // A failing operation reverts the ones that were applied before it.
+    let result = project
+        .update(cx, |project, cx| {
+            project.apply_fs_transaction(
+                vec![
+                    FsOperation::CreateDirectory(project_path("crates")),
+                    FsOperation::Rename {
+                        old_path: project_path("src"),
+                        new_path: project_path("crates/src"),
+                    },
+                    FsOperation::Delete(project_path("old.txt")),
+                    FsOperation::Rename {
+                        old_path: project_path("crates/src/a.rs"),
+                        new_path: project_path("crates/src/lib.rs"),
+                    },
+                ],
+                cx,
+            )
+        })
+        .await;
+    assert!(result.is_err());
+    cx.executor().run_until_parked();
+    assert_eq!(
+        project_paths(),
+        vec![
+            PathBuf::from(path!("/dir")),
+            PathBuf::from(path!("/dir/old.txt")),
+            PathBuf::from(path!("/dir/src")),
+            PathBuf::from(path!("/dir/src/a.rs")),
+            PathBuf::from(path!("/dir/src/lib.rs")),
+        ]
+    );
+
+    project
+        .update(cx, |project, cx| {
+            project.apply_fs_transaction(
+                vec![
+                    FsOperation::CreateDirectory(project_path("crates")),
+                    FsOperation::Rename {
+                        old_path: project_path("src"),
+                        new_path: project_path("crates/src"),
+                    },
+                    FsOperation::Copy {
+                        source: project_path("crates/src/a.rs"),
+                        destination: project_path("crates/src/b.rs"),
+                    },
+                    FsOperation::Delete(project_path("old.txt")),
+                ],
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    assert_eq!(
+        project_paths(),
+        vec![
+            PathBuf::from(path!("/dir")),
+            PathBuf::from(path!("/dir/crates")),
+            PathBuf::from(path!("/dir/crates/src")),
+            PathBuf::from(path!("/dir/crates/src/a.rs")),
+            PathBuf::from(path!("/dir/crates/src/b.rs")),
+            PathBuf::from(path!("/dir/crates/src/lib.rs")),
+        ]
+    );
+    project.read_with(cx, |project, cx| {
+        let worktree = project.worktree_for_id(worktree_id, cx).unwrap();
+        let worktree = worktree.read(cx);
+        assert!(
+            worktree
+                .entry_for_path(rel_path("crates/src/b.rs"))
+                .is_some()
+        );
+        assert!(worktree.entry_for_path(rel_path("src")).is_none());
+        assert!(worktree.entry_for_path(rel_path("old.txt")).is_none());
+    });
+}
+
+#[gpui::test]
+async fn test_apply_fs_transaction_rollback_reverts_lsp_edits(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE;",
+            "existing.rs": "",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let project_path = |path: &str| ProjectPath {
+        worktree_id,
+        path: rel_path(path).into(),
+    };
+
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    let watched_paths = lsp::FileOperationRegistrationOptions {
+        filters: vec![
+            FileOperationFilter {
+                scheme: Some("file".to_owned()),
+                pattern: lsp::FileOperationPattern {
+                    glob: "**/*.rs".to_owned(),
+                    matches: Some(lsp::FileOperationPatternKind::File),
+                    options: None,
+                },
+            },
+            FileOperationFilter {
+                scheme: Some("file".to_owned()),
+                pattern: lsp::FileOperationPattern {
+                    glob: "**/lib".to_owned(),
+                    matches: Some(lsp::FileOperationPatternKind::Folder),
+                    options: None,
+                },
+            },
+        ],
+    };
+    let mut fake_servers = language_registry.register_fake_lsp(
+        "Rust",
+        FakeLspAdapter {
+            capabilities: lsp::ServerCapabilities {
+                workspace: Some(lsp::WorkspaceServerCapabilities {
+                    workspace_folders: None,
+                    file_operations: Some(lsp::WorkspaceFileOperationsServerCapabilities {
+                        will_rename: Some(watched_paths),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    let (two_buffer, _handle) = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer_with_lsp(path!("/dir/two.rs"), cx)
+        })
+        .await
+        .unwrap();
+    let fake_server = fake_servers.next().await.unwrap();
+
+    let mut will_rename_requests =
+        fake_server.set_request_handler::<WillRenameFiles, _, _>(|params, _| async move {
+            // The directory created earlier in the batch is reported as one.
+            assert_eq!(
+                params
+                    .files
+                    .iter()
+                    .map(|file| file.old_uri.as_str())
+                    .collect::<Vec<_>>(),
+                vec![
+                    uri!("file:///dir/lib"),
+                    uri!("file:///dir/one.rs"),
+                    uri!("file:///dir/two.rs"),
+                ]
+            );
+            Ok(Some(lsp::WorkspaceEdit {
+                changes: Some(
+                    [(
+                        Uri::from_str(uri!("file:///dir/two.rs")).unwrap(),
+                        vec![lsp::TextEdit {
+                            range: lsp::Range::new(
+                                lsp::Position::new(0, 19),
+                                lsp::Position::new(0, 22),
+                            ),
+                            new_text: "three".to_owned(),
+                        }],
+                    )]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            }))
+        });
+
+    let result = project.update(cx, |project, cx| {
+        project.apply_fs_transaction(
+            vec![
+                FsOperation::CreateDirectory(project_path("lib")),
+                FsOperation::Rename {
+                    old_path: project_path("lib"),
+                    new_path: project_path("lib2"),
+                },
+                FsOperation::Rename {
+                    old_path: project_path("one.rs"),
+                    new_path: project_path("three.rs"),
+                },
+                FsOperation::Rename {
+                    old_path: project_path("two.rs"),
+                    new_path: project_path("existing.rs"),
+                },
+            ],
+            cx,
+        )
+    });
+    will_rename_requests.next().await.unwrap();
+    assert!(result.await.is_err());
+    cx.executor().run_until_parked();
+
+    two_buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "const TWO: usize = one::ONE;");
+    });
+    assert!(
+        fs.paths(false)
+            .contains(&PathBuf::from(path!("/dir/one.rs")))
+    );
+}
+
+#[gpui::test]
+async fn test_duplicate_entry(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.txt": "a",
+            "src": {
+                "lib.rs": "",
             },
-            DiagnosticEntry {
-                range: Point::new(2, 8)..Point::new(2, 17),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::ERROR,
-                    message: "error 2".to_string(),
-                    group_id: 0,
-                    is_primary: true,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            }
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let entry_id = |path: &str, cx: &mut gpui::TestAppContext| {
+        project.read_with(cx, |project, cx| {
+            let worktree = project.worktrees(cx).next().unwrap();
+            worktree.read(cx).entry_for_path(rel_path(path)).unwrap().id
+        })
+    };
+
+    for _ in 0..2 {
+        let a_id = entry_id("a.txt", cx);
+        project
+            .update(cx, |project, cx| project.duplicate_entry(a_id, cx))
+            .await
+            .unwrap();
+    }
+    let src_id = entry_id("src", cx);
+    project
+        .update(cx, |project, cx| project.duplicate_entry(src_id, cx))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        fs.paths(false),
+        vec![
+            PathBuf::from(path!("/")),
+            PathBuf::from(path!("/dir")),
+            PathBuf::from(path!("/dir/a copy 2.txt")),
+            PathBuf::from(path!("/dir/a copy.txt")),
+            PathBuf::from(path!("/dir/a.txt")),
+            PathBuf::from(path!("/dir/src")),
+            PathBuf::from(path!("/dir/src copy")),
+            PathBuf::from(path!("/dir/src copy/lib.rs")),
+            PathBuf::from(path!("/dir/src/lib.rs")),
         ]
     );
+}
+
+#[gpui::test]
+async fn test_normalize_line_endings(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.txt": "one\r\ntwo",
+            "b.txt": "four\r\n",
+            "sub": { "c.txt": "three\n" },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+
+    // Buffers with unsaved changes are converted, but not saved.
+    let buffer_b = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.txt"), cx)
+        })
+        .await
+        .unwrap();
+    buffer_b.update(cx, |buffer, cx| buffer.edit([(0..0, "zero ")], None, cx));
+
+    let transaction = project
+        .update(cx, |project, cx| {
+            project.normalize_line_endings(
+                [
+                    ProjectPath::from((worktree_id, rel_path("a.txt"))),
+                    ProjectPath::from((worktree_id, rel_path("b.txt"))),
+                    ProjectPath::from((worktree_id, rel_path("sub"))),
+                ],
+                LineEndingNormalization {
+                    line_ending: Some(LineEnding::Unix),
+                    final_newline: Some(true),
+                },
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
 
+    assert_eq!(transaction.0.len(), 2);
     assert_eq!(
-        buffer.diagnostic_group::<Point>(0).collect::<Vec<_>>(),
-        &[
-            DiagnosticEntry {
-                range: Point::new(1, 13)..Point::new(1, 15),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 2 hint 1".to_string(),
-                    group_id: 0,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(1, 13)..Point::new(1, 15),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 2 hint 2".to_string(),
-                    group_id: 0,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(2, 8)..Point::new(2, 17),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::ERROR,
-                    message: "error 2".to_string(),
-                    group_id: 0,
-                    is_primary: true,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "one\ntwo\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/b.txt").as_ref()).await.unwrap(),
+        "four\r\n"
+    );
+    assert_eq!(
+        fs.load(path!("/dir/sub/c.txt").as_ref()).await.unwrap(),
+        "three\n"
+    );
+    buffer_b.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+        assert!(buffer.is_dirty());
+    });
+
+    // Undoing the transaction restores the previous line endings and final newlines.
+    for (buffer, buffer_transaction) in &transaction.0 {
+        buffer.update(cx, |buffer, cx| {
+            buffer.undo_transaction(buffer_transaction.id, cx);
+        });
+    }
+    buffer_b.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert_eq!(buffer.text(), "zero four\n");
+    });
+    let buffer_a = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    buffer_a.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert_eq!(buffer.text(), "one\ntwo");
+    });
+}
+
+#[gpui::test]
+async fn test_read_only_and_locked_buffers(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": "one\n", "b.txt": "two\n" }))
+        .await;
+    fs.set_read_only(path!("/dir/a.txt"), true);
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+
+    let buffer_a = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    buffer_a.update(cx, |buffer, cx| {
+        assert!(buffer.read_only());
+        assert_eq!(
+            buffer.try_edit([(0..0, "zero\n")], None, cx),
+            Err(BufferLockedError {
+                lock: BufferLock::FileReadOnly
+            })
+        );
+        assert_eq!(buffer.text(), "one\n");
+        buffer.edit([(0..0, "zero\n")], None, cx);
+    });
+
+    // Read-only files can only be saved with elevated privileges.
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer_a.clone(), cx))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "one\n"
+    );
+    project
+        .update(cx, |project, cx| {
+            project.save_buffer_elevated(buffer_a.clone(), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "zero\none\n"
+    );
+    buffer_a.read_with(cx, |buffer, _| assert!(!buffer.is_dirty()));
+
+    let buffer_b = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.txt"), cx)
+        })
+        .await
+        .unwrap();
+    project
+        .update(cx, |project, cx| {
+            project.set_buffer_locked(&buffer_b, true, cx)
+        })
+        .await
+        .unwrap();
+    buffer_b.update(cx, |buffer, cx| {
+        assert_eq!(buffer.lock(), Some(BufferLock::Locked));
+        assert_eq!(
+            buffer.try_edit([(0..0, "one\n")], None, cx),
+            Err(BufferLockedError {
+                lock: BufferLock::Locked
+            })
+        );
+    });
+
+    // Locks apply to the buffers of the same file in other instances.
+    let other_project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let other_buffer_b = other_project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.txt"), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    other_buffer_b.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.lock(), Some(BufferLock::Locked))
+    });
+
+    project
+        .update(cx, |project, cx| {
+            project.set_buffer_locked(&buffer_b, false, cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.run_until_parked();
+    other_buffer_b.read_with(cx, |buffer, _| assert_eq!(buffer.lock(), None));
+    buffer_b.update(cx, |buffer, cx| {
+        assert!(!buffer.read_only());
+        assert!(buffer.try_edit([(0..0, "one\n")], None, cx).is_ok());
+        assert_eq!(buffer.text(), "one\ntwo\n");
+    });
+}
+
+#[gpui::test]
+async fn test_evict_buffers_over_memory_budget(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings(cx, |settings| {
+                settings.project.buffer_memory_budget_mb = Some(1);
+            });
+        })
+    });
+
+    let text = "a".repeat(600 * 1024);
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": text, "b.txt": text }))
+        .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    cx.update(|cx| {
+        let evicted = evicted.clone();
+        cx.subscribe(&project, move |_, event, _| {
+            if let Event::BufferEvicted { buffer_id, .. } = event {
+                evicted.lock().push(*buffer_id);
             }
-        ]
+        })
+        .detach();
+    });
+
+    let buffer_a = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    assert!(evicted.lock().is_empty());
+
+    // The least recently used buffer is evicted once the budget is exceeded.
+    let buffer_b = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.txt"), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    let buffer_a_id = buffer_a.read_with(cx, |buffer, _| buffer.remote_id());
+    let buffer_b_id = buffer_b.read_with(cx, |buffer, _| buffer.remote_id());
+    assert_eq!(mem::take(&mut *evicted.lock()), [buffer_a_id]);
+    project.read_with(cx, |project, cx| {
+        assert_eq!(
+            project.buffer_store().read(cx).buffer_memory(),
+            2 * text.len()
+        );
+    });
+
+    // Visible buffers are never evicted.
+    let project_path_a = project.read_with(cx, |project, cx| {
+        project.find_project_path(path!("/dir/a.txt"), cx).unwrap()
+    });
+    project.update(cx, |project, cx| {
+        project.set_visible_paths([project_path_a], cx)
+    });
+    cx.run_until_parked();
+    assert_eq!(mem::take(&mut *evicted.lock()), [buffer_b_id]);
+
+    // Neither are dirty ones.
+    buffer_b.update(cx, |buffer, cx| buffer.edit([(0..0, "b")], None, cx));
+    cx.run_until_parked();
+    assert!(evicted.lock().is_empty());
+
+    drop(buffer_a);
+    cx.run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert_eq!(
+            project.buffer_store().read(cx).buffer_memory(),
+            text.len() + 1
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_open_hex_buffer(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": "one\n" }))
+        .await;
+    fs.insert_file(
+        path!("/dir/b.data"),
+        vec![0x00, 0xFF, 0x12, 0x00, 0x99, 0x88, 0x77, 0x66, 0x00],
+    )
+    .await;
+    fs.insert_file(path!("/dir/c.bin"), vec![0x00, 0x01]).await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let (project_path, text_path, binary_extension_path) = project.read_with(cx, |project, cx| {
+        (
+            project.find_project_path(path!("/dir/b.data"), cx).unwrap(),
+            project.find_project_path(path!("/dir/a.txt"), cx).unwrap(),
+            project.find_project_path(path!("/dir/c.bin"), cx).unwrap(),
+        )
+    });
+
+    // Files with binary extensions aren't decoded before they're opened as hex buffers.
+    cx.update(|cx| {
+        assert!(Buffer::try_open(&project, &binary_extension_path, cx).is_none());
+        assert!(HexBuffer::try_open(&project, &binary_extension_path, cx).is_some());
+        assert!(HexBuffer::try_open(&project, &project_path, cx).is_none());
+    });
+
+    let result = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.data"), cx)
+        })
+        .await;
+    assert!(result.is_err());
+    project.read_with(cx, |project, cx| {
+        let buffer_store = project.buffer_store().read(cx);
+        assert!(buffer_store.is_binary_path(&project_path));
+        assert!(!buffer_store.is_binary_path(&text_path));
+    });
+    cx.update(|cx| {
+        assert!(Buffer::try_open(&project, &project_path, cx).is_none());
+        assert!(Buffer::try_open(&project, &text_path, cx).is_some());
+    });
+
+    let hex_buffer = project
+        .update(cx, |project, cx| project.open_hex_buffer(project_path, cx))
+        .await
+        .unwrap();
+    hex_buffer.read_with(cx, |hex_buffer, _| {
+        assert_eq!(hex_buffer.row_count(), 1);
+        assert_eq!(
+            hex_buffer.hex_row(0).as_deref(),
+            Some("00000000  00 ff 12 00 99 88 77 66  00                      |.........|")
+        );
+        assert_eq!(hex_buffer.hex_row(1), None);
+    });
+
+    hex_buffer.update(cx, |hex_buffer, cx| {
+        hex_buffer.edit(1..3, &[0x41, 0x42], cx)
+    });
+    hex_buffer.read_with(cx, |hex_buffer, _| assert!(hex_buffer.is_dirty));
+    hex_buffer
+        .update(cx, |hex_buffer, cx| hex_buffer.save(cx))
+        .await
+        .unwrap();
+    hex_buffer.read_with(cx, |hex_buffer, _| assert!(!hex_buffer.is_dirty));
+    assert_eq!(
+        fs.load_bytes(path!("/dir/b.data").as_ref()).await.unwrap(),
+        vec![0x00, 0x41, 0x42, 0x00, 0x99, 0x88, 0x77, 0x66, 0x00]
     );
 
+    // Saving fails when the file was changed on disk in the meantime.
+    fs.insert_file(path!("/dir/b.data"), vec![0x00, 0x01, 0x02])
+        .await;
+    hex_buffer.update(cx, |hex_buffer, cx| hex_buffer.edit(0..1, &[0x43], cx));
+    let result = hex_buffer
+        .update(cx, |hex_buffer, cx| hex_buffer.save(cx))
+        .await;
+    assert!(result.is_err());
     assert_eq!(
-        buffer.diagnostic_group::<Point>(1).collect::<Vec<_>>(),
-        &[
-            DiagnosticEntry {
-                range: Point::new(1, 8)..Point::new(1, 9),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::WARNING,
-                    message: "error 1".to_string(),
-                    group_id: 1,
-                    is_primary: true,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-            DiagnosticEntry {
-                range: Point::new(1, 8)..Point::new(1, 9),
-                diagnostic: Diagnostic {
-                    severity: DiagnosticSeverity::HINT,
-                    message: "error 1 hint 1".to_string(),
-                    group_id: 1,
-                    is_primary: false,
-                    source_kind: DiagnosticSourceKind::Pushed,
-                    ..Diagnostic::default()
-                }
-            },
-        ]
+        fs.load_bytes(path!("/dir/b.data").as_ref()).await.unwrap(),
+        vec![0x00, 0x01, 0x02]
     );
+    hex_buffer
+        .update(cx, |hex_buffer, cx| hex_buffer.reload(cx))
+        .await
+        .unwrap();
+    hex_buffer.read_with(cx, |hex_buffer, _| {
+        assert_eq!(hex_buffer.bytes(), [0x00, 0x01, 0x02]);
+        assert!(!hex_buffer.is_dirty);
+    });
+
+    // Once the file is changed and no longer open as a hex buffer, it's decoded again.
+    drop(hex_buffer);
+    fs.insert_file(path!("/dir/b.data"), b"text".to_vec()).await;
+    cx.executor().run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert!(
+            !project
+                .buffer_store()
+                .read(cx)
+                .is_binary_path(&project_path)
+        );
+    });
+    project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.data"), cx)
+        })
+        .await
+        .unwrap();
 }
 
 #[gpui::test]
-async fn test_lsp_rename_notifications(cx: &mut gpui::TestAppContext) {
+async fn test_autosave_buffers(cx: &mut gpui::TestAppContext) {
     init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings(cx, |settings| {
+                settings.workspace.autosave = Some(settings::AutosaveSetting::AfterDelay {
+                    milliseconds: settings::DelayMs(500),
+                });
+                settings.project.worktree.autosave_exclusions = Some(vec!["**/*.lock".into()]);
+            });
+        })
+    });
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
-        json!({
-            "one.rs": "const ONE: usize = 1;",
-            "two": {
-                "two.rs": "const TWO: usize = one::ONE + one::ONE;"
-            }
-
-        }),
+        json!({ "a.txt": "one\ntwo\n", "b.lock": "one\n" }),
     )
     .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let skipped = Arc::new(Mutex::new(Vec::new()));
+    cx.update(|cx| {
+        let skipped = skipped.clone();
+        cx.subscribe(&project, move |_, event, _| {
+            if let Event::AutosaveSkipped(buffer) = event {
+                skipped.lock().push(buffer.clone());
+            }
+        })
+        .detach();
+    });
+    let buffer_a = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
+        .await
+        .unwrap();
+    let buffer_b = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/b.lock"), cx)
+        })
+        .await
+        .unwrap();
 
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(rust_lang());
-    let watched_paths = lsp::FileOperationRegistrationOptions {
-        filters: vec![
-            FileOperationFilter {
-                scheme: Some("file".to_owned()),
-                pattern: lsp::FileOperationPattern {
-                    glob: "**/*.rs".to_owned(),
-                    matches: Some(lsp::FileOperationPatternKind::File),
-                    options: None,
-                },
-            },
-            FileOperationFilter {
-                scheme: Some("file".to_owned()),
-                pattern: lsp::FileOperationPattern {
-                    glob: "**/**".to_owned(),
-                    matches: Some(lsp::FileOperationPatternKind::Folder),
-                    options: None,
-                },
-            },
-        ],
-    };
-    let mut fake_servers = language_registry.register_fake_lsp(
-        "Rust",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                workspace: Some(lsp::WorkspaceServerCapabilities {
-                    workspace_folders: None,
-                    file_operations: Some(lsp::WorkspaceFileOperationsServerCapabilities {
-                        did_rename: Some(watched_paths.clone()),
-                        will_rename: Some(watched_paths),
-                        ..Default::default()
-                    }),
-                }),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
+    // Edits don't autosave buffers by themselves, as they may come from language servers or
+    // collaborators; the UI autosaves the buffers the user edits.
+    for buffer in [&buffer_a, &buffer_b] {
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..3, "ONE")], None, cx));
+    }
+    cx.executor().advance_clock(Duration::from_millis(1000));
+    cx.run_until_parked();
+    assert!(buffer_a.read_with(cx, |buffer, _| buffer.is_dirty()));
 
-    let _ = project
+    // Autosaving skips the excluded buffers.
+    project
         .update(cx, |project, cx| {
-            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
+            project.autosave_buffers(HashSet::from_iter([buffer_a.clone(), buffer_b.clone()]), cx)
         })
         .await
         .unwrap();
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "ONE\ntwo\n"
+    );
+    assert!(!buffer_a.read_with(cx, |buffer, _| buffer.is_dirty()));
+    assert_eq!(
+        fs.load(path!("/dir/b.lock").as_ref()).await.unwrap(),
+        "one\n"
+    );
+    assert!(buffer_b.read_with(cx, |buffer, _| buffer.is_dirty()));
+    assert!(skipped.lock().is_empty());
 
-    let fake_server = fake_servers.next().await.unwrap();
-    let response = project.update(cx, |project, cx| {
-        let worktree = project.worktrees(cx).next().unwrap();
-        let entry = worktree
-            .read(cx)
-            .entry_for_path(rel_path("one.rs"))
-            .unwrap();
-        project.rename_entry(
-            entry.id,
-            (worktree.read(cx).id(), rel_path("three.rs")).into(),
-            cx,
-        )
-    });
-    let expected_edit = lsp::WorkspaceEdit {
-        changes: None,
-        document_changes: Some(DocumentChanges::Edits({
-            vec![TextDocumentEdit {
-                edits: vec![lsp::Edit::Plain(lsp::TextEdit {
-                    range: lsp::Range {
-                        start: lsp::Position {
-                            line: 0,
-                            character: 1,
-                        },
-                        end: lsp::Position {
-                            line: 0,
-                            character: 3,
-                        },
-                    },
-                    new_text: "This is not a drill".to_owned(),
-                })],
-                text_document: lsp::OptionalVersionedTextDocumentIdentifier {
-                    uri: Uri::from_str(uri!("file:///dir/two/two.rs")).unwrap(),
-                    version: Some(1337),
-                },
-            }]
-        })),
-        change_annotations: None,
-    };
-    let resolved_workspace_edit = Arc::new(OnceLock::new());
-    fake_server
-        .set_request_handler::<WillRenameFiles, _, _>({
-            let resolved_workspace_edit = resolved_workspace_edit.clone();
-            let expected_edit = expected_edit.clone();
-            move |params, _| {
-                let resolved_workspace_edit = resolved_workspace_edit.clone();
-                let expected_edit = expected_edit.clone();
-                async move {
-                    assert_eq!(params.files.len(), 1);
-                    assert_eq!(params.files[0].old_uri, uri!("file:///dir/one.rs"));
-                    assert_eq!(params.files[0].new_uri, uri!("file:///dir/three.rs"));
-                    resolved_workspace_edit.set(expected_edit.clone()).unwrap();
-                    Ok(Some(expected_edit))
-                }
-            }
+    // Buffers that conflict with their files on disk aren't autosaved.
+    buffer_a.update(cx, |buffer, cx| buffer.edit([(4..7, "TWO")], None, cx));
+    fs.save(
+        path!("/dir/a.txt").as_ref(),
+        &"ONE\nTwo\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.run_until_parked();
+    assert!(buffer_a.read_with(cx, |buffer, _| buffer.has_conflict()));
+    project
+        .update(cx, |project, cx| {
+            project.autosave_buffers(HashSet::from_iter([buffer_a.clone()]), cx)
         })
-        .next()
         .await
         .unwrap();
-    let _ = response.await.unwrap();
-    fake_server
-        .handle_notification::<DidRenameFiles, _>(|params, _| {
-            assert_eq!(params.files.len(), 1);
-            assert_eq!(params.files[0].old_uri, uri!("file:///dir/one.rs"));
-            assert_eq!(params.files[0].new_uri, uri!("file:///dir/three.rs"));
+    assert_eq!(*skipped.lock(), [buffer_a.clone()]);
+    assert_eq!(
+        fs.load(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        "ONE\nTwo\n"
+    );
+
+    let after_delay = settings::AutosaveSetting::AfterDelay {
+        milliseconds: settings::DelayMs(500),
+    };
+    assert!(AutosaveTrigger::Delay.is_enabled(after_delay));
+    assert!(!AutosaveTrigger::FocusChange.is_enabled(after_delay));
+    assert!(AutosaveTrigger::WindowChange.is_enabled(settings::AutosaveSetting::OnFocusChange));
+    assert!(!AutosaveTrigger::FocusChange.is_enabled(settings::AutosaveSetting::OnWindowChange));
+}
+
+#[gpui::test]
+async fn test_merge_file_changes_on_disk(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({ "a.txt": "one\ntwo\nthree\nfour\nfive\n" }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
         })
-        .next()
         .await
         .unwrap();
-    assert_eq!(resolved_workspace_edit.get(), Some(&expected_edit));
+    buffer.update(cx, |buffer, cx| buffer.edit([(4..7, "TWO")], None, cx));
+
+    // Changes on disk that don't touch the buffer's own changes are merged into it.
+    fs.save(
+        path!("/dir/a.txt").as_ref(),
+        &"one\ntwo\nthree\nfour\nFIVE\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "one\nTWO\nthree\nfour\nFIVE\n");
+        assert!(buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+        assert!(buffer.merge_conflicts().is_empty());
+    });
+
+    // Changes to the same regions conflict, and leave the buffer's text in place.
+    fs.save(
+        path!("/dir/a.txt").as_ref(),
+        &"one\nTwo\nthree\nFOUR\nFIVE\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), "one\nTWO\nthree\nFOUR\nFIVE\n");
+        assert!(buffer.has_conflict());
+        let conflicts = buffer.merge_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].range.to_offset(&*buffer), 4..7);
+        assert_eq!(conflicts[0].disk_text.as_ref(), "Two");
+
+        buffer.resolve_merge_conflict(0, MergeConflictResolution::KeepDisk, cx);
+        assert_eq!(buffer.text(), "one\nTwo\nthree\nFOUR\nFIVE\n");
+        assert!(!buffer.has_conflict());
+    });
+
+    // The merged changes are the base of the next merge.
+    fs.save(
+        path!("/dir/a.txt").as_ref(),
+        &"one\nTwo\nthree\nFOUR\nFIVE\nsix\n".into(),
+        LineEnding::Unix,
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "one\nTwo\nthree\nFOUR\nFIVE\nsix\n");
+        assert!(!buffer.is_dirty());
+        assert!(!buffer.has_conflict());
+    });
 }
 
 #[gpui::test]
-async fn test_rename(cx: &mut gpui::TestAppContext) {
-    // hi
+async fn test_unsaved_journal(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": "one" }))
+        .await;
+    // The journal of an earlier session of this project that stopped running.
+    let stale_session_dir = paths::unsaved_journal_dir().join("1-0-0");
     fs.insert_tree(
-        path!("/dir"),
+        &stale_session_dir,
         json!({
-            "one.rs": "const ONE: usize = 1;",
-            "two.rs": "const TWO: usize = one::ONE + one::ONE;"
+            "heartbeat": "0",
+            "roots": serde_json::to_string(&[path!("/dir")]).unwrap(),
+            "1.json": r#"{ "abs_path": null, "text": "untitled" }"#,
+        }),
+    )
+    .await;
+    // The journal of an earlier session of another project, which is left for that project.
+    let other_session_dir = paths::unsaved_journal_dir().join("2-0-0");
+    fs.insert_tree(
+        &other_session_dir,
+        json!({
+            "heartbeat": "0",
+            "roots": serde_json::to_string(&[path!("/other")]).unwrap(),
+            "1.json": r#"{ "abs_path": null, "text": "other" }"#,
         }),
     )
     .await;
 
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    cx.run_until_parked();
+    let unsaved_journal =
+        project.read_with(cx, |project, _| project.unsaved_journal().cloned().unwrap());
+    let recoverable = unsaved_journal.read_with(cx, |unsaved_journal, _| {
+        unsaved_journal.recoverable_buffers().to_vec()
+    });
+    assert_eq!(recoverable.len(), 1);
+    assert_eq!(recoverable[0].abs_path, None);
 
-    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
-    language_registry.add(rust_lang());
-    let mut fake_servers = language_registry.register_fake_lsp(
-        "Rust",
-        FakeLspAdapter {
-            capabilities: lsp::ServerCapabilities {
-                rename_provider: Some(lsp::OneOf::Right(lsp::RenameOptions {
-                    prepare_provider: Some(true),
-                    work_done_progress_options: Default::default(),
-                })),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-    );
-
-    let (buffer, _handle) = project
+    let recovered = project
         .update(cx, |project, cx| {
-            project.open_local_buffer_with_lsp(path!("/dir/one.rs"), cx)
+            project.recover_buffer(recoverable[0].clone(), cx)
         })
         .await
         .unwrap();
-
-    let fake_server = fake_servers.next().await.unwrap();
-
-    let response = project.update(cx, |project, cx| {
-        project.prepare_rename(buffer.clone(), 7, cx)
+    recovered.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "untitled");
+        assert!(buffer.is_dirty());
     });
-    fake_server
-        .set_request_handler::<lsp::request::PrepareRenameRequest, _, _>(|params, _| async move {
-            assert_eq!(
-                params.text_document.uri.as_str(),
-                uri!("file:///dir/one.rs")
-            );
-            assert_eq!(params.position, lsp::Position::new(0, 7));
-            Ok(Some(lsp::PrepareRenameResponse::Range(lsp::Range::new(
-                lsp::Position::new(0, 6),
-                lsp::Position::new(0, 9),
-            ))))
+    assert!(!fs.is_dir(&stale_session_dir).await);
+    assert!(fs.is_dir(&other_session_dir).await);
+    unsaved_journal.read_with(cx, |unsaved_journal, _| {
+        assert!(unsaved_journal.recoverable_buffers().is_empty())
+    });
+    fs.remove_dir(
+        &other_session_dir,
+        RemoveOptions {
+            recursive: true,
+            ignore_if_not_exists: false,
+        },
+    )
+    .await
+    .unwrap();
+
+    // The contents of dirty buffers are journaled, and forgotten once they're saved.
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
         })
-        .next()
         .await
         .unwrap();
-    let response = response.await.unwrap();
-    let PrepareRenameResponse::Success(range) = response else {
-        panic!("{:?}", response);
+    buffer.update(cx, |buffer, cx| buffer.set_text("two", cx));
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.run_until_parked();
+    let journaled_texts = || {
+        fs.files_with_contents(paths::unsaved_journal_dir())
+            .into_iter()
+            .filter(|(path, _)| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .map(|(_, contents)| {
+                serde_json::from_slice::<serde_json::Value>(&contents).unwrap()["text"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .sorted()
+            .collect::<Vec<_>>()
     };
-    let range = buffer.update(cx, |buffer, _| range.to_offset(buffer));
-    assert_eq!(range, 6..9);
+    assert_eq!(journaled_texts(), ["two", "untitled"]);
 
-    let response = project.update(cx, |project, cx| {
-        project.perform_rename(buffer.clone(), 7, "THREE".to_string(), cx)
-    });
-    fake_server
-        .set_request_handler::<lsp::request::Rename, _, _>(|params, _| async move {
-            assert_eq!(
-                params.text_document_position.text_document.uri.as_str(),
-                uri!("file:///dir/one.rs")
-            );
-            assert_eq!(
-                params.text_document_position.position,
-                lsp::Position::new(0, 7)
-            );
-            assert_eq!(params.new_name, "THREE");
-            Ok(Some(lsp::WorkspaceEdit {
-                changes: Some(
-                    [
-                        (
-                            lsp::Uri::from_file_path(path!("/dir/one.rs")).unwrap(),
-                            vec![lsp::TextEdit::new(
-                                lsp::Range::new(lsp::Position::new(0, 6), lsp::Position::new(0, 9)),
-                                "THREE".to_string(),
-                            )],
-                        ),
-                        (
-                            lsp::Uri::from_file_path(path!("/dir/two.rs")).unwrap(),
-                            vec![
-                                lsp::TextEdit::new(
-                                    lsp::Range::new(
-                                        lsp::Position::new(0, 24),
-                                        lsp::Position::new(0, 27),
-                                    ),
-                                    "THREE".to_string(),
-                                ),
-                                lsp::TextEdit::new(
-                                    lsp::Range::new(
-                                        lsp::Position::new(0, 35),
-                                        lsp::Position::new(0, 38),
-                                    ),
-                                    "THREE".to_string(),
-                                ),
-                            ],
-                        ),
-                    ]
-                    .into_iter()
-                    .collect(),
-                ),
-                ..Default::default()
-            }))
-        })
-        .next()
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
         .await
         .unwrap();
-    let mut transaction = response.await.unwrap().0;
-    assert_eq!(transaction.len(), 2);
-    assert_eq!(
-        transaction
-            .remove_entry(&buffer)
-            .unwrap()
-            .0
-            .update(cx, |buffer, _| buffer.text()),
-        "const THREE: usize = 1;"
-    );
-    assert_eq!(
-        transaction
-            .into_keys()
-            .next()
-            .unwrap()
-            .update(cx, |buffer, _| buffer.text()),
-        "const TWO: usize = one::THREE + one::THREE;"
-    );
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.run_until_parked();
+    assert_eq!(journaled_texts(), ["untitled"]);
+
+    // Nothing is journaled while unsaved buffers aren't restored.
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings(cx, |settings| {
+                settings
+                    .session
+                    .get_or_insert_default()
+                    .restore_unsaved_buffers = Some(false);
+            });
+        })
+    });
+    cx.run_until_parked();
+    assert!(journaled_texts().is_empty());
 }
 
 #[gpui::test]
-async fn test_search(cx: &mut gpui::TestAppContext) {
+async fn test_unsaved_journal_after_crash(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": "one" }))
+        .await;
+    // The journal of a session that recorded that it was running just before crashing.
+    let heartbeat = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .to_string();
     fs.insert_tree(
-        path!("/dir"),
+        paths::unsaved_journal_dir().join("1-0-0"),
         json!({
-            "one.rs": "const ONE: usize = 1;",
-            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
-            "three.rs": "const THREE: usize = one::ONE + two::TWO;",
-            "four.rs": "const FOUR: usize = one::ONE + three::THREE;",
+            "heartbeat": heartbeat,
+            "1.json": r#"{ "abs_path": null, "text": "untitled" }"#,
+        }),
+    )
+    .await;
+    // The journal of another session that's still running.
+    fs.insert_tree(
+        paths::unsaved_journal_dir().join("2-0-0"),
+        json!({
+            "heartbeat": heartbeat,
+            "1.json": r#"{ "abs_path": null, "text": "running" }"#,
         }),
     )
     .await;
+
+    // Without knowing that the session crashed, its journal may belong to a running session.
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "TWO",
-                false,
-                true,
-                false,
-                Default::default(),
-                Default::default(),
-                false,
-                None
-            )
-            .unwrap(),
-            cx
+    cx.run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert!(
+            project
+                .unsaved_journal()
+                .unwrap()
+                .read(cx)
+                .recoverable_buffers()
+                .is_empty()
         )
+    });
+
+    let crashed = unsaved_journal::load_crashed_buffers(fs.as_ref(), 1)
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/two.rs").to_string(), vec![6..9]),
-            (path!("dir/three.rs").to_string(), vec![37..40])
-        ])
-    );
+        .unwrap();
+    assert_eq!(crashed.len(), 1);
+    assert_eq!(crashed[0].abs_path, None);
+    assert_eq!(crashed[0].text, "untitled");
+}
 
-    let buffer_4 = project
+#[gpui::test]
+async fn test_local_history(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(path!("/dir"), json!({ "a.txt": "one" }))
+        .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let local_history =
+        project.read_with(cx, |project, _| project.local_history().cloned().unwrap());
+    let buffer = project
         .update(cx, |project, cx| {
-            project.open_local_buffer(path!("/dir/four.rs"), cx)
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
         })
         .await
         .unwrap();
-    buffer_4.update(cx, |buffer, cx| {
-        let text = "two::TWO";
-        buffer.edit([(20..28, text), (31..43, text)], None, cx);
-    });
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "TWO",
-                false,
-                true,
-                false,
-                Default::default(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    for text in ["two", "three"] {
+        buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+        project
+            .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+            .await
+            .unwrap();
+        cx.run_until_parked();
+    }
+    // Saving a buffer without changes doesn't add a version.
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/two.rs").to_string(), vec![6..9]),
-            (path!("dir/three.rs").to_string(), vec![37..40]),
-            (path!("dir/four.rs").to_string(), vec![25..28, 36..39])
-        ])
+        .unwrap();
+    cx.run_until_parked();
+
+    let versions = local_history
+        .read_with(cx, |local_history, cx| {
+            local_history.versions(Path::new(path!("/dir/a.txt")), cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(versions.len(), 2);
+    assert!(versions[0].timestamp > versions[1].timestamp);
+    assert!(
+        versions
+            .iter()
+            .all(|version| version.trigger == LocalHistoryTrigger::Save)
     );
+    let text = local_history
+        .read_with(cx, |local_history, cx| {
+            local_history.load_version(&versions[1], cx)
+        })
+        .await
+        .unwrap();
+    assert_eq!(text, "two");
+
+    local_history
+        .read_with(cx, |local_history, cx| {
+            local_history.restore_version(buffer.clone(), &versions[1], cx)
+        })
+        .await
+        .unwrap();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), "two");
+        assert!(buffer.is_dirty());
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), "three");
+    });
 }
 
 #[gpui::test]
-async fn test_search_with_inclusions(cx: &mut gpui::TestAppContext) {
+async fn test_local_history_settings(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
-    let search_query = "file";
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".zed": {
+                "settings.json": r#"{ "local_history": { "max_versions_per_file": 1 } }"#
+            },
+            "a.txt": "one",
+            ".env": "SECRET=one",
+        }),
+    )
+    .await;
+    fs.insert_tree(path!("/other"), json!({ "b.txt": "one" }))
+        .await;
+    let project = Project::test(
+        fs.clone(),
+        [path!("/dir").as_ref(), path!("/other").as_ref()],
+        cx,
+    )
+    .await;
+    cx.run_until_parked();
+    let local_history =
+        project.read_with(cx, |project, _| project.local_history().cloned().unwrap());
+
+    for path in [
+        path!("/dir/a.txt"),
+        path!("/dir/.env"),
+        path!("/other/b.txt"),
+    ] {
+        let buffer = project
+            .update(cx, |project, cx| project.open_local_buffer(path, cx))
+            .await
+            .unwrap();
+        for text in ["two", "three"] {
+            buffer.update(cx, |buffer, cx| buffer.set_text(text, cx));
+            project
+                .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+                .await
+                .unwrap();
+            cx.run_until_parked();
+        }
+    }
+
+    // Retention is configured per project, and private files are never snapshotted.
+    for (path, expected_len) in [
+        (path!("/dir/a.txt"), 1),
+        (path!("/dir/.env"), 0),
+        (path!("/other/b.txt"), 2),
+    ] {
+        let versions = local_history
+            .read_with(cx, |local_history, cx| {
+                local_history.versions(Path::new(path), cx)
+            })
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), expected_len, "versions of {path}");
+    }
+}
+
+#[gpui::test]
+async fn test_open_large_file(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "one.rs": r#"// Rust file one"#,
-            "one.ts": r#"// TypeScript file one"#,
-            "two.rs": r#"// Rust file two"#,
-            "two.ts": r#"// TypeScript file two"#,
+            "log.txt": "one two\nthree\ntwo twofold\n",
         }),
     )
     .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
-                Default::default(),
-                false,
-                None
-            )
-            .unwrap(),
-            cx
-        )
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let large_file = project
+        .update(cx, |project, cx| {
+            project.open_large_file((worktree_id, rel_path("log.txt")), cx)
+        })
         .await
-        .unwrap()
-        .is_empty(),
-        "If no inclusions match, no files should be returned"
-    );
+        .unwrap();
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.rs".to_owned()], PathStyle::local()).unwrap(),
-                Default::default(),
-                false,
-                None
-            )
-            .unwrap(),
-            cx
-        )
+    let window = large_file
+        .read_with(cx, |large_file, cx| {
+            assert_eq!(large_file.size(), 26);
+            large_file.read_window(8, cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-        ]),
-        "Rust only search should give only Rust files"
-    );
+        .unwrap();
+    assert_eq!(window.range, 8..26);
+    assert_eq!(window.text, "three\ntwo twofold\n");
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    let query = SearchQuery::text(
+        "two",
+        true,
+        true,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    )
+    .unwrap();
+    let matches = large_file
+        .read_with(cx, |large_file, cx| large_file.search(query, cx))
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "TypeScript only search should give only TypeScript files, even if other inclusions don't match anything"
-    );
+        .unwrap();
+    assert_eq!(matches, [4..7, 14..17]);
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(
-                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
-                    PathStyle::local()
-                )
-                .unwrap(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    // Opening the file again reuses the open large file.
+    let reopened = project
+        .update(cx, |project, cx| {
+            project.open_large_file((worktree_id, rel_path("log.txt")), cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-        ]),
-        "Rust and typescript search should give both Rust and TypeScript files, even if other inclusions don't match anything"
-    );
+        .unwrap();
+    assert_eq!(reopened, large_file);
 }
 
 #[gpui::test]
-async fn test_search_with_exclusions(cx: &mut gpui::TestAppContext) {
+async fn test_reopen_buffer_with_encoding(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
-    let search_query = "file";
-
     let fs = FakeFs::new(cx.executor());
-    fs.insert_tree(
-        path!("/dir"),
-        json!({
-            "one.rs": r#"// Rust file one"#,
-            "one.ts": r#"// TypeScript file one"#,
-            "two.rs": r#"// Rust file two"#,
-            "two.ts": r#"// TypeScript file two"#,
-        }),
-    )
-    .await;
+    fs.insert_tree(path!("/dir"), json!({})).await;
+    fs.insert_file(path!("/dir/a.txt"), b"caf\xe9".to_vec())
+        .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/a.txt"), cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "If no exclusions match, all files should be returned"
-    );
+        .unwrap();
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.rs".to_owned()], PathStyle::local()).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    project
+        .update(cx, |project, cx| {
+            project.reopen_buffer_with_encoding(buffer.clone(), encoding_rs::ISO_8859_7, cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "Rust exclusion search should give only TypeScript files"
-    );
+        .unwrap();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "cafι");
+        assert_eq!(buffer.encoding(), encoding_rs::ISO_8859_7);
+        assert!(!buffer.is_dirty());
+    });
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    // Saving encodes the buffer with the encoding it was reopened with.
+    buffer.update(cx, |buffer, cx| {
+        let len = buffer.len();
+        buffer.edit([(len..len, "ς")], None, cx);
+    });
+    project
+        .update(cx, |project, cx| {
+            project.reopen_buffer_with_encoding(buffer.clone(), encoding_rs::WINDOWS_1252, cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-        ]),
-        "TypeScript exclusion search should give only Rust files, even if other exclusions don't match anything"
+        .unwrap_err();
+    project
+        .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs.load_bytes(path!("/dir/a.txt").as_ref()).await.unwrap(),
+        b"caf\xe9\xf2"
     );
 
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(
-                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
-                    PathStyle::local(),
-                )
-                .unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    project
+        .update(cx, |project, cx| {
+            project.reopen_buffer_with_encoding(buffer.clone(), encoding_rs::WINDOWS_1252, cx)
+        })
         .await
-        .unwrap()
-        .is_empty(),
-        "Rust and typescript exclusion should give no files, even if other exclusions don't match anything"
-    );
+        .unwrap();
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "caféò");
+        assert_eq!(buffer.encoding(), encoding_rs::WINDOWS_1252);
+    });
 }
 
 #[gpui::test]
-async fn test_search_with_buffer_exclusions(cx: &mut gpui::TestAppContext) {
+async fn test_trash_and_restore_entries(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
-    let search_query = "file";
-
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "one.rs": r#"// Rust file one"#,
-            "one.ts": r#"// TypeScript file one"#,
-            "two.rs": r#"// Rust file two"#,
-            "two.ts": r#"// TypeScript file two"#,
+            "a.txt": "a",
+            "src": { "b.txt": "b" },
         }),
     )
     .await;
-
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let path_style = PathStyle::local();
-    let _buffer = project.update(cx, |project, cx| {
-        project.create_local_buffer("file", None, false, cx)
-    });
+    let entry_id = |path: &str, cx: &mut gpui::TestAppContext| {
+        project.read_with(cx, |project, cx| {
+            let worktree = project.worktrees(cx).next().unwrap();
+            worktree.read(cx).entry_for_path(rel_path(path)).unwrap().id
+        })
+    };
+    let a_id = entry_id("a.txt", cx);
+    let src_id = entry_id("src", cx);
+    let missing_id = ProjectEntryId::from_proto(u64::MAX);
 
+    let results = project
+        .update(cx, |project, cx| {
+            project.delete_entries(vec![a_id, src_id, missing_id], true, cx)
+        })
+        .await;
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.odd".to_owned()], path_style).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "If no exclusions match, all files should be returned"
+        results
+            .iter()
+            .map(|(id, result)| (*id, result.is_ok()))
+            .collect::<Vec<_>>(),
+        [(a_id, true), (src_id, true), (missing_id, false)]
     );
+    cx.run_until_parked();
+    assert!(!fs.is_file(path!("/dir/a.txt").as_ref()).await);
 
+    let trashed = project.read_with(cx, |project, _| {
+        project
+            .recently_deleted()
+            .map(|entry| (entry.id, entry.path.clone(), entry.is_dir))
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(trashed.len(), 2);
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.rs".to_owned()], path_style).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "Rust exclusion search should give only TypeScript files"
+        trashed
+            .iter()
+            .map(|(_, path, is_dir)| (path.as_unix_str(), *is_dir))
+            .collect::<Vec<_>>(),
+        [("src", true), ("a.txt", false)]
     );
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], path_style).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    let restored = project
+        .update(cx, |project, cx| project.restore_entry(trashed[0].0, cx))
         .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![8..12]),
-            (path!("dir/two.rs").to_string(), vec![8..12]),
-        ]),
-        "TypeScript exclusion search should give only Rust files, even if other exclusions don't match anything"
+        .unwrap();
+    assert_eq!(restored.path.as_unix_str(), "src");
+    assert_eq!(
+        fs.load(path!("/dir/src/b.txt").as_ref()).await.unwrap(),
+        "b"
     );
 
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                Default::default(),
-                PathMatcher::new(
-                    &["*.rs".to_owned(), "*.ts".to_owned(), "*.odd".to_owned()],
-                    PathStyle::local(),
-                )
-                .unwrap(),
-                false,
-                None,
+    fs.insert_file(path!("/dir/a.txt"), b"new".to_vec()).await;
+    let result = project
+        .update(cx, |project, cx| project.restore_entry(trashed[1].0, cx))
+        .await;
+    assert!(result.is_err(), "Restoring should not overwrite new files");
+    project.read_with(cx, |project, _| {
+        assert_eq!(
+            project
+                .recently_deleted()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>(),
+            [trashed[1].0]
+        );
+    });
+}
+
+#[test]
+fn test_forgetting_trashed_entries() {
+    use crate::trash::{
+        MAX_TRASHED_AGE, MAX_TRASHED_ENTRIES, MAX_TRASHED_SIZE, TrashedEntry, TrashedEntryId,
+        entries_to_forget, stash_path,
+    };
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let entry = |size: u64, deleted_at: SystemTime| {
+        let id = TrashedEntryId::new();
+        TrashedEntry {
+            id,
+            worktree_id: WorktreeId::from_usize(1),
+            path: rel_path("a.txt").into(),
+            abs_path: path!("/dir/a.txt").into(),
+            is_dir: false,
+            size,
+            mtime: None,
+            deleted_at,
+            stash_path: stash_path(id),
+        }
+    };
+
+    let entries = (0..3).map(|_| entry(1, now)).collect::<Vec<_>>();
+    assert_eq!(entries_to_forget(&entries, now), 0);
+
+    let entries = (0..MAX_TRASHED_ENTRIES + 2)
+        .map(|_| entry(1, now))
+        .collect::<Vec<_>>();
+    assert_eq!(entries_to_forget(&entries, now), 2);
+
+    let entries = [
+        entry(MAX_TRASHED_SIZE / 2, now),
+        entry(MAX_TRASHED_SIZE / 2, now),
+        entry(1, now),
+    ];
+    assert_eq!(entries_to_forget(&entries, now), 1);
+
+    let expired = now - MAX_TRASHED_AGE - Duration::from_secs(1);
+    let entries = [entry(1, expired), entry(1, expired), entry(1, now)];
+    assert_eq!(entries_to_forget(&entries, now), 2);
+}
+
+#[gpui::test]
+async fn test_project_descriptor(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "app": { "main.py": "", "build": { "out.txt": "" } },
+            "lib": { "lib.py": "" },
+        }),
+    )
+    .await;
+    let project = Project::test(
+        fs.clone(),
+        [path!("/dir/app").as_ref(), path!("/dir/lib").as_ref()],
+        cx,
+    )
+    .await;
+    let app_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    project.update(cx, |project, cx| {
+        project
+            .settings_observer
+            .update(cx, |settings_observer, cx| {
+                settings_observer
+                    .set_worktree_settings_overlay(
+                        app_id,
+                        Some(r#"{ "file_scan_exclusions": ["build"] }"#.into()),
+                        cx,
+                    )
+                    .unwrap();
+            });
+        project.environment().update(cx, |environment, _| {
+            environment.set_overrides([("RUST_LOG".into(), "info".into())].into())
+        });
+    });
+    project
+        .update(cx, |project, cx| {
+            project.activate_toolchain(
+                ProjectPath {
+                    worktree_id: app_id,
+                    path: RelPath::empty().into_arc(),
+                },
+                Toolchain {
+                    name: "venv".into(),
+                    path: path!("/dir/app/.venv/bin/python").into(),
+                    language_name: LanguageName::new_static("Python"),
+                    as_json: serde_json::Value::Null,
+                },
+                cx,
             )
-            .unwrap(),
-            cx
-        )
+        })
         .await
-        .unwrap()
-        .is_empty(),
-        "Rust and typescript exclusion should give no files, even if other exclusions don't match anything"
+        .unwrap();
+
+    project
+        .read_with(cx, |project, cx| {
+            project.export_descriptor(PathBuf::from(path!("/dir/project.json")), cx)
+        })
+        .await
+        .unwrap();
+    let descriptor =
+        ProjectDescriptor::from_json(&fs.load(path!("/dir/project.json").as_ref()).await.unwrap())
+            .unwrap();
+    assert_eq!(
+        descriptor
+            .worktrees
+            .iter()
+            .map(|worktree| worktree.path.as_path())
+            .collect::<Vec<_>>(),
+        [Path::new("app"), Path::new("lib")]
     );
+    assert_eq!(descriptor.worktrees[0].excluded_paths, ["build"]);
+    assert_eq!(descriptor.worktrees[0].toolchains[0].name, "venv");
+
+    let imported = Project::test(fs.clone(), [], cx).await;
+    imported
+        .update(cx, |project, cx| {
+            project.open_from_descriptor(PathBuf::from(path!("/dir/project.json")), cx)
+        })
+        .await
+        .unwrap();
+    cx.run_until_parked();
+    imported.read_with(cx, |project, cx| {
+        assert_eq!(project.descriptor(Path::new(path!("/dir")), cx), descriptor);
+    });
+}
+
+#[gpui::test]
+async fn test_file_history_paths(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "a.rs": "",
+            "b.rs": "",
+            "c.rs": "",
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let worktree_id = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).id()
+    });
+    let project_path = |path: &str| ProjectPath {
+        worktree_id,
+        path: rel_path(path).into(),
+    };
+
+    for path in ["a.rs", "b.rs", "a.rs", "c.rs"] {
+        project
+            .update(cx, |project, cx| {
+                project.open_buffer(project_path(path), cx)
+            })
+            .await
+            .unwrap();
+    }
+    // Files that fail to open aren't recorded.
+    project
+        .update(cx, |project, cx| {
+            project.open_buffer(project_path("missing.rs"), cx)
+        })
+        .await
+        .unwrap_err();
+    cx.executor().run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert_eq!(project.frecent_paths(cx)[0], project_path("a.rs"));
+        assert_eq!(project.recent_paths(cx).len(), 3);
+        assert!(project.recently_closed_paths(cx).is_empty());
+        assert!(
+            project
+                .file_history
+                .visits(Path::new(path!("/dir/missing.rs")))
+                .is_none()
+        );
+    });
+
+    fs.remove_file(path!("/dir/a.rs").as_ref(), Default::default())
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    project.read_with(cx, |project, cx| {
+        assert!(
+            !project.frecent_paths(cx).contains(&project_path("a.rs")),
+            "Deleted files should not be suggested"
+        );
+    });
 }
 
 #[gpui::test]
-async fn test_search_with_exclusions_and_inclusions(cx: &mut gpui::TestAppContext) {
+async fn test_todo_store(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
-    let search_query = "file";
-
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "one.rs": r#"// Rust file one"#,
-            "one.ts": r#"// TypeScript file one"#,
-            "two.rs": r#"// Rust file two"#,
-            "two.ts": r#"// TypeScript file two"#,
+            "a.rs": "// TODO: handle errors\nfn a() {} // FIXME remove\n// TODOS are not tags\nconst TAG: &str = \"TODO: not a comment\";",
+            "b.rs": "fn b() {}",
         }),
     )
     .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
-                PathMatcher::new(&["*.odd".to_owned()], PathStyle::local()).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap()
-        .is_empty(),
-        "If both no exclusions and inclusions match, exclusions should win and return nothing"
+    project.read_with(cx, |project, _| project.languages().add(rust_lang()));
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings(cx, |settings| {
+                settings.project.todo_scanner = Some(true);
+                settings.project.todo_tags = Some(vec!["TODO".into(), "FIXME".into()]);
+            });
+        })
+    });
+    cx.executor().run_until_parked();
+
+    let todo_store = project.read_with(cx, |project, _| project.todo_store().cloned().unwrap());
+    let todos = |cx: &mut gpui::TestAppContext| {
+        todo_store.read_with(cx, |store, _| {
+            store
+                .todos()
+                .flat_map(|(path, todos)| {
+                    todos.iter().map(move |todo| {
+                        (
+                            path.path.as_unix_str().to_string(),
+                            todo.tag.to_string(),
+                            todo.text.clone(),
+                            todo.range.start.row,
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(
+        todos(cx),
+        [
+            ("a.rs".into(), "TODO".into(), "handle errors".into(), 0),
+            ("a.rs".into(), "FIXME".into(), "remove".into(), 1),
+        ]
     );
 
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.ts".to_owned()], PathStyle::local()).unwrap(),
-                PathMatcher::new(&["*.ts".to_owned()], PathStyle::local()).unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap()
-        .is_empty(),
-        "If both TypeScript exclusions and inclusions match, exclusions should win and return nothing files."
+    fs.save(
+        path!("/dir/b.rs").as_ref(),
+        &"fn b() {} // TODO: test".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    cx.executor().advance_clock(Duration::from_secs(1));
+    cx.executor().run_until_parked();
+    assert_eq!(todos(cx).len(), 3);
+    assert_eq!(
+        todo_store.read_with(cx, |store, _| store.todos_with_tag("TODO").count()),
+        2
     );
+}
 
-    assert!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap()
-        .is_empty(),
-        "Non-matching inclusions and exclusions should not change that."
+#[gpui::test]
+async fn test_project_statistics(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            ".gitignore": "ignored.js",
+            "a.js": "one\ntwo\nthree",
+            "ignored.js": "one",
+            "src": {
+                "b.js": "one\ntwo",
+                "c.json": "{}",
+                "notes.txt": "one\ntwo\nthree\nfour",
+            },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(js_lang());
+    language_registry.add(json_lang());
+    cx.executor().run_until_parked();
+
+    let statistics = project
+        .update(cx, |project, cx| project.statistics(cx))
+        .await;
+    assert_eq!(statistics.worktrees.len(), 1);
+    assert_eq!(statistics.file_count(), 5);
+    assert_eq!(statistics.total_size(), 50);
+    assert_eq!(
+        statistics.lines_by_language(),
+        BTreeMap::from_iter([
+            (LanguageName::new_static("JavaScript"), 5),
+            (LanguageName::new_static("JSON"), 1),
+        ])
     );
 
+    fs.save(
+        path!("/dir/src/b.js").as_ref(),
+        &"one".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    cx.executor().run_until_parked();
+    let statistics = project
+        .update(cx, |project, cx| project.statistics(cx))
+        .await;
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                search_query,
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.ts".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                PathMatcher::new(&["*.rs".to_owned(), "*.odd".to_owned()], PathStyle::local())
-                    .unwrap(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.ts").to_string(), vec![14..18]),
-            (path!("dir/two.ts").to_string(), vec![14..18]),
-        ]),
-        "Non-intersecting TypeScript inclusions and Rust exclusions should return TypeScript files"
+        statistics.lines_by_language()[&LanguageName::new_static("JavaScript")],
+        4
     );
 }
 
 #[gpui::test]
-async fn test_search_multiple_worktrees_with_inclusions(cx: &mut gpui::TestAppContext) {
+async fn test_search_limits_and_cancellation(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
-        path!("/worktree-a"),
+        path!("/dir"),
         json!({
-            "haystack.rs": r#"// NEEDLE"#,
-            "haystack.ts": r#"// NEEDLE"#,
+            "a.rs": "const A: usize = 1;",
+            "b.rs": "const B: usize = 2;",
+            "c.rs": "const C: usize = 3;",
         }),
     )
     .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let query = || {
+        SearchQuery::text(
+            "usize",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap()
+    };
+
+    let query_with_limits = query().with_limits(search::SearchLimits {
+        max_files: 1,
+        max_ranges: 10,
+    });
+    let results = project.update(cx, |project, cx| project.search(query_with_limits, cx));
+    let mut buffers = 0;
+    let mut skipped_files = None;
+    while let Ok(result) = results.rx.recv().await {
+        match result {
+            SearchResult::Buffer { .. } => buffers += 1,
+            SearchResult::LimitReached {
+                skipped_files: skipped,
+            } => skipped_files = Some(skipped),
+            SearchResult::BudgetExceeded { error, .. } => panic!("unexpected error: {error}"),
+        }
+    }
+    assert_eq!(buffers, 1);
+    assert_eq!(
+        skipped_files,
+        Some(2),
+        "the files whose matches weren't reported should count as skipped"
+    );
+
+    // All files are provided before the limit is reached, so the count of skipped files has to
+    // come from the later stages of the search.
+    let query_with_limits = query().with_limits(search::SearchLimits {
+        max_files: 10,
+        max_ranges: 1,
+    });
+    let results = project.update(cx, |project, cx| project.search(query_with_limits, cx));
+    let mut buffers = 0;
+    let mut skipped_files = None;
+    while let Ok(result) = results.rx.recv().await {
+        match result {
+            SearchResult::Buffer { .. } => buffers += 1,
+            SearchResult::LimitReached {
+                skipped_files: skipped,
+            } => skipped_files = Some(skipped),
+            SearchResult::BudgetExceeded { error, .. } => panic!("unexpected error: {error}"),
+        }
+    }
+    assert_eq!(buffers, 1);
+    assert_eq!(skipped_files, Some(2));
+
+    let cancelled_query = query();
+    cancelled_query.cancellation().cancel();
+    assert_eq!(
+        search(&project, cancelled_query, cx).await.unwrap(),
+        HashMap::default()
+    );
+    assert_eq!(search(&project, query(), cx).await.unwrap().len(), 3);
+}
+
+#[gpui::test]
+async fn test_search_exceeding_regex_budget(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
-        path!("/worktree-b"),
+        path!("/dir"),
         json!({
-            "haystack.rs": r#"// NEEDLE"#,
-            "haystack.ts": r#"// NEEDLE"#,
+            "fast.rs": "abc",
+            "slow.rs": "ab".repeat(32),
         }),
     )
     .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let query = || {
+        SearchQuery::regex(
+            r"(?i)(a|b|ab)*(?=c)",
+            false,
+            true,
+            false,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap()
+    };
 
-    let path_style = PathStyle::local();
-    let project = Project::test(
-        fs.clone(),
-        [path!("/worktree-a").as_ref(), path!("/worktree-b").as_ref()],
-        cx,
-    )
-    .await;
+    let results = project.update(cx, |project, cx| project.search(query(), cx));
+    let mut matched_paths = Vec::new();
+    let mut errors = Vec::new();
+    while let Ok(result) = results.rx.recv().await {
+        match result {
+            SearchResult::Buffer { buffer, .. } => matched_paths
+                .push(buffer.read_with(cx, |buffer, cx| buffer.file().unwrap().full_path(cx))),
+            SearchResult::LimitReached { .. } => panic!("unexpected limit"),
+            SearchResult::BudgetExceeded { path, error } => errors.push((path, error)),
+        }
+    }
+    assert_eq!(matched_paths, [PathBuf::from("dir/fast.rs")]);
+    assert_eq!(errors.len(), 1);
+    let (path, error) = &errors[0];
+    assert_eq!(path.as_ref().unwrap().path.as_ref(), rel_path("slow.rs"));
+    assert_eq!(*error, search::RegexBudgetError::BacktrackLimitExceeded);
 
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "NEEDLE",
-                false,
-                true,
-                false,
-                PathMatcher::new(&["worktree-a/*.rs".to_owned()], path_style).unwrap(),
-                Default::default(),
-                true,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
+    let error = project
+        .update(cx, |project, cx| {
+            project.replace_all(query().with_replacement("x".to_string()), cx)
+        })
         .await
-        .unwrap(),
-        HashMap::from_iter([(path!("worktree-a/haystack.rs").to_string(), vec![3..9])]),
-        "should only return results from included worktree"
-    );
+        .unwrap_err();
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "NEEDLE",
-                false,
-                true,
-                false,
-                PathMatcher::new(&["worktree-b/*.rs".to_owned()], path_style).unwrap(),
-                Default::default(),
-                true,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([(path!("worktree-b/haystack.rs").to_string(), vec![3..9])]),
-        "should only return results from included worktree"
+        error.downcast_ref::<search::RegexBudgetError>(),
+        Some(&search::RegexBudgetError::BacktrackLimitExceeded)
     );
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/fast.rs"), cx)
+        })
+        .await
+        .unwrap();
+    buffer.read_with(cx, |buffer, _| assert_eq!(buffer.text(), "abc"));
+}
+
+#[gpui::test]
+async fn test_persisted_search_history(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    // A root no other test uses, since tests share the same database.
+    let root = path!("/persisted-search-history");
+    fs.insert_tree(root, json!({ "a.rs": "" })).await;
+    let history = |project: &Entity<Project>, kind, cx: &mut gpui::TestAppContext| {
+        project.read_with(cx, |project, _| {
+            project
+                .search_history(kind)
+                .entries()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let project = Project::test(fs.clone(), [root.as_ref()], cx).await;
+    project.update(cx, |project, cx| {
+        project.persist_histories_for_test(cx);
+        let mut cursor = search_history::SearchHistoryCursor::default();
+        project.add_to_search_history(SearchInputKind::Query, &mut cursor, "one".into(), cx);
+        project.add_to_search_history(SearchInputKind::Query, &mut cursor, "two".into(), cx);
+        project.add_to_search_history(SearchInputKind::Include, &mut cursor, "*.rs".into(), cx);
+    });
+    cx.run_until_parked();
 
+    let restored_project = Project::test(fs.clone(), [root.as_ref()], cx).await;
+    restored_project.update(cx, |project, cx| {
+        let mut cursor = search_history::SearchHistoryCursor::default();
+        project.add_to_search_history(SearchInputKind::Query, &mut cursor, "three".into(), cx);
+        project.persist_histories_for_test(cx);
+    });
+    cx.run_until_parked();
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                "NEEDLE",
-                false,
-                true,
-                false,
-                PathMatcher::new(&["*.ts".to_owned()], path_style).unwrap(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("worktree-a/haystack.ts").to_string(), vec![3..9]),
-            (path!("worktree-b/haystack.ts").to_string(), vec![3..9])
-        ]),
-        "should return results from both worktrees"
+        history(&restored_project, SearchInputKind::Query, cx),
+        ["one", "two", "three"],
+        "the previous session's queries should precede the new ones"
+    );
+    assert_eq!(
+        history(&restored_project, SearchInputKind::Include, cx),
+        ["*.rs"]
+    );
+    assert!(history(&restored_project, SearchInputKind::Exclude, cx).is_empty());
+
+    let unpersisted_project = Project::test(fs.clone(), [root.as_ref()], cx).await;
+    cx.run_until_parked();
+    assert!(
+        history(&unpersisted_project, SearchInputKind::Query, cx).is_empty(),
+        "projects on fake file systems should not restore histories by default"
     );
 }
 
 #[gpui::test]
-async fn test_search_in_gitignored_dirs(cx: &mut gpui::TestAppContext) {
+async fn test_search_with_trigram_index(cx: &mut gpui::TestAppContext) {
     init_test(cx);
+    cx.update(|cx| {
+        SettingsStore::update_global(cx, |settings, cx| {
+            settings.update_user_settings(cx, |settings| {
+                settings.project.search_index = Some(true);
+            });
+        })
+    });
 
-    let fs = FakeFs::new(cx.background_executor.clone());
+    let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            ".git": {},
-            ".gitignore": "**/target\n/node_modules\n",
-            "target": {
-                "index.txt": "index_key:index_value"
-            },
-            "node_modules": {
-                "eslint": {
-                    "index.ts": "const eslint_key = 'eslint value'",
-                    "package.json": r#"{ "some_key": "some value" }"#,
-                },
-                "prettier": {
-                    "index.ts": "const prettier_key = 'prettier value'",
-                    "package.json": r#"{ "other_key": "other value" }"#,
-                },
-            },
-            "package.json": r#"{ "main_key": "main value" }"#,
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
         }),
     )
     .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-
-    let query = "key";
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                query,
-                false,
-                false,
-                false,
-                Default::default(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([(path!("dir/package.json").to_string(), vec![8..11])]),
-        "Only one non-ignored file should have the query"
+    cx.executor().run_until_parked();
+    assert!(
+        fs.paths(false)
+            .iter()
+            .any(|path| path.starts_with(paths::search_index_dir())),
+        "the index should have been persisted"
     );
 
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let path_style = PathStyle::local();
-    assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                query,
-                false,
-                false,
-                true,
-                Default::default(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
+    let query = || {
+        SearchQuery::text(
+            "one::ONE",
+            false,
+            true,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
         )
-        .await
-        .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/package.json").to_string(), vec![8..11]),
-            (path!("dir/target/index.txt").to_string(), vec![6..9]),
-            (
-                path!("dir/node_modules/prettier/package.json").to_string(),
-                vec![9..12]
-            ),
-            (
-                path!("dir/node_modules/prettier/index.ts").to_string(),
-                vec![15..18]
-            ),
-            (
-                path!("dir/node_modules/eslint/index.ts").to_string(),
-                vec![13..16]
-            ),
-            (
-                path!("dir/node_modules/eslint/package.json").to_string(),
-                vec![8..11]
-            ),
-        ]),
-        "Unrestricted search with ignored directories should find every file with the query"
+        .unwrap()
+    };
+    assert_eq!(
+        search(&project, query(), cx).await.unwrap(),
+        HashMap::from_iter([(path!("dir/two.rs").to_string(), vec![19..27, 30..38])])
     );
 
-    let files_to_include =
-        PathMatcher::new(&["node_modules/prettier/**".to_owned()], path_style).unwrap();
-    let files_to_exclude = PathMatcher::new(&["*.ts".to_owned()], path_style).unwrap();
-    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    // Files that changed since they were indexed are searched, even before being reindexed.
+    fs.save(
+        path!("/dir/one.rs").as_ref(),
+        &"const ONE: usize = one::ONE;".into(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(
-        search(
-            &project,
-            SearchQuery::text(
-                query,
-                false,
-                false,
-                true,
-                files_to_include,
-                files_to_exclude,
-                false,
-                None,
-            )
-            .unwrap(),
-            cx
-        )
-        .await
-        .unwrap(),
-        HashMap::from_iter([(
-            path!("dir/node_modules/prettier/package.json").to_string(),
-            vec![9..12]
-        )]),
-        "With search including ignored prettier directory and excluding TS files, only one file should be found"
+        search(&project, query(), cx).await.unwrap(),
+        HashMap::from_iter([
+            (path!("dir/one.rs").to_string(), vec![19..27]),
+            (path!("dir/two.rs").to_string(), vec![19..27, 30..38])
+        ])
     );
 }
 
 #[gpui::test]
-async fn test_search_with_unicode(cx: &mut gpui::TestAppContext) {
+async fn test_structural_search(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
         path!("/dir"),
         json!({
-            "one.rs": "// ПРИВЕТ? привет!",
-            "two.rs": "// ПРИВЕТ.",
-            "three.rs": "// привет",
+            "main.rs": "fn alpha() {}\nfn beta() {}\nfn also() {}\n",
+            "notes.txt": "fn alpha() {}\n",
         }),
     )
     .await;
     let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
-    let unicode_case_sensitive_query = SearchQuery::text(
-        "привет",
-        false,
-        true,
-        false,
-        Default::default(),
-        Default::default(),
-        false,
-        None,
-    );
-    assert_matches!(unicode_case_sensitive_query, Ok(SearchQuery::Text { .. }));
-    assert_eq!(
-        search(&project, unicode_case_sensitive_query.unwrap(), cx)
-            .await
-            .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![17..29]),
-            (path!("dir/three.rs").to_string(), vec![3..15]),
-        ])
-    );
+    let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+    language_registry.add(rust_lang());
+    project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/main.rs"), cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
 
-    let unicode_case_insensitive_query = SearchQuery::text(
-        "привет",
-        false,
-        false,
-        false,
-        Default::default(),
-        Default::default(),
-        false,
-        None,
-    );
-    assert_matches!(
-        unicode_case_insensitive_query,
-        Ok(SearchQuery::Regex { .. })
-    );
+    let query = |capture_filters: Vec<(String, String)>| {
+        SearchQuery::structural(
+            "(function_item name: (identifier) @name) @match",
+            capture_filters,
+            false,
+            Default::default(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap()
+    };
     assert_eq!(
-        search(&project, unicode_case_insensitive_query.unwrap(), cx)
-            .await
-            .unwrap(),
-        HashMap::from_iter([
-            (path!("dir/one.rs").to_string(), vec![3..15, 17..29]),
-            (path!("dir/two.rs").to_string(), vec![3..15]),
-            (path!("dir/three.rs").to_string(), vec![3..15]),
-        ])
+        search(&project, query(Vec::new()), cx).await.unwrap(),
+        HashMap::from_iter([(
+            path!("dir/main.rs").to_string(),
+            vec![0..13, 14..26, 27..39]
+        )])
     );
-
     assert_eq!(
         search(
             &project,
-            SearchQuery::text(
-                "привет.",
-                false,
-                false,
-                false,
-                Default::default(),
-                Default::default(),
-                false,
-                None,
-            )
-            .unwrap(),
+            query(vec![("@name".to_string(), "^al".to_string())]),
             cx
         )
         .await
         .unwrap(),
-        HashMap::from_iter([(path!("dir/two.rs").to_string(), vec![3..16]),])
+        HashMap::from_iter([(path!("dir/main.rs").to_string(), vec![0..13, 27..39])])
     );
 }
 
 #[gpui::test]
-async fn test_create_entry(cx: &mut gpui::TestAppContext) {
+async fn test_replace_all(cx: &mut gpui::TestAppContext) {
     init_test(cx);
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
-        "/one/two",
-        json!({
-            "three": {
-                "a.txt": "",
-                "four": {}
-            },
-            "c.rs": ""
+        path!("/dir"),
+        json!({
+            "one.rs": "const ONE: usize = 1;",
+            "two.rs": "const TWO: usize = one::ONE + one::ONE;",
+            "three.rs": "const THREE: usize = two::TWO;",
         }),
     )
     .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let query = SearchQuery::regex(
+        r"(\w+)::ONE",
+        false,
+        true,
+        false,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    )
+    .unwrap()
+    .with_replacement("crate::$1::UNO".to_string());
+    let replace = project
+        .update(cx, |project, cx| project.replace_all(query, cx))
+        .await
+        .unwrap();
+    assert_eq!(
+        replace.summary,
+        ReplaceSummary {
+            files_changed: 1,
+            replacements: 2,
+            limit_reached: false,
+        }
+    );
 
-    let project = Project::test(fs.clone(), ["/one/two/three".as_ref()], cx).await;
-    project
+    let buffer = project
         .update(cx, |project, cx| {
-            let id = project.worktrees(cx).next().unwrap().read(cx).id();
-            project.create_entry((id, rel_path("b..")), true, cx)
+            project.open_local_buffer(path!("/dir/two.rs"), cx)
         })
         .await
-        .unwrap()
-        .into_included()
         .unwrap();
+    assert!(replace.transaction.0.contains_key(&buffer));
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(
+            buffer.text(),
+            "const TWO: usize = crate::one::UNO + crate::one::UNO;"
+        );
+        assert!(buffer.is_dirty());
+    });
 
-    assert_eq!(
-        fs.paths(true),
-        vec![
-            PathBuf::from(path!("/")),
-            PathBuf::from(path!("/one")),
-            PathBuf::from(path!("/one/two")),
-            PathBuf::from(path!("/one/two/c.rs")),
-            PathBuf::from(path!("/one/two/three")),
-            PathBuf::from(path!("/one/two/three/a.txt")),
-            PathBuf::from(path!("/one/two/three/b..")),
-            PathBuf::from(path!("/one/two/three/four")),
-        ]
-    );
+    project.update(cx, |project, cx| {
+        project.undo_project_transaction(&replace.transaction, cx)
+    });
+    buffer.read_with(cx, |buffer, _| {
+        assert_eq!(buffer.text(), "const TWO: usize = one::ONE + one::ONE;");
+    });
 }
 
 #[gpui::test]
@@ -10404,10 +13287,11 @@ async fn search(
     let mut results = HashMap::default();
     while let Ok(search_result) = search_rx.rx.recv().await {
         match search_result {
-            SearchResult::Buffer { buffer, ranges } => {
+            SearchResult::Buffer { buffer, ranges, .. } => {
                 results.entry(buffer).or_insert(ranges);
             }
-            SearchResult::LimitReached => {}
+            SearchResult::LimitReached { .. } => {}
+            SearchResult::BudgetExceeded { error, .. } => return Err(error.into()),
         }
     }
     Ok(results
@@ -10810,6 +13694,128 @@ async fn test_find_project_path_abs(
     });
 }
 
+#[gpui::test]
+async fn test_resolve_path_in_buffer_with_position(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/dir"),
+        json!({
+            "src": {
+                "a.rs": "",
+                "b.rs": "",
+                "c(1).rs": "",
+            },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/dir").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/dir/src/b.rs"), cx)
+        })
+        .await
+        .unwrap();
+
+    let resolve = |path: String, cx: &mut gpui::TestAppContext| {
+        project.update(cx, |project, cx| {
+            project.resolve_path_in_buffer(&path, &buffer, cx)
+        })
+    };
+
+    let resolved = resolve("a.rs:3:4".into(), cx).await.unwrap();
+    assert_eq!(
+        resolved.path.project_path().map(|path| path.path.as_ref()),
+        Some(rel_path("src/a.rs"))
+    );
+    assert_eq!(resolved.point(), Some(Point::new(2, 3)));
+
+    let resolved = resolve("src/a.rs(7,2)".into(), cx).await.unwrap();
+    assert_eq!(
+        resolved.path.project_path().map(|path| path.path.as_ref()),
+        Some(rel_path("src/a.rs"))
+    );
+    assert_eq!(resolved.point(), Some(Point::new(6, 1)));
+
+    let url = format!(
+        "{}:5",
+        url::Url::from_file_path(path!("/dir/src/a.rs")).unwrap()
+    );
+    let resolved = resolve(url, cx).await.unwrap();
+    assert_eq!(
+        resolved.path.abs_path().map(Path::new),
+        Some(Path::new(path!("/dir/src/a.rs")))
+    );
+    assert_eq!(resolved.point(), Some(Point::new(4, 0)));
+
+    let resolved = resolve("c(1).rs".into(), cx).await.unwrap();
+    assert_eq!(
+        resolved.path.project_path().map(|path| path.path.as_ref()),
+        Some(rel_path("src/c(1).rs")),
+        "Suffixes that are part of the file name should be kept"
+    );
+    assert_eq!(resolved.point(), None);
+
+    assert!(resolve("missing.rs:1".into(), cx).await.is_none());
+}
+
+#[gpui::test]
+async fn test_find_project_path_fuzzy(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/root"),
+        json!({
+            "crates": {
+                "a": { "src": { "lib.rs": "", "main.rs": "" } },
+                "b": { "src": { "lib.rs": "" } },
+            },
+        }),
+    )
+    .await;
+    let project = Project::test(fs.clone(), [path!("/root").as_ref()], cx).await;
+    cx.run_until_parked();
+
+    project.read_with(cx, |project, cx| {
+        let found_path = project
+            .find_existing_project_path("src/main.rs", cx)
+            .unwrap();
+        assert_eq!(&*found_path.path, rel_path("crates/a/src/main.rs"));
+
+        let found_path = project
+            .find_existing_project_path("other/a/src/lib.rs", cx)
+            .unwrap();
+        assert_eq!(&*found_path.path, rel_path("crates/a/src/lib.rs"));
+
+        assert_eq!(
+            project.find_existing_project_path("src/lib.rs", cx),
+            None,
+            "Ambiguous suffixes should not match"
+        );
+        assert_eq!(
+            project.find_existing_project_path("main.rs", cx),
+            None,
+            "Matching only the file name should not be enough"
+        );
+        assert_eq!(
+            project.find_existing_project_path("src/MAIN.rs", cx),
+            None,
+            "Case should matter on case-sensitive file systems"
+        );
+        assert_eq!(
+            project.find_project_path("src/main.rs", cx),
+            None,
+            "Paths to create should never resolve to another file"
+        );
+        assert_eq!(
+            project.find_existing_project_path("crates/a/src/new.rs", cx),
+            None
+        );
+    });
+}
+
 #[gpui::test]
 async fn test_git_worktree_remove(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -11065,3 +14071,291 @@ async fn test_optimistic_hunks_in_staged_files(cx: &mut gpui::TestAppContext) {
         );
     });
 }
+
+#[gpui::test]
+async fn test_debug_scenario_generators(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "Cargo.toml": r#"
+                [package]
+                name = "app"
+
+                [[bin]]
+                name = "server"
+                path = "src/server.rs"
+            "#,
+            "package.json": r#"{ "scripts": { "start": "node index.js", "test": "jest" } }"#,
+            "src": {
+                "main.rs": "fn main() {}",
+                "server.rs": "fn main() {}",
+                "bin": {
+                    "migrate.rs": "fn main() {}",
+                    "README.md": "",
+                },
+            },
+            "tests": {
+                "test_api.py": "",
+                "conftest.py": "",
+            },
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/project").as_ref()], cx).await;
+    let snapshot = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).snapshot()
+    });
+
+    let mut tasks = Vec::new();
+    for generator in crate::debugger::generators::generators() {
+        tasks.extend(
+            generator
+                .tasks(fs.as_ref(), &snapshot)
+                .await
+                .into_iter()
+                .map(|task| (task.label, task.command, task.args.join(" "))),
+        );
+    }
+
+    let expected = [
+        ("Debug binary 'app'", "cargo", "run --package app --bin app"),
+        (
+            "Debug binary 'migrate'",
+            "cargo",
+            "run --package app --bin migrate",
+        ),
+        (
+            "Debug binary 'server'",
+            "cargo",
+            "run --package app --bin server",
+        ),
+        ("Debug tests in 'app'", "cargo", "test --package app"),
+        ("Debug script 'start'", "npm", "run start"),
+        ("Debug script 'test'", "npm", "run test"),
+        (
+            "Debug tests in 'tests/test_api.py'",
+            "python3",
+            "-m pytest tests/test_api.py",
+        ),
+    ]
+    .map(|(label, command, args)| (label.to_string(), command.to_string(), args.to_string()));
+    assert_eq!(tasks, expected);
+}
+
+#[gpui::test]
+async fn test_cargo_workspace_scenario_generator(cx: &mut gpui::TestAppContext) {
+    use crate::debugger::generators::{ScenarioGenerator as _, cargo::CargoGenerator};
+
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/workspace"),
+        json!({
+            "Cargo.toml": r#"
+                [workspace]
+                members = ["cli", "crates/*"]
+                exclude = ["crates/experimental"]
+            "#,
+            "cli": {
+                "Cargo.toml": "[package]\nname = \"cli\"",
+                "src": { "main.rs": "fn main() {}" },
+            },
+            "crates": {
+                "core": {
+                    "Cargo.toml": "[package]\nname = \"core\"",
+                    "src": {
+                        "lib.rs": "",
+                        "bin": { "import.rs": "fn main() {}" },
+                    },
+                },
+                "experimental": {
+                    "Cargo.toml": "[package]\nname = \"experimental\"",
+                    "src": { "main.rs": "fn main() {}" },
+                },
+                "nested": {
+                    "inner": {
+                        "Cargo.toml": "[package]\nname = \"inner\"",
+                        "src": { "main.rs": "fn main() {}" },
+                    },
+                },
+            },
+        }),
+    )
+    .await;
+
+    let project = Project::test(fs.clone(), [path!("/workspace").as_ref()], cx).await;
+    let snapshot = project.read_with(cx, |project, cx| {
+        project.worktrees(cx).next().unwrap().read(cx).snapshot()
+    });
+
+    let tasks = CargoGenerator
+        .tasks(fs.as_ref(), &snapshot)
+        .await
+        .into_iter()
+        .map(|task| (task.label, task.args.join(" ")))
+        .collect::<Vec<_>>();
+    let expected = [
+        ("Debug binary 'cli'", "run --package cli --bin cli"),
+        ("Debug tests in 'cli'", "test --package cli"),
+        ("Debug binary 'import'", "run --package core --bin import"),
+        ("Debug tests in 'core'", "test --package core"),
+    ]
+    .map(|(label, args)| (label.to_string(), args.to_string()));
+    assert_eq!(tasks, expected);
+}
+
+#[test]
+fn test_merging_generated_debug_scenarios() {
+    fn scenario(label: &'static str, program: &str) -> (TaskSourceKind, DebugScenario) {
+        (
+            TaskSourceKind::UserInput,
+            DebugScenario {
+                adapter: "CodeLLDB".into(),
+                label: label.into(),
+                build: None,
+                config: json!({ "program": program }),
+                tcp_connection: None,
+            },
+        )
+    }
+
+    let configured = vec![scenario("Debug binary 'app'", "configured")];
+    let generated = vec![
+        scenario("Debug binary 'app'", "generated"),
+        scenario("Debug tests in 'app'", "generated"),
+        scenario("Debug tests in 'app'", "duplicate"),
+    ];
+    let merged = crate::debugger::generators::merge_scenarios(configured, generated)
+        .into_iter()
+        .map(|(_, scenario)| {
+            (
+                scenario.label.to_string(),
+                scenario.config["program"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        merged,
+        [
+            ("Debug binary 'app'".to_string(), json!("configured")),
+            ("Debug tests in 'app'".to_string(), json!("generated")),
+        ]
+    );
+}
+
+#[gpui::test]
+async fn test_breakpoints_are_restored_from_serialized_state(cx: &mut gpui::TestAppContext) {
+    use crate::debugger::breakpoint_store::{
+        Breakpoint, BreakpointEditAction, BreakpointState, BreakpointStore, BreakpointWithPosition,
+    };
+
+    init_test(cx);
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        path!("/project"),
+        json!({
+            "main.rs": "First line\nSecond line\nThird line\n",
+            "other.rs": "First line\nSecond line\n",
+        }),
+    )
+    .await;
+
+    fn toggle_breakpoint(
+        breakpoint_store: &Entity<BreakpointStore>,
+        buffer: &Entity<Buffer>,
+        row: u32,
+        breakpoint: Breakpoint,
+        cx: &mut gpui::TestAppContext,
+    ) {
+        breakpoint_store.update(cx, |breakpoint_store, cx| {
+            let position = buffer.read(cx).snapshot().anchor_before(Point::new(row, 0));
+            breakpoint_store.toggle_breakpoint(
+                buffer.clone(),
+                BreakpointWithPosition {
+                    position,
+                    bp: breakpoint,
+                },
+                BreakpointEditAction::Toggle,
+                cx,
+            );
+        });
+    }
+
+    let project = Project::test(fs.clone(), [path!("/project").as_ref()], cx).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer(path!("/project/main.rs"), cx)
+        })
+        .await
+        .unwrap();
+    let breakpoint_store = project.read_with(cx, |project, _| project.breakpoint_store());
+    toggle_breakpoint(
+        &breakpoint_store,
+        &buffer,
+        1,
+        Breakpoint {
+            condition: Some("x > 1".into()),
+            state: BreakpointState::Disabled,
+            ..Breakpoint::new_standard()
+        },
+        cx,
+    );
+    let serialized = breakpoint_store.read_with(cx, |breakpoint_store, cx| {
+        breakpoint_store.all_source_breakpoints(cx)
+    });
+
+    // Breakpoints set before the serialized ones are restored don't survive the restore.
+    let project = Project::test(fs, [path!("/project").as_ref()], cx).await;
+    let breakpoint_store = project.read_with(cx, |project, _| project.breakpoint_store());
+    for (path, row) in [
+        (path!("/project/main.rs"), 2),
+        (path!("/project/other.rs"), 0),
+    ] {
+        let buffer = project
+            .update(cx, |project, cx| project.open_local_buffer(path, cx))
+            .await
+            .unwrap();
+        toggle_breakpoint(
+            &breakpoint_store,
+            &buffer,
+            row,
+            Breakpoint::new_standard(),
+            cx,
+        );
+    }
+    breakpoint_store
+        .update(cx, |breakpoint_store, cx| {
+            breakpoint_store.with_serialized_breakpoints(serialized, cx)
+        })
+        .await
+        .unwrap();
+
+    let breakpoints = breakpoint_store.read_with(cx, |breakpoint_store, cx| {
+        breakpoint_store.all_source_breakpoints(cx)
+    });
+    let restored = breakpoints[Path::new(path!("/project/main.rs"))]
+        .iter()
+        .map(|breakpoint| {
+            (
+                breakpoint.row,
+                breakpoint.condition.as_deref(),
+                breakpoint.state,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        restored,
+        vec![(1, Some("x > 1"), BreakpointState::Disabled)]
+    );
+    assert!(
+        breakpoints
+            .get(Path::new(path!("/project/other.rs")))
+            .is_none_or(|breakpoints| breakpoints.is_empty())
+    );
+}
@@ -1,3 +1,6 @@
+mod search_regex;
+mod structural;
+
 use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use anyhow::Result;
 use fancy_regex::{Captures, Regex, RegexBuilder};
@@ -10,7 +13,10 @@ use std::{
     borrow::Cow,
     io::{BufRead, BufReader, Read},
     ops::Range,
-    sync::{Arc, LazyLock},
+    sync::{
+        Arc, LazyLock,
+        atomic::{self, AtomicBool},
+    },
 };
 use text::Anchor;
 use util::{
@@ -18,13 +24,105 @@ use util::{
     rel_path::RelPath,
 };
 
+use crate::ProjectPath;
+
+pub use search_regex::{RegexBudgetError, SearchRegex};
+pub use structural::{MATCH_CAPTURE_NAME, StructuralQuery};
+
 #[derive(Debug)]
 pub enum SearchResult {
     Buffer {
         buffer: Entity<Buffer>,
         ranges: Vec<Range<Anchor>>,
+        /// For each range, the whole lines spanning it and the query's context lines around it.
+        /// `None` when the query has no context lines.
+        context: Option<Vec<Range<Anchor>>>,
+        /// How relevant the buffer's matches are, between 0 and 2. See [`relevance_score`].
+        score: f32,
     },
-    LimitReached,
+    /// The search stopped early because it reached the query's [`SearchLimits`].
+    LimitReached {
+        /// The number of files that were left unsearched.
+        skipped_files: usize,
+    },
+    /// A regex search of a file exceeded its budget, so the file's matches are missing.
+    BudgetExceeded {
+        path: Option<ProjectPath>,
+        error: RegexBudgetError,
+    },
+}
+
+/// Bounds on the results of a search, past which it stops early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub max_files: usize,
+    pub max_ranges: usize,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            max_files: 5_000,
+            max_ranges: 10_000,
+        }
+    }
+}
+
+/// A token that stops a search in progress, shared by all clones of the query it belongs to.
+#[derive(Clone, Debug, Default)]
+pub struct SearchCancellation(Arc<AtomicBool>);
+
+impl SearchCancellation {
+    pub fn cancel(&self) {
+        self.0.store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(atomic::Ordering::Relaxed)
+    }
+}
+
+/// Scores the matches of a buffer by the sum of its path's proximity to the active file and the
+/// density of matches within the buffer, both between 0 and 1.
+///
+/// Paths in the active file's directory have a proximity of 1, which decreases with the number
+/// of directories between them and the active file.
+pub fn relevance_score(
+    path: Option<&ProjectPath>,
+    active_path: Option<&ProjectPath>,
+    match_count: usize,
+    line_count: u32,
+) -> f32 {
+    let proximity = match (path, active_path) {
+        (Some(path), Some(active_path)) if path.worktree_id == active_path.worktree_id => {
+            let dir = path.path.parent().unwrap_or(RelPath::empty());
+            let active_dir = active_path.path.parent().unwrap_or(RelPath::empty());
+            let shared = dir
+                .components()
+                .zip(active_dir.components())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let depth = dir
+                .components()
+                .count()
+                .max(active_dir.components().count());
+            (shared + 1) as f32 / (depth + 1) as f32
+        }
+        _ => 0.,
+    };
+    let density = (match_count as f32 / line_count.max(1) as f32).min(1.);
+    proximity + density
+}
+
+/// Whether a match within a line neither starts nor ends in the middle of a word.
+fn is_whole_word(line: &str, range: Range<usize>) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let text = &line[range.clone()];
+    let splits_start = text.chars().next().is_some_and(is_word)
+        && line[..range.start].chars().next_back().is_some_and(is_word);
+    let splits_end = text.chars().next_back().is_some_and(is_word)
+        && line[range.end..].chars().next().is_some_and(is_word);
+    !splits_start && !splits_end
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -41,6 +139,9 @@ pub struct SearchInputs {
     files_to_exclude: PathMatcher,
     match_full_paths: bool,
     buffers: Option<Vec<Entity<Buffer>>>,
+    context_lines: u32,
+    limits: SearchLimits,
+    cancellation: SearchCancellation,
 }
 
 impl SearchInputs {
@@ -68,7 +169,7 @@ pub enum SearchQuery {
         inner: SearchInputs,
     },
     Regex {
-        regex: Regex,
+        regex: SearchRegex,
         replacement: Option<String>,
         multiline: bool,
         whole_word: bool,
@@ -77,6 +178,14 @@ pub enum SearchQuery {
         one_match_per_line: bool,
         inner: SearchInputs,
     },
+    /// Matches tree-sitter query patterns against the syntax trees of buffers, for any language
+    /// whose grammar the query can be compiled for.
+    Structural {
+        query: Arc<StructuralQuery>,
+        replacement: Option<String>,
+        include_ignored: bool,
+        inner: SearchInputs,
+    },
 }
 
 static WORD_MATCH_TEST: LazyLock<Regex> = LazyLock::new(|| {
@@ -126,6 +235,9 @@ impl SearchQuery {
             files_to_include,
             match_full_paths,
             buffers,
+            context_lines: 0,
+            limits: SearchLimits::default(),
+            cancellation: SearchCancellation::default(),
         };
         Ok(Self::Text {
             search,
@@ -180,15 +292,16 @@ impl SearchQuery {
         }
 
         let multiline = query.contains('\n') || query.contains("\\n");
-        let regex = RegexBuilder::new(&query)
-            .case_insensitive(!case_sensitive)
-            .build()?;
+        let regex = SearchRegex::new(&query, !case_sensitive)?;
         let inner = SearchInputs {
             query: initial_query,
             files_to_exclude,
             files_to_include,
             match_full_paths,
             buffers,
+            context_lines: 0,
+            limits: SearchLimits::default(),
+            cancellation: SearchCancellation::default(),
         };
         Ok(Self::Regex {
             regex,
@@ -202,6 +315,40 @@ impl SearchQuery {
         })
     }
 
+    /// Create a structural query from a tree-sitter query pattern.
+    ///
+    /// Each capture filter pairs a capture name of the pattern with a regex that the captured
+    /// text has to match. Matches are reported as the range of the `@match` capture, or as the
+    /// range spanning all captures when the pattern has none.
+    pub fn structural(
+        query: impl ToString,
+        capture_filters: Vec<(String, String)>,
+        include_ignored: bool,
+        files_to_include: PathMatcher,
+        files_to_exclude: PathMatcher,
+        match_full_paths: bool,
+        buffers: Option<Vec<Entity<Buffer>>>,
+    ) -> Result<Self> {
+        let query: Arc<str> = query.to_string().into();
+        let structural = StructuralQuery::new(query.clone(), capture_filters)?;
+        let inner = SearchInputs {
+            query,
+            files_to_exclude,
+            files_to_include,
+            match_full_paths,
+            buffers,
+            context_lines: 0,
+            limits: SearchLimits::default(),
+            cancellation: SearchCancellation::default(),
+        };
+        Ok(Self::Structural {
+            query: Arc::new(structural),
+            replacement: None,
+            include_ignored,
+            inner,
+        })
+    }
+
     /// Extracts case sensitivity settings from pattern items in the provided
     /// query and returns the same query, with the pattern items removed.
     ///
@@ -266,7 +413,21 @@ impl SearchQuery {
             message.files_to_exclude
         };
 
-        if message.regex {
+        if message.structural {
+            Self::structural(
+                message.query,
+                message
+                    .capture_filters
+                    .into_iter()
+                    .map(|filter| (filter.capture, filter.pattern))
+                    .collect(),
+                message.include_ignored,
+                PathMatcher::new(files_to_include, path_style)?,
+                PathMatcher::new(files_to_exclude, path_style)?,
+                message.match_full_paths,
+                None, // search opened only don't need search remote
+            )
+        } else if message.regex {
             Self::regex(
                 message.query,
                 message.whole_word,
@@ -292,6 +453,31 @@ impl SearchQuery {
         }
     }
 
+    /// Includes the given number of lines around each match in the results.
+    pub fn with_context_lines(mut self, context_lines: u32) -> Self {
+        self.as_inner_mut().context_lines = context_lines;
+        self
+    }
+
+    pub fn context_lines(&self) -> u32 {
+        self.as_inner().context_lines
+    }
+
+    pub fn with_limits(mut self, limits: SearchLimits) -> Self {
+        self.as_inner_mut().limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> SearchLimits {
+        self.as_inner().limits
+    }
+
+    /// The token that cancels searches for this query. Clone it before starting the search to
+    /// stop the search later.
+    pub fn cancellation(&self) -> &SearchCancellation {
+        &self.as_inner().cancellation
+    }
+
     pub fn with_replacement(mut self, new_replacement: String) -> Self {
         match self {
             Self::Text {
@@ -301,6 +487,10 @@ impl SearchQuery {
             | Self::Regex {
                 ref mut replacement,
                 ..
+            }
+            | Self::Structural {
+                ref mut replacement,
+                ..
             } => {
                 *replacement = Some(new_replacement);
                 self
@@ -323,6 +513,17 @@ impl SearchQuery {
             // Populate legacy fields for backwards compatibility
             files_to_include_legacy: files_to_include.join(","),
             files_to_exclude_legacy: files_to_exclude.join(","),
+            structural: self.is_structural(),
+            capture_filters: match self {
+                Self::Structural { query, .. } => query
+                    .capture_filters()
+                    .map(|(capture, pattern)| proto::StructuralCaptureFilter {
+                        capture: capture.to_string(),
+                        pattern: pattern.to_string(),
+                    })
+                    .collect(),
+                Self::Text { .. } | Self::Regex { .. } => Vec::new(),
+            },
         }
     }
 
@@ -389,20 +590,67 @@ impl SearchQuery {
                     Ok(false)
                 }
             }
+            Self::Structural { query, .. } => {
+                reader.read_to_string(&mut text)?;
+                Ok(query.could_match(&text))
+            }
+        }
+    }
+
+    /// Finds the matches of the query within a single line, for searching files line by line
+    /// without loading them into buffers. Multiline and structural queries can't be matched
+    /// this way.
+    pub(crate) fn find_in_line(&self, line: &str) -> Result<Vec<Range<usize>>> {
+        match self {
+            Self::Text {
+                search, whole_word, ..
+            } => {
+                anyhow::ensure!(
+                    !self.as_str().contains('\n'),
+                    "multiline queries can't be matched line by line"
+                );
+                Ok(search
+                    .find_iter(line)
+                    .map(|mat| mat.range())
+                    .filter(|range| !*whole_word || is_whole_word(line, range.clone()))
+                    .collect())
+            }
+            Self::Regex {
+                regex, multiline, ..
+            } => {
+                anyhow::ensure!(
+                    !*multiline,
+                    "multiline queries can't be matched line by line"
+                );
+                let mut matches = Vec::new();
+                for mat in regex.find_iter(line) {
+                    matches.push(mat?);
+                    if self.one_match_per_line() == Some(true) {
+                        break;
+                    }
+                }
+                Ok(matches)
+            }
+            Self::Structural { .. } => {
+                anyhow::bail!("structural queries can't be matched line by line")
+            }
         }
     }
+
     /// Returns the replacement text for this `SearchQuery`.
     pub fn replacement(&self) -> Option<&str> {
         match self {
-            SearchQuery::Text { replacement, .. } | SearchQuery::Regex { replacement, .. } => {
-                replacement.as_deref()
-            }
+            SearchQuery::Text { replacement, .. }
+            | SearchQuery::Regex { replacement, .. }
+            | SearchQuery::Structural { replacement, .. } => replacement.as_deref(),
         }
     }
     /// Replaces search hits if replacement is set. `text` is assumed to be a string that matches this `SearchQuery` exactly, without any leftovers on either side.
     pub fn replacement_for<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
         match self {
-            SearchQuery::Text { replacement, .. } => replacement.clone().map(Cow::from),
+            SearchQuery::Text { replacement, .. } | SearchQuery::Structural { replacement, .. } => {
+                replacement.clone().map(Cow::from)
+            }
             SearchQuery::Regex {
                 regex, replacement, ..
             } => {
@@ -418,7 +666,7 @@ impl SearchQuery {
                             x => unreachable!("Unexpected escape sequence: {}", x),
                         },
                     );
-                    Some(regex.replace(text, replacement))
+                    Some(regex.replace(text, &replacement))
                 } else {
                     None
                 }
@@ -431,10 +679,28 @@ impl SearchQuery {
         buffer: &BufferSnapshot,
         subrange: Option<Range<usize>>,
     ) -> Vec<Range<usize>> {
+        self.try_search(buffer, subrange)
+            .await
+            .unwrap_or_else(|error| {
+                log::warn!("searching for {:?} failed: {error}", self.as_str());
+                Vec::new()
+            })
+    }
+
+    /// Like [`Self::search`], but fails instead of returning no matches when a regex search
+    /// exceeds its budget.
+    pub async fn try_search(
+        &self,
+        buffer: &BufferSnapshot,
+        subrange: Option<Range<usize>>,
+    ) -> Result<Vec<Range<usize>>, RegexBudgetError> {
         const YIELD_INTERVAL: usize = 20000;
 
         if self.as_str().is_empty() {
-            return Default::default();
+            return Ok(Default::default());
+        }
+        if let Self::Structural { query, .. } = self {
+            return Ok(query.search(buffer, subrange));
         }
 
         let range_offset = subrange.as_ref().map(|r| r.start).unwrap_or(0);
@@ -490,9 +756,7 @@ impl SearchQuery {
                             yield_now().await;
                         }
 
-                        if let Ok(mat) = mat {
-                            matches.push(mat.start()..mat.end());
-                        }
+                        matches.push(mat?);
                     }
                 } else {
                     let mut line = String::new();
@@ -504,10 +768,9 @@ impl SearchQuery {
 
                         for (newline_ix, text) in chunk.split('\n').enumerate() {
                             if newline_ix > 0 {
-                                for mat in regex.find_iter(&line).flatten() {
-                                    let start = line_offset + mat.start();
-                                    let end = line_offset + mat.end();
-                                    matches.push(start..end);
+                                for mat in regex.find_iter(&line) {
+                                    let mat = mat?;
+                                    matches.push(line_offset + mat.start..line_offset + mat.end);
                                     if self.one_match_per_line() == Some(true) {
                                         break;
                                     }
@@ -521,9 +784,11 @@ impl SearchQuery {
                     }
                 }
             }
+
+            Self::Structural { .. } => {}
         }
 
-        matches
+        Ok(matches)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -538,6 +803,7 @@ impl SearchQuery {
         match self {
             Self::Text { whole_word, .. } => *whole_word,
             Self::Regex { whole_word, .. } => *whole_word,
+            Self::Structural { .. } => false,
         }
     }
 
@@ -545,6 +811,7 @@ impl SearchQuery {
         match self {
             Self::Text { case_sensitive, .. } => *case_sensitive,
             Self::Regex { case_sensitive, .. } => *case_sensitive,
+            Self::Structural { .. } => true,
         }
     }
 
@@ -556,6 +823,9 @@ impl SearchQuery {
             Self::Regex {
                 include_ignored, ..
             } => *include_ignored,
+            Self::Structural {
+                include_ignored, ..
+            } => *include_ignored,
         }
     }
 
@@ -563,6 +833,10 @@ impl SearchQuery {
         matches!(self, Self::Regex { .. })
     }
 
+    pub fn is_structural(&self) -> bool {
+        matches!(self, Self::Structural { .. })
+    }
+
     pub fn files_to_include(&self) -> &PathMatcher {
         self.as_inner().files_to_include()
     }
@@ -606,7 +880,17 @@ impl SearchQuery {
     }
     pub fn as_inner(&self) -> &SearchInputs {
         match self {
-            Self::Regex { inner, .. } | Self::Text { inner, .. } => inner,
+            Self::Regex { inner, .. }
+            | Self::Text { inner, .. }
+            | Self::Structural { inner, .. } => inner,
+        }
+    }
+
+    fn as_inner_mut(&mut self) -> &mut SearchInputs {
+        match self {
+            Self::Regex { inner, .. }
+            | Self::Text { inner, .. }
+            | Self::Structural { inner, .. } => inner,
         }
     }
 
@@ -619,7 +903,7 @@ impl SearchQuery {
             Self::Regex {
                 one_match_per_line, ..
             } => Some(*one_match_per_line),
-            Self::Text { .. } => None,
+            Self::Text { .. } | Self::Structural { .. } => None,
         }
     }
 }
@@ -755,4 +1039,65 @@ mod tests {
             "Case sensitivity should not be enabled when \\C pattern item is preceded by a backslash."
         );
     }
+
+    #[test]
+    fn test_structural_query_proto_round_trip() {
+        let query = SearchQuery::structural(
+            "(call_expression function: (identifier) @name) @match",
+            vec![("@name".to_string(), "^foo".to_string())],
+            true,
+            PathMatcher::new(&["src/**".to_owned()], PathStyle::local()).unwrap(),
+            Default::default(),
+            false,
+            None,
+        )
+        .unwrap();
+
+        let message = query.to_proto();
+        assert!(message.structural);
+        assert!(!message.regex);
+
+        let query = SearchQuery::from_proto(message, PathStyle::local()).unwrap();
+        let SearchQuery::Structural {
+            query: structural,
+            include_ignored,
+            ..
+        } = &query
+        else {
+            panic!("expected a structural query, got {query:?}");
+        };
+        assert_eq!(
+            query.as_str(),
+            "(call_expression function: (identifier) @name) @match"
+        );
+        assert!(include_ignored);
+        assert_eq!(
+            structural.capture_filters().collect::<Vec<_>>(),
+            [("name", "^foo")]
+        );
+        assert_eq!(
+            query.files_to_include().sources().collect::<Vec<_>>(),
+            ["src/**"]
+        );
+    }
+
+    #[test]
+    fn test_relevance_score() {
+        use util::rel_path::rel_path;
+        use worktree::WorktreeId;
+
+        let path = |worktree_id, path| ProjectPath {
+            worktree_id: WorktreeId::from_usize(worktree_id),
+            path: rel_path(path).into(),
+        };
+        let active_path = path(1, "src/b.rs");
+        let score = |path: &ProjectPath| relevance_score(Some(path), Some(&active_path), 0, 10);
+
+        assert_eq!(score(&path(1, "src/a.rs")), 1.);
+        assert_eq!(score(&path(1, "src/x/c.rs")), 2. / 3.);
+        assert_eq!(score(&path(1, "docs/d.md")), 0.5);
+        assert_eq!(score(&path(2, "src/a.rs")), 0.);
+        assert_eq!(relevance_score(None, Some(&active_path), 3, 10), 0.3);
+        assert_eq!(relevance_score(None, None, 50, 10), 1.);
+    }
 }
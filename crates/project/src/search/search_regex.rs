@@ -0,0 +1,151 @@
+use std::{borrow::Cow, ops::Range};
+
+use anyhow::Result;
+use itertools::Either;
+
+/// The maximum size of a compiled regex program, in bytes.
+const REGEX_SIZE_LIMIT: usize = 16 * 1024 * 1024;
+/// The maximum number of backtracking steps of a single match of a regex that uses lookaround
+/// or backreferences.
+const REGEX_BACKTRACK_LIMIT: usize = 1_000_000;
+
+/// The budget of a regex search was exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RegexBudgetError {
+    #[error("the regex is too large, try a simpler pattern")]
+    SizeLimitExceeded,
+    #[error("the regex search backtracked too much, try a simpler pattern")]
+    BacktrackLimitExceeded,
+}
+
+/// A compiled search regex.
+///
+/// Patterns are compiled with the linear-time engine of the `regex` crate, falling back to the
+/// backtracking engine of `fancy-regex` for patterns that need lookaround or backreferences.
+#[derive(Clone, Debug)]
+pub enum SearchRegex {
+    Standard(regex::Regex),
+    Backtracking(fancy_regex::Regex),
+}
+
+impl SearchRegex {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<Self> {
+        let error = match regex::RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+        {
+            Ok(regex) => return Ok(Self::Standard(regex)),
+            Err(regex::Error::CompiledTooBig(_)) => {
+                return Err(RegexBudgetError::SizeLimitExceeded.into());
+            }
+            Err(error) => error,
+        };
+
+        match fancy_regex::RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .delegate_size_limit(REGEX_SIZE_LIMIT)
+            .backtrack_limit(REGEX_BACKTRACK_LIMIT)
+            .build()
+        {
+            Ok(regex) => Ok(Self::Backtracking(regex)),
+            // Report the error of the standard engine, unless the pattern is only invalid there.
+            Err(fancy_regex::Error::ParseError(..)) => Err(error.into()),
+            Err(fancy_error) => Err(fancy_error.into()),
+        }
+    }
+
+    pub fn is_backtracking(&self) -> bool {
+        matches!(self, Self::Backtracking(_))
+    }
+
+    pub fn is_match(&self, text: &str) -> Result<bool, RegexBudgetError> {
+        match self {
+            Self::Standard(regex) => Ok(regex.is_match(text)),
+            Self::Backtracking(regex) => regex
+                .is_match(text)
+                .map_err(|_| RegexBudgetError::BacktrackLimitExceeded),
+        }
+    }
+
+    /// Returns the ranges of the successive non-overlapping matches in the text. Iteration ends
+    /// after yielding an error.
+    pub fn find_iter<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = Result<Range<usize>, RegexBudgetError>> + 'a {
+        match self {
+            Self::Standard(regex) => {
+                Either::Left(regex.find_iter(text).map(|found| Ok(found.range())))
+            }
+            Self::Backtracking(regex) => {
+                let mut failed = false;
+                Either::Right(regex.find_iter(text).map_while(move |found| {
+                    if failed {
+                        return None;
+                    }
+                    failed = found.is_err();
+                    Some(
+                        found
+                            .map(|found| found.range())
+                            .map_err(|_| RegexBudgetError::BacktrackLimitExceeded),
+                    )
+                }))
+            }
+        }
+    }
+
+    /// Replaces the first match in the text, expanding `$name` and `${name}` references to
+    /// capture groups in the replacement.
+    pub fn replace<'a>(&self, text: &'a str, replacement: &str) -> Cow<'a, str> {
+        match self {
+            Self::Standard(regex) => regex.replace(text, replacement),
+            Self::Backtracking(regex) => regex.replace(text, replacement),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_selection() {
+        let regex = SearchRegex::new(r"\bfoo\d+", false).unwrap();
+        assert!(!regex.is_backtracking());
+        assert_eq!(
+            regex.find_iter("foo1 xfoo2 FOO3").collect::<Vec<_>>(),
+            [Ok(0..4)]
+        );
+
+        let regex = SearchRegex::new(r"(\w+) \1", false).unwrap();
+        assert!(regex.is_backtracking());
+        assert_eq!(regex.is_match("the the"), Ok(true));
+        assert_eq!(regex.replace("say the the", "<$1>"), "say <the>");
+
+        let regex = SearchRegex::new(r"foo(?!bar)", true).unwrap();
+        assert!(regex.is_backtracking());
+        assert_eq!(
+            regex.find_iter("foobar FOObaz").collect::<Vec<_>>(),
+            [Ok(7..10)]
+        );
+
+        assert!(SearchRegex::new(r"(unclosed", false).is_err());
+    }
+
+    #[test]
+    fn test_budgets() {
+        let error = SearchRegex::new(r"(?:\w{1000}){100}", false).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<RegexBudgetError>(),
+            Some(&RegexBudgetError::SizeLimitExceeded)
+        );
+
+        let regex = SearchRegex::new(r"(?i)(a|b|ab)*(?=c)", false).unwrap();
+        let text = "ab".repeat(32);
+        assert_eq!(
+            regex.find_iter(&text).collect::<Vec<_>>(),
+            [Err(RegexBudgetError::BacktrackLimitExceeded)]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fmt, ops::Range, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use collections::HashMap;
+use fancy_regex::Regex;
+use language::{BufferSnapshot, GrammarId, Language};
+use parking_lot::Mutex;
+use streaming_iterator::StreamingIterator as _;
+use tree_sitter::{Query, QueryCursor};
+
+/// The capture whose range is reported for each match of a structural query. Matches of
+/// queries without such a capture span all of their captures.
+pub const MATCH_CAPTURE_NAME: &str = "match";
+
+/// A tree-sitter query pattern, compiled lazily for each grammar it is run against.
+pub struct StructuralQuery {
+    source: Arc<str>,
+    /// Regexes that the text of the named captures has to match for a match to be reported.
+    capture_filters: Vec<(String, Regex)>,
+    compiled: Mutex<HashMap<GrammarId, Option<Arc<Query>>>>,
+}
+
+impl fmt::Debug for StructuralQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StructuralQuery")
+            .field("source", &self.source)
+            .field("capture_filters", &self.capture_filters)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StructuralQuery {
+    pub fn new(source: Arc<str>, capture_filters: Vec<(String, String)>) -> Result<Self> {
+        if source.trim().is_empty() {
+            return Err(anyhow!("structural query is empty"));
+        }
+        let capture_filters = capture_filters
+            .into_iter()
+            .map(|(capture, pattern)| {
+                let capture = capture.trim_start_matches('@').to_string();
+                anyhow::Ok((capture, Regex::new(&pattern)?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            source,
+            capture_filters,
+            compiled: Mutex::default(),
+        })
+    }
+
+    pub fn capture_filters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.capture_filters
+            .iter()
+            .map(|(capture, regex)| (capture.as_str(), regex.as_str()))
+    }
+
+    /// Returns whether a file with the given contents may contain matches, without parsing it.
+    pub(crate) fn could_match(&self, text: &str) -> bool {
+        self.capture_filters
+            .iter()
+            .all(|(_, regex)| regex.is_match(text).unwrap_or(false))
+    }
+
+    fn query_for_language(&self, language: &Language) -> Option<Arc<Query>> {
+        let grammar = language.grammar()?;
+        self.compiled
+            .lock()
+            .entry(grammar.id())
+            .or_insert_with(|| match Query::new(&grammar.ts_language, &self.source) {
+                Ok(query) => Some(Arc::new(query)),
+                Err(error) => {
+                    log::debug!(
+                        "structural query does not apply to {}: {error}",
+                        language.name()
+                    );
+                    None
+                }
+            })
+            .clone()
+    }
+
+    /// Returns the ranges of all matches within the given subrange of the buffer, relative to
+    /// the start of the subrange.
+    pub(crate) fn search(
+        &self,
+        buffer: &BufferSnapshot,
+        subrange: Option<Range<usize>>,
+    ) -> Vec<Range<usize>> {
+        let range = subrange.unwrap_or(0..buffer.len());
+        let text = buffer.text();
+        let mut matches = Vec::new();
+        for layer in buffer.syntax_layers_for_range(range.clone(), true) {
+            let Some(query) = self.query_for_language(layer.language) else {
+                continue;
+            };
+            let Some(filters) = self
+                .capture_filters
+                .iter()
+                .map(|(capture, regex)| Some((query.capture_index_for_name(capture)?, regex)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let match_capture_ix = query.capture_index_for_name(MATCH_CAPTURE_NAME);
+
+            let mut cursor = QueryCursor::new();
+            cursor.set_byte_range(range.clone());
+            let mut query_matches = cursor.matches(&query, layer.node(), text.as_bytes());
+            while let Some(query_match) = query_matches.next() {
+                let passes_filters = filters.iter().all(|(capture_ix, regex)| {
+                    query_match
+                        .nodes_for_capture_index(*capture_ix)
+                        .all(|node| regex.is_match(&text[node.byte_range()]).unwrap_or(false))
+                });
+                if !passes_filters {
+                    continue;
+                }
+                let match_range = match match_capture_ix
+                    .and_then(|ix| query_match.nodes_for_capture_index(ix).next())
+                {
+                    Some(node) => node.byte_range(),
+                    None => {
+                        let Some(start) = query_match
+                            .captures
+                            .iter()
+                            .map(|capture| capture.node.start_byte())
+                            .min()
+                        else {
+                            continue;
+                        };
+                        let end = query_match
+                            .captures
+                            .iter()
+                            .map(|capture| capture.node.end_byte())
+                            .max()
+                            .unwrap_or(start);
+                        start..end
+                    }
+                };
+                if match_range.start >= range.start && match_range.end <= range.end {
+                    matches.push(match_range.start - range.start..match_range.end - range.start);
+                }
+            }
+        }
+        matches.sort_unstable_by_key(|range| (range.start, range.end));
+        matches.dedup();
+        matches
+    }
+}
@@ -1,4 +1,6 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, path::Path};
+
+use serde::{Deserialize, Serialize};
 
 /// Determines the behavior to use when inserting a new query into the search history.
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -91,6 +93,46 @@ impl SearchHistory {
         cursor.selection = Some(prev_index);
         Some(previous)
     }
+
+    /// Returns the queries in the history, from oldest to newest.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.history.iter().map(String::as_str)
+    }
+
+    /// Inserts queries from a previous session before the queries in the history, dropping the
+    /// oldest ones if the maximum length of the history is exceeded.
+    pub fn restore(&mut self, entries: Vec<String>) {
+        for entry in entries.into_iter().rev() {
+            if self
+                .max_history_len
+                .is_some_and(|max_history_len| self.history.len() >= max_history_len)
+            {
+                break;
+            }
+            self.history.push_front(entry);
+        }
+    }
+}
+
+/// The search histories of a project, persisted across sessions.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SerializedSearchHistories {
+    pub query: Vec<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// Returns the database key of the search histories of a project with the given worktree roots.
+pub(crate) fn search_history_key<'a>(roots: impl IntoIterator<Item = &'a Path>) -> Option<String> {
+    let mut roots = roots
+        .into_iter()
+        .map(|root| root.to_string_lossy())
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        return None;
+    }
+    roots.sort_unstable();
+    Some(format!("project_search_history-{}", roots.join(":")))
 }
 
 #[cfg(test)]
@@ -247,4 +289,26 @@ mod tests {
         assert_eq!(search_history.current(&cursor1), None);
         assert_eq!(search_history.current(&cursor2), None);
     }
+
+    #[test]
+    fn test_restore() {
+        let mut search_history = SearchHistory::new(Some(3), QueryInsertionBehavior::AlwaysInsert);
+        let mut cursor = SearchHistoryCursor::default();
+        search_history.add(&mut cursor, "current".to_string());
+
+        search_history.restore(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert_eq!(
+            search_history.entries().collect::<Vec<_>>(),
+            ["b", "c", "current"],
+            "Restored entries should precede the current ones, dropping the oldest"
+        );
+        cursor.reset();
+        assert_eq!(search_history.previous(&mut cursor), Some("current"));
+
+        assert_eq!(
+            search_history_key([Path::new("/b"), Path::new("/a")]),
+            search_history_key([Path::new("/a"), Path::new("/b")]),
+        );
+        assert_eq!(search_history_key([]), None);
+    }
 }
@@ -0,0 +1,404 @@
+//! An on-disk trigram index of the files in local worktrees, which lets project searches skip
+//! files that cannot contain the query without reading them.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context as _, Result};
+use collections::{HashMap, HashSet};
+use fs::{Fs, MTime};
+use gpui::{AppContext as _, AsyncApp, Context, Entity, Subscription, Task, WeakEntity};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use settings::{Settings as _, SettingsStore};
+use sha2::{Digest as _, Sha256};
+use util::{
+    ResultExt as _,
+    rel_path::{RelPath, RelPathBuf},
+};
+use worktree::{Entry, PathChange, Worktree, WorktreeId};
+
+use crate::{
+    project_settings::ProjectSettings,
+    search::SearchQuery,
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+};
+
+/// Files larger than this are not indexed, and are always read when searching.
+const MAX_INDEXED_FILE_SIZE: u64 = 4 * 1024 * 1024;
+/// The number of files indexed between persisting the index to disk.
+const SAVE_INTERVAL: usize = 1024;
+
+type Trigram = u32;
+
+#[derive(Serialize, Deserialize)]
+struct IndexedFile {
+    mtime: MTime,
+    /// The sorted, deduplicated trigrams of the file's ASCII-lowercased contents.
+    trigrams: Box<[Trigram]>,
+}
+
+#[derive(Default)]
+struct WorktreeIndex {
+    files: HashMap<Arc<RelPath>, IndexedFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedWorktreeIndex {
+    files: Vec<(RelPathBuf, IndexedFile)>,
+}
+
+/// The trigrams of the indexed files of all worktrees, shared with running searches.
+#[derive(Clone, Default)]
+pub(crate) struct TrigramIndex(Arc<RwLock<HashMap<WorktreeId, WorktreeIndex>>>);
+
+impl TrigramIndex {
+    /// Returns whether the file may contain all of the given trigrams. Files that haven't been
+    /// indexed at their current modification time always may.
+    pub(crate) fn may_contain(
+        &self,
+        worktree_id: WorktreeId,
+        entry: &Entry,
+        trigrams: &[Trigram],
+    ) -> bool {
+        let indices = self.0.read();
+        let Some(file) = indices
+            .get(&worktree_id)
+            .and_then(|index| index.files.get(&entry.path))
+        else {
+            return true;
+        };
+        if entry.mtime != Some(file.mtime) {
+            return true;
+        }
+        trigrams
+            .iter()
+            .all(|trigram| file.trigrams.binary_search(trigram).is_ok())
+    }
+}
+
+/// Returns the trigrams that every file matching the query contains, or `None` when the query
+/// can't be used to rule out files.
+pub(crate) fn query_trigrams(query: &SearchQuery) -> Option<Vec<Trigram>> {
+    match query {
+        SearchQuery::Text { .. } if query.as_str().len() >= 3 => {
+            Some(trigrams(query.as_str().as_bytes()).into_vec())
+        }
+        _ => None,
+    }
+}
+
+fn trigrams(content: &[u8]) -> Box<[Trigram]> {
+    // Buffers normalize line endings, so don't let carriage returns separate the characters
+    // that queries spanning multiple lines are matched against.
+    let content = content
+        .iter()
+        .filter(|byte| **byte != b'\r')
+        .map(u8::to_ascii_lowercase)
+        .collect::<Vec<_>>();
+    let mut trigrams = content
+        .windows(3)
+        .map(|window| u32::from_be_bytes([0, window[0], window[1], window[2]]))
+        .collect::<Vec<_>>();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams.into_boxed_slice()
+}
+
+/// Maintains the [`TrigramIndex`] of the project's local worktrees while the `search_index`
+/// setting is enabled, updating it from worktree events and persisting it across restarts.
+pub struct SearchIndex {
+    fs: Arc<dyn Fs>,
+    worktree_store: Entity<WorktreeStore>,
+    index: TrigramIndex,
+    enabled: bool,
+    pending_paths: HashMap<WorktreeId, HashSet<Arc<RelPath>>>,
+    indexing_tasks: HashMap<WorktreeId, Task<()>>,
+    _subscriptions: [Subscription; 2],
+}
+
+impl SearchIndex {
+    pub fn new(
+        fs: Arc<dyn Fs>,
+        worktree_store: Entity<WorktreeStore>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut this = Self {
+            fs,
+            index: TrigramIndex::default(),
+            enabled: false,
+            pending_paths: HashMap::default(),
+            indexing_tasks: HashMap::default(),
+            _subscriptions: [
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
+                cx.observe_global::<SettingsStore>(Self::on_settings_changed),
+            ],
+            worktree_store,
+        };
+        this.on_settings_changed(cx);
+        this
+    }
+
+    /// Returns the index to consult during searches, if indexing is enabled.
+    pub(crate) fn index(&self) -> Option<TrigramIndex> {
+        self.enabled.then(|| self.index.clone())
+    }
+
+    fn on_settings_changed(&mut self, cx: &mut Context<Self>) {
+        let enabled = ProjectSettings::get_global(cx).search_index;
+        if enabled == self.enabled {
+            return;
+        }
+        self.enabled = enabled;
+        if enabled {
+            let worktrees = self.worktree_store.read(cx).worktrees().collect::<Vec<_>>();
+            for worktree in worktrees {
+                self.load_worktree_index(worktree, cx);
+            }
+        } else {
+            self.pending_paths.clear();
+            self.indexing_tasks.clear();
+            self.index.0.write().clear();
+        }
+    }
+
+    fn on_worktree_store_event(
+        &mut self,
+        _: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(worktree) => {
+                self.load_worktree_index(worktree.clone(), cx)
+            }
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                self.pending_paths.remove(worktree_id);
+                self.indexing_tasks.remove(worktree_id);
+                self.index.0.write().remove(worktree_id);
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                let mut removed_paths = Vec::new();
+                let mut changed_paths = Vec::new();
+                for (path, _, change) in changes.iter() {
+                    match change {
+                        PathChange::Removed => removed_paths.push(path.clone()),
+                        _ => changed_paths.push(path.clone()),
+                    }
+                }
+                if let Some(index) = self.index.0.write().get_mut(worktree_id) {
+                    for path in &removed_paths {
+                        index.files.remove(path);
+                    }
+                }
+                self.queue_paths(*worktree_id, changed_paths, cx);
+            }
+            _ => {}
+        }
+    }
+
+    /// Loads the persisted index of the worktree, then reindexes the files that changed since
+    /// it was written.
+    fn load_worktree_index(&mut self, worktree: Entity<Worktree>, cx: &mut Context<Self>) {
+        let worktree_id = worktree.read(cx).id();
+        let Some(local) = worktree.read(cx).as_local() else {
+            return;
+        };
+        let index_path = index_path(&local.abs_path().to_string_lossy());
+        let fs = self.fs.clone();
+        let task = cx.spawn(async move |this, cx| {
+            let persisted = cx
+                .background_spawn(async move {
+                    let bytes = fs.load_bytes(&index_path).await.ok()?;
+                    bincode::deserialize::<PersistedWorktreeIndex>(&bytes).log_err()
+                })
+                .await;
+            this.update(cx, |this, cx| {
+                if let Some(persisted) = persisted {
+                    let files = persisted
+                        .files
+                        .into_iter()
+                        .map(|(path, file)| (path.into(), file))
+                        .collect();
+                    this.index
+                        .0
+                        .write()
+                        .insert(worktree_id, WorktreeIndex { files });
+                }
+                this.indexing_tasks.remove(&worktree_id);
+                let paths = worktree
+                    .read(cx)
+                    .snapshot()
+                    .files(false, 0)
+                    .map(|entry| entry.path.clone())
+                    .collect::<Vec<_>>();
+                this.queue_paths(worktree_id, paths, cx);
+            })
+            .log_err();
+        });
+        self.indexing_tasks.insert(worktree_id, task);
+    }
+
+    fn queue_paths(
+        &mut self,
+        worktree_id: WorktreeId,
+        paths: impl IntoIterator<Item = Arc<RelPath>>,
+        cx: &mut Context<Self>,
+    ) {
+        self.pending_paths
+            .entry(worktree_id)
+            .or_default()
+            .extend(paths);
+        if !self.indexing_tasks.contains_key(&worktree_id) {
+            let task = cx.spawn(async move |this, cx| {
+                Self::index_pending_paths(this, worktree_id, cx)
+                    .await
+                    .log_err();
+            });
+            self.indexing_tasks.insert(worktree_id, task);
+        }
+    }
+
+    async fn index_pending_paths(
+        this: WeakEntity<Self>,
+        worktree_id: WorktreeId,
+        cx: &mut AsyncApp,
+    ) -> Result<()> {
+        let mut indexed_since_save = 0;
+        loop {
+            let (fs, worktree, index, paths) = this.update(cx, |this, cx| {
+                let paths = this.pending_paths.remove(&worktree_id).unwrap_or_default();
+                let worktree = this
+                    .worktree_store
+                    .read(cx)
+                    .worktree_for_id(worktree_id, cx);
+                (this.fs.clone(), worktree, this.index.clone(), paths)
+            })?;
+            let Some(worktree) = worktree else {
+                return Ok(());
+            };
+            let snapshot = worktree.read_with(cx, |worktree, _| worktree.snapshot())?;
+            if paths.is_empty() {
+                if indexed_since_save > 0 {
+                    save_worktree_index(fs.as_ref(), &index, &snapshot).await?;
+                    indexed_since_save = 0;
+                }
+                // Paths may have been queued while saving, in which case this task has to keep
+                // indexing, as `queue_paths` won't start another one.
+                let is_done = this.update(cx, |this, _| {
+                    let is_done = this
+                        .pending_paths
+                        .get(&worktree_id)
+                        .is_none_or(|paths| paths.is_empty());
+                    if is_done {
+                        this.indexing_tasks.remove(&worktree_id);
+                    }
+                    is_done
+                })?;
+                if is_done {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            for path in paths {
+                let Some(entry) = snapshot.entry_for_path(&path) else {
+                    continue;
+                };
+                let Some(mtime) = entry.mtime else {
+                    continue;
+                };
+                if !entry.is_file()
+                    || entry.is_ignored
+                    || entry.is_fifo
+                    || entry.size > MAX_INDEXED_FILE_SIZE
+                {
+                    continue;
+                }
+                let is_up_to_date = index
+                    .0
+                    .read()
+                    .get(&worktree_id)
+                    .and_then(|worktree_index| worktree_index.files.get(&path))
+                    .is_some_and(|file| file.mtime == mtime);
+                if is_up_to_date {
+                    continue;
+                }
+
+                let abs_path = snapshot.absolutize(&path);
+                let Some(content) = fs.load_bytes(&abs_path).await.log_err() else {
+                    continue;
+                };
+                let trigrams = cx.background_spawn(async move { trigrams(&content) }).await;
+                index
+                    .0
+                    .write()
+                    .entry(worktree_id)
+                    .or_default()
+                    .files
+                    .insert(path, IndexedFile { mtime, trigrams });
+
+                indexed_since_save += 1;
+                if indexed_since_save >= SAVE_INTERVAL {
+                    save_worktree_index(fs.as_ref(), &index, &snapshot).await?;
+                    indexed_since_save = 0;
+                }
+            }
+        }
+    }
+}
+
+async fn save_worktree_index(
+    fs: &dyn Fs,
+    index: &TrigramIndex,
+    snapshot: &worktree::Snapshot,
+) -> Result<()> {
+    let bytes = {
+        let indices = index.0.read();
+        let Some(worktree_index) = indices.get(&snapshot.id()) else {
+            return Ok(());
+        };
+        let files = worktree_index
+            .files
+            .iter()
+            .map(|(path, file)| {
+                (
+                    path.to_rel_path_buf(),
+                    IndexedFile {
+                        mtime: file.mtime,
+                        trigrams: file.trigrams.clone(),
+                    },
+                )
+            })
+            .collect();
+        bincode::serialize(&PersistedWorktreeIndex { files })?
+    };
+    let index_path = index_path(&snapshot.abs_path().to_string_lossy());
+    if let Some(parent) = index_path.parent() {
+        fs.create_dir(parent).await?;
+    }
+    fs.write(&index_path, &bytes)
+        .await
+        .with_context(|| format!("writing search index to {index_path:?}"))
+}
+
+fn index_path(worktree_abs_path: &str) -> PathBuf {
+    let digest = format!("{:x}", Sha256::digest(worktree_abs_path.as_bytes()));
+    paths::search_index_dir().join(&digest[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigrams() {
+        let trigram = |text: &str| trigrams(text.as_bytes())[0];
+        assert_eq!(trigrams(b"ab"), Box::default());
+        assert_eq!(trigrams(b"abcabc").len(), 3);
+        assert_eq!(trigrams(b"ABC"), trigrams(b"abc"));
+        assert!(trigrams(b"a\r\nb").contains(&trigram("a\nb")));
+    }
+}
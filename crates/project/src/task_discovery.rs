@@ -0,0 +1,549 @@
+//! Discovery of the tasks defined by the build files of a worktree: `Makefile` targets, `justfile` recipes,
+//! `package.json` scripts, `Cargo.toml` targets and `Taskfile.yml` tasks.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use collections::{HashMap, HashSet};
+use fs::Fs;
+use gpui::{Context, Entity, Task};
+use task::{TaskTemplate, VariableName};
+use util::{ResultExt as _, rel_path::RelPath};
+use worktree::WorktreeId;
+
+use crate::{
+    Inventory,
+    task_store::TaskStore,
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+};
+
+/// How long to wait for more build file changes before reloading the changed ones.
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildFileKind {
+    Makefile,
+    Justfile,
+    PackageJson,
+    CargoManifest,
+    Taskfile,
+}
+
+impl BuildFileKind {
+    pub fn for_file_name(file_name: &str) -> Option<Self> {
+        match file_name {
+            "Makefile" | "makefile" | "GNUmakefile" => Some(Self::Makefile),
+            "justfile" | "Justfile" | ".justfile" => Some(Self::Justfile),
+            "package.json" => Some(Self::PackageJson),
+            "Cargo.toml" => Some(Self::CargoManifest),
+            "Taskfile.yml" | "Taskfile.yaml" | "taskfile.yml" | "taskfile.yaml" => {
+                Some(Self::Taskfile)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the commands the build file defines, with the zero-based rows they are defined at.
+    fn commands(self, contents: &str) -> Vec<(u32, Vec<String>)> {
+        match self {
+            Self::Makefile => makefile_commands(contents),
+            Self::Justfile => justfile_commands(contents),
+            Self::PackageJson => package_json_commands(contents),
+            Self::CargoManifest => cargo_manifest_commands(contents),
+            Self::Taskfile => taskfile_commands(contents),
+        }
+    }
+}
+
+/// A task defined by a build file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiscoveredTask {
+    /// Zero-based row of the build file the task is defined at.
+    pub row: u32,
+    pub template: TaskTemplate,
+}
+
+/// Returns the tasks defined by the build file at `path` in its worktree, run from the build file's directory.
+pub fn discover_tasks(kind: BuildFileKind, path: &RelPath, contents: &str) -> Vec<DiscoveredTask> {
+    let directory = path.parent().filter(|directory| !directory.is_empty());
+    kind.commands(contents)
+        .into_iter()
+        .filter_map(|(row, mut command_line)| {
+            if command_line.is_empty() {
+                return None;
+            }
+            let command = command_line.remove(0);
+            let mut label = command_line
+                .iter()
+                .fold(command.clone(), |label, arg| format!("{label} {arg}"));
+            if let Some(directory) = directory {
+                label = format!("{label} ({})", directory.as_unix_str());
+            }
+            Some(DiscoveredTask {
+                row,
+                template: TaskTemplate {
+                    label,
+                    command,
+                    args: command_line,
+                    cwd: directory.map(|directory| {
+                        format!(
+                            "${}/{}",
+                            VariableName::WorktreeRoot,
+                            directory.as_unix_str()
+                        )
+                    }),
+                    ..TaskTemplate::default()
+                },
+            })
+        })
+        .collect()
+}
+
+fn makefile_commands(contents: &str) -> Vec<(u32, Vec<String>)> {
+    let mut seen = HashSet::default();
+    let mut commands = Vec::new();
+    for (row, line) in contents.lines().enumerate() {
+        if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '.') {
+            continue;
+        }
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `:=` and `::=` are variable assignments, not rules.
+        if targets.contains('=') || rest.starts_with('=') || rest.starts_with(":=") {
+            continue;
+        }
+        for target in targets.split_whitespace() {
+            let is_plain_target = target
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'));
+            if is_plain_target && seen.insert(target) {
+                commands.push((row as u32, vec!["make".to_string(), target.to_string()]));
+            }
+        }
+    }
+    commands
+}
+
+fn justfile_commands(contents: &str) -> Vec<(u32, Vec<String>)> {
+    const NON_RECIPE_KEYWORDS: &[&str] = &["set", "alias", "import", "mod", "export"];
+
+    let mut commands = Vec::new();
+    for (row, line) in contents.lines().enumerate() {
+        if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '[') {
+            continue;
+        }
+        let Some((header, rest)) = line.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with('=') {
+            continue;
+        }
+        let Some(name) = header.trim_start_matches('@').split_whitespace().next() else {
+            continue;
+        };
+        // Recipes starting with an underscore are private.
+        if name.starts_with('_')
+            || NON_RECIPE_KEYWORDS.contains(&name)
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-'))
+        {
+            continue;
+        }
+        commands.push((row as u32, vec!["just".to_string(), name.to_string()]));
+    }
+    commands
+}
+
+fn package_json_commands(contents: &str) -> Vec<(u32, Vec<String>)> {
+    let Some(package) = serde_json::from_str::<serde_json::Value>(contents).log_err() else {
+        return Vec::new();
+    };
+    let Some(scripts) = package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+    else {
+        return Vec::new();
+    };
+    let lines = contents.lines().collect::<Vec<_>>();
+    let scripts_row = lines
+        .iter()
+        .position(|line| line.contains("\"scripts\""))
+        .unwrap_or_default();
+    scripts
+        .keys()
+        .map(|name| {
+            let key = format!("\"{name}\"");
+            let row = lines
+                .iter()
+                .enumerate()
+                .skip(scripts_row + 1)
+                .find(|(_, line)| line.trim_start().starts_with(&key))
+                .map_or(scripts_row, |(row, _)| row);
+            (
+                row as u32,
+                vec!["npm".to_string(), "run".to_string(), name.clone()],
+            )
+        })
+        .collect()
+}
+
+fn cargo_manifest_commands(contents: &str) -> Vec<(u32, Vec<String>)> {
+    let mut commands = Vec::new();
+    let mut section = None;
+    for (row, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            section = Some((line, row as u32));
+            if line == "[workspace]" {
+                commands.push((row as u32, cargo_command(&["build", "--workspace"])));
+                commands.push((row as u32, cargo_command(&["test", "--workspace"])));
+            }
+            continue;
+        }
+        let Some((header, header_row)) = section else {
+            continue;
+        };
+        let Some(name) = line
+            .strip_prefix("name")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+            .map(|value| value.trim().trim_matches('"'))
+        else {
+            continue;
+        };
+        match header {
+            "[package]" => {
+                commands.push((header_row, cargo_command(&["build", "-p", name])));
+                commands.push((header_row, cargo_command(&["test", "-p", name])));
+            }
+            "[[bin]]" => commands.push((header_row, cargo_command(&["run", "--bin", name]))),
+            "[[example]]" => {
+                commands.push((header_row, cargo_command(&["run", "--example", name])))
+            }
+            "[[test]]" => commands.push((header_row, cargo_command(&["test", "--test", name]))),
+            "[[bench]]" => commands.push((header_row, cargo_command(&["bench", "--bench", name]))),
+            _ => {}
+        }
+    }
+    commands
+}
+
+fn cargo_command(args: &[&str]) -> Vec<String> {
+    std::iter::once("cargo")
+        .chain(args.iter().copied())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn taskfile_commands(contents: &str) -> Vec<(u32, Vec<String>)> {
+    let mut commands = Vec::new();
+    let mut in_tasks = false;
+    let mut task_indent = None;
+    for (row, line) in contents.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent == 0 {
+            in_tasks = trimmed.trim_end() == "tasks:";
+            continue;
+        }
+        if !in_tasks || *task_indent.get_or_insert(indent) != indent {
+            continue;
+        }
+        if let Some((name, _)) = trimmed.split_once(':') {
+            let name = name.trim().trim_matches(|c| c == '"' || c == '\'');
+            if !name.is_empty() {
+                commands.push((row as u32, vec!["task".to_string(), name.to_string()]));
+            }
+        }
+    }
+    commands
+}
+
+/// Keeps the tasks of the worktrees' build files in the inventory up to date.
+pub(crate) struct BuildFileTasks {
+    fs: Arc<dyn Fs>,
+    worktree_store: Entity<WorktreeStore>,
+    inventory: Entity<Inventory>,
+    pending_paths: HashMap<WorktreeId, HashSet<Arc<RelPath>>>,
+    update_task: Option<Task<()>>,
+}
+
+impl BuildFileTasks {
+    pub(crate) fn new(
+        fs: Arc<dyn Fs>,
+        worktree_store: Entity<WorktreeStore>,
+        inventory: Entity<Inventory>,
+    ) -> Self {
+        Self {
+            fs,
+            worktree_store,
+            inventory,
+            pending_paths: HashMap::default(),
+            update_task: None,
+        }
+    }
+
+    pub(crate) fn on_worktree_store_event(
+        &mut self,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<TaskStore>,
+    ) {
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(worktree) => {
+                let worktree = worktree.read(cx);
+                let paths = worktree
+                    .files(false, 0)
+                    .filter(|entry| is_build_file(&entry.path))
+                    .map(|entry| entry.path.clone())
+                    .collect::<Vec<_>>();
+                self.queue_paths(worktree.id(), paths, cx);
+            }
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                self.pending_paths.remove(worktree_id);
+                self.inventory.update(cx, |inventory, _| {
+                    inventory.remove_worktree_build_file_tasks(*worktree_id)
+                });
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                let paths = changes
+                    .iter()
+                    .filter(|(path, _, _)| is_build_file(path))
+                    .map(|(path, _, _)| path.clone())
+                    .collect::<Vec<_>>();
+                self.queue_paths(*worktree_id, paths, cx);
+            }
+            _ => {}
+        }
+    }
+
+    fn queue_paths(
+        &mut self,
+        worktree_id: WorktreeId,
+        paths: Vec<Arc<RelPath>>,
+        cx: &mut Context<TaskStore>,
+    ) {
+        if paths.is_empty() {
+            return;
+        }
+        self.pending_paths
+            .entry(worktree_id)
+            .or_default()
+            .extend(paths);
+        if self.update_task.is_some() {
+            return;
+        }
+        let fs = self.fs.clone();
+        let worktree_store = self.worktree_store.clone();
+        let inventory = self.inventory.clone();
+        self.update_task = Some(cx.spawn(async move |task_store, cx| {
+            cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+            let Ok(pending_paths) = task_store.update(cx, |task_store, _| {
+                task_store
+                    .build_file_tasks_mut()
+                    .map(|build_file_tasks| {
+                        build_file_tasks.update_task = None;
+                        std::mem::take(&mut build_file_tasks.pending_paths)
+                    })
+                    .unwrap_or_default()
+            }) else {
+                return;
+            };
+            for (worktree_id, paths) in pending_paths {
+                let Ok(Some(worktree)) = worktree_store.read_with(cx, |worktree_store, cx| {
+                    worktree_store.worktree_for_id(worktree_id, cx)
+                }) else {
+                    continue;
+                };
+                let mut tasks_by_path = BTreeMap::new();
+                for path in paths {
+                    let Some(kind) = path.file_name().and_then(BuildFileKind::for_file_name) else {
+                        continue;
+                    };
+                    let Ok(abs_path) = worktree.read_with(cx, |worktree, _| {
+                        worktree
+                            .entry_for_path(&path)
+                            .filter(|entry| entry.is_file() && !entry.is_ignored)
+                            .map(|_| worktree.absolutize(&path))
+                    }) else {
+                        continue;
+                    };
+                    let tasks = match abs_path {
+                        Some(abs_path) => match fs.load(&abs_path).await {
+                            Ok(contents) => discover_tasks(kind, &path, &contents),
+                            Err(e) => {
+                                log::debug!("Failed to load build file {abs_path:?}: {e:#}");
+                                Vec::new()
+                            }
+                        },
+                        None => Vec::new(),
+                    };
+                    tasks_by_path.insert(path, tasks);
+                }
+                inventory
+                    .update(cx, |inventory, _| {
+                        for (path, tasks) in tasks_by_path {
+                            inventory.update_build_file_tasks(worktree_id, path, tasks);
+                        }
+                    })
+                    .ok();
+            }
+        }));
+    }
+}
+
+fn is_build_file(path: &RelPath) -> bool {
+    path.file_name()
+        .and_then(BuildFileKind::for_file_name)
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use util::rel_path::rel_path;
+
+    use super::*;
+
+    fn commands(kind: BuildFileKind, contents: &str) -> Vec<(u32, String)> {
+        kind.commands(contents)
+            .into_iter()
+            .map(|(row, command)| (row, command.join(" ")))
+            .collect()
+    }
+
+    #[test]
+    fn test_makefile_targets() {
+        let makefile = "\
+CC := gcc
+FLAGS = -O2 -Wl,-rpath:/opt
+.PHONY: all test
+all: build test
+
+build test: deps
+\t$(CC) main.c
+%.o: %.c
+\t$(CC) -c $<
+";
+        assert_eq!(
+            commands(BuildFileKind::Makefile, makefile),
+            vec![
+                (3, "make all".to_string()),
+                (5, "make build".to_string()),
+                (5, "make test".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_justfile_recipes() {
+        let justfile = "\
+set shell := [\"bash\", \"-c\"]
+version := \"1.0\"
+
+# Builds everything.
+build:
+    cargo build
+
+@test filter='': build
+    cargo test {{filter}}
+
+_helper:
+    echo private
+";
+        assert_eq!(
+            commands(BuildFileKind::Justfile, justfile),
+            vec![(4, "just build".to_string()), (7, "just test".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_package_json_scripts() {
+        let package_json = r#"{
+  "name": "app",
+  "scripts": {
+    "dev": "vite",
+    "build": "vite build"
+  }
+}"#;
+        assert_eq!(
+            commands(BuildFileKind::PackageJson, package_json),
+            vec![
+                (3, "npm run dev".to_string()),
+                (4, "npm run build".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_manifest_targets() {
+        let manifest = r#"[package]
+name = "app"
+version = "0.1.0"
+
+[dependencies]
+name = "not a target"
+
+[[bin]]
+name = "server"
+path = "src/server.rs"
+
+[[example]]
+name = "demo"
+"#;
+        assert_eq!(
+            commands(BuildFileKind::CargoManifest, manifest),
+            vec![
+                (0, "cargo build -p app".to_string()),
+                (0, "cargo test -p app".to_string()),
+                (7, "cargo run --bin server".to_string()),
+                (11, "cargo run --example demo".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_taskfile_tasks() {
+        let taskfile = "\
+version: '3'
+
+tasks:
+  build:
+    cmds:
+      - go build ./...
+  'lint':
+    cmds:
+      - golangci-lint run
+vars:
+  name: value
+";
+        assert_eq!(
+            commands(BuildFileKind::Taskfile, taskfile),
+            vec![(3, "task build".to_string()), (6, "task lint".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_nested_build_file_tasks() {
+        let tasks = discover_tasks(
+            BuildFileKind::Makefile,
+            rel_path("tools/Makefile"),
+            "lint:\n\techo lint\n",
+        );
+        assert_eq!(
+            tasks,
+            vec![DiscoveredTask {
+                row: 0,
+                template: TaskTemplate {
+                    label: "make lint (tools)".to_string(),
+                    command: "make".to_string(),
+                    args: vec!["lint".to_string()],
+                    cwd: Some("$VECTOR_WORKTREE_ROOT/tools".to_string()),
+                    ..TaskTemplate::default()
+                },
+            }]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! The environment of a task, composed of layers that apply in a defined order, so that tasks get the same
+//! environment as the project's terminals, with their own `env` on top.
+
+use std::path::Path;
+
+use collections::HashMap;
+use task::{ResolvedTask, VariableName, substitute_variables_in_map};
+
+/// A layer of a task's environment. The layers apply in the order of the variants, each one overriding the variables
+/// of the previous ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskEnvironmentLayer {
+    /// The environment of the project directory, as a login shell gets it there, including the variables loaded with
+    /// direnv.
+    Project,
+    /// The `env` of the terminal settings, which the terminals of the project get too.
+    Terminal,
+    /// `PATH`, starting with the directory of the active toolchain for the language of the task's file.
+    Toolchain,
+    /// The `env` of the task.
+    Task,
+    /// The task variables, like `VECTOR_FILE`.
+    TaskVariables,
+}
+
+/// The environment of a task, by layer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskEnvironment {
+    layers: Vec<(TaskEnvironmentLayer, HashMap<String, String>)>,
+}
+
+impl TaskEnvironment {
+    pub(crate) fn new(
+        project: HashMap<String, String>,
+        terminal: HashMap<String, String>,
+        toolchain_path: Option<&Path>,
+    ) -> Self {
+        let mut environment = Self::default();
+        environment.push_layer(TaskEnvironmentLayer::Project, project);
+        environment.push_layer(TaskEnvironmentLayer::Terminal, terminal);
+
+        let toolchain_dir = toolchain_path
+            .filter(|path| path.is_absolute())
+            .and_then(Path::parent);
+        if let Some(toolchain_dir) = toolchain_dir {
+            let path = environment.get("PATH").unwrap_or_default().to_owned();
+            let paths =
+                std::iter::once(toolchain_dir.to_path_buf()).chain(std::env::split_paths(&path));
+            if let Some(path) = std::env::join_paths(paths)
+                .ok()
+                .and_then(|path| path.into_string().ok())
+            {
+                environment.push_layer(
+                    TaskEnvironmentLayer::Toolchain,
+                    HashMap::from_iter([("PATH".to_string(), path)]),
+                );
+            }
+        }
+        environment
+    }
+
+    /// Adds the layers of a task, which was resolved with this environment as its project environment.
+    pub fn with_task(mut self, task: &ResolvedTask) -> Self {
+        let context = task.task_context();
+        let task_env =
+            substitute_variables_in_map(&task.original_task().env, context).unwrap_or_default();
+        let task_variables = context
+            .task_variables
+            .iter()
+            .filter(|(name, _)| !matches!(name, VariableName::Env(_)))
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        self.push_layer(TaskEnvironmentLayer::Task, task_env);
+        self.push_layer(TaskEnvironmentLayer::TaskVariables, task_variables);
+        self
+    }
+
+    fn push_layer(&mut self, layer: TaskEnvironmentLayer, variables: HashMap<String, String>) {
+        if !variables.is_empty() {
+            self.layers.push((layer, variables));
+        }
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = (TaskEnvironmentLayer, &HashMap<String, String>)> {
+        self.layers
+            .iter()
+            .map(|(layer, variables)| (*layer, variables))
+    }
+
+    /// The value the variable ends up with.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(_, variables)| variables.get(name))
+            .map(String::as_str)
+    }
+
+    /// The layer the value of the variable comes from.
+    pub fn source(&self, name: &str) -> Option<TaskEnvironmentLayer> {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(_, variables)| variables.contains_key(name))
+            .map(|(layer, _)| *layer)
+    }
+
+    /// The variables of all the layers, the way the task gets them.
+    pub fn resolve(&self) -> HashMap<String, String> {
+        let mut env = HashMap::default();
+        for (_, variables) in &self.layers {
+            env.extend(variables.clone());
+        }
+        env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use task::{TaskContext, TaskTemplate, TaskVariables};
+
+    use super::*;
+
+    #[test]
+    fn test_layers_apply_in_order() {
+        let toolchain_path = if cfg!(windows) {
+            PathBuf::from("C:\\project\\.venv\\Scripts\\python.exe")
+        } else {
+            PathBuf::from("/project/.venv/bin/python")
+        };
+        let toolchain_dir = toolchain_path
+            .parent()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let environment = TaskEnvironment::new(
+            HashMap::from_iter([
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("RUST_LOG".to_string(), "info".to_string()),
+                ("FROM_DIRENV".to_string(), "1".to_string()),
+            ]),
+            HashMap::from_iter([("RUST_LOG".to_string(), "debug".to_string())]),
+            Some(&toolchain_path),
+        );
+        assert_eq!(
+            environment.source("FROM_DIRENV"),
+            Some(TaskEnvironmentLayer::Project)
+        );
+        assert_eq!(environment.get("RUST_LOG"), Some("debug"));
+        assert_eq!(
+            environment.source("RUST_LOG"),
+            Some(TaskEnvironmentLayer::Terminal)
+        );
+        assert_eq!(
+            environment.source("PATH"),
+            Some(TaskEnvironmentLayer::Toolchain)
+        );
+        let path = environment.get("PATH").unwrap();
+        assert_eq!(
+            std::env::split_paths(path).collect::<Vec<_>>(),
+            vec![PathBuf::from(&toolchain_dir), PathBuf::from("/usr/bin")]
+        );
+
+        let template = TaskTemplate {
+            label: "run".to_string(),
+            command: "cargo run".to_string(),
+            env: HashMap::from_iter([("RUST_LOG".to_string(), "trace".to_string())]),
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::Stem, "main".to_string()),
+                (
+                    VariableName::Env(std::borrow::Cow::Borrowed("FROM_DIRENV")),
+                    "1".to_string(),
+                ),
+            ]),
+            project_env: environment.resolve(),
+        };
+        let task = template.resolve_task("test", &context).unwrap();
+        let environment = environment.with_task(&task);
+        assert_eq!(environment.get("RUST_LOG"), Some("trace"));
+        assert_eq!(
+            environment.source("RUST_LOG"),
+            Some(TaskEnvironmentLayer::Task)
+        );
+        assert_eq!(
+            environment.source("VECTOR_STEM"),
+            Some(TaskEnvironmentLayer::TaskVariables)
+        );
+        assert_eq!(environment.source("VECTOR_ENV_FROM_DIRENV"), None);
+        assert_eq!(
+            environment.resolve(),
+            task.resolved.env,
+            "the layers should add up to the environment the task runs with"
+        );
+    }
+}
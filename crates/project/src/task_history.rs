@@ -0,0 +1,321 @@
+//! The history of the tasks run in a project, kept across restarts so that they can be inspected and run again.
+
+use std::{path::PathBuf, time::Duration};
+
+use collections::HashMap;
+use gpui::App;
+use serde::{Deserialize, Serialize};
+use task::{ResolvedTask, TaskContext, TaskId, TaskTemplate, TaskVariables, VariableName};
+
+use crate::worktree_store::WorktreeStore;
+
+/// How many task runs are remembered per project.
+const MAX_TASK_RUNS: usize = 100;
+
+/// Identifies a [`TaskRun`] in the task history of the current session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskRunId(pub usize);
+
+/// A finished run of a task.
+#[derive(Clone, Debug)]
+pub struct TaskRun {
+    pub id: TaskRunId,
+    pub task_id: TaskId,
+    pub label: String,
+    /// The command, with all of its arguments, the way it was run.
+    pub command_label: String,
+    pub cwd: Option<PathBuf>,
+    /// The environment the task ran with. It may hold secrets, so it isn't kept across restarts.
+    pub env: HashMap<String, String>,
+    /// `None` when the task did not exit on its own, e.g. it was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    id_base: String,
+    template: TaskTemplate,
+    /// The context the task was resolved with, without the project environment.
+    context: TaskContext,
+}
+
+impl TaskRun {
+    pub(crate) fn new(
+        id_base: String,
+        task: &ResolvedTask,
+        exit_code: Option<i32>,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            id: TaskRunId(0),
+            task_id: task.id.clone(),
+            label: task.resolved_label.clone(),
+            command_label: task.resolved.command_label.clone(),
+            cwd: task.resolved.cwd.clone(),
+            env: task.resolved.env.clone(),
+            exit_code,
+            duration,
+            id_base,
+            template: task.original_task().clone(),
+            context: TaskContext {
+                project_env: HashMap::default(),
+                ..task.task_context().clone()
+            },
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// The directory the task was resolved in, whose environment it should be resolved again with.
+    pub fn context_cwd(&self) -> Option<&PathBuf> {
+        self.context.cwd.as_ref()
+    }
+
+    /// Resolves the task again with the variables it was run with and the current `project_env`, so that it runs
+    /// the same way.
+    pub fn resolve(&self, project_env: HashMap<String, String>) -> Option<ResolvedTask> {
+        let context = TaskContext {
+            project_env,
+            ..self.context.clone()
+        };
+        self.template.resolve_task(&self.id_base, &context)
+    }
+
+    fn serialize(&self) -> SerializedTaskRun {
+        SerializedTaskRun {
+            task_id: self.task_id.0.clone(),
+            label: self.label.clone(),
+            command_label: self.command_label.clone(),
+            cwd: self.cwd.clone(),
+            exit_code: self.exit_code,
+            duration_ms: self.duration.as_millis() as u64,
+            id_base: self.id_base.clone(),
+            template: self.template.clone(),
+            context_cwd: self.context.cwd.clone(),
+            task_variables: self
+                .context
+                .task_variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        }
+    }
+
+    fn deserialize(run: SerializedTaskRun) -> Self {
+        let task_variables = run
+            .task_variables
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.parse::<VariableName>().ok()?, value)))
+            .collect::<TaskVariables>();
+        Self {
+            id: TaskRunId(0),
+            task_id: TaskId(run.task_id),
+            label: run.label,
+            command_label: run.command_label,
+            cwd: run.cwd,
+            env: HashMap::default(),
+            context: TaskContext {
+                cwd: run.context_cwd,
+                task_variables,
+                project_env: HashMap::default(),
+            },
+            exit_code: run.exit_code,
+            duration: Duration::from_millis(run.duration_ms),
+            id_base: run.id_base,
+            template: run.template,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedTaskRun {
+    task_id: String,
+    label: String,
+    command_label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    duration_ms: u64,
+    id_base: String,
+    template: TaskTemplate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context_cwd: Option<PathBuf>,
+    #[serde(default)]
+    task_variables: HashMap<String, String>,
+}
+
+/// The task runs of a project, oldest first.
+#[derive(Default)]
+pub(crate) struct TaskHistory {
+    /// The key the history is stored under, once the project has visible worktrees.
+    key: Option<String>,
+    runs: Vec<TaskRun>,
+    next_id: usize,
+}
+
+impl TaskHistory {
+    pub(crate) fn runs(&self) -> &[TaskRun] {
+        &self.runs
+    }
+
+    pub(crate) fn run(&self, id: TaskRunId) -> Option<&TaskRun> {
+        self.runs.iter().find(|run| run.id == id)
+    }
+
+    pub(crate) fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Updates the key the history is stored under after the project's worktrees changed, returning it
+    /// if it is the first one, and so the history should be restored from it.
+    pub(crate) fn update_key(
+        &mut self,
+        worktree_store: &WorktreeStore,
+        cx: &App,
+    ) -> Option<String> {
+        let mut roots = worktree_store
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            return None;
+        }
+        roots.sort_unstable();
+        // Paths may contain any separator, so the roots are encoded as a JSON array.
+        let key = format!(
+            "project_task_history-{}",
+            serde_json::to_string(&roots).ok()?
+        );
+        let restore = self.key.is_none();
+        self.key = Some(key.clone());
+        restore.then_some(key)
+    }
+
+    pub(crate) fn push(&mut self, mut run: TaskRun) -> TaskRunId {
+        run.id = TaskRunId(self.next_id);
+        self.next_id += 1;
+        let id = run.id;
+        self.runs.push(run);
+        if self.runs.len() > MAX_TASK_RUNS {
+            self.runs.remove(0);
+        }
+        id
+    }
+
+    /// Adds the runs of a previous session before the ones of the current session.
+    pub(crate) fn restore(&mut self, serialized: &str) -> anyhow::Result<()> {
+        let serialized = serde_json::from_str::<Vec<SerializedTaskRun>>(serialized)?;
+        let mut runs = serialized
+            .into_iter()
+            .map(|run| {
+                let mut run = TaskRun::deserialize(run);
+                run.id = TaskRunId(self.next_id);
+                self.next_id += 1;
+                run
+            })
+            .collect::<Vec<_>>();
+        runs.append(&mut self.runs);
+        let excess = runs.len().saturating_sub(MAX_TASK_RUNS);
+        runs.drain(..excess);
+        self.runs = runs;
+        Ok(())
+    }
+
+    pub(crate) fn serialize(&self) -> anyhow::Result<String> {
+        let runs = self.runs.iter().map(TaskRun::serialize).collect::<Vec<_>>();
+        Ok(serde_json::to_string(&runs)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_task(history: &mut TaskHistory, label: &str, exit_code: i32) -> TaskRunId {
+        let template = TaskTemplate {
+            label: label.to_string(),
+            command: "echo".to_string(),
+            args: vec![VariableName::File.template_value()],
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: Some(PathBuf::from("/project")),
+            task_variables: TaskVariables::from_iter([(
+                VariableName::File,
+                "/project/main.rs".to_string(),
+            )]),
+            project_env: HashMap::from_iter([("PATH".to_string(), "/bin".to_string())]),
+        };
+        let task = template.resolve_task("test", &context).unwrap();
+        history.push(TaskRun::new(
+            "test".to_string(),
+            &task,
+            Some(exit_code),
+            Duration::from_millis(1500),
+        ))
+    }
+
+    #[test]
+    fn test_restored_runs_resolve_the_same_way() {
+        let mut history = TaskHistory::default();
+        let id = run_task(&mut history, "print file", 1);
+        let run = history.run(id).unwrap();
+        assert!(!run.succeeded());
+        assert_eq!(run.command_label, "echo /project/main.rs");
+        assert_eq!(run.env.get("PATH").map(String::as_str), Some("/bin"));
+        let resolved = run.resolve(HashMap::default()).unwrap();
+
+        let serialized = history.serialize().unwrap();
+        assert!(
+            !serialized.contains("/bin"),
+            "the project environment should not be persisted: {serialized}"
+        );
+        let mut restored_history = TaskHistory::default();
+        restored_history.restore(&serialized).unwrap();
+        let restored_run = &restored_history.runs()[0];
+        assert_eq!(restored_run.label, "print file");
+        assert_eq!(restored_run.exit_code, Some(1));
+        assert_eq!(restored_run.duration, Duration::from_millis(1500));
+        assert_eq!(restored_run.cwd, Some(PathBuf::from("/project")));
+        assert!(
+            restored_run.env.is_empty(),
+            "the environment should not be persisted"
+        );
+        let restored_resolved = restored_run.resolve(HashMap::default()).unwrap();
+        assert_eq!(restored_resolved.id, resolved.id);
+        assert_eq!(restored_resolved.resolved, resolved.resolved);
+    }
+
+    #[test]
+    fn test_restored_runs_come_before_the_current_ones() {
+        let mut previous_history = TaskHistory::default();
+        for ix in 0..MAX_TASK_RUNS {
+            run_task(&mut previous_history, &format!("previous {ix}"), 0);
+        }
+
+        let mut history = TaskHistory::default();
+        let current_id = run_task(&mut history, "current", 0);
+        history
+            .restore(&previous_history.serialize().unwrap())
+            .unwrap();
+        let labels = history
+            .runs()
+            .iter()
+            .map(|run| run.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels.len(), MAX_TASK_RUNS);
+        assert_eq!(labels[0], "previous 1");
+        assert_eq!(labels.last(), Some(&"current"));
+        assert_eq!(history.run(current_id).unwrap().label, "current");
+        assert_eq!(
+            history
+                .runs()
+                .iter()
+                .map(|run| run.id)
+                .collect::<collections::HashSet<_>>()
+                .len(),
+            MAX_TASK_RUNS,
+            "restored runs should get unique ids"
+        );
+    }
+}
@@ -3,7 +3,7 @@
 use std::{
     borrow::Cow,
     cmp::{self, Reverse},
-    collections::hash_map,
+    collections::{BTreeMap, hash_map},
     path::PathBuf,
     sync::Arc,
 };
@@ -28,7 +28,13 @@ use text::{BufferId, Point, ToPoint};
 use util::{NumericPrefixWithSuffix, ResultExt as _, post_inc, rel_path::RelPath};
 use worktree::WorktreeId;
 
-use crate::{task_store::TaskSettingsLocation, worktree_store::WorktreeStore};
+use crate::{
+    git_store::GitStore,
+    task_discovery::DiscoveredTask,
+    task_providers::{self, TaskProviderRegistry},
+    task_store::TaskSettingsLocation,
+    worktree_store::WorktreeStore,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct DebugScenarioContext {
@@ -43,6 +49,8 @@ pub struct Inventory {
     last_scheduled_scenarios: VecDeque<(DebugScenario, DebugScenarioContext)>,
     templates_from_settings: InventoryFor<TaskTemplate>,
     scenarios_from_settings: InventoryFor<DebugScenario>,
+    templates_from_build_files: HashMap<WorktreeId, BTreeMap<Arc<RelPath>, Vec<DiscoveredTask>>>,
+    task_providers: Entity<TaskProviderRegistry>,
 }
 
 impl std::fmt::Debug for Inventory {
@@ -52,6 +60,10 @@ impl std::fmt::Debug for Inventory {
             .field("last_scheduled_scenarios", &self.last_scheduled_scenarios)
             .field("templates_from_settings", &self.templates_from_settings)
             .field("scenarios_from_settings", &self.scenarios_from_settings)
+            .field(
+                "templates_from_build_files",
+                &self.templates_from_build_files,
+            )
             .finish()
     }
 }
@@ -141,6 +153,14 @@ pub enum TaskSourceKind {
         directory_in_worktree: Arc<RelPath>,
         id_base: Cow<'static, str>,
     },
+    /// Tasks discovered in the worktree's build files, such as `Makefile` targets or `package.json` scripts
+    BuildFile {
+        id: WorktreeId,
+        /// Path of the build file in the worktree.
+        path: Arc<RelPath>,
+        /// Zero-based row of the build file the task is defined at.
+        row: u32,
+    },
     /// `~/.config/vector/tasks.json` - global task definitions, applicable to any path
     AbsPath {
         id_base: Cow<'static, str>,
@@ -153,6 +173,8 @@ pub enum TaskSourceKind {
         language_name: SharedString,
         server: LanguageServerId,
     },
+    /// Tasks of a task provider of an extension, applicable to any path
+    Extension { provider: SharedString },
 }
 
 /// A collection of task contexts, derived from the current state of the workspace.
@@ -217,6 +239,18 @@ impl TaskContexts {
 }
 
 impl TaskSourceKind {
+    /// The worktree the tasks of this source are defined in.
+    pub fn worktree_id(&self) -> Option<WorktreeId> {
+        match self {
+            Self::Worktree { id, .. } | Self::BuildFile { id, .. } => Some(*id),
+            Self::UserInput
+            | Self::AbsPath { .. }
+            | Self::Language { .. }
+            | Self::Lsp { .. }
+            | Self::Extension { .. } => None,
+        }
+    }
+
     pub fn to_id_base(&self) -> String {
         match self {
             Self::UserInput => "oneshot".to_string(),
@@ -230,22 +264,30 @@ impl TaskSourceKind {
             } => {
                 format!("{id_base}_{id}_{}", directory_in_worktree.as_unix_str())
             }
+            // The row is left out, so that the task keeps its id when the lines above it change.
+            Self::BuildFile { id, path, .. } => {
+                format!("build file_{id}_{}", path.as_unix_str())
+            }
             Self::Language { name } => format!("language_{name}"),
             Self::Lsp {
                 server,
                 language_name,
             } => format!("lsp_{language_name}_{server}"),
+            Self::Extension { provider } => format!("extension_{provider}"),
         }
     }
 }
 
 impl Inventory {
     pub fn new(cx: &mut App) -> Entity<Self> {
+        let task_providers = TaskProviderRegistry::default_global(cx);
         cx.new(|_| Self {
             last_scheduled_tasks: VecDeque::default(),
             last_scheduled_scenarios: VecDeque::default(),
             templates_from_settings: InventoryFor::default(),
             scenarios_from_settings: InventoryFor::default(),
+            templates_from_build_files: HashMap::default(),
+            task_providers,
         })
     }
 
@@ -375,7 +417,7 @@ impl Inventory {
 
     /// Pulls its task sources relevant to the worktree and the language given,
     /// returns all task templates with their source kinds, worktree tasks first, language tasks second
-    /// and global tasks last. No specific order inside source kinds groups.
+    /// and global tasks last, followed by the tasks of extensions. No specific order inside source kinds groups.
     pub fn list_tasks(
         &self,
         file: Option<Arc<dyn File>>,
@@ -383,10 +425,14 @@ impl Inventory {
         worktree: Option<WorktreeId>,
         cx: &App,
     ) -> Task<Vec<(TaskSourceKind, TaskTemplate)>> {
-        let global_tasks = self.global_templates_from_settings().collect::<Vec<_>>();
+        let global_tasks = self.global_templates(cx);
+        let problem_matchers = self.task_providers.read(cx).problem_matchers();
         let mut worktree_tasks = worktree
             .into_iter()
-            .flat_map(|worktree| self.worktree_templates_from_settings(worktree))
+            .flat_map(|worktree| {
+                self.worktree_templates_from_settings(worktree)
+                    .chain(self.worktree_templates_from_build_files(worktree))
+            })
             .collect::<Vec<_>>();
 
         let task_source_kind = language.as_ref().map(|language| TaskSourceKind::Language {
@@ -413,6 +459,9 @@ impl Inventory {
                 }));
             }
             worktree_tasks.extend(global_tasks);
+            for (_, template) in &mut worktree_tasks {
+                task_providers::resolve_problem_matchers(template, &problem_matchers);
+            }
             worktree_tasks
         })
     }
@@ -475,7 +524,8 @@ impl Inventory {
             .collect::<Vec<_>>();
 
         let not_used_score = post_inc(&mut lru_score);
-        let global_tasks = self.global_templates_from_settings().collect::<Vec<_>>();
+        let global_tasks = self.global_templates(cx);
+        let problem_matchers = self.task_providers.read(cx).problem_matchers();
         let associated_tasks = language
             .filter(|language| {
                 language_settings(Some(language.name()), file.as_ref(), cx)
@@ -489,7 +539,10 @@ impl Inventory {
             });
         let worktree_tasks = worktree
             .into_iter()
-            .flat_map(|worktree| self.worktree_templates_from_settings(worktree))
+            .flat_map(|worktree| {
+                self.worktree_templates_from_settings(worktree)
+                    .chain(self.worktree_templates_from_build_files(worktree))
+            })
             .collect::<Vec<_>>();
         let task_contexts = task_contexts.clone();
         cx.background_spawn(async move {
@@ -510,34 +563,35 @@ impl Inventory {
                 .chain(global_tasks);
 
             let new_resolved_tasks = worktree_tasks
+                .map(|(kind, mut task)| {
+                    task_providers::resolve_problem_matchers(&mut task, &problem_matchers);
+                    (kind, task)
+                })
                 .flat_map(|(kind, task)| {
                     let id_base = kind.to_id_base();
-                    if let TaskSourceKind::Worktree { id, .. } = &kind {
+                    if let Some(id) = kind.worktree_id() {
                         None.or_else(|| {
                             let (_, _, item_context) =
-                                task_contexts.active_item_context.as_ref().filter(
-                                    |(worktree_id, _, _)| Some(id) == worktree_id.as_ref(),
-                                )?;
+                                task_contexts
+                                    .active_item_context
+                                    .as_ref()
+                                    .filter(|(worktree_id, _, _)| Some(id) == *worktree_id)?;
                             task.resolve_task(&id_base, item_context)
                         })
                         .or_else(|| {
                             let (_, worktree_context) = task_contexts
                                 .active_worktree_context
                                 .as_ref()
-                                .filter(|(worktree_id, _)| id == worktree_id)?;
+                                .filter(|(worktree_id, _)| id == *worktree_id)?;
                             task.resolve_task(&id_base, worktree_context)
                         })
                         .or_else(|| {
-                            if let TaskSourceKind::Worktree { id, .. } = &kind {
-                                let worktree_context = task_contexts
-                                    .other_worktree_contexts
-                                    .iter()
-                                    .find(|(worktree_id, _)| worktree_id == id)
-                                    .map(|(_, context)| context)?;
-                                task.resolve_task(&id_base, worktree_context)
-                            } else {
-                                None
-                            }
+                            let worktree_context = task_contexts
+                                .other_worktree_contexts
+                                .iter()
+                                .find(|(worktree_id, _)| *worktree_id == id)
+                                .map(|(_, context)| context)?;
+                            task.resolve_task(&id_base, worktree_context)
                         })
                     } else {
                         None.or_else(|| {
@@ -615,6 +669,13 @@ impl Inventory {
         self.templates_from_settings.global_scenarios()
     }
 
+    /// The global tasks from the settings, followed by the tasks of extensions.
+    fn global_templates(&self, cx: &App) -> Vec<(TaskSourceKind, TaskTemplate)> {
+        self.global_templates_from_settings()
+            .chain(self.task_providers.read(cx).task_templates())
+            .collect()
+    }
+
     fn global_debug_scenarios_from_settings(
         &self,
     ) -> impl '_ + Iterator<Item = (TaskSourceKind, DebugScenario)> {
@@ -635,6 +696,50 @@ impl Inventory {
         self.templates_from_settings.worktree_scenarios(worktree)
     }
 
+    fn worktree_templates_from_build_files(
+        &self,
+        worktree: WorktreeId,
+    ) -> impl '_ + Iterator<Item = (TaskSourceKind, TaskTemplate)> {
+        self.templates_from_build_files
+            .get(&worktree)
+            .into_iter()
+            .flatten()
+            .flat_map(move |(path, tasks)| {
+                tasks.iter().map(move |task| {
+                    (
+                        TaskSourceKind::BuildFile {
+                            id: worktree,
+                            path: path.clone(),
+                            row: task.row,
+                        },
+                        task.template.clone(),
+                    )
+                })
+            })
+    }
+
+    /// Replaces the tasks discovered in the build file at `path`, removing them if there are none.
+    pub(crate) fn update_build_file_tasks(
+        &mut self,
+        worktree_id: WorktreeId,
+        path: Arc<RelPath>,
+        tasks: Vec<DiscoveredTask>,
+    ) {
+        let worktree_tasks = self
+            .templates_from_build_files
+            .entry(worktree_id)
+            .or_default();
+        if tasks.is_empty() {
+            worktree_tasks.remove(&path);
+        } else {
+            worktree_tasks.insert(path, tasks);
+        }
+    }
+
+    pub(crate) fn remove_worktree_build_file_tasks(&mut self, worktree_id: WorktreeId) {
+        self.templates_from_build_files.remove(&worktree_id);
+    }
+
     /// Updates in-memory task metadata from the JSON string given.
     /// Will fail if the JSON is not a valid array of objects, but will continue if any object will not parse into a [`TaskTemplate`].
     ///
@@ -831,7 +936,9 @@ fn task_source_kind_preference(kind: &TaskSourceKind) -> u32 {
         TaskSourceKind::Language { .. } => 1,
         TaskSourceKind::UserInput => 2,
         TaskSourceKind::Worktree { .. } => 3,
-        TaskSourceKind::AbsPath { .. } => 4,
+        TaskSourceKind::BuildFile { .. } => 4,
+        TaskSourceKind::AbsPath { .. } => 5,
+        TaskSourceKind::Extension { .. } => 6,
     }
 }
 
@@ -962,13 +1069,28 @@ mod test_inventory {
 
 /// A context provided that tries to provide values for all non-custom [`VariableName`] variants for a currently opened file.
 /// Applied as a base for every custom [`ContextProvider`] unless explicitly oped out.
+///
+/// Besides the location of the file and the cursor in it, the variables are resolved from:
+/// - [`VariableName::GitBranch`]: the branch of the repository containing the file, if a git store is set with
+///   [`BasicContextProvider::with_git_store`];
+/// - [`VariableName::Toolchain`]: the active toolchain for the file's language, in the file's directory;
+/// - [`VariableName::Env`]: every variable of the project environment.
 pub struct BasicContextProvider {
     worktree_store: Entity<WorktreeStore>,
+    git_store: Option<Entity<GitStore>>,
 }
 
 impl BasicContextProvider {
     pub fn new(worktree_store: Entity<WorktreeStore>) -> Self {
-        Self { worktree_store }
+        Self {
+            worktree_store,
+            git_store: None,
+        }
+    }
+
+    pub fn with_git_store(mut self, git_store: Entity<GitStore>) -> Self {
+        self.git_store = Some(git_store);
+        self
     }
 }
 
@@ -977,8 +1099,8 @@ impl ContextProvider for BasicContextProvider {
         &self,
         _: &TaskVariables,
         location: ContextLocation<'_>,
-        _: Option<HashMap<String, String>>,
-        _: Arc<dyn LanguageToolchainStore>,
+        project_env: Option<HashMap<String, String>>,
+        toolchain_store: Arc<dyn LanguageToolchainStore>,
         cx: &mut App,
     ) -> Task<Result<TaskVariables>> {
         let location = location.file_location;
@@ -1065,7 +1187,43 @@ impl ContextProvider for BasicContextProvider {
             task_variables.insert(VariableName::File, path.to_string_lossy().into_owned());
         }
 
-        Task::ready(Ok(task_variables))
+        let branch = self.git_store.as_ref().and_then(|git_store| {
+            let (repository, _) = git_store
+                .read(cx)
+                .repository_and_path_for_buffer_id(buffer.remote_id(), cx)?;
+            let branch = repository.read(cx).branch.as_ref()?.name().to_string();
+            Some(branch)
+        });
+        if let Some(branch) = branch {
+            task_variables.insert(VariableName::GitBranch, branch);
+        }
+        for (name, value) in project_env.into_iter().flatten() {
+            task_variables.insert(VariableName::Env(Cow::Owned(name)), value);
+        }
+
+        let toolchain_location = buffer
+            .language()
+            .zip(buffer.file())
+            .map(|(language, file)| {
+                let directory = file
+                    .path()
+                    .parent()
+                    .map(Arc::from)
+                    .unwrap_or_else(|| RelPath::empty().into());
+                (file.worktree_id(cx), directory, language.name())
+            });
+        let Some((worktree_id, directory, language_name)) = toolchain_location else {
+            return Task::ready(Ok(task_variables));
+        };
+        cx.spawn(async move |cx| {
+            if let Some(toolchain) = toolchain_store
+                .active_toolchain(worktree_id, directory, language_name, cx)
+                .await
+            {
+                task_variables.insert(VariableName::Toolchain, toolchain.path.to_string());
+            }
+            Ok(task_variables)
+        })
     }
 }
 
@@ -1101,6 +1259,80 @@ mod tests {
     use super::test_inventory::*;
     use super::*;
 
+    #[gpui::test]
+    async fn test_extension_tasks(cx: &mut TestAppContext) {
+        init_test(cx);
+        let inventory = cx.update(|cx| Inventory::new(cx));
+        let task_providers = cx.update(TaskProviderRegistry::default_global);
+        let matcher = task::ProblemMatcher {
+            pattern: r"(?m)^(?P<file>[^:]+):(?P<line>\d+): (?P<message>.+)$".to_string(),
+            file_pattern: None,
+            severity: task::ProblemSeverity::Error,
+        };
+        let other_matcher = task::ProblemMatcher {
+            severity: task::ProblemSeverity::Warning,
+            ..matcher.clone()
+        };
+        task_providers.update(cx, |task_providers, cx| {
+            task_providers.set_problem_matchers(
+                ("c-tools".into(), "gcc".into()),
+                vec![matcher.clone()],
+                cx,
+            );
+            task_providers.set_problem_matchers(
+                ("other-tools".into(), "gcc".into()),
+                vec![other_matcher.clone()],
+                cx,
+            );
+            task_providers.set_task_templates(
+                ("c-tools".into(), "make".into()),
+                vec![TaskTemplate {
+                    label: "make all".to_string(),
+                    command: "make".to_string(),
+                    problem_matchers: vec![
+                        task::ProblemMatcherDefinition::Named("gcc".to_string()),
+                        task::ProblemMatcherDefinition::Named("other-tools/gcc".to_string()),
+                        task::ProblemMatcherDefinition::Named("clang".to_string()),
+                    ],
+                    ..TaskTemplate::default()
+                }],
+                cx,
+            );
+        });
+
+        let tasks = inventory
+            .update(cx, |inventory, cx| {
+                inventory.list_tasks(None, None, None, cx)
+            })
+            .await;
+        assert_eq!(tasks.len(), 1);
+        let (kind, template) = &tasks[0];
+        assert_eq!(
+            kind,
+            &TaskSourceKind::Extension {
+                provider: "make".into()
+            }
+        );
+        assert_eq!(
+            template.problem_matchers,
+            vec![
+                task::ProblemMatcherDefinition::Custom(matcher),
+                task::ProblemMatcherDefinition::Custom(other_matcher),
+                task::ProblemMatcherDefinition::Named("clang".to_string()),
+            ]
+        );
+
+        task_providers.update(cx, |task_providers, cx| {
+            task_providers.remove_task_templates(&("c-tools".into(), "make".into()), cx);
+        });
+        let tasks = inventory
+            .update(cx, |inventory, cx| {
+                inventory.list_tasks(None, None, None, cx)
+            })
+            .await;
+        assert!(tasks.is_empty());
+    }
+
     #[gpui::test]
     async fn test_task_list_sorting(cx: &mut TestAppContext) {
         init_test(cx);
@@ -0,0 +1,236 @@
+//! Running tasks headless, without a terminal, capturing what they write to stdout and stderr.
+
+use std::process::Stdio;
+
+use anyhow::Context as _;
+use collections::HashMap;
+use futures::{
+    AsyncBufReadExt as _, AsyncRead, SinkExt as _, StreamExt as _,
+    channel::mpsc::{self, Receiver, Sender},
+    io::BufReader,
+};
+use gpui::{BackgroundExecutor, Task};
+use task::{ShellBuilder, SpawnInTerminal};
+use util::command::new_smol_command;
+
+use crate::dev_container::DevContainer;
+
+/// How much of each stream of a headless task's output is kept. Past that, the oldest lines are dropped.
+const MAX_OUTPUT_LEN: usize = 4 * 1024 * 1024;
+/// Lines longer than this, like progress bars redrawn with carriage returns, are split.
+const MAX_LINE_LEN: usize = 64 * 1024;
+/// How many lines are read ahead of the task store handling them, before the task is made to wait.
+const LINES_BUFFER_LEN: usize = 1024;
+
+/// The stream a line of a headless task's output was written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskOutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// What a task that ran headless wrote, and how it exited.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` when the task did not exit on its own, e.g. it was killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+impl TaskOutput {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    fn push_line(&mut self, stream: TaskOutputStream, line: &str) {
+        let output = match stream {
+            TaskOutputStream::Stdout => &mut self.stdout,
+            TaskOutputStream::Stderr => &mut self.stderr,
+        };
+        output.push_str(line);
+        output.push('\n');
+        if output.len() > MAX_OUTPUT_LEN {
+            // Drop down to half the limit at once, so that lines aren't dropped one at a time.
+            let excess = output.len() - MAX_OUTPUT_LEN / 2;
+            let line_start = output.as_bytes()[excess..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map_or(output.len(), |newline| excess + newline + 1);
+            output.drain(..line_start);
+        }
+    }
+
+    /// Collects the rest of the output of a headless task, calling `on_line` for each line as it is written.
+    pub(crate) async fn collect(
+        mut task: HeadlessTask,
+        mut on_line: impl FnMut(TaskOutputStream, &str),
+    ) -> anyhow::Result<Self> {
+        let mut output = Self::default();
+        while let Some((stream, line)) = task.next_line().await {
+            on_line(stream, &line);
+            output.push_line(stream, &line);
+        }
+        output.exit_code = task.exit_code().await?;
+        Ok(output)
+    }
+}
+
+/// A task running headless. Its process is killed when this is dropped before the task exits.
+pub(crate) struct HeadlessTask {
+    child: smol::process::Child,
+    lines: Receiver<(TaskOutputStream, String)>,
+    _readers: [Task<()>; 2],
+}
+
+impl HeadlessTask {
+    /// Starts a task, in the given Dev Container with the given container environment, if any.
+    pub(crate) fn spawn(
+        spawn: &SpawnInTerminal,
+        dev_container: Option<(&DevContainer, &HashMap<String, String>)>,
+        executor: &BackgroundExecutor,
+    ) -> anyhow::Result<Self> {
+        let command = spawn
+            .command
+            .as_deref()
+            .with_context(|| format!("task `{}` has no command", spawn.label))?;
+        let mut command = match dev_container {
+            Some((dev_container, container_env)) => {
+                let (program, args) = dev_container.wrap_task(
+                    command,
+                    spawn,
+                    spawn.cwd.as_deref(),
+                    container_env,
+                    &[],
+                    false,
+                );
+                let mut command = new_smol_command(program);
+                command.args(args);
+                command
+            }
+            None => {
+                let mut command = ShellBuilder::new(&spawn.shell, cfg!(windows))
+                    .non_interactive()
+                    .build_command(Some(command.to_owned()), &spawn.args);
+                command.envs(&spawn.env);
+                if let Some(cwd) = &spawn.cwd {
+                    command.current_dir(cwd);
+                }
+                command
+            }
+        };
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("spawning task `{}`", spawn.label))?;
+
+        let (lines_tx, lines) = mpsc::channel(LINES_BUFFER_LEN);
+        let stdout = child.stdout.take().context("taking the task's stdout")?;
+        let stderr = child.stderr.take().context("taking the task's stderr")?;
+        let readers = [
+            executor.spawn(forward_lines(
+                stdout,
+                TaskOutputStream::Stdout,
+                lines_tx.clone(),
+            )),
+            executor.spawn(forward_lines(stderr, TaskOutputStream::Stderr, lines_tx)),
+        ];
+        Ok(Self {
+            child,
+            lines,
+            _readers: readers,
+        })
+    }
+
+    /// The next line the task wrote, or `None` once it closed both of its output streams.
+    pub(crate) async fn next_line(&mut self) -> Option<(TaskOutputStream, String)> {
+        self.lines.next().await
+    }
+
+    /// Waits for the task to exit, returning its exit code.
+    pub(crate) async fn exit_code(mut self) -> anyhow::Result<Option<i32>> {
+        let status = self.child.status().await.context("waiting for the task")?;
+        Ok(status.code())
+    }
+}
+
+/// Sends the lines of a stream of the task's output, decoding them lossily, as tasks may write anything.
+async fn forward_lines(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    stream: TaskOutputStream,
+    mut lines_tx: Sender<(TaskOutputStream, String)>,
+) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        let Ok(available) = reader.fill_buf().await else {
+            break;
+        };
+        if available.is_empty() {
+            if !line.is_empty() {
+                lines_tx.send((stream, decode_line(&line))).await.ok();
+            }
+            break;
+        }
+        let (consumed, line_ended) = match available.iter().position(|byte| *byte == b'\n') {
+            Some(newline) => (newline + 1, true),
+            None => (available.len().min(MAX_LINE_LEN - line.len()), false),
+        };
+        line.extend_from_slice(&available[..consumed]);
+        reader.consume_unpin(consumed);
+        if line_ended || line.len() >= MAX_LINE_LEN {
+            if lines_tx.send((stream, decode_line(&line))).await.is_err() {
+                break;
+            }
+            line.clear();
+        }
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[gpui::test]
+    async fn test_forward_lines(cx: &mut gpui::TestAppContext) {
+        let (lines_tx, lines) = mpsc::channel(LINES_BUFFER_LEN);
+        let output = b"compiling \xff\r\nwarning\nno newline".to_vec();
+        cx.executor()
+            .spawn(forward_lines(
+                futures::io::Cursor::new(output),
+                TaskOutputStream::Stderr,
+                lines_tx,
+            ))
+            .await;
+        assert_eq!(
+            lines.collect::<Vec<_>>().await,
+            [
+                (TaskOutputStream::Stderr, "compiling \u{fffd}".to_string()),
+                (TaskOutputStream::Stderr, "warning".to_string()),
+                (TaskOutputStream::Stderr, "no newline".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_is_capped() {
+        let mut output = TaskOutput::default();
+        let line = "x".repeat(1023);
+        for _ in 0..(MAX_OUTPUT_LEN / 1024) * 2 {
+            output.push_line(TaskOutputStream::Stdout, &line);
+        }
+        assert!(output.stdout.len() <= MAX_OUTPUT_LEN);
+        assert!(output.stdout.starts_with(&line));
+        assert!(output.stdout.ends_with(&format!("{line}\n")));
+    }
+}
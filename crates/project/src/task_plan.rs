@@ -0,0 +1,184 @@
+//! Resolution of task dependencies into the order tasks are run in.
+//!
+//! A task lists the labels of the tasks to run before it with `depends_on`, which may depend on other tasks in turn.
+//! The plan contains every task of that graph once, even when several tasks depend on it, and every task comes after
+//! all of its dependencies.
+
+use anyhow::{Context as _, Result, bail};
+use collections::HashMap;
+use task::{ResolvedTask, TaskTemplate};
+
+use crate::TaskSourceKind;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+    pub task_source_kind: TaskSourceKind,
+    pub task: ResolvedTask,
+    /// Indices of the steps this one depends on, in the order they were declared.
+    pub dependencies: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionPlan {
+    /// The steps of the plan, each after its dependencies; the task the plan was created for comes last.
+    pub steps: Vec<PlanStep>,
+}
+
+impl ExecutionPlan {
+    /// Resolves the dependencies of `task` among `templates`, with the context `task` was resolved with.
+    pub fn new(
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        templates: &[(TaskSourceKind, TaskTemplate)],
+    ) -> Result<Self> {
+        let mut builder = PlanBuilder {
+            templates,
+            steps: Vec::new(),
+            step_ix_by_label: HashMap::default(),
+            visiting: Vec::new(),
+        };
+        builder.add_step(task_source_kind, task)?;
+        Ok(Self {
+            steps: builder.steps,
+        })
+    }
+
+    pub fn root(&self) -> Option<&PlanStep> {
+        self.steps.last()
+    }
+}
+
+struct PlanBuilder<'a> {
+    templates: &'a [(TaskSourceKind, TaskTemplate)],
+    steps: Vec<PlanStep>,
+    step_ix_by_label: HashMap<String, usize>,
+    /// Labels of the tasks whose dependencies are being resolved, to detect cycles.
+    visiting: Vec<String>,
+}
+
+impl PlanBuilder<'_> {
+    fn add_step(&mut self, task_source_kind: TaskSourceKind, task: ResolvedTask) -> Result<usize> {
+        let label = task.original_task().label.clone();
+        self.visiting.push(label.clone());
+        let mut dependencies = Vec::new();
+        for dependency in &task.original_task().depends_on {
+            let step_ix = match self.step_ix_by_label.get(dependency) {
+                Some(step_ix) => *step_ix,
+                None => {
+                    if self.visiting.contains(dependency) {
+                        bail!(
+                            "task dependency cycle: {} -> {dependency}",
+                            self.visiting.join(" -> ")
+                        );
+                    }
+                    let (source_kind, template) = self
+                        .templates
+                        .iter()
+                        .find(|(_, template)| &template.label == dependency)
+                        .with_context(|| {
+                            format!("task `{label}` depends on unknown task `{dependency}`")
+                        })?;
+                    let resolved = template
+                        .resolve_task(&source_kind.to_id_base(), task.task_context())
+                        .with_context(|| {
+                            format!(
+                                "failed to resolve task `{dependency}`, a dependency of `{label}`"
+                            )
+                        })?;
+                    self.add_step(source_kind.clone(), resolved)?
+                }
+            };
+            if !dependencies.contains(&step_ix) {
+                dependencies.push(step_ix);
+            }
+        }
+        self.visiting.pop();
+
+        let step_ix = self.steps.len();
+        self.step_ix_by_label.insert(label, step_ix);
+        self.steps.push(PlanStep {
+            task_source_kind,
+            task,
+            dependencies,
+        });
+        Ok(step_ix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use task::TaskContext;
+
+    use super::*;
+
+    fn template(label: &str, depends_on: &[&str]) -> (TaskSourceKind, TaskTemplate) {
+        (
+            TaskSourceKind::UserInput,
+            TaskTemplate {
+                label: label.to_string(),
+                command: format!("echo {label}"),
+                depends_on: depends_on.iter().map(|label| label.to_string()).collect(),
+                ..TaskTemplate::default()
+            },
+        )
+    }
+
+    fn plan(label: &str, templates: &[(TaskSourceKind, TaskTemplate)]) -> Result<ExecutionPlan> {
+        let (kind, root) = templates
+            .iter()
+            .find(|(_, template)| template.label == label)
+            .unwrap();
+        let root = root
+            .resolve_task(&kind.to_id_base(), &TaskContext::default())
+            .unwrap();
+        ExecutionPlan::new(kind.clone(), root, templates)
+    }
+
+    #[test]
+    fn test_execution_plan() {
+        let templates = [
+            template("build", &[]),
+            template("lint", &["build"]),
+            template("test", &["build"]),
+            template("run", &["lint", "test"]),
+        ];
+
+        let plan = plan("run", &templates).unwrap();
+        let steps = plan
+            .steps
+            .iter()
+            .map(|step| {
+                (
+                    step.task.original_task().label.as_str(),
+                    step.dependencies.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                ("build", vec![]),
+                ("lint", vec![0]),
+                ("test", vec![0]),
+                ("run", vec![1, 2]),
+            ]
+        );
+        assert_eq!(plan.root().unwrap().task.original_task().label, "run");
+    }
+
+    #[test]
+    fn test_execution_plan_errors() {
+        let templates = [
+            template("a", &["b"]),
+            template("b", &["c"]),
+            template("c", &["a"]),
+            template("d", &["missing"]),
+        ];
+
+        let error = plan("a", &templates).unwrap_err().to_string();
+        assert_eq!(error, "task dependency cycle: a -> b -> c -> a");
+
+        let error = plan("d", &templates).unwrap_err().to_string();
+        assert_eq!(error, "task `d` depends on unknown task `missing`");
+    }
+}
@@ -0,0 +1,266 @@
+//! Task templates and problem matchers provided by extensions.
+//!
+//! Extensions are asked for them once, when they are loaded, so that reloading an extension refreshes them. Both are
+//! keyed by the id of their extension and their name, so that extensions can use the same names.
+
+use std::{collections::BTreeMap, mem, sync::Arc};
+
+use collections::HashMap;
+use extension::{Extension, ExtensionHostProxy, ExtensionTaskProviderProxy};
+use gpui::{App, AppContext as _, Context, Entity, Global, SharedString, Task};
+use task::{ProblemMatcher, ProblemMatcherDefinition, TaskTemplate};
+
+use crate::TaskSourceKind;
+
+pub fn init(cx: &mut App) {
+    let proxy = ExtensionHostProxy::default_global(cx);
+    proxy.register_task_provider_proxy(TaskProviderRegistryProxy {
+        registry: TaskProviderRegistry::default_global(cx),
+    });
+}
+
+struct GlobalTaskProviderRegistry(Entity<TaskProviderRegistry>);
+
+impl Global for GlobalTaskProviderRegistry {}
+
+/// The id of an extension and the name of one of its task providers or problem matchers.
+pub type ExtensionItemKey = (Arc<str>, Arc<str>);
+
+#[derive(Default)]
+pub struct TaskProviderRegistry {
+    task_templates: BTreeMap<ExtensionItemKey, Vec<TaskTemplate>>,
+    problem_matchers: BTreeMap<ExtensionItemKey, Vec<ProblemMatcher>>,
+    loading_task_templates: HashMap<ExtensionItemKey, Task<()>>,
+    loading_problem_matchers: HashMap<ExtensionItemKey, Task<()>>,
+}
+
+impl TaskProviderRegistry {
+    /// Returns the global [`TaskProviderRegistry`].
+    ///
+    /// Inserts a default [`TaskProviderRegistry`] if one does not yet exist.
+    pub fn default_global(cx: &mut App) -> Entity<Self> {
+        if !cx.has_global::<GlobalTaskProviderRegistry>() {
+            let registry = cx.new(|_| Self::default());
+            cx.set_global(GlobalTaskProviderRegistry(registry));
+        }
+        cx.global::<GlobalTaskProviderRegistry>().0.clone()
+    }
+
+    /// The task templates of all the providers, with their source kinds.
+    pub fn task_templates(&self) -> impl '_ + Iterator<Item = (TaskSourceKind, TaskTemplate)> {
+        self.task_templates
+            .iter()
+            .flat_map(|((_, provider_name), templates)| {
+                templates.iter().map(|template| {
+                    (
+                        TaskSourceKind::Extension {
+                            provider: SharedString::from(provider_name.to_string()),
+                        },
+                        template.clone(),
+                    )
+                })
+            })
+    }
+
+    pub fn problem_matchers(&self) -> BTreeMap<ExtensionItemKey, Vec<ProblemMatcher>> {
+        self.problem_matchers.clone()
+    }
+
+    /// Replaces the task templates of the provider with the ones `extension` provides.
+    pub fn load_task_templates(
+        &mut self,
+        extension: Arc<dyn Extension>,
+        provider_name: Arc<str>,
+        cx: &mut Context<Self>,
+    ) {
+        let key = (extension.manifest().id.clone(), provider_name.clone());
+        let load = cx.spawn({
+            let key = key.clone();
+            async move |this, cx| {
+                let templates = extension
+                    .task_templates(provider_name.clone())
+                    .await
+                    .unwrap_or_else(|error| {
+                        log::error!(
+                            "failed to load the tasks of provider {provider_name} of extension {}: {error:#}",
+                            key.0
+                        );
+                        Vec::new()
+                    });
+                this.update(cx, |this, cx| {
+                    this.loading_task_templates.remove(&key);
+                    this.set_task_templates(key, templates, cx);
+                })
+                .ok();
+            }
+        });
+        self.loading_task_templates.insert(key, load);
+    }
+
+    pub fn set_task_templates(
+        &mut self,
+        key: ExtensionItemKey,
+        templates: Vec<TaskTemplate>,
+        cx: &mut Context<Self>,
+    ) {
+        self.task_templates.insert(key, templates);
+        cx.notify();
+    }
+
+    pub fn remove_task_templates(&mut self, key: &ExtensionItemKey, cx: &mut Context<Self>) {
+        self.loading_task_templates.remove(key);
+        self.task_templates.remove(key);
+        cx.notify();
+    }
+
+    /// Replaces the matchers of the problem matcher with the ones `extension` provides.
+    pub fn load_problem_matchers(
+        &mut self,
+        extension: Arc<dyn Extension>,
+        matcher_name: Arc<str>,
+        cx: &mut Context<Self>,
+    ) {
+        let key = (extension.manifest().id.clone(), matcher_name.clone());
+        let load = cx.spawn({
+            let key = key.clone();
+            async move |this, cx| {
+                let matchers = extension
+                    .problem_matchers(matcher_name.clone())
+                    .await
+                    .unwrap_or_else(|error| {
+                        log::error!(
+                            "failed to load the problem matcher {matcher_name} of extension {}: {error:#}",
+                            key.0
+                        );
+                        Vec::new()
+                    });
+                this.update(cx, |this, cx| {
+                    this.loading_problem_matchers.remove(&key);
+                    this.set_problem_matchers(key, matchers, cx);
+                })
+                .ok();
+            }
+        });
+        self.loading_problem_matchers.insert(key, load);
+    }
+
+    pub fn set_problem_matchers(
+        &mut self,
+        key: ExtensionItemKey,
+        matchers: Vec<ProblemMatcher>,
+        cx: &mut Context<Self>,
+    ) {
+        self.problem_matchers.insert(key, matchers);
+        cx.notify();
+    }
+
+    pub fn remove_problem_matchers(&mut self, key: &ExtensionItemKey, cx: &mut Context<Self>) {
+        self.loading_problem_matchers.remove(key);
+        self.problem_matchers.remove(key);
+        cx.notify();
+    }
+}
+
+/// Finds the matchers `name` refers to: either `<extension id>/<matcher name>`, or only the name of the matcher, in
+/// which case the first extension providing a matcher with that name is used.
+fn named_problem_matchers<'a>(
+    name: &str,
+    problem_matchers: &'a BTreeMap<ExtensionItemKey, Vec<ProblemMatcher>>,
+) -> Option<&'a Vec<ProblemMatcher>> {
+    if let Some((extension_id, matcher_name)) = name.split_once('/')
+        && let Some(matchers) =
+            problem_matchers.get(&(Arc::from(extension_id), Arc::from(matcher_name)))
+    {
+        return Some(matchers);
+    }
+    problem_matchers
+        .iter()
+        .find(|((_, matcher_name), _)| matcher_name.as_ref() == name)
+        .map(|(_, matchers)| matchers)
+}
+
+/// Replaces the names of the problem matchers that extensions provide with their matchers.
+pub(crate) fn resolve_problem_matchers(
+    template: &mut TaskTemplate,
+    problem_matchers: &BTreeMap<ExtensionItemKey, Vec<ProblemMatcher>>,
+) {
+    if !template
+        .problem_matchers
+        .iter()
+        .any(|definition| matches!(definition, ProblemMatcherDefinition::Named(_)))
+    {
+        return;
+    }
+    template.problem_matchers = mem::take(&mut template.problem_matchers)
+        .into_iter()
+        .flat_map(|definition| match definition {
+            ProblemMatcherDefinition::Named(name) => {
+                match named_problem_matchers(&name, problem_matchers) {
+                    Some(matchers) => matchers
+                        .iter()
+                        .cloned()
+                        .map(ProblemMatcherDefinition::Custom)
+                        .collect(),
+                    None => {
+                        log::warn!(
+                            "task `{}` uses the problem matcher `{name}`, which no extension provides",
+                            template.label
+                        );
+                        vec![ProblemMatcherDefinition::Named(name)]
+                    }
+                }
+            }
+            definition => vec![definition],
+        })
+        .collect();
+}
+
+struct TaskProviderRegistryProxy {
+    registry: Entity<TaskProviderRegistry>,
+}
+
+impl ExtensionTaskProviderProxy for TaskProviderRegistryProxy {
+    fn register_task_provider(
+        &self,
+        extension: Arc<dyn Extension>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        self.registry.update(cx, |registry, cx| {
+            registry.load_task_templates(extension, provider_name, cx)
+        });
+    }
+
+    fn unregister_task_provider(
+        &self,
+        extension_id: Arc<str>,
+        provider_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        self.registry.update(cx, |registry, cx| {
+            registry.remove_task_templates(&(extension_id, provider_name), cx)
+        });
+    }
+
+    fn register_problem_matcher(
+        &self,
+        extension: Arc<dyn Extension>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        self.registry.update(cx, |registry, cx| {
+            registry.load_problem_matchers(extension, matcher_name, cx)
+        });
+    }
+
+    fn unregister_problem_matcher(
+        &self,
+        extension_id: Arc<str>,
+        matcher_name: Arc<str>,
+        cx: &mut App,
+    ) {
+        self.registry.update(cx, |registry, cx| {
+            registry.remove_problem_matchers(&(extension_id, matcher_name), cx)
+        });
+    }
+}
@@ -1,19 +1,36 @@
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
-use anyhow::Context as _;
-use collections::HashMap;
+use anyhow::{Context as _, anyhow};
+use collections::{HashMap, HashSet};
+use db::kvp::KEY_VALUE_STORE;
 use fs::Fs;
-use gpui::{App, Context, Entity, EventEmitter, Task};
-use language::{ContextLocation, ContextProvider as _, LanguageToolchainStore, Location};
-use settings::{InvalidSettingsError, SettingsLocation};
-use task::{TaskContext, TaskVariables};
-use util::ResultExt;
+use futures::{
+    StreamExt as _,
+    channel::{mpsc, oneshot},
+};
+use gpui::{App, Context, Entity, EventEmitter, Subscription, Task};
+use language::{Buffer, ContextLocation, ContextProvider as _, LanguageToolchainStore, Location};
+use regex::Regex;
+use settings::{InvalidSettingsError, Settings as _, SettingsLocation};
+use task::{ExecutionTarget, ResolvedTask, SpawnInTerminal, TaskContext, TaskId, TaskVariables};
+use terminal::{Terminal, terminal_settings::TerminalSettings};
+use util::{ResultExt, paths::PathMatcher, rel_path::RelPath};
 use worktree::File;
 
-use crate::{BasicContextProvider, Inventory, ProjectEnvironment, worktree_store::WorktreeStore};
+use crate::{
+    BasicContextProvider, ExecutionPlan, Inventory, ProjectEnvironment, TaskSourceKind,
+    git_store::GitStore,
+    task_discovery::BuildFileTasks,
+    task_environment::TaskEnvironment,
+    task_history::{TaskHistory, TaskRun, TaskRunId},
+    task_output::{HeadlessTask, TaskOutput, TaskOutputStream},
+    trusted_worktrees::TrustedWorktrees,
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+};
 
 // platform-dependent warning
 pub enum TaskStore {
@@ -25,10 +42,123 @@ pub struct StoreState {
     environment: Entity<ProjectEnvironment>,
     task_inventory: Entity<Inventory>,
     worktree_store: Entity<WorktreeStore>,
+    git_store: Entity<GitStore>,
     toolchain_store: Arc<dyn LanguageToolchainStore>,
+    background_tasks: HashMap<TaskId, BackgroundTask>,
+    build_file_tasks: Option<BuildFileTasks>,
+    save_triggers: SaveTriggers,
+    task_history: TaskHistory,
+    _worktree_store_subscription: Subscription,
+}
+
+/// How many times in a row a background task is restarted after failing before it is given up on.
+const MAX_BACKGROUND_TASK_RESTARTS: usize = 5;
+const BACKGROUND_TASK_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_BACKGROUND_TASK_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackgroundTaskStatus {
+    /// The task was started, and its ready pattern was not matched yet.
+    Starting,
+    Ready,
+    /// The task failed, and is about to be started again.
+    Restarting {
+        attempt: usize,
+    },
+    /// The task exited, and is not going to be restarted.
+    Stopped,
+}
+
+struct BackgroundTask {
+    status: BackgroundTaskStatus,
+    /// Failed restarts since the task was last ready.
+    restarts: usize,
+    ready_waiters: Vec<oneshot::Sender<anyhow::Result<()>>>,
+    /// Matches the task's output against its ready pattern, until it is ready.
+    _output_subscription: Option<Subscription>,
+}
+
+/// How long to wait for more saves before running the tasks they trigger.
+const SAVE_TRIGGER_DEBOUNCE: Duration = Duration::from_millis(300);
+/// How many tasks triggered by saves may run at once; the other ones wait for them to finish.
+const MAX_CONCURRENT_TRIGGERED_TASKS: usize = 2;
+
+/// The tasks triggered by saving files matching their `run_on_save` globs.
+struct SaveTriggers {
+    /// Tasks to run once the debounce ends and a slot is free, at most one per label.
+    pending: Vec<(TaskSourceKind, ResolvedTask)>,
+    /// Labels of the triggered tasks that are running.
+    running: HashSet<String>,
+    debounce: Option<Task<()>>,
+    /// Receives the labels of the [`TriggeredTaskSlot`]s that got dropped.
+    finished_tx: mpsc::UnboundedSender<String>,
+    _finished_listener: Task<()>,
+}
+
+impl SaveTriggers {
+    fn new(cx: &mut Context<TaskStore>) -> Self {
+        let (finished_tx, mut finished_rx) = mpsc::unbounded::<String>();
+        let finished_listener = cx.spawn(async move |task_store, cx| {
+            while let Some(label) = finished_rx.next().await {
+                task_store
+                    .update(cx, |task_store, cx| {
+                        task_store.triggered_task_finished(&label, cx)
+                    })
+                    .ok();
+            }
+        });
+        Self {
+            pending: Vec::new(),
+            running: HashSet::default(),
+            debounce: None,
+            finished_tx,
+            _finished_listener: finished_listener,
+        }
+    }
+}
+
+/// The slot taken up by a task emitted with [`TaskStoreEvent::TaskTriggered`].
+///
+/// The slot is freed, letting the next pending triggered tasks run, once all of its clones are
+/// dropped, so whoever runs the task should hold on to it until the task finishes.
+#[derive(Clone)]
+pub struct TriggeredTaskSlot(Arc<TriggeredTaskSlotState>);
+
+struct TriggeredTaskSlotState {
+    label: String,
+    finished_tx: mpsc::UnboundedSender<String>,
+}
+
+impl Drop for TriggeredTaskSlotState {
+    fn drop(&mut self) {
+        self.finished_tx
+            .unbounded_send(std::mem::take(&mut self.label))
+            .ok();
+    }
+}
+
+pub enum TaskStoreEvent {
+    /// A task should be run, as a file matching its `run_on_save` globs was saved.
+    TaskTriggered {
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        slot: TriggeredTaskSlot,
+    },
+    /// A task from the history should be run again.
+    RerunRequested {
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+    },
+    /// A task run with [`TaskStore::run_headless`] wrote a line to its stdout or stderr.
+    HeadlessTaskOutput {
+        task_id: TaskId,
+        stream: TaskOutputStream,
+        line: String,
+    },
 }
 
 impl EventEmitter<crate::Event> for TaskStore {}
+impl EventEmitter<TaskStoreEvent> for TaskStore {}
 
 #[derive(Debug)]
 pub enum TaskSettingsLocation<'a> {
@@ -41,15 +171,39 @@ impl TaskStore {
 
     pub fn local(
         worktree_store: Entity<WorktreeStore>,
+        git_store: Entity<GitStore>,
         toolchain_store: Arc<dyn LanguageToolchainStore>,
         environment: Entity<ProjectEnvironment>,
         cx: &mut Context<Self>,
     ) -> Self {
+        let task_inventory = Inventory::new(cx);
+        let build_file_tasks = worktree_store
+            .read(cx)
+            .fs()
+            .map(|fs| BuildFileTasks::new(fs, worktree_store.clone(), task_inventory.clone()));
+        let worktree_store_subscription =
+            cx.subscribe(&worktree_store, |task_store, _, event, cx| {
+                if let Some(build_file_tasks) = task_store.build_file_tasks_mut() {
+                    build_file_tasks.on_worktree_store_event(event, cx);
+                }
+                if matches!(
+                    event,
+                    WorktreeStoreEvent::WorktreeAdded(_) | WorktreeStoreEvent::WorktreeRemoved(..)
+                ) {
+                    task_store.update_task_history_key(cx);
+                }
+            });
         Self::Functional(StoreState {
             environment,
-            task_inventory: Inventory::new(cx),
+            task_inventory,
             toolchain_store,
             worktree_store,
+            git_store,
+            background_tasks: HashMap::default(),
+            build_file_tasks,
+            save_triggers: SaveTriggers::new(cx),
+            task_history: TaskHistory::default(),
+            _worktree_store_subscription: worktree_store_subscription,
         })
     }
 
@@ -62,6 +216,7 @@ impl TaskStore {
         match self {
             TaskStore::Functional(state) => local_task_context_for_location(
                 state.worktree_store.clone(),
+                state.git_store.clone(),
                 state.toolchain_store.clone(),
                 state.environment.clone(),
                 captured_variables,
@@ -72,6 +227,13 @@ impl TaskStore {
         }
     }
 
+    pub(crate) fn build_file_tasks_mut(&mut self) -> Option<&mut BuildFileTasks> {
+        match self {
+            TaskStore::Functional(state) => state.build_file_tasks.as_mut(),
+            TaskStore::Noop => None,
+        }
+    }
+
     pub fn task_inventory(&self) -> Option<&Entity<Inventory>> {
         match self {
             TaskStore::Functional(state) => Some(&state.task_inventory),
@@ -79,6 +241,567 @@ impl TaskStore {
         }
     }
 
+    /// The finished runs of tasks in this project, oldest first, including the ones from previous sessions.
+    pub fn history(&self) -> &[TaskRun] {
+        match self {
+            TaskStore::Functional(state) => state.task_history.runs(),
+            TaskStore::Noop => &[],
+        }
+    }
+
+    /// Records a finished run of a task in the [`history`](Self::history), persisting it.
+    pub fn task_run_finished(
+        &mut self,
+        task_source_kind: &TaskSourceKind,
+        task: &ResolvedTask,
+        exit_code: Option<i32>,
+        duration: Duration,
+        cx: &mut Context<Self>,
+    ) -> Option<TaskRunId> {
+        let TaskStore::Functional(state) = self else {
+            return None;
+        };
+        let id = state.task_history.push(TaskRun::new(
+            task_source_kind.to_id_base(),
+            task,
+            exit_code,
+            duration,
+        ));
+        if let Some(key) = state.task_history.key().map(ToOwned::to_owned)
+            && let Some(value) = state.task_history.serialize().log_err()
+        {
+            db::write_and_log(cx, move || async move {
+                KEY_VALUE_STORE.write_kvp(key, value).await
+            });
+        }
+        Some(id)
+    }
+
+    /// Runs a task from the [`history`](Self::history) again, resolving it with the variables it ran with and the
+    /// current environment of the directory it ran in.
+    pub fn rerun(&mut self, id: TaskRunId, cx: &mut Context<Self>) -> Task<anyhow::Result<()>> {
+        let TaskStore::Functional(state) = self else {
+            return Task::ready(Err(anyhow!("tasks are not supported in this project")));
+        };
+        let Some(run) = state.task_history.run(id).cloned() else {
+            return Task::ready(Err(anyhow!("no task run with id {}", id.0)));
+        };
+        let project_env = run.context_cwd().map(|cwd| {
+            let cwd = Arc::from(cwd.as_path());
+            state.environment.update(cx, |environment, cx| {
+                environment.directory_environment(cwd, cx)
+            })
+        });
+        cx.spawn(async move |task_store, cx| {
+            let project_env = match project_env {
+                Some(project_env) => project_env.await.unwrap_or_default(),
+                None => HashMap::default(),
+            };
+            let task = run
+                .resolve(project_env)
+                .with_context(|| format!("resolving task `{}`", run.label))?;
+            task_store.update(cx, |_, cx| {
+                cx.emit(TaskStoreEvent::RerunRequested {
+                    task_source_kind: TaskSourceKind::UserInput,
+                    task,
+                })
+            })
+        })
+    }
+
+    /// Runs a task without a terminal, emitting [`TaskStoreEvent::HeadlessTaskOutput`] for each line it writes, and
+    /// returns everything it wrote once it exits. The run is added to the [`history`](Self::history).
+    /// Like in a terminal, the task runs in the Dev Container of the project, unless its execution target says otherwise.
+    ///
+    /// Dropping the returned task kills the task's process.
+    pub fn run_headless(
+        &mut self,
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        cx: &mut Context<Self>,
+    ) -> Task<anyhow::Result<TaskOutput>> {
+        let TaskStore::Functional(state) = self else {
+            return Task::ready(Err(anyhow!("tasks are not supported in this project")));
+        };
+        let execution_target = task.resolved.execution_target;
+        let cwd = task.resolved.cwd.clone().map(Arc::<Path>::from);
+        let settings_location = cwd.as_ref().and_then(|cwd| {
+            let (worktree, _) = state.worktree_store.read(cx).find_worktree(cwd, cx)?;
+            Some(SettingsLocation {
+                worktree_id: worktree.read(cx).id(),
+                path: RelPath::empty(),
+            })
+        });
+        let terminal_env = TerminalSettings::get(settings_location, cx).env.clone();
+        let dev_container_task = cwd.map(|cwd| {
+            state.environment.update(cx, |environment, cx| {
+                environment.task_dev_container(cwd, execution_target, cx)
+            })
+        });
+        let environment = state.environment.clone();
+        cx.spawn(async move |task_store, cx| {
+            let dev_container = match dev_container_task {
+                Some(dev_container_task) => dev_container_task.await,
+                None => None,
+            };
+            if execution_target == Some(ExecutionTarget::DevContainer) && dev_container.is_none() {
+                return Err(anyhow!(
+                    "no Dev Container to run task `{}` in",
+                    task.resolved.label
+                ));
+            }
+            let container_env = match &dev_container {
+                Some(dev_container) => {
+                    let mut container_env = environment
+                        .update(cx, |environment, cx| {
+                            environment.dev_container_environment(dev_container.clone(), cx)
+                        })?
+                        .await
+                        .unwrap_or_default();
+                    container_env.extend(terminal_env);
+                    container_env
+                }
+                None => HashMap::default(),
+            };
+
+            let started_at = Instant::now();
+            let headless_task = HeadlessTask::spawn(
+                &task.resolved,
+                dev_container
+                    .as_deref()
+                    .map(|dev_container| (dev_container, &container_env)),
+                cx.background_executor(),
+            )?;
+            let output = TaskOutput::collect(headless_task, |stream, line| {
+                task_store
+                    .update(cx, |_, cx| {
+                        cx.emit(TaskStoreEvent::HeadlessTaskOutput {
+                            task_id: task.id.clone(),
+                            stream,
+                            line: line.to_owned(),
+                        })
+                    })
+                    .ok();
+            })
+            .await?;
+            task_store.update(cx, |task_store, cx| {
+                task_store.task_run_finished(
+                    &task_source_kind,
+                    &task,
+                    output.exit_code,
+                    started_at.elapsed(),
+                    cx,
+                );
+            })?;
+            Ok(output)
+        })
+    }
+
+    fn update_task_history_key(&mut self, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        let Some(key) = state
+            .task_history
+            .update_key(state.worktree_store.read(cx), cx)
+        else {
+            return;
+        };
+        cx.spawn(async move |task_store, cx| {
+            let serialized = cx
+                .background_spawn(async move { KEY_VALUE_STORE.read_kvp(&key) })
+                .await?;
+            let Some(serialized) = serialized else {
+                return Ok(());
+            };
+            task_store.update(cx, |task_store, cx| {
+                let TaskStore::Functional(state) = task_store else {
+                    return Ok(());
+                };
+                state.task_history.restore(&serialized)?;
+                // Keep rerunning the last task working after a restart, unless a task ran already. The terminal adds
+                // the project environment when the task runs.
+                let last_task = state
+                    .task_history
+                    .runs()
+                    .last()
+                    .and_then(|run| run.resolve(HashMap::default()));
+                if let Some(last_task) = last_task {
+                    state.task_inventory.update(cx, |inventory, _| {
+                        if inventory.last_scheduled_task(None).is_none() {
+                            inventory.task_scheduled(TaskSourceKind::UserInput, last_task);
+                        }
+                    });
+                }
+                anyhow::Ok(())
+            })?
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Composes the environment of the tasks run for the location, before the layers of a particular task apply; see
+    /// [`TaskEnvironment::with_task`].
+    pub fn task_environment(
+        &self,
+        location: &Location,
+        cx: &mut App,
+    ) -> Task<Option<TaskEnvironment>> {
+        match self {
+            TaskStore::Functional(state) => local_task_environment(
+                &state.worktree_store,
+                state.toolchain_store.clone(),
+                &state.environment,
+                &location.buffer,
+                cx,
+            ),
+            TaskStore::Noop => Task::ready(None),
+        }
+    }
+
+    /// Resolves the tasks `task` depends on, directly or not, into the order they should run in.
+    ///
+    /// Dependencies are looked up by label among the tasks of the task's worktree, the tasks for the file and language
+    /// of `buffer`, and the global ones.
+    pub fn execution_plan(
+        &self,
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        buffer: Option<Entity<Buffer>>,
+        cx: &App,
+    ) -> Task<anyhow::Result<ExecutionPlan>> {
+        let TaskStore::Functional(state) = self else {
+            return Task::ready(Err(anyhow!("tasks are not supported in this project")));
+        };
+        let (file, language) = buffer
+            .map(|buffer| {
+                let buffer = buffer.read(cx);
+                (buffer.file().cloned(), buffer.language().cloned())
+            })
+            .unwrap_or((None, None));
+        let worktree_id = task_source_kind
+            .worktree_id()
+            .or_else(|| file.as_ref().map(|file| file.worktree_id(cx)));
+        let templates = state
+            .task_inventory
+            .read(cx)
+            .list_tasks(file, language, worktree_id, cx);
+        cx.background_spawn(async move {
+            let templates = templates.await;
+            ExecutionPlan::new(task_source_kind, task, &templates)
+        })
+    }
+
+    pub fn background_task_status(&self, id: &TaskId) -> Option<BackgroundTaskStatus> {
+        match self {
+            TaskStore::Functional(state) => state.background_tasks.get(id).map(|task| task.status),
+            TaskStore::Noop => None,
+        }
+    }
+
+    /// Records that the background task was (re)started, and is not ready yet.
+    pub fn background_task_started(&mut self, task: &SpawnInTerminal, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        let background_task = state
+            .background_tasks
+            .entry(task.id.clone())
+            .or_insert_with(|| BackgroundTask {
+                status: BackgroundTaskStatus::Starting,
+                restarts: 0,
+                ready_waiters: Vec::new(),
+                _output_subscription: None,
+            });
+        background_task.status = BackgroundTaskStatus::Starting;
+        background_task._output_subscription = None;
+        cx.notify();
+    }
+
+    /// Tracks the background task running in `terminal`, which gets ready once its output matches the task's ready pattern.
+    pub fn watch_background_task(
+        &mut self,
+        task: &SpawnInTerminal,
+        terminal: &Entity<Terminal>,
+        cx: &mut Context<Self>,
+    ) {
+        self.background_task_started(task, cx);
+        let ready_pattern = match task.ready_pattern.as_deref().map(Regex::new) {
+            Some(Ok(ready_pattern)) => ready_pattern,
+            Some(Err(e)) => {
+                log::error!(
+                    "Invalid ready pattern of task `{}`, considering it ready: {e}",
+                    task.full_label
+                );
+                self.background_task_ready(&task.id, cx);
+                return;
+            }
+            None => {
+                self.background_task_ready(&task.id, cx);
+                return;
+            }
+        };
+        let (output, mut scanned_line) = terminal.read(cx).output_since(None);
+        if ready_pattern.is_match(&output) {
+            self.background_task_ready(&task.id, cx);
+            return;
+        }
+
+        // Only the output printed since the last wakeup is matched, starting from the line the cursor was on, which
+        // may have been printed partially.
+        let task_id = task.id.clone();
+        let subscription = cx.subscribe(terminal, move |task_store, terminal, event, cx| {
+            if !matches!(event, terminal::Event::Wakeup) {
+                return;
+            }
+            let (output, cursor_line) = terminal.read(cx).output_since(Some(scanned_line));
+            scanned_line = cursor_line;
+            if ready_pattern.is_match(&output) {
+                task_store.background_task_ready(&task_id, cx);
+            }
+        });
+        if let TaskStore::Functional(state) = self
+            && let Some(background_task) = state.background_tasks.get_mut(&task.id)
+        {
+            background_task._output_subscription = Some(subscription);
+        }
+    }
+
+    /// Records that the background task is ready, resolving everything [`Self::wait_until_ready`] on it.
+    pub fn background_task_ready(&mut self, id: &TaskId, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        let Some(background_task) = state.background_tasks.get_mut(id) else {
+            return;
+        };
+        background_task.status = BackgroundTaskStatus::Ready;
+        background_task.restarts = 0;
+        background_task._output_subscription = None;
+        for waiter in background_task.ready_waiters.drain(..) {
+            waiter.send(Ok(())).ok();
+        }
+        cx.notify();
+    }
+
+    /// Records that the background task exited, and returns how long to wait before restarting it, if it should be.
+    ///
+    /// Tasks that failed are restarted with an exponentially increasing delay, until they fail too many times in a row
+    /// without getting ready; tasks that succeeded or were cancelled stay stopped.
+    pub fn background_task_exited(
+        &mut self,
+        id: &TaskId,
+        failed: bool,
+        cx: &mut Context<Self>,
+    ) -> Option<Duration> {
+        let TaskStore::Functional(state) = self else {
+            return None;
+        };
+        let background_task = state.background_tasks.get_mut(id)?;
+        background_task._output_subscription = None;
+        cx.notify();
+        if failed && background_task.restarts < MAX_BACKGROUND_TASK_RESTARTS {
+            background_task.restarts += 1;
+            background_task.status = BackgroundTaskStatus::Restarting {
+                attempt: background_task.restarts,
+            };
+            return Some(background_task_restart_delay(background_task.restarts));
+        }
+
+        background_task.status = BackgroundTaskStatus::Stopped;
+        for waiter in background_task.ready_waiters.drain(..) {
+            waiter
+                .send(Err(anyhow!("background task exited before getting ready")))
+                .ok();
+        }
+        None
+    }
+
+    /// Resolves once the background task is ready, or fails if it stops before that.
+    pub fn wait_until_ready(&mut self, id: &TaskId, cx: &App) -> Task<anyhow::Result<()>> {
+        let TaskStore::Functional(state) = self else {
+            return Task::ready(Err(anyhow!("tasks are not supported in this project")));
+        };
+        let Some(background_task) = state.background_tasks.get_mut(id) else {
+            return Task::ready(Err(anyhow!("background task {id:?} is not running")));
+        };
+        match background_task.status {
+            BackgroundTaskStatus::Ready => Task::ready(Ok(())),
+            BackgroundTaskStatus::Stopped => {
+                Task::ready(Err(anyhow!("background task {id:?} is stopped")))
+            }
+            BackgroundTaskStatus::Starting | BackgroundTaskStatus::Restarting { .. } => {
+                let (tx, rx) = oneshot::channel();
+                background_task.ready_waiters.push(tx);
+                cx.background_spawn(async move {
+                    rx.await
+                        .context("background task got dropped before getting ready")?
+                })
+            }
+        }
+    }
+
+    /// Queues the tasks whose `run_on_save` globs match the saved buffer's file, to be run once saves settle down.
+    pub fn buffer_saved(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        let Some(file) = buffer.read(cx).file().cloned() else {
+            return;
+        };
+        let worktree_id = file.worktree_id(cx);
+        let Some(worktree) = state
+            .worktree_store
+            .read(cx)
+            .worktree_for_id(worktree_id, cx)
+        else {
+            return;
+        };
+        let path_style = worktree.read(cx).path_style();
+        let path = file.path().clone();
+        let language = buffer.read(cx).language().cloned();
+        // Tasks defined in a worktree run arbitrary commands without the user asking for them,
+        // so they are only triggered once the worktree is trusted.
+        let worktree_store = state.worktree_store.clone();
+        let is_worktree_trusted = TrustedWorktrees::try_get_global(cx).is_none_or(|trusted| {
+            trusted.update(cx, |trusted, cx| {
+                trusted.can_trust(&worktree_store, worktree_id, cx)
+            })
+        });
+        let templates =
+            state
+                .task_inventory
+                .read(cx)
+                .list_tasks(Some(file), language, Some(worktree_id), cx);
+        let buffer = buffer.clone();
+        cx.spawn(async move |task_store, cx| {
+            let triggered_templates = templates
+                .await
+                .into_iter()
+                .filter(|(_, template)| {
+                    !template.run_on_save.is_empty()
+                        && PathMatcher::new(&template.run_on_save, path_style)
+                            .log_err()
+                            .is_some_and(|matcher| matcher.is_match(&path))
+                })
+                .filter(|(task_source_kind, template)| {
+                    let is_trusted =
+                        task_source_kind.worktree_id().is_none() || is_worktree_trusted;
+                    if !is_trusted {
+                        log::info!(
+                            "Not running task `{}` on save, as its worktree is not trusted",
+                            template.label
+                        );
+                    }
+                    is_trusted
+                })
+                .collect::<Vec<_>>();
+            if triggered_templates.is_empty() {
+                return;
+            }
+
+            let task_context = task_store
+                .update(cx, |task_store, cx| {
+                    let anchor = buffer.read(cx).anchor_before(0);
+                    let location = Location {
+                        buffer,
+                        range: anchor..anchor,
+                    };
+                    task_store.task_context_for_location(TaskVariables::default(), location, cx)
+                })
+                .ok();
+            let task_context = match task_context {
+                Some(task_context) => task_context.await.unwrap_or_default(),
+                None => return,
+            };
+            let triggered_tasks = triggered_templates
+                .into_iter()
+                .filter_map(|(task_source_kind, template)| {
+                    let task =
+                        template.resolve_task(&task_source_kind.to_id_base(), &task_context)?;
+                    Some((task_source_kind, task))
+                })
+                .collect::<Vec<_>>();
+            task_store
+                .update(cx, |task_store, cx| {
+                    task_store.queue_triggered_tasks(triggered_tasks, cx)
+                })
+                .ok();
+        })
+        .detach();
+    }
+
+    fn queue_triggered_tasks(
+        &mut self,
+        tasks: Vec<(TaskSourceKind, ResolvedTask)>,
+        cx: &mut Context<Self>,
+    ) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        if tasks.is_empty() {
+            return;
+        }
+        let pending = &mut state.save_triggers.pending;
+        for (task_source_kind, task) in tasks {
+            pending.retain(|(_, pending_task)| {
+                pending_task.original_task().label != task.original_task().label
+            });
+            pending.push((task_source_kind, task));
+        }
+        state.save_triggers.debounce = Some(cx.spawn(async move |task_store, cx| {
+            cx.background_executor().timer(SAVE_TRIGGER_DEBOUNCE).await;
+            task_store
+                .update(cx, |task_store, cx| {
+                    if let TaskStore::Functional(state) = task_store {
+                        state.save_triggers.debounce = None;
+                    }
+                    task_store.run_triggered_tasks(cx);
+                })
+                .ok();
+        }));
+    }
+
+    /// Emits the pending triggered tasks, as long as there are free slots, and none with the same label is running.
+    fn run_triggered_tasks(&mut self, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        let save_triggers = &mut state.save_triggers;
+        let mut ix = 0;
+        while ix < save_triggers.pending.len()
+            && save_triggers.running.len() < MAX_CONCURRENT_TRIGGERED_TASKS
+        {
+            let label = &save_triggers.pending[ix].1.original_task().label;
+            if save_triggers.running.contains(label) {
+                ix += 1;
+                continue;
+            }
+            let (task_source_kind, task) = save_triggers.pending.remove(ix);
+            let label = task.original_task().label.clone();
+            save_triggers.running.insert(label.clone());
+            let slot = TriggeredTaskSlot(Arc::new(TriggeredTaskSlotState {
+                label,
+                finished_tx: save_triggers.finished_tx.clone(),
+            }));
+            cx.emit(TaskStoreEvent::TaskTriggered {
+                task_source_kind,
+                task,
+                slot,
+            });
+        }
+    }
+
+    /// Frees the slot of a triggered task, letting the next pending ones run.
+    fn triggered_task_finished(&mut self, label: &str, cx: &mut Context<Self>) {
+        let TaskStore::Functional(state) = self else {
+            return;
+        };
+        state.save_triggers.running.remove(label);
+        if state.save_triggers.debounce.is_none() {
+            self.run_triggered_tasks(cx);
+        }
+    }
+
     pub(super) fn update_user_tasks(
         &self,
         location: TaskSettingsLocation<'_>,
@@ -118,8 +841,16 @@ impl TaskStore {
     }
 }
 
+fn background_task_restart_delay(attempt: usize) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16) as u32;
+    BACKGROUND_TASK_RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(exponent))
+        .min(MAX_BACKGROUND_TASK_RESTART_DELAY)
+}
+
 fn local_task_context_for_location(
     worktree_store: Entity<WorktreeStore>,
+    git_store: Entity<GitStore>,
     toolchain_store: Arc<dyn LanguageToolchainStore>,
     environment: Entity<ProjectEnvironment>,
     captured_variables: TaskVariables,
@@ -133,12 +864,19 @@ fn local_task_context_for_location(
     let fs = worktree_store.read(cx).fs();
 
     cx.spawn(async move |cx| {
-        let project_env = environment
-            .update(cx, |environment, cx| {
-                environment.buffer_environment(&location.buffer, &worktree_store, cx)
+        let project_env = cx
+            .update(|cx| {
+                local_task_environment(
+                    &worktree_store,
+                    toolchain_store.clone(),
+                    &environment,
+                    &location.buffer,
+                    cx,
+                )
             })
             .ok()?
-            .await;
+            .await
+            .map(|environment| environment.resolve());
 
         let mut task_variables = cx
             .update(|cx| {
@@ -148,7 +886,7 @@ fn local_task_context_for_location(
                     worktree_store.clone(),
                     location,
                     project_env.clone(),
-                    BasicContextProvider::new(worktree_store),
+                    BasicContextProvider::new(worktree_store).with_git_store(git_store),
                     toolchain_store,
                     cx,
                 )
@@ -167,6 +905,51 @@ fn local_task_context_for_location(
     })
 }
 
+fn local_task_environment(
+    worktree_store: &Entity<WorktreeStore>,
+    toolchain_store: Arc<dyn LanguageToolchainStore>,
+    environment: &Entity<ProjectEnvironment>,
+    buffer: &Entity<Buffer>,
+    cx: &mut App,
+) -> Task<Option<TaskEnvironment>> {
+    let project_env = environment.update(cx, |environment, cx| {
+        environment.buffer_environment(buffer, worktree_store, cx)
+    });
+    let buffer = buffer.read(cx);
+    let file = buffer.file();
+    let settings_location = file.map(|file| SettingsLocation {
+        worktree_id: file.worktree_id(cx),
+        path: RelPath::empty(),
+    });
+    let terminal_env = TerminalSettings::get(settings_location, cx).env.clone();
+    let toolchain_location = buffer.language().zip(file).map(|(language, file)| {
+        let directory = file
+            .path()
+            .parent()
+            .map(Arc::from)
+            .unwrap_or_else(|| RelPath::empty().into());
+        (file.worktree_id(cx), directory, language.name())
+    });
+
+    cx.spawn(async move |cx| {
+        let project_env = project_env.await.unwrap_or_default();
+        let toolchain = match toolchain_location {
+            Some((worktree_id, directory, language_name)) => {
+                toolchain_store
+                    .active_toolchain(worktree_id, directory, language_name, cx)
+                    .await
+            }
+            None => None,
+        };
+        let toolchain_path = toolchain.map(|toolchain| PathBuf::from(toolchain.path.to_string()));
+        Some(TaskEnvironment::new(
+            project_env,
+            terminal_env,
+            toolchain_path.as_deref(),
+        ))
+    })
+}
+
 fn combine_task_variables(
     mut captured_variables: TaskVariables,
     fs: Option<Arc<dyn Fs>>,
@@ -1,28 +1,56 @@
-use anyhow::Result;
+mod shell_integration;
+
+use anyhow::{Context as _, Result, anyhow, bail};
 use collections::HashMap;
-use gpui::{App, AppContext as _, Context, Entity, Task, WeakEntity};
+use gpui::{App, AppContext as _, AsyncApp, Context, Entity, PromptLevel, Task, WeakEntity};
 
-use futures::{FutureExt, future::Shared};
+use futures::{FutureExt, channel::oneshot, future::Shared};
 use itertools::Itertools as _;
-use language::LanguageName;
+use language::{Buffer, LanguageName, LanguageRegistry, Toolchain};
+use lsp::MessageActionItem;
 use settings::{Settings, SettingsLocation};
 use smol::channel::bounded;
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
-use task::{Shell, ShellBuilder, ShellKind, SpawnInTerminal};
+use task::{ExecutionTarget, Shell, ShellBuilder, ShellKind, SpawnInTerminal};
 use terminal::{
     TaskState, TaskStatus, Terminal, TerminalBuilder, terminal_settings::TerminalSettings,
 };
-use util::{command::new_std_command, maybe, rel_path::RelPath};
+use util::{command::new_std_command, rel_path::RelPath};
 
-use crate::{Project, ProjectPath};
+use crate::{
+    Event, LanguageServerPromptRequest, Project, ProjectEnvironment, ProjectPath,
+    dev_container::{self, DevContainer},
+};
 
 pub struct Terminals {
     pub(crate) local_handles: Vec<WeakEntity<terminal::Terminal>>,
 }
 
+/// What a command run with [`Project::run_terminal_command`] printed, and how it exited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminalCommandOutput {
+    pub output: String,
+    /// `None` when the shell didn't report it.
+    pub exit_code: Option<i32>,
+}
+
+/// The line of the prompt a terminal's shell waits at, to tell the command typed at it apart from others.
+fn terminal_prompt_line(terminal: &Terminal) -> Result<usize> {
+    if terminal.commands().is_empty() {
+        bail!("the terminal's shell has no shell integration");
+    }
+    terminal
+        .commands()
+        .last()
+        .filter(|_| terminal.is_at_prompt())
+        .map(|command| command.prompt_line)
+        .context("the terminal's shell is running a command")
+}
+
 impl Project {
     pub fn active_project_directory(&self, cx: &App) -> Option<Arc<Path>> {
         self.active_entry()
@@ -81,6 +109,14 @@ impl Project {
         let is_windows = self.path_style(cx).is_windows();
         let shell_kind = ShellKind::new(&shell, is_windows);
 
+        let execution_target = spawn_task.execution_target;
+        let dev_container_task = match path.clone() {
+            Some(path) => self.environment.update(cx, |project_env, cx| {
+                project_env.task_dev_container(path, execution_target, cx)
+            }),
+            None => Task::ready(None).shared(),
+        };
+        let environment = self.environment.clone();
         // Prepare a task for resolving the environment
         let env_task = self.resolve_directory_environment(&shell, path, cx);
 
@@ -102,27 +138,32 @@ impl Project {
             .collect::<Vec<_>>();
         let lang_registry = self.languages.clone();
         cx.spawn(async move |project, cx| {
+            let dev_container = dev_container_task.await;
+            if execution_target == Some(ExecutionTarget::DevContainer) && dev_container.is_none() {
+                bail!("no Dev Container to run task `{}` in", spawn_task.label);
+            }
+            // Processes in a Dev Container get the container's environment rather than the host's.
+            let mut container_env = match &dev_container {
+                Some(dev_container) => environment
+                    .update(cx, |environment, cx| {
+                        environment.dev_container_environment(dev_container.clone(), cx)
+                    })?
+                    .await
+                    .unwrap_or_default(),
+                None => HashMap::default(),
+            };
             let mut env = env_task.await.unwrap_or_default();
+            container_env.extend(settings.env.clone());
             env.extend(settings.env);
 
-            let activation_script = maybe!(async {
-                for toolchain in toolchains {
-                    let Some(toolchain) = toolchain.await else {
-                        continue;
-                    };
-                    let language = lang_registry
-                        .language_for_name(&toolchain.language_name.0)
-                        .await
-                        .ok();
-                    let lister = language?.toolchain_lister()?;
-                    return cx
-                        .update(|cx| lister.activation_script(&toolchain, shell_kind, cx))
-                        .ok();
-                }
-                None
-            })
-            .await
-            .unwrap_or_default();
+            let activation_script = toolchain_activation_script(
+                toolchains,
+                &lang_registry,
+                shell_kind,
+                dev_container.as_deref(),
+                cx,
+            )
+            .await;
 
             let builder = project
                 .update(cx, move |_, cx| {
@@ -142,7 +183,41 @@ impl Project {
                         }
                     };
 
-                    let (shell, env) = {
+                    let (shell, env) = if let Some(dev_container) = &dev_container {
+                        let (program, args) = match &spawn_task.command {
+                            Some(command) => dev_container.wrap_task(
+                                command,
+                                &spawn_task,
+                                local_path.as_deref(),
+                                &container_env,
+                                &activation_script,
+                                true,
+                            ),
+                            None => {
+                                let (program, args) = dev_container_login_shell();
+                                let (program, args) = dev_container::with_activation_script(
+                                    program,
+                                    args,
+                                    &activation_script,
+                                );
+                                dev_container.wrap_command(
+                                    &program,
+                                    &args,
+                                    local_path.as_deref(),
+                                    &container_env,
+                                    true,
+                                )
+                            }
+                        };
+                        (
+                            Shell::WithArguments {
+                                program,
+                                args,
+                                title_override: None,
+                            },
+                            env,
+                        )
+                    } else {
                         env.extend(spawn_task.env);
                         match activation_script.clone() {
                             activation_script if !activation_script.is_empty() => {
@@ -227,10 +302,23 @@ impl Project {
         })
     }
 
+    /// Starts a shell in a new terminal, with the default env profile from the `terminal.default_env_profile`
+    /// setting, if any.
     pub fn create_terminal_shell(
         &mut self,
         cwd: Option<PathBuf>,
         cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Terminal>>> {
+        self.create_terminal_shell_with_env_profile(cwd, None, cx)
+    }
+
+    /// Starts a shell in a new terminal, with the named env profile from the `terminal.env_profiles` setting, or the
+    /// default one when `env_profile` is `None`.
+    pub fn create_terminal_shell_with_env_profile(
+        &mut self,
+        cwd: Option<PathBuf>,
+        env_profile: Option<String>,
+        cx: &mut Context<Self>,
     ) -> Task<Result<Entity<Terminal>>> {
         let path = cwd.map(|p| Arc::from(&*p));
         let is_via_remote = false;
@@ -245,6 +333,22 @@ impl Project {
             });
         }
         let settings = TerminalSettings::get(settings_location, cx).clone();
+        let env_profile_name = match env_profile {
+            Some(name) if !settings.env_profiles.contains_key(&name) => {
+                return Task::ready(Err(anyhow!("unknown terminal env profile `{name}`")));
+            }
+            Some(name) => Some(name),
+            None => settings.default_env_profile.clone().filter(|name| {
+                let known = settings.env_profiles.contains_key(name);
+                if !known {
+                    log::error!("unknown default terminal env profile `{name}`");
+                }
+                known
+            }),
+        };
+        let env_profile = env_profile_name
+            .as_ref()
+            .map(|name| settings.env_profiles[name].clone());
         let detect_venv = settings.detect_venv.as_option().is_some();
         let local_path = path.clone();
 
@@ -268,37 +372,79 @@ impl Project {
 
         let is_windows = self.path_style(cx).is_windows();
 
+        let dev_container_task = self.resolve_dev_container(path.clone(), cx);
         // Prepare a task for resolving the environment
         let env_task = self.resolve_directory_environment(&shell, path, cx);
 
         let lang_registry = self.languages.clone();
         cx.spawn(async move |project, cx| {
             let shell_kind = ShellKind::new(&shell, is_windows);
+            let dev_container = dev_container_task.await;
             let mut env = env_task.await.unwrap_or_default();
+            let mut container_env = settings.env.clone();
             env.extend(settings.env);
-
-            let activation_script = maybe!(async {
-                for toolchain in toolchains {
-                    let Some(toolchain) = toolchain.await else {
-                        continue;
-                    };
-                    let language = lang_registry
-                        .language_for_name(&toolchain.language_name.0)
-                        .await
-                        .ok();
-                    let lister = language?.toolchain_lister()?;
-                    return cx
-                        .update(|cx| lister.activation_script(&toolchain, shell_kind, cx))
-                        .ok();
+            if let Some(env_profile) = &env_profile {
+                // The directories the profile adds to `PATH` are on the host, so a Dev Container only gets its
+                // variables.
+                container_env.extend(env_profile.env.clone());
+                ProjectEnvironment::apply_terminal_env_profile(
+                    env_profile,
+                    local_path.as_deref(),
+                    is_windows,
+                    &mut env,
+                );
+            }
+
+            let mut activation_script = toolchain_activation_script(
+                toolchains,
+                &lang_registry,
+                shell_kind,
+                dev_container.as_deref(),
+                cx,
+            )
+            .await;
+            if let Some(env_profile) = env_profile {
+                activation_script.extend(env_profile.activation_script);
+            }
+
+            // Shells in a Dev Container can't read the scripts installed on the host.
+            let integrated_shell = if settings.shell_integration && dev_container.is_none() {
+                let scripts_dir = shell_integration::scripts_dir();
+                match shell_integration::install_scripts(&scripts_dir).await {
+                    Ok(()) => shell_integration::integrate_shell(
+                        &settings.shell,
+                        &scripts_dir,
+                        is_windows,
+                        &mut env,
+                    ),
+                    Err(error) => {
+                        log::error!("failed to install the shell integration scripts: {error:#}");
+                        None
+                    }
                 }
+            } else {
                 None
-            })
-            .await
-            .unwrap_or_default();
+            };
 
             let builder = project
                 .update(cx, move |_, cx| {
-                    let (shell, env) = (settings.shell, env);
+                    let shell = if let Some(dev_container) = &dev_container {
+                        let (program, args) = dev_container_login_shell();
+                        let (program, args) = dev_container.wrap_command(
+                            &program,
+                            &args,
+                            local_path.as_deref(),
+                            &container_env,
+                            true,
+                        );
+                        Shell::WithArguments {
+                            program,
+                            args,
+                            title_override: None,
+                        }
+                    } else {
+                        integrated_shell.unwrap_or(settings.shell)
+                    };
                     anyhow::Ok(TerminalBuilder::new(
                         local_path.map(|path| path.to_path_buf()),
                         None,
@@ -318,7 +464,11 @@ impl Project {
                 })??
                 .await?;
             project.update(cx, move |this, cx| {
-                let terminal_handle = cx.new(|cx| builder.subscribe(cx));
+                let terminal_handle = cx.new(|cx| {
+                    let mut terminal = builder.subscribe(cx);
+                    terminal.set_env_profile(env_profile_name);
+                    terminal
+                });
 
                 this.terminals
                     .local_handles
@@ -343,6 +493,129 @@ impl Project {
         })
     }
 
+    /// Runs a command in the shell of one of this project's terminals, as if the user typed it, and returns its output
+    /// once it finishes.
+    ///
+    /// The user confirms the command before it's typed. The shell needs shell integration, to tell where the command's
+    /// output starts and ends, and must be waiting at its prompt, so that commands are never typed into programs
+    /// running in the terminal. The command is interrupted with ctrl-c when it runs longer than `timeout`, or when the
+    /// returned task is dropped.
+    pub fn run_terminal_command(
+        &mut self,
+        terminal: &Entity<Terminal>,
+        command: &str,
+        timeout: Duration,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<TerminalCommandOutput>> {
+        let is_project_terminal = self
+            .terminals
+            .local_handles
+            .iter()
+            .any(|handle| handle.entity_id() == terminal.entity_id());
+        if !is_project_terminal {
+            return Task::ready(Err(anyhow!("the terminal doesn't belong to this project")));
+        }
+        if command.contains(['\n', '\r']) {
+            return Task::ready(Err(anyhow!(
+                "can't run `{command}` in a terminal: it spans several lines"
+            )));
+        }
+        if let Err(error) = terminal_prompt_line(terminal.read(cx)) {
+            return Task::ready(Err(error));
+        }
+
+        let run_action = MessageActionItem {
+            title: "Run".to_owned(),
+            properties: Default::default(),
+        };
+        let cancel_action = MessageActionItem {
+            title: "Cancel".to_owned(),
+            properties: Default::default(),
+        };
+        let (response_tx, response_rx) = bounded(1);
+        cx.emit(Event::LanguageServerPrompt(LanguageServerPromptRequest {
+            level: PromptLevel::Warning,
+            message: format!("Run `{command}` in the terminal?"),
+            actions: vec![run_action.clone(), cancel_action],
+            lsp_name: "Terminal".to_owned(),
+            response_channel: response_tx,
+        }));
+
+        let terminal = terminal.downgrade();
+        let command = command.to_owned();
+        cx.spawn(async move |this, cx| {
+            if response_rx.recv().await.ok() != Some(run_action) {
+                bail!("running `{command}` in the terminal was cancelled");
+            }
+
+            let (output_tx, output_rx) = oneshot::channel();
+            let mut output_tx = Some(output_tx);
+            let (_subscription, interrupt) = this.update(cx, |_, cx| {
+                let terminal = terminal.upgrade().context("the terminal closed")?;
+                // The shell may have started something else while the user was deciding.
+                let prompt_line = terminal.update(cx, |terminal, _| {
+                    let prompt_line = terminal_prompt_line(terminal)?;
+                    terminal.send_text(&format!("{command}\r"))?;
+                    anyhow::Ok(prompt_line)
+                })?;
+                let interrupt = terminal.read(cx).interrupt_on_drop();
+                let subscription = cx.subscribe(&terminal, move |_, terminal, event, cx| {
+                    let terminal = terminal.read(cx);
+                    if let terminal::Event::CommandFinished { exit_code } = event
+                        && let Some(command) = terminal.commands().iter().rev().find(|command| {
+                            command.prompt_line == prompt_line && command.finished
+                        })
+                        && let Some(output_tx) = output_tx.take()
+                    {
+                        output_tx
+                            .send(TerminalCommandOutput {
+                                output: terminal.command_output(command).unwrap_or_default(),
+                                exit_code: *exit_code,
+                            })
+                            .ok();
+                    }
+                });
+                anyhow::Ok((subscription, interrupt))
+            })??;
+
+            let mut timer = cx.background_executor().timer(timeout).fuse();
+            let output = futures::select_biased! {
+                output = output_rx.fuse() => {
+                    output.context("the terminal closed before the command finished")?
+                }
+                _ = timer => bail!("`{command}` didn't finish within {timeout:?} and was interrupted"),
+            };
+            interrupt.disarm();
+            Ok(output)
+        })
+    }
+
+    /// Writes the scrollback of a terminal to a file, e.g. to keep the log of a long build.
+    pub fn export_terminal_scrollback(
+        &self,
+        terminal: &Entity<Terminal>,
+        abs_path: PathBuf,
+        cx: &App,
+    ) -> Task<Result<()>> {
+        let text = terminal.read(cx).scrollback_text();
+        let fs = self.fs.clone();
+        cx.background_spawn(async move {
+            fs.atomic_write(abs_path.clone(), text)
+                .await
+                .with_context(|| format!("exporting the terminal scrollback to {abs_path:?}"))
+        })
+    }
+
+    /// Opens the scrollback of a terminal in a new buffer, to dig through it like any other text.
+    pub fn open_terminal_scrollback(
+        &mut self,
+        terminal: &Entity<Terminal>,
+        cx: &mut Context<Self>,
+    ) -> Entity<Buffer> {
+        let text = terminal.read(cx).scrollback_text();
+        self.create_local_buffer(&text, None, false, cx)
+    }
+
     pub fn clone_terminal(
         &mut self,
         terminal: &Entity<Terminal>,
@@ -356,11 +629,16 @@ impl Project {
         }
         let local_path = cwd;
 
+        let env_profile = terminal.read(cx).env_profile().map(ToOwned::to_owned);
         let builder = terminal.read(cx).clone_builder(cx, local_path);
         cx.spawn(async |project, cx| {
             let terminal = builder.await?;
             project.update(cx, |project, cx| {
-                let terminal_handle = cx.new(|cx| terminal.subscribe(cx));
+                let terminal_handle = cx.new(|cx| {
+                    let mut terminal = terminal.subscribe(cx);
+                    terminal.set_env_profile(env_profile);
+                    terminal
+                });
 
                 project
                     .terminals
@@ -442,6 +720,19 @@ impl Project {
         &self.terminals.local_handles
     }
 
+    fn resolve_dev_container(
+        &self,
+        path: Option<Arc<Path>>,
+        cx: &mut App,
+    ) -> Shared<Task<Option<Arc<DevContainer>>>> {
+        if let Some(path) = path {
+            self.environment
+                .update(cx, |project_env, cx| project_env.dev_container(path, cx))
+        } else {
+            Task::ready(None).shared()
+        }
+    }
+
     fn resolve_directory_environment(
         &self,
         shell: &str,
@@ -458,3 +749,62 @@ impl Project {
         }
     }
 }
+
+/// Returns the commands activating the first of the given toolchains that can be activated, inside of the Dev
+/// Container when there is one.
+async fn toolchain_activation_script(
+    toolchains: Vec<Task<Option<Toolchain>>>,
+    lang_registry: &LanguageRegistry,
+    shell_kind: ShellKind,
+    dev_container: Option<&DevContainer>,
+    cx: &mut AsyncApp,
+) -> Vec<String> {
+    // The shell of the container user isn't known ahead of time, so it gets POSIX commands.
+    let shell_kind = if dev_container.is_some() {
+        ShellKind::Posix
+    } else {
+        shell_kind
+    };
+    for toolchain in toolchains {
+        let Some(toolchain) = toolchain.await else {
+            continue;
+        };
+        let Some(lister) = lang_registry
+            .language_for_name(&toolchain.language_name.0)
+            .await
+            .ok()
+            .and_then(|language| language.toolchain_lister())
+        else {
+            continue;
+        };
+        let Ok(activation_script) =
+            cx.update(|cx| lister.activation_script(&toolchain, shell_kind, cx))
+        else {
+            break;
+        };
+        match dev_container {
+            // Toolchains outside of the workspace folder are only on the host.
+            Some(dev_container) => {
+                if let Some(activation_script) = dev_container.toolchain_activation_script(
+                    Path::new(toolchain.path.as_ref()),
+                    activation_script,
+                ) {
+                    return activation_script;
+                }
+            }
+            None => return activation_script,
+        }
+    }
+    Vec::new()
+}
+
+/// Starts the login shell of the container user, falling back to `sh` for images without `$SHELL`.
+fn dev_container_login_shell() -> (String, Vec<String>) {
+    (
+        "sh".to_string(),
+        vec![
+            "-lc".to_string(),
+            "exec \"${SHELL:-/bin/sh}\" -l".to_string(),
+        ],
+    )
+}
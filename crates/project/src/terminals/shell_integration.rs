@@ -0,0 +1,166 @@
+//! Shell integration scripts, which make the shells started in terminals mark their prompts and commands, and
+//! report their working directory.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use collections::HashMap;
+use task::{Shell, ShellKind};
+
+const BASH_SCRIPT: &str = include_str!("shell_integration/vector.bash");
+const ZSH_SCRIPT: &str = include_str!("shell_integration/zsh/.zshenv");
+const FISH_SCRIPT: &str = include_str!("shell_integration/vector.fish");
+const POWERSHELL_SCRIPT: &str = include_str!("shell_integration/vector.ps1");
+
+const SCRIPTS: [(&str, &str); 4] = [
+    ("vector.bash", BASH_SCRIPT),
+    ("zsh/.zshenv", ZSH_SCRIPT),
+    ("vector.fish", FISH_SCRIPT),
+    ("vector.ps1", POWERSHELL_SCRIPT),
+];
+
+/// The directory the shell integration scripts are installed in.
+pub(super) fn scripts_dir() -> PathBuf {
+    paths::temp_dir().join("shell_integration")
+}
+
+/// Writes the shell integration scripts to `dir`, unless they are there already.
+pub(super) async fn install_scripts(dir: &Path) -> Result<()> {
+    for (name, script) in SCRIPTS {
+        let path = dir.join(name);
+        if smol::fs::read_to_string(&path)
+            .await
+            .is_ok_and(|installed| installed == script)
+        {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            smol::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("creating {parent:?}"))?;
+        }
+        smol::fs::write(&path, script)
+            .await
+            .with_context(|| format!("writing {path:?}"))?;
+    }
+    Ok(())
+}
+
+/// Returns the shell to start with shell integration loaded from the scripts in `scripts_dir`, adding the
+/// environment variables it needs to `env`, or `None` when the shell is not one that Vector can integrate with.
+///
+/// Shells started with custom arguments are left alone, as those could conflict with the ones loading the scripts.
+pub(super) fn integrate_shell(
+    shell: &Shell,
+    scripts_dir: &Path,
+    is_windows: bool,
+    env: &mut HashMap<String, String>,
+) -> Option<Shell> {
+    if matches!(shell, Shell::WithArguments { .. }) {
+        return None;
+    }
+    let program = shell.program();
+    let script = |name: &str| scripts_dir.join(name).to_string_lossy().into_owned();
+    let args = match Path::new(&program).file_stem()?.to_str()? {
+        "bash" => vec![
+            "--init-file".to_owned(),
+            script("vector.bash"),
+            "-i".to_owned(),
+        ],
+        "zsh" => {
+            let user_zdotdir = env
+                .get("ZDOTDIR")
+                .cloned()
+                .or_else(|| std::env::var("ZDOTDIR").ok());
+            if let Some(user_zdotdir) = user_zdotdir {
+                env.insert("VECTOR_USER_ZDOTDIR".to_owned(), user_zdotdir);
+            }
+            env.insert("ZDOTDIR".to_owned(), script("zsh"));
+            Vec::new()
+        }
+        _ => match ShellKind::new(&program, is_windows) {
+            ShellKind::Fish => vec![
+                "--init-command".to_owned(),
+                format!(
+                    "source {}",
+                    ShellKind::Fish.try_quote(&script("vector.fish"))?
+                ),
+            ],
+            shell_kind @ (ShellKind::PowerShell | ShellKind::Pwsh) => vec![
+                "-NoExit".to_owned(),
+                "-Command".to_owned(),
+                format!(". {}", shell_kind.try_quote(&script("vector.ps1"))?),
+            ],
+            _ => return None,
+        },
+    };
+    Some(Shell::WithArguments {
+        program,
+        args,
+        title_override: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_integrate_shell() {
+        let scripts_dir = Path::new("/scripts");
+
+        let mut env = HashMap::default();
+        let shell = integrate_shell(
+            &Shell::Program("/bin/bash".to_owned()),
+            scripts_dir,
+            false,
+            &mut env,
+        );
+        assert_eq!(
+            shell,
+            Some(Shell::WithArguments {
+                program: "/bin/bash".to_owned(),
+                args: vec![
+                    "--init-file".to_owned(),
+                    "/scripts/vector.bash".to_owned(),
+                    "-i".to_owned()
+                ],
+                title_override: None,
+            })
+        );
+        assert!(env.is_empty());
+
+        let mut env = HashMap::from_iter([("ZDOTDIR".to_owned(), "/home/user/.zsh".to_owned())]);
+        let shell = integrate_shell(
+            &Shell::Program("/usr/bin/zsh".to_owned()),
+            scripts_dir,
+            false,
+            &mut env,
+        );
+        assert_eq!(
+            shell,
+            Some(Shell::WithArguments {
+                program: "/usr/bin/zsh".to_owned(),
+                args: Vec::new(),
+                title_override: None,
+            })
+        );
+        assert_eq!(env["ZDOTDIR"], "/scripts/zsh");
+        assert_eq!(env["VECTOR_USER_ZDOTDIR"], "/home/user/.zsh");
+
+        let mut env = HashMap::default();
+        for shell in [
+            Shell::Program("/bin/sh".to_owned()),
+            Shell::Program("nu".to_owned()),
+            Shell::WithArguments {
+                program: "/bin/bash".to_owned(),
+                args: vec!["-l".to_owned()],
+                title_override: None,
+            },
+        ] {
+            assert_eq!(integrate_shell(&shell, scripts_dir, false, &mut env), None);
+        }
+        assert!(env.is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+# Shell integration for Vector's terminal, loaded with `bash --init-file` in place of the system-wide bashrc and
+# ~/.bashrc, which are sourced here instead.
+
+if [ -r /etc/bash.bashrc ]; then
+    . /etc/bash.bashrc
+fi
+
+if [ -r ~/.bashrc ]; then
+    . ~/.bashrc
+fi
+
+__vector_prompt_start() {
+    local ret=$?
+    printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' "$ret" "$HOSTNAME" "$PWD"
+    return $ret
+}
+
+__vector_prompt_end() {
+    local ret=$?
+    case "$PS1" in
+        *'133;B'*) ;;
+        *) PS1="$PS1"'\[\e]133;B\a\]' ;;
+    esac
+    return $ret
+}
+
+PROMPT_COMMAND="__vector_prompt_start${PROMPT_COMMAND:+; $PROMPT_COMMAND}; __vector_prompt_end"
+PS0="${PS0}"'\e]133;C\a'
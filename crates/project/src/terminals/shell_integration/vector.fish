@@ -0,0 +1,13 @@
+# Shell integration for Vector's terminal, loaded with `fish --init-command`.
+
+function __vector_prompt --on-event fish_prompt
+    printf '\e]7;file://%s%s\a\e]133;A\a' $hostname $PWD
+end
+
+function __vector_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+
+function __vector_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
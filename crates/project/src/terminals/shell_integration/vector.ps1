@@ -0,0 +1,21 @@
+# Shell integration for Vector's terminal, dot-sourced with `-NoExit -Command`.
+
+$Global:__VectorPrompt = $function:prompt
+
+function Global:prompt {
+    $exitCode = if ($?) { 0 } elseif ($LASTEXITCODE) { $LASTEXITCODE } else { 1 }
+    $esc = [char]27
+    $bel = [char]7
+    $path = (Get-Location).ProviderPath -replace '\\', '/'
+    if (-not $path.StartsWith('/')) {
+        $path = "/$path"
+    }
+    "$esc]133;D;$exitCode$bel$esc]7;file://$([System.Net.Dns]::GetHostName())$path$bel$esc]133;A$bel" + (& $Global:__VectorPrompt)
+}
+
+if (Get-Module -Name PSReadLine) {
+    Set-PSReadLineKeyHandler -Chord Enter -ScriptBlock {
+        [Microsoft.PowerShell.PSConsoleReadLine]::AcceptLine()
+        [Console]::Write("$([char]27)]133;C$([char]7)")
+    }
+}
@@ -0,0 +1,377 @@
+//! Collects comments tagged with `TODO`, `FIXME` and the like across the project, keeping them
+//! up to date as files change.
+//!
+//! Tags are only collected inside comment syntax nodes, unless the file has no syntax tree.
+//! The store doesn't keep the buffers it scans alive: items are stored by path and position.
+
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Cursor, Read},
+    mem,
+    ops::Range,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use collections::HashSet;
+use fs::Fs;
+use gpui::{
+    AppContext as _, AsyncApp, Context, Entity, EventEmitter, SharedString, Subscription, Task,
+    WeakEntity,
+};
+use itertools::Itertools as _;
+use language::BufferSnapshot;
+use settings::{Settings as _, SettingsStore};
+use text::{OffsetRangeExt as _, Point};
+use util::ResultExt as _;
+use worktree::PathChange;
+
+use crate::{
+    ProjectPath,
+    buffer_store::BufferStore,
+    project_search::Search,
+    project_settings::ProjectSettings,
+    search::{SearchQuery, SearchResult},
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+};
+
+/// How long to wait for more file changes before rescanning changed files.
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TodoItem {
+    /// The range of the tag and the text following it on its line, as of the last time its file
+    /// was scanned.
+    pub range: Range<Point>,
+    pub tag: SharedString,
+    /// The text following the tag, without the separating colon.
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TodoStoreEvent {
+    TodosUpdated,
+}
+
+impl EventEmitter<TodoStoreEvent> for TodoStore {}
+
+/// The tagged comments of the project, collected with project searches while the `todo_scanner`
+/// setting is enabled.
+pub struct TodoStore {
+    fs: Arc<dyn Fs>,
+    buffer_store: Entity<BufferStore>,
+    worktree_store: Entity<WorktreeStore>,
+    tags: Vec<String>,
+    query: Option<Arc<SearchQuery>>,
+    todos: BTreeMap<ProjectPath, Vec<TodoItem>>,
+    pending_paths: HashSet<ProjectPath>,
+    scan_task: Option<Task<()>>,
+    update_task: Option<Task<()>>,
+    _subscriptions: [Subscription; 2],
+}
+
+impl TodoStore {
+    pub fn new(
+        fs: Arc<dyn Fs>,
+        buffer_store: Entity<BufferStore>,
+        worktree_store: Entity<WorktreeStore>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut this = Self {
+            fs,
+            buffer_store,
+            tags: Vec::new(),
+            query: None,
+            todos: BTreeMap::new(),
+            pending_paths: HashSet::default(),
+            scan_task: None,
+            update_task: None,
+            _subscriptions: [
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
+                cx.observe_global::<SettingsStore>(Self::on_settings_changed),
+            ],
+            worktree_store,
+        };
+        this.on_settings_changed(cx);
+        this
+    }
+
+    /// Returns the tagged comments of each file, ordered by path.
+    pub fn todos(&self) -> impl Iterator<Item = (&ProjectPath, &[TodoItem])> {
+        self.todos
+            .iter()
+            .map(|(path, todos)| (path, todos.as_slice()))
+    }
+
+    pub fn todos_for_path(&self, path: &ProjectPath) -> &[TodoItem] {
+        self.todos.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn todos_with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a TodoItem> {
+        self.todos
+            .values()
+            .flatten()
+            .filter(move |todo| todo.tag == tag)
+    }
+
+    /// Whether the project is being scanned for tagged comments from scratch.
+    pub fn is_scanning(&self) -> bool {
+        self.scan_task.is_some()
+    }
+
+    fn on_settings_changed(&mut self, cx: &mut Context<Self>) {
+        let settings = ProjectSettings::get_global(cx);
+        let tags = if settings.todo_scanner {
+            settings.todo_tags.clone()
+        } else {
+            Vec::new()
+        };
+        if tags == self.tags {
+            return;
+        }
+        self.query = todo_query(&tags).map(Arc::new);
+        self.tags = tags;
+        self.pending_paths.clear();
+        self.update_task = None;
+        if !self.todos.is_empty() {
+            self.todos.clear();
+            cx.emit(TodoStoreEvent::TodosUpdated);
+        }
+        self.rescan(cx);
+    }
+
+    fn on_worktree_store_event(
+        &mut self,
+        _: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        if self.query.is_none() {
+            return;
+        }
+        match event {
+            WorktreeStoreEvent::WorktreeAdded(_) => self.rescan(cx),
+            WorktreeStoreEvent::WorktreeRemoved(_, worktree_id) => {
+                self.todos
+                    .retain(|path, _| path.worktree_id != *worktree_id);
+                cx.emit(TodoStoreEvent::TodosUpdated);
+            }
+            WorktreeStoreEvent::WorktreeUpdatedEntries(worktree_id, changes) => {
+                let Some(worktree) = self
+                    .worktree_store
+                    .read(cx)
+                    .worktree_for_id(*worktree_id, cx)
+                else {
+                    return;
+                };
+                let worktree = worktree.read(cx);
+                let mut removed = false;
+                let mut changed_paths = Vec::new();
+                for (path, _, change) in changes.iter() {
+                    let project_path = ProjectPath {
+                        worktree_id: *worktree_id,
+                        path: path.clone(),
+                    };
+                    if *change == PathChange::Removed {
+                        removed |= self.todos.remove(&project_path).is_some();
+                    } else if worktree
+                        .entry_for_path(path)
+                        .is_some_and(|entry| entry.is_file() && !entry.is_ignored)
+                    {
+                        changed_paths.push(project_path);
+                    }
+                }
+                if removed {
+                    cx.emit(TodoStoreEvent::TodosUpdated);
+                }
+                self.queue_paths(changed_paths, cx);
+            }
+            _ => {}
+        }
+    }
+
+    /// Searches all files of the project for tagged comments, replacing the collected ones.
+    fn rescan(&mut self, cx: &mut Context<Self>) {
+        let Some(query) = self.query.clone() else {
+            self.scan_task = None;
+            return;
+        };
+        let search = Search::local(
+            self.buffer_store.clone(),
+            self.worktree_store.clone(),
+            None,
+            query.limits().max_files + 1,
+            cx,
+        )
+        .into_handle(query.as_ref().clone(), cx)
+        .results(cx);
+        let tags = self.tags.clone();
+        self.scan_task = Some(cx.spawn(async move |this, cx| {
+            let mut todos = BTreeMap::new();
+            while let Ok(result) = search.rx.recv().await {
+                let SearchResult::Buffer { buffer, ranges, .. } = result else {
+                    continue;
+                };
+                let Ok(parsing_idle) = buffer.read_with(cx, |buffer, _| buffer.parsing_idle())
+                else {
+                    continue;
+                };
+                parsing_idle.await;
+                let Ok(Some((path, items))) = buffer.read_with(cx, |this, cx| {
+                    let path = ProjectPath::from_file(this.file()?.as_ref(), cx);
+                    let snapshot = this.snapshot();
+                    let ranges = ranges.iter().map(|range| range.to_offset(&snapshot));
+                    Some((path, todo_items(&tags, &snapshot, ranges)))
+                }) else {
+                    continue;
+                };
+                todos.insert(path, items);
+            }
+            this.update(cx, |this, cx| {
+                this.todos = todos;
+                this.scan_task = None;
+                cx.emit(TodoStoreEvent::TodosUpdated);
+            })
+            .ok();
+        }));
+    }
+
+    fn queue_paths(&mut self, paths: Vec<ProjectPath>, cx: &mut Context<Self>) {
+        self.pending_paths.extend(paths);
+        if self.update_task.is_none() && !self.pending_paths.is_empty() {
+            self.update_task = Some(cx.spawn(async move |this, cx| {
+                cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+                Self::update_pending_paths(this, cx).await.log_err();
+            }));
+        }
+    }
+
+    /// Rescans the files that changed since the last update. Files that aren't open are only
+    /// opened when their contents match.
+    async fn update_pending_paths(this: WeakEntity<Self>, cx: &mut AsyncApp) -> Result<()> {
+        let (paths, query, tags, fs, buffer_store, worktree_store) =
+            this.update(cx, |this, _| {
+                (
+                    mem::take(&mut this.pending_paths),
+                    this.query.clone(),
+                    this.tags.clone(),
+                    this.fs.clone(),
+                    this.buffer_store.clone(),
+                    this.worktree_store.clone(),
+                )
+            })?;
+        let Some(query) = query else {
+            return Ok(());
+        };
+
+        let mut updates = Vec::new();
+        for path in paths {
+            let mut buffer = buffer_store.read_with(cx, |store, _| store.get_by_path(&path))?;
+            if buffer.is_none()
+                && let Some(abs_path) =
+                    worktree_store.read_with(cx, |store, cx| store.absolutize(&path, cx))?
+                && let Ok(text) = fs.load(&abs_path).await
+            {
+                let reader: Box<dyn Read + Send + Sync> = Box::new(Cursor::new(text.into_bytes()));
+                if query.detect(BufReader::new(reader)).await.unwrap_or(false) {
+                    buffer = buffer_store
+                        .update(cx, |store, cx| store.open_buffer(path.clone(), cx))?
+                        .await
+                        .log_err();
+                }
+            }
+            let items = match buffer {
+                Some(buffer) => {
+                    buffer
+                        .read_with(cx, |buffer, _| buffer.parsing_idle())?
+                        .await;
+                    let snapshot = buffer.read_with(cx, |buffer, _| buffer.snapshot())?;
+                    let ranges = query.search(&snapshot, None).await;
+                    todo_items(&tags, &snapshot, ranges)
+                }
+                None => Vec::new(),
+            };
+            updates.push((path, items));
+        }
+
+        this.update(cx, |this, cx| {
+            for (path, items) in updates {
+                if items.is_empty() {
+                    this.todos.remove(&path);
+                } else {
+                    this.todos.insert(path, items);
+                }
+            }
+            this.update_task = None;
+            cx.emit(TodoStoreEvent::TodosUpdated);
+            let pending_paths = this.pending_paths.drain().collect();
+            this.queue_paths(pending_paths, cx);
+        })
+    }
+}
+
+/// Builds a query matching any of the tags as a whole word, along with the rest of its line.
+fn todo_query(tags: &[String]) -> Option<SearchQuery> {
+    if tags.is_empty() {
+        return None;
+    }
+    let pattern = format!(
+        r"\b(?:{})\b.*",
+        tags.iter().map(|tag| regex::escape(tag)).join("|")
+    );
+    SearchQuery::regex(
+        pattern,
+        false,
+        true,
+        false,
+        false,
+        Default::default(),
+        Default::default(),
+        false,
+        None,
+    )
+    .log_err()
+}
+
+fn todo_items(
+    tags: &[String],
+    snapshot: &BufferSnapshot,
+    ranges: impl IntoIterator<Item = Range<usize>>,
+) -> Vec<TodoItem> {
+    ranges
+        .into_iter()
+        .filter(|range| is_in_comment(snapshot, range.start))
+        .filter_map(|range| {
+            let matched = snapshot.text_for_range(range.clone()).collect::<String>();
+            let tag = tags.iter().find(|tag| matched.starts_with(tag.as_str()))?;
+            let text = matched[tag.len()..]
+                .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
+                .trim_end()
+                .trim_end_matches("*/")
+                .trim_end()
+                .to_string();
+            Some(TodoItem {
+                range: range.to_point(snapshot),
+                tag: SharedString::from(tag.clone()),
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Whether the offset is inside a comment. Files without a syntax tree are treated as if they
+/// were all comments, since their comments can't be told apart.
+fn is_in_comment(snapshot: &BufferSnapshot, offset: usize) -> bool {
+    let Some(layer) = snapshot.syntax_layer_at(offset) else {
+        return true;
+    };
+    let mut node = layer.node().descendant_for_byte_range(offset, offset + 1);
+    while let Some(current) = node {
+        if current.kind().contains("comment") {
+            return true;
+        }
+        node = current.parent();
+    }
+    false
+}
@@ -122,6 +122,16 @@ impl ToolchainStore {
         }
     }
 
+    /// Returns the toolchains activated in the project, along with the directories they were
+    /// activated for. Toolchains of remote projects are not known locally.
+    pub(crate) fn active_toolchains(&self, cx: &App) -> Vec<(ProjectPath, Toolchain)> {
+        match &self.mode {
+            ToolchainStoreInner::Local(local) => local.read(cx).active_toolchains().collect(),
+            #[cfg(feature = "collab")]
+            ToolchainStoreInner::Remote(_) => Vec::new(),
+        }
+    }
+
     pub(crate) fn user_toolchains(&self) -> BTreeMap<ToolchainScope, IndexSet<Toolchain>> {
         self.user_toolchains.clone()
     }
@@ -582,6 +592,22 @@ impl LocalToolchainStore {
             .await
         })
     }
+    fn active_toolchains(&self) -> impl Iterator<Item = (ProjectPath, Toolchain)> + '_ {
+        self.active_toolchains
+            .iter()
+            .flat_map(|((worktree_id, _), toolchains)| {
+                toolchains.iter().map(|(path, toolchain)| {
+                    (
+                        ProjectPath {
+                            worktree_id: *worktree_id,
+                            path: path.clone(),
+                        },
+                        toolchain.clone(),
+                    )
+                })
+            })
+    }
+
     pub(crate) fn active_toolchain(
         &self,
         worktree_id: WorktreeId,
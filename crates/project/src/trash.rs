@@ -0,0 +1,127 @@
+//! Entries moved to the trash from the project, with copies of their contents kept so that they
+//! can be restored.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use fs::{Fs, MTime, RemoveOptions};
+use futures::StreamExt as _;
+use util::rel_path::RelPath;
+use worktree::WorktreeId;
+
+/// Entries larger than this, in bytes, are trashed without being made restorable.
+pub(crate) const MAX_RESTORABLE_SIZE: u64 = 64 * 1024 * 1024;
+/// The number of trashed entries kept restorable, beyond which the oldest ones are forgotten.
+pub(crate) const MAX_TRASHED_ENTRIES: usize = 50;
+/// The total size, in bytes, of the trashed entries kept restorable.
+pub(crate) const MAX_TRASHED_SIZE: u64 = 512 * 1024 * 1024;
+/// How long trashed entries are kept restorable. Copies that earlier sessions left behind are
+/// removed once they're older than this.
+pub(crate) const MAX_TRASHED_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+static NEXT_TRASHED_ENTRY_ID: AtomicUsize = AtomicUsize::new(0);
+static STALE_STASHES_REMOVED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrashedEntryId(usize);
+
+impl TrashedEntryId {
+    pub(crate) fn new() -> Self {
+        Self(NEXT_TRASHED_ENTRY_ID.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrashedEntry {
+    pub id: TrashedEntryId,
+    pub worktree_id: WorktreeId,
+    pub path: Arc<RelPath>,
+    pub abs_path: PathBuf,
+    pub is_dir: bool,
+    /// The size of the entry in bytes, including the files within it for directories.
+    pub size: u64,
+    pub mtime: Option<MTime>,
+    pub deleted_at: SystemTime,
+    /// Where the copy of the entry is kept until it's restored or forgotten.
+    pub(crate) stash_path: PathBuf,
+}
+
+/// Returns the path that the copy of a trashed entry is kept at.
+pub(crate) fn stash_path(id: TrashedEntryId) -> PathBuf {
+    stash_dir().join(format!("{}-{}", std::process::id(), id.0))
+}
+
+fn stash_dir() -> PathBuf {
+    paths::temp_dir().join("trash")
+}
+
+/// Returns how many of the given entries, oldest first, should be forgotten to stay within the
+/// count, size and age limits.
+pub(crate) fn entries_to_forget(entries: &[TrashedEntry], now: SystemTime) -> usize {
+    let mut total_size = entries.iter().map(|entry| entry.size).sum::<u64>();
+    let mut forgotten = 0;
+    for entry in entries {
+        let expired = now
+            .duration_since(entry.deleted_at)
+            .is_ok_and(|age| age > MAX_TRASHED_AGE);
+        if !expired
+            && entries.len() - forgotten <= MAX_TRASHED_ENTRIES
+            && total_size <= MAX_TRASHED_SIZE
+        {
+            break;
+        }
+        total_size -= entry.size;
+        forgotten += 1;
+    }
+    forgotten
+}
+
+/// Removes the expired copies that other sessions left behind. Only does anything the first
+/// time it's called in a process.
+pub(crate) async fn remove_stale_stashes(fs: &dyn Fs) -> Result<()> {
+    if STALE_STASHES_REMOVED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let stash_dir = stash_dir();
+    if !fs.is_dir(&stash_dir).await {
+        return Ok(());
+    }
+    let own_prefix = format!("{}-", std::process::id());
+    let now = SystemTime::now();
+    let mut stashes = fs.read_dir(&stash_dir).await?;
+    while let Some(path) = stashes.next().await {
+        let path = path?;
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(&own_prefix))
+        {
+            continue;
+        }
+        let Some(metadata) = fs.metadata(&path).await? else {
+            continue;
+        };
+        let expired = now
+            .duration_since(metadata.mtime.timestamp_for_user())
+            .is_ok_and(|age| age > MAX_TRASHED_AGE);
+        if !expired {
+            continue;
+        }
+        let options = RemoveOptions {
+            recursive: true,
+            ignore_if_not_exists: true,
+        };
+        if metadata.is_dir {
+            fs.remove_dir(&path, options).await?;
+        } else {
+            fs.remove_file(&path, options).await?;
+        }
+    }
+    Ok(())
+}
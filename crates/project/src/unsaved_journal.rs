@@ -0,0 +1,573 @@
+//! A journal of the contents of buffers with unsaved changes, written in the background so that
+//! the changes can be recovered after a session ends abnormally without saving them.
+//!
+//! Like the restoration of unsaved buffers by the workspace, the journal is only kept while the
+//! `session.restore_unsaved_buffers` setting is enabled. Each session records the worktree roots
+//! of its project, so that a project only offers to recover the changes journaled for it.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context as _, Result};
+use collections::{HashMap, HashSet};
+use fs::{Fs, RemoveOptions};
+use futures::StreamExt as _;
+use gpui::{AppContext as _, Context, Entity, EventEmitter, Subscription, Task};
+use language::{Buffer, BufferEvent};
+use serde::{Deserialize, Serialize};
+use settings::{Settings as _, SettingsStore};
+use text::BufferId;
+use util::{ResultExt as _, time::unix_millis};
+
+use crate::{
+    buffer_store::{BufferStore, BufferStoreEvent},
+    project_settings::ProjectSettings,
+    worktree_store::{WorktreeStore, WorktreeStoreEvent},
+};
+
+/// How long after a buffer changes its contents are journaled.
+const JOURNAL_DEBOUNCE: Duration = Duration::from_secs(1);
+/// How often a session with journaled buffers records that it's still running.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// The journals of other sessions that haven't recorded that they're running for this long are
+/// considered to be left behind by sessions that ended abnormally.
+const STALE_JOURNAL_AGE: Duration = Duration::from_secs(2 * 60);
+const HEARTBEAT_FILE_NAME: &str = "heartbeat";
+const ROOTS_FILE_NAME: &str = "roots";
+
+static NEXT_JOURNAL_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    abs_path: Option<PathBuf>,
+    text: String,
+}
+
+/// The unsaved contents of a buffer, journaled by a session that ended without saving them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecoverableBuffer {
+    /// The path of the buffer's file, or `None` for a buffer that was never saved.
+    pub abs_path: Option<PathBuf>,
+    pub text: String,
+    /// When the session that journaled the contents was last running, in milliseconds since the
+    /// Unix epoch.
+    pub journaled_at: u64,
+    entry_path: PathBuf,
+}
+
+pub enum UnsavedJournalEvent {
+    RecoverableBuffersFound,
+}
+
+impl EventEmitter<UnsavedJournalEvent> for UnsavedJournal {}
+
+/// Journals the contents of the project's dirty buffers, including ones that were never saved,
+/// and finds the ones journaled by earlier sessions that ended abnormally.
+pub struct UnsavedJournal {
+    fs: Arc<dyn Fs>,
+    buffer_store: Entity<BufferStore>,
+    worktree_store: Entity<WorktreeStore>,
+    session_dir: PathBuf,
+    /// Whether `session.restore_unsaved_buffers` is enabled.
+    enabled: bool,
+    /// The sorted roots of the project's visible worktrees.
+    project_roots: Vec<PathBuf>,
+    /// Whether the recoverable buffers of the project were looked for.
+    loaded_recoverable_buffers: bool,
+    recoverable_buffers: Vec<RecoverableBuffer>,
+    /// The buffers that changed since their contents were last journaled.
+    pending_buffers: HashSet<BufferId>,
+    journaled_buffers: HashSet<BufferId>,
+    buffer_subscriptions: HashMap<BufferId, Subscription>,
+    flush_task: Option<Task<()>>,
+    heartbeat_task: Option<Task<()>>,
+    _subscriptions: [Subscription; 4],
+}
+
+impl UnsavedJournal {
+    pub fn new(
+        fs: Arc<dyn Fs>,
+        buffer_store: Entity<BufferStore>,
+        worktree_store: Entity<WorktreeStore>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let session_dir = paths::unsaved_journal_dir().join(format!(
+            "{}-{}-{}",
+            std::process::id(),
+            unix_millis(SystemTime::now()),
+            NEXT_JOURNAL_ID.fetch_add(1, Ordering::SeqCst)
+        ));
+        let mut this = Self {
+            fs,
+            session_dir,
+            enabled: false,
+            project_roots: Vec::new(),
+            loaded_recoverable_buffers: false,
+            recoverable_buffers: Vec::new(),
+            pending_buffers: HashSet::default(),
+            journaled_buffers: HashSet::default(),
+            buffer_subscriptions: HashMap::default(),
+            flush_task: None,
+            heartbeat_task: None,
+            _subscriptions: [
+                cx.subscribe(&buffer_store, Self::on_buffer_store_event),
+                cx.subscribe(&worktree_store, Self::on_worktree_store_event),
+                cx.observe_global::<SettingsStore>(Self::on_settings_changed),
+                // Unsaved changes are only recoverable when the session ends abnormally.
+                cx.on_release(|this, cx| {
+                    let fs = this.fs.clone();
+                    let session_dir = this.session_dir.clone();
+                    cx.background_spawn(async move {
+                        remove_session_dir(fs.as_ref(), session_dir).await
+                    })
+                    .detach_and_log_err(cx);
+                }),
+            ],
+            buffer_store: buffer_store.clone(),
+            worktree_store,
+        };
+        for buffer in buffer_store.read(cx).buffers().collect::<Vec<_>>() {
+            this.subscribe_to_buffer(&buffer, cx);
+        }
+        this.on_settings_changed(cx);
+        this
+    }
+
+    fn on_settings_changed(&mut self, cx: &mut Context<Self>) {
+        let enabled = ProjectSettings::get_global(cx)
+            .session
+            .restore_unsaved_buffers;
+        if enabled == self.enabled {
+            return;
+        }
+        self.enabled = enabled;
+        if enabled {
+            let dirty_buffers = self
+                .buffer_store
+                .read(cx)
+                .buffers()
+                .filter(|buffer| buffer.read(cx).is_dirty())
+                .map(|buffer| buffer.read(cx).remote_id())
+                .collect::<Vec<_>>();
+            self.pending_buffers.extend(dirty_buffers);
+            if !self.pending_buffers.is_empty() {
+                self.schedule_flush(cx);
+            }
+            self.load_recoverable_buffers(cx);
+        } else {
+            self.pending_buffers.clear();
+            self.journaled_buffers.clear();
+            self.flush_task = None;
+            self.heartbeat_task = None;
+            self.recoverable_buffers.clear();
+            let fs = self.fs.clone();
+            let session_dir = self.session_dir.clone();
+            cx.background_spawn(async move { remove_session_dir(fs.as_ref(), session_dir).await })
+                .detach_and_log_err(cx);
+        }
+    }
+
+    fn on_worktree_store_event(
+        &mut self,
+        worktree_store: Entity<WorktreeStore>,
+        event: &WorktreeStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        if !matches!(
+            event,
+            WorktreeStoreEvent::WorktreeAdded(_) | WorktreeStoreEvent::WorktreeRemoved(..)
+        ) {
+            return;
+        }
+        let mut project_roots = worktree_store
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path().to_path_buf())
+            .collect::<Vec<_>>();
+        project_roots.sort_unstable();
+        if project_roots == self.project_roots {
+            return;
+        }
+        self.project_roots = project_roots;
+        // Record the new roots with the journaled buffers.
+        if !self.journaled_buffers.is_empty() {
+            self.pending_buffers
+                .extend(self.journaled_buffers.iter().copied());
+            self.schedule_flush(cx);
+        }
+        self.load_recoverable_buffers(cx);
+    }
+
+    /// Looks for the buffers journaled for this project by earlier sessions, once the project's
+    /// worktrees are known.
+    fn load_recoverable_buffers(&mut self, cx: &mut Context<Self>) {
+        if !self.enabled || self.loaded_recoverable_buffers || self.project_roots.is_empty() {
+            return;
+        }
+        self.loaded_recoverable_buffers = true;
+        let fs = self.fs.clone();
+        let session_dir = self.session_dir.clone();
+        let project_roots = self.project_roots.clone();
+        cx.spawn(async move |this, cx| {
+            let recoverable_buffers = cx
+                .background_spawn(async move {
+                    load_recoverable_buffers(fs.as_ref(), &session_dir, &project_roots).await
+                })
+                .await?;
+            if !recoverable_buffers.is_empty() {
+                this.update(cx, |this, cx| {
+                    if this.enabled {
+                        this.recoverable_buffers = recoverable_buffers;
+                        cx.emit(UnsavedJournalEvent::RecoverableBuffersFound);
+                    }
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// The unsaved contents of buffers journaled by earlier sessions that ended abnormally, most
+    /// recent first.
+    pub fn recoverable_buffers(&self) -> &[RecoverableBuffer] {
+        &self.recoverable_buffers
+    }
+
+    /// Forgets the unsaved contents of a buffer journaled by an earlier session.
+    pub fn discard(
+        &mut self,
+        recoverable: &RecoverableBuffer,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        self.recoverable_buffers
+            .retain(|buffer| buffer.entry_path != recoverable.entry_path);
+        let fs = self.fs.clone();
+        let entry_path = recoverable.entry_path.clone();
+        let session_dir = entry_path.parent().map(|dir| dir.to_path_buf());
+        let session_is_empty = !self
+            .recoverable_buffers
+            .iter()
+            .any(|buffer| buffer.entry_path.parent() == session_dir.as_deref());
+        cx.background_spawn(async move {
+            match session_dir {
+                Some(session_dir) if session_is_empty => {
+                    remove_session_dir(fs.as_ref(), session_dir).await
+                }
+                _ => {
+                    fs.remove_file(
+                        &entry_path,
+                        RemoveOptions {
+                            ignore_if_not_exists: true,
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                }
+            }
+        })
+    }
+
+    fn on_buffer_store_event(
+        &mut self,
+        _: Entity<BufferStore>,
+        event: &BufferStoreEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            BufferStoreEvent::BufferAdded(buffer) => self.subscribe_to_buffer(buffer, cx),
+            BufferStoreEvent::BufferDropped(buffer_id) => {
+                self.buffer_subscriptions.remove(buffer_id);
+                if self.enabled && self.journaled_buffers.contains(buffer_id) {
+                    self.pending_buffers.insert(*buffer_id);
+                    self.schedule_flush(cx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn subscribe_to_buffer(&mut self, buffer: &Entity<Buffer>, cx: &mut Context<Self>) {
+        let subscription = cx.subscribe(buffer, |this, buffer, event, cx| {
+            if this.enabled
+                && matches!(
+                    event,
+                    BufferEvent::Edited
+                        | BufferEvent::DirtyChanged
+                        | BufferEvent::Saved
+                        | BufferEvent::Reloaded
+                        | BufferEvent::FileHandleChanged
+                )
+            {
+                this.pending_buffers.insert(buffer.read(cx).remote_id());
+                this.schedule_flush(cx);
+            }
+        });
+        self.buffer_subscriptions
+            .insert(buffer.read(cx).remote_id(), subscription);
+    }
+
+    fn schedule_flush(&mut self, cx: &mut Context<Self>) {
+        if self.flush_task.is_some() {
+            return;
+        }
+        self.flush_task = Some(cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor().timer(JOURNAL_DEBOUNCE).await;
+                let Ok(Some((fs, session_dir, project_roots, writes))) =
+                    this.update(cx, |this, cx| {
+                        if this.pending_buffers.is_empty() {
+                            this.flush_task = None;
+                            return None;
+                        }
+                        let writes = this.take_pending_writes(cx);
+                        Some((
+                            this.fs.clone(),
+                            this.session_dir.clone(),
+                            this.project_roots.clone(),
+                            writes,
+                        ))
+                    })
+                else {
+                    break;
+                };
+                cx.background_spawn(async move {
+                    write_journal(fs.as_ref(), session_dir, &project_roots, writes).await
+                })
+                .await
+                .log_err();
+            }
+        }));
+    }
+
+    /// Returns the journal entries to write, or `None` to remove the session's journal once no
+    /// buffers have unsaved changes.
+    fn take_pending_writes(
+        &mut self,
+        cx: &mut Context<Self>,
+    ) -> Option<Vec<(PathBuf, Option<String>)>> {
+        let mut writes = Vec::new();
+        for buffer_id in std::mem::take(&mut self.pending_buffers) {
+            let entry_path = self.session_dir.join(format!("{buffer_id}.json"));
+            let buffer = self
+                .buffer_store
+                .read(cx)
+                .get(buffer_id)
+                .filter(|buffer| buffer.read(cx).is_dirty());
+            let entry = buffer.and_then(|buffer| {
+                let buffer = buffer.read(cx);
+                let abs_path = match buffer.file() {
+                    Some(file) => Some(file.as_local()?.abs_path(cx)),
+                    None => None,
+                };
+                serde_json::to_string(&JournalEntry {
+                    abs_path,
+                    text: buffer.text(),
+                })
+                .log_err()
+            });
+            match entry {
+                Some(entry) => {
+                    self.journaled_buffers.insert(buffer_id);
+                    writes.push((entry_path, Some(entry)));
+                }
+                None => {
+                    if self.journaled_buffers.remove(&buffer_id) {
+                        writes.push((entry_path, None));
+                    }
+                }
+            }
+        }
+
+        if self.journaled_buffers.is_empty() {
+            self.heartbeat_task = None;
+            return None;
+        }
+        if self.heartbeat_task.is_none() {
+            self.heartbeat_task = Some(cx.spawn(async move |this, cx| {
+                loop {
+                    cx.background_executor().timer(HEARTBEAT_INTERVAL).await;
+                    let Ok((fs, session_dir)) =
+                        this.read_with(cx, |this, _| (this.fs.clone(), this.session_dir.clone()))
+                    else {
+                        break;
+                    };
+                    write_heartbeat(fs.as_ref(), &session_dir).await.log_err();
+                }
+            }));
+        }
+        Some(writes)
+    }
+}
+
+async fn write_journal(
+    fs: &dyn Fs,
+    session_dir: PathBuf,
+    project_roots: &[PathBuf],
+    writes: Option<Vec<(PathBuf, Option<String>)>>,
+) -> Result<()> {
+    let Some(writes) = writes else {
+        return remove_session_dir(fs, session_dir).await;
+    };
+    fs.create_dir(&session_dir).await?;
+    fs.atomic_write(
+        session_dir.join(ROOTS_FILE_NAME),
+        serde_json::to_string(project_roots)?,
+    )
+    .await?;
+    for (entry_path, entry) in writes {
+        match entry {
+            Some(entry) => fs.atomic_write(entry_path, entry).await?,
+            None => {
+                fs.remove_file(
+                    &entry_path,
+                    RemoveOptions {
+                        ignore_if_not_exists: true,
+                        ..Default::default()
+                    },
+                )
+                .await?
+            }
+        }
+    }
+    write_heartbeat(fs, &session_dir).await
+}
+
+async fn write_heartbeat(fs: &dyn Fs, session_dir: &Path) -> Result<()> {
+    fs.write(
+        &session_dir.join(HEARTBEAT_FILE_NAME),
+        unix_millis(SystemTime::now()).to_string().as_bytes(),
+    )
+    .await
+}
+
+async fn remove_session_dir(fs: &dyn Fs, session_dir: PathBuf) -> Result<()> {
+    fs.remove_dir(
+        &session_dir,
+        RemoveOptions {
+            recursive: true,
+            ignore_if_not_exists: true,
+        },
+    )
+    .await
+}
+
+/// Loads the entries of the journals of other sessions of the project with the given roots that
+/// are no longer running.
+async fn load_recoverable_buffers(
+    fs: &dyn Fs,
+    own_session_dir: &Path,
+    project_roots: &[PathBuf],
+) -> Result<Vec<RecoverableBuffer>> {
+    let min_heartbeat =
+        unix_millis(SystemTime::now()).saturating_sub(STALE_JOURNAL_AGE.as_millis() as u64);
+    let mut recoverable_buffers = Vec::new();
+    for recoverable in load_journaled_buffers(fs, |session_dir, heartbeat| {
+        session_dir != own_session_dir && heartbeat < min_heartbeat
+    })
+    .await?
+    {
+        let Some(session_dir) = recoverable.entry_path.parent() else {
+            continue;
+        };
+        let session_roots = fs
+            .load(&session_dir.join(ROOTS_FILE_NAME))
+            .await
+            .ok()
+            .and_then(|roots| serde_json::from_str::<Vec<PathBuf>>(&roots).log_err());
+        if session_roots.as_deref() == Some(project_roots) {
+            recoverable_buffers.push(recoverable);
+        }
+    }
+    Ok(recoverable_buffers)
+}
+
+/// Loads the entries of the journals of the sessions of the process that crashed, including the ones that recorded
+/// recently that they're running, along with the ones of other sessions that are no longer running.
+pub async fn load_crashed_buffers(
+    fs: &dyn Fs,
+    crashed_process_id: u32,
+) -> Result<Vec<RecoverableBuffer>> {
+    let own_prefix = format!("{}-", std::process::id());
+    let crashed_prefix = format!("{crashed_process_id}-");
+    let min_heartbeat =
+        unix_millis(SystemTime::now()).saturating_sub(STALE_JOURNAL_AGE.as_millis() as u64);
+    load_journaled_buffers(fs, |session_dir, heartbeat| {
+        let Some(name) = session_dir.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        !name.starts_with(&own_prefix)
+            && (name.starts_with(&crashed_prefix) || heartbeat < min_heartbeat)
+    })
+    .await
+}
+
+/// Loads the entries of the journals of the sessions for which `is_recoverable` returns true,
+/// given the directory of the session and when it last recorded that it's running.
+async fn load_journaled_buffers(
+    fs: &dyn Fs,
+    is_recoverable: impl Fn(&Path, u64) -> bool,
+) -> Result<Vec<RecoverableBuffer>> {
+    let journal_dir = paths::unsaved_journal_dir();
+    if !fs.is_dir(journal_dir).await {
+        return Ok(Vec::new());
+    }
+    let mut session_dirs = fs
+        .read_dir(journal_dir)
+        .await
+        .with_context(|| format!("reading unsaved journal {journal_dir:?}"))?;
+    let mut recoverable_buffers = Vec::new();
+    while let Some(session_dir) = session_dirs.next().await {
+        let Some(session_dir) = session_dir.log_err() else {
+            continue;
+        };
+        let heartbeat = fs
+            .load(&session_dir.join(HEARTBEAT_FILE_NAME))
+            .await
+            .ok()
+            .and_then(|heartbeat| heartbeat.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if !is_recoverable(&session_dir, heartbeat) {
+            continue;
+        }
+
+        let mut entry_paths = fs.read_dir(&session_dir).await?;
+        let mut has_entries = false;
+        while let Some(entry_path) = entry_paths.next().await {
+            let Some(entry_path) = entry_path.log_err() else {
+                continue;
+            };
+            if entry_path
+                .extension()
+                .is_none_or(|extension| extension != "json")
+            {
+                continue;
+            }
+            let Some(entry) = fs
+                .load(&entry_path)
+                .await
+                .log_err()
+                .and_then(|entry| serde_json::from_str::<JournalEntry>(&entry).log_err())
+            else {
+                continue;
+            };
+            has_entries = true;
+            recoverable_buffers.push(RecoverableBuffer {
+                abs_path: entry.abs_path,
+                text: entry.text,
+                journaled_at: heartbeat,
+                entry_path,
+            });
+        }
+        if !has_entries {
+            remove_session_dir(fs, session_dir).await.log_err();
+        }
+    }
+    recoverable_buffers.sort_by_key(|buffer| std::cmp::Reverse(buffer.journaled_at));
+    Ok(recoverable_buffers)
+}
@@ -5,8 +5,8 @@ use std::{
 
 use anyhow::{Context as _, Result, anyhow, bail};
 use collections::HashMap;
-use fs::{Fs, copy_recursive};
-use futures::{FutureExt, future::Shared};
+use fs::{Fs, MemoryFs, copy_recursive};
+use futures::{FutureExt, StreamExt as _, future::Shared};
 use gpui::{
     App, AppContext as _, AsyncApp, Context, Entity, EntityId, EventEmitter, Task, WeakEntity,
 };
@@ -20,15 +20,20 @@ use text::ReplicaId;
 use util::{
     ResultExt,
     paths::{PathStyle, SanitizedPath},
+    post_inc,
     rel_path::RelPath,
 };
 use worktree::{
-    CreatedEntry, Entry, ProjectEntryId, UpdatedEntriesSet, UpdatedGitRepositoriesSet, Worktree,
-    WorktreeId,
+    CreatedEntry, Entry, ProjectEntryId, ScanProgress, UpdatedEntriesSet,
+    UpdatedGitRepositoriesSet, Worktree, WorktreeId,
 };
 
 use crate::ProjectPath;
 
+mod archive;
+
+pub use archive::ArchiveKind;
+
 enum WorktreeStoreState {
     Local {
         fs: Arc<dyn Fs>,
@@ -50,6 +55,11 @@ pub struct WorktreeStore {
     #[allow(clippy::type_complexity)]
     loading_worktrees:
         HashMap<Arc<SanitizedPath>, Shared<Task<Result<Entity<Worktree>, Arc<anyhow::Error>>>>>,
+    /// Read-only worktrees backed by an extracted archive, mapped to the archive's path.
+    archive_worktrees: HashMap<WorktreeId, Arc<Path>>,
+    /// Worktrees that only exist in memory, mapped to the file system holding their contents.
+    scratch_worktrees: HashMap<WorktreeId, Arc<dyn Fs>>,
+    next_scratch_id: usize,
     state: WorktreeStoreState,
 }
 
@@ -63,6 +73,8 @@ pub enum WorktreeStoreEvent {
     WorktreeUpdatedEntries(WorktreeId, UpdatedEntriesSet),
     WorktreeUpdatedGitRepositories(WorktreeId, UpdatedGitRepositoriesSet),
     WorktreeDeletedEntry(WorktreeId, ProjectEntryId),
+    WorktreeScanProgress(WorktreeId, ScanProgress),
+    WorktreeSymlinkEscapedRoot(WorktreeId, Arc<RelPath>, Arc<Path>),
 }
 
 impl EventEmitter<WorktreeStoreEvent> for WorktreeStore {}
@@ -81,6 +93,9 @@ impl WorktreeStore {
         Self {
             next_entry_id: Default::default(),
             loading_worktrees: Default::default(),
+            archive_worktrees: Default::default(),
+            scratch_worktrees: Default::default(),
+            next_scratch_id: 0,
             worktrees: Vec::new(),
             worktrees_reordered: false,
             scanning_enabled: true,
@@ -99,6 +114,9 @@ impl WorktreeStore {
         Self {
             next_entry_id: Default::default(),
             loading_worktrees: Default::default(),
+            archive_worktrees: Default::default(),
+            scratch_worktrees: Default::default(),
+            next_scratch_id: 0,
             downstream_client: None,
             worktrees: Vec::new(),
             worktrees_reordered: false,
@@ -161,6 +179,9 @@ impl WorktreeStore {
         cx: &App,
     ) -> Option<(Entity<Worktree>, Arc<RelPath>)> {
         let abs_path = SanitizedPath::new(abs_path.as_ref());
+        if let Some(worktree) = self.worktree_for_archive(abs_path.as_path(), cx) {
+            return Some((worktree, RelPath::empty().into()));
+        }
         for tree in self.worktrees() {
             let path_style = tree.read(cx).path_style();
             if let Ok(relative_path) = abs_path.as_ref().strip_prefix(tree.read(cx).abs_path())
@@ -194,12 +215,215 @@ impl WorktreeStore {
         let abs_path = abs_path.as_ref();
         if let Some((tree, relative_path)) = self.find_worktree(abs_path, cx) {
             Task::ready(Ok((tree, relative_path)))
+        } else if let Some(kind) = ArchiveKind::from_path(abs_path) {
+            let worktree = self.create_archive_worktree(abs_path, kind, visible, cx);
+            cx.background_spawn(async move { Ok((worktree.await?, RelPath::empty().into())) })
         } else {
             let worktree = self.create_worktree(abs_path, visible, cx);
             cx.background_spawn(async move { Ok((worktree.await?, RelPath::empty().into())) })
         }
     }
 
+    /// Extracts the archive at `archive_path` and exposes its contents as a read-only worktree.
+    ///
+    /// Directories that are merely named like archives are opened as regular worktrees.
+    pub fn create_archive_worktree(
+        &mut self,
+        archive_path: &Path,
+        kind: ArchiveKind,
+        visible: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Worktree>>> {
+        let fs = match &self.state {
+            WorktreeStoreState::Local { fs } => fs.clone(),
+            #[cfg(feature = "collab")]
+            WorktreeStoreState::Remote { .. } => {
+                return Task::ready(Err(anyhow!("remote archive worktrees are not supported")));
+            }
+        };
+        let archive_path: Arc<Path> = archive_path.into();
+        cx.spawn(async move |this, cx| {
+            if fs.is_dir(&archive_path).await {
+                return this
+                    .update(cx, |this, cx| {
+                        this.create_worktree(&archive_path, visible, cx)
+                    })?
+                    .await;
+            }
+            let extracted_path = cx
+                .background_spawn({
+                    let archive_path = archive_path.clone();
+                    async move { archive::extract_archive(fs.as_ref(), &archive_path, kind).await }
+                })
+                .await
+                .with_context(|| format!("extracting archive {archive_path:?}"))?;
+            let worktree = this
+                .update(cx, |this, cx| {
+                    this.create_worktree(&extracted_path, visible, cx)
+                })?
+                .await?;
+            this.update(cx, |this, cx| {
+                this.archive_worktrees
+                    .insert(worktree.read(cx).id(), archive_path);
+            })?;
+            Ok(worktree)
+        })
+    }
+
+    fn worktree_for_archive(&self, archive_path: &Path, cx: &App) -> Option<Entity<Worktree>> {
+        let (worktree_id, _) = self
+            .archive_worktrees
+            .iter()
+            .find(|(_, path)| path.as_ref() == archive_path)?;
+        self.worktree_for_id(*worktree_id, cx)
+    }
+
+    /// Returns the path of the archive backing the given worktree, if it was opened from one.
+    pub fn archive_path(&self, worktree_id: WorktreeId) -> Option<&Arc<Path>> {
+        self.archive_worktrees.get(&worktree_id)
+    }
+
+    /// Whether entries and buffers of the given worktree must not be modified.
+    pub fn is_read_only(&self, worktree_id: WorktreeId) -> bool {
+        self.archive_worktrees.contains_key(&worktree_id)
+    }
+
+    /// Creates an empty worktree that only exists in memory until it is written to disk with
+    /// [`Self::materialize_scratch_worktree`].
+    pub fn create_scratch_worktree(
+        &mut self,
+        name: &str,
+        visible: bool,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Entity<Worktree>>> {
+        #[cfg(feature = "collab")]
+        if let WorktreeStoreState::Remote { .. } = &self.state {
+            return Task::ready(Err(anyhow!("remote scratch worktrees are not supported")));
+        }
+        if !RelPath::unix(name).is_ok_and(|path| path.components().count() == 1) {
+            return Task::ready(Err(anyhow!("invalid scratch worktree name {name:?}")));
+        }
+        // The path is never created on disk, but it must not clash with other worktrees.
+        let root_path: Arc<Path> = paths::scratch_dir()
+            .join(format!(
+                "{}-{}",
+                std::process::id(),
+                post_inc(&mut self.next_scratch_id)
+            ))
+            .join(name)
+            .into();
+        let fs: Arc<dyn Fs> = MemoryFs::new(&root_path);
+        let next_entry_id = self.next_entry_id.clone();
+        let scanning_enabled = self.scanning_enabled;
+        cx.spawn(async move |this, cx| {
+            let worktree = Worktree::local(
+                root_path,
+                visible,
+                fs.clone(),
+                next_entry_id,
+                scanning_enabled,
+                cx,
+            )
+            .await?;
+            this.update(cx, |this, cx| {
+                this.scratch_worktrees.insert(worktree.read(cx).id(), fs);
+                this.add(&worktree, cx);
+            })?;
+            Ok(worktree)
+        })
+    }
+
+    /// Whether the given worktree is a scratch worktree that hasn't been materialized yet.
+    pub fn is_scratch(&self, worktree_id: WorktreeId) -> bool {
+        self.scratch_worktrees.contains_key(&worktree_id)
+    }
+
+    /// Writes the contents of a scratch worktree to `destination` and turns it into a regular
+    /// worktree there. Entries, buffers and entry ids are preserved.
+    pub fn materialize_scratch_worktree(
+        &mut self,
+        worktree_id: WorktreeId,
+        destination: PathBuf,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let fs = match &self.state {
+            WorktreeStoreState::Local { fs } => fs.clone(),
+            #[cfg(feature = "collab")]
+            WorktreeStoreState::Remote { .. } => {
+                return Task::ready(Err(anyhow!("remote scratch worktrees are not supported")));
+            }
+        };
+        let Some(scratch_fs) = self.scratch_worktrees.get(&worktree_id).cloned() else {
+            return Task::ready(Err(anyhow!(
+                "worktree {worktree_id:?} is not a scratch worktree"
+            )));
+        };
+        let Some(worktree) = self.worktree_for_id(worktree_id, cx) else {
+            return Task::ready(Err(anyhow!("no worktree for id {worktree_id:?}")));
+        };
+        let source = worktree.read(cx).abs_path();
+        cx.spawn(async move |this, cx| {
+            if fs.metadata(&destination).await?.is_some() {
+                bail!("{destination:?} already exists");
+            }
+            if let Some(parent) = destination.parent() {
+                fs.create_dir(parent).await?;
+            }
+            copy_between_fs(scratch_fs.as_ref(), &source, fs.as_ref(), &destination)
+                .await
+                .with_context(|| format!("writing scratch worktree to {destination:?}"))?;
+            let fs_case_sensitive = fs.is_case_sensitive().await.unwrap_or(true);
+
+            let new_path = SanitizedPath::new_arc(&destination);
+            worktree.update(cx, |worktree, cx| {
+                if let Some(worktree) = worktree.as_local_mut() {
+                    worktree.update_fs_and_abs_path_and_refresh(
+                        fs,
+                        fs_case_sensitive,
+                        new_path,
+                        cx,
+                    );
+                }
+            })?;
+            this.update(cx, |this, _| {
+                this.scratch_worktrees.remove(&worktree_id);
+            })?;
+            Ok(())
+        })
+    }
+
+    /// Returns the file system that entries can be moved or copied between the given worktrees
+    /// with, which fails when only one of them is backed by a scratch worktree's memory.
+    fn shared_fs(
+        &self,
+        fs: &Arc<dyn Fs>,
+        old_worktree_id: WorktreeId,
+        new_worktree_id: WorktreeId,
+    ) -> Result<Arc<dyn Fs>> {
+        match (
+            self.scratch_worktrees.get(&old_worktree_id),
+            self.scratch_worktrees.get(&new_worktree_id),
+        ) {
+            (None, None) => Ok(fs.clone()),
+            (Some(scratch_fs), Some(_)) if old_worktree_id == new_worktree_id => {
+                Ok(scratch_fs.clone())
+            }
+            _ => Err(anyhow!(
+                "cannot move or copy entries out of or into a scratch worktree"
+            )),
+        }
+    }
+
+    /// Scans the directories leading to the given path before the rest of its worktree,
+    /// if that worktree is still being scanned.
+    pub fn prioritize_path_for_scan(&self, project_path: &ProjectPath, cx: &App) {
+        if let Some(worktree) = self.worktree_for_id(project_path.worktree_id, cx)
+            && let Some(worktree) = worktree.read(cx).as_local()
+        {
+            worktree.prioritize_path_for_scan(project_path.path.clone());
+        }
+    }
+
     pub fn entry_for_id<'a>(&'a self, entry_id: ProjectEntryId, cx: &'a App) -> Option<&'a Entry> {
         self.worktrees()
             .find_map(|worktree| worktree.read(cx).entry_for_id(entry_id))
@@ -239,12 +463,22 @@ impl WorktreeStore {
         let Some(new_worktree) = self.worktree_for_id(new_project_path.worktree_id, cx) else {
             return Task::ready(Err(anyhow!("no such worktree")));
         };
+        if self.is_read_only(new_project_path.worktree_id) {
+            return Task::ready(Err(anyhow!("cannot copy into a read-only worktree")));
+        }
 
         match &self.state {
             WorktreeStoreState::Local { fs } => {
+                let fs = match self.shared_fs(
+                    fs,
+                    old_worktree.read(cx).id(),
+                    new_project_path.worktree_id,
+                ) {
+                    Ok(fs) => fs,
+                    Err(error) => return Task::ready(Err(error)),
+                };
                 let old_abs_path = old_worktree.read(cx).absolutize(&old_entry.path);
                 let new_abs_path = new_worktree.read(cx).absolutize(&new_project_path.path);
-                let fs = fs.clone();
                 let copy = cx.background_spawn(async move {
                     copy_recursive(
                         fs.as_ref(),
@@ -275,6 +509,26 @@ impl WorktreeStore {
         }
     }
 
+    /// Copies the entry next to itself, naming the copy `name copy`, `name copy 2` and so on.
+    pub fn duplicate_entry(
+        &mut self,
+        entry_id: ProjectEntryId,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<Option<Entry>>> {
+        let Some((worktree, entry)) = self.worktree_and_entry_for_id(entry_id, cx) else {
+            return Task::ready(Err(anyhow!("no such entry")));
+        };
+        let worktree = worktree.read(cx);
+        let Some(new_path) = duplicate_entry_path(worktree, entry) else {
+            return Task::ready(Err(anyhow!("cannot duplicate the worktree root")));
+        };
+        let new_project_path = ProjectPath {
+            worktree_id: worktree.id(),
+            path: new_path,
+        };
+        self.copy_entry(entry_id, new_project_path, cx)
+    }
+
     pub fn rename_entry(
         &mut self,
         entry_id: ProjectEntryId,
@@ -290,9 +544,24 @@ impl WorktreeStore {
         let Some(new_worktree) = self.worktree_for_id(new_project_path.worktree_id, cx) else {
             return Task::ready(Err(anyhow!("no such worktree")));
         };
+        if self.is_read_only(old_worktree.read(cx).id())
+            || self.is_read_only(new_project_path.worktree_id)
+        {
+            return Task::ready(Err(anyhow!(
+                "cannot rename entries of a read-only worktree"
+            )));
+        }
 
         match &self.state {
             WorktreeStoreState::Local { fs } => {
+                let fs = match self.shared_fs(
+                    fs,
+                    old_worktree.read(cx).id(),
+                    new_project_path.worktree_id,
+                ) {
+                    Ok(fs) => fs,
+                    Err(error) => return Task::ready(Err(error)),
+                };
                 let abs_old_path = old_worktree.read(cx).absolutize(&old_entry.path);
                 let new_worktree_ref = new_worktree.read(cx);
                 let is_root_entry = new_worktree_ref
@@ -308,7 +577,6 @@ impl WorktreeStore {
                     new_worktree_ref.absolutize(&new_project_path.path)
                 };
 
-                let fs = fs.clone();
                 let case_sensitive = new_worktree
                     .read(cx)
                     .as_local()
@@ -518,6 +786,19 @@ impl WorktreeStore {
                 worktree::Event::DeletedEntry(id) => {
                     cx.emit(WorktreeStoreEvent::WorktreeDeletedEntry(worktree_id, *id))
                 }
+                worktree::Event::ScanProgress(progress) => {
+                    cx.emit(WorktreeStoreEvent::WorktreeScanProgress(
+                        worktree_id,
+                        *progress,
+                    ));
+                }
+                worktree::Event::SymlinkEscapedRoot { path, target } => {
+                    cx.emit(WorktreeStoreEvent::WorktreeSymlinkEscapedRoot(
+                        worktree_id,
+                        path.clone(),
+                        target.clone(),
+                    ));
+                }
             }
         })
         .detach();
@@ -535,6 +816,8 @@ impl WorktreeStore {
     }
 
     pub fn remove_worktree(&mut self, id_to_remove: WorktreeId, cx: &mut Context<Self>) {
+        self.archive_worktrees.remove(&id_to_remove);
+        self.scratch_worktrees.remove(&id_to_remove);
         self.worktrees.retain(|worktree| {
             if let Some(worktree) = worktree.upgrade() {
                 if worktree.read(cx).id() == id_to_remove {
@@ -954,6 +1237,59 @@ impl WorktreeStore {
     }
 }
 
+/// Copies the file or directory at `source` in `source_fs` to `target` in `target_fs`.
+async fn copy_between_fs(
+    source_fs: &dyn Fs,
+    source: &Path,
+    target_fs: &dyn Fs,
+    target: &Path,
+) -> Result<()> {
+    let mut pending = vec![(source.to_path_buf(), target.to_path_buf())];
+    while let Some((source, target)) = pending.pop() {
+        if source_fs.is_dir(&source).await {
+            target_fs.create_dir(&target).await?;
+            let mut children = source_fs.read_dir(&source).await?;
+            while let Some(child) = children.next().await {
+                let child = child?;
+                let file_name = child
+                    .file_name()
+                    .with_context(|| format!("no file name for {child:?}"))?;
+                pending.push((child.clone(), target.join(file_name)));
+            }
+        } else {
+            let content = source_fs.load_bytes(&source).await?;
+            target_fs.write(&target, &content).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the first free path of the form `name copy.ext`, `name copy 2.ext`, ... next to the
+/// given entry.
+fn duplicate_entry_path(snapshot: &worktree::Snapshot, entry: &Entry) -> Option<Arc<RelPath>> {
+    let parent = entry.path.parent()?;
+    let file_name = entry.path.file_name()?;
+    let (stem, extension) = match (entry.is_file(), entry.path.extension()) {
+        (true, Some(extension)) => (entry.path.file_stem()?, Some(extension)),
+        _ => (file_name, None),
+    };
+    (1..).find_map(|ix| {
+        let mut new_file_name = format!("{stem} copy");
+        if ix > 1 {
+            new_file_name.push_str(&format!(" {ix}"));
+        }
+        if let Some(extension) = extension {
+            new_file_name.push('.');
+            new_file_name.push_str(extension);
+        }
+        let new_path = parent.join(RelPath::unix(&new_file_name).ok()?);
+        snapshot
+            .entry_for_path(&new_path)
+            .is_none()
+            .then_some(new_path)
+    })
+}
+
 #[derive(Clone, Debug)]
 enum WorktreeHandle {
     Strong(Entity<Worktree>),
@@ -0,0 +1,312 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context as _, Result, bail};
+use async_compression::futures::bufread::GzipDecoder;
+use async_tar::Archive;
+use async_zip::base::read::stream::ZipFileReader;
+use fs::{Fs, RemoveOptions};
+use futures::{
+    AsyncRead,
+    io::{AllowStdIo, BufReader},
+};
+use sha2::{Digest as _, Sha256};
+
+/// The archive formats that can be opened as read-only worktrees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    /// Gzip-compressed tarballs, including `.crate` files.
+    TarGz,
+}
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if file_name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if file_name.ends_with(".tar.gz")
+            || file_name.ends_with(".tgz")
+            || file_name.ends_with(".crate")
+        {
+            Some(Self::TarGz)
+        } else if file_name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts the archive into a cache directory keyed by the archive's path and modification
+/// time, reusing a previous extraction when the archive hasn't changed since.
+pub(super) async fn extract_archive(
+    fs: &dyn Fs,
+    archive_path: &Path,
+    kind: ArchiveKind,
+) -> Result<PathBuf> {
+    let metadata = fs
+        .metadata(archive_path)
+        .await?
+        .with_context(|| format!("archive {archive_path:?} does not exist"))?;
+    if metadata.is_dir {
+        bail!("{archive_path:?} is a directory");
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(archive_path.to_string_lossy().as_bytes());
+    if let Some((seconds, nanos)) = metadata.mtime.to_seconds_and_nanos_for_persistence() {
+        hasher.update(seconds.to_le_bytes());
+        hasher.update(nanos.to_le_bytes());
+    }
+    let digest = format!("{:x}", hasher.finalize());
+    let archive_name = archive_path
+        .file_name()
+        .context("archive has no file name")?
+        .to_string_lossy();
+    let extraction_dir = paths::archives_dir().join(&digest[..16]);
+    let destination = extraction_dir.join(archive_name.as_ref());
+    if fs.is_dir(&destination).await {
+        return Ok(destination);
+    }
+
+    // Extract into a staging directory first, so that an interrupted extraction
+    // is never mistaken for a complete one.
+    let staging = extraction_dir.join(".staging");
+    fs.remove_dir(
+        &staging,
+        RemoveOptions {
+            recursive: true,
+            ignore_if_not_exists: true,
+        },
+    )
+    .await?;
+    fs.create_dir(&staging).await?;
+
+    let file = AllowStdIo::new(
+        fs.open_sync(archive_path)
+            .await
+            .with_context(|| format!("opening archive {archive_path:?}"))?,
+    );
+    match kind {
+        ArchiveKind::Zip => extract_zip(fs, &staging, file).await?,
+        ArchiveKind::Tar => {
+            futures::pin_mut!(file);
+            fs.extract_tar_file(&staging, Archive::new(file)).await?
+        }
+        ArchiveKind::TarGz => {
+            let file = GzipDecoder::new(BufReader::new(file));
+            futures::pin_mut!(file);
+            fs.extract_tar_file(&staging, Archive::new(file)).await?
+        }
+    }
+    fs.rename(&staging, &destination, Default::default())
+        .await
+        .with_context(|| format!("moving extracted archive into {destination:?}"))?;
+    Ok(destination)
+}
+
+async fn extract_zip(
+    fs: &dyn Fs,
+    destination: &Path,
+    reader: impl AsyncRead + Send + Unpin,
+) -> Result<()> {
+    let mut reader = ZipFileReader::new(BufReader::new(reader));
+    while let Some(mut item) = reader.next_with_entry().await? {
+        let entry_reader = item.reader_mut();
+        let entry = entry_reader.entry();
+        let file_name = entry
+            .filename()
+            .as_str()
+            .context("reading zip entry file name")?;
+        let relative_path = Path::new(file_name);
+        if !relative_path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!("zip entry {file_name:?} is outside of the archive");
+        }
+        let path = destination.join(relative_path);
+        if entry
+            .dir()
+            .with_context(|| format!("reading zip entry metadata for path {path:?}"))?
+        {
+            fs.create_dir(&path).await?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs.create_dir(parent).await?;
+            }
+            futures::pin_mut!(entry_reader);
+            fs.create_file_with(&path, entry_reader)
+                .await
+                .with_context(|| format!("extracting into file {path:?}"))?;
+        }
+        reader = item.skip().await.context("reading next zip entry")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::futures::bufread::GzipEncoder;
+    use async_zip::{Compression, ZipEntryBuilder, base::write::ZipFileWriter};
+    use fs::FakeFs;
+    use futures::AsyncReadExt as _;
+    use gpui::TestAppContext;
+    use serde_json::json;
+    use util::path;
+
+    async fn zip_bytes(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = ZipFileWriter::new(&mut bytes);
+        for (path, contents) in files {
+            let entry = ZipEntryBuilder::new(path.to_string().into(), Compression::Deflate);
+            writer
+                .write_entry_whole(entry, contents.as_bytes())
+                .await
+                .unwrap();
+        }
+        writer.close().await.unwrap();
+        bytes
+    }
+
+    async fn tar_gz_bytes(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut archive = async_tar::Builder::new(&mut bytes);
+        for (path, contents) in files {
+            let mut header = async_tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            archive
+                .append_data(&mut header, path, contents.as_bytes())
+                .await
+                .unwrap();
+        }
+        archive.into_inner().await.unwrap();
+        let mut gzipped_bytes = Vec::new();
+        GzipEncoder::new(BufReader::new(bytes.as_slice()))
+            .read_to_end(&mut gzipped_bytes)
+            .await
+            .unwrap();
+        gzipped_bytes
+    }
+
+    #[gpui::test]
+    async fn test_extract_archive(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            path!("/archives"),
+            json!({
+                "assets.zip": {
+                    "logo.svg": "",
+                },
+            }),
+        )
+        .await;
+        fs.insert_file(
+            path!("/archives/release.zip"),
+            zip_bytes(&[("src/lib.rs", "pub fn f() {}"), ("README.md", "# Release")]).await,
+        )
+        .await;
+        fs.insert_file(
+            path!("/archives/serde-1.0.0.crate"),
+            tar_gz_bytes(&[("serde-1.0.0/Cargo.toml", "[package]")]).await,
+        )
+        .await;
+        fs.insert_file(
+            path!("/archives/evil.zip"),
+            zip_bytes(&[("../escaped.txt", "")]).await,
+        )
+        .await;
+
+        let extracted = extract_archive(
+            fs.as_ref(),
+            Path::new(path!("/archives/release.zip")),
+            ArchiveKind::Zip,
+        )
+        .await
+        .unwrap();
+        assert_eq!(extracted.file_name().unwrap(), "release.zip");
+        assert_eq!(
+            fs.load(&extracted.join("src/lib.rs")).await.unwrap(),
+            "pub fn f() {}"
+        );
+        assert_eq!(
+            fs.load(&extracted.join("README.md")).await.unwrap(),
+            "# Release"
+        );
+        // The extraction is reused while the archive is unchanged.
+        assert_eq!(
+            extract_archive(
+                fs.as_ref(),
+                Path::new(path!("/archives/release.zip")),
+                ArchiveKind::Zip,
+            )
+            .await
+            .unwrap(),
+            extracted
+        );
+
+        let extracted = extract_archive(
+            fs.as_ref(),
+            Path::new(path!("/archives/serde-1.0.0.crate")),
+            ArchiveKind::TarGz,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            fs.load(&extracted.join("serde-1.0.0/Cargo.toml"))
+                .await
+                .unwrap(),
+            "[package]"
+        );
+
+        // Directories named like archives aren't extracted.
+        assert!(
+            extract_archive(
+                fs.as_ref(),
+                Path::new(path!("/archives/assets.zip")),
+                ArchiveKind::Zip,
+            )
+            .await
+            .is_err()
+        );
+        // Entries can't escape the extraction directory.
+        assert!(
+            extract_archive(
+                fs.as_ref(),
+                Path::new(path!("/archives/evil.zip")),
+                ArchiveKind::Zip,
+            )
+            .await
+            .is_err()
+        );
+        assert!(
+            !fs.paths(false)
+                .iter()
+                .any(|path| path.ends_with("escaped.txt"))
+        );
+    }
+
+    #[test]
+    fn test_archive_kind_from_path() {
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/tmp/release.zip")),
+            Some(ArchiveKind::Zip)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/tmp/release.TAR.GZ")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/tmp/serde-1.0.0.crate")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("/tmp/release.tar")),
+            Some(ArchiveKind::Tar)
+        );
+        assert_eq!(ArchiveKind::from_path(Path::new("/tmp/src")), None);
+        assert_eq!(ArchiveKind::from_path(Path::new("/tmp/main.rs")), None);
+    }
+}
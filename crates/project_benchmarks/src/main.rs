@@ -74,6 +74,7 @@ fn main() -> Result<(), anyhow::Error> {
             fs,
             Some(Default::default()),
             false,
+            false,
             cx,
         );
 
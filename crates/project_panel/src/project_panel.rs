@@ -26,12 +26,12 @@ use gpui::{
     UniformListScrollHandle, WeakEntity, Window, actions, anchored, deferred, div, hsla,
     linear_color_stop, linear_gradient, point, px, size, transparent_white, uniform_list,
 };
-use language::DiagnosticSeverity;
+use language::{DiagnosticSeverity, LineEnding};
 use menu::{Confirm, SelectFirst, SelectLast, SelectNext, SelectPrevious};
 use notifications::status_toast::{StatusToast, ToastIcon};
 use project::{
-    Entry, EntryKind, Fs, GitEntry, GitEntryRef, GitTraversal, Project, ProjectEntryId,
-    ProjectPath, Worktree, WorktreeId,
+    Entry, EntryKind, Fs, GitEntry, GitEntryRef, GitTraversal, LineEndingNormalization, Project,
+    ProjectEntryId, ProjectPath, Worktree, WorktreeId,
     git_store::{GitStoreEvent, RepositoryEvent, git_traversal::ChildEntriesGitIter},
     project_settings::GoToDiagnosticSeverityFilter,
 };
@@ -338,6 +338,12 @@ actions!(
         SelectPrevDirectory,
         /// Opens a diff view to compare two marked files.
         CompareMarkedFiles,
+        /// Converts the line endings of the selected files, and of the files in the selected
+        /// directories, to LF.
+        ConvertLineEndingsToLf,
+        /// Converts the line endings of the selected files, and of the files in the selected
+        /// directories, to CRLF.
+        ConvertLineEndingsToCrlf,
     ]
 );
 
@@ -1719,7 +1725,7 @@ impl ProjectPanel {
 
             edited_entry_id = NEW_ENTRY_ID;
             edit_task = self.project.update(cx, |project, cx| {
-                project.create_entry((worktree_id, new_path), is_dir, cx)
+                project.create_entry((worktree_id, new_path), is_dir, None, cx)
             });
         } else {
             let new_path = if let Some(parent) = entry.path.clone().parent() {
@@ -3103,6 +3109,44 @@ impl ProjectPanel {
         }
     }
 
+    fn convert_line_endings_to_lf(
+        &mut self,
+        _: &ConvertLineEndingsToLf,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.convert_line_endings(LineEnding::Unix, cx);
+    }
+
+    fn convert_line_endings_to_crlf(
+        &mut self,
+        _: &ConvertLineEndingsToCrlf,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.convert_line_endings(LineEnding::Windows, cx);
+    }
+
+    fn convert_line_endings(&mut self, line_ending: LineEnding, cx: &mut Context<Self>) {
+        let paths = self
+            .disjoint_entries(cx)
+            .into_iter()
+            .filter_map(|entry| self.project.read(cx).path_for_entry(entry.entry_id, cx))
+            .collect::<Vec<_>>();
+        self.project
+            .update(cx, |project, cx| {
+                project.normalize_line_endings(
+                    paths,
+                    LineEndingNormalization {
+                        line_ending: Some(line_ending),
+                        final_newline: None,
+                    },
+                    cx,
+                )
+            })
+            .detach_and_log_err(cx);
+    }
+
     fn open_system(&mut self, _: &OpenWithSystem, _: &mut Window, cx: &mut Context<Self>) {
         if let Some((worktree, entry)) = self.selected_entry(cx) {
             let abs_path = worktree.absolutize(&entry.path);
@@ -5722,6 +5766,8 @@ impl Render for ProjectPanel {
                         .on_action(cx.listener(Self::paste))
                         .on_action(cx.listener(Self::duplicate))
                         .on_action(cx.listener(Self::restore_file))
+                        .on_action(cx.listener(Self::convert_line_endings_to_lf))
+                        .on_action(cx.listener(Self::convert_line_endings_to_crlf))
                         .when(!project.is_remote(), |el| {
                             el.on_action(cx.listener(Self::trash))
                         })
@@ -26,7 +26,7 @@ use language::{Buffer, Language};
 use menu::Confirm;
 use project::{
     Project, ProjectPath, SearchResults,
-    search::{SearchInputKind, SearchQuery},
+    search::{RegexBudgetError, SearchInputKind, SearchQuery},
     search_history::SearchHistoryCursor,
 };
 use settings::Settings;
@@ -204,6 +204,7 @@ pub struct ProjectSearch {
     search_id: usize,
     no_results: Option<bool>,
     limit_reached: bool,
+    budget_error: Option<RegexBudgetError>,
     search_history_cursor: SearchHistoryCursor,
     search_included_history_cursor: SearchHistoryCursor,
     search_excluded_history_cursor: SearchHistoryCursor,
@@ -263,6 +264,7 @@ impl ProjectSearch {
             search_id: 0,
             no_results: None,
             limit_reached: false,
+            budget_error: None,
             search_history_cursor: Default::default(),
             search_included_history_cursor: Default::default(),
             search_excluded_history_cursor: Default::default(),
@@ -282,6 +284,7 @@ impl ProjectSearch {
             search_id: self.search_id,
             no_results: self.no_results,
             limit_reached: self.limit_reached,
+            budget_error: self.budget_error,
             search_history_cursor: self.search_history_cursor.clone(),
             search_included_history_cursor: self.search_included_history_cursor.clone(),
             search_excluded_history_cursor: self.search_excluded_history_cursor.clone(),
@@ -304,20 +307,29 @@ impl ProjectSearch {
 
     fn search(&mut self, query: SearchQuery, cx: &mut Context<Self>) {
         let search = self.project.update(cx, |project, cx| {
-            project
-                .search_history_mut(SearchInputKind::Query)
-                .add(&mut self.search_history_cursor, query.as_str().to_string());
+            project.add_to_search_history(
+                SearchInputKind::Query,
+                &mut self.search_history_cursor,
+                query.as_str().to_string(),
+                cx,
+            );
             let included = query.as_inner().files_to_include().sources().join(",");
             if !included.is_empty() {
-                project
-                    .search_history_mut(SearchInputKind::Include)
-                    .add(&mut self.search_included_history_cursor, included);
+                project.add_to_search_history(
+                    SearchInputKind::Include,
+                    &mut self.search_included_history_cursor,
+                    included,
+                    cx,
+                );
             }
             let excluded = query.as_inner().files_to_exclude().sources().join(",");
             if !excluded.is_empty() {
-                project
-                    .search_history_mut(SearchInputKind::Exclude)
-                    .add(&mut self.search_excluded_history_cursor, excluded);
+                project.add_to_search_history(
+                    SearchInputKind::Exclude,
+                    &mut self.search_excluded_history_cursor,
+                    excluded,
+                    cx,
+                );
             }
             project.search(query.clone(), cx)
         });
@@ -337,30 +349,39 @@ impl ProjectSearch {
                         .update(cx, |excerpts, cx| excerpts.clear(cx));
                     project_search.no_results = Some(true);
                     project_search.limit_reached = false;
+                    project_search.budget_error = None;
                 })
                 .ok()?;
 
             let mut limit_reached = false;
+            let mut budget_error = None;
             while let Some(results) = matches.next().await {
-                let (buffers_with_ranges, has_reached_limit) = cx
+                let (buffers_with_ranges, has_reached_limit, has_exceeded_budget) = cx
                     .background_executor()
                     .spawn(async move {
                         let mut limit_reached = false;
+                        let mut budget_error = None;
                         let mut buffers_with_ranges = Vec::with_capacity(results.len());
                         for result in results {
                             match result {
-                                project::search::SearchResult::Buffer { buffer, ranges } => {
+                                project::search::SearchResult::Buffer {
+                                    buffer, ranges, ..
+                                } => {
                                     buffers_with_ranges.push((buffer, ranges));
                                 }
-                                project::search::SearchResult::LimitReached => {
+                                project::search::SearchResult::LimitReached { .. } => {
                                     limit_reached = true;
                                 }
+                                project::search::SearchResult::BudgetExceeded { error, .. } => {
+                                    budget_error = Some(error);
+                                }
                             }
                         }
-                        (buffers_with_ranges, limit_reached)
+                        (buffers_with_ranges, limit_reached, budget_error)
                     })
                     .await;
                 limit_reached |= has_reached_limit;
+                budget_error = budget_error.or(has_exceeded_budget);
                 let mut new_ranges = project_search
                     .update(cx, |project_search, cx| {
                         project_search.excerpts.update(cx, |excerpts, cx| {
@@ -398,6 +419,7 @@ impl ProjectSearch {
                         project_search.no_results = Some(false);
                     }
                     project_search.limit_reached = limit_reached;
+                    project_search.budget_error = budget_error;
                     project_search.pending_search.take();
                     cx.notify();
                 })
@@ -445,7 +467,16 @@ impl Render for ProjectSearchView {
                 .child(Label::new(heading_text).size(LabelSize::Large));
 
             let page_content: Option<AnyElement> = if let Some(no_results) = model.no_results {
-                if model.pending_search.is_none() && no_results {
+                if model.pending_search.is_none()
+                    && let Some(error) = model.budget_error
+                {
+                    Some(
+                        Label::new(format!("Some files could not be searched: {error}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Error)
+                            .into_any_element(),
+                    )
+                } else if model.pending_search.is_none() && no_results {
                     Some(
                         Label::new("No results found in this project for the provided query")
                             .size(LabelSize::Small)
@@ -2049,6 +2080,7 @@ impl Render for ProjectSearchBar {
         let theme_colors = cx.theme().colors();
         let project_search = search.entity.read(cx);
         let limit_reached = project_search.limit_reached;
+        let budget_error = project_search.budget_error;
 
         let color_override = match (
             &project_search.pending_search,
@@ -2057,6 +2089,7 @@ impl Render for ProjectSearchBar {
             &project_search.last_search_query_text,
         ) {
             (None, Some(true), Some(q), Some(p)) if q.as_str() == p => Some(Color::Error),
+            (None, _, _, _) if budget_error.is_some() => Some(Color::Error),
             _ => None,
         };
 
@@ -2148,10 +2181,18 @@ impl Render for ProjectSearchBar {
                             Color::Disabled
                         },
                     ))
-                    .when(limit_reached, |el| {
-                        el.tooltip(Tooltip::text(
-                            "Search limits reached.\nTry narrowing your search.",
-                        ))
+                    .map(|el| {
+                        if let Some(error) = budget_error {
+                            el.tooltip(Tooltip::text(format!(
+                                "Some files could not be searched:\n{error}."
+                            )))
+                        } else if limit_reached {
+                            el.tooltip(Tooltip::text(
+                                "Search limits reached.\nTry narrowing your search.",
+                            ))
+                        } else {
+                            el
+                        }
                     }),
             );
 
@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::time::Duration;
 
+use anyhow::{Context as _, Result};
 use db::kvp::KEY_VALUE_STORE;
 use gpui::{App, AppContext as _, Context, Subscription, Task, WindowId};
+use serde::{Deserialize, Serialize};
 use util::ResultExt;
 
 pub struct Session {
@@ -12,6 +16,14 @@ pub struct Session {
 
 const SESSION_ID_KEY: &str = "session_id";
 const SESSION_WINDOW_STACK_KEY: &str = "session_window_stack";
+const NAMED_SESSIONS_KEY: &str = "named_sessions";
+
+/// The workspaces of a session saved under a name, to restore instead of the last session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedSession {
+    /// The root paths of the local workspaces of the session, ordered front-to-back.
+    pub workspaces: Vec<Vec<PathBuf>>,
+}
 
 impl Session {
     pub async fn new(session_id: String) -> Self {
@@ -56,6 +68,7 @@ impl Session {
 
 pub struct AppSession {
     session: Session,
+    named_session_to_restore: Option<String>,
     _serialization_task: Task<()>,
     _subscriptions: Vec<Subscription>,
 }
@@ -82,6 +95,7 @@ impl AppSession {
 
         Self {
             session,
+            named_session_to_restore: None,
             _subscriptions,
             _serialization_task,
         }
@@ -106,6 +120,57 @@ impl AppSession {
     pub fn last_session_window_stack(&self) -> Option<Vec<WindowId>> {
         self.session.old_window_ids.clone()
     }
+
+    /// The named session to restore on startup instead of the last session, if any.
+    pub fn named_session_to_restore(&self) -> Option<&str> {
+        self.named_session_to_restore.as_deref()
+    }
+
+    pub fn set_named_session_to_restore(&mut self, name: Option<String>) {
+        self.named_session_to_restore = name;
+    }
+}
+
+/// Returns the saved named sessions, by name.
+pub fn named_sessions() -> Result<BTreeMap<String, NamedSession>> {
+    let Some(json) = KEY_VALUE_STORE.read_kvp(NAMED_SESSIONS_KEY)? else {
+        return Ok(BTreeMap::default());
+    };
+    serde_json::from_str(&json).context("invalid named sessions")
+}
+
+/// Saves `session` under `name`, replacing the session saved under it before.
+pub async fn save_named_session(name: String, session: NamedSession) -> Result<()> {
+    update_named_sessions(move |sessions| {
+        sessions.insert(name, session);
+        true
+    })
+    .await?;
+    Ok(())
+}
+
+/// Deletes the session saved under `name`, returning whether there was one.
+pub async fn delete_named_session(name: String) -> Result<bool> {
+    update_named_sessions(move |sessions| sessions.remove(&name).is_some()).await
+}
+
+/// Updates the named sessions in a single write, so that sessions saved or deleted at the same time aren't lost.
+/// `update` returns whether it changed the sessions.
+async fn update_named_sessions(
+    update: impl 'static + Send + FnOnce(&mut BTreeMap<String, NamedSession>) -> bool,
+) -> Result<bool> {
+    KEY_VALUE_STORE
+        .update_kvp(NAMED_SESSIONS_KEY.to_string(), move |json| {
+            let mut sessions: BTreeMap<String, NamedSession> = match json {
+                Some(json) => serde_json::from_str(&json).context("invalid named sessions")?,
+                None => BTreeMap::default(),
+            };
+            if !update(&mut sessions) {
+                return Ok(None);
+            }
+            Ok(Some(serde_json::to_string(&sessions)?))
+        })
+        .await
 }
 
 fn window_stack(cx: &App) -> Option<Vec<u64>> {
@@ -125,3 +190,39 @@ async fn store_window_stack(windows: &[u64]) {
             .log_err();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[gpui::test]
+    async fn test_named_sessions() {
+        let session = |path: &str| NamedSession {
+            workspaces: vec![vec![PathBuf::from(path)]],
+        };
+
+        save_named_session("work".to_string(), session("/work"))
+            .await
+            .unwrap();
+        save_named_session("home".to_string(), session("/home"))
+            .await
+            .unwrap();
+        save_named_session("work".to_string(), session("/work/api"))
+            .await
+            .unwrap();
+        assert_eq!(
+            named_sessions().unwrap(),
+            BTreeMap::from_iter([
+                ("home".to_string(), session("/home")),
+                ("work".to_string(), session("/work/api")),
+            ])
+        );
+
+        assert!(delete_named_session("home".to_string()).await.unwrap());
+        assert!(!delete_named_session("home".to_string()).await.unwrap());
+        assert_eq!(
+            named_sessions().unwrap(),
+            BTreeMap::from_iter([("work".to_string(), session("/work/api"))])
+        );
+    }
+}
@@ -400,6 +400,11 @@ pub struct DebuggerSettingsContent {
     ///
     /// Default: true
     pub format_dap_log_messages: Option<bool>,
+    /// Whether to record the messages exchanged with debug adapters to disk, so that sessions can be replayed.
+    /// Recording doesn't depend on `log_dap_communications`, and values that look like secrets are redacted.
+    ///
+    /// Default: false
+    pub record_dap_sessions: Option<bool>,
     /// The dock position of the debug panel
     ///
     /// Default: Bottom
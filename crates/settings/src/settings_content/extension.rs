@@ -20,6 +20,10 @@ pub struct ExtensionSettingsContent {
     pub auto_update_extensions: HashMap<Arc<str>, bool>,
     /// The capabilities granted to extensions.
     pub granted_extension_capabilities: Option<Vec<ExtensionCapabilityContent>>,
+    /// The capabilities granted to individual extensions, by extension ID, in addition to those granted to all
+    /// extensions. Capabilities allowed when Vector asks for them are remembered here.
+    #[serde(default)]
+    pub extension_capability_grants: HashMap<Arc<str>, Vec<ExtensionCapabilityContent>>,
 }
 
 /// A capability for an extension.
@@ -42,4 +46,15 @@ pub enum ExtensionCapabilityContent {
     NpmInstallPackage {
         package: String,
     },
+    #[serde(rename = "network:fetch")]
+    Network {
+        /// The host to send requests to. Use `*` for any host, or `*.example.com` for the subdomains of a host.
+        host: String,
+    },
+    #[serde(rename = "fs:read")]
+    ReadFile {
+        /// The components of the paths of the files to read, relative to the root of a worktree. Use `*` for a
+        /// single wildcard component. If the last element is `**`, then any trailing components are allowed.
+        path: Vec<String>,
+    },
 }
@@ -311,6 +311,11 @@ pub struct LanguageSettingsContent {
     ///
     /// Default: true
     pub ensure_final_newline_on_save: Option<bool>,
+    /// Whether or not buffers in this language are saved automatically, according to
+    /// the `autosave` setting.
+    ///
+    /// Default: true
+    pub autosave: Option<bool>,
     /// How to perform a buffer format.
     ///
     /// Default: auto
@@ -436,6 +441,12 @@ pub struct LanguageSettingsContent {
     ///
     /// Default: []
     pub debuggers: Option<Vec<String>>,
+    /// The template for the contents of new files of this language, used when creating an
+    /// entry with the language template. `${filename}`, `${module}` and `${date}` are
+    /// replaced with the new file's name, its module name and the current date.
+    ///
+    /// Default: null
+    pub file_template: Option<String>,
     /// Whether to enable word diff highlighting in the editor.
     ///
     /// When enabled, changed words within modified lines are highlighted
@@ -57,6 +57,49 @@ pub struct ProjectSettingsContent {
     /// Configuration for how direnv configuration should be loaded
     pub load_direnv: Option<DirenvSettings>,
 
+    /// Configuration for running project processes inside a Dev Container.
+    pub dev_container: Option<DevContainerSettingsContent>,
+
+    /// Named templates for the contents of new files, which can be selected when creating an
+    /// entry. `${filename}`, `${module}` and `${date}` are replaced with the new file's name,
+    /// its module name and the current date.
+    ///
+    /// Default: {}
+    #[serde(default)]
+    pub file_templates: HashMap<String, String>,
+
+    /// Whether to maintain an on-disk trigram index of the files in local worktrees, which
+    /// project searches consult to skip files that cannot contain the query.
+    ///
+    /// Default: false
+    pub search_index: Option<bool>,
+
+    /// Whether to collect comments tagged with one of the `todo_tags` across the project.
+    ///
+    /// Default: false
+    pub todo_scanner: Option<bool>,
+
+    /// The tags that mark comments collected by the TODO scanner.
+    ///
+    /// Default: ["TODO", "FIXME", "HACK"]
+    pub todo_tags: Option<Vec<String>>,
+
+    /// Configuration for the local history of the versions of edited files.
+    pub local_history: Option<LocalHistorySettingsContent>,
+
+    /// The amount of memory, in megabytes, that the text of open buffers may take up before
+    /// clean buffers that aren't visible are evicted: their tabs are closed, and can be reopened
+    /// from the history. 0 disables eviction.
+    ///
+    /// Default: 1024
+    pub buffer_memory_budget_mb: Option<u64>,
+
+    /// The size, in megabytes, from which files are opened read-only, showing a window of their
+    /// contents at a time, rather than loaded into buffers.
+    ///
+    /// Default: 256
+    pub large_file_threshold_mb: Option<u64>,
+
     /// Settings for slash commands.
     pub slash_commands: Option<SlashCommandSettings>,
 
@@ -103,6 +146,15 @@ pub struct WorktreeSettingsContent {
     /// ]
     pub file_scan_inclusions: Option<Vec<String>>,
 
+    /// Directories matching these globs are still scanned, but are not watched for changes.
+    /// Useful for large generated directories that would otherwise exhaust the file watcher
+    /// limit. Changes to this setting are applied without rescanning the worktree.
+    ///
+    /// Only takes effect on platforms that watch each directory individually (Linux).
+    ///
+    /// Default: ["**/node_modules", "**/.venv"]
+    pub watch_exclusions: Option<Vec<String>>,
+
     /// Treat the files matching these globs as `.env` files.
     /// Default: ["**/.env*", "**/*.pem", "**/*.key", "**/*.cert", "**/*.crt", "**/secrets.yml"]
     pub private_files: Option<ExtendingVec<String>>,
@@ -110,6 +162,31 @@ pub struct WorktreeSettingsContent {
     /// Treat the files matching these globs as hidden files. You can hide hidden files in the project panel.
     /// Default: ["**/.*"]
     pub hidden_files: Option<Vec<String>>,
+
+    /// How symbolic links within the worktree are scanned.
+    ///
+    /// Default: follow
+    pub symlinks: Option<SymlinkPolicy>,
+
+    /// Never autosave the buffers of files matching these globs.
+    ///
+    /// Default: []
+    pub autosave_exclusions: Option<Vec<String>>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, MergeFrom,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    /// Scan the targets of symbolic links as if they were within the worktree, skipping the links
+    /// that point to one of their own ancestors.
+    #[default]
+    Follow,
+    /// Show symbolic links as entries of the worktree without scanning their targets.
+    ShowAsLink,
+    /// Leave symbolic links out of the worktree.
+    Ignore,
 }
 
 #[with_fallible_options]
@@ -542,6 +619,44 @@ pub struct NodeBinarySettings {
     pub ignore_system_version: Option<bool>,
 }
 
+#[with_fallible_options]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, MergeFrom)]
+pub struct DevContainerSettingsContent {
+    /// Whether to start the Dev Container described by `.devcontainer/devcontainer.json`
+    /// and run terminals and tasks of the worktree inside of it.
+    ///
+    /// Default: false
+    pub enabled: Option<bool>,
+    /// The path to the `devcontainer` CLI. When unset, the CLI is looked up in `$PATH`
+    /// and in Vector's data directory.
+    ///
+    /// Default: null
+    pub cli_path: Option<String>,
+}
+
+#[with_fallible_options]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, MergeFrom)]
+pub struct LocalHistorySettingsContent {
+    /// Whether to snapshot the contents of local files when they're saved, and periodically
+    /// while they have unsaved changes, so that earlier versions can be restored.
+    ///
+    /// Default: true
+    pub enabled: Option<bool>,
+    /// How often to snapshot files with unsaved changes, in seconds. 0 disables periodic
+    /// snapshots. Project settings can only disable them.
+    ///
+    /// Default: 300
+    pub snapshot_interval_secs: Option<u64>,
+    /// The number of versions kept for each file, beyond which the oldest ones are deleted.
+    ///
+    /// Default: 50
+    pub max_versions_per_file: Option<usize>,
+    /// The number of days versions are kept for.
+    ///
+    /// Default: 30
+    pub max_age_days: Option<u64>,
+}
+
 #[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize, JsonSchema, MergeFrom)]
 #[serde(rename_all = "snake_case")]
 pub enum DirenvSettings {
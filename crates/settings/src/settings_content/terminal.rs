@@ -29,6 +29,23 @@ pub struct ProjectTerminalSettingsContent {
     ///
     /// Default: on
     pub detect_venv: Option<VenvSettings>,
+    /// Whether to set up the shell of the terminal to report its working directory, and where its prompts and
+    /// commands start and end, so that Vector can navigate between commands and resolve paths relative to the
+    /// shell's working directory. Supported for bash, zsh, fish and PowerShell.
+    ///
+    /// Default: true
+    pub shell_integration: Option<bool>,
+    /// Named environments that terminals can be started with, on top of `env`:
+    /// variables to set, directories to prepend to `PATH`, and commands to run
+    /// in the shell once it starts, e.g. to activate a virtual environment or a
+    /// Node.js version.
+    ///
+    /// Default: {}
+    pub env_profiles: Option<HashMap<String, TerminalEnvProfileContent>>,
+    /// The env profile, from `env_profiles`, that new terminals are started with.
+    ///
+    /// Default: null
+    pub default_env_profile: Option<String>,
     /// Regexes used to identify paths for hyperlink navigation.
     ///
     /// Default: [
@@ -234,6 +251,26 @@ pub enum WorkingDirectory {
     Always { directory: String },
 }
 
+#[with_fallible_options]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq, Eq, Default)]
+pub struct TerminalEnvProfileContent {
+    /// Variables to set in the terminal's environment.
+    ///
+    /// Default: {}
+    pub env: Option<HashMap<String, String>>,
+    /// Directories to prepend to the terminal's `PATH`, in order. Relative
+    /// directories are relative to the terminal's working directory, and `~`
+    /// is expanded to the home directory.
+    ///
+    /// Default: []
+    pub path_prepend: Option<Vec<String>>,
+    /// Commands to run in the shell once it starts, such as
+    /// `source .venv/bin/activate` or `nvm use`.
+    ///
+    /// Default: []
+    pub activation_script: Option<Vec<String>>,
+}
+
 #[with_fallible_options]
 #[derive(
     Clone, Copy, Debug, Serialize, Deserialize, JsonSchema, MergeFrom, PartialEq, Eq, Default,
@@ -91,6 +91,11 @@ pub struct WorkspaceSettingsContent {
     ///
     /// Default: auto (nothing on macOS, "app quit" otherwise)
     pub on_last_window_closed: Option<OnLastWindowClosed>,
+    /// How many minutes Vector keeps running in the background without windows before quitting, when
+    /// `on_last_window_closed` is `run_in_background`.
+    ///
+    /// Default: 60
+    pub background_idle_timeout_minutes: Option<u64>,
     /// Whether to resize all the panels in a dock when resizing the dock.
     ///
     /// Default: ["left"]
@@ -543,15 +548,22 @@ pub enum OnLastWindowClosed {
     PlatformDefault,
     /// Quit the application the last window is closed
     QuitApp,
+    /// Keep the application running in the background, so that the next window opens instantly,
+    /// until it has been in the background for an hour
+    RunInBackground,
 }
 
 impl OnLastWindowClosed {
     pub fn is_quit_app(&self) -> bool {
         match self {
-            OnLastWindowClosed::PlatformDefault => false,
+            OnLastWindowClosed::PlatformDefault | OnLastWindowClosed::RunInBackground => false,
             OnLastWindowClosed::QuitApp => true,
         }
     }
+
+    pub fn is_run_in_background(&self) -> bool {
+        matches!(self, OnLastWindowClosed::RunInBackground)
+    }
 }
 
 #[with_fallible_options]
@@ -153,6 +153,7 @@ pub struct SettingsStore {
     merged_settings: Rc<SettingsContent>,
 
     local_settings: BTreeMap<(WorktreeId, Arc<RelPath>), SettingsContent>,
+    worktree_overlays: BTreeMap<WorktreeId, SettingsContent>,
     raw_editorconfig_settings: BTreeMap<(WorktreeId, Arc<RelPath>), (String, Option<Editorconfig>)>,
 
     _setting_file_updates: Task<()>,
@@ -277,6 +278,7 @@ impl SettingsStore {
 
             merged_settings: default_settings,
             local_settings: BTreeMap::default(),
+            worktree_overlays: BTreeMap::default(),
             raw_editorconfig_settings: BTreeMap::default(),
             setting_file_updates_tx,
             _setting_file_updates: cx.spawn(async move |cx| {
@@ -956,6 +958,67 @@ impl SettingsStore {
         Ok(())
     }
 
+    /// Sets the settings overlay of a worktree, which applies to all of its files.
+    ///
+    /// Settings are merged in this order, later ones taking precedence: default, extension,
+    /// global, user, server, the worktree's overlay, and finally its local settings files, from
+    /// the worktree root to the deepest directory.
+    pub fn set_worktree_overlay(
+        &mut self,
+        root_id: WorktreeId,
+        settings_content: Option<&str>,
+        cx: &mut App,
+    ) -> std::result::Result<(), InvalidSettingsError> {
+        match settings_content
+            .map(|content| content.trim())
+            .filter(|content| !content.is_empty())
+        {
+            None => {
+                if self.worktree_overlays.remove(&root_id).is_none() {
+                    return Ok(());
+                }
+                for setting_value in self.setting_values.values_mut() {
+                    setting_value.clear_local_values(root_id);
+                }
+            }
+            Some(settings_content) => {
+                let (new_settings, parse_status) =
+                    fallible_options::parse_json::<ProjectSettingsContent>(settings_content);
+                if let ParseStatus::Failed { error } = parse_status {
+                    return Err(InvalidSettingsError::WorktreeOverlay {
+                        worktree_id: root_id,
+                        message: error,
+                    });
+                }
+                let Some(new_settings) = new_settings else {
+                    return Ok(());
+                };
+                if self
+                    .worktree_overlays
+                    .get(&root_id)
+                    .is_some_and(|overlay| overlay.project == new_settings)
+                {
+                    return Ok(());
+                }
+                self.worktree_overlays.insert(
+                    root_id,
+                    SettingsContent {
+                        project: new_settings,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        self.recompute_values(Some((root_id, RelPath::empty())), cx);
+        Ok(())
+    }
+
+    pub fn worktree_overlay(&self, root_id: WorktreeId) -> Option<&ProjectSettingsContent> {
+        self.worktree_overlays
+            .get(&root_id)
+            .map(|overlay| &overlay.project)
+    }
+
     pub fn set_extension_settings(
         &mut self,
         content: ExtensionsSettingsContent,
@@ -976,6 +1039,7 @@ impl SettingsStore {
     pub fn clear_local_settings(&mut self, root_id: WorktreeId, cx: &mut App) -> Result<()> {
         self.local_settings
             .retain(|(worktree_id, _), _| worktree_id != &root_id);
+        self.worktree_overlays.remove(&root_id);
         self.raw_editorconfig_settings
             .retain(|(worktree_id, _), _| worktree_id != &root_id);
         for setting_value in self.setting_values.values_mut() {
@@ -1140,7 +1204,21 @@ impl SettingsStore {
             }
         }
 
-        for ((root_id, directory_path), local_settings) in &self.local_settings {
+        // A worktree's overlay applies at its root, before its local settings files.
+        let root_path: Arc<RelPath> = RelPath::empty().into();
+        let mut settings_by_path = self
+            .worktree_overlays
+            .iter()
+            .map(|(root_id, overlay)| ((*root_id, root_path.clone()), overlay))
+            .chain(
+                self.local_settings
+                    .iter()
+                    .map(|((root_id, path), content)| ((*root_id, path.clone()), content)),
+            )
+            .collect::<Vec<_>>();
+        settings_by_path.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for ((root_id, directory_path), local_settings) in &settings_by_path {
             // Build a stack of all of the local values for that setting.
             while let Some(prev_entry) = paths_stack.last() {
                 if let Some((prev_root_id, prev_path)) = prev_entry
@@ -1300,19 +1378,42 @@ impl SettingsParseResult {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum InvalidSettingsError {
-    LocalSettings { path: Arc<RelPath>, message: String },
-    UserSettings { message: String },
-    ServerSettings { message: String },
-    DefaultSettings { message: String },
-    Editorconfig { path: Arc<RelPath>, message: String },
-    Tasks { path: PathBuf, message: String },
-    Debug { path: PathBuf, message: String },
+    LocalSettings {
+        path: Arc<RelPath>,
+        message: String,
+    },
+    WorktreeOverlay {
+        worktree_id: WorktreeId,
+        message: String,
+    },
+    UserSettings {
+        message: String,
+    },
+    ServerSettings {
+        message: String,
+    },
+    DefaultSettings {
+        message: String,
+    },
+    Editorconfig {
+        path: Arc<RelPath>,
+        message: String,
+    },
+    Tasks {
+        path: PathBuf,
+        message: String,
+    },
+    Debug {
+        path: PathBuf,
+        message: String,
+    },
 }
 
 impl std::fmt::Display for InvalidSettingsError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             InvalidSettingsError::LocalSettings { message, .. }
+            | InvalidSettingsError::WorktreeOverlay { message, .. }
             | InvalidSettingsError::UserSettings { message }
             | InvalidSettingsError::ServerSettings { message }
             | InvalidSettingsError::DefaultSettings { message }
@@ -1574,6 +1675,72 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_worktree_overlays(cx: &mut App) {
+        let mut store = SettingsStore::new(cx, &default_settings());
+        store.register_setting::<DefaultLanguageSettings>();
+        let worktree_id = WorktreeId::from_usize(1);
+        let location = |path| {
+            Some(SettingsLocation {
+                worktree_id,
+                path: rel_path(path),
+            })
+        };
+
+        store.set_user_settings(r#"{ "tab_size": 3 }"#, cx).unwrap();
+        store
+            .set_worktree_overlay(
+                worktree_id,
+                Some(r#"{ "tab_size": 2, "preferred_line_length": 100 }"#),
+                cx,
+            )
+            .unwrap();
+        store
+            .set_local_settings(
+                worktree_id,
+                rel_path("sub").into(),
+                LocalSettingsKind::Settings,
+                Some(r#"{ "tab_size": 8 }"#),
+                cx,
+            )
+            .unwrap();
+
+        assert_eq!(
+            store.get::<DefaultLanguageSettings>(location("file")),
+            &DefaultLanguageSettings {
+                preferred_line_length: 100,
+                tab_size: 2.try_into().unwrap(),
+            }
+        );
+        assert_eq!(
+            store.get::<DefaultLanguageSettings>(location("sub/file")),
+            &DefaultLanguageSettings {
+                preferred_line_length: 100,
+                tab_size: 8.try_into().unwrap(),
+            }
+        );
+
+        assert!(
+            store
+                .set_worktree_overlay(worktree_id, Some("{ invalid"), cx)
+                .is_err()
+        );
+        store.set_worktree_overlay(worktree_id, None, cx).unwrap();
+        assert_eq!(
+            store.get::<DefaultLanguageSettings>(location("file")),
+            &DefaultLanguageSettings {
+                preferred_line_length: 80,
+                tab_size: 3.try_into().unwrap(),
+            }
+        );
+        assert_eq!(
+            store
+                .get::<DefaultLanguageSettings>(location("sub/file"))
+                .tab_size,
+            8.try_into().unwrap()
+        );
+    }
+
     #[gpui::test]
     fn test_setting_store_assign_json_before_register(cx: &mut App) {
         let mut store = SettingsStore::new(cx, &test_settings());
@@ -404,6 +404,14 @@ impl VsCodeSettings {
             dap: Default::default(),
             context_servers: self.context_servers(),
             load_direnv: None,
+            dev_container: None,
+            file_templates: Default::default(),
+            search_index: None,
+            todo_scanner: None,
+            todo_tags: None,
+            local_history: None,
+            buffer_memory_budget_mb: None,
+            large_file_threshold_mb: None,
             slash_commands: None,
             git_hosting_providers: None,
         }
@@ -415,6 +423,7 @@ impl VsCodeSettings {
             always_treat_brackets_as_autoclosed: None,
             auto_indent: None,
             auto_indent_on_paste: self.read_bool("editor.formatOnPaste"),
+            autosave: None,
             code_actions_on_format: None,
             completions: skip_default(CompletionSettingsContent {
                 words: self.read_bool("editor.suggest.showWords").map(|b| {
@@ -427,6 +436,7 @@ impl VsCodeSettings {
                 ..Default::default()
             }),
             debuggers: None,
+            file_template: None,
             edit_predictions_disabled_in: None,
             enable_language_server: None,
             ensure_final_newline_on_save: self.read_bool("files.insertFinalNewline"),
@@ -783,6 +793,9 @@ impl VsCodeSettings {
             working_directory: None,
             env,
             detect_venv: None,
+            shell_integration: self.read_bool("terminal.integrated.shellIntegration.enabled"),
+            env_profiles: None,
+            default_env_profile: None,
             path_hyperlink_regexes: None,
             path_hyperlink_timeout_ms: None,
         }
@@ -849,6 +862,7 @@ impl VsCodeSettings {
                 None
             },
             on_last_window_closed: None,
+            background_idle_timeout_minutes: None,
             pane_split_direction_horizontal: None,
             pane_split_direction_vertical: None,
             resize_all_panels_in_dock: None,
@@ -907,6 +921,9 @@ impl VsCodeSettings {
                 .filter(|r| !r.is_empty()),
             private_files: None,
             hidden_files: None,
+            watch_exclusions: None,
+            symlinks: None,
+            autosave_exclusions: None,
         }
     }
 }
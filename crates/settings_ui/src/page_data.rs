@@ -4775,6 +4775,28 @@ pub(crate) fn settings_data(cx: &App) -> Vec<SettingsPage> {
                     metadata: None,
                     files: USER,
                 }),
+                SettingsPageItem::SettingItem(SettingItem {
+                    title: "Record DAP Sessions",
+                    description: "Whether to record the messages exchanged with debug adapters to disk, so that sessions can be replayed.",
+                    field: Box::new(SettingField {
+                        json_path: Some("debugger.record_dap_sessions"),
+                        pick: |settings_content| {
+                            settings_content
+                                .debugger
+                                .as_ref()?
+                                .record_dap_sessions
+                                .as_ref()
+                        },
+                        write: |settings_content, value| {
+                            settings_content
+                                .debugger
+                                .get_or_insert_default()
+                                .record_dap_sessions = value;
+                        },
+                    }),
+                    metadata: None,
+                    files: USER,
+                }),
             ],
         },
         SettingsPage {
@@ -5112,6 +5134,30 @@ pub(crate) fn settings_data(cx: &App) -> Vec<SettingsPage> {
                     metadata: None,
                     files: USER | PROJECT,
                 }),
+                SettingsPageItem::SettingItem(SettingItem {
+                    title: "Shell Integration",
+                    description: "Whether the shell reports its working directory and commands, for navigating between commands and opening paths relative to it.",
+                    field: Box::new(SettingField {
+                        json_path: Some("terminal.shell_integration"),
+                        pick: |settings_content| {
+                            settings_content
+                                .terminal
+                                .as_ref()?
+                                .project
+                                .shell_integration
+                                .as_ref()
+                        },
+                        write: |settings_content, value| {
+                            settings_content
+                                .terminal
+                                .get_or_insert_default()
+                                .project
+                                .shell_integration = value;
+                        },
+                    }),
+                    metadata: None,
+                    files: USER | PROJECT,
+                }),
                 SettingsPageItem::SectionHeader("Font"),
                 SettingsPageItem::SettingItem(SettingItem {
                     title: "Font Size",
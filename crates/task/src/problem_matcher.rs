@@ -0,0 +1,342 @@
+//! Problem matchers extract the errors and warnings reported in a task's output, so that they can be shown as
+//! diagnostics.
+
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use util::ResultExt as _;
+
+/// A problem matcher of a task: either the name of a built-in one or of one provided by an extension, or a custom one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ProblemMatcherDefinition {
+    BuiltIn(BuiltInProblemMatcher),
+    /// The name of a problem matcher provided by an extension, which the project replaces with its matchers when
+    /// listing the tasks.
+    Named(String),
+    Custom(ProblemMatcher),
+}
+
+impl ProblemMatcherDefinition {
+    /// The matchers of this definition, none for the name of a problem matcher that no extension provides.
+    pub fn matchers(&self) -> Vec<ProblemMatcher> {
+        match self {
+            Self::BuiltIn(built_in) => built_in.matchers(),
+            Self::Named(_) => Vec::new(),
+            Self::Custom(matcher) => vec![matcher.clone()],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltInProblemMatcher {
+    /// Errors and warnings of `cargo` and `rustc`.
+    Cargo,
+    /// Errors of the TypeScript compiler, with or without `--pretty`.
+    Tsc,
+    /// Problems reported by ESLint's default `stylish` formatter.
+    Eslint,
+}
+
+impl BuiltInProblemMatcher {
+    fn matchers(self) -> Vec<ProblemMatcher> {
+        match self {
+            Self::Cargo => vec![ProblemMatcher::new(
+                r"(?m)^(?P<severity>error|warning)(?:\[\w+\])?: (?P<message>.+)\n\s*--> (?P<file>.+?):(?P<line>\d+):(?P<column>\d+)$",
+            )],
+            Self::Tsc => vec![
+                ProblemMatcher::new(
+                    r"(?m)^(?P<file>\S.*?)\((?P<line>\d+),(?P<column>\d+)\): (?P<severity>error|warning) (?P<message>TS\d+: .+)$",
+                ),
+                ProblemMatcher::new(
+                    r"(?m)^(?P<file>\S.*?):(?P<line>\d+):(?P<column>\d+) - (?P<severity>error|warning) (?P<message>TS\d+: .+)$",
+                ),
+            ],
+            Self::Eslint => vec![ProblemMatcher {
+                file_pattern: Some(r"(?m)^(?P<file>\S.*)$".to_string()),
+                ..ProblemMatcher::new(
+                    r"(?m)^\s+(?P<line>\d+):(?P<column>\d+)\s+(?P<severity>error|warning)\s+(?P<message>.+?)(?:\s{2,}\S+)?$",
+                )
+            }],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ProblemMatcher {
+    /// A regular expression matched against the task's output, with the named capture groups `file` and `line`,
+    /// and optionally `column`, `severity` and `message`.
+    pub pattern: String,
+    /// A regular expression with a `file` capture group, matching the lines that name the file the following problems
+    /// are in, for tools that group problems by file.
+    #[serde(default)]
+    pub file_pattern: Option<String>,
+    /// The severity of the problems without a recognized `severity` capture.
+    #[serde(default)]
+    pub severity: ProblemSeverity,
+}
+
+impl ProblemMatcher {
+    fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            file_pattern: None,
+            severity: ProblemSeverity::default(),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemSeverity {
+    #[default]
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl ProblemSeverity {
+    fn parse(severity: &str) -> Option<Self> {
+        let severity = severity.to_lowercase();
+        if severity.starts_with("err") || severity.starts_with("fatal") {
+            Some(Self::Error)
+        } else if severity.starts_with("warn") {
+            Some(Self::Warning)
+        } else if severity.starts_with("info") || severity.starts_with("note") {
+            Some(Self::Info)
+        } else if severity.starts_with("hint") || severity.starts_with("help") {
+            Some(Self::Hint)
+        } else {
+            None
+        }
+    }
+}
+
+/// A problem found in a task's output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    /// The path of the file, as printed by the task: it may be relative to the task's working directory.
+    pub path: PathBuf,
+    /// Zero-based row.
+    pub row: u32,
+    /// Zero-based column.
+    pub column: u32,
+    pub severity: ProblemSeverity,
+    pub message: String,
+}
+
+/// Finds the problems reported in `output`, in the order of `matchers`.
+pub fn match_problems(matchers: &[ProblemMatcher], output: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for matcher in matchers {
+        let Some(pattern) = Regex::new(&matcher.pattern)
+            .context("invalid problem matcher pattern")
+            .log_err()
+        else {
+            continue;
+        };
+        let file_headers: Vec<(usize, &str)> = match &matcher.file_pattern {
+            Some(file_pattern) => {
+                let Some(file_pattern) = Regex::new(file_pattern)
+                    .context("invalid problem matcher file pattern")
+                    .log_err()
+                else {
+                    continue;
+                };
+                file_pattern
+                    .captures_iter(output)
+                    .filter_map(|captures| {
+                        Some((captures.get(0)?.start(), captures.name("file")?.as_str()))
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        for captures in pattern.captures_iter(output) {
+            let start = captures.get(0).map_or(0, |whole| whole.start());
+            let path = match captures.name("file") {
+                Some(file) => file.as_str(),
+                None => match file_headers
+                    .iter()
+                    .rev()
+                    .find(|(header, _)| *header < start)
+                {
+                    Some((_, file)) => file,
+                    None => continue,
+                },
+            };
+            let Some(line) = captures
+                .name("line")
+                .and_then(|line| line.as_str().parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let column = captures
+                .name("column")
+                .and_then(|column| column.as_str().parse::<u32>().ok())
+                .unwrap_or(1);
+            let severity = captures
+                .name("severity")
+                .and_then(|severity| ProblemSeverity::parse(severity.as_str()))
+                .unwrap_or(matcher.severity);
+            problems.push(Problem {
+                path: PathBuf::from(path.trim()),
+                row: line.saturating_sub(1),
+                column: column.saturating_sub(1),
+                severity,
+                message: captures
+                    .name("message")
+                    .map_or("", |message| message.as_str())
+                    .trim()
+                    .to_string(),
+            });
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(
+        matcher: BuiltInProblemMatcher,
+        output: &str,
+    ) -> Vec<(String, u32, u32, ProblemSeverity, String)> {
+        match_problems(&matcher.matchers(), output)
+            .into_iter()
+            .map(|problem| {
+                (
+                    problem.path.to_string_lossy().into_owned(),
+                    problem.row,
+                    problem.column,
+                    problem.severity,
+                    problem.message,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_built_in_problem_matchers() {
+        let cargo_output = "   Compiling app v0.1.0
+warning: unused variable: `x`
+ --> src/main.rs:2:9
+  |
+2 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+error[E0308]: mismatched types
+  --> src/lib.rs:10:18
+   |
+";
+        assert_eq!(
+            problems(BuiltInProblemMatcher::Cargo, cargo_output),
+            vec![
+                (
+                    "src/main.rs".into(),
+                    1,
+                    8,
+                    ProblemSeverity::Warning,
+                    "unused variable: `x`".into()
+                ),
+                (
+                    "src/lib.rs".into(),
+                    9,
+                    17,
+                    ProblemSeverity::Error,
+                    "mismatched types".into()
+                ),
+            ]
+        );
+
+        let tsc_output =
+            "src/index.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+src/app.ts:12:1 - error TS2304: Cannot find name 'foo'.
+";
+        assert_eq!(
+            problems(BuiltInProblemMatcher::Tsc, tsc_output),
+            vec![
+                (
+                    "src/index.ts".into(),
+                    2,
+                    6,
+                    ProblemSeverity::Error,
+                    "TS2322: Type 'string' is not assignable to type 'number'.".into()
+                ),
+                (
+                    "src/app.ts".into(),
+                    11,
+                    0,
+                    ProblemSeverity::Error,
+                    "TS2304: Cannot find name 'foo'.".into()
+                ),
+            ]
+        );
+
+        let eslint_output = "
+/home/me/app/src/index.js
+   1:7   error    'unused' is assigned a value but never used  no-unused-vars
+  10:1   warning  Unexpected console statement                 no-console
+
+✖ 2 problems (1 error, 1 warning)
+";
+        assert_eq!(
+            problems(BuiltInProblemMatcher::Eslint, eslint_output),
+            vec![
+                (
+                    "/home/me/app/src/index.js".into(),
+                    0,
+                    6,
+                    ProblemSeverity::Error,
+                    "'unused' is assigned a value but never used".into()
+                ),
+                (
+                    "/home/me/app/src/index.js".into(),
+                    9,
+                    0,
+                    ProblemSeverity::Warning,
+                    "Unexpected console statement".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_problem_matcher() {
+        let matcher: ProblemMatcherDefinition = serde_json::from_value(serde_json::json!({
+            "pattern": "(?m)^(?P<file>[^:]+):(?P<line>\\d+): (?P<message>.+)$",
+            "severity": "warning",
+        }))
+        .unwrap();
+        let problems = match_problems(&matcher.matchers(), "lint.txt:4: trailing whitespace\n");
+        assert_eq!(
+            problems,
+            vec![Problem {
+                path: PathBuf::from("lint.txt"),
+                row: 3,
+                column: 0,
+                severity: ProblemSeverity::Warning,
+                message: "trailing whitespace".into(),
+            }]
+        );
+
+        let built_in: ProblemMatcherDefinition =
+            serde_json::from_value(serde_json::json!("cargo")).unwrap();
+        assert_eq!(
+            built_in,
+            ProblemMatcherDefinition::BuiltIn(BuiltInProblemMatcher::Cargo)
+        );
+
+        let named: ProblemMatcherDefinition =
+            serde_json::from_value(serde_json::json!("gcc")).unwrap();
+        assert_eq!(named, ProblemMatcherDefinition::Named("gcc".into()));
+        assert!(named.matchers().is_empty());
+    }
+}
@@ -2,6 +2,7 @@
 
 mod adapter_schema;
 mod debug_format;
+mod problem_matcher;
 mod serde_helpers;
 pub mod static_source;
 mod task_template;
@@ -21,9 +22,14 @@ pub use debug_format::{
     AttachRequest, BuildTaskDefinition, DebugRequest, DebugScenario, DebugTaskFile, LaunchRequest,
     Request, TcpArgumentsTemplate, VectorDebugConfig,
 };
+pub use problem_matcher::{
+    BuiltInProblemMatcher, Problem, ProblemMatcher, ProblemMatcherDefinition, ProblemSeverity,
+    match_problems,
+};
 pub use task_template::{
-    DebugArgsRequest, HideStrategy, RevealStrategy, TaskTemplate, TaskTemplates,
-    substitute_variables_in_map, substitute_variables_in_str,
+    DebugArgsRequest, DependencyFailurePolicy, DependsOrder, ExecutionTarget, HideStrategy,
+    RevealStrategy, TaskTemplate, TaskTemplates, substitute_variables_in_map,
+    substitute_variables_in_str,
 };
 pub use util::shell::{Shell, ShellKind};
 pub use util::shell_builder::ShellBuilder;
@@ -56,6 +62,10 @@ pub struct SpawnInTerminal {
     pub cwd: Option<PathBuf>,
     /// Env overrides for the command, will be appended to the terminal's environment from the settings.
     pub env: HashMap<String, String>,
+    /// The part of `env` that the task itself defines, with its `env` and the task variables, without the project
+    /// environment of this machine. Tasks that run in a Dev Container only get these on top of the container's own
+    /// environment.
+    pub task_env: HashMap<String, String>,
     /// Whether to use a new terminal tab or reuse the existing one to spawn the process.
     pub use_new_terminal: bool,
     /// Whether to allow multiple instances of the same task to be run, or rather wait for the existing ones to finish.
@@ -74,6 +84,14 @@ pub struct SpawnInTerminal {
     pub show_command: bool,
     /// Whether to show the rerun button in the terminal tab.
     pub show_rerun: bool,
+    /// Problem matchers to turn the task's output into diagnostics with, once it finishes.
+    pub problem_matchers: Vec<ProblemMatcher>,
+    /// Whether the task keeps running in the background, and is restarted when it fails.
+    pub is_background: bool,
+    /// A regular expression matched against the output of a background task, to tell when it is ready.
+    pub ready_pattern: Option<String>,
+    /// Where to run the task, or `None` to run it where the project's terminals run.
+    pub execution_target: Option<ExecutionTarget>,
 }
 
 impl SpawnInTerminal {
@@ -117,6 +135,8 @@ pub struct ResolvedTask {
     pub id: TaskId,
     /// A template the task got resolved from.
     original_task: TaskTemplate,
+    /// The context the task got resolved with, used to resolve the tasks it depends on.
+    task_context: TaskContext,
     /// Full, unshortened label of the task after all resolutions are made.
     pub resolved_label: String,
     /// Variables that were substituted during the task template resolution.
@@ -132,6 +152,11 @@ impl ResolvedTask {
         &self.original_task
     }
 
+    /// The context the task got resolved with.
+    pub fn task_context(&self) -> &TaskContext {
+        &self.task_context
+    }
+
     /// Variables that were substituted during the task template resolution.
     pub fn substituted_variables(&self) -> &HashSet<VariableName> {
         &self.substituted_variables
@@ -176,6 +201,13 @@ pub enum VariableName {
     /// Open a Picker to select a process ID to use in place
     /// Can only be used to debug configurations
     PickProcessId,
+    /// The name of the git branch checked out in the repository, that contains the currently opened file.
+    GitBranch,
+    /// An absolute path of the active toolchain for the currently opened file's language, e.g. a Python interpreter.
+    Toolchain,
+    /// A variable of the project environment, e.g. one set by direnv.
+    /// Will be printed with `ENV_` prefix, to tell it from the environment variable itself.
+    Env(Cow<'static, str>),
     /// Custom variable, provided by the plugin or other external source.
     /// Will be printed with `CUSTOM_` prefix to avoid potential conflicts with other variables.
     Custom(Cow<'static, str>),
@@ -213,11 +245,17 @@ impl FromStr for VariableName {
             "SELECTED_TEXT" => Self::SelectedText,
             "ROW" => Self::Row,
             "COLUMN" => Self::Column,
+            "GIT_BRANCH" => Self::GitBranch,
+            "TOOLCHAIN" => Self::Toolchain,
             _ => {
                 if let Some(custom_name) =
                     without_prefix.strip_prefix(VECTOR_CUSTOM_VARIABLE_NAME_PREFIX)
                 {
                     Self::Custom(Cow::Owned(custom_name.to_owned()))
+                } else if let Some(env_name) =
+                    without_prefix.strip_prefix(VECTOR_ENV_VARIABLE_NAME_PREFIX)
+                {
+                    Self::Env(Cow::Owned(env_name.to_owned()))
                 } else {
                     return Err(());
                 }
@@ -231,6 +269,7 @@ impl FromStr for VariableName {
 pub const VECTOR_VARIABLE_NAME_PREFIX: &str = "VECTOR_";
 pub const ZED_VARIABLE_NAME_PREFIX: &str = "ZED_";
 const VECTOR_CUSTOM_VARIABLE_NAME_PREFIX: &str = "CUSTOM_";
+const VECTOR_ENV_VARIABLE_NAME_PREFIX: &str = "ENV_";
 
 impl std::fmt::Display for VariableName {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -248,6 +287,12 @@ impl std::fmt::Display for VariableName {
             Self::SelectedText => write!(f, "{VECTOR_VARIABLE_NAME_PREFIX}SELECTED_TEXT"),
             Self::RunnableSymbol => write!(f, "{VECTOR_VARIABLE_NAME_PREFIX}RUNNABLE_SYMBOL"),
             Self::PickProcessId => write!(f, "{VECTOR_VARIABLE_NAME_PREFIX}PICK_PID"),
+            Self::GitBranch => write!(f, "{VECTOR_VARIABLE_NAME_PREFIX}GIT_BRANCH"),
+            Self::Toolchain => write!(f, "{VECTOR_VARIABLE_NAME_PREFIX}TOOLCHAIN"),
+            Self::Env(s) => write!(
+                f,
+                "{VECTOR_VARIABLE_NAME_PREFIX}{VECTOR_ENV_VARIABLE_NAME_PREFIX}{s}"
+            ),
             Self::Custom(s) => write!(
                 f,
                 "{VECTOR_VARIABLE_NAME_PREFIX}{VECTOR_CUSTOM_VARIABLE_NAME_PREFIX}{s}"
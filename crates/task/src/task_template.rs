@@ -9,8 +9,8 @@ use util::serde::default_true;
 use util::{ResultExt, truncate_and_remove_front};
 
 use crate::{
-    AttachRequest, ResolvedTask, RevealTarget, Shell, SpawnInTerminal, TaskContext, TaskId,
-    VECTOR_VARIABLE_NAME_PREFIX, VariableName, ZED_VARIABLE_NAME_PREFIX,
+    AttachRequest, ProblemMatcherDefinition, ResolvedTask, RevealTarget, Shell, SpawnInTerminal,
+    TaskContext, TaskId, VECTOR_VARIABLE_NAME_PREFIX, VariableName, ZED_VARIABLE_NAME_PREFIX,
     serde_helpers::non_empty_string_vec,
 };
 
@@ -73,6 +73,41 @@ pub struct TaskTemplate {
     /// Whether to show the command line in the task output.
     #[serde(default = "default_true")]
     pub show_command: bool,
+    /// Labels of the tasks to run before this one.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// How to run the tasks this one depends on:
+    /// * `sequence` — one after another, in the order they are listed (default)
+    /// * `parallel` — all at once
+    #[serde(default)]
+    pub depends_order: DependsOrder,
+    /// What to do when one of the tasks this one depends on fails:
+    /// * `abort` — do not run this task, and in a sequence, the remaining dependencies (default)
+    /// * `continue` — run this task regardless
+    #[serde(default)]
+    pub on_dependency_failure: DependencyFailurePolicy,
+    /// Problem matchers to extract diagnostics from the task's output with, once it finishes.
+    /// Either the name of a built-in matcher (`cargo`, `tsc` or `eslint`) or of one provided by an extension, or a custom matcher:
+    /// a regular expression with named `file`, `line`, `column`, `severity` and `message` capture groups.
+    #[serde(default)]
+    pub problem_matchers: Vec<ProblemMatcherDefinition>,
+    /// Whether the task keeps running in the background, like a file watcher or a development server.
+    /// Tasks that depend on a background task wait until it is ready rather than until it exits,
+    /// and a background task that exits with an error is restarted, with an increasing delay between attempts.
+    #[serde(default)]
+    pub is_background: bool,
+    /// A regular expression matched against the output of a background task, to tell when it is ready.
+    /// Without one, a background task is considered ready as soon as it is started.
+    #[serde(default)]
+    pub ready_pattern: Option<String>,
+    /// Globs, relative to the worktree root, of the files whose saving runs this task.
+    #[serde(default)]
+    pub run_on_save: Vec<String>,
+    /// Where to run the task, rather than where the project's terminals run:
+    /// * `host` — on this machine, even when the project runs its processes in a Dev Container
+    /// * `dev_container` — in the project's Dev Container, starting it if needed
+    #[serde(default)]
+    pub execution_target: Option<ExecutionTarget>,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Clone, Debug)]
@@ -110,6 +145,38 @@ pub enum HideStrategy {
     OnSuccess,
 }
 
+/// How to run the tasks a task depends on.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DependsOrder {
+    /// Run the dependencies one after another, in the order they are listed.
+    #[default]
+    Sequence,
+    /// Run all dependencies at once.
+    Parallel,
+}
+
+/// What to do when one of the tasks a task depends on fails.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyFailurePolicy {
+    /// Do not run the task, nor the dependencies that were not started yet.
+    #[default]
+    Abort,
+    /// Run the task regardless.
+    Continue,
+}
+
+/// Where to run a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionTarget {
+    /// On this machine.
+    Host,
+    /// In the project's Dev Container.
+    DevContainer,
+}
+
 /// A group of Tasks defined in a JSON file.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct TaskTemplates(pub Vec<TaskTemplate>);
@@ -238,15 +305,33 @@ impl TaskTemplate {
                 &mut substituted_variables,
             )?;
 
-            // Last step: set the task variables as environment variables too
-            env.extend(task_variables.into_iter().map(|(k, v)| (k, v.to_owned())));
+            // Set the task variables as environment variables too,
+            // except for the ones that come from the environment already
+            env.extend(
+                task_variables
+                    .into_iter()
+                    .filter(|(k, _)| !matches!(variable_names.get(k), Some(VariableName::Env(_))))
+                    .map(|(k, v)| (k, v.to_owned())),
+            );
             env
         };
+        // Last step: keep apart what the task adds to the project environment, for when it runs elsewhere
+        let task_env = env
+            .iter()
+            .filter(|(name, _)| {
+                self.env.contains_key(*name)
+                    || variable_names
+                        .get(*name)
+                        .is_some_and(|variable| !matches!(variable, VariableName::Env(_)))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
 
         Some(ResolvedTask {
             id: id.clone(),
             substituted_variables,
             original_task: self.clone(),
+            task_context: cx.clone(),
             resolved_label: full_label.clone(),
             resolved: SpawnInTerminal {
                 id,
@@ -264,6 +349,7 @@ impl TaskTemplate {
                 command: Some(command),
                 args: args_with_substitutions,
                 env,
+                task_env,
                 use_new_terminal: self.use_new_terminal,
                 allow_concurrent_runs: self.allow_concurrent_runs,
                 reveal: self.reveal,
@@ -273,6 +359,14 @@ impl TaskTemplate {
                 show_summary: self.show_summary,
                 show_command: self.show_command,
                 show_rerun: true,
+                problem_matchers: self
+                    .problem_matchers
+                    .iter()
+                    .flat_map(ProblemMatcherDefinition::matchers)
+                    .collect(),
+                is_background: self.is_background,
+                ready_pattern: self.ready_pattern.clone().filter(|_| self.is_background),
+                execution_target: self.execution_target,
             },
         })
     }
@@ -896,6 +990,55 @@ mod tests {
             resolved.env["PROJECT_ENV_WILL_BE_OVERWRITTEN"],
             "overwritten"
         );
+        assert_eq!(
+            resolved.task_env["PROJECT_ENV_WILL_BE_OVERWRITTEN"],
+            "overwritten"
+        );
+        assert_eq!(resolved.task_env["TASK_ENV_VAR2"], "env_var_2 1234 5678");
+        assert_eq!(resolved.task_env["VECTOR_ROW"], "1234");
+        assert!(
+            !resolved.task_env.contains_key("PROJECT_ENV_VAR1"),
+            "the task environment should not include the project one"
+        );
+    }
+
+    #[test]
+    fn test_environment_variables() {
+        let home = VariableName::Env(Cow::Borrowed("HOME"));
+        assert_eq!(home.to_string(), "VECTOR_ENV_HOME");
+        assert_eq!("VECTOR_ENV_HOME".parse::<VariableName>(), Ok(home.clone()));
+        assert_eq!(
+            "VECTOR_GIT_BRANCH".parse::<VariableName>(),
+            Ok(VariableName::GitBranch)
+        );
+
+        let template = TaskTemplate {
+            label: "build $VECTOR_GIT_BRANCH".to_string(),
+            command: "make".to_string(),
+            cwd: Some(format!("{}/build", home.template_value())),
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::GitBranch, "main".to_string()),
+                (home, "/home/user".to_string()),
+            ]),
+            project_env: HashMap::from_iter([("HOME".to_string(), "/home/user".to_string())]),
+        };
+
+        let resolved = template.resolve_task(TEST_ID_BASE, &context).unwrap();
+        assert_eq!(resolved.resolved_label, "build main");
+        assert_eq!(
+            resolved.resolved.cwd,
+            Some(PathBuf::from("/home/user/build"))
+        );
+        assert_eq!(resolved.resolved.env["VECTOR_GIT_BRANCH"], "main");
+        assert_eq!(resolved.resolved.env["HOME"], "/home/user");
+        assert!(
+            !resolved.resolved.env.contains_key("VECTOR_ENV_HOME"),
+            "environment variables should not be exported twice"
+        );
     }
 
     #[test]
@@ -474,6 +474,12 @@ impl PickerDelegate for TasksModalDelegate {
                     .as_str(),
             );
         }
+        if let TaskSourceKind::BuildFile { path, row, .. } = source_kind {
+            if !tooltip_label_text.trim().is_empty() {
+                tooltip_label_text.push('\n');
+            }
+            tooltip_label_text.push_str(&format!("{}:{}", path.as_unix_str(), row + 1));
+        }
         let tooltip_label = if tooltip_label_text.trim().is_empty() {
             None
         } else {
@@ -489,6 +495,8 @@ impl PickerDelegate for TasksModalDelegate {
             TaskSourceKind::UserInput => Some(Icon::new(IconName::Terminal)),
             TaskSourceKind::AbsPath { .. } => Some(Icon::new(IconName::Settings)),
             TaskSourceKind::Worktree { .. } => Some(Icon::new(IconName::FileTree)),
+            TaskSourceKind::BuildFile { .. } => Some(Icon::new(IconName::ToolHammer)),
+            TaskSourceKind::Extension { .. } => Some(Icon::new(IconName::Blocks)),
             TaskSourceKind::Lsp {
                 language_name: name,
                 ..
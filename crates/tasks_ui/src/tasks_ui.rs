@@ -2,9 +2,11 @@ use std::{path::Path, sync::Arc};
 
 use collections::HashMap;
 use editor::Editor;
-use gpui::{App, AppContext as _, Context, Entity, Task, Window};
+use gpui::{App, AppContext as _, AsyncWindowContext, Context, Entity, Task, WeakEntity, Window};
 use project::{Location, TaskContexts, TaskSourceKind, Worktree};
-use task::{RevealTarget, TaskContext, TaskId, TaskTemplate, TaskVariables, VariableName};
+use task::{
+    ResolvedTask, RevealTarget, TaskContext, TaskId, TaskTemplate, TaskVariables, VariableName,
+};
 use workspace::Workspace;
 
 mod modal;
@@ -188,37 +190,7 @@ where
     F: FnMut((&TaskSourceKind, &TaskTemplate)) -> bool + 'static,
 {
     cx.spawn_in(window, async move |workspace, cx| {
-        let task_contexts = workspace.update_in(cx, |workspace, window, cx| {
-            task_contexts(workspace, window, cx)
-        })?;
-        let task_contexts = task_contexts.await;
-        let mut tasks = workspace
-            .update(cx, |workspace, cx| {
-                let Some(task_inventory) = workspace
-                    .project()
-                    .read(cx)
-                    .task_store()
-                    .read(cx)
-                    .task_inventory()
-                    .cloned()
-                else {
-                    return Task::ready(Vec::new());
-                };
-                let (file, language) = task_contexts
-                    .location()
-                    .map(|location| {
-                        let buffer = location.buffer.read(cx);
-                        (
-                            buffer.file().cloned(),
-                            buffer.language_at(location.range.start),
-                        )
-                    })
-                    .unwrap_or_default();
-                task_inventory
-                    .read(cx)
-                    .list_tasks(file, language, task_contexts.worktree(), cx)
-            })?
-            .await;
+        let (task_contexts, mut tasks) = list_tasks(&workspace, cx).await?;
 
         let did_spawn = workspace
             .update_in(cx, |workspace, window, cx| {
@@ -268,6 +240,66 @@ where
     })
 }
 
+/// Resolves the tasks that [`Spawn::ByName`] runs for the name, in the context of the active item, to show them
+/// before running them.
+pub fn resolve_tasks_by_name(
+    name: String,
+    window: &mut Window,
+    cx: &mut Context<Workspace>,
+) -> Task<anyhow::Result<Vec<ResolvedTask>>> {
+    cx.spawn_in(window, async move |workspace, cx| {
+        let (task_contexts, tasks) = list_tasks(&workspace, cx).await?;
+        let default_context = TaskContext::default();
+        let active_context = task_contexts.active_context().unwrap_or(&default_context);
+        Ok(tasks
+            .into_iter()
+            .filter(|(_, task)| task.label == name)
+            .filter_map(|(task_source_kind, task)| {
+                task.resolve_task(&task_source_kind.to_id_base(), active_context)
+            })
+            .collect())
+    })
+}
+
+/// Lists the tasks available for the active item, along with the contexts to resolve them with.
+async fn list_tasks(
+    workspace: &WeakEntity<Workspace>,
+    cx: &mut AsyncWindowContext,
+) -> anyhow::Result<(TaskContexts, Vec<(TaskSourceKind, TaskTemplate)>)> {
+    let task_contexts = workspace.update_in(cx, |workspace, window, cx| {
+        task_contexts(workspace, window, cx)
+    })?;
+    let task_contexts = task_contexts.await;
+    let tasks = workspace
+        .update(cx, |workspace, cx| {
+            let Some(task_inventory) = workspace
+                .project()
+                .read(cx)
+                .task_store()
+                .read(cx)
+                .task_inventory()
+                .cloned()
+            else {
+                return Task::ready(Vec::new());
+            };
+            let (file, language) = task_contexts
+                .location()
+                .map(|location| {
+                    let buffer = location.buffer.read(cx);
+                    (
+                        buffer.file().cloned(),
+                        buffer.language_at(location.range.start),
+                    )
+                })
+                .unwrap_or_default();
+            task_inventory
+                .read(cx)
+                .list_tasks(file, language, task_contexts.worktree(), cx)
+        })?
+        .await;
+    Ok((task_contexts, tasks))
+}
+
 pub fn task_contexts(
     workspace: &Workspace,
     window: &mut Window,
@@ -0,0 +1,459 @@
+//! Shell integration: the `OSC 133` marks that shells emit around their prompts and commands, and the `OSC 7`
+//! reports of their working directory, read from the PTY output before it reaches the terminal emulator, which
+//! ignores them.
+//!
+//! The output is handed to the emulator up to the end of each sequence, and the sequence is positioned at the
+//! emulator's cursor once it parsed that output, before the output after it is handed over.
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::{
+    io::{self, Read as _},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use alacritty_terminal::{
+    Term,
+    event::{OnResize, WindowSize},
+    sync::FairMutex,
+    tty::{ChildEvent, EventedPty, EventedReadWrite},
+};
+use futures::channel::mpsc::UnboundedSender;
+use polling::{Event as PollingEvent, PollMode, Poller};
+
+use crate::{AlacrittyListener, Scrollback, ScrollbackPoint};
+
+/// OSC payloads longer than this are not shell integration sequences, and are skipped.
+const MAX_OSC_LEN: usize = 4096;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ShellIntegrationEvent {
+    /// `OSC 133 ; A`: the shell started printing its prompt.
+    PromptStart,
+    /// `OSC 133 ; B`: the prompt was printed, and the user is typing a command.
+    CommandStart,
+    /// `OSC 133 ; C`: the command was submitted, and its output follows.
+    CommandExecuted,
+    /// `OSC 133 ; D [; <exit code>]`: the command finished.
+    CommandFinished { exit_code: Option<i32> },
+    /// `OSC 7 ; file://<host>/<path>`: the shell changed its working directory.
+    WorkingDirectory(PathBuf),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScanState {
+    Ground,
+    Escape,
+    Osc,
+    OscEscape,
+}
+
+/// Finds the shell integration sequences in a stream of PTY output, which may split them across reads.
+pub(crate) struct ShellIntegrationScanner {
+    state: ScanState,
+    payload: Vec<u8>,
+}
+
+impl Default for ShellIntegrationScanner {
+    fn default() -> Self {
+        Self {
+            state: ScanState::Ground,
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl ShellIntegrationScanner {
+    /// Scans `bytes` up to the end of the first shell integration sequence in them, returning how many bytes were
+    /// scanned, and the sequence's event if one ended.
+    pub(crate) fn scan(&mut self, bytes: &[u8]) -> (usize, Option<ShellIntegrationEvent>) {
+        for (ix, &byte) in bytes.iter().enumerate() {
+            let event = match self.state {
+                ScanState::Ground => {
+                    if byte == ESC {
+                        self.state = ScanState::Escape;
+                    }
+                    None
+                }
+                ScanState::Escape => {
+                    self.escape(byte);
+                    None
+                }
+                ScanState::Osc => match byte {
+                    BEL => self.dispatch(),
+                    ESC => {
+                        self.state = ScanState::OscEscape;
+                        None
+                    }
+                    _ if self.payload.len() < MAX_OSC_LEN => {
+                        self.payload.push(byte);
+                        None
+                    }
+                    _ => {
+                        self.state = ScanState::Ground;
+                        None
+                    }
+                },
+                ScanState::OscEscape => {
+                    if byte == b'\\' {
+                        self.dispatch()
+                    } else {
+                        self.escape(byte);
+                        None
+                    }
+                }
+            };
+            if event.is_some() {
+                return (ix + 1, event);
+            }
+        }
+        (bytes.len(), None)
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = match byte {
+            b']' => {
+                self.payload.clear();
+                ScanState::Osc
+            }
+            ESC => ScanState::Escape,
+            _ => ScanState::Ground,
+        };
+    }
+
+    fn dispatch(&mut self) -> Option<ShellIntegrationEvent> {
+        self.state = ScanState::Ground;
+        let event = std::str::from_utf8(&self.payload).ok().and_then(parse_osc);
+        self.payload.clear();
+        event
+    }
+}
+
+fn parse_osc(payload: &str) -> Option<ShellIntegrationEvent> {
+    if let Some(url) = payload.strip_prefix("7;") {
+        return parse_file_url(url).map(ShellIntegrationEvent::WorkingDirectory);
+    }
+    let mut params = payload.strip_prefix("133;")?.split(';');
+    match params.next()? {
+        "A" => Some(ShellIntegrationEvent::PromptStart),
+        "B" => Some(ShellIntegrationEvent::CommandStart),
+        "C" => Some(ShellIntegrationEvent::CommandExecuted),
+        "D" => Some(ShellIntegrationEvent::CommandFinished {
+            exit_code: params.next().and_then(|code| code.parse().ok()),
+        }),
+        _ => None,
+    }
+}
+
+/// Parses the `file://<host>/<path>` URLs of `OSC 7`, ignoring the host: the shell runs where the PTY is.
+fn parse_file_url(url: &str) -> Option<PathBuf> {
+    let host_and_path = url.strip_prefix("file://")?;
+    let path = &host_and_path[host_and_path.find('/')?..];
+    let path = urlencoding::decode(path).ok()?;
+    // `file://host/C:/Users` on Windows.
+    let path = match path.strip_prefix('/') {
+        Some(windows_path) if cfg!(windows) && windows_path.get(1..2) == Some(":") => windows_path,
+        _ => path.as_ref(),
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Reads the output of a PTY, reporting the shell integration sequences in it along with where the terminal emulator
+/// got to them.
+pub(crate) struct ShellIntegrationReader<P> {
+    pty: P,
+    scanner: ShellIntegrationScanner,
+    term: Arc<FairMutex<Term<AlacrittyListener>>>,
+    scrollback: Arc<Scrollback>,
+    events_tx: UnboundedSender<(ShellIntegrationEvent, ScrollbackPoint)>,
+    /// The output read after a sequence, held back until the sequence is positioned.
+    held_output: Vec<u8>,
+    /// The sequence at the end of the output last handed to the emulator, until it's positioned.
+    unpositioned_event: Option<ShellIntegrationEvent>,
+    /// Whether the event loop ended the pass in which it parsed the output up to the unpositioned sequence, and with it
+    /// its lock of the terminal.
+    read_pass_ended: bool,
+    /// The size of the event loop's read buffer, which it reads into whole once it parsed all of the output read.
+    read_buffer_len: usize,
+    waker: ReadWaker,
+}
+
+impl<P> ShellIntegrationReader<P> {
+    /// Positions the sequence at the end of the output last handed to the emulator, once the emulator parsed that
+    /// output, or else asks for the read to be retried.
+    fn position_event(&mut self, buf: &[u8]) -> io::Result<()> {
+        let Some(event) = self.unpositioned_event.take() else {
+            return Ok(());
+        };
+        // Part of the output read is waiting for the event loop to lock the terminal and parse it.
+        let parsed = buf.len() >= self.read_buffer_len;
+        if !parsed || !self.read_pass_ended {
+            // The event loop keeps the terminal locked until reading would block with all of the output parsed, so
+            // the sequence is positioned in its next pass.
+            if parsed {
+                self.read_pass_ended = true;
+                self.waker.wake();
+            }
+            self.unpositioned_event = Some(event);
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.read_pass_ended = false;
+        let cursor = self.scrollback.cursor(&mut self.term.lock_unfair());
+        self.events_tx.unbounded_send((event, cursor)).ok();
+        Ok(())
+    }
+}
+
+impl<P: EventedReadWrite> io::Read for ShellIntegrationReader<P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_buffer_len = self.read_buffer_len.max(buf.len());
+        self.waker.drain();
+        self.position_event(buf)?;
+
+        let len = if self.held_output.is_empty() {
+            self.pty.reader().read(buf)?
+        } else {
+            let len = self.held_output.len().min(buf.len());
+            buf[..len].copy_from_slice(&self.held_output[..len]);
+            self.held_output.drain(..len);
+            len
+        };
+        let (scanned_len, event) = self.scanner.scan(&buf[..len]);
+        if event.is_some() {
+            self.held_output
+                .splice(0..0, buf[scanned_len..len].iter().copied());
+            self.unpositioned_event = event;
+        }
+        Ok(scanned_len)
+    }
+}
+
+/// Makes the event loop read again once it ended its pass with the terminal's output held back, although the PTY may
+/// have no new output.
+#[derive(Default)]
+struct ReadWaker {
+    /// A socket registered with the PTY's interest, which is readable once woken.
+    #[cfg(unix)]
+    sockets: Option<(UnixStream, UnixStream)>,
+    #[cfg(windows)]
+    poll: Option<(Arc<Poller>, usize)>,
+}
+
+impl ReadWaker {
+    #[cfg(unix)]
+    fn register(&mut self, poll: &Poller, key: usize, mode: PollMode) -> io::Result<()> {
+        let (sender, receiver) = UnixStream::pair()?;
+        sender.set_nonblocking(true)?;
+        receiver.set_nonblocking(true)?;
+        // SAFETY: the socket is removed from the poller before it's dropped, in `deregister`.
+        unsafe { poll.add_with_mode(&receiver, PollingEvent::readable(key), mode)? };
+        self.sockets = Some((sender, receiver));
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn register(&mut self, poll: &Arc<Poller>, key: usize, _: PollMode) -> io::Result<()> {
+        self.poll = Some((poll.clone(), key));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn deregister(&mut self, poll: &Poller) -> io::Result<()> {
+        match self.sockets.take() {
+            Some((_, receiver)) => poll.delete(&receiver),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(windows)]
+    fn deregister(&mut self, _: &Poller) -> io::Result<()> {
+        self.poll = None;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn wake(&self) {
+        use std::io::Write as _;
+
+        if let Some((sender, _)) = &self.sockets {
+            let mut sender: &UnixStream = sender;
+            sender.write_all(&[0]).ok();
+        }
+    }
+
+    #[cfg(windows)]
+    fn wake(&self) {
+        use polling::os::iocp::{CompletionPacket, PollerIocpExt as _};
+
+        if let Some((poll, key)) = &self.poll {
+            poll.post(CompletionPacket::new(PollingEvent::readable(*key)))
+                .ok();
+        }
+    }
+
+    fn drain(&self) {
+        #[cfg(unix)]
+        if let Some((_, receiver)) = &self.sockets {
+            let mut receiver: &UnixStream = receiver;
+            let mut buf = [0; 64];
+            while matches!(receiver.read(&mut buf), Ok(len) if len > 0) {}
+        }
+    }
+}
+
+/// A PTY whose output is scanned for shell integration sequences on its way to the terminal emulator.
+pub(crate) struct ShellIntegrationPty<P> {
+    reader: ShellIntegrationReader<P>,
+}
+
+impl<P> ShellIntegrationPty<P> {
+    pub(crate) fn new(
+        pty: P,
+        term: Arc<FairMutex<Term<AlacrittyListener>>>,
+        scrollback: Arc<Scrollback>,
+        events_tx: UnboundedSender<(ShellIntegrationEvent, ScrollbackPoint)>,
+    ) -> Self {
+        Self {
+            reader: ShellIntegrationReader {
+                pty,
+                scanner: ShellIntegrationScanner::default(),
+                term,
+                scrollback,
+                events_tx,
+                held_output: Vec::new(),
+                unpositioned_event: None,
+                read_pass_ended: false,
+                read_buffer_len: 0,
+                waker: ReadWaker::default(),
+            },
+        }
+    }
+}
+
+impl<P: EventedReadWrite> EventedReadWrite for ShellIntegrationPty<P> {
+    type Reader = ShellIntegrationReader<P>;
+    type Writer = P::Writer;
+
+    unsafe fn register(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollingEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        self.reader.waker.register(poll, interest.key, mode)?;
+        // SAFETY: the PTY is owned by this wrapper, and lives as long as its registration.
+        unsafe { self.reader.pty.register(poll, interest, mode) }
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &Arc<Poller>,
+        interest: PollingEvent,
+        mode: PollMode,
+    ) -> io::Result<()> {
+        self.reader.pty.reregister(poll, interest, mode)
+    }
+
+    fn deregister(&mut self, poll: &Arc<Poller>) -> io::Result<()> {
+        self.reader.waker.deregister(poll)?;
+        self.reader.pty.deregister(poll)
+    }
+
+    fn reader(&mut self) -> &mut Self::Reader {
+        &mut self.reader
+    }
+
+    fn writer(&mut self) -> &mut Self::Writer {
+        self.reader.pty.writer()
+    }
+}
+
+impl<P: EventedPty> EventedPty for ShellIntegrationPty<P> {
+    fn next_child_event(&mut self) -> Option<ChildEvent> {
+        self.reader.pty.next_child_event()
+    }
+}
+
+impl<P: OnResize> OnResize for ShellIntegrationPty<P> {
+    fn on_resize(&mut self, window_size: WindowSize) {
+        self.reader.pty.on_resize(window_size)
+    }
+}
+
+/// A command run in a shell with shell integration.
+///
+/// Lines are counted from the first line the terminal printed, so that they still point at the command once earlier
+/// lines left the scrollback.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminalCommand {
+    /// The line the command's prompt starts on.
+    pub prompt_line: usize,
+    /// The working directory the shell reported before printing the command's prompt.
+    pub working_directory: Option<PathBuf>,
+    /// The line the command's output starts on, once it was submitted.
+    pub output_line: Option<usize>,
+    /// Where the cursor was when the command finished, right after its output.
+    pub output_end: Option<ScrollbackPoint>,
+    /// Whether the command finished running.
+    pub finished: bool,
+    /// The exit code of the command, if it finished and the shell reported it.
+    pub exit_code: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(chunks: &[&[u8]]) -> Vec<ShellIntegrationEvent> {
+        let mut scanner = ShellIntegrationScanner::default();
+        let mut events = Vec::new();
+        for mut chunk in chunks.iter().copied() {
+            while !chunk.is_empty() {
+                let (scanned_len, event) = scanner.scan(chunk);
+                events.extend(event);
+                chunk = &chunk[scanned_len..];
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn test_scan_command_marks() {
+        assert_eq!(
+            scan(&[
+                b"\x1b]133;A\x07$ \x1b]133;B\x07",
+                b"ls\r\n\x1b]133;C\x1b\\file.txt\r\n\x1b]13",
+                b"3;D;2\x07\x1b]133;A;cl=m\x07",
+            ]),
+            vec![
+                ShellIntegrationEvent::PromptStart,
+                ShellIntegrationEvent::CommandStart,
+                ShellIntegrationEvent::CommandExecuted,
+                ShellIntegrationEvent::CommandFinished { exit_code: Some(2) },
+                ShellIntegrationEvent::PromptStart,
+            ]
+        );
+        assert_eq!(
+            scan(&[b"\x1b]133;D\x07\x1b]0;title\x07\x1b[1m\x1b]133;X\x07"]),
+            vec![ShellIntegrationEvent::CommandFinished { exit_code: None }]
+        );
+    }
+
+    #[test]
+    fn test_scan_working_directory() {
+        assert_eq!(
+            scan(&[b"\x1b]7;file://host/home/user/my%20project\x1b\\"]),
+            vec![ShellIntegrationEvent::WorkingDirectory(PathBuf::from(
+                "/home/user/my project"
+            ))]
+        );
+        assert_eq!(
+            scan(&[b"\x1b]7;file://host\x07", b"\x1b]7;not a url\x07"]),
+            Vec::new()
+        );
+    }
+}
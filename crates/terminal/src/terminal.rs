@@ -3,13 +3,16 @@ pub mod mappings;
 pub use alacritty_terminal;
 
 mod pty_info;
+mod shell_integration;
 mod terminal_hyperlinks;
 pub mod terminal_settings;
 
+pub use shell_integration::TerminalCommand;
+
 use alacritty_terminal::{
     Term,
     event::{Event as AlacTermEvent, EventListener, Notify, WindowSize},
-    event_loop::{EventLoop, Msg, Notifier},
+    event_loop::{EventLoop, EventLoopSender, Msg, Notifier},
     grid::{Dimensions, Grid, Row, Scroll as AlacScroll},
     index::{Boundary, Column, Direction as AlacDirection, Line, Point as AlacPoint},
     selection::{Selection, SelectionRange, SelectionType},
@@ -25,7 +28,7 @@ use alacritty_terminal::{
         ClearMode, CursorStyle as AlacCursorStyle, Handler, NamedPrivateMode, PrivateMode,
     },
 };
-use anyhow::{Context as _, Result, bail};
+use anyhow::{Context as _, Result, anyhow, bail};
 use log::trace;
 
 use futures::{
@@ -44,6 +47,7 @@ use futures::StreamExt;
 use pty_info::{ProcessIdGetter, PtyProcessInfo};
 use serde::{Deserialize, Serialize};
 use settings::Settings;
+use shell_integration::{ShellIntegrationEvent, ShellIntegrationPty, ShellIntegrationScanner};
 use smol::channel::{Receiver, Sender};
 use task::{HideStrategy, Shell, SpawnInTerminal};
 use terminal_hyperlinks::RegexSearches;
@@ -59,7 +63,10 @@ use std::{
     ops::{Deref, RangeInclusive},
     path::PathBuf,
     process::ExitStatus,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
     time::Instant,
 };
 use thiserror::Error;
@@ -105,6 +112,10 @@ actions!(
         ToggleViMode,
         /// Selects all text in the terminal.
         SelectAll,
+        /// Scrolls up to the prompt of the previous command run in the shell.
+        ScrollToPreviousCommand,
+        /// Scrolls down to the prompt of the next command run in the shell.
+        ScrollToNextCommand,
     ]
 );
 
@@ -125,6 +136,14 @@ pub enum Event {
     SelectionsChanged,
     NewNavigationTarget(Option<MaybeNavigationTarget>),
     Open(MaybeNavigationTarget),
+    /// The shell reported a new working directory.
+    WorkingDirectoryChanged,
+    /// A command was submitted in the shell.
+    CommandStarted,
+    /// A command run in the shell finished.
+    CommandFinished {
+        exit_code: Option<i32>,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -136,6 +155,41 @@ pub struct PathLikeTarget {
     pub terminal_dir: Option<PathBuf>,
 }
 
+/// A position in the scrollback of a terminal, with lines counted from the top of the scrollback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScrollbackPoint {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ScrollbackPoint {
+    fn new(point: AlacPoint, history_size: usize) -> Self {
+        Self {
+            line: (history_size as i32 + point.line.0).max(0) as usize,
+            column: point.column.0,
+        }
+    }
+}
+
+/// A match of [`Terminal::search_scrollback`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScrollbackMatch {
+    pub start: ScrollbackPoint,
+    /// The position of the last character of the match.
+    pub end: ScrollbackPoint,
+    pub text: String,
+}
+
+/// How [`Terminal::search_scrollback`] matches its query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScrollbackSearchOptions {
+    /// Whether the query is a regex, rather than text to match literally.
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Whether to only match the query where it's surrounded by word boundaries.
+    pub whole_word: bool,
+}
+
 /// A string inside terminal, potentially useful as a URI that can be opened.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MaybeNavigationTarget {
@@ -321,10 +375,85 @@ impl Display for TerminalError {
 // https://github.com/alacritty/alacritty/blob/cb3a79dbf6472740daca8440d5166c1d4af5029e/extra/man/alacritty.5.scd?plain=1#L207-L213
 const DEFAULT_SCROLL_HISTORY_LINES: usize = 10_000;
 pub const MAX_SCROLL_HISTORY_LINES: usize = 100_000;
+/// The lines alacritty may keep past the scrollback limit until [`Terminal::trim_scrollback`] drops them, so that the
+/// terminal knows how many lines left the scrollback.
+const SCROLLBACK_SLACK_LINES: usize = 10_000;
+
+/// Counts the lines that left the top of the scrollback, to count lines from the first one the terminal printed.
+///
+/// Shared with the PTY reader, which positions the shell integration sequences as they're parsed, and only changed while
+/// the terminal is locked.
+pub(crate) struct Scrollback {
+    /// The lines of scrollback to keep, without the slack alacritty is allowed on top of it.
+    limit: usize,
+    scrolled_out_lines: AtomicUsize,
+    /// Whether alacritty dropped lines itself before they were counted.
+    overflowed: AtomicBool,
+}
+
+impl Scrollback {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            scrolled_out_lines: AtomicUsize::new(0),
+            overflowed: AtomicBool::new(false),
+        }
+    }
+
+    /// Drops the scrollback lines past the limit, which alacritty keeps until the slack runs out, counting them.
+    fn trim(&self, term: &mut Term<AlacrittyListener>) {
+        let history_size = term.grid().history_size();
+        if history_size <= self.limit {
+            return;
+        }
+        if history_size >= self.limit + SCROLLBACK_SLACK_LINES {
+            self.overflowed.store(true, Ordering::SeqCst);
+        }
+        term.grid_mut().update_history(self.limit);
+        term.grid_mut()
+            .update_history(self.limit + SCROLLBACK_SLACK_LINES);
+        self.scroll_out(history_size - self.limit);
+    }
+
+    fn scroll_out(&self, lines: usize) {
+        self.scrolled_out_lines.fetch_add(lines, Ordering::SeqCst);
+    }
+
+    fn scrolled_out_lines(&self) -> usize {
+        self.scrolled_out_lines.load(Ordering::SeqCst)
+    }
+
+    /// Whether alacritty dropped lines itself since the last call, so that lines counted before can't be trusted.
+    fn take_overflowed(&self) -> bool {
+        self.overflowed.swap(false, Ordering::SeqCst)
+    }
+
+    /// Where the cursor is, once the scrollback was trimmed.
+    pub(crate) fn cursor(&self, term: &mut Term<AlacrittyListener>) -> ScrollbackPoint {
+        self.trim(term);
+        let point = term.grid().cursor.point;
+        ScrollbackPoint {
+            line: self.absolute_line(term.grid().history_size(), point.line),
+            column: point.column.0,
+        }
+    }
+
+    /// The number of a line of the grid, counted from the first line the terminal printed.
+    fn absolute_line(&self, history_size: usize, line: Line) -> usize {
+        (self.scrolled_out_lines() + history_size).saturating_add_signed(line.0 as isize)
+    }
+
+    /// The line of the grid a line counted from the first one the terminal printed is on.
+    fn grid_line(&self, history_size: usize, line: usize) -> Line {
+        let line = line as i64 - (self.scrolled_out_lines() + history_size) as i64;
+        Line(line.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
 
 pub struct TerminalBuilder {
     terminal: Terminal,
     events_rx: UnboundedReceiver<AlacTermEvent>,
+    shell_integration_rx: Option<UnboundedReceiver<(ShellIntegrationEvent, ScrollbackPoint)>>,
 }
 
 impl TerminalBuilder {
@@ -340,7 +469,7 @@ impl TerminalBuilder {
             .unwrap_or(DEFAULT_SCROLL_HISTORY_LINES)
             .min(MAX_SCROLL_HISTORY_LINES);
         let config = Config {
-            scrolling_history,
+            scrolling_history: scrolling_history + SCROLLBACK_SLACK_LINES,
             default_cursor_style,
             ..Config::default()
         };
@@ -364,6 +493,7 @@ impl TerminalBuilder {
             completion_tx: None,
             term,
             term_config: config,
+            scrollback: Arc::new(Scrollback::new(scrolling_history)),
             title_override: None,
             events: VecDeque::with_capacity(10),
             last_content: Default::default(),
@@ -396,11 +526,16 @@ impl TerminalBuilder {
             },
             child_exited: None,
             event_loop_task: Task::ready(Ok(())),
+            shell_working_directory: None,
+            commands: Vec::new(),
+            shell_integration_task: Task::ready(Ok(())),
+            env_profile: None,
         };
 
         Ok(TerminalBuilder {
             terminal,
             events_rx,
+            shell_integration_rx: None,
         })
     }
 
@@ -531,7 +666,7 @@ impl TerminalBuilder {
                     .min(MAX_SCROLL_HISTORY_LINES)
             };
             let config = Config {
-                scrolling_history,
+                scrolling_history: scrolling_history + SCROLLBACK_SLACK_LINES,
                 default_cursor_style,
                 ..Config::default()
             };
@@ -568,6 +703,14 @@ impl TerminalBuilder {
             let term = Arc::new(FairMutex::new(term));
 
             let pty_info = PtyProcessInfo::new(&pty);
+            let scrollback = Arc::new(Scrollback::new(scrolling_history));
+            let (shell_integration_tx, shell_integration_rx) = unbounded();
+            let pty = ShellIntegrationPty::new(
+                pty,
+                term.clone(),
+                scrollback.clone(),
+                shell_integration_tx,
+            );
 
             //And connect them together
             let event_loop = EventLoop::new(
@@ -592,6 +735,7 @@ impl TerminalBuilder {
                 completion_tx,
                 term,
                 term_config: config,
+                scrollback,
                 title_override: terminal_title_override,
                 events: VecDeque::with_capacity(10), //Should never get this high.
                 last_content: Default::default(),
@@ -627,6 +771,10 @@ impl TerminalBuilder {
                 },
                 child_exited: None,
                 event_loop_task: Task::ready(Ok(())),
+                shell_working_directory: None,
+                commands: Vec::new(),
+                shell_integration_task: Task::ready(Ok(())),
+                env_profile: None,
             };
 
             if !activation_script.is_empty() && no_task {
@@ -653,6 +801,7 @@ impl TerminalBuilder {
             Ok(TerminalBuilder {
                 terminal,
                 events_rx,
+                shell_integration_rx: Some(shell_integration_rx),
             })
         };
         // the thread we spawn things on has an effect on signal handling
@@ -664,6 +813,17 @@ impl TerminalBuilder {
     }
 
     pub fn subscribe(mut self, cx: &Context<Terminal>) -> Terminal {
+        if let Some(mut shell_integration_rx) = self.shell_integration_rx.take() {
+            self.terminal.shell_integration_task = cx.spawn(async move |terminal, cx| {
+                while let Some((event, cursor)) = shell_integration_rx.next().await {
+                    terminal.update(cx, |terminal, cx| {
+                        terminal.process_shell_integration_event(event, cursor, cx);
+                    })?;
+                }
+                anyhow::Ok(())
+            });
+        }
+
         //Event loop
         self.terminal.event_loop_task = cx.spawn(async move |terminal, cx| {
             while let Some(event) = self.events_rx.next().await {
@@ -805,6 +965,26 @@ impl Default for TerminalContent {
     }
 }
 
+/// Interrupts the command running in a terminal when dropped, unless it was disarmed. See
+/// [`Terminal::interrupt_on_drop`].
+#[must_use]
+pub struct InterruptOnDrop(Option<EventLoopSender>);
+
+impl InterruptOnDrop {
+    /// Keeps the command running when the guard is dropped.
+    pub fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for InterruptOnDrop {
+    fn drop(&mut self) {
+        if let Some(pty_tx) = self.0.take() {
+            pty_tx.send(Msg::Input(Cow::Borrowed(b"\x03"))).ok();
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum SelectionPhase {
     Selecting,
@@ -824,6 +1004,7 @@ pub struct Terminal {
     completion_tx: Option<Sender<Option<ExitStatus>>>,
     term: Arc<FairMutex<Term<AlacrittyListener>>>,
     term_config: Config,
+    scrollback: Arc<Scrollback>,
     events: VecDeque<InternalEvent>,
     /// This is only used for mouse mode cell change detection
     last_mouse: Option<(AlacPoint, AlacDirection)>,
@@ -849,6 +1030,13 @@ pub struct Terminal {
     activation_script: Vec<String>,
     child_exited: Option<ExitStatus>,
     event_loop_task: Task<Result<(), anyhow::Error>>,
+    /// The working directory last reported by the shell, when it has shell integration.
+    shell_working_directory: Option<PathBuf>,
+    /// The commands run in the shell, when it has shell integration, oldest first.
+    commands: Vec<TerminalCommand>,
+    shell_integration_task: Task<Result<(), anyhow::Error>>,
+    /// The name of the env profile the terminal was started with.
+    env_profile: Option<String>,
 }
 
 struct CopyTemplate {
@@ -897,6 +1085,9 @@ impl TaskStatus {
 
 const FIND_HYPERLINK_THROTTLE_PX: Pixels = px(5.0);
 
+/// How many of the commands run in a terminal are remembered for navigating between them.
+const MAX_COMMANDS: usize = 1000;
+
 impl Terminal {
     fn process_event(&mut self, event: AlacTermEvent, cx: &mut Context<Self>) {
         match event {
@@ -952,6 +1143,8 @@ impl Terminal {
                 //NOOP, Handled in render
             }
             AlacTermEvent::Wakeup => {
+                let term = self.term.clone();
+                self.trim_scrollback(&mut term.lock());
                 cx.emit(Event::Wakeup);
 
                 if let TerminalType::Pty { info, .. } = &mut self.terminal_type {
@@ -979,6 +1172,165 @@ impl Terminal {
         }
     }
 
+    /// Handles a shell integration sequence, with where the cursor was when the terminal emulator got to it.
+    fn process_shell_integration_event(
+        &mut self,
+        event: ShellIntegrationEvent,
+        cursor: ScrollbackPoint,
+        cx: &mut Context<Self>,
+    ) {
+        self.forget_scrolled_out_commands();
+        let line = cursor.line;
+        match event {
+            ShellIntegrationEvent::PromptStart => {
+                // A prompt redrawn before any command was submitted replaces the previous one.
+                if self
+                    .commands
+                    .last()
+                    .is_some_and(|command| command.output_line.is_none())
+                {
+                    self.commands.pop();
+                }
+                if self.commands.len() >= MAX_COMMANDS {
+                    self.commands.remove(0);
+                }
+                self.commands.push(TerminalCommand {
+                    prompt_line: line,
+                    working_directory: self.shell_working_directory.clone(),
+                    output_line: None,
+                    output_end: None,
+                    finished: false,
+                    exit_code: None,
+                });
+            }
+            ShellIntegrationEvent::CommandStart => {}
+            ShellIntegrationEvent::CommandExecuted => {
+                if let Some(command) = self.commands.last_mut() {
+                    command.output_line = Some(line);
+                }
+                cx.emit(Event::CommandStarted);
+            }
+            ShellIntegrationEvent::CommandFinished { exit_code } => {
+                if let Some(command) = self
+                    .commands
+                    .last_mut()
+                    .filter(|command| command.output_line.is_some() && !command.finished)
+                {
+                    command.output_end = Some(cursor);
+                    command.finished = true;
+                    command.exit_code = exit_code;
+                    cx.emit(Event::CommandFinished { exit_code });
+                }
+            }
+            ShellIntegrationEvent::WorkingDirectory(path) => {
+                if self.shell_working_directory.as_ref() != Some(&path) {
+                    self.shell_working_directory = Some(path);
+                    cx.emit(Event::WorkingDirectoryChanged);
+                    cx.emit(Event::TitleChanged);
+                }
+            }
+        }
+    }
+
+    /// The directory to resolve the relative paths printed on `line` of the grid against: the one the shell was in
+    /// when it ran the command that printed them, if it reported it, or else the terminal's working directory.
+    fn working_directory_at(&self, history_size: usize, line: Line) -> Option<PathBuf> {
+        if self.is_remote_terminal {
+            return None;
+        }
+        let line = self.absolute_line(history_size, line);
+        self.commands
+            .iter()
+            .rev()
+            .find(|command| command.prompt_line <= line)
+            .and_then(|command| command.working_directory.clone())
+            .or_else(|| self.working_directory())
+    }
+
+    /// Drops the scrollback lines past the limit, counting them so that the lines of commands stay right once the
+    /// scrollback is full.
+    fn trim_scrollback(&mut self, term: &mut Term<AlacrittyListener>) {
+        self.scrollback.trim(term);
+        self.forget_scrolled_out_commands();
+    }
+
+    /// Forgets the commands that left the scrollback along with the prompt of the command after them, or all of them
+    /// when alacritty dropped lines before they were counted, as their lines can't be trusted anymore.
+    fn forget_scrolled_out_commands(&mut self) {
+        if self.scrollback.take_overflowed() {
+            self.commands.clear();
+            return;
+        }
+        let first_line = self.scrollback.scrolled_out_lines();
+        let scrolled_out_commands = self
+            .commands
+            .windows(2)
+            .take_while(|commands| commands[1].prompt_line <= first_line)
+            .count();
+        self.commands.drain(..scrolled_out_commands);
+    }
+
+    /// The number of a line of the grid, counted from the first line the terminal printed.
+    fn absolute_line(&self, history_size: usize, line: Line) -> usize {
+        self.scrollback.absolute_line(history_size, line)
+    }
+
+    /// The line of the grid a line counted from the first one the terminal printed is on.
+    fn grid_line(&self, history_size: usize, line: usize) -> Line {
+        self.scrollback.grid_line(history_size, line)
+    }
+
+    /// The name of the env profile, from the `terminal.env_profiles` setting, the terminal was started with.
+    pub fn env_profile(&self) -> Option<&str> {
+        self.env_profile.as_deref()
+    }
+
+    pub fn set_env_profile(&mut self, env_profile: Option<String>) {
+        self.env_profile = env_profile;
+    }
+
+    /// The commands run in the shell, oldest first. Empty unless the shell has shell integration.
+    pub fn commands(&self) -> &[TerminalCommand] {
+        &self.commands
+    }
+
+    /// Whether the shell is waiting at its prompt for a command. Always false without shell integration.
+    pub fn is_at_prompt(&self) -> bool {
+        self.commands
+            .last()
+            .is_some_and(|command| command.output_line.is_none())
+    }
+
+    /// The output of a finished command, without trailing empty lines, or `None` if it's still running or its output
+    /// left the scrollback.
+    pub fn command_output(&self, command: &TerminalCommand) -> Option<String> {
+        let start = command.output_line?;
+        let end = command.output_end?;
+        let term = self.term.lock();
+        let history_size = term.grid().history_size();
+        let start = AlacPoint::new(self.grid_line(history_size, start), Column(0));
+        if start.line < term.topmost_line() {
+            return None;
+        }
+        // The command's output ends right before where the cursor was when it finished.
+        let end_line = self.grid_line(history_size, end.line);
+        let end = if end.column > 0 {
+            AlacPoint::new(end_line, Column(end.column - 1))
+        } else {
+            AlacPoint::new(end_line - 1, term.last_column())
+        };
+        if end < start {
+            return Some(String::new());
+        }
+        let output = term.bounds_to_string(start, end);
+        let output = output.trim_end_matches('\n');
+        Some(if output.is_empty() {
+            String::new()
+        } else {
+            format!("{output}\n")
+        })
+    }
+
     pub fn selection_started(&self) -> bool {
         self.selection_phase == SelectionPhase::Selecting
     }
@@ -1013,6 +1365,11 @@ impl Terminal {
             }
             InternalEvent::Clear => {
                 trace!("Clearing");
+                // The lines above the cursor's leave the scrollback.
+                self.scrollback.scroll_out(
+                    term.grid().history_size() + term.grid().cursor.point.line.0.max(0) as usize,
+                );
+
                 // Clear back buffer
                 term.clear_screen(ClearMode::Saved);
 
@@ -1042,6 +1399,9 @@ impl Terminal {
                     term.grid_mut().reset_region((new_cursor.line + 1)..);
                 }
 
+                // The prompts of the cleared commands are gone.
+                self.commands.clear();
+
                 cx.emit(Event::Wakeup);
             }
             InternalEvent::Scroll(scroll) => {
@@ -1169,7 +1529,9 @@ impl Terminal {
                     &mut self.hyperlink_regex_searches,
                 ) {
                     Some(hyperlink) => {
-                        self.process_hyperlink(hyperlink, *open, cx);
+                        let terminal_dir =
+                            self.working_directory_at(term.grid().history_size(), point.line);
+                        self.process_hyperlink(hyperlink, terminal_dir, *open, cx);
                     }
                     None => {
                         cx.emit(Event::NewNavigationTarget(None));
@@ -1177,7 +1539,9 @@ impl Terminal {
                 }
             }
             InternalEvent::ProcessHyperlink(hyperlink, open) => {
-                self.process_hyperlink(hyperlink.clone(), *open, cx);
+                let terminal_dir =
+                    self.working_directory_at(term.grid().history_size(), hyperlink.2.start().line);
+                self.process_hyperlink(hyperlink.clone(), terminal_dir, *open, cx);
             }
         }
     }
@@ -1185,6 +1549,7 @@ impl Terminal {
     fn process_hyperlink(
         &mut self,
         hyperlink: (String, bool, Match),
+        terminal_dir: Option<PathBuf>,
         open: bool,
         cx: &mut Context<Self>,
     ) {
@@ -1199,7 +1564,7 @@ impl Terminal {
 
                 MaybeNavigationTarget::PathLike(PathLikeTarget {
                     maybe_path: decoded_path,
-                    terminal_dir: self.working_directory(),
+                    terminal_dir,
                 })
             } else {
                 MaybeNavigationTarget::Url(maybe_url_or_path.clone())
@@ -1207,7 +1572,7 @@ impl Terminal {
         } else {
             MaybeNavigationTarget::PathLike(PathLikeTarget {
                 maybe_path: maybe_url_or_path.clone(),
-                terminal_dir: self.working_directory(),
+                terminal_dir,
             })
         };
 
@@ -1286,9 +1651,25 @@ impl Terminal {
         let mut processor = alacritty_terminal::vte::ansi::Processor::<
             alacritty_terminal::vte::ansi::StdSyncHandler,
         >::new();
+        // Like the PTY reader, position the shell integration sequences where the emulator got to them.
+        let mut scanner = ShellIntegrationScanner::default();
+        let mut shell_integration_events = Vec::new();
         {
-            let mut term = self.term.lock();
-            processor.advance(&mut *term, &converted);
+            let term = self.term.clone();
+            let mut term = term.lock();
+            let mut output = converted.as_slice();
+            while !output.is_empty() {
+                let (scanned_len, event) = scanner.scan(output);
+                processor.advance(&mut *term, &output[..scanned_len]);
+                output = &output[scanned_len..];
+                if let Some(event) = event {
+                    shell_integration_events.push((event, self.scrollback.cursor(&mut term)));
+                }
+            }
+            self.trim_scrollback(&mut term);
+        }
+        for (event, cursor) in shell_integration_events {
+            self.process_shell_integration_event(event, cursor, cx);
         }
         cx.emit(Event::Wakeup);
     }
@@ -1394,6 +1775,50 @@ impl Terminal {
             .push_back(InternalEvent::Scroll(AlacScroll::Bottom));
     }
 
+    /// Scrolls the prompt of the command above the top of the viewport to the top.
+    pub fn scroll_to_previous_command(&mut self) {
+        let top_line = self.top_line();
+        if let Some(command) = self
+            .commands
+            .iter()
+            .rev()
+            .find(|command| command.prompt_line < top_line)
+        {
+            self.scroll_to_line(top_line, command.prompt_line);
+        }
+    }
+
+    /// Scrolls the prompt of the command below the top of the viewport to the top, or to the bottom
+    /// when there is none.
+    pub fn scroll_to_next_command(&mut self) {
+        let top_line = self.top_line();
+        match self
+            .commands
+            .iter()
+            .find(|command| command.prompt_line > top_line)
+        {
+            Some(command) => self.scroll_to_line(top_line, command.prompt_line),
+            None => self.scroll_to_bottom(),
+        }
+    }
+
+    /// The line at the top of the viewport, counted from the first line the terminal printed.
+    fn top_line(&self) -> usize {
+        let term = self.term.lock();
+        self.absolute_line(
+            term.grid().history_size(),
+            Line(-(term.grid().display_offset() as i32)),
+        )
+    }
+
+    fn scroll_to_line(&mut self, top_line: usize, line: usize) {
+        let delta = top_line as i64 - line as i64;
+        self.events
+            .push_back(InternalEvent::Scroll(AlacScroll::Delta(
+                delta.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+            )));
+    }
+
     pub fn scrolled_to_top(&self) -> bool {
         self.last_content.scrolled_to_top
     }
@@ -1437,6 +1862,53 @@ impl Terminal {
         self.events.push_back(InternalEvent::ToggleViMode);
     }
 
+    /// Types text into the terminal, without pressing enter.
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.ensure_accepts_input()?;
+        self.input(text.as_bytes().to_vec());
+        Ok(())
+    }
+
+    /// Presses keys in the terminal, given as keystrokes separated by spaces, like `ctrl-c` or `up enter`.
+    pub fn send_keys(&mut self, keys: &str, cx: &App) -> Result<()> {
+        self.ensure_accepts_input()?;
+        let keystrokes = keys
+            .split_whitespace()
+            .map(Keystroke::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        let option_as_meta = TerminalSettings::get_global(cx).option_as_meta;
+        for keystroke in keystrokes {
+            let esc = to_esc_str(&keystroke, &self.last_content.mode, option_as_meta);
+            let input = match (esc, &keystroke.key_char) {
+                (Some(esc), _) => esc.into_owned(),
+                (None, Some(key_char)) => key_char.clone(),
+                (None, None) if keystroke.key.chars().count() == 1 => keystroke.key.clone(),
+                (None, None) => bail!("can't send `{}` to a terminal", keystroke.unparse()),
+            };
+            self.input(input.into_bytes());
+        }
+        Ok(())
+    }
+
+    /// Returns a guard that presses ctrl-c in the terminal when dropped, to interrupt a command run on the user's
+    /// behalf when whoever waits for it gives up.
+    pub fn interrupt_on_drop(&self) -> InterruptOnDrop {
+        InterruptOnDrop(match &self.terminal_type {
+            TerminalType::Pty { pty_tx, .. } => Some(pty_tx.0.clone()),
+            TerminalType::DisplayOnly => None,
+        })
+    }
+
+    fn ensure_accepts_input(&self) -> Result<()> {
+        if !matches!(self.terminal_type, TerminalType::Pty { .. }) {
+            bail!("the terminal doesn't run a process to send input to");
+        }
+        if let Some(status) = self.child_exited {
+            bail!("the terminal's process exited with {status}");
+        }
+        Ok(())
+    }
+
     pub fn vi_motion(&mut self, keystroke: &Keystroke) {
         if !self.vi_mode_enabled {
             return;
@@ -1576,6 +2048,7 @@ impl Terminal {
     pub fn sync(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         let term = self.term.clone();
         let mut terminal = term.lock_unfair();
+        self.trim_scrollback(&mut terminal);
         //Note that the ordering of events matters for event processing
         while let Some(e) = self.events.pop_front() {
             self.process_terminal_event(&e, &mut terminal, window, cx)
@@ -1627,6 +2100,27 @@ impl Terminal {
         term.bounds_to_string(start, end)
     }
 
+    /// Returns the text printed from `line`, counted from the first line the terminal printed, to the cursor, along
+    /// with the line of the cursor to continue from next time, so that output can be scanned as it arrives.
+    ///
+    /// Without a line, the whole content is returned, and lines that already left the scrollback are skipped.
+    pub fn output_since(&self, line: Option<usize>) -> (String, usize) {
+        let term = self.term.lock_unfair();
+        let history_size = term.grid().history_size();
+        let cursor = term.grid().cursor.point;
+        let cursor_line = self.absolute_line(history_size, cursor.line);
+        let start = match line {
+            None => term.topmost_line(),
+            Some(line) if line < cursor_line => {
+                self.grid_line(history_size, line).max(term.topmost_line())
+            }
+            Some(_) => Line(0),
+        };
+        let end = AlacPoint::new(cursor.line, term.last_column());
+        let output = term.bounds_to_string(AlacPoint::new(start, Column(0)), end);
+        (output, cursor_line)
+    }
+
     pub fn last_n_non_empty_lines(&self, n: usize) -> Vec<String> {
         let term = self.term.clone();
         let terminal = term.lock_unfair();
@@ -2058,6 +2552,53 @@ impl Terminal {
         })
     }
 
+    /// Searches the whole scrollback, returning the matches from the top of the scrollback down.
+    pub fn search_scrollback(
+        &self,
+        query: &str,
+        options: ScrollbackSearchOptions,
+        cx: &App,
+    ) -> Task<Result<Vec<ScrollbackMatch>>> {
+        if query.is_empty() {
+            return Task::ready(Ok(Vec::new()));
+        }
+        let mut pattern = if options.regex {
+            query.to_owned()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+        // Alacritty's searches are case sensitive only for queries with uppercase letters, unless a flag says otherwise.
+        let case_flag = if options.case_sensitive { "-i" } else { "i" };
+        let pattern = format!("(?{case_flag}){pattern}");
+        let term = self.term.clone();
+        cx.background_spawn(async move {
+            let mut searcher = RegexSearch::new(&pattern)
+                .map_err(|error| anyhow!("invalid scrollback search query: {error}"))?;
+            let term = term.lock();
+            let history_size = term.grid().history_size();
+            Ok(all_search_matches(&term, &mut searcher)
+                .map(|search_match| ScrollbackMatch {
+                    start: ScrollbackPoint::new(*search_match.start(), history_size),
+                    end: ScrollbackPoint::new(*search_match.end(), history_size),
+                    text: term.bounds_to_string(*search_match.start(), *search_match.end()),
+                })
+                .collect())
+        })
+    }
+
+    /// The text of the whole scrollback, without the empty lines below the last output.
+    pub fn scrollback_text(&self) -> String {
+        let mut text = self.get_content();
+        text.truncate(text.trim_end().len());
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+
     pub fn working_directory(&self) -> Option<PathBuf> {
         if self.is_remote_terminal {
             // We can't yet reliably detect the working directory of a shell on the
@@ -2065,7 +2606,9 @@ impl Terminal {
             // the working directory on the client and persist that.
             None
         } else {
-            self.client_side_working_directory()
+            self.shell_working_directory
+                .clone()
+                .or_else(|| self.client_side_working_directory())
         }
     }
 
@@ -3077,6 +3620,217 @@ mod tests {
         });
     }
 
+    #[gpui::test]
+    async fn test_relative_paths_resolve_against_command_working_directory(
+        cx: &mut TestAppContext,
+    ) {
+        let terminal = init_ctrl_click_hyperlink_test(cx, b"");
+
+        terminal.update(cx, |terminal, cx| {
+            for (dir, output) in [("/one", "src/main.rs"), ("/two", "lib.rs")] {
+                terminal.write_output(
+                    format!(
+                        "\x1b]7;file://host{dir}\x07\x1b]133;A\x07$ ls\r\n\x1b]133;C\x07{output}\r\n\x1b]133;D;0\x07"
+                    )
+                    .as_bytes(),
+                    cx,
+                );
+            }
+
+            assert_eq!(
+                terminal
+                    .commands()
+                    .iter()
+                    .map(|command| command.prompt_line)
+                    .collect::<Vec<_>>(),
+                vec![0, 2]
+            );
+            assert_eq!(
+                terminal.working_directory_at(0, Line(1)),
+                Some(PathBuf::from("/one"))
+            );
+            assert_eq!(
+                terminal.working_directory_at(0, Line(3)),
+                Some(PathBuf::from("/two"))
+            );
+            assert_eq!(terminal.working_directory(), Some(PathBuf::from("/two")));
+        });
+    }
+
+    #[gpui::test]
+    async fn test_command_output(cx: &mut TestAppContext) {
+        let terminal = init_ctrl_click_hyperlink_test(cx, b"");
+
+        terminal.update(cx, |terminal, cx| {
+            terminal.write_output(b"\x1b]133;A\x07$ ls\r\n", cx);
+            assert!(terminal.is_at_prompt());
+            terminal.write_output(b"\x1b]133;C\x07a.rs\r\nb.rs\r\n", cx);
+            assert!(!terminal.is_at_prompt());
+            assert_eq!(terminal.command_output(&terminal.commands()[0]), None);
+            terminal.write_output(b"\x1b]133;D;0\x07", cx);
+
+            // The sequences are positioned where they are in the output, even when it's written at once.
+            terminal.write_output(
+                b"\x1b]133;A\x07$ true\r\n\x1b]133;C\x07\x1b]133;D;1\x07\x1b]133;A\x07$ ",
+                cx,
+            );
+            assert!(terminal.is_at_prompt());
+
+            let commands = terminal.commands().to_vec();
+            assert_eq!(commands.len(), 3);
+            assert_eq!(
+                commands
+                    .iter()
+                    .map(|command| command.prompt_line)
+                    .collect::<Vec<_>>(),
+                vec![0, 3, 4]
+            );
+            assert_eq!(
+                terminal.command_output(&commands[0]).as_deref(),
+                Some("a.rs\nb.rs\n")
+            );
+            assert_eq!(commands[1].exit_code, Some(1));
+            assert_eq!(terminal.command_output(&commands[1]).as_deref(), Some(""));
+
+            // Display-only terminals have no process to take input.
+            assert!(terminal.send_text("ls").is_err());
+        });
+    }
+
+    #[gpui::test]
+    async fn test_command_output_once_scrollback_is_full(cx: &mut TestAppContext) {
+        fn run_command(
+            terminal: &mut Terminal,
+            command: &str,
+            output: &str,
+            cx: &mut Context<Terminal>,
+        ) {
+            terminal.write_output(
+                format!("\x1b]133;A\x07$ {command}\r\n\x1b]133;C\x07{output}\x1b]133;D;0\x07")
+                    .as_bytes(),
+                cx,
+            );
+        }
+
+        cx.update(|cx| {
+            let settings_store = settings::SettingsStore::test(cx);
+            cx.set_global(settings_store);
+        });
+
+        let terminal = cx.new(|cx| {
+            TerminalBuilder::new_display_only(
+                CursorShape::default(),
+                AlternateScroll::On,
+                Some(5),
+                0,
+            )
+            .unwrap()
+            .subscribe(cx)
+        });
+
+        terminal.update(cx, |terminal, cx| {
+            let long_output = (0..50)
+                .map(|line| format!("line {line}\r\n"))
+                .collect::<String>();
+            run_command(terminal, "seq 50", &long_output, cx);
+            run_command(terminal, "ls", "a.rs\r\nb.rs\r\n", cx);
+            terminal.write_output(b"\x1b]133;A\x07$ ", cx);
+
+            assert_eq!(terminal.term.lock().grid().history_size(), 5);
+            let commands = terminal.commands().to_vec();
+            assert_eq!(commands.len(), 3);
+            // The first command's output left the scrollback, the second one's didn't move with it.
+            assert_eq!(terminal.command_output(&commands[0]), None);
+            assert_eq!(
+                terminal.command_output(&commands[1]).as_deref(),
+                Some("a.rs\nb.rs\n")
+            );
+        });
+    }
+
+    #[gpui::test]
+    async fn test_output_since(cx: &mut TestAppContext) {
+        let terminal = init_ctrl_click_hyperlink_test(cx, b"Compiling\r\n");
+
+        terminal.update(cx, |terminal, cx| {
+            let (output, line) = terminal.output_since(None);
+            assert!(output.contains("Compiling"));
+
+            terminal.write_output(b"Watching for file changes\r\n", cx);
+            let (output, next_line) = terminal.output_since(Some(line));
+            assert!(output.contains("Watching for file changes"));
+            assert!(!output.contains("Compiling"));
+            assert_eq!(next_line, line + 1);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_search_scrollback(cx: &mut TestAppContext) {
+        let terminal =
+            init_ctrl_click_hyperlink_test(cx, b"error: a.rs\r\nError: b.rs\r\nerrors: 2\r\n\r\n");
+
+        let search = |query: &str, options: ScrollbackSearchOptions, cx: &mut TestAppContext| {
+            terminal.update(cx, |terminal, cx| {
+                terminal.search_scrollback(query, options, cx)
+            })
+        };
+
+        let matches = search("Error", ScrollbackSearchOptions::default(), cx)
+            .await
+            .unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|search_match| (search_match.start, search_match.text.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (ScrollbackPoint { line: 0, column: 0 }, "error"),
+                (ScrollbackPoint { line: 1, column: 0 }, "Error"),
+                (ScrollbackPoint { line: 2, column: 0 }, "error"),
+            ]
+        );
+        assert_eq!(matches[0].end, ScrollbackPoint { line: 0, column: 4 });
+
+        let case_sensitive = ScrollbackSearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(search("error", case_sensitive, cx).await.unwrap().len(), 2);
+        let whole_word = ScrollbackSearchOptions {
+            whole_word: true,
+            ..case_sensitive
+        };
+        assert_eq!(search("error", whole_word, cx).await.unwrap().len(), 1);
+        let regex = ScrollbackSearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            search(r"\w+\.rs", regex, cx)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|search_match| search_match.text)
+                .collect::<Vec<_>>(),
+            vec!["a.rs", "b.rs"]
+        );
+        assert_eq!(search("a.rs", regex, cx).await.unwrap().len(), 1);
+        assert!(search("(", regex, cx).await.is_err());
+        assert_eq!(
+            search("(", ScrollbackSearchOptions::default(), cx)
+                .await
+                .unwrap(),
+            Vec::new()
+        );
+
+        terminal.update(cx, |terminal, _| {
+            assert_eq!(
+                terminal.scrollback_text(),
+                "error: a.rs\nError: b.rs\nerrors: 2\n"
+            );
+        });
+    }
+
     mod perf {
         use super::super::*;
         use gpui::{
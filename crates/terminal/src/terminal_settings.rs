@@ -42,6 +42,9 @@ pub struct TerminalSettings {
     pub default_width: Pixels,
     pub default_height: Pixels,
     pub detect_venv: VenvSettings,
+    pub shell_integration: bool,
+    pub env_profiles: HashMap<String, TerminalEnvProfile>,
+    pub default_env_profile: Option<String>,
     pub max_scroll_history_lines: Option<usize>,
     pub scroll_multiplier: f32,
     pub toolbar: Toolbar,
@@ -51,6 +54,16 @@ pub struct TerminalSettings {
     pub path_hyperlink_timeout_ms: u64,
 }
 
+/// A named environment that terminals can be started with.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct TerminalEnvProfile {
+    pub env: HashMap<String, String>,
+    /// Directories to prepend to `PATH`, in order.
+    pub path_prepend: Vec<String>,
+    /// Commands to run in the shell once it starts.
+    pub activation_script: Vec<String>,
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct ScrollbarSettings {
     /// When to show the scrollbar in the terminal.
@@ -109,6 +122,21 @@ impl settings::Settings for TerminalSettings {
             default_width: px(user_content.default_width.unwrap()),
             default_height: px(user_content.default_height.unwrap()),
             detect_venv: project_content.detect_venv.unwrap(),
+            shell_integration: project_content.shell_integration.unwrap(),
+            env_profiles: project_content
+                .env_profiles
+                .unwrap()
+                .into_iter()
+                .map(|(name, profile)| {
+                    let profile = TerminalEnvProfile {
+                        env: profile.env.unwrap_or_default(),
+                        path_prepend: profile.path_prepend.unwrap_or_default(),
+                        activation_script: profile.activation_script.unwrap_or_default(),
+                    };
+                    (name, profile)
+                })
+                .collect(),
+            default_env_profile: project_content.default_env_profile,
             scroll_multiplier: user_content.scroll_multiplier.unwrap(),
             max_scroll_history_lines: user_content.max_scroll_history_lines,
             toolbar: Toolbar {
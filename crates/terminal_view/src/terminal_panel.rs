@@ -583,6 +583,45 @@ impl TerminalPanel {
         cx.spawn(async move |_, _| rx.await?)
     }
 
+    /// Replaces the diagnostics of the task's previous run with the problems its problem matchers find in `output`.
+    fn update_task_diagnostics(&self, task: &SpawnInTerminal, output: Option<&str>, cx: &mut App) {
+        if task.problem_matchers.is_empty() {
+            return;
+        }
+        let Some(workspace) = self.workspace.upgrade() else {
+            return;
+        };
+        let problems = output
+            .map(|output| task::match_problems(&task.problem_matchers, output))
+            .unwrap_or_default();
+        let lsp_store = workspace.read(cx).project().read(cx).lsp_store();
+        lsp_store
+            .update(cx, |lsp_store, cx| {
+                lsp_store.update_task_diagnostics(
+                    &task.full_label,
+                    task.cwd.as_deref(),
+                    problems,
+                    cx,
+                )
+            })
+            .log_err();
+    }
+
+    fn watch_background_task(
+        &self,
+        task: &SpawnInTerminal,
+        terminal: &Entity<Terminal>,
+        cx: &mut App,
+    ) {
+        let Some(workspace) = self.workspace.upgrade() else {
+            return;
+        };
+        let task_store = workspace.read(cx).project().read(cx).task_store().clone();
+        task_store.update(cx, |task_store, cx| {
+            task_store.watch_background_task(task, terminal, cx)
+        });
+    }
+
     fn spawn_in_new_terminal(
         &mut self,
         spawn_task: SpawnInTerminal,
@@ -1689,16 +1728,34 @@ impl workspace::TerminalProvider for TerminalProvider {
         window.spawn(cx, async move |cx| {
             let terminal = terminal_panel
                 .update_in(cx, |terminal_panel, window, cx| {
+                    terminal_panel.update_task_diagnostics(&task, None, cx);
                     terminal_panel.spawn_task(&task, window, cx)
                 })
                 .ok()?
                 .await;
             match terminal {
                 Ok(terminal) => {
+                    if task.is_background {
+                        terminal_panel
+                            .update(cx, |terminal_panel, cx| {
+                                terminal_panel.watch_background_task(&task, &terminal, cx)
+                            })
+                            .ok();
+                    }
                     let exit_status = terminal
                         .read_with(cx, |terminal, cx| terminal.wait_for_completed_task(cx))
                         .ok()?
                         .await?;
+                    if !task.problem_matchers.is_empty()
+                        && let Ok(output) =
+                            terminal.read_with(cx, |terminal, _| terminal.get_content())
+                    {
+                        terminal_panel
+                            .update(cx, |terminal_panel, cx| {
+                                terminal_panel.update_task_diagnostics(&task, Some(&output), cx)
+                            })
+                            .ok();
+                    }
                     Some(Ok(exit_status))
                 }
                 Err(e) => Some(Err(e)),
@@ -1449,7 +1449,10 @@ impl Buffer {
         Some(self.undo_or_redo(transaction))
     }
 
-    pub fn undo_to_transaction(&mut self, transaction_id: TransactionId) -> Vec<Operation> {
+    pub fn undo_to_transaction(
+        &mut self,
+        transaction_id: TransactionId,
+    ) -> Vec<(TransactionId, Operation)> {
         let transactions = self
             .history
             .remove_from_undo_until(transaction_id)
@@ -1459,7 +1462,7 @@ impl Buffer {
 
         transactions
             .into_iter()
-            .map(|transaction| self.undo_or_redo(transaction))
+            .map(|transaction| (transaction.id, self.undo_or_redo(transaction)))
             .collect()
     }
 
@@ -1486,7 +1489,10 @@ impl Buffer {
         }
     }
 
-    pub fn redo_to_transaction(&mut self, transaction_id: TransactionId) -> Vec<Operation> {
+    pub fn redo_to_transaction(
+        &mut self,
+        transaction_id: TransactionId,
+    ) -> Vec<(TransactionId, Operation)> {
         let transactions = self
             .history
             .remove_from_redo(transaction_id)
@@ -1496,7 +1502,7 @@ impl Buffer {
 
         transactions
             .into_iter()
-            .map(|transaction| self.undo_or_redo(transaction))
+            .map(|transaction| (transaction.id, self.undo_or_redo(transaction)))
             .collect()
     }
 
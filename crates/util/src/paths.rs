@@ -1370,6 +1370,18 @@ impl WslPath {
             None
         }
     }
+
+    /// Returns the `wsl.exe` arguments that run a program inside of this path's distro,
+    /// with this path as the working directory. The program and its arguments should follow.
+    pub fn exec_args(&self) -> Vec<std::ffi::OsString> {
+        vec![
+            "--distribution".into(),
+            self.distro.clone().into(),
+            "--cd".into(),
+            self.path.clone(),
+            "--exec".into(),
+        ]
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,20 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
+
+/// Returns the number of seconds from the Unix epoch to the given time, or 0 for earlier times.
+pub fn unix_secs(time: SystemTime) -> u64 {
+    since_unix_epoch(time).as_secs()
+}
+
+/// Returns the number of milliseconds from the Unix epoch to the given time, or 0 for earlier
+/// times.
+pub fn unix_millis(time: SystemTime) -> u64 {
+    since_unix_epoch(time).as_millis() as u64
+}
+
+fn since_unix_epoch(time: SystemTime) -> Duration {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+}
 
 pub fn duration_alt_display(duration: Duration) -> String {
     if duration < Duration::from_secs(60) {
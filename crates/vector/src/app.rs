@@ -1,9 +1,13 @@
 mod app_menus;
+pub(crate) mod crash_recovery;
+pub(crate) mod extension_bisect;
+pub(crate) mod headless;
 #[cfg(target_os = "macos")]
 pub(crate) mod mac_only_instance;
 mod migrate;
 mod open_listener;
 mod quick_action_bar;
+pub(crate) mod startup_profile;
 #[cfg(target_os = "windows")]
 pub(crate) mod windows_only_instance;
 
@@ -11,7 +15,7 @@ use anyhow::Context as _;
 pub use app_menus::*;
 use assets::Assets;
 use breadcrumbs::Breadcrumbs;
-use collections::VecDeque;
+use collections::{HashSet, VecDeque};
 use debugger_ui::debugger_panel::DebugPanel;
 use editor::{Editor, MultiBuffer};
 use extension_host::ExtensionStore;
@@ -23,10 +27,10 @@ use git_ui::commit_view::CommitViewToolbar;
 use git_ui::git_panel::GitPanel;
 use git_ui::project_diff::ProjectDiffToolbar;
 use gpui::{
-    Action, App, AppContext as _, Context, DismissEvent, Element, Entity, Focusable, KeyBinding,
-    ParentElement, PathPromptOptions, PromptLevel, ReadGlobal, SharedString, TitlebarOptions,
-    UpdateGlobal, WeakEntity, Window, WindowKind, WindowOptions, actions, image_cache, point, px,
-    retain_all,
+    Action, App, AppContext as _, Context, DismissEvent, Element, Entity, Focusable, Global,
+    KeyBinding, ParentElement, PathPromptOptions, PromptLevel, ReadGlobal, SharedString, Task,
+    TitlebarOptions, UpdateGlobal, WeakEntity, Window, WindowId, WindowKind, WindowOptions,
+    actions, image_cache, point, px, retain_all,
 };
 use image_viewer::ImageInfo;
 use language::Capability;
@@ -56,9 +60,11 @@ use settings::{
 };
 use std::{
     borrow::Cow,
+    iter,
     path::{Path, PathBuf},
     sync::Arc,
     sync::atomic::{self, AtomicBool},
+    time::Duration,
 };
 use terminal_view::terminal_panel::{self, TerminalPanel};
 use theme::{ActiveTheme, GlobalTheme, SystemAppearance, ThemeRegistry, ThemeSettings};
@@ -67,7 +73,7 @@ use util::markdown::MarkdownString;
 use util::rel_path::RelPath;
 use util::{ResultExt, asset_str};
 use uuid::Uuid;
-use vector_actions::{OpenSettingsFile, OpenVectorUrl, Quit};
+use vector_actions::{OpenSettingsFile, OpenVectorUrl, Quit, SwitchProfile};
 use vim_mode_setting::VimModeSetting;
 use workspace::notifications::{NotificationId, dismiss_app_notification, show_app_notification};
 use workspace::{
@@ -241,7 +247,92 @@ pub fn init(cx: &mut App) {
     });
 }
 
+/// The windows opened for `--no-daemon` requests, which quit Vector once they're closed along with all other windows,
+/// even when configured to run in the background.
+#[derive(Default)]
+struct NoDaemonWindows {
+    window_ids: HashSet<WindowId>,
+    /// Set once one of the `window_ids` closed, to quit when no windows are left.
+    window_closed: bool,
+    /// Set when Vector was launched with `--no-daemon`, to quit once it has no windows for the first time.
+    launched_with_no_daemon: bool,
+}
+
+impl Global for NoDaemonWindows {}
+
+impl NoDaemonWindows {
+    /// Forgets the windows that closed, returning whether Vector should quit rather than keep running in the
+    /// background.
+    fn should_quit(&mut self, open_window_ids: &HashSet<WindowId>) -> bool {
+        let window_count = self.window_ids.len();
+        self.window_ids
+            .retain(|window_id| open_window_ids.contains(window_id));
+        if self.window_ids.len() < window_count {
+            self.window_closed = true;
+        }
+        open_window_ids.is_empty() && (self.window_closed || self.launched_with_no_daemon)
+    }
+}
+
+/// Makes Vector quit once its windows are closed, even when configured to run in the background, for `--no-daemon`
+/// passed when launching it.
+pub fn disable_daemon_for_launch(cx: &mut App) {
+    cx.default_global::<NoDaemonWindows>()
+        .launched_with_no_daemon = true;
+}
+
+/// Makes the windows of the workspaces opened until the returned subscription is dropped quit Vector once they're
+/// closed along with all other windows, even when configured to run in the background, for a `--no-daemon` request.
+pub fn disable_daemon_for_new_windows(cx: &mut App) -> gpui::Subscription {
+    cx.observe_new::<Workspace>(|_, window, cx| {
+        if let Some(window) = window {
+            cx.default_global::<NoDaemonWindows>()
+                .window_ids
+                .insert(window.window_handle().window_id());
+        }
+    })
+}
+
 fn bind_on_window_closed(cx: &mut App) -> Option<gpui::Subscription> {
+    if WorkspaceSettings::get_global(cx)
+        .on_last_window_closed
+        .is_run_in_background()
+    {
+        let mut idle_timeout = None;
+        return Some(cx.on_window_closed(move |cx| {
+            let open_window_ids = cx
+                .windows()
+                .iter()
+                .map(|window| window.window_id())
+                .collect::<HashSet<_>>();
+            if cx
+                .default_global::<NoDaemonWindows>()
+                .should_quit(&open_window_ids)
+            {
+                cx.quit();
+                return;
+            }
+            if !open_window_ids.is_empty() {
+                return;
+            }
+            let timeout = Duration::from_secs(
+                WorkspaceSettings::get_global(cx)
+                    .background_idle_timeout_minutes
+                    .saturating_mul(60),
+            );
+            // Replacing the timeout of an earlier idle period cancels it.
+            idle_timeout = Some(cx.spawn(async move |cx| {
+                cx.background_executor().timer(timeout).await;
+                cx.update(|cx| {
+                    if cx.windows().is_empty() {
+                        cx.quit();
+                    }
+                })
+                .ok();
+            }));
+        }));
+    }
+
     #[cfg(target_os = "macos")]
     {
         WorkspaceSettings::get_global(cx)
@@ -614,6 +705,7 @@ fn register_actions(
         .register_action(|_, _: &ToggleFullScreen, window, _| {
             window.toggle_fullscreen();
         })
+        .register_action(|_, action: &SwitchProfile, window, cx| switch_profile(action, window, cx))
         .register_action(|_, action: &OpenVectorUrl, _, cx| {
             OpenListener::global(cx).open(RawOpenRequest {
                 urls: vec![action.url.clone()],
@@ -960,8 +1052,20 @@ fn install_cli(
 
 static WAITING_QUIT_CONFIRMATION: AtomicBool = AtomicBool::new(false);
 fn quit(_: &Quit, cx: &mut App) {
+    let prepare_to_quit = prepare_to_quit(cx);
+    cx.spawn(async move |cx| {
+        if prepare_to_quit.await? {
+            cx.update(|cx| cx.quit())?;
+        }
+        anyhow::Ok(())
+    })
+    .detach_and_log_err(cx);
+}
+
+/// Confirms quitting and prepares every workspace to close, resolving to whether Vector can quit.
+fn prepare_to_quit(cx: &mut App) -> Task<anyhow::Result<bool>> {
     if WAITING_QUIT_CONFIRMATION.load(atomic::Ordering::Acquire) {
-        return;
+        return Task::ready(Ok(false));
     }
 
     let should_confirm = WorkspaceSettings::get_global(cx).confirm_quit;
@@ -998,7 +1102,7 @@ fn quit(_: &Quit, cx: &mut App) {
                 let answer = answer.await.ok();
                 WAITING_QUIT_CONFIRMATION.store(false, atomic::Ordering::Release);
                 if answer != Some(0) {
-                    return Ok(());
+                    return Ok(false);
                 }
             }
         }
@@ -1012,13 +1116,61 @@ fn quit(_: &Quit, cx: &mut App) {
                 .log_err()
                 && !should_close.await?
             {
-                return Ok(());
+                return Ok(false);
             }
         }
-        cx.update(|cx| cx.quit())?;
+        anyhow::Ok(true)
+    })
+}
+
+fn switch_profile(action: &SwitchProfile, window: &mut Window, cx: &mut Context<Workspace>) {
+    // `None` is the default profile.
+    let profile = if let Some(name) = &action.name {
+        Either::Left(futures::future::ready(Some(Some(name.clone()))))
+    } else {
+        let profiles = iter::once(None)
+            .chain(paths::profile_names().into_iter().map(Some))
+            .filter(|profile| profile.as_deref() != paths::profile())
+            .collect::<Vec<_>>();
+        let mut answers = profiles
+            .iter()
+            .map(|profile| profile.as_deref().unwrap_or("Default"))
+            .collect::<Vec<_>>();
+        answers.push("Cancel");
+        let answer = window.prompt(
+            PromptLevel::Info,
+            "Switch to which profile?",
+            Some("Profiles are created with `vector --profile <NAME>`."),
+            &answers,
+            cx,
+        );
+        Either::Right(async move { profiles.get(answer.await.ok()?).cloned() })
+    };
+    cx.spawn_in(window, async move |_, cx| {
+        let Some(profile) = profile.await else {
+            return Ok(());
+        };
+        if profile.as_deref() == paths::profile() {
+            return Ok(());
+        }
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        if let Some(profile) = &profile {
+            anyhow::ensure!(
+                paths::is_valid_profile_name(profile),
+                "Invalid profile name {profile:?}"
+            );
+            command.arg("--profile").arg(profile);
+        }
+        // Launch the profile's instance only once quitting can no longer be cancelled, so that
+        // both instances never keep running.
+        if !cx.update(|_, cx| prepare_to_quit(cx))?.await? {
+            return Ok(());
+        }
+        command.spawn().context("launching Vector")?;
+        cx.update(|_, cx| cx.quit())?;
         anyhow::Ok(())
     })
-    .detach_and_log_err(cx);
+    .detach_and_prompt_err("Failed to switch profile", window, cx, |_, _, _| None);
 }
 
 fn open_log_file(workspace: &mut Workspace, window: &mut Window, cx: &mut Context<Workspace>) {
@@ -1576,7 +1728,7 @@ fn open_local_file(
                 {
                     project
                         .update(cx, |project, cx| {
-                            project.create_entry((tree_id, dir_path), true, cx)
+                            project.create_entry((tree_id, dir_path), true, None, cx)
                         })?
                         .await
                         .context("worktree was removed")?;
@@ -1587,7 +1739,7 @@ fn open_local_file(
                 })? {
                     project
                         .update(cx, |project, cx| {
-                            project.create_entry((tree_id, settings_relative_path), false, cx)
+                            project.create_entry((tree_id, settings_relative_path), false, None, cx)
                         })?
                         .await
                         .context("worktree was removed")?;
@@ -2053,6 +2205,74 @@ mod tests {
         assert_eq!(cx.update(|cx| cx.windows().len()), 2);
     }
 
+    #[gpui::test]
+    async fn test_no_daemon_windows(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(path!("/root"), json!({"a": "hey", "b": "hi"}))
+            .await;
+
+        let subscription = cx.update(disable_daemon_for_new_windows);
+        cx.update(|cx| {
+            open_paths(
+                &[PathBuf::from(path!("/root/a"))],
+                app_state.clone(),
+                workspace::OpenOptions::default(),
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+        drop(subscription);
+        cx.update(|cx| {
+            open_paths(
+                &[PathBuf::from(path!("/root/b"))],
+                app_state.clone(),
+                workspace::OpenOptions {
+                    open_new_workspace: Some(true),
+                    ..Default::default()
+                },
+                cx,
+            )
+        })
+        .await
+        .unwrap();
+
+        let window_ids = cx.update(|cx| {
+            cx.windows()
+                .iter()
+                .map(|window| window.window_id())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(window_ids.len(), 2);
+        let (no_daemon_window_id, other_window_id) = (window_ids[0], window_ids[1]);
+        cx.update(|cx| {
+            let no_daemon_windows = cx.global_mut::<NoDaemonWindows>();
+            assert_eq!(
+                no_daemon_windows.window_ids,
+                HashSet::from_iter([no_daemon_window_id])
+            );
+
+            // Closing a window opened with `--no-daemon` quits only once no windows are left.
+            assert!(!no_daemon_windows.should_quit(&HashSet::from_iter(window_ids.clone())));
+            assert!(!no_daemon_windows.should_quit(&HashSet::from_iter([other_window_id])));
+            assert!(no_daemon_windows.should_quit(&HashSet::default()));
+        });
+
+        // Closing the other windows keeps Vector running in the background.
+        let mut no_daemon_windows = NoDaemonWindows {
+            window_ids: HashSet::from_iter([no_daemon_window_id]),
+            ..Default::default()
+        };
+        assert!(!no_daemon_windows.should_quit(&HashSet::from_iter([no_daemon_window_id])));
+        let mut no_daemon_windows = NoDaemonWindows::default();
+        assert!(!no_daemon_windows.should_quit(&HashSet::default()));
+        no_daemon_windows.launched_with_no_daemon = true;
+        assert!(no_daemon_windows.should_quit(&HashSet::default()));
+    }
+
     #[gpui::test]
     async fn test_open_file_in_many_spaces(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
@@ -2121,6 +2341,114 @@ mod tests {
         assert!(cx.update(|cx| cx.active_window().unwrap()) == window2);
     }
 
+    #[gpui::test]
+    async fn test_open_large_file(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings(cx, |settings| {
+                    settings.project.large_file_threshold_mb = Some(1);
+                });
+            });
+        });
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                path!("/root"),
+                json!({"large.log": "a\n".repeat(1024 * 1024), "small.log": "a\n"}),
+            )
+            .await;
+
+        // Files over the threshold are opened read-only in windows, rather than in editors.
+        for (path, opens_as_large_file) in [
+            (path!("/root/large.log"), true),
+            (path!("/root/small.log"), false),
+        ] {
+            cx.update(|cx| {
+                open_paths(
+                    &[PathBuf::from(path)],
+                    app_state.clone(),
+                    workspace::OpenOptions::default(),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+            cx.run_until_parked();
+            let workspace = cx
+                .update(|cx| cx.windows()[0].downcast::<Workspace>())
+                .unwrap();
+            workspace
+                .update(cx, |workspace, _, cx| {
+                    let item = workspace.active_item(cx).unwrap();
+                    assert_eq!(
+                        item.downcast::<workspace::large_file_view::LargeFileView>()
+                            .is_some(),
+                        opens_as_large_file
+                    );
+                    assert_eq!(item.downcast::<Editor>().is_some(), !opens_as_large_file);
+                })
+                .unwrap();
+        }
+    }
+
+    #[gpui::test]
+    async fn test_evicted_buffers_are_closed(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        cx.update(|cx| {
+            SettingsStore::update_global(cx, |store, cx| {
+                store.update_user_settings(cx, |settings| {
+                    settings.project.buffer_memory_budget_mb = Some(1);
+                });
+            });
+        });
+        let text = "a".repeat(600 * 1024);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(path!("/root"), json!({"a.txt": text, "b.txt": text}))
+            .await;
+
+        for path in [path!("/root/a.txt"), path!("/root/b.txt")] {
+            cx.update(|cx| {
+                open_paths(
+                    &[PathBuf::from(path)],
+                    app_state.clone(),
+                    workspace::OpenOptions::default(),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+            cx.run_until_parked();
+        }
+
+        // The inactive tab of the evicted buffer is closed, releasing the buffer.
+        let workspace = cx
+            .update(|cx| cx.windows()[0].downcast::<Workspace>())
+            .unwrap();
+        workspace
+            .update(cx, |workspace, _, cx| {
+                let pane = workspace.active_pane().read(cx);
+                assert_eq!(pane.items_len(), 1);
+                assert_eq!(
+                    pane.active_item().unwrap().tab_content_text(0, cx).as_ref(),
+                    "b.txt"
+                );
+                assert_eq!(
+                    workspace
+                        .project()
+                        .read(cx)
+                        .buffer_store()
+                        .read(cx)
+                        .buffer_memory(),
+                    text.len()
+                );
+            })
+            .unwrap();
+    }
+
     #[gpui::test]
     async fn test_window_edit_state_restoring_disabled(cx: &mut TestAppContext) {
         let executor = cx.executor();
@@ -0,0 +1,200 @@
+//! Recovering the session that crashed.
+//!
+//! The first launch after a crash doesn't apply `restore_on_startup`, but asks whether to restore the windows of the
+//! session that crashed, with the layouts of their panes, docks and terminals, together with the unsaved changes the
+//! session journaled before crashing.
+
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use editor::Editor;
+use gpui::{AsyncApp, PromptLevel, WindowHandle};
+use project::unsaved_journal::{self, RecoverableBuffer};
+use util::ResultExt as _;
+use workspace::{AppState, OpenOptions, PathList, SerializedWorkspaceLocation, Workspace};
+
+/// Offers to recover the session of the process that crashed, returning `false` when there's nothing to recover, so
+/// that the workspaces are restored as usual instead.
+pub async fn recover_from_crash(
+    crashed_process_id: u32,
+    app_state: Arc<AppState>,
+    cx: &mut AsyncApp,
+) -> Result<bool> {
+    let locations = crashed_session_locations(&app_state, cx)?;
+    let recoverable_buffers =
+        unsaved_journal::load_crashed_buffers(app_state.fs.as_ref(), crashed_process_id)
+            .await
+            .log_err()
+            .unwrap_or_default();
+    if locations.is_empty() && recoverable_buffers.is_empty() {
+        return Ok(false);
+    }
+
+    let (window, _) = cx
+        .update(|cx| Workspace::new_local(Vec::new(), app_state.clone(), None, None, None, cx))?
+        .await?;
+    let mut detail = String::new();
+    if !locations.is_empty() {
+        detail.push_str(&format!(
+            "Restore its {} with their panes and terminals",
+            pluralize(locations.len(), "window")
+        ));
+    }
+    if !recoverable_buffers.is_empty() {
+        detail.push_str(if detail.is_empty() {
+            "Recover "
+        } else {
+            ", and recover "
+        });
+        detail.push_str(&format!(
+            "the unsaved changes of {}",
+            pluralize(recoverable_buffers.len(), "file")
+        ));
+    }
+    detail.push('?');
+    let answer = window
+        .update(cx, |_, window, cx| {
+            window.prompt(
+                PromptLevel::Warning,
+                "Vector quit unexpectedly",
+                Some(&detail),
+                &["Restore", "Start Fresh"],
+                cx,
+            )
+        })?
+        .await?;
+    if answer != 0 {
+        window
+            .update(cx, |workspace, window, cx| {
+                Editor::new_file(workspace, &Default::default(), window, cx);
+            })
+            .log_err();
+        return Ok(true);
+    }
+
+    // The frontmost window of the session opens last, replacing the window of the prompt, so that it stays in front.
+    let mut frontmost_window = window;
+    for (index, (_, paths)) in locations.iter().enumerate() {
+        let replace_window = (index == 0).then_some(window);
+        let open_task = cx.update(|cx| {
+            workspace::open_paths(
+                &paths.paths(),
+                app_state.clone(),
+                OpenOptions {
+                    replace_window,
+                    ..Default::default()
+                },
+                cx,
+            )
+        })?;
+        match open_task.await {
+            Ok((opened_window, _)) => frontmost_window = opened_window,
+            Err(error) => log::error!("failed to restore workspace {paths:?}: {error:#}"),
+        }
+    }
+
+    for recoverable in recoverable_buffers {
+        recover_buffer(frontmost_window, recoverable, cx)
+            .await
+            .context("failed to recover unsaved changes")
+            .log_err();
+    }
+    Ok(true)
+}
+
+/// The local workspaces of the session that crashed, in the order to open them.
+fn crashed_session_locations(
+    app_state: &AppState,
+    cx: &mut AsyncApp,
+) -> Result<Vec<(SerializedWorkspaceLocation, PathList)>> {
+    let (last_session_id, last_session_window_stack) =
+        app_state.session.read_with(cx, |session, _| {
+            (
+                session.last_session_id().map(|id| id.to_string()),
+                session.last_session_window_stack(),
+            )
+        })?;
+    let Some(last_session_id) = last_session_id else {
+        return Ok(Vec::new());
+    };
+    let ordered = last_session_window_stack.is_some();
+    let mut locations =
+        workspace::last_session_workspace_locations(&last_session_id, last_session_window_stack)
+            .unwrap_or_default();
+    // The locations are ordered front-to-back.
+    if ordered {
+        locations.reverse();
+    }
+    Ok(locations)
+}
+
+/// Opens the unsaved changes of a buffer in the window whose project contains it, or in `fallback_window`, unless the
+/// restored workspace already reopened them.
+async fn recover_buffer(
+    fallback_window: WindowHandle<Workspace>,
+    recoverable: RecoverableBuffer,
+    cx: &mut AsyncApp,
+) -> Result<()> {
+    let window = cx.update(|cx| {
+        recoverable
+            .abs_path
+            .as_ref()
+            .and_then(|abs_path| {
+                workspace::local_workspace_windows(cx)
+                    .into_iter()
+                    .find(|window| {
+                        window.read(cx).is_ok_and(|workspace| {
+                            workspace
+                                .project()
+                                .read(cx)
+                                .find_worktree(abs_path, cx)
+                                .is_some()
+                        })
+                    })
+            })
+            .unwrap_or(fallback_window)
+    })?;
+    let project = window.read_with(cx, |workspace, _| workspace.project().clone())?;
+    let (already_restored, unsaved_journal) = project.read_with(cx, |project, cx| {
+        let already_restored = project.opened_buffers(cx).into_iter().any(|buffer| {
+            let buffer = buffer.read(cx);
+            let same_file = match (&recoverable.abs_path, buffer.file()) {
+                (Some(abs_path), Some(file)) => file
+                    .as_local()
+                    .is_some_and(|file| file.abs_path(cx) == *abs_path),
+                (None, None) => true,
+                _ => false,
+            };
+            same_file && buffer.text() == recoverable.text
+        });
+        (already_restored, project.unsaved_journal().cloned())
+    })?;
+    if already_restored {
+        if let Some(unsaved_journal) = unsaved_journal {
+            unsaved_journal
+                .update(cx, |unsaved_journal, cx| {
+                    unsaved_journal.discard(&recoverable, cx)
+                })?
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let buffer = project
+        .update(cx, |project, cx| project.recover_buffer(recoverable, cx))?
+        .await?;
+    // A buffer the restored workspace reopened with other contents keeps its tab.
+    window.update(cx, |workspace, window, cx| {
+        let pane = workspace.active_pane().clone();
+        workspace.open_project_item::<Editor>(pane, buffer, true, true, true, false, window, cx);
+    })?;
+    Ok(())
+}
+
+fn pluralize(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
@@ -0,0 +1,218 @@
+//! Bisecting the installed extensions to find the one that keeps Vector from working.
+//!
+//! Each step of the bisect launches Vector with only half of the suspected extensions enabled. The user then tells
+//! whether Vector worked, which halves the suspects, until one is left. The bisect in progress is saved in the data
+//! directory, so that it survives Vector crashing on launch.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result, bail};
+use collections::HashSet;
+use extension_host::DisabledExtensions;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BisectStep {
+    /// Starts bisecting all the installed extensions.
+    Start,
+    /// Vector worked with the extensions of the last launch.
+    Good,
+    /// Vector didn't work with the extensions of the last launch.
+    Bad,
+    /// Stops bisecting, and launches Vector with all the extensions.
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BisectState {
+    /// The extensions that may keep Vector from working.
+    suspects: Vec<Arc<str>>,
+    /// The suspects enabled in the launches of the current step.
+    enabled: Vec<Arc<str>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Bisect {
+    Testing(BisectState),
+    Found(Arc<str>),
+    /// Vector didn't work even without any of the suspects.
+    NotFound,
+}
+
+impl BisectState {
+    fn new(suspects: Vec<Arc<str>>) -> Self {
+        let enabled = suspects[..suspects.len().div_ceil(2)].to_vec();
+        Self { suspects, enabled }
+    }
+
+    fn next(self, worked: bool) -> Bisect {
+        let suspects = if worked {
+            self.suspects
+                .into_iter()
+                .filter(|suspect| !self.enabled.contains(suspect))
+                .collect()
+        } else {
+            self.enabled
+        };
+        match suspects.as_slice() {
+            [] => Bisect::NotFound,
+            // A suspect left only because the others worked hasn't been launched on its own yet, so it gets a final
+            // launch to tell it apart from Vector not working for some other reason.
+            [culprit] if !worked => Bisect::Found(culprit.clone()),
+            _ => Bisect::Testing(Self::new(suspects)),
+        }
+    }
+}
+
+fn state_path() -> PathBuf {
+    paths::data_dir().join("extension_bisect.json")
+}
+
+fn load_state() -> Result<Option<BisectState>> {
+    let path = state_path();
+    match std::fs::read(&path) {
+        Ok(json) => serde_json::from_slice(&json)
+            .map(Some)
+            .with_context(|| format!("invalid extension bisect state in {path:?}")),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_context(|| format!("failed to read {path:?}")),
+    }
+}
+
+fn save_state(state: &BisectState) -> Result<()> {
+    let path = state_path();
+    std::fs::write(&path, serde_json::to_vec_pretty(state)?)
+        .with_context(|| format!("failed to write {path:?}"))
+}
+
+fn clear_state() -> Result<()> {
+    match std::fs::remove_file(state_path()) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+fn installed_extensions() -> Result<Vec<Arc<str>>> {
+    let installed_dir = paths::extensions_dir().join("installed");
+    let mut extensions = Vec::new();
+    let entries = match std::fs::read_dir(&installed_dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(extensions),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to read {installed_dir:?}"));
+        }
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.path().is_dir()
+            && let Some(name) = entry.file_name().to_str()
+        {
+            extensions.push(name.into());
+        }
+    }
+    extensions.sort();
+    Ok(extensions)
+}
+
+/// Takes `step` of the bisect in progress, and returns the extensions to disable when launching Vector, or `None`
+/// when the bisect finished and Vector shouldn't launch.
+pub fn disabled_extensions(step: Option<BisectStep>) -> Result<Option<DisabledExtensions>> {
+    let state = match step {
+        None => load_state()?,
+        Some(BisectStep::Reset) => {
+            clear_state()?;
+            println!("Stopped bisecting extensions.");
+            None
+        }
+        Some(BisectStep::Start) => {
+            let installed = installed_extensions()?;
+            if installed.is_empty() {
+                println!("No extensions are installed.");
+                return Ok(None);
+            }
+            let state = BisectState::new(installed);
+            save_state(&state)?;
+            Some(state)
+        }
+        Some(step @ (BisectStep::Good | BisectStep::Bad)) => {
+            let Some(state) = load_state()? else {
+                bail!(
+                    "no extension bisect in progress, start one with `--bisect-extensions start`"
+                );
+            };
+            match state.next(step == BisectStep::Good) {
+                Bisect::Testing(state) => {
+                    save_state(&state)?;
+                    Some(state)
+                }
+                Bisect::Found(culprit) => {
+                    clear_state()?;
+                    println!(
+                        "The extension {culprit:?} keeps Vector from working. It's installed in {:?}.",
+                        paths::extensions_dir()
+                            .join("installed")
+                            .join(culprit.as_ref())
+                    );
+                    return Ok(None);
+                }
+                Bisect::NotFound => {
+                    clear_state()?;
+                    println!(
+                        "Vector doesn't work even without the suspected extensions, so no single extension is to blame. \
+                        Try `--safe-mode`, which also skips the user keymap and themes."
+                    );
+                    return Ok(None);
+                }
+            }
+        }
+    };
+    let Some(state) = state else {
+        return Ok(Some(DisabledExtensions::None));
+    };
+    println!(
+        "Bisecting {} extensions, launching with only these enabled: {}.\n\
+        Run `vector --bisect-extensions good` if Vector works, or `vector --bisect-extensions bad` if it doesn't.",
+        state.suspects.len(),
+        state.enabled.join(", "),
+    );
+    Ok(Some(DisabledExtensions::AllExcept(HashSet::from_iter(
+        state.enabled,
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisect_extensions() {
+        let state = BisectState::new(vec![
+            "a".into(),
+            "b".into(),
+            "c".into(),
+            "d".into(),
+            "e".into(),
+        ]);
+        assert_eq!(state.enabled, [Arc::from("a"), "b".into(), "c".into()]);
+
+        let Bisect::Testing(state) = state.next(true) else {
+            panic!("expected more steps");
+        };
+        assert_eq!(state.suspects, [Arc::from("d"), "e".into()]);
+        assert_eq!(state.enabled, [Arc::from("d")]);
+        assert_eq!(state.clone().next(false), Bisect::Found("d".into()));
+
+        let Bisect::Testing(state) = state.next(true) else {
+            panic!("expected a final launch with the last suspect");
+        };
+        assert_eq!(state.suspects, [Arc::from("e")]);
+        assert_eq!(state.enabled, [Arc::from("e")]);
+        assert_eq!(state.clone().next(false), Bisect::Found("e".into()));
+        assert_eq!(state.next(true), Bisect::NotFound);
+
+        let state = BisectState::new(vec!["a".into()]);
+        assert_eq!(state.clone().next(false), Bisect::Found("a".into()));
+        assert_eq!(state.next(true), Bisect::NotFound);
+    }
+}
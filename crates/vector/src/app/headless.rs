@@ -0,0 +1,735 @@
+//! `vector --headless`: runs the project, language servers and git without opening windows, and
+//! serves requests over a local socket, for scripts and other programs using Vector as a service.
+//!
+//! The protocol is newline-delimited JSON. Each request is an object like
+//! `{"id": 1, "method": "search", "params": {"query": "fn main"}}`, and is answered with a line
+//! `{"id": 1, "result": ...}`, or `{"id": 1, "error": "..."}` when it fails. Requests are answered
+//! concurrently, so responses can arrive in a different order than the requests.
+//!
+//! The server stops after a shutdown request, or once every client that connected disconnected.
+
+use anyhow::{Context as _, Result};
+use assets::Assets;
+use collections::{BTreeMap, HashMap};
+use fs::{Fs, RealFs};
+use futures::{FutureExt as _, StreamExt as _, channel::mpsc, stream::FuturesUnordered};
+use git::GitHostingProviderRegistry;
+use gpui::{Application, AsyncApp, Entity, Subscription};
+use http_client::{BlockedHttpClient, HttpClientWithUrl};
+use language::{Buffer, BufferEvent, DiagnosticSeverity, LanguageRegistry, Point, ToPoint as _};
+use node_runtime::NodeRuntime;
+use project::{Project, lsp_store::OpenLspBufferHandle, search::SearchQuery, search::SearchResult};
+use release_channel::RELEASE_CHANNEL_NAME;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use settings::{SettingsStore, watch_config_file};
+use std::{
+    cell::RefCell,
+    io::{BufRead as _, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use util::{ResultExt as _, paths::PathMatcher};
+
+use crate::app::handle_settings_file_changes;
+
+#[derive(Debug, Deserialize)]
+pub struct HeadlessRequest {
+    pub id: u64,
+    #[serde(flatten)]
+    pub method: HeadlessMethod,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum HeadlessMethod {
+    /// Adds a directory or file to the project.
+    AddWorktree {
+        path: PathBuf,
+    },
+    /// Opens a file, starting the language servers for it.
+    OpenFile {
+        path: PathBuf,
+    },
+    Search {
+        query: String,
+        #[serde(default)]
+        case_sensitive: bool,
+        #[serde(default)]
+        whole_word: bool,
+    },
+    /// The diagnostics of a file, opening it if needed, or the number of errors and warnings of
+    /// every file with diagnostics when no path is given.
+    Diagnostics {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+    Shutdown,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HeadlessResponse {
+    /// `None` when the request couldn't be parsed.
+    pub id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A request, with the channel its connection's responses are written from.
+type PendingRequest = (HeadlessRequest, mpsc::UnboundedSender<HeadlessResponse>);
+
+/// How long a diagnostics request waits for the language servers to publish the diagnostics of a
+/// file they haven't published any for yet.
+const DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn run(paths: Vec<String>, socket_path: Option<PathBuf>, app_version: Version) {
+    let app = Application::headless().with_assets(Assets);
+    let fs = Arc::new(RealFs::new(None, app.background_executor()));
+    let user_settings_file_rx = watch_config_file(
+        &app.background_executor(),
+        fs.clone(),
+        paths::settings_file().clone(),
+    );
+    let global_settings_file_rx = watch_config_file(
+        &app.background_executor(),
+        fs.clone(),
+        paths::global_settings_file().clone(),
+    );
+
+    app.run(move |cx| {
+        release_channel::init(app_version, cx);
+        settings::init(cx);
+        handle_settings_file_changes(user_settings_file_rx, global_settings_file_rx, cx);
+
+        // Strict offline: provide a blocked HTTP client so any accidental network usage fails fast.
+        let http = Arc::new(HttpClientWithUrl::new_url(
+            Arc::new(BlockedHttpClient::new()),
+            "https://vector.dev".to_string(),
+            None,
+        ));
+        cx.set_http_client(http.clone());
+        <dyn Fs>::set_global(fs.clone(), cx);
+        GitHostingProviderRegistry::set_global(Arc::new(GitHostingProviderRegistry::new()), cx);
+
+        let mut languages = LanguageRegistry::new(cx.background_executor().clone());
+        languages.set_language_server_download_dir(paths::languages_dir().clone());
+        let languages = Arc::new(languages);
+
+        let (mut tx, rx) = watch::channel(None);
+        cx.observe_global::<SettingsStore>(move |cx| {
+            tx.send(Some(crate::node_binary_options(cx))).log_err();
+        })
+        .detach();
+        let node_runtime = NodeRuntime::new(http.clone(), None, rx);
+
+        languages::init(languages.clone(), fs.clone(), node_runtime.clone(), cx);
+        Project::init(cx);
+
+        let project = Project::local(http, node_runtime, languages, fs, None, false, true, cx);
+        let socket_path = socket_path.unwrap_or_else(|| {
+            paths::data_dir().join(format!("vector-headless-{}.sock", *RELEASE_CHANNEL_NAME))
+        });
+        let (requests_tx, requests_rx) = mpsc::unbounded();
+        if let Err(error) = listen(&socket_path, requests_tx) {
+            eprintln!("Vector failed to start the headless server: {error:#}");
+            cx.quit();
+            return;
+        }
+        println!("Vector is listening on {}", socket_path.display());
+
+        cx.spawn(async move |cx| {
+            let server = HeadlessServer::new(project);
+            for path in paths {
+                let path = std::path::absolute(&path).unwrap_or_else(|_| PathBuf::from(&path));
+                server
+                    .handle(HeadlessMethod::AddWorktree { path }, cx)
+                    .await
+                    .log_err();
+            }
+            server.serve(requests_rx, cx).await;
+            std::fs::remove_file(&socket_path).log_err();
+            cx.update(|cx| cx.quit()).log_err();
+        })
+        .detach();
+    });
+}
+
+#[cfg(unix)]
+fn listen(socket_path: &Path, requests: mpsc::UnboundedSender<PendingRequest>) -> Result<()> {
+    use std::{
+        fs::DirBuilder,
+        os::unix::{
+            fs::{DirBuilderExt as _, PermissionsExt as _},
+            net::{UnixListener, UnixStream},
+        },
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    match UnixStream::connect(socket_path) {
+        Ok(_) => anyhow::bail!("another server is listening on {}", socket_path.display()),
+        // remove the socket if the process listening on it has died
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            std::fs::remove_file(socket_path)?;
+        }
+        Err(_) => {}
+    }
+
+    // The requests read and search the user's files, so only the user may send them. The socket
+    // is created and restricted in a directory only the user can access, and then moved into
+    // place, so that nobody can connect to it before it's restricted.
+    let parent = socket_path
+        .parent()
+        .with_context(|| format!("{} has no parent directory", socket_path.display()))?;
+    let file_name = socket_path
+        .file_name()
+        .with_context(|| format!("{} has no file name", socket_path.display()))?;
+    let staging_dir = parent.join(format!(
+        ".{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::remove_dir_all(&staging_dir).ok();
+    DirBuilder::new()
+        .mode(0o700)
+        .create(&staging_dir)
+        .with_context(|| format!("failed to create {}", staging_dir.display()))?;
+    let staging_socket_path = staging_dir.join(file_name);
+    let listener = UnixListener::bind(&staging_socket_path)
+        .and_then(|listener| {
+            std::fs::set_permissions(&staging_socket_path, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staging_socket_path, socket_path)?;
+            Ok(listener)
+        })
+        .with_context(|| format!("failed to listen on {}", socket_path.display()));
+    std::fs::remove_dir_all(&staging_dir).log_err();
+    let listener = listener?;
+    std::thread::spawn(move || {
+        let connection_count = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming() {
+            if requests.is_closed() {
+                break;
+            }
+            let Some(stream) = stream.log_err() else {
+                continue;
+            };
+            connection_count.fetch_add(1, Ordering::SeqCst);
+            let connection_count = connection_count.clone();
+            let requests = requests.clone();
+            std::thread::spawn(move || {
+                stream
+                    .try_clone()
+                    .map_err(anyhow::Error::from)
+                    .and_then(|writer| serve_connection(stream, writer, requests.clone()))
+                    .log_err();
+                // Closing the channel for every sender, including the listener's, stops the
+                // server once the last client disconnected.
+                if connection_count.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    requests.close_channel();
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen(_socket_path: &Path, _requests: mpsc::UnboundedSender<PendingRequest>) -> Result<()> {
+    anyhow::bail!("the headless server is only supported on macOS and Linux")
+}
+
+/// Forwards the requests read from a connection to the server without waiting for them to be
+/// answered, and writes back its responses as they arrive.
+fn serve_connection(
+    reader: impl Read,
+    mut writer: impl Write + Send,
+    requests: mpsc::UnboundedSender<PendingRequest>,
+) -> Result<()> {
+    let (responses_tx, mut responses_rx) = mpsc::unbounded::<HeadlessResponse>();
+    std::thread::scope(|scope| -> Result<()> {
+        // Stops once the responses of every forwarded request were written.
+        let writer_thread = scope.spawn(move || -> Result<()> {
+            while let Some(response) = smol::block_on(responses_rx.next()) {
+                serde_json::to_writer(&mut writer, &response)?;
+                writer.write_all(b"\n")?;
+                writer.flush()?;
+            }
+            Ok(())
+        });
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<HeadlessRequest>(&line) {
+                Ok(request) => {
+                    if requests
+                        .unbounded_send((request, responses_tx.clone()))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    responses_tx
+                        .unbounded_send(HeadlessResponse {
+                            id: None,
+                            result: None,
+                            error: Some(format!("invalid request: {error}")),
+                        })
+                        .ok();
+                }
+            }
+        }
+        drop(responses_tx);
+        writer_thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("the connection's writer panicked"))?
+    })
+}
+
+/// A buffer opened by a request, kept open with its language servers running.
+struct OpenBuffer {
+    buffer: Entity<Buffer>,
+    /// Becomes `true` once a language server published diagnostics for the buffer.
+    diagnostics_published: watch::Receiver<bool>,
+    _lsp_handle: OpenLspBufferHandle,
+    _diagnostics_subscription: Subscription,
+}
+
+pub struct HeadlessServer {
+    project: Entity<Project>,
+    open_buffers: RefCell<HashMap<PathBuf, OpenBuffer>>,
+}
+
+impl HeadlessServer {
+    pub fn new(project: Entity<Project>) -> Self {
+        Self {
+            project,
+            open_buffers: RefCell::default(),
+        }
+    }
+
+    /// Answers requests concurrently until a shutdown request, or until all the connections
+    /// closed.
+    async fn serve(
+        &self,
+        mut requests: mpsc::UnboundedReceiver<PendingRequest>,
+        cx: &mut AsyncApp,
+    ) {
+        let mut responses = FuturesUnordered::new();
+        loop {
+            futures::select_biased! {
+                shutdown = responses.select_next_some() => {
+                    if shutdown {
+                        break;
+                    }
+                }
+                request = requests.next() => {
+                    let Some((request, response_tx)) = request else {
+                        // Answer the requests in flight before stopping.
+                        while responses.next().await.is_some() {}
+                        break;
+                    };
+                    responses.push(self.respond(request, response_tx, cx.clone()));
+                }
+            }
+        }
+    }
+
+    /// Answers a request, returning whether it was a shutdown request.
+    async fn respond(
+        &self,
+        request: HeadlessRequest,
+        response_tx: mpsc::UnboundedSender<HeadlessResponse>,
+        mut cx: AsyncApp,
+    ) -> bool {
+        let shutdown = request.method == HeadlessMethod::Shutdown;
+        let response = match self.handle(request.method, &mut cx).await {
+            Ok(result) => HeadlessResponse {
+                id: Some(request.id),
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => HeadlessResponse {
+                id: Some(request.id),
+                result: None,
+                error: Some(format!("{error:#}")),
+            },
+        };
+        response_tx.unbounded_send(response).ok();
+        shutdown
+    }
+
+    pub async fn handle(&self, method: HeadlessMethod, cx: &mut AsyncApp) -> Result<Value> {
+        match method {
+            HeadlessMethod::AddWorktree { path } => {
+                let (worktree, _) = self
+                    .project
+                    .update(cx, |project, cx| {
+                        project.find_or_create_worktree(&path, true, cx)
+                    })?
+                    .await?;
+                let scan_complete = worktree.read_with(cx, |worktree, _| {
+                    worktree.as_local().map(|worktree| worktree.scan_complete())
+                })?;
+                if let Some(scan_complete) = scan_complete {
+                    scan_complete.await;
+                }
+                Ok(Value::Null)
+            }
+            HeadlessMethod::OpenFile { path } => {
+                let (buffer, _) = self.open_file(path, cx).await?;
+                buffer.read_with(cx, |buffer, _| {
+                    json!({
+                        "language": buffer.language().map(|language| language.name().to_string()),
+                        "line_count": buffer.max_point().row + 1,
+                    })
+                })
+            }
+            HeadlessMethod::Search {
+                query,
+                case_sensitive,
+                whole_word,
+            } => self.search(query, case_sensitive, whole_word, cx).await,
+            HeadlessMethod::Diagnostics { path: Some(path) } => {
+                let (buffer, diagnostics_published) = self.open_file(path, cx).await?;
+                self.wait_for_diagnostics(&buffer, diagnostics_published, cx)
+                    .await?;
+                buffer.read_with(cx, |buffer, _| {
+                    let snapshot = buffer.snapshot();
+                    let diagnostics = snapshot
+                        .diagnostics_in_range::<_, Point>(0..snapshot.len(), false)
+                        .map(|entry| {
+                            json!({
+                                "line": entry.range.start.row + 1,
+                                "column": entry.range.start.column + 1,
+                                "severity": severity_name(entry.diagnostic.severity),
+                                "message": entry.diagnostic.message,
+                                "source": entry.diagnostic.source,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    Value::from(diagnostics)
+                })
+            }
+            HeadlessMethod::Diagnostics { path: None } => {
+                self.project.read_with(cx, |project, cx| {
+                    let mut summaries = BTreeMap::<PathBuf, (usize, usize)>::default();
+                    for (project_path, _, summary) in project.diagnostic_summaries(false, cx) {
+                        if let Some(path) = project.absolute_path(&project_path, cx) {
+                            let (errors, warnings) = summaries.entry(path).or_default();
+                            *errors += summary.error_count;
+                            *warnings += summary.warning_count;
+                        }
+                    }
+                    summaries
+                        .into_iter()
+                        .map(|(path, (errors, warnings))| {
+                            json!({"path": path, "errors": errors, "warnings": warnings})
+                        })
+                        .collect::<Vec<_>>()
+                        .into()
+                })
+            }
+            HeadlessMethod::Shutdown => Ok(Value::Null),
+        }
+    }
+
+    async fn open_file(
+        &self,
+        path: PathBuf,
+        cx: &mut AsyncApp,
+    ) -> Result<(Entity<Buffer>, watch::Receiver<bool>)> {
+        if let Some(open_buffer) = self.open_buffers.borrow().get(&path) {
+            return Ok((
+                open_buffer.buffer.clone(),
+                open_buffer.diagnostics_published.clone(),
+            ));
+        }
+        let buffer = self
+            .project
+            .update(cx, |project, cx| project.open_local_buffer(&path, cx))?
+            .await
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let (mut diagnostics_published_tx, diagnostics_published) = watch::channel(false);
+        let diagnostics_subscription = cx.update(|cx| {
+            cx.subscribe(&buffer, move |_, event, _| {
+                if matches!(event, BufferEvent::DiagnosticsUpdated) {
+                    diagnostics_published_tx.send(true).ok();
+                }
+            })
+        })?;
+        let lsp_handle = self.project.update(cx, |project, cx| {
+            project.register_buffer_with_language_servers(&buffer, cx)
+        })?;
+        // Another request may have opened the file meanwhile, keep the buffer it registered.
+        let mut open_buffers = self.open_buffers.borrow_mut();
+        let open_buffer = open_buffers.entry(path).or_insert(OpenBuffer {
+            buffer,
+            diagnostics_published,
+            _lsp_handle: lsp_handle,
+            _diagnostics_subscription: diagnostics_subscription,
+        });
+        Ok((
+            open_buffer.buffer.clone(),
+            open_buffer.diagnostics_published.clone(),
+        ))
+    }
+
+    /// Waits until a language server published the diagnostics of the buffer, so that a file
+    /// that was just opened isn't reported without diagnostics before its language servers
+    /// checked it.
+    async fn wait_for_diagnostics(
+        &self,
+        buffer: &Entity<Buffer>,
+        mut diagnostics_published: watch::Receiver<bool>,
+        cx: &mut AsyncApp,
+    ) -> Result<()> {
+        let has_language_servers = self.project.read_with(cx, |project, cx| {
+            buffer.read(cx).language().is_some_and(|language| {
+                !project
+                    .languages()
+                    .lsp_adapters(&language.name())
+                    .is_empty()
+            })
+        })?;
+        if !has_language_servers {
+            return Ok(());
+        }
+        let published = async {
+            while !*diagnostics_published.borrow() {
+                if diagnostics_published.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+        let timeout = cx.background_executor().timer(DIAGNOSTICS_TIMEOUT);
+        futures::select_biased! {
+            _ = published.fuse() => {}
+            _ = timeout.fuse() => {}
+        }
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        query: String,
+        case_sensitive: bool,
+        whole_word: bool,
+        cx: &mut AsyncApp,
+    ) -> Result<Value> {
+        let query = SearchQuery::text(
+            query,
+            whole_word,
+            case_sensitive,
+            false,
+            PathMatcher::default(),
+            PathMatcher::default(),
+            false,
+            None,
+        )?;
+        let results = self
+            .project
+            .update(cx, |project, cx| project.search(query, cx))?;
+        let mut matches = Vec::new();
+        let mut limit_reached = false;
+        let mut errors = Vec::new();
+        while let Ok(result) = results.rx.recv().await {
+            match result {
+                SearchResult::Buffer { buffer, ranges, .. } => {
+                    buffer.read_with(cx, |buffer, cx| {
+                        let path = buffer.file().map(|file| file.full_path(cx));
+                        for range in ranges {
+                            let start = range.start.to_point(buffer);
+                            let line = buffer
+                                .text_for_range(
+                                    Point::new(start.row, 0)
+                                        ..Point::new(start.row, buffer.line_len(start.row)),
+                                )
+                                .collect::<String>();
+                            matches.push(json!({
+                                "path": path,
+                                "line": start.row + 1,
+                                "column": start.column + 1,
+                                "text": line,
+                            }));
+                        }
+                    })?;
+                }
+                SearchResult::LimitReached { .. } => limit_reached = true,
+                SearchResult::BudgetExceeded { path, error } => {
+                    let path = path.map(|path| path.path.as_unix_str().to_string());
+                    errors.push(json!({"path": path, "error": error.to_string()}));
+                }
+            }
+        }
+        Ok(json!({"matches": matches, "limit_reached": limit_reached, "errors": errors}))
+    }
+}
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "information",
+        _ => "hint",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::tests::init_test;
+    use gpui::TestAppContext;
+    use language::{FakeLspAdapter, rust_lang};
+    use std::rc::Rc;
+    use util::path;
+
+    #[gpui::test]
+    async fn test_headless_server(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                path!("/root"),
+                json!({
+                    "main.rs": "fn main() {\n    run();\n}\n",
+                    "lib.rs": "pub fn run() {}\n",
+                }),
+            )
+            .await;
+        let project = Project::test(app_state.fs.clone(), [path!("/root").as_ref()], cx).await;
+        let server = HeadlessServer::new(project);
+        let mut async_cx = cx.to_async();
+
+        let request: HeadlessRequest = serde_json::from_str(
+            r#"{"id": 1, "method": "search", "params": {"query": "run", "whole_word": true}}"#,
+        )
+        .unwrap();
+        let result = server.handle(request.method, &mut async_cx).await.unwrap();
+        let mut matches = result["matches"].as_array().unwrap().clone();
+        matches.sort_by_key(|m| m["path"].as_str().unwrap().to_string());
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0]["line"], 1);
+        assert_eq!(matches[1]["line"], 2);
+        assert_eq!(matches[1]["text"], "    run();");
+
+        let result = server
+            .handle(
+                HeadlessMethod::OpenFile {
+                    path: path!("/root/main.rs").into(),
+                },
+                &mut async_cx,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result["line_count"], 4);
+        assert!(
+            server
+                .handle(
+                    HeadlessMethod::OpenFile {
+                        path: path!("/root/missing.rs").into(),
+                    },
+                    &mut async_cx,
+                )
+                .await
+                .is_err()
+        );
+
+        // Answer the second request first, which only works if the first one doesn't hold up
+        // the connection.
+        let (requests_tx, mut requests_rx) = mpsc::unbounded::<PendingRequest>();
+        std::thread::spawn(move || {
+            let first = smol::block_on(requests_rx.next());
+            let second = smol::block_on(requests_rx.next());
+            for (request, response_tx) in second.into_iter().chain(first) {
+                response_tx
+                    .unbounded_send(HeadlessResponse {
+                        id: Some(request.id),
+                        result: Some(Value::Null),
+                        error: None,
+                    })
+                    .ok();
+            }
+        });
+        let mut output = Vec::new();
+        serve_connection(
+            concat!(
+                "{\"id\": 7, \"method\": \"search\", \"params\": {\"query\": \"run\"}}\n",
+                "not json\n",
+                "{\"id\": 8, \"method\": \"shutdown\"}\n",
+            )
+            .as_bytes(),
+            &mut output,
+            requests_tx,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let (errors, responses): (Vec<_>, Vec<_>) = output
+            .lines()
+            .partition(|line| line.starts_with("{\"id\":null,"));
+        assert_eq!(
+            responses,
+            ["{\"id\":8,\"result\":null}", "{\"id\":7,\"result\":null}"]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("{\"id\":null,\"error\":\"invalid request"));
+    }
+
+    #[gpui::test]
+    async fn test_headless_diagnostics_wait_for_language_servers(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(path!("/root"), json!({"main.rs": "fn main() {}\n"}))
+            .await;
+        let project = Project::test(app_state.fs.clone(), [path!("/root").as_ref()], cx).await;
+        let language_registry = project.read_with(cx, |project, _| project.languages().clone());
+        language_registry.add(rust_lang());
+        let mut fake_servers =
+            language_registry.register_fake_lsp("Rust", FakeLspAdapter::default());
+        let server = Rc::new(HeadlessServer::new(project));
+
+        let diagnostics = cx.spawn({
+            let server = server.clone();
+            async move |mut cx| {
+                server
+                    .handle(
+                        HeadlessMethod::Diagnostics {
+                            path: Some(path!("/root/main.rs").into()),
+                        },
+                        &mut cx,
+                    )
+                    .await
+            }
+        });
+        let fake_server = fake_servers.next().await.unwrap();
+        cx.run_until_parked();
+        fake_server.notify::<lsp::notification::PublishDiagnostics>(
+            lsp::PublishDiagnosticsParams {
+                uri: lsp::Uri::from_file_path(path!("/root/main.rs")).unwrap(),
+                version: None,
+                diagnostics: vec![lsp::Diagnostic {
+                    range: lsp::Range::new(lsp::Position::new(0, 3), lsp::Position::new(0, 7)),
+                    severity: Some(lsp::DiagnosticSeverity::WARNING),
+                    message: "function `main` is never used".to_string(),
+                    ..Default::default()
+                }],
+            },
+        );
+
+        let result = diagnostics.await.unwrap();
+        let diagnostics = result.as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["line"], 1);
+        assert_eq!(diagnostics[0]["column"], 4);
+        assert_eq!(diagnostics[0]["severity"], "warning");
+        assert_eq!(diagnostics[0]["message"], "function `main` is never used");
+    }
+}
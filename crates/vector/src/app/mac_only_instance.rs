@@ -1,13 +1,15 @@
 use std::{
-    io::{Read, Write},
+    io::{self, Read, Write},
     net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
     thread,
     time::Duration,
 };
 
 use sysinfo::System;
 
-use release_channel::ReleaseChannel;
+use release_channel::{RELEASE_CHANNEL_NAME, ReleaseChannel};
 
 const LOCALHOST: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
 const CONNECT_TIMEOUT: Duration = Duration::from_millis(10);
@@ -79,6 +81,12 @@ fn instance_handshake() -> &'static str {
     }
 }
 
+/// The socket of a named profile's instance, which lives in the profile's data directory so that
+/// each profile has its own.
+fn profile_socket_path() -> PathBuf {
+    paths::data_dir().join(format!("vector-{}.sock", *RELEASE_CHANNEL_NAME))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IsOnlyInstance {
     Yes,
@@ -86,6 +94,9 @@ pub enum IsOnlyInstance {
 }
 
 pub fn ensure_only_instance() -> IsOnlyInstance {
+    if paths::profile().is_some() {
+        return ensure_only_profile_instance();
+    }
     if check_got_handshake() {
         return IsOnlyInstance::No;
     }
@@ -126,26 +137,73 @@ pub fn ensure_only_instance() -> IsOnlyInstance {
     IsOnlyInstance::Yes
 }
 
-fn check_got_handshake() -> bool {
-    match TcpStream::connect_timeout(&address(), CONNECT_TIMEOUT) {
+fn ensure_only_profile_instance() -> IsOnlyInstance {
+    let socket_path = profile_socket_path();
+    match UnixStream::connect(&socket_path) {
         Ok(mut stream) => {
-            let mut buf = vec![0u8; instance_handshake().len()];
-
             stream.set_read_timeout(Some(RECEIVE_TIMEOUT)).unwrap();
-            if let Err(err) = stream.read_exact(&mut buf) {
-                log::warn!("Connected to single instance port but failed to read: {err}");
-                return false;
+            if read_handshake(&mut stream) {
+                return IsOnlyInstance::No;
+            }
+        }
+        // Remove the socket if the instance listening on it has died.
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+            if let Err(err) = std::fs::remove_file(&socket_path) {
+                log::warn!("Error removing stale single instance socket: {err}");
             }
+        }
+        Err(_) => {}
+    }
+
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::warn!("Error binding to single instance socket: {err}");
+            return IsOnlyInstance::Yes;
+        }
+    };
+
+    thread::Builder::new()
+        .name("EnsureSingleton".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => return,
+                };
 
-            if buf == instance_handshake().as_bytes() {
-                log::info!("Got instance handshake");
-                return true;
+                _ = stream.set_write_timeout(Some(SEND_TIMEOUT));
+                _ = stream.write_all(instance_handshake().as_bytes());
             }
+        })
+        .unwrap();
+
+    IsOnlyInstance::Yes
+}
 
-            log::warn!("Got wrong instance handshake value");
-            false
+fn check_got_handshake() -> bool {
+    match TcpStream::connect_timeout(&address(), CONNECT_TIMEOUT) {
+        Ok(mut stream) => {
+            stream.set_read_timeout(Some(RECEIVE_TIMEOUT)).unwrap();
+            read_handshake(&mut stream)
         }
 
         Err(_) => false,
     }
 }
+
+fn read_handshake(stream: &mut impl Read) -> bool {
+    let mut buf = vec![0u8; instance_handshake().len()];
+    if let Err(err) = stream.read_exact(&mut buf) {
+        log::warn!("Connected to single instance port but failed to read: {err}");
+        return false;
+    }
+
+    if buf == instance_handshake().as_bytes() {
+        log::info!("Got instance handshake");
+        return true;
+    }
+
+    log::warn!("Got wrong instance handshake value");
+    false
+}
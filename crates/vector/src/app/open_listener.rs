@@ -1,11 +1,12 @@
 use crate::handle_open_request;
-use crate::restorable_workspace_locations;
-use anyhow::{Context as _, Result, anyhow};
-use cli::{CliRequest, CliResponse, ipc::IpcSender};
+use crate::{named_session_workspace_locations, restorable_workspace_locations};
+use anyhow::{Context as _, Result, anyhow, bail};
+use cli::{CliRequest, CliResponse, ExtensionCommand, SessionCommand, ipc::IpcSender};
 use cli::{IpcHandshake, ipc};
 use collections::HashMap;
 use db::kvp::KEY_VALUE_STORE;
 use editor::Editor;
+use extension_host::ExtensionStore;
 use fs::Fs;
 use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
 use futures::channel::{mpsc, oneshot};
@@ -13,10 +14,11 @@ use futures::future;
 use futures::future::join_all;
 use futures::{FutureExt, SinkExt, StreamExt};
 use git_ui::file_diff_view::FileDiffView;
-use gpui::{AsyncApp, Global, WindowHandle};
+use gpui::{Action, App, AsyncApp, Global, WindowHandle};
 use language::Point;
 use onboarding::FIRST_OPEN;
 use onboarding::show_onboarding_view;
+use session::NamedSession;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
@@ -32,6 +34,8 @@ pub struct OpenRequest {
     pub kind: Option<OpenRequestKind>,
     pub open_paths: Vec<String>,
     pub diff_paths: Vec<[String; 2]>,
+    /// The local, base and remote versions of a file, and the path to write their merge to.
+    pub merge_paths: Option<[String; 4]>,
 }
 
 #[derive(Debug)]
@@ -50,6 +54,79 @@ pub enum OpenRequestKind {
     GitCommit {
         sha: String,
     },
+    /// Opens a file as it was at a git revision.
+    GitFile {
+        revision: String,
+        path: PathBuf,
+    },
+    Command(DeepLinkCommand),
+}
+
+/// A command run by a `vector://command/<name>?<arguments>` URL. Any application or web page can open these URLs, so
+/// only the commands listed here can be run, and those with side effects are confirmed first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkCommand {
+    /// `vector://command/settings`, or `vector://command/settings?path=<setting>` to open the settings at a setting.
+    OpenSettings { path: Option<String> },
+    /// `vector://command/extension?id=<id>`
+    OpenExtension { id: String },
+    /// `vector://command/task?name=<name>`
+    SpawnTask { name: String },
+}
+
+impl DeepLinkCommand {
+    fn parse(command: &str) -> Result<Self> {
+        let (name, query) = command.split_once('?').unwrap_or((command, ""));
+        let argument = |key: &str| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find_map(|(k, value)| (k == key).then(|| value.into_owned()))
+                .filter(|value| !value.is_empty())
+        };
+        match name.trim_end_matches('/') {
+            "settings" => Ok(Self::OpenSettings {
+                path: argument("path"),
+            }),
+            "extension" => Ok(Self::OpenExtension {
+                id: argument("id").context("invalid command url: missing id query parameter")?,
+            }),
+            "task" => Ok(Self::SpawnTask {
+                name: argument("name")
+                    .context("invalid command url: missing name query parameter")?,
+            }),
+            _ => bail!("the command {name:?} can't be run from a url"),
+        }
+    }
+
+    /// Whether to ask the user before running the command, because it has side effects.
+    pub fn requires_confirmation(&self) -> bool {
+        matches!(self, Self::SpawnTask { .. })
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Self::OpenSettings { path: None } => "Open the settings".to_string(),
+            Self::OpenSettings { path: Some(path) } => format!("Open the setting {path:?}"),
+            Self::OpenExtension { id } => format!("Open the extension {id:?}"),
+            Self::SpawnTask { name } => format!("Run the task {name:?}"),
+        }
+    }
+
+    pub fn action(&self) -> Box<dyn Action> {
+        match self {
+            Self::OpenSettings { path: None } => Box::new(vector_actions::OpenSettings),
+            Self::OpenSettings { path: Some(path) } => {
+                Box::new(vector_actions::OpenSettingsAt { path: path.clone() })
+            }
+            Self::OpenExtension { id } => Box::new(vector_actions::Extensions {
+                category_filter: None,
+                id: Some(id.clone()),
+            }),
+            Self::SpawnTask { name } => Box::new(vector_actions::Spawn::ByName {
+                task_name: name.clone(),
+                reveal_target: None,
+            }),
+        }
+    }
 }
 
 impl OpenRequest {
@@ -57,12 +134,19 @@ impl OpenRequest {
         let mut this = Self::default();
 
         this.diff_paths = request.diff_paths;
+        this.merge_paths = request.merge_paths;
         // Strict offline: ignore WSL / remote connection hints.
 
         for url in request.urls {
-            if let Some(server_name) = url.strip_prefix("zed-cli://") {
+            if let Some(server_name) = url
+                .strip_prefix("vector-cli://")
+                .or_else(|| url.strip_prefix("zed-cli://"))
+            {
                 this.kind = Some(OpenRequestKind::CliConnection(connect_to_cli(server_name)?));
-            } else if let Some(action_index) = url.strip_prefix("zed-dock-action://") {
+            } else if let Some(action_index) = url
+                .strip_prefix("vector-dock-action://")
+                .or_else(|| url.strip_prefix("zed-dock-action://"))
+            {
                 this.kind = Some(OpenRequestKind::DockMenuAction {
                     index: action_index.parse()?,
                 });
@@ -82,6 +166,13 @@ impl OpenRequest {
                 });
             } else if let Some(commit_path) = url.strip_prefix("zed://git/commit/") {
                 this.parse_git_commit_url(commit_path)?
+            } else if let Some(file_path) = url.strip_prefix(cli::GIT_FILE_URL_PREFIX) {
+                this.parse_git_file_url(file_path)?
+            } else if let Some(command) = url
+                .strip_prefix("vector://command/")
+                .or_else(|| url.strip_prefix("zed://command/"))
+            {
+                this.kind = Some(OpenRequestKind::Command(DeepLinkCommand::parse(command)?));
             } else {
                 log::error!("unhandled url: {}", url);
             }
@@ -117,6 +208,33 @@ impl OpenRequest {
 
         Ok(())
     }
+
+    fn parse_git_file_url(&mut self, file_path: &str) -> Result<()> {
+        // Format: <revision>?path=<path>
+        let (revision, query) = file_path
+            .split_once('?')
+            .context("invalid git file url: missing query string")?;
+        let revision = urlencoding::decode(revision)?.into_owned();
+        anyhow::ensure!(
+            !revision.is_empty(),
+            "invalid git file url: missing revision"
+        );
+
+        let path = url::form_urlencoded::parse(query.as_bytes())
+            .find_map(|(key, value)| (key == "path").then_some(value))
+            .filter(|s| !s.is_empty())
+            .context("invalid git file url: missing path query parameter")?;
+        let path = PathBuf::from(path.as_ref());
+
+        // Open the directory of the file, so that its repository is in the workspace, without opening the current
+        // version of the file.
+        if let Some(parent) = path.parent() {
+            self.open_paths.push(parent.to_string_lossy().into_owned());
+        }
+        self.kind = Some(OpenRequestKind::GitFile { revision, path });
+
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -126,6 +244,7 @@ pub struct OpenListener(UnboundedSender<RawOpenRequest>);
 pub struct RawOpenRequest {
     pub urls: Vec<String>,
     pub diff_paths: Vec<[String; 2]>,
+    pub merge_paths: Option<[String; 4]>,
 }
 
 impl Global for OpenListener {}
@@ -234,9 +353,12 @@ pub async fn open_paths_with_positions(
         let new_path = Path::new(&diff_pair[1]).canonicalize()?;
         if let Ok(diff_view) = workspace.update(cx, |workspace, window, cx| {
             FileDiffView::open(old_path, new_path, workspace, window, cx)
-        }) && let Some(diff_view) = diff_view.await.log_err()
-        {
-            items.push(Some(Ok(Box::new(diff_view))))
+        }) {
+            items.push(Some(
+                diff_view
+                    .await
+                    .map(|diff_view| Box::new(diff_view) as Box<dyn ItemHandle>),
+            ))
         }
     }
 
@@ -265,8 +387,11 @@ pub async fn open_paths_with_positions(
     Ok((workspace, items))
 }
 
+/// Serves a request of the CLI. `launched_for_connection` tells whether Vector was launched to serve it, rather than
+/// already running.
 pub async fn handle_cli_connection(
     (mut requests, responses): (mpsc::Receiver<CliRequest>, IpcSender<CliResponse>),
+    launched_for_connection: bool,
     app_state: Arc<AppState>,
     cx: &mut AsyncApp,
 ) {
@@ -276,18 +401,49 @@ pub async fn handle_cli_connection(
                 urls,
                 paths,
                 diff_paths,
-                wait,
+                merge_paths,
+                mut wait,
                 wsl: _,
                 open_new_workspace,
                 reuse,
+                focus,
                 env,
                 user_data_dir: _,
+                no_daemon,
             } => {
+                // The windows the request opens quit Vector once they're closed, rather than leaving it running in the
+                // background.
+                let _no_daemon_subscription = no_daemon
+                    .then(|| {
+                        cx.update(crate::app::disable_daemon_for_new_windows)
+                            .log_err()
+                    })
+                    .flatten();
+                if focus {
+                    let status = match focus_workspace_window(&paths, cx) {
+                        Ok(()) => 0,
+                        Err(error) => {
+                            responses
+                                .send(CliResponse::Stderr {
+                                    message: format!("{error:#}"),
+                                })
+                                .log_err();
+                            1
+                        }
+                    };
+                    responses.send(CliResponse::Exit { status }).log_err();
+                    return;
+                }
                 if !urls.is_empty() {
                     cx.update(|cx| {
-                        match OpenRequest::parse(RawOpenRequest { urls, diff_paths }) {
+                        match OpenRequest::parse(RawOpenRequest {
+                            urls,
+                            diff_paths,
+                            merge_paths,
+                        }) {
                             Ok(open_request) => {
-                                handle_open_request(open_request, app_state.clone(), cx);
+                                handle_open_request(open_request, false, app_state.clone(), cx)
+                                    .detach();
                                 responses.send(CliResponse::Exit { status: 0 }).log_err();
                             }
                             Err(e) => {
@@ -304,6 +460,28 @@ pub async fn handle_cli_connection(
                     return;
                 }
 
+                let mut paths = paths;
+                let mut merged_path = None;
+                if let Some(merge_paths) = &merge_paths {
+                    match write_merge(merge_paths, app_state.fs.as_ref()).await {
+                        Ok(path) => {
+                            paths.push(path.to_string_lossy().into_owned());
+                            merged_path = Some(path);
+                            // Report whether the conflicts were resolved once the merge is closed.
+                            wait = true;
+                        }
+                        Err(error) => {
+                            responses
+                                .send(CliResponse::Stderr {
+                                    message: format!("{error:#}"),
+                                })
+                                .log_err();
+                            responses.send(CliResponse::Exit { status: 1 }).log_err();
+                            return;
+                        }
+                    }
+                }
+
                 let open_workspace_result = open_workspaces(
                     paths,
                     diff_paths,
@@ -317,11 +495,278 @@ pub async fn handle_cli_connection(
                 )
                 .await;
 
-                let status = if open_workspace_result.is_err() { 1 } else { 0 };
+                let mut status = if open_workspace_result.is_err() { 1 } else { 0 };
+                if let Some(merged_path) = merged_path {
+                    let resolved = app_state
+                        .fs
+                        .load(&merged_path)
+                        .await
+                        .is_ok_and(|text| !has_conflict_markers(&text));
+                    if !resolved {
+                        responses
+                            .send(CliResponse::Stderr {
+                                message: format!(
+                                    "unresolved conflicts remain in {}",
+                                    merged_path.display()
+                                ),
+                            })
+                            .log_err();
+                        status = 1;
+                    }
+                }
+                responses.send(CliResponse::Exit { status }).log_err();
+            }
+            CliRequest::Extensions { command } => {
+                let status = match run_extension_command(command, &responses, cx).await {
+                    Ok(()) => 0,
+                    Err(error) => {
+                        responses
+                            .send(CliResponse::Stderr {
+                                message: format!("{error:#}"),
+                            })
+                            .log_err();
+                        1
+                    }
+                };
+                responses.send(CliResponse::Exit { status }).log_err();
+                // Vector launched only to run the command quits once it's done, unless a window opened meanwhile.
+                if launched_for_connection {
+                    cx.update(|cx| {
+                        if cx.windows().is_empty() {
+                            cx.quit();
+                        }
+                    })
+                    .log_err();
+                }
+            }
+            CliRequest::Sessions { command } => {
+                let status = match run_session_command(command, &responses, app_state, cx).await {
+                    Ok(()) => 0,
+                    Err(error) => {
+                        responses
+                            .send(CliResponse::Stderr {
+                                message: format!("{error:#}"),
+                            })
+                            .log_err();
+                        1
+                    }
+                };
                 responses.send(CliResponse::Exit { status }).log_err();
+                // Vector launched only to run the command quits once it's done, unless a window opened meanwhile.
+                if launched_for_connection {
+                    cx.update(|cx| {
+                        if cx.windows().is_empty() {
+                            cx.quit();
+                        }
+                    })
+                    .log_err();
+                }
+            }
+        }
+    }
+}
+
+async fn run_session_command(
+    command: SessionCommand,
+    responses: &IpcSender<CliResponse>,
+    app_state: Arc<AppState>,
+    cx: &mut AsyncApp,
+) -> Result<()> {
+    match command {
+        SessionCommand::List => {
+            for (name, session) in session::named_sessions()? {
+                let workspaces = session
+                    .workspaces
+                    .iter()
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .map(|path| path.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .collect::<Vec<_>>();
+                responses
+                    .send(CliResponse::Stdout {
+                        message: format!("{name}: {}", workspaces.join("; ")),
+                    })
+                    .log_err();
             }
         }
+        SessionCommand::Save { name } => {
+            anyhow::ensure!(!name.trim().is_empty(), "session names can't be empty");
+            let session = cx.update(|cx| current_named_session(cx))?;
+            anyhow::ensure!(
+                !session.workspaces.is_empty(),
+                "no local workspace is open to save in the session"
+            );
+            let workspace_count = session.workspaces.len();
+            session::save_named_session(name.clone(), session).await?;
+            responses
+                .send(CliResponse::Stdout {
+                    message: format!("Saved {workspace_count} workspaces as the session {name}"),
+                })
+                .log_err();
+        }
+        SessionCommand::Restore { name } => {
+            let Some(session) = session::named_sessions()?.remove(&name) else {
+                bail!("no session is saved under the name {name:?}");
+            };
+            let locations = named_session_workspace_locations(session, cx).await;
+            anyhow::ensure!(
+                !locations.is_empty(),
+                "the workspaces of the session {name} no longer exist"
+            );
+            for (_, paths) in locations {
+                let open_task = cx.update(|cx| {
+                    workspace::open_paths(
+                        &paths.paths(),
+                        app_state.clone(),
+                        OpenOptions::default(),
+                        cx,
+                    )
+                })?;
+                open_task.await?;
+            }
+        }
+        SessionCommand::Delete { name } => {
+            if !session::delete_named_session(name.clone()).await? {
+                bail!("no session is saved under the name {name:?}");
+            }
+            responses
+                .send(CliResponse::Stdout {
+                    message: format!("Deleted the session {name}"),
+                })
+                .log_err();
+        }
     }
+    Ok(())
+}
+
+/// The local workspaces of the open windows, front-to-back.
+fn current_named_session(cx: &App) -> NamedSession {
+    let windows = cx.window_stack().unwrap_or_else(|| cx.windows());
+    let workspaces = windows
+        .into_iter()
+        .filter_map(|window| {
+            let workspace = window.downcast::<Workspace>()?;
+            let workspace = workspace.read(cx).ok()?;
+            if !workspace.project().read(cx).is_local() {
+                return None;
+            }
+            let paths = workspace
+                .root_paths(cx)
+                .iter()
+                .map(|path| path.to_path_buf())
+                .collect::<Vec<_>>();
+            (!paths.is_empty()).then_some(paths)
+        })
+        .collect();
+    NamedSession { workspaces }
+}
+
+async fn run_extension_command(
+    command: ExtensionCommand,
+    responses: &IpcSender<CliResponse>,
+    cx: &mut AsyncApp,
+) -> Result<()> {
+    let store = cx.update(|cx| ExtensionStore::global(cx))?;
+    match command {
+        ExtensionCommand::List => {
+            let extensions = store.read_with(cx, |store, _| {
+                store
+                    .installed_extensions()
+                    .iter()
+                    .map(|(id, entry)| {
+                        let dev = if entry.dev { " (dev)" } else { "" };
+                        format!("{id} {}{dev}", entry.manifest.version)
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+            for message in extensions {
+                responses.send(CliResponse::Stdout { message }).log_err();
+            }
+        }
+        ExtensionCommand::Install { id_or_path } => {
+            // The CLI sends the paths of directories as absolute paths, which IDs never are.
+            let path = PathBuf::from(&id_or_path);
+            if !path.is_absolute() {
+                let installed = store.read_with(cx, |store, _| {
+                    store
+                        .installed_extensions()
+                        .contains_key(id_or_path.as_str())
+                })?;
+                if !installed {
+                    bail!(
+                        "installing extensions by ID needs the extension registry, which Vector doesn't use: \
+                        install {id_or_path} from its directory instead"
+                    );
+                }
+                responses
+                    .send(CliResponse::Stdout {
+                        message: format!("Extension {id_or_path} is already installed"),
+                    })
+                    .log_err();
+                return Ok(());
+            }
+            store
+                .update(cx, |store, cx| {
+                    store.install_dev_extension(path.clone(), cx)
+                })?
+                .await
+                .with_context(|| format!("failed to install the extension in {path:?}"))?;
+            responses
+                .send(CliResponse::Stdout {
+                    message: format!("Installed the extension in {}", path.display()),
+                })
+                .log_err();
+        }
+        ExtensionCommand::Uninstall { id } => {
+            let installed = store.read_with(cx, |store, _| {
+                store.installed_extensions().contains_key(id.as_str())
+            })?;
+            if !installed {
+                bail!("extension {id} is not installed");
+            }
+            store
+                .update(cx, |store, cx| {
+                    store.uninstall_extension(id.as_str().into(), cx)
+                })?
+                .await?;
+            responses
+                .send(CliResponse::Stdout {
+                    message: format!("Uninstalled extension {id}"),
+                })
+                .log_err();
+        }
+    }
+    Ok(())
+}
+
+/// Brings the window whose workspace contains all of `paths` to the front, or the frontmost window when `paths` is
+/// empty.
+fn focus_workspace_window(paths: &[String], cx: &mut AsyncApp) -> Result<()> {
+    cx.update(|cx| {
+        let paths = paths
+            .iter()
+            .map(|path| PathWithPosition::parse_str(path).path)
+            .collect::<Vec<_>>();
+        let window = workspace::local_workspace_windows_front_to_back(cx)
+            .into_iter()
+            .find(|window| {
+                window.read(cx).is_ok_and(|workspace| {
+                    let project = workspace.project().read(cx);
+                    paths
+                        .iter()
+                        .all(|path| project.find_worktree(path, cx).is_some())
+                })
+            });
+        match window {
+            Some(window) => window.update(cx, |_, window, _| window.activate_window()),
+            None if paths.is_empty() => bail!("no window is open"),
+            None => bail!("no window has {paths:?} open"),
+        }
+    })?
 }
 
 async fn open_workspaces(
@@ -353,9 +798,8 @@ async fn open_workspaces(
 
     if grouped_locations.is_empty() {
         // If we have no paths to open, show the welcome screen if this is the first launch
-        if matches!(KEY_VALUE_STORE.read_kvp(FIRST_OPEN), Ok(None)) {
-            cx.update(|cx| show_onboarding_view(app_state, cx).detach())
-                .log_err();
+        let open_task = if matches!(KEY_VALUE_STORE.read_kvp(FIRST_OPEN), Ok(None)) {
+            cx.update(|cx| show_onboarding_view(app_state, cx))
         }
         // If not the first launch, show an empty window with empty editor
         else {
@@ -367,9 +811,10 @@ async fn open_workspaces(
                 workspace::open_new(open_options, app_state, cx, |workspace, window, cx| {
                     Editor::new_file(workspace, &Default::default(), window, cx)
                 })
-                .detach();
             })
-            .log_err();
+        };
+        if let Some(open_task) = open_task.log_err() {
+            open_task.await.log_err();
         }
     } else {
         // If there are paths to open, open a workspace for each grouping of paths
@@ -424,11 +869,11 @@ async fn open_local_workspace(
     let paths_with_position =
         derive_paths_with_position(app_state.fs.as_ref(), workspace_paths).await;
 
-    // If reuse flag is passed, open a new workspace in an existing window.
+    // If reuse flag is passed, open a new workspace in the frontmost window.
     let (open_new_workspace, replace_window) = if reuse {
         (
             Some(true),
-            cx.update(|cx| workspace::local_workspace_windows(cx).into_iter().next())
+            cx.update(|cx| workspace::frontmost_local_workspace_window(cx))
                 .ok()
                 .flatten(),
         )
@@ -543,6 +988,36 @@ async fn open_local_workspace(
     errored
 }
 
+/// Merges the changes made to the base version of a file in its local and remote versions, given
+/// as `[local, base, remote, merged]` paths, and writes the merge to the merged path, with
+/// conflict markers around the changes that conflict. Returns the path of the merge.
+pub async fn write_merge(merge_paths: &[String; 4], fs: &dyn Fs) -> Result<PathBuf> {
+    let [local_path, base_path, remote_path, merged_path] = merge_paths;
+    let load = async |path: &str| {
+        fs.load(Path::new(path))
+            .await
+            .with_context(|| format!("error reading {path}"))
+    };
+    let local = load(local_path).await?;
+    let base = load(base_path).await?;
+    let remote = load(remote_path).await?;
+    let merged = language::three_way_merge(&base, &local, &remote).apply_with_conflict_markers(
+        &local,
+        local_path,
+        remote_path,
+    );
+    let merged_path = PathBuf::from(merged_path);
+    fs.atomic_write(merged_path.clone(), merged)
+        .await
+        .with_context(|| format!("error writing {}", merged_path.display()))?;
+    Ok(merged_path)
+}
+
+fn has_conflict_markers(text: &str) -> bool {
+    text.lines()
+        .any(|line| line.starts_with("<<<<<<< ") || line.starts_with(">>>>>>> "))
+}
+
 pub async fn derive_paths_with_position(
     fs: &dyn Fs,
     path_strings: impl IntoIterator<Item = impl AsRef<str>>,
@@ -562,15 +1037,19 @@ pub async fn derive_paths_with_position(
 
 #[cfg(test)]
 mod tests {
-    use super::{OpenRequest, OpenRequestKind, RawOpenRequest};
+    use super::{
+        DeepLinkCommand, OpenRequest, OpenRequestKind, RawOpenRequest, focus_workspace_window,
+        has_conflict_markers, write_merge,
+    };
     use crate::app::{open_listener::open_local_workspace, tests::init_test};
     use cli::{
         CliResponse,
         ipc::{self},
     };
     use editor::Editor;
+    use fs::Fs as _;
     use futures::poll;
-    use gpui::{AppContext as _, TestAppContext};
+    use gpui::{AppContext as _, TestAppContext, WindowHandle};
     use language::LineEnding;
     use rope::Rope;
     use serde_json::json;
@@ -646,6 +1125,40 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    fn test_parse_git_file_url(cx: &mut TestAppContext) {
+        let _app_state = init_test(cx);
+
+        let request = cx.update(|_| {
+            OpenRequest::parse(RawOpenRequest {
+                urls: vec!["vector://git/file/HEAD%40%7B1%7D?path=%2Frepo%2Fsrc%2Fmain.rs".into()],
+                ..Default::default()
+            })
+            .unwrap()
+        });
+        match request.kind.unwrap() {
+            OpenRequestKind::GitFile { revision, path } => {
+                assert_eq!(revision, "HEAD@{1}");
+                assert_eq!(path, Path::new("/repo/src/main.rs"));
+            }
+            _ => panic!("expected GitFile variant"),
+        }
+        // The directory of the file is opened, not the file itself.
+        assert_eq!(request.open_paths, vec!["/repo/src"]);
+
+        cx.update(|_| {
+            assert!(
+                OpenRequest::parse(RawOpenRequest {
+                    urls: vec!["vector://git/file/?path=%2Frepo%2Fmain.rs".into()],
+                    ..Default::default()
+                })
+                .unwrap_err()
+                .to_string()
+                .contains("missing revision")
+            );
+        });
+    }
+
     #[gpui::test]
     async fn test_open_workspace_with_directory(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
@@ -708,6 +1221,37 @@ mod tests {
             .unwrap();
     }
 
+    #[gpui::test]
+    async fn test_write_merge(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let fs = app_state.fs.as_fake();
+        fs.insert_tree(
+            path!("/root"),
+            json!({
+                "base.txt": "one\ntwo\nthree\n",
+                "local.txt": "one\nTWO\nthree\n",
+                "remote.txt": "one\ntwo\nthree\nfour\n",
+            }),
+        )
+        .await;
+
+        let merge_paths = [
+            path!("/root/local.txt").to_string(),
+            path!("/root/base.txt").to_string(),
+            path!("/root/remote.txt").to_string(),
+            path!("/root/merged.txt").to_string(),
+        ];
+        let merged_path = write_merge(&merge_paths, fs.as_ref()).await.unwrap();
+        let merged = fs.load(&merged_path).await.unwrap();
+        assert_eq!(merged, "one\nTWO\nthree\nfour\n");
+        assert!(!has_conflict_markers(&merged));
+
+        fs.insert_file(path!("/root/remote.txt"), b"one\nToo\nthree\n".to_vec())
+            .await;
+        let merged_path = write_merge(&merge_paths, fs.as_ref()).await.unwrap();
+        assert!(has_conflict_markers(&fs.load(&merged_path).await.unwrap()));
+    }
+
     #[gpui::test]
     async fn test_wait_with_directory_waits_for_window_close(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
@@ -762,6 +1306,65 @@ mod tests {
         assert!(!errored);
     }
 
+    #[gpui::test]
+    async fn test_wait_with_file_waits_for_item_close(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                path!("/root"),
+                json!({
+                    "COMMIT_EDITMSG": "\n# Please enter the commit message for your changes.\n",
+                }),
+            )
+            .await;
+
+        let (response_tx, _) = ipc::channel::<CliResponse>().unwrap();
+        let workspace_paths = vec![path!("/root/COMMIT_EDITMSG").to_owned()];
+
+        let (done_tx, mut done_rx) = futures::channel::oneshot::channel();
+        cx.spawn({
+            let app_state = app_state.clone();
+            move |mut cx| async move {
+                let errored = open_local_workspace(
+                    workspace_paths,
+                    vec![],
+                    None,
+                    false,
+                    true,
+                    &response_tx,
+                    None,
+                    &app_state,
+                    &mut cx,
+                )
+                .await;
+                let _ = done_tx.send(errored);
+            }
+        })
+        .detach();
+
+        cx.background_executor.run_until_parked();
+        assert!(matches!(poll!(&mut done_rx), Poll::Pending));
+
+        let workspace = cx.windows()[0].downcast::<Workspace>().unwrap();
+        workspace
+            .update(cx, |workspace, window, cx| {
+                workspace.active_pane().update(cx, |pane, cx| {
+                    pane.close_active_item(&Default::default(), window, cx)
+                        .detach_and_log_err(cx);
+                })
+            })
+            .unwrap();
+        cx.background_executor.run_until_parked();
+
+        // Closing the file is enough, the window stays open.
+        assert_eq!(cx.windows().len(), 1);
+        let errored = done_rx.await.unwrap();
+        assert!(!errored);
+    }
+
     #[gpui::test]
     async fn test_open_workspace_with_nonexistent_files(cx: &mut TestAppContext) {
         let app_state = init_test(cx);
@@ -939,5 +1542,123 @@ mod tests {
         assert!(!errored_reuse);
     }
 
+    #[gpui::test]
+    fn test_parse_command_url(cx: &mut TestAppContext) {
+        let parse = |url: &str| {
+            cx.update(|_| {
+                OpenRequest::parse(RawOpenRequest {
+                    urls: vec![url.into()],
+                    ..Default::default()
+                })
+            })
+        };
+        let command = |url: &str| match parse(url).unwrap().kind {
+            Some(OpenRequestKind::Command(command)) => command,
+            kind => panic!("expected Command variant, got {kind:?}"),
+        };
+
+        assert_eq!(
+            command("vector://command/settings"),
+            DeepLinkCommand::OpenSettings { path: None }
+        );
+        assert_eq!(
+            command("vector://command/settings?path=theme.mode"),
+            DeepLinkCommand::OpenSettings {
+                path: Some("theme.mode".into())
+            }
+        );
+        assert_eq!(
+            command("zed://command/extension?id=my-extension"),
+            DeepLinkCommand::OpenExtension {
+                id: "my-extension".into()
+            }
+        );
+        let task = command("vector://command/task?name=run%20tests");
+        assert_eq!(
+            task,
+            DeepLinkCommand::SpawnTask {
+                name: "run tests".into()
+            }
+        );
+        assert!(task.requires_confirmation());
+        assert!(!command("vector://command/settings").requires_confirmation());
+
+        assert!(
+            parse("vector://command/extension")
+                .unwrap_err()
+                .to_string()
+                .contains("missing id")
+        );
+        assert!(
+            parse("vector://command/workspace::CloseWindow")
+                .unwrap_err()
+                .to_string()
+                .contains("can't be run from a url")
+        );
+    }
+
+    #[gpui::test]
+    async fn test_focus_and_frontmost_window(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let (first_dir, second_dir) = (path!("/root/first"), path!("/root/second"));
+        app_state.fs.create_dir(Path::new(first_dir)).await.unwrap();
+        app_state
+            .fs
+            .create_dir(Path::new(second_dir))
+            .await
+            .unwrap();
+
+        let (response_tx, _response_rx) = ipc::channel::<CliResponse>().unwrap();
+        for dir in [first_dir, second_dir] {
+            let errored = cx
+                .spawn({
+                    let app_state = app_state.clone();
+                    let response_tx = response_tx.clone();
+                    move |mut cx| async move {
+                        open_local_workspace(
+                            vec![dir.to_string()],
+                            vec![],
+                            Some(true),
+                            false,
+                            false,
+                            &response_tx,
+                            None,
+                            &app_state,
+                            &mut cx,
+                        )
+                        .await
+                    }
+                })
+                .await;
+            assert!(!errored);
+        }
+
+        let worktree_root = |window: WindowHandle<Workspace>, cx: &mut TestAppContext| {
+            window
+                .read_with(cx, |workspace, cx| {
+                    workspace
+                        .project()
+                        .read(cx)
+                        .visible_worktrees(cx)
+                        .next()
+                        .unwrap()
+                        .read(cx)
+                        .abs_path()
+                        .to_path_buf()
+                })
+                .unwrap()
+        };
+        let frontmost = cx
+            .update(|cx| workspace::frontmost_local_workspace_window(cx))
+            .unwrap();
+        assert_eq!(worktree_root(frontmost, cx), Path::new(second_dir));
+
+        cx.spawn(move |mut cx| async move {
+            focus_workspace_window(&[first_dir.to_string()], &mut cx).unwrap();
+            assert!(focus_workspace_window(&[path!("/elsewhere").to_string()], &mut cx).is_err());
+        })
+        .await;
+    }
+
     // Git clone URLs are intentionally unsupported in offline-only mode.
 }
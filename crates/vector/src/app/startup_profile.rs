@@ -0,0 +1,165 @@
+//! Recording how long each step of the startup takes, for `--profile-startup`.
+//!
+//! The spans are written in the Chrome trace format, which `chrome://tracing`, Perfetto, and Speedscope open, once
+//! the first workspace is restored or opened.
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use anyhow::{Context as _, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+static PROFILE: OnceLock<StartupProfile> = OnceLock::new();
+
+struct StartupProfile {
+    path: PathBuf,
+    started_at: Instant,
+    /// The spans recorded so far, or `None` once the profile is written.
+    events: Mutex<Option<Vec<TraceEvent>>>,
+}
+
+/// A complete event of the Chrome trace format, with times in microseconds since the start of the process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    ts: u64,
+    dur: u64,
+    pid: u32,
+    tid: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace<'a> {
+    trace_events: &'a [TraceEvent],
+    display_time_unit: &'static str,
+}
+
+/// Records the spans of the startup, to write them to `path` once Vector started. `started_at` is when the process
+/// started, which the times of the spans are relative to.
+pub fn init(path: PathBuf, started_at: Instant) {
+    PROFILE
+        .set(StartupProfile {
+            path,
+            started_at,
+            events: Mutex::new(Some(Vec::new())),
+        })
+        .ok();
+}
+
+/// Starts a span of the startup, which ends when the returned guard is dropped.
+#[must_use]
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        started_at: PROFILE.get().map(|_| Instant::now()),
+    }
+}
+
+/// Records a span of the startup that started at `started_at` and ends now.
+pub fn record(name: &'static str, started_at: Instant) {
+    drop(Span {
+        name,
+        started_at: PROFILE.get().map(|_| started_at),
+    });
+}
+
+pub struct Span {
+    name: &'static str,
+    /// `None` when the startup isn't profiled.
+    started_at: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let (Some(profile), Some(started_at)) = (PROFILE.get(), self.started_at) else {
+            return;
+        };
+        let event = TraceEvent {
+            name: self.name,
+            ph: "X",
+            ts: started_at
+                .saturating_duration_since(profile.started_at)
+                .as_micros() as u64,
+            dur: started_at.elapsed().as_micros() as u64,
+            pid: std::process::id(),
+            tid: thread_id(),
+        };
+        if let Some(events) = profile.events.lock().as_mut() {
+            events.push(event);
+        }
+    }
+}
+
+/// A small number identifying the current thread, since the trace format wants numbers.
+fn thread_id() -> u64 {
+    static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+    }
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+fn to_json(events: &[TraceEvent]) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec_pretty(&Trace {
+        trace_events: events,
+        display_time_unit: "ms",
+    })?)
+}
+
+/// Writes the spans recorded so far to the profile, when the startup is profiled. Spans that end afterwards aren't
+/// recorded.
+pub fn finish() {
+    let Some(profile) = PROFILE.get() else {
+        return;
+    };
+    let Some(events) = profile.events.lock().take() else {
+        return;
+    };
+    let result = to_json(&events).and_then(|json| {
+        std::fs::write(&profile.path, json)
+            .with_context(|| format!("failed to write the startup profile to {:?}", profile.path))
+    });
+    match result {
+        Ok(()) => log::info!("wrote the startup profile to {:?}", profile.path),
+        Err(error) => log::error!("{error:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_profile_json() {
+        let json = to_json(&[TraceEvent {
+            name: "settings",
+            ph: "X",
+            ts: 1500,
+            dur: 250,
+            pid: 42,
+            tid: 1,
+        }])
+        .unwrap();
+        let trace: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            trace,
+            serde_json::json!({
+                "traceEvents": [
+                    {"name": "settings", "ph": "X", "ts": 1500, "dur": 250, "pid": 42, "tid": 1}
+                ],
+                "displayTimeUnit": "ms",
+            })
+        );
+    }
+}
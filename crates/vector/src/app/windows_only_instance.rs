@@ -25,13 +25,21 @@ use windows::{
 
 use crate::{Args, OpenListener, RawOpenRequest};
 
+/// Identifies the instance of the current profile, since each profile runs its own instance.
+fn instance_identifier() -> String {
+    match paths::profile() {
+        Some(profile) => format!("{}-Profile-{profile}", app_identifier()),
+        None => app_identifier().to_string(),
+    }
+}
+
 #[inline]
 fn is_first_instance() -> bool {
     unsafe {
         CreateMutexW(
             None,
             false,
-            &HSTRING::from(format!("{}-Instance-Mutex", app_identifier())),
+            &HSTRING::from(format!("{}-Instance-Mutex", instance_identifier())),
         )
         .expect("Unable to create instance mutex.")
     };
@@ -64,7 +72,7 @@ pub fn handle_single_instance(opener: OpenListener, args: &Args) -> bool {
 fn with_pipe(f: impl Fn(String)) {
     let pipe = unsafe {
         CreateNamedPipeW(
-            &HSTRING::from(format!("\\\\.\\pipe\\{}-Named-Pipe", app_identifier())),
+            &HSTRING::from(format!("\\\\.\\pipe\\{}-Named-Pipe", instance_identifier())),
             PIPE_ACCESS_INBOUND,
             PIPE_TYPE_MESSAGE | PIPE_READMODE_MESSAGE | PIPE_WAIT,
             1,
@@ -154,12 +162,20 @@ fn send_args_to_instance(args: &Args) -> anyhow::Result<()> {
             paths,
             urls,
             diff_paths,
-            wait: false,
+            merge_paths: <[String; 4]>::try_from(args.merge.clone()).ok(),
+            wait: args.wait || args.merge.len() == 4,
             wsl: args.wsl.clone(),
             open_new_workspace: None,
             reuse: false,
+            focus: false,
             env: None,
-            user_data_dir: args.user_data_dir.clone(),
+            user_data_dir: args.user_data_dir.clone().or_else(|| {
+                // The profile's data directory was set before the single instance check.
+                args.profile
+                    .as_ref()
+                    .map(|_| paths::data_dir().to_string_lossy().into_owned())
+            }),
+            no_daemon: args.no_daemon,
         }
     };
 
@@ -201,7 +217,7 @@ fn send_args_to_instance(args: &Args) -> anyhow::Result<()> {
 fn write_message_to_instance_pipe(message: &[u8]) -> anyhow::Result<()> {
     unsafe {
         let pipe = CreateFileW(
-            &HSTRING::from(format!("\\\\.\\pipe\\{}-Named-Pipe", app_identifier())),
+            &HSTRING::from(format!("\\\\.\\pipe\\{}-Named-Pipe", instance_identifier())),
             GENERIC_WRITE.0,
             FILE_SHARE_MODE::default(),
             None,
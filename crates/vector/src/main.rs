@@ -11,26 +11,31 @@ use collections::HashMap;
 use db::kvp::KEY_VALUE_STORE;
 use editor::Editor;
 use extension::ExtensionHostProxy;
+use extension_host::{DisabledExtensions, ExtensionStore};
 use fs::{Fs, RealFs};
-use futures::{FutureExt as _, StreamExt, channel::oneshot};
+use futures::{
+    FutureExt as _, StreamExt,
+    channel::{mpsc, oneshot},
+};
 use git::GitHostingProviderRegistry;
-use gpui::{App, AppContext, Application, AsyncApp, QuitMode, UpdateGlobal as _};
+use gpui::{App, AppContext, Application, AsyncApp, QuitMode, Task, UpdateGlobal as _};
 use http_client::{BlockedHttpClient, HttpClientWithUrl};
 use language::LanguageRegistry;
 use onboarding::{FIRST_OPEN, show_onboarding_view};
 
 use crate::app::eager_load_active_theme_and_icon_theme;
 use crate::app::{
-    OpenListener, OpenRequest, OpenRequestKind, RawOpenRequest, app_menus, build_window_options,
-    derive_paths_with_position, handle_cli_connection, handle_keymap_file_changes,
-    handle_settings_file_changes, initialize_workspace, open_paths_with_positions,
+    DeepLinkCommand, OpenListener, OpenRequest, OpenRequestKind, RawOpenRequest, app_menus,
+    build_window_options, crash_recovery, derive_paths_with_position, handle_cli_connection,
+    handle_keymap_file_changes, handle_settings_file_changes, initialize_workspace,
+    open_paths_with_positions, startup_profile, write_merge,
 };
 use assets::Assets;
 use node_runtime::{NodeBinaryOptions, NodeRuntime};
 use parking_lot::Mutex;
 use project::{project_settings::ProjectSettings, trusted_worktrees};
 use release_channel::{AppCommitSha, AppVersion, ReleaseChannel};
-use session::{AppSession, Session};
+use session::{AppSession, NamedSession, Session};
 use settings::{Settings as _, SettingsStore, watch_config_file};
 use std::{
     io::{self, IsTerminal},
@@ -194,10 +199,24 @@ fn main() {
         return;
     }
 
+    if let Some(path) = &args.profile_startup {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        startup_profile::init(path, *STARTUP_TIME.get().unwrap());
+    }
+
     // Set custom data directory.
     if let Some(dir) = &args.user_data_dir {
         paths::set_custom_data_dir(dir);
     }
+    if let Some(profile) = &args.profile {
+        if !paths::is_valid_profile_name(profile) {
+            eprintln!(
+                "Invalid profile name {profile:?}: profile names can only contain ASCII letters, digits, '-' and '_'"
+            );
+            process::exit(1);
+        }
+        paths::set_profile(profile);
+    }
 
     #[cfg(target_os = "windows")]
     match util::get_zed_cli_path() {
@@ -256,6 +275,11 @@ fn main() {
     #[cfg(windows)]
     check_for_conpty_dll();
 
+    if args.headless {
+        app::headless::run(args.paths_or_urls, args.headless_socket, app_version);
+        return;
+    }
+
     let app = Application::new().with_assets(Assets);
 
     let session_id = Uuid::new_v4().to_string();
@@ -285,10 +309,30 @@ fn main() {
         }
     };
     if failed_single_instance_check {
+        if args.safe_mode || args.bisect_extensions.is_some() {
+            eprintln!(
+                "Vector is already running. Quit it before launching with `--safe-mode` or `--bisect-extensions`."
+            );
+            process::exit(1);
+        }
         println!("Vector is already running");
         return;
     }
 
+    let disabled_extensions = if args.safe_mode {
+        log::info!("starting in safe mode");
+        DisabledExtensions::All
+    } else {
+        match app::extension_bisect::disabled_extensions(args.bisect_extensions) {
+            Ok(Some(disabled_extensions)) => disabled_extensions,
+            Ok(None) => return,
+            Err(error) => {
+                eprintln!("Failed to bisect extensions: {error:#}");
+                process::exit(1);
+            }
+        }
+    };
+
     let git_hosting_provider_registry = Arc::new(GitHostingProviderRegistry::new());
     let git_binary_path =
         if cfg!(target_os = "macos") && option_env!("VECTOR_BUNDLE").as_deref() == Some("true") {
@@ -313,11 +357,17 @@ fn main() {
         fs.clone(),
         paths::global_settings_file().clone(),
     );
-    let user_keymap_file_rx = watch_config_file(
-        &app.background_executor(),
-        fs.clone(),
-        paths::keymap_file().clone(),
-    );
+    let user_keymap_file_rx = if args.safe_mode {
+        let (tx, rx) = mpsc::unbounded();
+        tx.unbounded_send(String::new()).ok();
+        rx
+    } else {
+        watch_config_file(
+            &app.background_executor(),
+            fs.clone(),
+            paths::keymap_file().clone(),
+        )
+    };
 
     let (shell_env_loaded_tx, shell_env_loaded_rx) = oneshot::channel();
     if !stdout_is_a_pty() {
@@ -337,7 +387,7 @@ fn main() {
         move |urls| {
             open_listener.open(RawOpenRequest {
                 urls,
-                diff_paths: Vec::new(),
+                ..Default::default()
             })
         }
     });
@@ -356,7 +406,20 @@ fn main() {
         }
     });
 
+    let crashed_process_id = reliability::take_crash_marker();
+
     app.run(move |cx| {
+        startup_profile::record("launch", *STARTUP_TIME.get().unwrap());
+        cx.on_app_quit(|_| {
+            reliability::clear_crash_marker();
+            async {}
+        })
+        .detach();
+        if args.no_daemon {
+            app::disable_daemon_for_launch(cx);
+        }
+
+        let span = startup_profile::span("settings");
         let db_trusted_paths = match workspace::WORKSPACE_DB.fetch_trusted_worktrees() {
             Ok(trusted_paths) => trusted_paths,
             Err(e) => {
@@ -377,6 +440,7 @@ fn main() {
         zlog_settings::init(cx);
         handle_settings_file_changes(user_settings_file_rx, global_settings_file_rx, cx);
         handle_keymap_file_changes(user_keymap_file_rx, cx);
+        drop(span);
         auto_update::init(cx);
 
         // Strict offline: provide a blocked HTTP client so any accidental network usage fails fast.
@@ -394,6 +458,7 @@ fn main() {
 
         OpenListener::set_global(cx, open_listener.clone());
 
+        let span = startup_profile::span("languages");
         extension::init(cx);
         let extension_host_proxy = ExtensionHostProxy::global(cx);
 
@@ -425,27 +490,7 @@ fn main() {
 
         let (mut tx, rx) = watch::channel(None);
         cx.observe_global::<SettingsStore>(move |cx| {
-            let settings = &ProjectSettings::get_global(cx).node;
-            let options = NodeBinaryOptions {
-                allow_path_lookup: !settings.ignore_system_version,
-                // Strict offline: never download Node automatically.
-                allow_binary_download: false,
-                use_paths: settings.path.as_ref().map(|node_path| {
-                    let node_path = PathBuf::from(shellexpand::tilde(node_path).as_ref());
-                    let npm_path = settings
-                        .npm_path
-                        .as_ref()
-                        .map(|path| PathBuf::from(shellexpand::tilde(&path).as_ref()));
-                    (
-                        node_path.clone(),
-                        npm_path.unwrap_or_else(|| {
-                            let base_path = PathBuf::new();
-                            node_path.parent().unwrap_or(&base_path).join("npm")
-                        }),
-                    )
-                }),
-            };
-            tx.send(Some(options)).log_err();
+            tx.send(Some(node_binary_options(cx))).log_err();
         })
         .detach();
 
@@ -458,9 +503,16 @@ fn main() {
         project::Project::init(cx);
         debugger_ui::init(cx);
         debugger_tools::init(cx);
+        drop(span);
 
+        let span = startup_profile::span("session");
         let session = cx.background_executor().block(session);
-        let app_session = cx.new(|cx| AppSession::new(session, cx));
+        let app_session = cx.new(|cx| {
+            let mut app_session = AppSession::new(session, cx);
+            app_session.set_named_session_to_restore(args.session.clone());
+            app_session
+        });
+        drop(span);
 
         let app_state = Arc::new(AppState {
             languages,
@@ -475,14 +527,24 @@ fn main() {
 
         dap_adapters::init(cx);
 
+        let span = startup_profile::span("extensions");
         extension_host::init(
             extension_host_proxy.clone(),
             app_state.fs.clone(),
             app_state.http_client.clone(),
             app_state.node_runtime.clone(),
+            disabled_extensions,
             cx,
         );
+        // The installed extensions are loaded in the background.
+        let extensions_loaded = ExtensionStore::global(cx).read(cx).wait_for_initial_load();
+        cx.spawn(async move |_| {
+            extensions_loaded.await;
+            drop(span);
+        })
+        .detach();
 
+        let span = startup_profile::span("themes");
         theme::init(theme::LoadThemes::All(Box::new(Assets)), cx);
         eager_load_active_theme_and_icon_theme(fs.clone(), cx);
         theme_extension::init(
@@ -490,6 +552,9 @@ fn main() {
             ThemeRegistry::global(cx),
             cx.background_executor().clone(),
         );
+        drop(span);
+
+        let span = startup_profile::span("ui");
         command_palette::init(cx);
         snippet_provider::init(cx);
         repl::init(app_state.fs.clone(), cx);
@@ -537,6 +602,7 @@ fn main() {
         json_schema_store::init(cx);
         miniprofiler_ui::init(*STARTUP_TIME.get().unwrap(), cx);
         which_key::init(cx);
+        drop(span);
 
         cx.observe_global::<SettingsStore>({
             let fs = fs.clone();
@@ -558,8 +624,10 @@ fn main() {
         .detach();
 
         let fs = app_state.fs.clone();
-        load_user_themes_in_background(fs.clone(), cx);
-        watch_themes(fs.clone(), cx);
+        if !args.safe_mode {
+            load_user_themes_in_background(fs.clone(), cx);
+            watch_themes(fs.clone(), cx);
+        }
         watch_languages(fs.clone(), app_state.languages.clone(), cx);
 
         let menus = app_menus(cx);
@@ -580,8 +648,14 @@ fn main() {
             .map(|chunk| [chunk[0].clone(), chunk[1].clone()])
             .collect();
 
-        if !urls.is_empty() || !diff_paths.is_empty() {
-            open_listener.open(RawOpenRequest { urls, diff_paths })
+        let merge_paths = <[String; 4]>::try_from(args.merge).ok();
+
+        if !urls.is_empty() || !diff_paths.is_empty() || merge_paths.is_some() {
+            open_listener.open(RawOpenRequest {
+                urls,
+                diff_paths,
+                merge_paths,
+            })
         }
 
         match open_rx
@@ -591,15 +665,41 @@ fn main() {
             .and_then(|request| OpenRequest::parse(request).log_err())
         {
             Some(request) => {
-                handle_open_request(request, app_state.clone(), cx);
+                let span = startup_profile::span("open request");
+                let open_request = handle_open_request(request, true, app_state.clone(), cx);
+                cx.spawn(async move |_| {
+                    open_request.await;
+                    drop(span);
+                    startup_profile::finish();
+                })
+                .detach();
             }
             None => {
                 cx.spawn({
                     let app_state = app_state.clone();
                     async move |cx| {
-                        if let Err(e) = restore_or_create_workspace(app_state, cx).await {
+                        let span = startup_profile::span("workspace restore");
+                        let recovered = match crashed_process_id {
+                            Some(crashed_process_id) => {
+                                crash_recovery::recover_from_crash(
+                                    crashed_process_id,
+                                    app_state.clone(),
+                                    cx,
+                                )
+                                .await
+                            }
+                            None => Ok(false),
+                        };
+                        let result = match recovered {
+                            Ok(true) => Ok(()),
+                            Ok(false) => restore_or_create_workspace(app_state, cx).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = result {
                             fail_to_open_window_async(e, cx)
                         }
+                        drop(span);
+                        startup_profile::finish();
                     }
                 })
                 .detach();
@@ -612,7 +712,7 @@ fn main() {
             while let Some(urls) = open_rx.next().await {
                 cx.update(|cx| {
                     if let Some(request) = OpenRequest::parse(urls).log_err() {
-                        handle_open_request(request, app_state.clone(), cx);
+                        handle_open_request(request, false, app_state.clone(), cx).detach();
                     }
                 })
                 .ok();
@@ -622,15 +722,22 @@ fn main() {
     });
 }
 
-fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut App) {
+/// Handles a request to open something, returning a task that completes once it's opened. `launched_for_request` tells
+/// whether Vector was launched to handle it.
+fn handle_open_request(
+    request: OpenRequest,
+    launched_for_request: bool,
+    app_state: Arc<AppState>,
+    cx: &mut App,
+) -> Task<()> {
     if let Some(kind) = request.kind {
-        match kind {
-            OpenRequestKind::CliConnection(connection) => {
-                cx.spawn(async move |cx| handle_cli_connection(connection, app_state, cx).await)
-                    .detach();
-            }
+        return match kind {
+            OpenRequestKind::CliConnection(connection) => cx.spawn(async move |cx| {
+                handle_cli_connection(connection, launched_for_request, app_state, cx).await
+            }),
             OpenRequestKind::DockMenuAction { index } => {
                 cx.perform_dock_menu_action(index);
+                Task::ready(())
             }
             OpenRequestKind::BuiltinJsonSchema { schema_path } => {
                 workspace::with_active_or_new_workspace(cx, |_workspace, window, cx| {
@@ -691,13 +798,14 @@ fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut
                     })
                     .detach();
                 });
+                Task::ready(())
             }
             OpenRequestKind::Setting { setting_path } => {
                 // zed://settings/languages/$(language)/tab_size  - DONT SUPPORT
                 // zed://settings/languages/Rust/tab_size  - SUPPORT
                 // languages.$(language).tab_size
                 // [ languages $(language) tab_size]
-                cx.spawn(async move |cx| {
+                let task = cx.spawn(async move |cx| {
                     let workspace =
                         workspace::get_any_active_workspace(app_state, cx.clone()).await?;
 
@@ -708,11 +816,71 @@ fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut
                             cx,
                         ),
                     })
-                })
-                .detach_and_log_err(cx);
+                });
+                log_task_error(task, cx)
+            }
+            OpenRequestKind::Command(command) => {
+                let task = cx.spawn(async move |cx| {
+                    let workspace =
+                        workspace::get_any_active_workspace(app_state, cx.clone()).await?;
+                    if command.requires_confirmation() {
+                        let mut detail = format!("{}?", command.description());
+                        if let DeepLinkCommand::SpawnTask { name } = &command {
+                            let tasks = workspace
+                                .update(cx, |_, window, cx| {
+                                    tasks_ui::resolve_tasks_by_name(name.clone(), window, cx)
+                                })?
+                                .await?;
+                            if tasks.is_empty() {
+                                detail.push_str(
+                                    "\n\nNo task has this name, so the task picker opens instead.",
+                                );
+                            }
+                            for task in tasks {
+                                detail.push_str(&format!(
+                                    "\n\nCommand: {}",
+                                    task.resolved.command_label
+                                ));
+                                if let Some(cwd) = &task.resolved.cwd {
+                                    detail.push_str(&format!("\nIn: {}", cwd.display()));
+                                }
+                            }
+                        }
+                        let project = workspace.update(cx, |workspace, _, cx| {
+                            workspace
+                                .project()
+                                .read(cx)
+                                .visible_worktrees(cx)
+                                .map(|worktree| worktree.read(cx).abs_path().display().to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        })?;
+                        if !project.is_empty() {
+                            detail.push_str(&format!("\n\nProject: {project}"));
+                        }
+                        let answer = workspace
+                            .update(cx, |_, window, cx| {
+                                window.prompt(
+                                    gpui::PromptLevel::Warning,
+                                    "A link wants to run a command",
+                                    Some(&detail),
+                                    &["Run", "Cancel"],
+                                    cx,
+                                )
+                            })?
+                            .await?;
+                        if answer != 0 {
+                            return Ok(());
+                        }
+                    }
+                    workspace.update(cx, |_, window, cx| {
+                        window.dispatch_action(command.action(), cx)
+                    })
+                });
+                log_task_error(task, cx)
             }
             OpenRequestKind::GitCommit { sha } => {
-                cx.spawn(async move |cx| {
+                let task = cx.spawn(async move |cx| {
                     let paths_with_position =
                         derive_paths_with_position(app_state.fs.as_ref(), request.open_paths).await;
                     let (workspace, _results) = open_paths_with_positions(
@@ -746,20 +914,49 @@ fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut
                         .log_err();
 
                     anyhow::Ok(())
-                })
-                .detach_and_log_err(cx);
+                });
+                log_task_error(task, cx)
             }
-        }
+            OpenRequestKind::GitFile { revision, path } => {
+                let task = cx.spawn(async move |cx| {
+                    let paths_with_position =
+                        derive_paths_with_position(app_state.fs.as_ref(), request.open_paths).await;
+                    let (workspace, _results) = open_paths_with_positions(
+                        &paths_with_position,
+                        &[],
+                        app_state,
+                        workspace::OpenOptions::default(),
+                        cx,
+                    )
+                    .await?;
 
-        return;
+                    workspace
+                        .update(cx, |workspace, window, cx| {
+                            git_ui::commit_view::open_file_at_revision(
+                                revision, path, workspace, window, cx,
+                            )
+                        })?
+                        .await
+                });
+                log_task_error(task, cx)
+            }
+        };
     }
 
     let mut task = None;
-    if !request.open_paths.is_empty() || !request.diff_paths.is_empty() {
+    if !request.open_paths.is_empty()
+        || !request.diff_paths.is_empty()
+        || request.merge_paths.is_some()
+    {
         let app_state = app_state.clone();
         task = Some(cx.spawn(async move |cx| {
+            let mut open_paths = request.open_paths;
+            if let Some(merge_paths) = &request.merge_paths {
+                let merged_path = write_merge(merge_paths, app_state.fs.as_ref()).await?;
+                open_paths.push(merged_path.to_string_lossy().into_owned());
+            }
             let paths_with_position =
-                derive_paths_with_position(app_state.fs.as_ref(), request.open_paths).await;
+                derive_paths_with_position(app_state.fs.as_ref(), open_paths).await;
             let (_window, results) = open_paths_with_positions(
                 &paths_with_position,
                 &request.diff_paths,
@@ -777,14 +974,21 @@ fn handle_open_request(request: OpenRequest, app_state: Arc<AppState>, cx: &mut
         }));
     }
 
-    if let Some(task) = task {
-        cx.spawn(async move |cx| {
-            if let Err(err) = task.await {
-                fail_to_open_window_async(err, cx);
-            }
-        })
-        .detach();
-    }
+    let Some(task) = task else {
+        return Task::ready(());
+    };
+    cx.spawn(async move |cx| {
+        if let Err(err) = task.await {
+            fail_to_open_window_async(err, cx);
+        }
+    })
+}
+
+/// Logs the error a task fails with, in a task that can be awaited rather than detached.
+fn log_task_error<T: 'static>(task: Task<Result<T>>, cx: &App) -> Task<()> {
+    cx.spawn(async move |_| {
+        task.await.log_err();
+    })
 }
 
 async fn restore_or_create_workspace(app_state: Arc<AppState>, cx: &mut AsyncApp) -> Result<()> {
@@ -906,17 +1110,31 @@ pub(crate) async fn restorable_workspace_locations(
         .ok()?;
 
     let session_handle = app_state.session.clone();
-    let (last_session_id, last_session_window_stack) = cx
+    let (last_session_id, last_session_window_stack, named_session) = cx
         .update(|cx| {
             let session = session_handle.read(cx);
 
             (
                 session.last_session_id().map(|id| id.to_string()),
                 session.last_session_window_stack(),
+                session
+                    .named_session_to_restore()
+                    .map(|name| name.to_string()),
             )
         })
         .ok()?;
 
+    if let Some(name) = named_session {
+        match session::named_sessions().map(|mut sessions| sessions.remove(&name)) {
+            Ok(Some(named_session)) => {
+                return Some(named_session_workspace_locations(named_session, cx).await)
+                    .filter(|locations| !locations.is_empty());
+            }
+            Ok(None) => log::error!("no session is saved under the name {name:?}"),
+            Err(error) => log::error!("failed to load the named sessions: {error:#}"),
+        }
+    }
+
     if last_session_id.is_none()
         && matches!(
             restore_behavior,
@@ -957,6 +1175,26 @@ pub(crate) async fn restorable_workspace_locations(
     }
 }
 
+/// Returns the locations of the workspaces of a named session that still exist, in the order to open them, which puts
+/// the frontmost workspace in front.
+pub(crate) async fn named_session_workspace_locations(
+    session: NamedSession,
+    cx: &AsyncApp,
+) -> Vec<(SerializedWorkspaceLocation, PathList)> {
+    cx.background_spawn(async move {
+        session
+            .workspaces
+            .into_iter()
+            .rev()
+            .filter(|paths| {
+                paths.iter().all(|path| path.exists()) && paths.iter().any(|path| path.is_dir())
+            })
+            .map(|paths| (SerializedWorkspaceLocation::Local, PathList::new(&paths)))
+            .collect()
+    })
+    .await
+}
+
 fn init_paths() -> HashMap<io::ErrorKind, Vec<&'static Path>> {
     [
         paths::config_dir(),
@@ -996,6 +1234,25 @@ struct Args {
     #[arg(long, action = clap::ArgAction::Append, num_args = 2, value_names = ["OLD_PATH", "NEW_PATH"])]
     diff: Vec<String>,
 
+    /// Merges the changes made to a base file in local and remote versions of it, and opens the
+    /// merge, with conflict markers around the conflicting changes.
+    #[arg(long, num_args = 4, value_names = ["LOCAL", "BASE", "REMOTE", "MERGED"])]
+    merge: Vec<String>,
+
+    /// Runs Vector without opening windows, serving requests to open files, search the project,
+    /// and get diagnostics over a local socket. The paths are added to the project.
+    ///
+    /// The headless server never loads extensions, the keymap or themes, so it can't be combined
+    /// with `--safe-mode` or `--bisect-extensions`.
+    #[arg(long, conflicts_with_all = ["safe_mode", "bisect_extensions"])]
+    headless: bool,
+
+    /// The socket the headless server listens on.
+    ///
+    /// Defaults to a socket in the data directory.
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    headless_socket: Option<PathBuf>,
+
     /// Sets a custom directory for all user data (e.g., database, extensions, logs).
     ///
     /// This overrides the default platform-specific data directory location.
@@ -1005,6 +1262,41 @@ struct Args {
     #[arg(long, value_name = "DIR", verbatim_doc_comment)]
     user_data_dir: Option<String>,
 
+    /// Uses the named profile, which has its own settings, extensions and sessions, and runs its
+    /// own instance of Vector. Profiles are created when first used.
+    #[arg(long, value_name = "NAME", conflicts_with = "user_data_dir")]
+    profile: Option<String>,
+
+    /// Quits Vector once its windows are closed, even when `on_last_window_closed` is
+    /// `run_in_background`. Passed to an instance that's already running, quits it once the
+    /// windows this opens are closed along with all other windows.
+    #[arg(long)]
+    no_daemon: bool,
+
+    /// Starts Vector without extensions, the user keymap, and user themes, to find out whether
+    /// they keep Vector from working.
+    #[arg(long)]
+    safe_mode: bool,
+
+    /// Bisects the installed extensions, to find the one that keeps Vector from working.
+    ///
+    /// `start` launches Vector with half of the extensions enabled. After each launch, run
+    /// Vector again with `good` if it worked, or `bad` if it didn't, until the extension is
+    /// found. `reset` stops bisecting.
+    #[arg(long, value_name = "STEP", conflicts_with = "safe_mode")]
+    bisect_extensions: Option<app::extension_bisect::BisectStep>,
+
+    /// Restores the workspaces of the session saved under a name, instead of the last session.
+    ///
+    /// Sessions are saved with `vector --save-session NAME`.
+    #[arg(long, value_name = "NAME")]
+    session: Option<String>,
+
+    /// Records how long each step of the startup takes, and writes it to a file in the Chrome
+    /// trace format, which `chrome://tracing` and Perfetto open, once the first workspace opened.
+    #[arg(long, value_name = "PATH")]
+    profile_startup: Option<PathBuf>,
+
     /// The username and WSL distribution to use when opening paths. If not specified,
     /// Zed will attempt to open the paths directly.
     ///
@@ -1040,6 +1332,14 @@ struct Args {
     #[arg(long, hide = true)]
     crash_handler: Option<PathBuf>,
 
+    /// Wait for all of the given paths to be opened/closed before exiting, when Vector is already
+    /// running. Used by tools opening files in an editor, like `GIT_EDITOR="vector --wait"`.
+    ///
+    /// When opening a directory, waits until the created window is closed.
+    #[arg(short, long)]
+    #[cfg(target_os = "windows")]
+    wait: bool,
+
     /// Run zed in the foreground, only used on Windows, to match the behavior on macOS.
     #[arg(long)]
     #[cfg(target_os = "windows")]
@@ -1067,11 +1367,36 @@ struct Args {
     printenv: bool,
 }
 
+fn node_binary_options(cx: &App) -> NodeBinaryOptions {
+    let settings = &ProjectSettings::get_global(cx).node;
+    NodeBinaryOptions {
+        allow_path_lookup: !settings.ignore_system_version,
+        // Strict offline: never download Node automatically.
+        allow_binary_download: false,
+        use_paths: settings.path.as_ref().map(|node_path| {
+            let node_path = PathBuf::from(shellexpand::tilde(node_path).as_ref());
+            let npm_path = settings
+                .npm_path
+                .as_ref()
+                .map(|path| PathBuf::from(shellexpand::tilde(&path).as_ref()));
+            (
+                node_path.clone(),
+                npm_path.unwrap_or_else(|| {
+                    let base_path = PathBuf::new();
+                    node_path.parent().unwrap_or(&base_path).join("npm")
+                }),
+            )
+        }),
+    }
+}
+
 fn parse_url_arg(arg: &str) -> String {
     match std::fs::canonicalize(Path::new(&arg)) {
         Ok(path) => format!("file://{}", path.display()),
         Err(_) => {
-            if arg.starts_with("file://")
+            if let Some(url) = cli::git_file_url(arg) {
+                url
+            } else if arg.starts_with("file://")
                 || arg.starts_with("vector-cli://")
                 || arg.starts_with("zed-cli://")
                 || arg.starts_with("vector-dock-action://")
@@ -2,12 +2,33 @@ use crate::stdout_is_a_pty;
 use backtrace::{self, Backtrace};
 use chrono::Utc;
 use release_channel::{AppCommitSha, RELEASE_CHANNEL, ReleaseChannel};
-use std::{env, ffi::c_void, sync::atomic::Ordering};
+use std::{env, ffi::c_void, path::PathBuf, sync::atomic::Ordering};
 use std::{io::Write, panic, sync::atomic::AtomicU32, thread};
 use util::ResultExt;
 
 static PANIC_COUNT: AtomicU32 = AtomicU32::new(0);
 
+/// The file that records the process of the running launch, removed when it quits cleanly, so that the next launch
+/// can tell the previous one crashed, whether it panicked, was killed or went down with the system.
+fn crash_marker_file() -> PathBuf {
+    paths::data_dir().join("running")
+}
+
+/// Returns the process id of the previous launch if it didn't quit cleanly, and records that this launch is running
+/// until [`clear_crash_marker`] is called.
+pub fn take_crash_marker() -> Option<u32> {
+    let crashed_process_id = std::fs::read_to_string(crash_marker_file())
+        .ok()
+        .and_then(|process_id| process_id.trim().parse().ok());
+    std::fs::write(crash_marker_file(), std::process::id().to_string()).log_err();
+    crashed_process_id
+}
+
+/// Records that this launch quit cleanly.
+pub fn clear_crash_marker() {
+    std::fs::remove_file(crash_marker_file()).log_err();
+}
+
 mod system_info {
     pub fn os_name() -> String {
         #[cfg(target_os = "macos")]
@@ -104,6 +104,16 @@ pub struct Extensions {
     pub id: Option<String>,
 }
 
+/// Switches to another profile, quitting this instance of Vector and launching the profile's.
+#[derive(PartialEq, Clone, Default, Debug, Deserialize, JsonSchema, Action)]
+#[action(namespace = zed)]
+#[serde(deny_unknown_fields)]
+pub struct SwitchProfile {
+    /// The profile to switch to. Prompts for the profile when not specified.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
 /// Decreases the font size in the editor buffer.
 #[derive(PartialEq, Clone, Default, Debug, Deserialize, JsonSchema, Action)]
 #[action(namespace = zed)]
@@ -0,0 +1,202 @@
+//! Images and files attached to the messages of completion requests.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+
+/// The largest image, once decoded, that can be attached to a message.
+pub const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+/// The largest file that can be referenced from a message.
+pub const MAX_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// The most images and files that can be attached to the messages of one request.
+pub const MAX_ATTACHMENTS_PER_REQUEST: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ImageMediaType {
+    #[serde(rename = "image/png")]
+    Png,
+    #[serde(rename = "image/jpeg")]
+    Jpeg,
+    #[serde(rename = "image/gif")]
+    Gif,
+    #[serde(rename = "image/webp")]
+    Webp,
+}
+
+impl ImageMediaType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    /// Detects the type of an image from its first bytes.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(b"\xff\xd8\xff") {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+}
+
+/// An image attached to a message, such as a screenshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    pub media_type: ImageMediaType,
+    /// The base64-encoded image.
+    pub data: String,
+}
+
+impl ImageAttachment {
+    /// Attaches an image, detecting its type.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AttachmentError> {
+        let media_type = ImageMediaType::sniff(bytes).ok_or(AttachmentError::UnsupportedImage)?;
+        if bytes.len() > MAX_IMAGE_SIZE {
+            return Err(AttachmentError::ImageTooLarge { size: bytes.len() });
+        }
+        Ok(Self {
+            media_type,
+            data: BASE64.encode(bytes),
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), AttachmentError> {
+        let bytes = BASE64
+            .decode(&self.data)
+            .map_err(|_| AttachmentError::InvalidBase64)?;
+        if bytes.len() > MAX_IMAGE_SIZE {
+            return Err(AttachmentError::ImageTooLarge { size: bytes.len() });
+        }
+        match ImageMediaType::sniff(&bytes) {
+            Some(media_type) if media_type == self.media_type => Ok(()),
+            _ => Err(AttachmentError::MediaTypeMismatch),
+        }
+    }
+}
+
+/// A file attached to a message by reference, such as one uploaded beforehand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAttachment {
+    pub uri: String,
+    /// The MIME type of the file, such as `application/pdf`.
+    pub media_type: String,
+    /// The size of the file in bytes.
+    pub size: u64,
+}
+
+impl FileAttachment {
+    pub fn validate(&self) -> Result<(), AttachmentError> {
+        if self.uri.is_empty() {
+            return Err(AttachmentError::MissingUri);
+        }
+        if !is_media_type(&self.media_type) {
+            return Err(AttachmentError::InvalidMediaType(self.media_type.clone()));
+        }
+        if self.size > MAX_FILE_SIZE {
+            return Err(AttachmentError::FileTooLarge { size: self.size });
+        }
+        Ok(())
+    }
+}
+
+/// Whether `media_type` looks like `type/subtype`.
+fn is_media_type(media_type: &str) -> bool {
+    media_type.split_once('/').is_some_and(|(kind, subtype)| {
+        !kind.is_empty()
+            && !subtype.is_empty()
+            && !media_type.contains(char::is_whitespace)
+            && !subtype.contains('/')
+    })
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum AttachmentError {
+    #[error("image is not a PNG, JPEG, GIF or WebP image")]
+    UnsupportedImage,
+    #[error("image data is not valid base64")]
+    InvalidBase64,
+    #[error("image data doesn't match its media type")]
+    MediaTypeMismatch,
+    #[error("image is {size} bytes, more than the maximum of {MAX_IMAGE_SIZE}")]
+    ImageTooLarge { size: usize },
+    #[error("file attachment has no URI")]
+    MissingUri,
+    #[error("invalid media type {0:?}")]
+    InvalidMediaType(String),
+    #[error("file is {size} bytes, more than the maximum of {MAX_FILE_SIZE}")]
+    FileTooLarge { size: u64 },
+    #[error(
+        "request has {count} attachments, more than the maximum of {MAX_ATTACHMENTS_PER_REQUEST}"
+    )]
+    TooManyAttachments { count: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_attachments() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let image = ImageAttachment::from_bytes(png).unwrap();
+        assert_eq!(image.media_type, ImageMediaType::Png);
+        assert_eq!(image.validate(), Ok(()));
+        assert_eq!(
+            ImageAttachment {
+                media_type: ImageMediaType::Jpeg,
+                ..image.clone()
+            }
+            .validate(),
+            Err(AttachmentError::MediaTypeMismatch)
+        );
+        assert_eq!(
+            ImageAttachment {
+                data: "not base64!".to_owned(),
+                ..image
+            }
+            .validate(),
+            Err(AttachmentError::InvalidBase64)
+        );
+        assert_eq!(
+            ImageAttachment::from_bytes(b"plain text"),
+            Err(AttachmentError::UnsupportedImage)
+        );
+
+        let file = FileAttachment {
+            uri: "file-123".to_owned(),
+            media_type: "application/pdf".to_owned(),
+            size: 1024,
+        };
+        assert_eq!(file.validate(), Ok(()));
+        assert_eq!(
+            FileAttachment {
+                media_type: "pdf".to_owned(),
+                ..file.clone()
+            }
+            .validate(),
+            Err(AttachmentError::InvalidMediaType("pdf".to_owned()))
+        );
+        assert_eq!(
+            FileAttachment {
+                size: MAX_FILE_SIZE + 1,
+                ..file
+            }
+            .validate(),
+            Err(AttachmentError::FileTooLarge {
+                size: MAX_FILE_SIZE + 1
+            })
+        );
+    }
+}
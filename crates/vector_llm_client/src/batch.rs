@@ -0,0 +1,292 @@
+//! Batches of completion requests, which the server completes asynchronously at a lower cost, for jobs that don't need
+//! the responses right away, such as summarizing every file in a directory.
+//!
+//! A batch is submitted with [`BatchRequestsBody`], polled until it [ended](BatchStatus::Ended), and its results are
+//! downloaded as JSON lines, decoded with [`BatchResultsDecoder`].
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::completion::{CompletionBody, CompletionResponse};
+use crate::error::CompletionError;
+use crate::provider::{HttpMethod, HttpRequest};
+
+pub const MAX_REQUESTS_PER_BATCH: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BatchId(pub String);
+
+/// A request of a batch, with an id chosen by the client to match it with its result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub body: CompletionBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchRequestsBody {
+    pub requests: Vec<BatchRequest>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum BatchError {
+    #[error("a batch needs at least one request")]
+    Empty,
+    #[error("a batch can have at most {MAX_REQUESTS_PER_BATCH} requests, got {0}")]
+    TooManyRequests(usize),
+    #[error("several requests with custom id {0}")]
+    DuplicateCustomId(String),
+}
+
+impl BatchRequestsBody {
+    pub fn validate(&self) -> Result<(), BatchError> {
+        if self.requests.is_empty() {
+            return Err(BatchError::Empty);
+        }
+        if self.requests.len() > MAX_REQUESTS_PER_BATCH {
+            return Err(BatchError::TooManyRequests(self.requests.len()));
+        }
+        let mut custom_ids = HashSet::new();
+        for request in &self.requests {
+            if !custom_ids.insert(request.custom_id.as_str()) {
+                return Err(BatchError::DuplicateCustomId(request.custom_id.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The request submitting the batch to the server at `base_url`, which responds with the [`Batch`].
+    pub fn submit_request(&self, base_url: &str) -> Result<HttpRequest, BatchError> {
+        self.validate()?;
+        Ok(HttpRequest {
+            method: HttpMethod::Post,
+            url: format!("{}/batches", base_url.trim_end_matches('/')),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: Some(serde_json::to_vec(self).expect("batch bodies serialize")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    InProgress,
+    Canceling,
+    /// All the requests of the batch succeeded, failed, were cancelled, or expired, and the results can be downloaded.
+    Ended,
+}
+
+/// How many requests of a batch are in each state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Batch {
+    pub id: BatchId,
+    pub status: BatchStatus,
+    pub request_counts: BatchRequestCounts,
+}
+
+impl BatchId {
+    fn url(&self, base_url: &str) -> String {
+        format!("{}/batches/{}", base_url.trim_end_matches('/'), self.0)
+    }
+
+    /// The request for the [`Batch`] with this id.
+    pub fn status_request(&self, base_url: &str) -> HttpRequest {
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: self.url(base_url),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// The request for the results of the batch, as JSON lines of [`BatchResult`]s.
+    pub fn results_request(&self, base_url: &str) -> HttpRequest {
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: format!("{}/results", self.url(base_url)),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// The request cancelling the requests of the batch that didn't start yet.
+    pub fn cancel_request(&self, base_url: &str) -> HttpRequest {
+        HttpRequest {
+            method: HttpMethod::Post,
+            url: format!("{}/cancel", self.url(base_url)),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub custom_id: String,
+    pub outcome: BatchOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum BatchOutcome {
+    Succeeded {
+        response: CompletionResponse,
+    },
+    Errored {
+        code: String,
+        message: String,
+    },
+    Canceled,
+    /// The batch ended before the request started.
+    Expired,
+}
+
+/// Decodes the results of a batch from the chunks of the response, which has one JSON result per line.
+#[derive(Debug, Default)]
+pub struct BatchResultsDecoder {
+    buffer: Vec<u8>,
+}
+
+impl BatchResultsDecoder {
+    /// Decodes the results of the complete lines received so far.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Result<BatchResult, serde_json::Error>> {
+        self.buffer.extend_from_slice(chunk);
+        let Some(end) = self.buffer.iter().rposition(|byte| *byte == b'\n') else {
+            return Vec::new();
+        };
+        let lines = self.buffer.drain(..=end).collect::<Vec<_>>();
+        Self::decode_lines(&lines)
+    }
+
+    /// Decodes the last line, if it didn't end with a newline.
+    pub fn finish(self) -> Vec<Result<BatchResult, serde_json::Error>> {
+        Self::decode_lines(&self.buffer)
+    }
+
+    fn decode_lines(lines: &[u8]) -> Vec<Result<BatchResult, serde_json::Error>> {
+        lines
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .map(serde_json::from_slice)
+            .collect()
+    }
+}
+
+/// Polls a batch with `poll` every `interval`, waiting with `sleep`, until it ends.
+pub async fn poll_batch<PollFn, PollFuture, SleepFn, SleepFuture>(
+    interval: Duration,
+    mut poll: PollFn,
+    mut sleep: SleepFn,
+) -> Result<Batch, CompletionError>
+where
+    PollFn: FnMut() -> PollFuture,
+    PollFuture: Future<Output = Result<Batch, CompletionError>>,
+    SleepFn: FnMut(Duration) -> SleepFuture,
+    SleepFuture: Future<Output = ()>,
+{
+    loop {
+        let batch = poll().await?;
+        if batch.status == BatchStatus::Ended {
+            return Ok(batch);
+        }
+        sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::completion::{Message, MessageContent};
+
+    #[test]
+    fn test_batch_round_trip() {
+        let request = |custom_id: &str| BatchRequest {
+            custom_id: custom_id.to_string(),
+            body: CompletionBody::new("model", vec![Message::user("Summarize src/main.rs")]),
+        };
+        let mut body = BatchRequestsBody {
+            requests: vec![request("src/main.rs"), request("src/main.rs")],
+        };
+        assert_eq!(
+            body.validate(),
+            Err(BatchError::DuplicateCustomId("src/main.rs".to_string()))
+        );
+        body.requests[1].custom_id = "src/lib.rs".to_string();
+        let submit = body.submit_request("https://llm.example.com/").unwrap();
+        assert_eq!(submit.url, "https://llm.example.com/batches");
+        assert_eq!(
+            BatchId("batch_1".to_string())
+                .results_request("https://llm.example.com")
+                .url,
+            "https://llm.example.com/batches/batch_1/results"
+        );
+
+        let polls = RefCell::new(0);
+        let batch = block_on(poll_batch(
+            Duration::from_secs(10),
+            || {
+                *polls.borrow_mut() += 1;
+                let status = if *polls.borrow() < 3 {
+                    BatchStatus::InProgress
+                } else {
+                    BatchStatus::Ended
+                };
+                async move {
+                    Ok(Batch {
+                        id: BatchId("batch_1".to_string()),
+                        status,
+                        request_counts: BatchRequestCounts::default(),
+                    })
+                }
+            },
+            |_| async {},
+        ))
+        .unwrap();
+        assert_eq!(batch.status, BatchStatus::Ended);
+        assert_eq!(polls.into_inner(), 3);
+
+        let mut decoder = BatchResultsDecoder::default();
+        let mut results = decoder.push(
+            concat!(
+                "{\"custom_id\":\"src/main.rs\",\"outcome\":{\"type\":\"succeeded\",\"response\":",
+                "{\"content\":[{\"type\":\"text\",\"text\":\"The entry point.\"}],",
+            )
+            .as_bytes(),
+        );
+        assert!(results.is_empty());
+        results.extend(decoder.push(concat!(
+            "\"stop_reason\":\"end_turn\",\"usage\":{\"input_tokens\":40,\"output_tokens\":4}}}}\n",
+            "{\"custom_id\":\"src/lib.rs\",\"outcome\":{\"type\":\"expired\"}}",
+        ).as_bytes()));
+        results.extend(decoder.finish());
+        let results = results.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        let BatchOutcome::Succeeded { response } = &results[0].outcome else {
+            panic!("unexpected outcome {:?}", results[0].outcome);
+        };
+        assert_eq!(
+            response.content,
+            vec![MessageContent::Text {
+                text: "The entry point.".to_string()
+            }]
+        );
+        assert_eq!(results[1].outcome, BatchOutcome::Expired);
+    }
+}
@@ -0,0 +1,154 @@
+//! The catalog of the models the server serves, which model pickers list instead of hard-coding models.
+//!
+//! The catalog rarely changes, so [`ModelCatalogCache`] keeps the last one and only downloads it again when its ETag
+//! changed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{CompletionError, CompletionErrorKind};
+use crate::provider::{HttpMethod, HttpRequest, ModelCapabilities};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCatalog {
+    pub models: Vec<CatalogModel>,
+    /// The model requests use when they don't name one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+}
+
+impl ModelCatalog {
+    pub fn model(&self, id: &str) -> Option<&CatalogModel> {
+        self.models.iter().find(|model| model.id == id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CatalogModel {
+    pub id: String,
+    pub display_name: String,
+    pub pricing_tier: PricingTier,
+    /// The kinds of content the model accepts in prompts.
+    #[serde(default)]
+    pub input_modalities: Vec<Modality>,
+    #[serde(flatten)]
+    pub capabilities: ModelCapabilities,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingTier {
+    Free,
+    Standard,
+    Premium,
+    /// A tier this version of the client doesn't know about.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Image,
+    File,
+    Audio,
+    /// A modality this version of the client doesn't know about.
+    #[serde(other)]
+    Other,
+}
+
+/// The last catalog downloaded from the server, with its ETag.
+#[derive(Debug, Clone, Default)]
+pub struct ModelCatalogCache {
+    catalog: Option<ModelCatalog>,
+    etag: Option<String>,
+}
+
+impl ModelCatalogCache {
+    pub fn catalog(&self) -> Option<&ModelCatalog> {
+        self.catalog.as_ref()
+    }
+
+    /// The request for the catalog of the server at `base_url`, which the server answers with `304 Not Modified` when
+    /// the cached catalog is still current.
+    pub fn request(&self, base_url: &str) -> HttpRequest {
+        let mut headers = Vec::new();
+        if let (Some(_), Some(etag)) = (&self.catalog, &self.etag) {
+            headers.push(("If-None-Match".to_string(), etag.clone()));
+        }
+        HttpRequest {
+            method: HttpMethod::Get,
+            url: format!("{}/models", base_url.trim_end_matches('/')),
+            headers,
+            body: None,
+        }
+    }
+
+    /// Updates the cache from the response to [`Self::request`], with the value of its `ETag` header, and returns the
+    /// current catalog.
+    pub fn handle_response(
+        &mut self,
+        status_code: u16,
+        etag: Option<&str>,
+        body: &[u8],
+    ) -> Result<&ModelCatalog, CompletionError> {
+        let error_response =
+            || CompletionError::from_response(status_code, None, &String::from_utf8_lossy(body));
+        match status_code {
+            304 => self.catalog.as_ref().ok_or_else(error_response),
+            200..=299 => {
+                let catalog = serde_json::from_slice(body).map_err(|error| {
+                    CompletionError::new(
+                        CompletionErrorKind::Other,
+                        format!("invalid model catalog: {error}"),
+                    )
+                })?;
+                self.etag = etag.map(ToOwned::to_owned);
+                Ok(self.catalog.insert(catalog))
+            }
+            _ => Err(error_response()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_catalog_cache() {
+        let mut cache = ModelCatalogCache::default();
+        let request = cache.request("https://llm.example.com/");
+        assert_eq!(request.url, "https://llm.example.com/models");
+        assert!(request.headers.is_empty());
+
+        let body = br#"{
+            "models": [{
+                "id": "claude-sonnet-4",
+                "display_name": "Claude Sonnet 4",
+                "pricing_tier": "standard",
+                "input_modalities": ["text", "image", "video"],
+                "max_context_tokens": 200000,
+                "supports_tools": true,
+                "supports_images": true
+            }],
+            "default_model": "claude-sonnet-4"
+        }"#;
+        let catalog = cache.handle_response(200, Some("\"v1\""), body).unwrap();
+        let model = catalog.model("claude-sonnet-4").unwrap();
+        assert_eq!(
+            model.input_modalities,
+            [Modality::Text, Modality::Image, Modality::Other]
+        );
+        assert_eq!(model.capabilities.max_context_tokens, Some(200_000));
+
+        assert_eq!(
+            cache.request("https://llm.example.com").headers,
+            [("If-None-Match".to_string(), "\"v1\"".to_string())]
+        );
+        let catalog = cache.handle_response(304, None, b"").unwrap();
+        assert_eq!(catalog.models.len(), 1);
+        assert!(cache.handle_response(500, None, b"unavailable").is_err());
+        assert!(cache.catalog().is_some());
+    }
+}
@@ -0,0 +1,447 @@
+//! The bodies of completion requests and responses, including the tools the model can call.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attachment::{
+    AttachmentError, FileAttachment, ImageAttachment, MAX_ATTACHMENTS_PER_REQUEST,
+};
+use crate::completion_stream::{CompletionEvent, StopReason, ToolCallDelta, UsageDelta};
+use crate::provider::ProviderId;
+use crate::structured_output::ResponseFormat;
+use crate::{CompletionIntent, CompletionMode};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompletionBody {
+    pub model: String,
+    pub mode: CompletionMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<CompletionIntent>,
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Whether the model may call several tools in one response. The server decides when this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// The format the model has to respond in. The model responds with text when this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// The provider to send the request to, instead of the default one. Not sent to the provider.
+    #[serde(skip)]
+    pub provider: Option<ProviderId>,
+}
+
+impl CompletionBody {
+    pub fn new(model: impl Into<String>, messages: Vec<Message>) -> Self {
+        Self {
+            model: model.into(),
+            mode: CompletionMode::Normal,
+            intent: None,
+            messages,
+            tools: Vec::new(),
+            tool_choice: None,
+            parallel_tool_calls: None,
+            max_tokens: None,
+            temperature: None,
+            response_format: None,
+            provider: None,
+        }
+    }
+
+    /// Checks the images and files attached to the messages against the limits of the server.
+    pub fn validate_attachments(&self) -> Result<(), AttachmentError> {
+        let mut count = 0;
+        for content in self.messages.iter().flat_map(|message| &message.content) {
+            match content {
+                MessageContent::Image(image) => image.validate()?,
+                MessageContent::File(file) => file.validate()?,
+                _ => continue,
+            }
+            count += 1;
+        }
+        if count > MAX_ATTACHMENTS_PER_REQUEST {
+            return Err(AttachmentError::TooManyAttachments { count });
+        }
+        Ok(())
+    }
+
+    /// Continues the conversation after the model called tools: appends the model's response with the calls, and the
+    /// results of the calls, which the next request sends with [`CompletionIntent::ToolResults`].
+    pub fn push_tool_results(
+        &mut self,
+        response: Vec<MessageContent>,
+        results: Vec<ToolResult>,
+    ) -> Result<(), ToolResultError> {
+        let call_ids = response
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::ToolCall(call) => Some(call.id.as_str()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        let mut result_ids = HashSet::default();
+        for result in &results {
+            if !call_ids.contains(result.tool_call_id.as_str()) {
+                return Err(ToolResultError::UnknownToolCall(
+                    result.tool_call_id.clone(),
+                ));
+            }
+            if !result_ids.insert(result.tool_call_id.as_str()) {
+                return Err(ToolResultError::DuplicateResult(
+                    result.tool_call_id.clone(),
+                ));
+            }
+        }
+        if let Some(missing) = call_ids.difference(&result_ids).next() {
+            return Err(ToolResultError::MissingResult(missing.to_string()));
+        }
+
+        self.messages.push(Message {
+            role: Role::Assistant,
+            content: response,
+            cache_control: None,
+        });
+        self.messages.push(Message {
+            role: Role::User,
+            content: results
+                .into_iter()
+                .map(MessageContent::ToolResult)
+                .collect(),
+            cache_control: None,
+        });
+        self.intent = Some(CompletionIntent::ToolResults);
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ToolResultError {
+    #[error("tool result for unknown tool call {0}")]
+    UnknownToolCall(String),
+    #[error("several results for tool call {0}")]
+    DuplicateResult(String),
+    #[error("no result for tool call {0}")]
+    MissingResult(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: Vec<MessageContent>,
+    /// Makes the message a cache breakpoint: the server caches the prompt up to and including the message, and the
+    /// requests that start with the same prompt read it from the cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+impl Message {
+    pub fn user(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: vec![MessageContent::Text { text: text.into() }],
+            cache_control: None,
+        }
+    }
+
+    pub fn system(text: impl Into<String>) -> Self {
+        Self {
+            role: Role::System,
+            content: vec![MessageContent::Text { text: text.into() }],
+            cache_control: None,
+        }
+    }
+
+    /// Makes the message a cache breakpoint, such as the last message of a long system prompt.
+    pub fn with_cache_breakpoint(mut self, ttl: Option<CacheTtl>) -> Self {
+        self.cache_control = Some(CacheControl::Ephemeral { ttl });
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum CacheControl {
+    /// The prompt stays cached for `ttl` after it was last read, or for the default TTL of the server when `None`.
+    Ephemeral {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl: Option<CacheTtl>,
+    },
+}
+
+/// How long a cached prompt stays cached. Longer TTLs cost more to write to the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum MessageContent {
+    Text { text: String },
+    Image(ImageAttachment),
+    File(FileAttachment),
+    ToolCall(ToolCall),
+    ToolResult(ToolResult),
+}
+
+/// A tool the model can call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    /// The JSON schema of the tool's input.
+    pub input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools.
+    Auto,
+    /// The model has to call one of the tools.
+    Any,
+    /// The model has to call the named tool.
+    Tool { name: String },
+    /// The model can't call tools.
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    pub content: String,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompletionResponse {
+    pub content: Vec<MessageContent>,
+    pub stop_reason: Option<StopReason>,
+    pub usage: UsageDelta,
+}
+
+impl CompletionResponse {
+    /// The text of the response, without the tool calls.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn tool_calls(&self) -> impl Iterator<Item = &ToolCall> {
+        self.content.iter().filter_map(|content| match content {
+            MessageContent::ToolCall(call) => Some(call),
+            _ => None,
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ToolCallAssemblyError {
+    #[error("tool call {index} has no {field}")]
+    MissingField { index: usize, field: &'static str },
+    #[error("invalid arguments for tool call {id}: {error}")]
+    InvalidArguments {
+        id: String,
+        #[source]
+        error: serde_json::Error,
+    },
+}
+
+/// Builds the response of a streamed completion from its events, assembling the tool calls from their deltas, which
+/// are interleaved when the model calls several tools at once.
+#[derive(Debug, Default)]
+pub struct CompletionResponseBuilder {
+    text: String,
+    tool_calls: BTreeMap<usize, ToolCallDelta>,
+    stop_reason: Option<StopReason>,
+    usage: UsageDelta,
+}
+
+impl CompletionResponseBuilder {
+    pub fn push_event(&mut self, event: &CompletionEvent) {
+        match event {
+            CompletionEvent::Status(_) => {}
+            CompletionEvent::ContentDelta(delta) => self.text.push_str(&delta.text),
+            CompletionEvent::ToolCallDelta(delta) => {
+                let call = self
+                    .tool_calls
+                    .entry(delta.index)
+                    .or_insert_with(|| ToolCallDelta {
+                        index: delta.index,
+                        id: None,
+                        name: None,
+                        arguments: String::new(),
+                    });
+                if delta.id.is_some() {
+                    call.id.clone_from(&delta.id);
+                }
+                if delta.name.is_some() {
+                    call.name.clone_from(&delta.name);
+                }
+                call.arguments.push_str(&delta.arguments);
+            }
+            CompletionEvent::UsageDelta(delta) => self.usage += *delta,
+            CompletionEvent::Stop { reason } => self.stop_reason = Some(*reason),
+            CompletionEvent::Cancelled { .. } => {}
+        }
+    }
+
+    pub fn build(self) -> Result<CompletionResponse, ToolCallAssemblyError> {
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(MessageContent::Text { text: self.text });
+        }
+        for (index, call) in self.tool_calls {
+            let id = call
+                .id
+                .ok_or(ToolCallAssemblyError::MissingField { index, field: "id" })?;
+            let name = call.name.ok_or(ToolCallAssemblyError::MissingField {
+                index,
+                field: "name",
+            })?;
+            let input = if call.arguments.trim().is_empty() {
+                serde_json::Value::Object(Default::default())
+            } else {
+                match serde_json::from_str(&call.arguments) {
+                    Ok(input) => input,
+                    Err(error) => {
+                        return Err(ToolCallAssemblyError::InvalidArguments { id, error });
+                    }
+                }
+            };
+            content.push(MessageContent::ToolCall(ToolCall { id, name, input }));
+        }
+        Ok(CompletionResponse {
+            content,
+            stop_reason: self.stop_reason,
+            usage: self.usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::completion_stream::ContentDelta;
+
+    #[test]
+    fn test_parallel_tool_calls_round_trip() {
+        let tool_call_delta = |index, id: Option<&str>, name: Option<&str>, arguments: &str| {
+            CompletionEvent::ToolCallDelta(ToolCallDelta {
+                index,
+                id: id.map(ToOwned::to_owned),
+                name: name.map(ToOwned::to_owned),
+                arguments: arguments.to_owned(),
+            })
+        };
+        let mut builder = CompletionResponseBuilder::default();
+        for event in [
+            CompletionEvent::ContentDelta(ContentDelta {
+                text: "Searching.".to_owned(),
+            }),
+            tool_call_delta(0, Some("call_a"), Some("grep"), "{\"query\":"),
+            tool_call_delta(1, Some("call_b"), Some("list_directory"), ""),
+            tool_call_delta(0, None, None, "\"fn main\"}"),
+            CompletionEvent::Stop {
+                reason: StopReason::ToolUse,
+            },
+        ] {
+            builder.push_event(&event);
+        }
+        let response = builder.build().unwrap();
+        assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(
+            response.tool_calls().cloned().collect::<Vec<_>>(),
+            vec![
+                ToolCall {
+                    id: "call_a".to_owned(),
+                    name: "grep".to_owned(),
+                    input: json!({"query": "fn main"}),
+                },
+                ToolCall {
+                    id: "call_b".to_owned(),
+                    name: "list_directory".to_owned(),
+                    input: json!({}),
+                },
+            ]
+        );
+
+        let mut body = CompletionBody::new("model", vec![Message::user("Find main")]);
+        let result = |id: &str| ToolResult {
+            tool_call_id: id.to_owned(),
+            content: "src/main.rs".to_owned(),
+            is_error: false,
+        };
+        assert_eq!(
+            body.push_tool_results(response.content.clone(), vec![result("call_a")]),
+            Err(ToolResultError::MissingResult("call_b".to_owned()))
+        );
+        assert_eq!(
+            body.push_tool_results(response.content.clone(), vec![result("call_c")]),
+            Err(ToolResultError::UnknownToolCall("call_c".to_owned()))
+        );
+        body.push_tool_results(response.content, vec![result("call_b"), result("call_a")])
+            .unwrap();
+        assert_eq!(body.intent, Some(CompletionIntent::ToolResults));
+        assert_eq!(
+            serde_json::to_value(&body.messages[2]).unwrap(),
+            json!({
+                "role": "user",
+                "content": [
+                    {
+                        "type": "tool_result",
+                        "tool_call_id": "call_b",
+                        "content": "src/main.rs",
+                        "is_error": false,
+                    },
+                    {
+                        "type": "tool_result",
+                        "tool_call_id": "call_a",
+                        "content": "src/main.rs",
+                        "is_error": false,
+                    },
+                ]
+            })
+        );
+
+        let system = Message::system("You are a coding agent.")
+            .with_cache_breakpoint(Some(CacheTtl::OneHour));
+        assert_eq!(
+            serde_json::to_value(&system).unwrap()["cache_control"],
+            json!({"type": "ephemeral", "ttl": "1h"})
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! The events of streamed completions, and a decoder for the server-sent events they arrive as.
+
+use std::collections::VecDeque;
+use std::ops::AddAssign;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+
+use futures::task::AtomicWaker;
+use futures::{Stream, StreamExt as _, stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CompletionRequestStatus, MODEL_REQUESTS_USAGE_AMOUNT_HEADER_NAME,
+    MODEL_REQUESTS_USAGE_LIMIT_HEADER_NAME, UsageLimit, UsageLimitParseError,
+};
+
+/// The `data` of the server-sent event that marks the end of a completion stream.
+const DONE_MARKER: &str = "[DONE]";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum CompletionEvent {
+    Status(CompletionRequestStatus),
+    ContentDelta(ContentDelta),
+    ToolCallDelta(ToolCallDelta),
+    UsageDelta(UsageDelta),
+    Stop {
+        reason: StopReason,
+    },
+    /// The last event of a stream that the client cancelled with a [`CancellationHandle`], with the usage the server
+    /// reported until then.
+    Cancelled {
+        usage: UsageDelta,
+    },
+}
+
+/// A piece of the text of the completion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentDelta {
+    pub text: String,
+}
+
+/// A piece of a tool call. The first delta of a call has its `id` and `name`, and the `arguments` of all of its
+/// deltas concatenated are the JSON arguments of the call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Which of the tool calls of the completion this delta belongs to.
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+/// Tokens used since the previous usage delta of the completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageDelta {
+    /// The input tokens that were neither read from nor written to the prompt cache.
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    /// The input tokens written to the prompt cache, which are billed at a higher rate than other input tokens.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_creation_input_tokens: u64,
+    /// The input tokens read from the prompt cache, which are billed at a lower rate than other input tokens.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_read_input_tokens: u64,
+}
+
+impl UsageDelta {
+    /// All the input tokens, cached or not.
+    pub fn total_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+}
+
+impl AddAssign for UsageDelta {
+    fn add_assign(&mut self, delta: Self) {
+        self.input_tokens += delta.input_tokens;
+        self.output_tokens += delta.output_tokens;
+        self.cache_creation_input_tokens += delta.cache_creation_input_tokens;
+        self.cache_read_input_tokens += delta.cache_read_input_tokens;
+    }
+}
+
+fn is_zero(tokens: &u64) -> bool {
+    *tokens == 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    EndTurn,
+    MaxTokens,
+    StopSequence,
+    ToolUse,
+    Refusal,
+}
+
+/// The model requests used so far, as reported by the usage headers of a completion response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelRequestUsage {
+    pub amount: u32,
+    pub limit: UsageLimit,
+}
+
+impl ModelRequestUsage {
+    /// Reads the usage from the headers of a response, looked up by name with `header`. Returns `None` when the
+    /// response has no usage headers.
+    pub fn from_headers<'a>(
+        header: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Option<Self>, UsageLimitParseError> {
+        let (Some(limit), Some(amount)) = (
+            header(MODEL_REQUESTS_USAGE_LIMIT_HEADER_NAME),
+            header(MODEL_REQUESTS_USAGE_AMOUNT_HEADER_NAME),
+        ) else {
+            return Ok(None);
+        };
+        let amount = amount
+            .trim()
+            .parse()
+            .map_err(|_| UsageLimitParseError::Invalid(amount.to_string()))?;
+        Ok(Some(Self {
+            amount,
+            limit: limit.parse()?,
+        }))
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CompletionEventDecodeError {
+    #[error("completion stream is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("invalid completion event {data:?}: {error}")]
+    InvalidEvent {
+        data: String,
+        #[source]
+        error: serde_json::Error,
+    },
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CompletionStreamError<E> {
+    #[error("failed to read the completion stream: {0}")]
+    Transport(E),
+    #[error(transparent)]
+    Decode(#[from] CompletionEventDecodeError),
+}
+
+/// Parses the `data` of a server-sent event into the completion events it holds.
+pub type CompletionEventParser = fn(&str) -> Result<Vec<CompletionEvent>, serde_json::Error>;
+
+/// Decodes the completion events of a stream of server-sent events, which may be split across chunks anywhere.
+#[derive(Debug)]
+pub struct CompletionEventDecoder {
+    buffer: Vec<u8>,
+    data: Option<String>,
+    done: bool,
+    parse: CompletionEventParser,
+}
+
+impl Default for CompletionEventDecoder {
+    /// A decoder for the events of Vector's completion endpoint, one [`CompletionEvent`] per server-sent event.
+    fn default() -> Self {
+        Self::new(|data| Ok(vec![serde_json::from_str(data)?]))
+    }
+}
+
+impl CompletionEventDecoder {
+    /// A decoder for server-sent events in another format, such as the one of another provider.
+    pub fn new(parse: CompletionEventParser) -> Self {
+        Self {
+            buffer: Vec::new(),
+            data: None,
+            done: false,
+            parse,
+        }
+    }
+
+    /// Decodes the events completed by the next chunk of the stream.
+    pub fn push(
+        &mut self,
+        chunk: &[u8],
+    ) -> Vec<Result<CompletionEvent, CompletionEventDecodeError>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line = self.buffer.drain(..=newline).collect::<Vec<_>>();
+            let line = line.strip_suffix(b"\n").unwrap_or(&line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            match std::str::from_utf8(line) {
+                Ok(line) => self.process_line(line, &mut events),
+                Err(_) => events.push(Err(CompletionEventDecodeError::InvalidUtf8)),
+            }
+        }
+        events
+    }
+
+    /// Decodes the last event of the stream, if it did not end with a blank line.
+    pub fn finish(&mut self) -> Vec<Result<CompletionEvent, CompletionEventDecodeError>> {
+        let mut events = Vec::new();
+        if !self.buffer.is_empty() {
+            match String::from_utf8(std::mem::take(&mut self.buffer)) {
+                Ok(line) => self.process_line(line.trim_end_matches('\r'), &mut events),
+                Err(_) => events.push(Err(CompletionEventDecodeError::InvalidUtf8)),
+            }
+        }
+        self.dispatch(&mut events);
+        events
+    }
+
+    /// Whether the server marked the end of the stream.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    fn process_line(
+        &mut self,
+        line: &str,
+        events: &mut Vec<Result<CompletionEvent, CompletionEventDecodeError>>,
+    ) {
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+        // Other fields, such as `event` and `id`, and comments, which start with `:`, carry nothing that is not in
+        // the data of the events.
+        let Some(data) = line.strip_prefix("data:") else {
+            return;
+        };
+        let data = data.strip_prefix(' ').unwrap_or(data);
+        match &mut self.data {
+            Some(pending) => {
+                pending.push('\n');
+                pending.push_str(data);
+            }
+            None => self.data = Some(data.to_string()),
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<Result<CompletionEvent, CompletionEventDecodeError>>) {
+        let Some(data) = self.data.take() else {
+            return;
+        };
+        if data == DONE_MARKER {
+            self.done = true;
+            return;
+        }
+        if self.done {
+            return;
+        }
+        match (self.parse)(&data) {
+            Ok(parsed) => events.extend(parsed.into_iter().map(Ok)),
+            Err(error) => events.push(Err(CompletionEventDecodeError::InvalidEvent {
+                data,
+                error,
+            })),
+        }
+    }
+}
+
+/// Decodes the completion events of a stream of server-sent event chunks, such as the body of a completion response.
+pub fn decode_completion_events<S, B, E>(
+    chunks: S,
+) -> impl Stream<Item = Result<CompletionEvent, CompletionStreamError<E>>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    decode_completion_events_with(chunks, CompletionEventDecoder::default())
+}
+
+/// Decodes the completion events of a stream of server-sent event chunks with the given decoder.
+pub fn decode_completion_events_with<S, B, E>(
+    chunks: S,
+    decoder: CompletionEventDecoder,
+) -> impl Stream<Item = Result<CompletionEvent, CompletionStreamError<E>>>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+{
+    let state = (Some(chunks), decoder, VecDeque::new());
+    stream::unfold(state, |(mut chunks, mut decoder, mut pending)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, (chunks, decoder, pending)));
+            }
+            let stream = chunks.as_mut()?;
+            match stream.next().await {
+                Some(Ok(chunk)) => pending.extend(
+                    decoder
+                        .push(chunk.as_ref())
+                        .into_iter()
+                        .map(|event| event.map_err(CompletionStreamError::from)),
+                ),
+                Some(Err(error)) => {
+                    chunks = None;
+                    pending.push_back(Err(CompletionStreamError::Transport(error)));
+                }
+                None => {
+                    chunks = None;
+                    pending.extend(
+                        decoder
+                            .finish()
+                            .into_iter()
+                            .map(|event| event.map_err(CompletionStreamError::from)),
+                    );
+                }
+            }
+        }
+    })
+}
+
+/// Cancels the completion stream it was created with.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationHandle {
+    state: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl CancellationHandle {
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.waker.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// A completion stream that can be cancelled. Once cancelled, it drops the stream it wraps, closing the connection the
+/// events arrive on so that the server stops generating them, and ends with a [`CompletionEvent::Cancelled`] event.
+pub struct CancellableCompletionStream<S> {
+    events: Option<Pin<Box<S>>>,
+    usage: UsageDelta,
+    state: Arc<CancellationState>,
+}
+
+impl<S> CancellableCompletionStream<S> {
+    pub fn new(events: S) -> (Self, CancellationHandle) {
+        let handle = CancellationHandle::default();
+        let stream = Self {
+            events: Some(Box::pin(events)),
+            usage: UsageDelta::default(),
+            state: handle.state.clone(),
+        };
+        (stream, handle)
+    }
+}
+
+impl<S, E> Stream for CancellableCompletionStream<S>
+where
+    S: Stream<Item = Result<CompletionEvent, E>>,
+{
+    type Item = Result<CompletionEvent, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let Some(events) = this.events.as_mut() else {
+            return Poll::Ready(None);
+        };
+        this.state.waker.register(cx.waker());
+        if this.state.cancelled.load(Ordering::SeqCst) {
+            this.events = None;
+            return Poll::Ready(Some(Ok(CompletionEvent::Cancelled { usage: this.usage })));
+        }
+        let event = std::task::ready!(events.as_mut().poll_next(cx));
+        match &event {
+            Some(Ok(CompletionEvent::UsageDelta(delta))) => this.usage += *delta,
+            None => this.events = None,
+            _ => {}
+        }
+        Poll::Ready(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_split_events() {
+        let chunks: [&[u8]; 4] = [
+            b": keep-alive\n\nevent: content_delta\ndata: {\"type\":\"content_delta\",",
+            b"\"text\":\"Hello\"}\r\n\r\ndata: {\"type\":\"tool_call_delta\",\"index\":0,\"id\":\"call_1\",\"name\":\"grep\"}\n",
+            b"\ndata: {\"type\":\"usage_delta\",\"output_tokens\":3,\"cache_read_input_tokens\":1200}\n\ndata: {\"type\":\"stop\",\"reason\":\"tool_use\"}\n\ndata: [DONE]\n\n",
+            b"data: {\"type\":\"stop\",\"reason\":\"end_turn\"}\n\n",
+        ];
+        let mut decoder = CompletionEventDecoder::default();
+        let events = chunks
+            .iter()
+            .flat_map(|chunk| decoder.push(chunk))
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert!(decoder.is_done());
+        assert_eq!(
+            events,
+            vec![
+                CompletionEvent::ContentDelta(ContentDelta {
+                    text: "Hello".to_string()
+                }),
+                CompletionEvent::ToolCallDelta(ToolCallDelta {
+                    index: 0,
+                    id: Some("call_1".to_string()),
+                    name: Some("grep".to_string()),
+                    arguments: String::new(),
+                }),
+                CompletionEvent::UsageDelta(UsageDelta {
+                    output_tokens: 3,
+                    cache_read_input_tokens: 1200,
+                    ..UsageDelta::default()
+                }),
+                CompletionEvent::Stop {
+                    reason: StopReason::ToolUse
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_completion_events() {
+        let chunks = stream::iter([
+            Ok::<_, String>("data: {\"type\":\"status\",\"status\":\"queued\",\"position\":2}\n\n"),
+            Ok("data: not json\n\n"),
+            Ok("data: {\"type\":\"content_delta\",\"text\":\"done\"}"),
+        ]);
+        let events =
+            futures::executor::block_on(decode_completion_events(chunks).collect::<Vec<_>>());
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0].as_ref().unwrap(),
+            &CompletionEvent::Status(CompletionRequestStatus::Queued { position: 2 })
+        );
+        assert!(matches!(
+            events[1],
+            Err(CompletionStreamError::Decode(
+                CompletionEventDecodeError::InvalidEvent { .. }
+            ))
+        ));
+        assert_eq!(
+            events[2].as_ref().unwrap(),
+            &CompletionEvent::ContentDelta(ContentDelta {
+                text: "done".to_string()
+            })
+        );
+
+        let usage = ModelRequestUsage::from_headers(|name| match name {
+            MODEL_REQUESTS_USAGE_LIMIT_HEADER_NAME => Some("500"),
+            MODEL_REQUESTS_USAGE_AMOUNT_HEADER_NAME => Some("42"),
+            _ => None,
+        });
+        assert_eq!(
+            usage.unwrap(),
+            Some(ModelRequestUsage {
+                amount: 42,
+                limit: UsageLimit::Limited { limit: 500 },
+            })
+        );
+        assert_eq!(ModelRequestUsage::from_headers(|_| None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cancel_completion_stream() {
+        let usage = UsageDelta {
+            input_tokens: 100,
+            output_tokens: 5,
+            ..UsageDelta::default()
+        };
+        let events = stream::iter([Ok::<_, ()>(CompletionEvent::UsageDelta(usage))])
+            .chain(stream::pending());
+        let (mut events, handle) = CancellableCompletionStream::new(events);
+        futures::executor::block_on(async {
+            assert_eq!(
+                events.next().await,
+                Some(Ok(CompletionEvent::UsageDelta(usage)))
+            );
+            handle.cancel();
+            assert_eq!(
+                events.next().await,
+                Some(Ok(CompletionEvent::Cancelled { usage }))
+            );
+            assert_eq!(events.next().await, None);
+        });
+        assert!(handle.is_cancelled());
+    }
+}
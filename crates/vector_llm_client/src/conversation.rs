@@ -0,0 +1,177 @@
+//! The format conversations are saved, exported, and imported in, which later releases of the client keep reading.
+//!
+//! Saved conversations record the version of the format. When the format changes, [`CONVERSATION_FORMAT_VERSION`] is
+//! bumped, and a migration from the previous version is appended to [`MIGRATIONS`], which converts the JSON of a
+//! conversation saved in the previous version to the new one.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::completion::Message;
+use crate::completion_stream::{StopReason, UsageDelta};
+
+pub const CONVERSATION_FORMAT_VERSION: u32 = 1;
+
+/// Converts the JSON of a conversation from a version of the format to the next one.
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// The migration at index `i` migrates conversations from version `i + 1` to version `i + 2`.
+pub const MIGRATIONS: &[Migration] = &[];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedConversation {
+    pub version: u32,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The model the conversation was last continued with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<SavedMessage>,
+}
+
+/// A message of a saved conversation, with the usage of the completion that responded with it, if any.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageDelta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+}
+
+impl From<Message> for SavedMessage {
+    fn from(message: Message) -> Self {
+        Self {
+            message,
+            usage: None,
+            stop_reason: None,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConversationFormatError {
+    #[error("invalid conversation: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("conversation has no format version")]
+    MissingVersion,
+    #[error(
+        "conversation was saved in format version {version}, which is newer than version {CONVERSATION_FORMAT_VERSION}"
+    )]
+    UnsupportedVersion { version: u32 },
+    #[error("failed to migrate conversation from format version {from}: {message}")]
+    Migration { from: u32, message: String },
+}
+
+impl SavedConversation {
+    pub fn new(id: impl Into<String>, now: DateTime<Utc>) -> Self {
+        Self {
+            version: CONVERSATION_FORMAT_VERSION,
+            id: id.into(),
+            title: None,
+            model: None,
+            created_at: now,
+            updated_at: now,
+            messages: Vec::new(),
+        }
+    }
+
+    /// The usage of all the completions of the conversation.
+    pub fn total_usage(&self) -> UsageDelta {
+        let mut total = UsageDelta::default();
+        for usage in self.messages.iter().filter_map(|message| message.usage) {
+            total += usage;
+        }
+        total
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).expect("conversations serialize")
+    }
+
+    /// Reads a conversation saved in any version of the format up to the current one, migrating it to the current
+    /// version.
+    pub fn from_json(json: &[u8]) -> Result<Self, ConversationFormatError> {
+        let mut value: Value = serde_json::from_slice(json)?;
+        let mut version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|version| *version > 0)
+            .ok_or(ConversationFormatError::MissingVersion)?;
+        if version > CONVERSATION_FORMAT_VERSION {
+            return Err(ConversationFormatError::UnsupportedVersion { version });
+        }
+        while version < CONVERSATION_FORMAT_VERSION {
+            let migrate = MIGRATIONS[version as usize - 1];
+            value = migrate(value).map_err(|message| ConversationFormatError::Migration {
+                from: version,
+                message,
+            })?;
+            version += 1;
+            value["version"] = version.into();
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::completion::{MessageContent, Role, ToolCall};
+
+    #[test]
+    fn test_conversation_round_trip() {
+        assert_eq!(MIGRATIONS.len() as u32, CONVERSATION_FORMAT_VERSION - 1);
+
+        let now = DateTime::parse_from_rfc3339("2025-06-02T09:30:00Z")
+            .unwrap()
+            .to_utc();
+        let mut conversation = SavedConversation::new("thread_1", now);
+        conversation
+            .messages
+            .push(Message::user("Where is main?").into());
+        conversation.messages.push(SavedMessage {
+            message: Message {
+                role: Role::Assistant,
+                content: vec![MessageContent::ToolCall(ToolCall {
+                    id: "call_1".to_string(),
+                    name: "grep".to_string(),
+                    input: json!({"query": "fn main"}),
+                })],
+                cache_control: None,
+            },
+            usage: Some(UsageDelta {
+                input_tokens: 30,
+                output_tokens: 12,
+                ..UsageDelta::default()
+            }),
+            stop_reason: Some(StopReason::ToolUse),
+        });
+        let json = conversation.to_json();
+        let value: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(value["version"], CONVERSATION_FORMAT_VERSION);
+        assert_eq!(value["messages"][1]["content"][0]["type"], "tool_call");
+        let loaded = SavedConversation::from_json(&json).unwrap();
+        assert_eq!(loaded, conversation);
+        assert_eq!(loaded.total_usage().output_tokens, 12);
+
+        let mut newer = value;
+        newer["version"] = json!(CONVERSATION_FORMAT_VERSION + 1);
+        assert!(matches!(
+            SavedConversation::from_json(newer.to_string().as_bytes()),
+            Err(ConversationFormatError::UnsupportedVersion { .. })
+        ));
+        assert!(matches!(
+            SavedConversation::from_json(b"{\"id\": \"thread_1\"}"),
+            Err(ConversationFormatError::MissingVersion)
+        ));
+    }
+}
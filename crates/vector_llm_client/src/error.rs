@@ -0,0 +1,107 @@
+//! The ways completion requests fail, as reported by HTTP status codes and by the `code` of failed request statuses.
+
+use std::time::Duration;
+
+use crate::CompletionRequestStatus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionErrorKind {
+    /// The model is overloaded, and the request can be retried later.
+    Overloaded,
+    /// The client sent too many requests, and can retry them later.
+    RateLimited,
+    /// The request doesn't fit in the context window of the model.
+    ContextTooLong,
+    /// The client is not signed in, or not allowed to use the model.
+    Authentication,
+    /// The server failed to handle the request.
+    Server,
+    /// The server rejected the request as invalid.
+    InvalidRequest,
+    Other,
+}
+
+impl CompletionErrorKind {
+    pub fn from_status_code(status_code: u16) -> Self {
+        match status_code {
+            401 | 403 => Self::Authentication,
+            413 => Self::ContextTooLong,
+            429 => Self::RateLimited,
+            503 | 529 => Self::Overloaded,
+            500..=599 => Self::Server,
+            400..=499 => Self::InvalidRequest,
+            _ => Self::Other,
+        }
+    }
+
+    /// Maps the `code` of a [`CompletionRequestStatus::Failed`] status to its kind.
+    pub fn from_failed_code(code: &str) -> Self {
+        match code {
+            "overloaded" | "overloaded_error" | "upstream_overloaded" => Self::Overloaded,
+            "rate_limited" | "rate_limit_error" | "rate_limit_exceeded" => Self::RateLimited,
+            "context_too_long" | "context_length_exceeded" | "prompt_too_long" => {
+                Self::ContextTooLong
+            }
+            "unauthorized"
+            | "forbidden"
+            | "authentication_error"
+            | "permission_error"
+            | "invalid_api_key" => Self::Authentication,
+            "internal_error" | "server_error" | "api_error" | "upstream_error" => Self::Server,
+            "invalid_request" | "invalid_request_error" | "bad_request" => Self::InvalidRequest,
+            _ => Self::Other,
+        }
+    }
+
+    /// Whether the same request can succeed when retried.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Overloaded | Self::RateLimited | Self::Server)
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct CompletionError {
+    pub kind: CompletionErrorKind,
+    pub message: String,
+    /// How long the server asked the client to wait before retrying, from its `retry-after` header.
+    pub retry_after: Option<Duration>,
+}
+
+impl CompletionError {
+    pub fn new(kind: CompletionErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// The error of a failed response, from its status code and `retry-after` header, in seconds.
+    pub fn from_response(status_code: u16, retry_after: Option<&str>, body: &str) -> Self {
+        Self {
+            kind: CompletionErrorKind::from_status_code(status_code),
+            message: format!("request failed with status {status_code}: {body}"),
+            retry_after: retry_after
+                .and_then(|seconds| seconds.trim().parse().ok())
+                .map(Duration::from_secs),
+        }
+    }
+}
+
+impl CompletionRequestStatus {
+    /// The error of a [`CompletionRequestStatus::Failed`] status.
+    pub fn error(&self) -> Option<CompletionError> {
+        match self {
+            CompletionRequestStatus::Failed {
+                code,
+                message,
+                request_id,
+            } => Some(CompletionError::new(
+                CompletionErrorKind::from_failed_code(code),
+                format!("{message} (request {request_id})"),
+            )),
+            _ => None,
+        }
+    }
+}
@@ -1,13 +1,36 @@
+pub mod attachment;
+pub mod batch;
+pub mod catalog;
+pub mod completion;
+pub mod completion_stream;
+pub mod conversation;
+pub mod error;
+pub mod openai_compatible;
+pub mod provider;
+pub mod rate_limit;
+pub mod retry;
+pub mod structured_output;
+pub mod telemetry;
+pub mod tokens;
+pub mod usage;
+
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::fmt;
 use std::str::FromStr;
 
 pub const MODEL_REQUESTS_USAGE_LIMIT_HEADER_NAME: &str = "x-vector-model-requests-usage-limit";
 pub const MODEL_REQUESTS_USAGE_AMOUNT_HEADER_NAME: &str = "x-vector-model-requests-usage-amount";
 
+/// Tells servers that predate [`CLIENT_SUPPORTED_WEB_SEARCH_PROVIDERS_HEADER_NAME`] that the client supports Exa.
 pub const CLIENT_SUPPORTS_EXA_WEB_SEARCH_PROVIDER_HEADER_NAME: &str =
     "x-vector-client-supports-exa-web-search-provider";
 
+/// The web search providers the client supports, as a comma-separated list of [`WebSearchProvider`]s, in order of
+/// preference. The server searches with the first one it supports.
+pub const CLIENT_SUPPORTED_WEB_SEARCH_PROVIDERS_HEADER_NAME: &str =
+    "x-vector-client-supported-web-search-providers";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CompletionMode {
@@ -32,14 +55,19 @@ pub enum CompletionIntent {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "status")]
 pub enum CompletionRequestStatus {
-    Queued { position: usize },
+    Queued {
+        position: usize,
+    },
     Started,
     Failed {
         code: String,
         message: String,
         request_id: String,
     },
-    UsageUpdated { amount: u32, limit: UsageLimit },
+    UsageUpdated {
+        amount: u32,
+        limit: UsageLimit,
+    },
     ToolUseLimitReached,
 }
 
@@ -82,19 +110,187 @@ impl FromStr for UsageLimit {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearchProvider {
+    Exa,
+    Brave,
+    Tavily,
+    /// A provider this version of the client doesn't know about.
+    #[serde(other)]
+    Other,
+}
+
+impl WebSearchProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebSearchProvider::Exa => "exa",
+            WebSearchProvider::Brave => "brave",
+            WebSearchProvider::Tavily => "tavily",
+            WebSearchProvider::Other => "other",
+        }
+    }
+
+    /// The value of the [`CLIENT_SUPPORTED_WEB_SEARCH_PROVIDERS_HEADER_NAME`] header.
+    pub fn header_value(providers: &[WebSearchProvider]) -> String {
+        providers
+            .iter()
+            .filter(|provider| **provider != WebSearchProvider::Other)
+            .map(|provider| provider.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Parses the value of the [`CLIENT_SUPPORTED_WEB_SEARCH_PROVIDERS_HEADER_NAME`] header, skipping the providers
+    /// it doesn't know about.
+    pub fn parse_header_value(value: &str) -> Vec<WebSearchProvider> {
+        value
+            .split(',')
+            .filter_map(|provider| provider.trim().parse().ok())
+            .collect()
+    }
+}
+
+impl fmt::Display for WebSearchProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebSearchProviderParseError {
+    #[error("unknown web search provider: {0}")]
+    Unknown(String),
+}
+
+impl FromStr for WebSearchProvider {
+    type Err = WebSearchProviderParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exa" => Ok(WebSearchProvider::Exa),
+            "brave" => Ok(WebSearchProvider::Brave),
+            "tavily" => Ok(WebSearchProvider::Tavily),
+            _ => Err(WebSearchProviderParseError::Unknown(s.to_string())),
+        }
+    }
+}
+
+/// How recent the results of a web search have to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearchFreshness {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WebSearchBody {
     pub query: String,
+    /// The provider to search with, if the server supports it. The server picks one when this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<WebSearchProvider>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_results: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<WebSearchFreshness>,
+    /// Only return results from these domains, and their subdomains.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_domains: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_domains: Vec<String>,
+    /// Options specific to the provider, which the server passes to it as is. Other providers ignore them.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub provider_options: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+impl WebSearchBody {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            provider: None,
+            max_results: None,
+            freshness: None,
+            include_domains: Vec::new(),
+            exclude_domains: Vec::new(),
+            provider_options: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WebSearchResponse {
     pub results: Vec<WebSearchResult>,
+    /// The provider the server searched with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<WebSearchProvider>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WebSearchResult {
     pub title: String,
     pub url: String,
     pub snippet: Option<String>,
+    /// When the page was published, as an ISO 8601 date or date and time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_date: Option<String>,
+    /// How relevant the result is to the query, from 0 to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// The name of the site, such as "Rust Documentation".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_web_search_provider_negotiation() {
+        let header = WebSearchProvider::header_value(&[
+            WebSearchProvider::Brave,
+            WebSearchProvider::Other,
+            WebSearchProvider::Exa,
+        ]);
+        assert_eq!(header, "brave,exa");
+        assert_eq!(
+            WebSearchProvider::parse_header_value("brave, kagi,exa"),
+            [WebSearchProvider::Brave, WebSearchProvider::Exa]
+        );
+
+        let mut body = WebSearchBody::new("rust async traits");
+        body.freshness = Some(WebSearchFreshness::Month);
+        body.include_domains = vec!["doc.rust-lang.org".to_string()];
+        body.provider_options
+            .insert("livecrawl".to_string(), json!("always"));
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            json!({
+                "query": "rust async traits",
+                "freshness": "month",
+                "include_domains": ["doc.rust-lang.org"],
+                "provider_options": {"livecrawl": "always"},
+            })
+        );
+
+        let response: WebSearchResponse = serde_json::from_value(json!({
+            "provider": "kagi",
+            "results": [{
+                "title": "Async functions in traits",
+                "url": "https://blog.rust-lang.org/2023/12/21/async-fn-rpit-in-traits.html",
+                "snippet": null,
+                "published_date": "2023-12-21",
+                "score": 0.92,
+            }],
+        }))
+        .unwrap();
+        assert_eq!(response.provider, Some(WebSearchProvider::Other));
+        assert_eq!(response.results[0].score, Some(0.92));
+        assert_eq!(response.results[0].site_name, None);
+    }
 }
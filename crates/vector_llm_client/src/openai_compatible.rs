@@ -0,0 +1,808 @@
+//! Providers with an OpenAI-compatible API, such as the servers of Ollama and llama.cpp running models locally, and
+//! the gateways of organizations.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::completion::{CompletionBody, MessageContent, Role, ToolChoice};
+use crate::completion_stream::{
+    CompletionEvent, CompletionEventDecoder, ContentDelta, StopReason, ToolCallDelta, UsageDelta,
+};
+use crate::error::{CompletionError, CompletionErrorKind};
+use crate::provider::{
+    HttpMethod, HttpRequest, ModelCapabilities, ModelInfo, Provider, ProviderId,
+};
+use crate::structured_output::ResponseFormat;
+
+pub const OLLAMA_DEFAULT_URL: &str = "http://localhost:11434/v1";
+pub const LLAMA_CPP_DEFAULT_URL: &str = "http://localhost:8080/v1";
+
+/// The configuration of an OpenAI-compatible endpoint, such as an internal gateway.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAiCompatibleConfig {
+    pub id: ProviderId,
+    /// The URL the API's paths, such as `/chat/completions`, are relative to.
+    pub base_url: String,
+    /// Sent as a bearer token in the `Authorization` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Headers to send with every request, such as the ones gateways authenticate clients with.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// The models of the endpoint, for endpoints that don't list them or to override what they report.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<ConfiguredModel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfiguredModel {
+    /// The name of the model on the endpoint.
+    pub name: String,
+    /// The name requests can use for the model instead, such as `gpt-4o` for a gateway's `azure-gpt-4o-2024-08-06`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(flatten)]
+    pub capabilities: ConfiguredCapabilities,
+}
+
+/// The capabilities of a configured model. The ones left out are assumed like for the models the
+/// endpoint lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfiguredCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_images: Option<bool>,
+}
+
+impl ConfiguredModel {
+    /// The name the model is listed with.
+    pub fn id(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The configured capabilities, with the ones left out assumed like for listed models.
+    pub fn capabilities(&self) -> ModelCapabilities {
+        let listed = listed_model_capabilities(&self.name, None, None);
+        ModelCapabilities {
+            max_context_tokens: self
+                .capabilities
+                .max_context_tokens
+                .or(listed.max_context_tokens),
+            max_output_tokens: self
+                .capabilities
+                .max_output_tokens
+                .or(listed.max_output_tokens),
+            supports_tools: self
+                .capabilities
+                .supports_tools
+                .unwrap_or(listed.supports_tools),
+            supports_images: self
+                .capabilities
+                .supports_images
+                .unwrap_or(listed.supports_images),
+        }
+    }
+}
+
+impl fmt::Debug for OpenAiCompatibleConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenAiCompatibleConfig")
+            .field("id", &self.id)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("headers", &redacted_headers(&self.headers))
+            .field("models", &self.models)
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct OpenAiCompatibleProvider {
+    id: ProviderId,
+    base_url: String,
+    api_key: Option<String>,
+    headers: BTreeMap<String, String>,
+    models: Vec<ConfiguredModel>,
+}
+
+impl fmt::Debug for OpenAiCompatibleProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpenAiCompatibleProvider")
+            .field("id", &self.id)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("headers", &redacted_headers(&self.headers))
+            .field("models", &self.models)
+            .finish()
+    }
+}
+
+/// Shown in place of secrets, such as API keys and header values, in debug output.
+const REDACTED: &str = "<redacted>";
+
+/// The names of the headers with their values redacted, since they may be credentials.
+fn redacted_headers(headers: &BTreeMap<String, String>) -> BTreeMap<&str, &str> {
+    headers
+        .keys()
+        .map(|name| (name.as_str(), REDACTED))
+        .collect()
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(id: ProviderId, base_url: impl Into<String>) -> Self {
+        Self::from_config(OpenAiCompatibleConfig {
+            id,
+            base_url: base_url.into(),
+            api_key: None,
+            headers: BTreeMap::new(),
+            models: Vec::new(),
+        })
+    }
+
+    pub fn from_config(config: OpenAiCompatibleConfig) -> Self {
+        Self {
+            id: config.id,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key,
+            headers: config.headers,
+            models: config.models,
+        }
+    }
+
+    /// An Ollama server, at its default URL unless `base_url` is given.
+    pub fn ollama(base_url: Option<&str>) -> Self {
+        Self::new(
+            ProviderId::new("ollama"),
+            base_url.unwrap_or(OLLAMA_DEFAULT_URL),
+        )
+    }
+
+    /// A llama.cpp server, at its default URL unless `base_url` is given.
+    pub fn llama_cpp(base_url: Option<&str>) -> Self {
+        Self::new(
+            ProviderId::new("llama_cpp"),
+            base_url.unwrap_or(LLAMA_CPP_DEFAULT_URL),
+        )
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// The configured model a request for `model` is for, by its name or its alias.
+    pub fn configured_model(&self, model: &str) -> Option<&ConfiguredModel> {
+        self.models
+            .iter()
+            .find(|configured| configured.alias.as_deref() == Some(model))
+            .or_else(|| {
+                self.models
+                    .iter()
+                    .find(|configured| configured.name == model)
+            })
+    }
+
+    fn request(&self, method: HttpMethod, path: &str, body: Option<Vec<u8>>) -> HttpRequest {
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        if let Some(api_key) = &self.api_key {
+            headers.push(("Authorization".to_string(), format!("Bearer {api_key}")));
+        }
+        for (name, value) in &self.headers {
+            headers.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            headers.push((name.clone(), value.clone()));
+        }
+        HttpRequest {
+            method,
+            url: format!("{}{path}", self.base_url),
+            headers,
+            body,
+        }
+    }
+}
+
+impl Provider for OpenAiCompatibleProvider {
+    fn id(&self) -> &ProviderId {
+        &self.id
+    }
+
+    fn models_request(&self) -> HttpRequest {
+        self.request(HttpMethod::Get, "/models", None)
+    }
+
+    fn parse_models(&self, response: &[u8]) -> Result<Vec<ModelInfo>, CompletionError> {
+        let response: ModelsResponse = serde_json::from_slice(response).map_err(|error| {
+            CompletionError::new(
+                CompletionErrorKind::Server,
+                format!("invalid model list: {error}"),
+            )
+        })?;
+        let configured = self.models.iter().map(|model| ModelInfo {
+            id: model.id().to_string(),
+            provider: self.id.clone(),
+            capabilities: model.capabilities(),
+        });
+        let listed = response
+            .data
+            .into_iter()
+            .filter(|model| self.configured_model(&model.id).is_none())
+            .map(|model| ModelInfo {
+                // llama.cpp reports the context the model was trained with.
+                capabilities: listed_model_capabilities(
+                    &model.id,
+                    model.meta.as_ref().and_then(|meta| meta.n_ctx_train),
+                    model.supports_tools(),
+                ),
+                id: model.id,
+                provider: self.id.clone(),
+            });
+        Ok(configured.chain(listed).collect())
+    }
+
+    fn completion_request(&self, body: &CompletionBody) -> Result<HttpRequest, CompletionError> {
+        let mut model = body.model.as_str();
+        let mut max_tokens = body.max_tokens;
+        if let Some(configured) = self.configured_model(model) {
+            let capabilities = configured.capabilities();
+            let unsupported = |what: &str| {
+                CompletionError::new(
+                    CompletionErrorKind::InvalidRequest,
+                    format!("model {} doesn't support {what}", configured.id()),
+                )
+            };
+            if !body.tools.is_empty() && !capabilities.supports_tools {
+                return Err(unsupported("tools"));
+            }
+            let has_images = body
+                .messages
+                .iter()
+                .flat_map(|message| &message.content)
+                .any(|content| matches!(content, MessageContent::Image(_)));
+            if has_images && !capabilities.supports_images {
+                return Err(unsupported("images"));
+            }
+            if let Some(max_output_tokens) = capabilities.max_output_tokens {
+                let max_output_tokens = u32::try_from(max_output_tokens).unwrap_or(u32::MAX);
+                max_tokens = Some(max_tokens.map_or(max_output_tokens, |max_tokens| {
+                    max_tokens.min(max_output_tokens)
+                }));
+            }
+            model = &configured.name;
+        }
+        let body = chat_completion_body(body, model, max_tokens)?;
+        let body = serde_json::to_vec(&body).map_err(|error| {
+            CompletionError::new(CompletionErrorKind::InvalidRequest, error.to_string())
+        })?;
+        Ok(self.request(HttpMethod::Post, "/chat/completions", Some(body)))
+    }
+
+    fn event_decoder(&self) -> CompletionEventDecoder {
+        CompletionEventDecoder::new(parse_chat_completion_chunk)
+    }
+}
+
+/// The capabilities of a model that the endpoint lists, assumed for the ones it doesn't report.
+fn listed_model_capabilities(
+    id: &str,
+    max_context_tokens: Option<u64>,
+    supports_tools: Option<bool>,
+) -> ModelCapabilities {
+    ModelCapabilities {
+        max_context_tokens,
+        max_output_tokens: None,
+        supports_tools: supports_tools.unwrap_or(true),
+        supports_images: is_vision_model(id),
+    }
+}
+
+/// Whether a model takes images, going by the names of the common open vision models.
+fn is_vision_model(id: &str) -> bool {
+    let id = id.to_ascii_lowercase();
+    ["llava", "vision", "-vl", "bakllava", "moondream", "gemma3"]
+        .iter()
+        .any(|name| id.contains(name))
+}
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    meta: Option<ModelMeta>,
+    /// The features of the model, such as `tool_use`, as some endpoints such as LM Studio report them.
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    /// The request parameters the model takes, such as `tools`, as OpenRouter reports them.
+    #[serde(default)]
+    supported_parameters: Option<Vec<String>>,
+}
+
+impl ModelEntry {
+    /// Whether the model can call tools, if the endpoint reports it.
+    fn supports_tools(&self) -> Option<bool> {
+        let capabilities = self.capabilities.as_ref();
+        let reported = capabilities
+            .into_iter()
+            .chain(self.supported_parameters.as_ref())
+            .flatten()
+            .any(|name| name == "tools" || name == "tool_use");
+        (capabilities.is_some() || self.supported_parameters.is_some()).then_some(reported)
+    }
+}
+
+#[derive(Deserialize)]
+struct ModelMeta {
+    #[serde(default)]
+    n_ctx_train: Option<u64>,
+}
+
+/// Translates a completion body to the body of a streaming `/chat/completions` request for `model`.
+fn chat_completion_body(
+    body: &CompletionBody,
+    model: &str,
+    max_tokens: Option<u32>,
+) -> Result<Value, CompletionError> {
+    let mut messages = Vec::new();
+    for message in &body.messages {
+        let role = match message.role {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        let mut parts = Vec::new();
+        let mut tool_calls = Vec::new();
+        for content in &message.content {
+            match content {
+                MessageContent::Text { text } => parts.push(json!({"type": "text", "text": text})),
+                MessageContent::Image(image) => parts.push(json!({
+                    "type": "image_url",
+                    "image_url": {
+                        "url": format!("data:{};base64,{}", image.media_type.as_str(), image.data),
+                    },
+                })),
+                MessageContent::File(_) => {
+                    return Err(CompletionError::new(
+                        CompletionErrorKind::InvalidRequest,
+                        "file attachments are not supported by OpenAI-compatible providers",
+                    ));
+                }
+                MessageContent::ToolCall(call) => tool_calls.push(json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": {"name": call.name, "arguments": call.input.to_string()},
+                })),
+                // Tool results are messages of their own, which can't be marked as errors, so the content says so.
+                MessageContent::ToolResult(result) => messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": result.tool_call_id,
+                    "content": if result.is_error {
+                        format!("Error: {}", result.content)
+                    } else {
+                        result.content.clone()
+                    },
+                })),
+            }
+        }
+        if parts.is_empty() && tool_calls.is_empty() {
+            continue;
+        }
+        let content = match parts.as_slice() {
+            [] => Value::Null,
+            [part] if part["type"] == "text" => part["text"].clone(),
+            _ => Value::Array(parts),
+        };
+        let mut message = json!({"role": role, "content": content});
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = Value::Array(tool_calls);
+        }
+        messages.push(message);
+    }
+
+    let mut request = json!({
+        "model": model,
+        "messages": messages,
+        "stream": true,
+        "stream_options": {"include_usage": true},
+    });
+    if !body.tools.is_empty() {
+        request["tools"] = body
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "type": "function",
+                    "function": {
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.input_schema,
+                    },
+                })
+            })
+            .collect();
+    }
+    if let Some(tool_choice) = &body.tool_choice {
+        request["tool_choice"] = match tool_choice {
+            ToolChoice::Auto => json!("auto"),
+            ToolChoice::Any => json!("required"),
+            ToolChoice::None => json!("none"),
+            ToolChoice::Tool { name } => json!({"type": "function", "function": {"name": name}}),
+        };
+    }
+    if let Some(parallel_tool_calls) = body.parallel_tool_calls {
+        request["parallel_tool_calls"] = json!(parallel_tool_calls);
+    }
+    if let Some(max_tokens) = max_tokens {
+        request["max_tokens"] = json!(max_tokens);
+    }
+    if let Some(temperature) = body.temperature {
+        request["temperature"] = json!(temperature);
+    }
+    if let Some(response_format) = &body.response_format {
+        request["response_format"] = match response_format {
+            ResponseFormat::Text => json!({"type": "text"}),
+            ResponseFormat::JsonObject => json!({"type": "json_object"}),
+            ResponseFormat::JsonSchema {
+                name,
+                schema,
+                strict,
+            } => json!({
+                "type": "json_schema",
+                "json_schema": {"name": name, "schema": schema, "strict": strict},
+            }),
+        };
+    }
+    Ok(request)
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<ChunkUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChunkToolCall>,
+}
+
+#[derive(Deserialize)]
+struct ChunkToolCall {
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: ChunkFunction,
+}
+
+#[derive(Default, Deserialize)]
+struct ChunkFunction {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChunkUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    #[serde(default)]
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct PromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
+}
+
+fn parse_chat_completion_chunk(data: &str) -> Result<Vec<CompletionEvent>, serde_json::Error> {
+    let chunk: ChatCompletionChunk = serde_json::from_str(data)?;
+    let mut events = Vec::new();
+    for choice in chunk.choices {
+        if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+            events.push(CompletionEvent::ContentDelta(ContentDelta { text }));
+        }
+        for call in choice.delta.tool_calls {
+            events.push(CompletionEvent::ToolCallDelta(ToolCallDelta {
+                index: call.index,
+                id: call.id,
+                name: call.function.name,
+                arguments: call.function.arguments.unwrap_or_default(),
+            }));
+        }
+        if let Some(finish_reason) = choice.finish_reason {
+            let reason = match finish_reason.as_str() {
+                "length" => StopReason::MaxTokens,
+                "tool_calls" | "function_call" => StopReason::ToolUse,
+                "content_filter" => StopReason::Refusal,
+                _ => StopReason::EndTurn,
+            };
+            events.push(CompletionEvent::Stop { reason });
+        }
+    }
+    if let Some(usage) = chunk.usage {
+        // The prompt tokens include the cached ones, which OpenAI-compatible servers cache without breakpoints.
+        let cached_tokens = usage
+            .prompt_tokens_details
+            .map_or(0, |details| details.cached_tokens);
+        events.push(CompletionEvent::UsageDelta(UsageDelta {
+            input_tokens: usage.prompt_tokens.saturating_sub(cached_tokens),
+            output_tokens: usage.completion_tokens,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: cached_tokens,
+        }));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{
+        CompletionResponseBuilder, Message, ToolCall, ToolDefinition, ToolResult,
+    };
+    use crate::provider::Providers;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_ollama_provider() {
+        let provider = OpenAiCompatibleProvider::ollama(None);
+        assert_eq!(
+            provider.models_request().url,
+            "http://localhost:11434/v1/models"
+        );
+        let models = provider
+            .parse_models(
+                br#"{"data": [
+                    {"id": "qwen2.5-coder:7b"},
+                    {"id": "llava:13b", "meta": {"n_ctx_train": 4096}, "capabilities": ["completion", "vision"]},
+                    {"id": "mistral-small", "supported_parameters": ["tools", "temperature"]}
+                ]}"#,
+            )
+            .unwrap();
+        assert_eq!(models[0].id, "qwen2.5-coder:7b");
+        assert!(!models[0].capabilities.supports_images);
+        assert!(models[0].capabilities.supports_tools);
+        assert_eq!(models[1].capabilities.max_context_tokens, Some(4096));
+        assert!(models[1].capabilities.supports_images);
+        assert!(!models[1].capabilities.supports_tools);
+        assert!(models[2].capabilities.supports_tools);
+
+        let mut body = CompletionBody::new("qwen2.5-coder:7b", vec![Message::user("Find main")]);
+        body.tools.push(ToolDefinition {
+            name: "grep".to_owned(),
+            description: "Searches files".to_owned(),
+            input_schema: json!({"type": "object"}),
+        });
+        body.push_tool_results(
+            vec![MessageContent::ToolCall(ToolCall {
+                id: "call_1".to_owned(),
+                name: "grep".to_owned(),
+                input: json!({"query": "fn main"}),
+            })],
+            vec![ToolResult {
+                tool_call_id: "call_1".to_owned(),
+                content: "src/main.rs".to_owned(),
+                is_error: false,
+            }],
+        )
+        .unwrap();
+        body.push_tool_results(
+            vec![MessageContent::ToolCall(ToolCall {
+                id: "call_2".to_owned(),
+                name: "grep".to_owned(),
+                input: json!({"query": "("}),
+            })],
+            vec![ToolResult {
+                tool_call_id: "call_2".to_owned(),
+                content: "invalid regex".to_owned(),
+                is_error: true,
+            }],
+        )
+        .unwrap();
+        let request = provider.completion_request(&body).unwrap();
+        assert_eq!(request.url, "http://localhost:11434/v1/chat/completions");
+        let request: Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(
+            request["messages"],
+            json!([
+                {"role": "user", "content": "Find main"},
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "grep", "arguments": "{\"query\":\"fn main\"}"},
+                    }],
+                },
+                {"role": "tool", "tool_call_id": "call_1", "content": "src/main.rs"},
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_2",
+                        "type": "function",
+                        "function": {"name": "grep", "arguments": "{\"query\":\"(\"}"},
+                    }],
+                },
+                {"role": "tool", "tool_call_id": "call_2", "content": "Error: invalid regex"},
+            ])
+        );
+        assert_eq!(request["tools"][0]["function"]["name"], "grep");
+
+        let mut decoder = provider.event_decoder();
+        let mut builder = CompletionResponseBuilder::default();
+        for event in decoder.push(
+            concat!(
+                "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"It's in \"}}]}\n\n",
+                "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"src/main.rs\"},\"finish_reason\":\"stop\"}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":20,\"completion_tokens\":6,\"prompt_tokens_details\":{\"cached_tokens\":16}}}\n\n",
+                "data: [DONE]\n\n",
+            )
+            .as_bytes(),
+        ) {
+            builder.push_event(&event.unwrap());
+        }
+        let response = builder.build().unwrap();
+        assert_eq!(
+            response.content,
+            vec![MessageContent::Text {
+                text: "It's in src/main.rs".to_owned()
+            }]
+        );
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(
+            response.usage,
+            UsageDelta {
+                input_tokens: 4,
+                output_tokens: 6,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 16,
+            }
+        );
+
+        let mut providers = Providers::default();
+        providers.register(Arc::new(provider));
+        assert!(providers.for_request(&body).unwrap().is_none());
+        body.provider = Some(ProviderId::new("ollama"));
+        assert_eq!(
+            providers.for_request(&body).unwrap().unwrap().id(),
+            &ProviderId::new("ollama")
+        );
+        body.provider = Some(ProviderId::new("llama_cpp"));
+        assert!(providers.for_request(&body).is_err());
+    }
+
+    #[test]
+    fn test_configured_endpoint() {
+        let config: OpenAiCompatibleConfig = serde_json::from_value(json!({
+            "id": "gateway",
+            "base_url": "https://llm.example.com/openai/",
+            "headers": {"X-Api-Key": "secret"},
+            "models": [{
+                "name": "azure-gpt-4o-2024-08-06",
+                "alias": "gpt-4o",
+                "max_output_tokens": 4096,
+                "supports_tools": false,
+            }],
+        }))
+        .unwrap();
+        let provider = OpenAiCompatibleProvider::from_config(config);
+
+        let models = provider
+            .parse_models(br#"{"data": [{"id": "azure-gpt-4o-2024-08-06"}, {"id": "embedder"}]}"#)
+            .unwrap();
+        assert_eq!(
+            models
+                .iter()
+                .map(|model| model.id.as_str())
+                .collect::<Vec<_>>(),
+            ["gpt-4o", "embedder"]
+        );
+        assert_eq!(models[0].capabilities.max_output_tokens, Some(4096));
+        assert!(!models[0].capabilities.supports_tools);
+
+        let mut body = CompletionBody::new("gpt-4o", vec![Message::user("Hi")]);
+        body.max_tokens = Some(10_000);
+        let request = provider.completion_request(&body).unwrap();
+        assert_eq!(
+            request.url,
+            "https://llm.example.com/openai/chat/completions"
+        );
+        assert!(
+            request
+                .headers
+                .contains(&("X-Api-Key".to_owned(), "secret".to_owned()))
+        );
+        let request: Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+        assert_eq!(request["model"], "azure-gpt-4o-2024-08-06");
+        assert_eq!(request["max_tokens"], 4096);
+
+        body.tools.push(ToolDefinition {
+            name: "grep".to_owned(),
+            description: "Searches files".to_owned(),
+            input_schema: json!({"type": "object"}),
+        });
+        assert_eq!(
+            provider.completion_request(&body).unwrap_err().message,
+            "model gpt-4o doesn't support tools"
+        );
+
+        let debug = format!("{provider:?}");
+        assert!(debug.contains("X-Api-Key"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[test]
+    fn test_configured_model_capability_defaults() {
+        let config: OpenAiCompatibleConfig = serde_json::from_value(json!({
+            "id": "gateway",
+            "base_url": "https://llm.example.com/openai",
+            "api_key": "sk-secret",
+            "models": [
+                {"name": "qwen2.5-coder:32b", "max_context_tokens": 32768},
+                {"name": "llava:13b"},
+                {"name": "llava:7b", "supports_images": false},
+            ],
+        }))
+        .unwrap();
+        assert!(!format!("{config:?}").contains("sk-secret"));
+        let provider = OpenAiCompatibleProvider::from_config(config);
+        let models = provider.parse_models(br#"{"data": []}"#).unwrap();
+        assert_eq!(
+            models
+                .iter()
+                .map(|model| model.capabilities.clone())
+                .collect::<Vec<_>>(),
+            [
+                ModelCapabilities {
+                    max_context_tokens: Some(32768),
+                    max_output_tokens: None,
+                    supports_tools: true,
+                    supports_images: false,
+                },
+                ModelCapabilities {
+                    max_context_tokens: None,
+                    max_output_tokens: None,
+                    supports_tools: true,
+                    supports_images: true,
+                },
+                ModelCapabilities {
+                    max_context_tokens: None,
+                    max_output_tokens: None,
+                    supports_tools: true,
+                    supports_images: false,
+                },
+            ]
+        );
+        assert!(!format!("{provider:?}").contains("sk-secret"));
+    }
+}
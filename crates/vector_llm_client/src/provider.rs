@@ -0,0 +1,124 @@
+//! Providers of completions other than Vector's own endpoint, and the models they serve.
+//!
+//! Providers don't send requests themselves: they build the HTTP requests to send with the client of the caller, and
+//! parse the responses.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::completion::CompletionBody;
+use crate::completion_stream::CompletionEventDecoder;
+use crate::error::{CompletionError, CompletionErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProviderId(pub String);
+
+impl ProviderId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+}
+
+impl fmt::Display for ProviderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+/// A request for the caller to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// What a model can do. Unknown limits are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default)]
+    pub supports_tools: bool,
+    #[serde(default)]
+    pub supports_images: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub provider: ProviderId,
+    pub capabilities: ModelCapabilities,
+}
+
+pub trait Provider: Send + Sync {
+    fn id(&self) -> &ProviderId;
+
+    /// The request listing the models of the provider.
+    fn models_request(&self) -> HttpRequest;
+
+    fn parse_models(&self, response: &[u8]) -> Result<Vec<ModelInfo>, CompletionError>;
+
+    /// The request streaming the completion of `body`.
+    fn completion_request(&self, body: &CompletionBody) -> Result<HttpRequest, CompletionError>;
+
+    /// A decoder for the server-sent events of the responses to completion requests.
+    fn event_decoder(&self) -> CompletionEventDecoder;
+}
+
+/// The providers requests can be sent to, by id.
+#[derive(Default, Clone)]
+pub struct Providers {
+    providers: Vec<Arc<dyn Provider>>,
+    default: Option<ProviderId>,
+}
+
+impl Providers {
+    /// Adds a provider, replacing the one with the same id, if any.
+    pub fn register(&mut self, provider: Arc<dyn Provider>) {
+        self.providers
+            .retain(|registered| registered.id() != provider.id());
+        self.providers.push(provider);
+    }
+
+    /// Sets the provider of the requests that don't name one. Without a default, they go to Vector's endpoint.
+    pub fn set_default(&mut self, id: Option<ProviderId>) {
+        self.default = id;
+    }
+
+    pub fn get(&self, id: &ProviderId) -> Option<&Arc<dyn Provider>> {
+        self.providers.iter().find(|provider| provider.id() == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Provider>> {
+        self.providers.iter()
+    }
+
+    /// The provider to send a request to: the one it names, or the default one. `Ok(None)` means Vector's endpoint.
+    pub fn for_request(
+        &self,
+        body: &CompletionBody,
+    ) -> Result<Option<&Arc<dyn Provider>>, CompletionError> {
+        match body.provider.as_ref().or(self.default.as_ref()) {
+            Some(id) => self.get(id).map(Some).ok_or_else(|| {
+                CompletionError::new(
+                    CompletionErrorKind::InvalidRequest,
+                    format!("unknown provider {id}"),
+                )
+            }),
+            None => Ok(None),
+        }
+    }
+}
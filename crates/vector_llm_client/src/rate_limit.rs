@@ -0,0 +1,453 @@
+//! The rate limits the server reports in the headers of its responses, and a scheduler that queues requests on the
+//! client while the limits are nearly reached, instead of sending requests the server would reject.
+//!
+//! Queued requests report their position with [`CompletionRequestStatus::Queued`], like the requests the server
+//! queues, so that the UI shows both the same way.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::CompletionRequestStatus;
+
+/// The prefix of the rate limit headers, which are named `{prefix}{resource}-{limit|remaining|reset}`, with the
+/// resource being `requests`, `input-tokens` or `output-tokens`, and the reset in seconds.
+pub const RATE_LIMIT_HEADER_PREFIX: &str = "x-vector-ratelimit-";
+
+/// How long queued requests wait before checking the limits again, when no limit resets sooner.
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// How long until `remaining` goes back to `limit`.
+    pub reset: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests: Option<RateLimit>,
+    pub input_tokens: Option<RateLimit>,
+    pub output_tokens: Option<RateLimit>,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("invalid value for rate limit header {header}: {value}")]
+pub struct RateLimitParseError {
+    pub header: String,
+    pub value: String,
+}
+
+impl RateLimits {
+    /// Reads the limits from the headers of a response, looked up by name with `header`. The limits are `None` when
+    /// the response has no headers for them.
+    pub fn from_headers<'a>(
+        header: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Self, RateLimitParseError> {
+        let limit = |resource: &str| -> Result<Option<RateLimit>, RateLimitParseError> {
+            let parse = |name: &str| -> Result<Option<u64>, RateLimitParseError> {
+                let header_name = format!("{RATE_LIMIT_HEADER_PREFIX}{resource}-{name}");
+                header(&header_name)
+                    .map(|value| {
+                        value.trim().parse().map_err(|_| RateLimitParseError {
+                            header: header_name.clone(),
+                            value: value.to_string(),
+                        })
+                    })
+                    .transpose()
+            };
+            let (Some(limit), Some(remaining)) = (parse("limit")?, parse("remaining")?) else {
+                return Ok(None);
+            };
+            Ok(Some(RateLimit {
+                limit,
+                remaining,
+                reset: parse("reset")?.map(Duration::from_secs),
+            }))
+        };
+        Ok(Self {
+            requests: limit("requests")?,
+            input_tokens: limit("input-tokens")?,
+            output_tokens: limit("output-tokens")?,
+        })
+    }
+}
+
+/// Why a request can't be sent within the rate limits, however long it waits.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitError {
+    #[error("the request needs {needed} {resource}, more than the limit of {limit}")]
+    ExceedsLimit {
+        resource: &'static str,
+        needed: u64,
+        limit: u64,
+    },
+    #[error("the rate limit of {resource} is reached, and the server didn't say when it resets")]
+    NoReset { resource: &'static str },
+}
+
+/// Queues requests while sending them would exceed the rate limits, sending them in order once the limits reset.
+pub struct RateLimitScheduler {
+    /// How many requests to keep in reserve, for requests that don't go through the scheduler.
+    reserved_requests: u64,
+    state: Mutex<SchedulerState>,
+}
+
+/// What's left of a rate limit until it resets, as of the latest response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LimitWindow {
+    limit: u64,
+    remaining: u64,
+    reset_at: Option<Instant>,
+    /// How long the window lasts, assumed to be the time until the reset the server reported.
+    length: Option<Duration>,
+}
+
+impl LimitWindow {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit: limit.limit,
+            remaining: limit.remaining,
+            reset_at: limit.reset.map(|reset| now + reset),
+            length: limit.reset,
+        }
+    }
+
+    /// Once the window reset, restores the remaining amount, and moves the window forward past `now`.
+    fn advance(&mut self, now: Instant) {
+        let Some(reset_at) = self.reset_at.filter(|reset_at| now >= *reset_at) else {
+            return;
+        };
+        self.remaining = self.limit;
+        self.reset_at = self
+            .length
+            .filter(|length| !length.is_zero())
+            .and_then(|length| {
+                let windows = (now - reset_at).as_nanos() / length.as_nanos() + 1;
+                let windows = u32::try_from(windows).unwrap_or(u32::MAX);
+                reset_at.checked_add(length.checked_mul(windows)?)
+            });
+    }
+
+    /// Whether `needed` fits in what's left of the window, or an error if it never will.
+    fn fits(&self, needed: u64, resource: &'static str) -> Result<bool, RateLimitError> {
+        if needed > self.limit {
+            Err(RateLimitError::ExceedsLimit {
+                resource,
+                needed,
+                limit: self.limit,
+            })
+        } else if needed <= self.remaining {
+            Ok(true)
+        } else if self.reset_at.is_none() {
+            Err(RateLimitError::NoReset { resource })
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    requests: Option<LimitWindow>,
+    input_tokens: Option<LimitWindow>,
+    output_tokens: Option<LimitWindow>,
+    queue: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+impl SchedulerState {
+    fn windows(&mut self) -> impl Iterator<Item = &mut LimitWindow> {
+        [
+            &mut self.requests,
+            &mut self.input_tokens,
+            &mut self.output_tokens,
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Whether a request with `input_tokens` and up to `output_tokens` can be sent now, and takes it out of the
+    /// remaining limits if so.
+    fn try_start(
+        &mut self,
+        input_tokens: u64,
+        output_tokens: u64,
+        reserved_requests: u64,
+        now: Instant,
+    ) -> Result<bool, RateLimitError> {
+        for window in self.windows() {
+            window.advance(now);
+        }
+        let mut fits = true;
+        for (window, needed, resource) in [
+            (self.requests, reserved_requests + 1, "requests"),
+            (self.input_tokens, input_tokens, "input tokens"),
+            (self.output_tokens, output_tokens, "output tokens"),
+        ] {
+            if let Some(window) = window {
+                fits &= window.fits(needed, resource)?;
+            }
+        }
+        if !fits {
+            return Ok(false);
+        }
+        for (window, used) in [
+            (&mut self.requests, 1),
+            (&mut self.input_tokens, input_tokens),
+            (&mut self.output_tokens, output_tokens),
+        ] {
+            if let Some(window) = window {
+                window.remaining = window.remaining.saturating_sub(used);
+            }
+        }
+        Ok(true)
+    }
+
+    /// How long to wait before checking the limits again.
+    fn wait(&mut self, now: Instant) -> Duration {
+        self.windows()
+            .filter_map(|window| window.reset_at)
+            .map(|reset_at| reset_at.saturating_duration_since(now))
+            .filter(|wait| !wait.is_zero())
+            .min()
+            .unwrap_or(QUEUE_POLL_INTERVAL)
+    }
+}
+
+/// Removes a request from the queue when it's sent, or when its future is dropped while it waits.
+struct Ticket<'a> {
+    state: &'a Mutex<SchedulerState>,
+    id: u64,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.queue.retain(|id| *id != self.id);
+    }
+}
+
+impl RateLimitScheduler {
+    pub fn new(reserved_requests: u64) -> Self {
+        Self {
+            reserved_requests,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Updates the limits from the headers of the latest response.
+    pub fn update(&self, limits: RateLimits) {
+        let now = Instant::now();
+        let window = |limit: Option<RateLimit>| limit.map(|limit| LimitWindow::new(limit, now));
+        let mut state = self.state.lock().unwrap();
+        state.requests = window(limits.requests);
+        state.input_tokens = window(limits.input_tokens);
+        state.output_tokens = window(limits.output_tokens);
+    }
+
+    /// Waits until a request with about `input_tokens` of input, and up to `output_tokens` of output, can be sent
+    /// without exceeding the limits, after the requests queued before it. While the request is queued, `status`
+    /// receives its position in the queue, starting at 0, whenever it changes, and the scheduler waits with `sleep`
+    /// between checks of the limits.
+    ///
+    /// Fails once it's the request's turn if it would never fit: when it needs more than a limit, or when a limit is
+    /// reached and the server didn't say when it resets.
+    pub async fn wait_for_turn<SleepFn, SleepFuture>(
+        &self,
+        input_tokens: u64,
+        output_tokens: u64,
+        mut status: impl FnMut(CompletionRequestStatus),
+        mut sleep: SleepFn,
+    ) -> Result<(), RateLimitError>
+    where
+        SleepFn: FnMut(Duration) -> SleepFuture,
+        SleepFuture: Future<Output = ()>,
+    {
+        let ticket = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_ticket;
+            state.next_ticket += 1;
+            state.queue.push_back(id);
+            Ticket {
+                state: &self.state,
+                id,
+            }
+        };
+        let mut reported_position = None;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                let position = state
+                    .queue
+                    .iter()
+                    .position(|id| *id == ticket.id)
+                    .expect("queued requests have a ticket");
+                if position == 0
+                    && state.try_start(input_tokens, output_tokens, self.reserved_requests, now)?
+                {
+                    return Ok(());
+                }
+                if reported_position != Some(position) {
+                    reported_position = Some(position);
+                    status(CompletionRequestStatus::Queued { position });
+                }
+                state.wait(now)
+            };
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_scheduler() {
+        let limits = RateLimits::from_headers(|name| match name {
+            "x-vector-ratelimit-requests-limit" => Some("50"),
+            "x-vector-ratelimit-requests-remaining" => Some("2"),
+            "x-vector-ratelimit-input-tokens-limit" => Some("40000"),
+            "x-vector-ratelimit-input-tokens-remaining" => Some("39000"),
+            "x-vector-ratelimit-input-tokens-reset" => Some("30"),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(
+            limits.requests,
+            Some(RateLimit {
+                limit: 50,
+                remaining: 2,
+                reset: None,
+            })
+        );
+        assert_eq!(
+            limits.input_tokens.unwrap().reset,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(limits.output_tokens, None);
+        assert_eq!(
+            RateLimits::from_headers(|name| name.ends_with("limit").then_some("many")),
+            Err(RateLimitParseError {
+                header: "x-vector-ratelimit-requests-limit".to_string(),
+                value: "many".to_string(),
+            })
+        );
+
+        let scheduler = RateLimitScheduler::new(1);
+        scheduler.update(RateLimits {
+            requests: Some(RateLimit {
+                reset: Some(Duration::from_secs(60)),
+                ..limits.requests.unwrap()
+            }),
+            ..limits
+        });
+        let statuses = RefCell::new(Vec::new());
+        block_on(scheduler.wait_for_turn(
+            1000,
+            0,
+            |status| statuses.borrow_mut().push(status),
+            |_| async {},
+        ))
+        .unwrap();
+        assert!(statuses.borrow().is_empty());
+
+        // The remaining request is reserved, so the next one waits until a response reports more remaining requests.
+        let sleeps = RefCell::new(0);
+        block_on(scheduler.wait_for_turn(
+            1000,
+            0,
+            |status| statuses.borrow_mut().push(status),
+            |_| {
+                *sleeps.borrow_mut() += 1;
+                if *sleeps.borrow() == 2 {
+                    scheduler.update(RateLimits {
+                        requests: Some(RateLimit {
+                            limit: 50,
+                            remaining: 50,
+                            reset: None,
+                        }),
+                        ..limits
+                    });
+                }
+                async {}
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            statuses.into_inner(),
+            [CompletionRequestStatus::Queued { position: 0 }]
+        );
+        assert_eq!(sleeps.into_inner(), 2);
+        assert!(scheduler.state.lock().unwrap().queue.is_empty());
+    }
+
+    #[test]
+    fn test_requests_that_never_fit() {
+        let scheduler = RateLimitScheduler::new(0);
+        scheduler.update(RateLimits {
+            requests: None,
+            input_tokens: Some(RateLimit {
+                limit: 1000,
+                remaining: 1000,
+                reset: Some(Duration::from_secs(60)),
+            }),
+            output_tokens: Some(RateLimit {
+                limit: 500,
+                remaining: 100,
+                reset: None,
+            }),
+        });
+        let wait_for_turn = |input_tokens, output_tokens| {
+            block_on(scheduler.wait_for_turn(input_tokens, output_tokens, |_| {}, |_| async {}))
+        };
+
+        assert_eq!(
+            wait_for_turn(2000, 0),
+            Err(RateLimitError::ExceedsLimit {
+                resource: "input tokens",
+                needed: 2000,
+                limit: 1000,
+            })
+        );
+        // The output tokens are counted, and they don't reset.
+        assert_eq!(wait_for_turn(100, 50), Ok(()));
+        assert_eq!(
+            wait_for_turn(100, 100),
+            Err(RateLimitError::NoReset {
+                resource: "output tokens"
+            })
+        );
+        assert!(scheduler.state.lock().unwrap().queue.is_empty());
+    }
+
+    #[test]
+    fn test_limit_window_advance() {
+        let now = Instant::now();
+        let mut window = LimitWindow::new(
+            RateLimit {
+                limit: 10,
+                remaining: 0,
+                reset: Some(Duration::from_secs(60)),
+            },
+            now,
+        );
+        window.advance(now + Duration::from_secs(30));
+        assert_eq!(window.remaining, 0);
+
+        // Once the window passes, it resets, and the next one starts where it ended.
+        window.remaining = 3;
+        window.advance(now + Duration::from_secs(150));
+        assert_eq!(window.remaining, 10);
+        assert_eq!(window.reset_at, Some(now + Duration::from_secs(180)));
+        assert_eq!(window.fits(5, "requests"), Ok(true));
+    }
+}
@@ -0,0 +1,204 @@
+//! Retrying completion requests that failed for reasons that can go away, such as the model being overloaded.
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng as _;
+use uuid::Uuid;
+
+use crate::error::CompletionError;
+
+/// The name of the header with the idempotency key of a request, which stays the same when the request is retried, so
+/// that the server handles it at most once.
+pub const IDEMPOTENCY_KEY_HEADER_NAME: &str = "x-vector-idempotency-key";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// How many times to send a request, including the first time.
+    pub max_attempts: u32,
+    /// How long to wait before the first retry. The wait doubles with every retry after that.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// The longest wait a server can ask for with the `retry_after` of an error. Longer waits are shortened to it, so
+    /// that a misbehaving server can't stall a request indefinitely.
+    pub max_retry_after: Duration,
+    /// The fraction of each wait that is randomized, so that clients that failed together don't retry together.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(60),
+            jitter: 0.25,
+        }
+    }
+}
+
+/// An attempt at sending a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestAttempt {
+    /// Starts at 1.
+    pub number: u32,
+    /// The value of the [`IDEMPOTENCY_KEY_HEADER_NAME`] header to send the request with.
+    pub idempotency_key: String,
+}
+
+impl RetryPolicy {
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait before the given retry, starting at 1, with `random` in `0.0..1.0` picking the jitter.
+    pub fn backoff(&self, retry: u32, random: f64) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        backoff.mul_f64(1.0 - jitter + jitter * random.clamp(0.0, 1.0))
+    }
+
+    /// How long to wait before a retry when the server asked to wait for `retry_after`: at least the backoff, and no
+    /// longer than [`Self::max_retry_after`] unless the backoff is.
+    pub fn backoff_for_retry_after(
+        &self,
+        backoff: Duration,
+        retry_after: Option<Duration>,
+    ) -> Duration {
+        retry_after.map_or(backoff, |retry_after| {
+            retry_after.clamp(backoff, self.max_retry_after.max(backoff))
+        })
+    }
+
+    /// Sends a request with `send`, retrying it after a backoff while it fails with a retryable error. The server can
+    /// ask for a longer backoff with the `retry_after` of the error.
+    pub async fn send<T, SendFn, SendFuture, SleepFn, SleepFuture>(
+        &self,
+        mut send: SendFn,
+        mut sleep: SleepFn,
+    ) -> Result<T, CompletionError>
+    where
+        SendFn: FnMut(RequestAttempt) -> SendFuture,
+        SendFuture: Future<Output = Result<T, CompletionError>>,
+        SleepFn: FnMut(Duration) -> SleepFuture,
+        SleepFuture: Future<Output = ()>,
+    {
+        let idempotency_key = Uuid::new_v4().to_string();
+        let mut number = 1;
+        loop {
+            let attempt = RequestAttempt {
+                number,
+                idempotency_key: idempotency_key.clone(),
+            };
+            let error = match send(attempt).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if number >= self.max_attempts || !error.kind.is_retryable() {
+                return Err(error);
+            }
+            let backoff = self.backoff(number, rand::rng().random());
+            sleep(self.backoff_for_retry_after(backoff, error.retry_after)).await;
+            number += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::CompletionRequestStatus;
+    use crate::error::CompletionErrorKind;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(5),
+            max_retry_after: Duration::from_secs(10),
+            jitter: 0.5,
+        };
+        assert_eq!(policy.backoff(1, 1.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3, 1.0), Duration::from_secs(5));
+
+        let backoff = Duration::from_secs(2);
+        let retry_after =
+            |seconds| policy.backoff_for_retry_after(backoff, Some(Duration::from_secs(seconds)));
+        assert_eq!(policy.backoff_for_retry_after(backoff, None), backoff);
+        assert_eq!(retry_after(1), backoff);
+        assert_eq!(retry_after(7), Duration::from_secs(7));
+        assert_eq!(retry_after(3600), Duration::from_secs(10));
+        assert_eq!(retry_after(u64::MAX), Duration::from_secs(10));
+
+        let overloaded = CompletionRequestStatus::Failed {
+            code: "overloaded".to_owned(),
+            message: "Overloaded".to_owned(),
+            request_id: "req_1".to_owned(),
+        }
+        .error()
+        .unwrap();
+        assert_eq!(overloaded.kind, CompletionErrorKind::Overloaded);
+
+        let attempts = RefCell::new(Vec::new());
+        let sleeps = RefCell::new(Vec::new());
+        let result = block_on(policy.send(
+            |attempt| {
+                attempts.borrow_mut().push(attempt);
+                let error = overloaded.clone();
+                async move { Err::<(), _>(error) }
+            },
+            |duration| {
+                sleeps.borrow_mut().push(duration);
+                async {}
+            },
+        ));
+        assert_eq!(result, Err(overloaded));
+        let attempts = attempts.into_inner();
+        assert_eq!(
+            attempts
+                .iter()
+                .map(|attempt| attempt.number)
+                .collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(
+            attempts
+                .iter()
+                .all(|attempt| attempt.idempotency_key == attempts[0].idempotency_key)
+        );
+        assert_eq!(sleeps.into_inner().len(), 2);
+
+        let attempts = RefCell::new(0);
+        let result = block_on(policy.send(
+            |_| {
+                *attempts.borrow_mut() += 1;
+                async {
+                    Err::<(), _>(CompletionError::from_response(
+                        400,
+                        None,
+                        "prompt is too long",
+                    ))
+                }
+            },
+            |_| async {},
+        ));
+        assert_eq!(
+            result.unwrap_err().kind,
+            CompletionErrorKind::InvalidRequest
+        );
+        assert_eq!(attempts.into_inner(), 1);
+    }
+}
@@ -0,0 +1,227 @@
+//! Constraining the responses of models to JSON, optionally matching a schema, so that tool pipelines can rely on their
+//! structure, and decoding those responses.
+
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::completion::{CompletionBody, CompletionResponse, Message, MessageContent, Role};
+use crate::error::CompletionError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ResponseFormat {
+    Text,
+    /// Any JSON object.
+    JsonObject,
+    /// JSON matching a schema.
+    JsonSchema {
+        name: String,
+        schema: Value,
+        /// Whether the server constrains the sampling of the model to the schema, instead of only asking the model to
+        /// follow it. Servers only support a subset of JSON schema in strict mode.
+        #[serde(default)]
+        strict: bool,
+    },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum StructuredOutputError {
+    #[error("invalid response schema: {0}")]
+    InvalidSchema(String),
+    #[error("response is not valid JSON: {0}")]
+    InvalidJson(String),
+    #[error("response doesn't match the schema: {}", .0.join("; "))]
+    SchemaMismatch(Vec<String>),
+    #[error(transparent)]
+    Completion(#[from] CompletionError),
+}
+
+/// Decodes the responses of a model asked to respond in a [`ResponseFormat`], repairing the JSON when the model wrapped
+/// it in prose or left trailing commas, and validating it against the schema, if any.
+pub struct StructuredOutputDecoder {
+    json: bool,
+    validator: Option<jsonschema::Validator>,
+}
+
+impl StructuredOutputDecoder {
+    pub fn new(format: &ResponseFormat) -> Result<Self, StructuredOutputError> {
+        Ok(match format {
+            ResponseFormat::Text => Self {
+                json: false,
+                validator: None,
+            },
+            ResponseFormat::JsonObject => Self {
+                json: true,
+                validator: None,
+            },
+            ResponseFormat::JsonSchema { schema, .. } => Self {
+                json: true,
+                validator: Some(
+                    jsonschema::validator_for(schema)
+                        .map_err(|error| StructuredOutputError::InvalidSchema(error.to_string()))?,
+                ),
+            },
+        })
+    }
+
+    /// Decodes the text of a response. Text responses decode to a JSON string.
+    pub fn decode(&self, text: &str) -> Result<Value, StructuredOutputError> {
+        if !self.json {
+            return Ok(Value::String(text.to_string()));
+        }
+        let value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(error) => repair_json(text)
+                .and_then(|repaired| serde_json::from_str(&repaired).ok())
+                .ok_or_else(|| StructuredOutputError::InvalidJson(error.to_string()))?,
+        };
+        if let Some(validator) = &self.validator {
+            let errors = validator
+                .iter_errors(&value)
+                .map(|error| match error.instance_path().as_str() {
+                    "" => error.to_string(),
+                    path => format!("{path}: {error}"),
+                })
+                .collect::<Vec<_>>();
+            if !errors.is_empty() {
+                return Err(StructuredOutputError::SchemaMismatch(errors));
+            }
+        }
+        Ok(value)
+    }
+
+    /// Completes `body` with `complete` and decodes the response. When the response can't be decoded, the request is
+    /// sent again, up to `max_attempts` times in total, with the invalid response and the reason it's invalid appended
+    /// to the conversation, so that the model can correct it.
+    pub async fn complete<CompleteFn, CompleteFuture>(
+        &self,
+        mut body: CompletionBody,
+        max_attempts: u32,
+        mut complete: CompleteFn,
+    ) -> Result<Value, StructuredOutputError>
+    where
+        CompleteFn: FnMut(CompletionBody) -> CompleteFuture,
+        CompleteFuture: Future<Output = Result<CompletionResponse, CompletionError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let text = complete(body.clone()).await?.text();
+            let error = match self.decode(&text) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if attempt >= max_attempts {
+                return Err(error);
+            }
+            body.messages.push(Message {
+                role: Role::Assistant,
+                content: vec![MessageContent::Text { text }],
+                cache_control: None,
+            });
+            body.messages.push(Message::user(format!(
+                "That response is invalid: {error}. Respond again with only the corrected JSON."
+            )));
+            attempt += 1;
+        }
+    }
+}
+
+/// Repairs the mistakes models commonly make when responding with JSON: wrapping it in a Markdown code block or in
+/// prose, and leaving trailing commas in objects and arrays. `None` when there is nothing to repair.
+pub fn repair_json(text: &str) -> Option<String> {
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    if end < start {
+        return None;
+    }
+    let json = &text[start..=end];
+
+    let mut repaired = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (ix, c) in json.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && json[ix + 1..].trim_start().starts_with(['}', ']']) {
+            continue;
+        }
+        repaired.push(c);
+    }
+    (repaired != text.trim()).then_some(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_structured_output() {
+        let format = ResponseFormat::JsonSchema {
+            name: "rename".to_owned(),
+            schema: json!({
+                "type": "object",
+                "properties": {"old_name": {"type": "string"}, "new_name": {"type": "string"}},
+                "required": ["old_name", "new_name"],
+                "additionalProperties": false,
+            }),
+            strict: true,
+        };
+        let decoder = StructuredOutputDecoder::new(&format).unwrap();
+        assert_eq!(
+            decoder
+                .decode(
+                    "Here you go:\n```json\n{\"old_name\": \"a, b\", \"new_name\": \"c\",}\n```"
+                )
+                .unwrap(),
+            json!({"old_name": "a, b", "new_name": "c"})
+        );
+        assert!(matches!(
+            decoder.decode("{\"old_name\": \"a\"}"),
+            Err(StructuredOutputError::SchemaMismatch(_))
+        ));
+        assert!(matches!(
+            decoder.decode("no JSON here"),
+            Err(StructuredOutputError::InvalidJson(_))
+        ));
+
+        let mut body = CompletionBody::new("model", vec![Message::user("Rename a to c")]);
+        body.response_format = Some(format);
+        let requests = RefCell::new(Vec::new());
+        let value = block_on(decoder.complete(body, 3, |body| {
+            let text = if requests.borrow().is_empty() {
+                "{\"old_name\": \"a\"}"
+            } else {
+                "{\"old_name\": \"a\", \"new_name\": \"c\"}"
+            };
+            requests.borrow_mut().push(body);
+            async move {
+                Ok(CompletionResponse {
+                    content: vec![MessageContent::Text {
+                        text: text.to_owned(),
+                    }],
+                    ..CompletionResponse::default()
+                })
+            }
+        }))
+        .unwrap();
+        assert_eq!(value, json!({"old_name": "a", "new_name": "c"}));
+        let requests = requests.into_inner();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].messages.len(), 3);
+        assert_eq!(requests[1].messages[1].role, Role::Assistant);
+    }
+}
@@ -0,0 +1,222 @@
+//! Hooks observing completion requests, for logging and metrics.
+//!
+//! Observers only see the metadata of requests: the events they receive have no field that can hold the contents of
+//! prompts or responses, or the messages of errors, which can quote them.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::completion::{CompletionBody, MessageContent};
+use crate::completion_stream::{CompletionEvent, StopReason, UsageDelta};
+use crate::error::{CompletionError, CompletionErrorKind};
+use crate::provider::ProviderId;
+use crate::{CompletionIntent, CompletionMode};
+
+pub trait RequestObserver: Send + Sync {
+    fn request_started(&self, event: &RequestStarted);
+
+    fn request_finished(&self, event: &RequestFinished);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestStarted {
+    /// Identifies the request in the [`RequestFinished`] event.
+    pub request_id: Uuid,
+    pub model: String,
+    pub provider: Option<ProviderId>,
+    pub mode: CompletionMode,
+    pub intent: Option<CompletionIntent>,
+    pub message_count: usize,
+    pub attachment_count: usize,
+    pub tool_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestOutcome {
+    Completed,
+    /// The client cancelled the request, or dropped it before it finished.
+    Cancelled,
+    Failed(CompletionErrorKind),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestFinished {
+    pub request_id: Uuid,
+    pub outcome: RequestOutcome,
+    pub latency: Duration,
+    /// How long the first event of the response took to arrive.
+    pub time_to_first_event: Option<Duration>,
+    pub usage: UsageDelta,
+    pub stop_reason: Option<StopReason>,
+}
+
+/// The observers of the requests of a client.
+#[derive(Default, Clone)]
+pub struct RequestObservers {
+    observers: Vec<Arc<dyn RequestObserver>>,
+}
+
+impl RequestObservers {
+    pub fn add(&mut self, observer: Arc<dyn RequestObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Notifies the observers that the request of `body` started, and returns a tracker to pass the events of the
+    /// response to, which notifies them when the request finishes.
+    pub fn start(&self, body: &CompletionBody) -> RequestTracker {
+        let event = RequestStarted {
+            request_id: Uuid::new_v4(),
+            model: body.model.clone(),
+            provider: body.provider.clone(),
+            mode: body.mode,
+            intent: body.intent,
+            message_count: body.messages.len(),
+            attachment_count: body
+                .messages
+                .iter()
+                .flat_map(|message| &message.content)
+                .filter(|content| {
+                    matches!(content, MessageContent::Image(_) | MessageContent::File(_))
+                })
+                .count(),
+            tool_count: body.tools.len(),
+        };
+        for observer in &self.observers {
+            observer.request_started(&event);
+        }
+        RequestTracker {
+            observers: self.observers.clone(),
+            request_id: event.request_id,
+            started_at: Instant::now(),
+            time_to_first_event: None,
+            usage: UsageDelta::default(),
+            stop_reason: None,
+            outcome: None,
+        }
+    }
+}
+
+/// Tracks a request until it finishes. Notifies the observers that the request was cancelled when dropped before
+/// [`Self::finish`].
+pub struct RequestTracker {
+    observers: Vec<Arc<dyn RequestObserver>>,
+    request_id: Uuid,
+    started_at: Instant,
+    time_to_first_event: Option<Duration>,
+    usage: UsageDelta,
+    stop_reason: Option<StopReason>,
+    outcome: Option<RequestOutcome>,
+}
+
+impl RequestTracker {
+    pub fn request_id(&self) -> Uuid {
+        self.request_id
+    }
+
+    pub fn push_event(&mut self, event: &CompletionEvent) {
+        self.time_to_first_event
+            .get_or_insert_with(|| self.started_at.elapsed());
+        match event {
+            CompletionEvent::UsageDelta(delta) => self.usage += *delta,
+            CompletionEvent::Stop { reason } => self.stop_reason = Some(*reason),
+            CompletionEvent::Cancelled { usage } => {
+                self.usage = *usage;
+                self.outcome = Some(RequestOutcome::Cancelled);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finish(mut self, result: Result<(), &CompletionError>) {
+        let outcome = match result {
+            Ok(()) => RequestOutcome::Completed,
+            Err(error) => RequestOutcome::Failed(error.kind),
+        };
+        self.outcome.get_or_insert(outcome);
+    }
+}
+
+impl Drop for RequestTracker {
+    fn drop(&mut self) {
+        let event = RequestFinished {
+            request_id: self.request_id,
+            outcome: self.outcome.unwrap_or(RequestOutcome::Cancelled),
+            latency: self.started_at.elapsed(),
+            time_to_first_event: self.time_to_first_event,
+            usage: self.usage,
+            stop_reason: self.stop_reason,
+        };
+        for observer in &self.observers {
+            observer.request_finished(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::completion::Message;
+    use crate::completion_stream::ContentDelta;
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        started: Mutex<Vec<RequestStarted>>,
+        finished: Mutex<Vec<RequestFinished>>,
+    }
+
+    impl RequestObserver for RecordingObserver {
+        fn request_started(&self, event: &RequestStarted) {
+            self.started.lock().unwrap().push(event.clone());
+        }
+
+        fn request_finished(&self, event: &RequestFinished) {
+            self.finished.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_request_observers() {
+        let observer = Arc::new(RecordingObserver::default());
+        let mut observers = RequestObservers::default();
+        observers.add(observer.clone());
+
+        let body = CompletionBody::new("model", vec![Message::user("my secret prompt")]);
+        let mut tracker = observers.start(&body);
+        let request_id = tracker.request_id();
+        tracker.push_event(&CompletionEvent::ContentDelta(ContentDelta {
+            text: "my secret response".to_string(),
+        }));
+        tracker.push_event(&CompletionEvent::UsageDelta(UsageDelta {
+            input_tokens: 10,
+            output_tokens: 3,
+            ..UsageDelta::default()
+        }));
+        tracker.finish(Err(&CompletionError::new(
+            CompletionErrorKind::Overloaded,
+            "my secret prompt was too much",
+        )));
+        drop(observers.start(&body));
+
+        let started = observer.started.lock().unwrap();
+        assert_eq!(started.len(), 2);
+        assert_eq!(started[0].request_id, request_id);
+        assert_eq!(started[0].message_count, 1);
+        let finished = observer.finished.lock().unwrap();
+        assert_eq!(finished.len(), 2);
+        assert_eq!(
+            finished[0].outcome,
+            RequestOutcome::Failed(CompletionErrorKind::Overloaded)
+        );
+        assert_eq!(finished[0].usage.output_tokens, 3);
+        assert!(finished[0].time_to_first_event.is_some());
+        assert_eq!(finished[1].outcome, RequestOutcome::Cancelled);
+        assert!(
+            !format!("{started:?}{finished:?}").contains("secret"),
+            "observers must not see the contents of requests"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Estimates of how many tokens text and messages take up, so that callers can trim the context of a request before
+//! it exceeds the context window of the model.
+
+use crate::completion::{Message, MessageContent, Role};
+
+/// Tokens that every message takes up for its role and delimiters, in addition to its content.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// What an attached image is counted as, whatever its dimensions.
+const IMAGE_TOKENS: usize = 1600;
+
+/// What a referenced file is counted as, as its content is read by the server.
+const FILE_TOKENS: usize = 256;
+
+pub trait Tokenizer {
+    fn count_tokens(&self, text: &str) -> usize;
+
+    fn count_message_tokens(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| {
+                let content = message
+                    .content
+                    .iter()
+                    .map(|content| match content {
+                        MessageContent::Text { text } => self.count_tokens(text),
+                        MessageContent::Image(_) => IMAGE_TOKENS,
+                        MessageContent::File(_) => FILE_TOKENS,
+                        MessageContent::ToolCall(call) => {
+                            self.count_tokens(&call.name)
+                                + self.count_tokens(&call.input.to_string())
+                        }
+                        MessageContent::ToolResult(result) => self.count_tokens(&result.content),
+                    })
+                    .sum::<usize>();
+                MESSAGE_OVERHEAD_TOKENS + content
+            })
+            .sum()
+    }
+}
+
+/// The families of models that share a tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelFamily {
+    Claude,
+    Gpt,
+    Gemini,
+    Llama,
+    Other,
+}
+
+impl ModelFamily {
+    pub fn from_model(model: &str) -> Self {
+        let model = model.to_ascii_lowercase();
+        if model.contains("claude") {
+            Self::Claude
+        } else if model.starts_with("gpt")
+            || model.starts_with('o') && model[1..].starts_with(char::is_numeric)
+        {
+            Self::Gpt
+        } else if model.contains("gemini") {
+            Self::Gemini
+        } else if model.contains("llama") {
+            Self::Llama
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn tokenizer(self) -> EstimatingTokenizer {
+        let chars_per_token = match self {
+            Self::Claude => 3.5,
+            Self::Gpt => 4.0,
+            Self::Gemini => 4.0,
+            Self::Llama => 3.8,
+            Self::Other => 3.5,
+        };
+        EstimatingTokenizer { chars_per_token }
+    }
+}
+
+/// A tokenizer that estimates the tokens of text from the average length of the tokens of a model family, rounding up
+/// per word, so that it errs towards overestimating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EstimatingTokenizer {
+    chars_per_token: f32,
+}
+
+impl Tokenizer for EstimatingTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut tokens = 0;
+        let mut word_len = 0;
+        let mut end_word = |word_len: &mut usize| {
+            if *word_len > 0 {
+                tokens += (*word_len as f32 / self.chars_per_token).ceil() as usize;
+                *word_len = 0;
+            }
+        };
+        let mut other_tokens = 0;
+        for char in text.chars() {
+            if char.is_ascii_alphanumeric() {
+                word_len += 1;
+            } else {
+                end_word(&mut word_len);
+                // Punctuation and non-ASCII characters are mostly tokens of their own, while whitespace is mostly
+                // part of the token that follows it.
+                if !char.is_whitespace() {
+                    other_tokens += 1;
+                }
+            }
+        }
+        end_word(&mut word_len);
+        tokens + other_tokens
+    }
+}
+
+/// Returns the index of the first message to keep after the leading system messages, which are always kept, so that
+/// the messages kept fit in `budget` tokens. Returns `None` when even the system messages and the last message don't
+/// fit.
+pub fn first_message_within_budget(
+    tokenizer: &impl Tokenizer,
+    messages: &[Message],
+    budget: usize,
+) -> Option<usize> {
+    let system_messages = messages
+        .iter()
+        .take_while(|message| message.role == Role::System)
+        .count();
+    let mut used = tokenizer.count_message_tokens(&messages[..system_messages]);
+    let mut first = messages.len();
+    while first > system_messages {
+        let tokens = tokenizer.count_message_tokens(&messages[first - 1..first]);
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        first -= 1;
+    }
+    if used > budget || (first == messages.len() && messages.len() > system_messages) {
+        return None;
+    }
+    Some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_tokens() {
+        let tokenizer = ModelFamily::from_model("claude-sonnet-4").tokenizer();
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.count_tokens("fn main() {}"), 7);
+        assert_eq!(ModelFamily::from_model("gpt-4o"), ModelFamily::Gpt);
+        assert_eq!(ModelFamily::from_model("o3-mini"), ModelFamily::Gpt);
+        assert_eq!(ModelFamily::from_model("qwen2.5-coder"), ModelFamily::Other);
+
+        let messages = [
+            Message::system("You are helpful."),
+            Message::user("one two three"),
+            Message::user("four five six"),
+            Message::user("seven"),
+        ];
+        let tokens = |messages: &[Message]| tokenizer.count_message_tokens(messages);
+        assert_eq!(
+            first_message_within_budget(&tokenizer, &messages, usize::MAX),
+            Some(1)
+        );
+        let budget = tokens(&messages[..1]) + tokens(&messages[2..]);
+        assert_eq!(
+            first_message_within_budget(&tokenizer, &messages, budget),
+            Some(2)
+        );
+        assert_eq!(
+            first_message_within_budget(&tokenizer, &messages, tokens(&messages[..1])),
+            None
+        );
+    }
+}
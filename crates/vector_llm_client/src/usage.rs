@@ -0,0 +1,348 @@
+//! Accounting of the tokens that completions use and what they cost, per model, per session and per day, with
+//! budgets that warn before the credits run out.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::UsageLimit;
+use crate::completion_stream::{ModelRequestUsage, UsageDelta};
+
+/// The price of a model, in US dollars per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    /// The price of writing to the prompt cache. Input tokens are billed at the input price when this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_million: Option<f64>,
+    /// The price of reading from the prompt cache. Input tokens are billed at the input price when this is `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    pub fn cost(&self, usage: &UsageDelta) -> f64 {
+        let cache_write = self
+            .cache_write_per_million
+            .unwrap_or(self.input_per_million);
+        let cache_read = self
+            .cache_read_per_million
+            .unwrap_or(self.input_per_million);
+        (usage.input_tokens as f64 * self.input_per_million
+            + usage.output_tokens as f64 * self.output_per_million
+            + usage.cache_creation_input_tokens as f64 * cache_write
+            + usage.cache_read_input_tokens as f64 * cache_read)
+            / 1_000_000.0
+    }
+}
+
+/// The prices of models, by model name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPricing>,
+}
+
+impl PricingTable {
+    pub fn insert(&mut self, model: impl Into<String>, pricing: ModelPricing) {
+        self.prices.insert(model.into(), pricing);
+    }
+
+    pub fn get(&self, model: &str) -> Option<&ModelPricing> {
+        self.prices.get(model)
+    }
+
+    /// The estimated cost of `usage` of `model`, or `None` when the table has no price for the model.
+    pub fn cost(&self, model: &str, usage: &UsageDelta) -> Option<f64> {
+        Some(self.get(model)?.cost(usage))
+    }
+}
+
+/// The usage of a model over some period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default)]
+    pub cache_read_input_tokens: u64,
+    /// The estimated cost of the requests whose model has a price.
+    pub cost: f64,
+}
+
+impl ModelUsage {
+    fn add(&mut self, usage: &UsageDelta, cost: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.cache_creation_input_tokens += usage.cache_creation_input_tokens;
+        self.cache_read_input_tokens += usage.cache_read_input_tokens;
+        self.cost += cost.unwrap_or(0.0);
+    }
+
+    /// All the input and output tokens, cached or not.
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens
+            + self.output_tokens
+            + self.cache_creation_input_tokens
+            + self.cache_read_input_tokens
+    }
+}
+
+/// The usage of each model over some period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UsageSummary {
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageSummary {
+    pub fn total(&self) -> ModelUsage {
+        self.models
+            .values()
+            .fold(ModelUsage::default(), |mut total, usage| {
+                total.requests += usage.requests;
+                total.input_tokens += usage.input_tokens;
+                total.output_tokens += usage.output_tokens;
+                total.cache_creation_input_tokens += usage.cache_creation_input_tokens;
+                total.cache_read_input_tokens += usage.cache_read_input_tokens;
+                total.cost += usage.cost;
+                total
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    Session,
+    Day,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    /// An estimated cost, in US dollars.
+    Cost(f64),
+    /// Input and output tokens, cached or not.
+    Tokens(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub period: BudgetPeriod,
+    pub limit: BudgetLimit,
+    /// The fractions of the limit to warn at, such as `0.8` and `1.0`.
+    pub thresholds: Vec<f64>,
+}
+
+/// The usage of a budget crossed one of its thresholds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetAlert {
+    pub budget: Budget,
+    pub threshold: f64,
+    /// The fraction of the limit used, which can be past the threshold.
+    pub used: f64,
+}
+
+struct BudgetWatch {
+    budget: Budget,
+    on_threshold: Box<dyn FnMut(&BudgetAlert) + Send>,
+    /// The thresholds that were crossed in the current period, which the callback isn't called for again.
+    crossed: Vec<f64>,
+    day: Option<NaiveDate>,
+}
+
+/// Records the usage of completions.
+#[derive(Default)]
+pub struct UsageLedger {
+    pricing: PricingTable,
+    session: UsageSummary,
+    days: BTreeMap<NaiveDate, UsageSummary>,
+    request_usage: Option<ModelRequestUsage>,
+    budgets: Vec<BudgetWatch>,
+}
+
+impl UsageLedger {
+    pub fn new(pricing: PricingTable) -> Self {
+        Self {
+            pricing,
+            ..Self::default()
+        }
+    }
+
+    /// Calls `on_threshold` whenever the usage crosses one of the thresholds of `budget`, once per threshold and
+    /// period.
+    pub fn add_budget(
+        &mut self,
+        budget: Budget,
+        on_threshold: impl FnMut(&BudgetAlert) + Send + 'static,
+    ) {
+        self.budgets.push(BudgetWatch {
+            budget,
+            on_threshold: Box::new(on_threshold),
+            crossed: Vec::new(),
+            day: None,
+        });
+    }
+
+    /// Records the usage of a completion that finished today.
+    pub fn record(&mut self, model: &str, usage: &UsageDelta) {
+        self.record_on(Local::now().date_naive(), model, usage);
+    }
+
+    pub fn record_on(&mut self, day: NaiveDate, model: &str, usage: &UsageDelta) {
+        let cost = self.pricing.cost(model, usage);
+        self.session
+            .models
+            .entry(model.to_string())
+            .or_default()
+            .add(usage, cost);
+        self.days
+            .entry(day)
+            .or_default()
+            .models
+            .entry(model.to_string())
+            .or_default()
+            .add(usage, cost);
+        self.check_budgets(day);
+    }
+
+    /// Records the model requests used so far, as reported by the usage headers of a response.
+    pub fn record_request_usage(&mut self, usage: ModelRequestUsage) {
+        self.request_usage = Some(usage);
+    }
+
+    /// The model requests left before reaching the limit, or `None` when unlimited or unknown.
+    pub fn remaining_requests(&self) -> Option<u32> {
+        let usage = self.request_usage?;
+        match usage.limit {
+            UsageLimit::Limited { limit } => Some(limit.saturating_sub(usage.amount)),
+            UsageLimit::Unlimited => None,
+        }
+    }
+
+    pub fn session(&self) -> &UsageSummary {
+        &self.session
+    }
+
+    pub fn day(&self, day: NaiveDate) -> Option<&UsageSummary> {
+        self.days.get(&day)
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = (&NaiveDate, &UsageSummary)> {
+        self.days.iter()
+    }
+
+    fn check_budgets(&mut self, day: NaiveDate) {
+        for watch in &mut self.budgets {
+            let summary = match watch.budget.period {
+                BudgetPeriod::Session => &self.session,
+                BudgetPeriod::Day => {
+                    if watch.day != Some(day) {
+                        watch.day = Some(day);
+                        watch.crossed.clear();
+                    }
+                    &self.days[&day]
+                }
+            };
+            let total = summary.total();
+            let used = match watch.budget.limit {
+                BudgetLimit::Cost(limit) => total.cost / limit,
+                BudgetLimit::Tokens(limit) => total.total_tokens() as f64 / limit as f64,
+            };
+            for &threshold in &watch.budget.thresholds {
+                if used >= threshold && !watch.crossed.contains(&threshold) {
+                    watch.crossed.push(threshold);
+                    (watch.on_threshold)(&BudgetAlert {
+                        budget: watch.budget.clone(),
+                        threshold,
+                        used,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_usage_ledger() {
+        let mut pricing = PricingTable::default();
+        pricing.insert(
+            "claude-sonnet-4",
+            ModelPricing {
+                input_per_million: 3.0,
+                output_per_million: 15.0,
+                cache_write_per_million: Some(3.75),
+                cache_read_per_million: Some(0.3),
+            },
+        );
+        let mut ledger = UsageLedger::new(pricing);
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        ledger.add_budget(
+            Budget {
+                period: BudgetPeriod::Day,
+                limit: BudgetLimit::Cost(1.0),
+                thresholds: vec![0.5, 1.0],
+            },
+            {
+                let alerts = alerts.clone();
+                move |alert| alerts.lock().unwrap().push(alert.threshold)
+            },
+        );
+
+        let monday = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let tuesday = monday.succ_opt().unwrap();
+        let usage = UsageDelta {
+            input_tokens: 50_000,
+            output_tokens: 10_000,
+            ..UsageDelta::default()
+        };
+        ledger.record_on(monday, "claude-sonnet-4", &usage);
+        ledger.record_on(monday, "local-model", &usage);
+        assert_eq!(*alerts.lock().unwrap(), Vec::<f64>::new());
+        ledger.record_on(monday, "claude-sonnet-4", &usage);
+        assert_eq!(*alerts.lock().unwrap(), vec![0.5]);
+        ledger.record_on(monday, "claude-sonnet-4", &usage);
+        ledger.record_on(monday, "claude-sonnet-4", &usage);
+        assert_eq!(*alerts.lock().unwrap(), vec![0.5, 1.0]);
+        ledger.record_on(tuesday, "claude-sonnet-4", &usage);
+        ledger.record_on(tuesday, "claude-sonnet-4", &usage);
+        assert_eq!(*alerts.lock().unwrap(), vec![0.5, 1.0, 0.5]);
+
+        let session = ledger.session();
+        assert_eq!(session.models["claude-sonnet-4"].requests, 6);
+        assert_eq!(session.models["local-model"].cost, 0.0);
+        assert_eq!(session.total().input_tokens, 350_000);
+        assert!((ledger.day(monday).unwrap().total().cost - 1.2).abs() < 1e-9);
+
+        ledger.record_on(
+            tuesday,
+            "claude-sonnet-4",
+            &UsageDelta {
+                input_tokens: 1_000,
+                output_tokens: 1_000,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 100_000,
+            },
+        );
+        assert!((ledger.day(tuesday).unwrap().total().cost - 0.648).abs() < 1e-9);
+
+        assert_eq!(ledger.remaining_requests(), None);
+        ledger.record_request_usage(ModelRequestUsage {
+            amount: 480,
+            limit: UsageLimit::Limited { limit: 500 },
+        });
+        assert_eq!(ledger.remaining_requests(), Some(20));
+    }
+}
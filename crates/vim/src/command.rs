@@ -2418,6 +2418,7 @@ impl ShellExec {
                     command_label: command.clone(),
                     cwd,
                     env: HashMap::default(),
+                    task_env: HashMap::default(),
                     use_new_terminal: true,
                     allow_concurrent_runs: true,
                     reveal: RevealStrategy::NoFocus,
@@ -2427,6 +2428,10 @@ impl ShellExec {
                     show_summary: false,
                     show_command: false,
                     show_rerun: false,
+                    problem_matchers: Vec::new(),
+                    is_background: false,
+                    ready_pattern: None,
+                    execution_target: None,
                 };
 
                 let task_status = workspace.spawn_in_terminal(spawn_in_terminal, window, cx);
@@ -0,0 +1,213 @@
+//! A view of a binary file, showing its bytes in hexadecimal next to their printable characters.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use gpui::{
+    Entity, EventEmitter, FocusHandle, Focusable, Subscription, Task, UniformListScrollHandle,
+    uniform_list,
+};
+use language::File as _;
+use project::{
+    Project,
+    hex_buffer::{HexBuffer, HexBufferEvent},
+};
+use settings::Settings as _;
+use theme::ThemeSettings;
+use ui::{Window, prelude::*};
+
+use crate::{
+    Pane,
+    item::{Item, ItemBufferKind, ItemEvent, ProjectItem, SaveOptions},
+};
+
+pub struct HexBufferView {
+    hex_buffer: Entity<HexBuffer>,
+    error: Option<SharedString>,
+    focus_handle: FocusHandle,
+    scroll_handle: UniformListScrollHandle,
+    _subscription: Subscription,
+}
+
+impl HexBufferView {
+    pub fn new(hex_buffer: Entity<HexBuffer>, cx: &mut Context<Self>) -> Self {
+        let subscription = cx.subscribe(&hex_buffer, |this, _, event, cx| {
+            match event {
+                HexBufferEvent::DirtyChanged | HexBufferEvent::Saved => {
+                    this.error = None;
+                    cx.emit(());
+                }
+                HexBufferEvent::Edited => {}
+            }
+            cx.notify();
+        });
+        Self {
+            hex_buffer,
+            error: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: UniformListScrollHandle::new(),
+            _subscription: subscription,
+        }
+    }
+
+    fn render_rows(&self, range: Range<usize>, cx: &App) -> Vec<SharedString> {
+        let hex_buffer = self.hex_buffer.read(cx);
+        range
+            .filter_map(|row| hex_buffer.hex_row(row))
+            .map(SharedString::from)
+            .collect()
+    }
+
+    /// Shows the error of the given task in the view if it fails, then passes it on.
+    fn report_error(&self, task: Task<Result<()>>, cx: &mut Context<Self>) -> Task<Result<()>> {
+        cx.spawn(async move |this, cx| {
+            let result = task.await;
+            if let Err(error) = &result {
+                let error = SharedString::from(format!("{error:#}"));
+                this.update(cx, |this, cx| {
+                    this.error = Some(error);
+                    cx.notify();
+                })
+                .ok();
+            }
+            result
+        })
+    }
+}
+
+impl Item for HexBufferView {
+    type Event = ();
+
+    fn to_item_events(_: &Self::Event, mut f: impl FnMut(ItemEvent)) {
+        f(ItemEvent::UpdateTab);
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &App,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::ProjectItem),
+    ) {
+        f(self.hex_buffer.entity_id(), self.hex_buffer.read(cx))
+    }
+
+    fn tab_tooltip_text(&self, cx: &App) -> Option<SharedString> {
+        Some(
+            self.hex_buffer
+                .read(cx)
+                .abs_path()
+                .to_string_lossy()
+                .into_owned()
+                .into(),
+        )
+    }
+
+    fn tab_content_text(&self, _: usize, cx: &App) -> SharedString {
+        self.hex_buffer
+            .read(cx)
+            .file
+            .file_name(cx)
+            .to_string()
+            .into()
+    }
+
+    fn buffer_kind(&self, _: &App) -> ItemBufferKind {
+        ItemBufferKind::Singleton
+    }
+
+    fn is_dirty(&self, cx: &App) -> bool {
+        project::ProjectItem::is_dirty(self.hex_buffer.read(cx))
+    }
+
+    fn can_save(&self, _: &App) -> bool {
+        true
+    }
+
+    fn save(
+        &mut self,
+        _: SaveOptions,
+        _: Entity<Project>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let save = self
+            .hex_buffer
+            .update(cx, |hex_buffer, cx| hex_buffer.save(cx));
+        self.report_error(save, cx)
+    }
+
+    fn reload(
+        &mut self,
+        _: Entity<Project>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Task<Result<()>> {
+        let reload = self
+            .hex_buffer
+            .update(cx, |hex_buffer, cx| hex_buffer.reload(cx));
+        self.report_error(reload, cx)
+    }
+}
+
+impl ProjectItem for HexBufferView {
+    type Item = HexBuffer;
+
+    fn for_project_item(
+        _: Entity<Project>,
+        _: Option<&Pane>,
+        item: Entity<Self::Item>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        Self::new(item, cx)
+    }
+}
+
+impl EventEmitter<()> for HexBufferView {}
+
+impl Focusable for HexBufferView {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for HexBufferView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let hex_buffer = self.hex_buffer.read(cx);
+        let status = format!(
+            "{} bytes. This file is binary, so it is shown in hexadecimal.",
+            hex_buffer.bytes().len()
+        );
+        let row_count = hex_buffer.row_count();
+
+        v_flex()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .key_context("HexBufferView")
+            .child(
+                h_flex()
+                    .p_2()
+                    .gap_2()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .child(
+                        Label::new(status)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .when_some(self.error.clone(), |this, error| {
+                        this.child(Label::new(error).size(LabelSize::Small).color(Color::Error))
+                    }),
+            )
+            .child(
+                uniform_list(
+                    "hex-buffer-rows",
+                    row_count,
+                    cx.processor(|this, range: Range<usize>, _, cx| this.render_rows(range, cx)),
+                )
+                .flex_1()
+                .p_2()
+                .track_scroll(&self.scroll_handle)
+                .font(ThemeSettings::get_global(cx).buffer_font.clone()),
+            )
+    }
+}
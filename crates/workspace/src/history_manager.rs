@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use anyhow::Context as _;
+use collections::HashSet;
 use gpui::{AppContext, Entity, Global, MenuItem};
 use smallvec::SmallVec;
 use ui::App;
@@ -56,7 +58,7 @@ impl HistoryManager {
                 .collect::<Vec<_>>();
             this.update(cx, |this, cx| {
                 this.history = recent_folders;
-                this.update_jump_list(cx);
+                this.history_changed(cx);
             })
         })
         .detach();
@@ -76,7 +78,7 @@ impl HistoryManager {
             self.history.remove(pos);
         }
         self.history.push(entry);
-        self.update_jump_list(cx);
+        self.history_changed(cx);
     }
 
     pub fn delete_history(&mut self, id: WorkspaceId, cx: &App) {
@@ -84,7 +86,12 @@ impl HistoryManager {
             return;
         };
         self.history.remove(pos);
+        self.history_changed(cx);
+    }
+
+    fn history_changed(&mut self, cx: &App) {
         self.update_jump_list(cx);
+        self.save_recent_workspaces(cx);
     }
 
     fn update_jump_list(&mut self, cx: &App) {
@@ -93,7 +100,7 @@ impl HistoryManager {
             .history
             .iter()
             .rev()
-            .map(|entry| entry.path.clone())
+            .map(|entry| entry.compact_path())
             .collect::<Vec<_>>();
         let user_removed = cx.update_jump_list(menus, entries);
         self.remove_user_removed_workspaces(user_removed, cx);
@@ -110,7 +117,7 @@ impl HistoryManager {
         let mut deleted_ids = Vec::new();
         for idx in (0..self.history.len()).rev() {
             if let Some(entry) = self.history.get(idx)
-                && user_removed.contains(&entry.path)
+                && user_removed.contains(&entry.compact_path())
             {
                 deleted_ids.push(entry.id);
                 self.history.remove(idx);
@@ -123,14 +130,43 @@ impl HistoryManager {
         })
         .detach();
     }
+
+    /// Writes the paths of the history to [`paths::recent_workspaces_file`], for the CLI to complete.
+    fn save_recent_workspaces(&self, cx: &App) {
+        if cfg!(any(test, feature = "test-support")) {
+            return;
+        }
+        let mut contents = String::new();
+        let mut seen = HashSet::default();
+        for path in self.history.iter().rev().flat_map(|entry| &entry.path) {
+            if let Some(path) = path.to_str()
+                && !path.contains('\n')
+                && seen.insert(path)
+            {
+                contents.push_str(path);
+                contents.push('\n');
+            }
+        }
+        cx.background_spawn(async move {
+            std::fs::write(paths::recent_workspaces_file(), contents)
+                .context("failed to save the recent workspaces")
+                .log_err();
+        })
+        .detach();
+    }
 }
 
 impl HistoryManagerEntry {
     pub fn new(id: WorkspaceId, paths: &PathList) -> Self {
         let path = paths
             .ordered_paths()
-            .map(|path| path.compact())
+            .cloned()
             .collect::<SmallVec<[PathBuf; 2]>>();
         Self { id, path }
     }
+
+    /// The paths of the workspace, with the home directory shortened to `~`.
+    fn compact_path(&self) -> SmallVec<[PathBuf; 2]> {
+        self.path.iter().map(|path| path.compact()).collect()
+    }
 }
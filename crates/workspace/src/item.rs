@@ -13,7 +13,7 @@ use gpui::{
     EventEmitter, FocusHandle, Focusable, Font, HighlightStyle, Pixels, Point, Render,
     SharedString, Task, WeakEntity, Window,
 };
-use project::{Project, ProjectEntryId, ProjectPath};
+use project::{Project, ProjectEntryId, ProjectPath, buffer_store::AutosaveTrigger};
 pub use settings::{
     ActivateOnClose, ClosePosition, RegisterSetting, Settings, SettingsLocation, ShowCloseButton,
     ShowDiagnostics,
@@ -778,6 +778,7 @@ impl<T: Item> ItemHandle for Entity<T> {
                                         Pane::autosave_item(
                                             &item,
                                             workspace.project().clone(),
+                                            AutosaveTrigger::Delay,
                                             window,
                                             cx,
                                         )
@@ -808,8 +809,14 @@ impl<T: Item> ItemHandle for Entity<T> {
                         if !focus_handle.contains_focused(window, cx)
                             && !workspace.has_active_modal(window, cx)
                         {
-                            Pane::autosave_item(&item, workspace.project.clone(), window, cx)
-                                .detach_and_log_err(cx);
+                            Pane::autosave_item(
+                                &item,
+                                workspace.project.clone(),
+                                AutosaveTrigger::FocusChange,
+                                window,
+                                cx,
+                            )
+                            .detach_and_log_err(cx);
                         }
                     }
                 },
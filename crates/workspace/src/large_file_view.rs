@@ -0,0 +1,196 @@
+//! A read-only view of a file too large to be loaded into a buffer, showing one window of its
+//! contents at a time.
+
+use gpui::{Entity, EventEmitter, FocusHandle, Focusable, ScrollHandle, Task};
+use language::File as _;
+use project::{
+    Project,
+    large_file::{LARGE_FILE_WINDOW_SIZE, LargeFile, LargeFileWindow},
+};
+use settings::Settings as _;
+use theme::ThemeSettings;
+use ui::{Window, prelude::*};
+
+use crate::{
+    Pane,
+    item::{Item, ItemBufferKind, ProjectItem},
+};
+
+pub struct LargeFileView {
+    large_file: Entity<LargeFile>,
+    /// The window of the file that's shown, once it's read.
+    contents: Option<LargeFileWindow>,
+    error: Option<SharedString>,
+    focus_handle: FocusHandle,
+    scroll_handle: ScrollHandle,
+    _read_contents: Task<()>,
+}
+
+impl LargeFileView {
+    pub fn new(large_file: Entity<LargeFile>, cx: &mut Context<Self>) -> Self {
+        let mut this = Self {
+            large_file,
+            contents: None,
+            error: None,
+            focus_handle: cx.focus_handle(),
+            scroll_handle: ScrollHandle::new(),
+            _read_contents: Task::ready(()),
+        };
+        this.show_window(0, cx);
+        this
+    }
+
+    /// Reads and shows the window of the file starting at the given byte offset.
+    fn show_window(&mut self, offset: u64, cx: &mut Context<Self>) {
+        let read_window = self.large_file.read(cx).read_window(offset, cx);
+        self._read_contents = cx.spawn(async move |this, cx| {
+            let result = read_window.await;
+            this.update(cx, |this, cx| {
+                match result {
+                    Ok(contents) => {
+                        this.contents = Some(contents);
+                        this.error = None;
+                        this.scroll_handle.set_offset(Default::default());
+                    }
+                    Err(error) => this.error = Some(format!("{error:#}").into()),
+                }
+                cx.notify();
+            })
+            .ok();
+        });
+    }
+}
+
+impl Item for LargeFileView {
+    type Event = ();
+
+    fn for_each_project_item(
+        &self,
+        cx: &App,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::ProjectItem),
+    ) {
+        f(self.large_file.entity_id(), self.large_file.read(cx))
+    }
+
+    fn tab_tooltip_text(&self, cx: &App) -> Option<SharedString> {
+        Some(
+            self.large_file
+                .read(cx)
+                .abs_path()
+                .to_string_lossy()
+                .into_owned()
+                .into(),
+        )
+    }
+
+    fn tab_content_text(&self, _: usize, cx: &App) -> SharedString {
+        self.large_file
+            .read(cx)
+            .file
+            .file_name(cx)
+            .to_string()
+            .into()
+    }
+
+    fn buffer_kind(&self, _: &App) -> ItemBufferKind {
+        ItemBufferKind::Singleton
+    }
+}
+
+impl ProjectItem for LargeFileView {
+    type Item = LargeFile;
+
+    fn for_project_item(
+        _: Entity<Project>,
+        _: Option<&Pane>,
+        item: Entity<Self::Item>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        Self::new(item, cx)
+    }
+}
+
+impl EventEmitter<()> for LargeFileView {}
+
+impl Focusable for LargeFileView {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for LargeFileView {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let size = self.large_file.read(cx).size();
+        let range = self
+            .contents
+            .as_ref()
+            .map(|contents| contents.range.clone());
+        let status = match &range {
+            Some(range) => format!(
+                "Showing bytes {}–{} of {size}. Files this large are read-only.",
+                range.start, range.end
+            ),
+            None => format!("Reading {size} bytes…"),
+        };
+        let previous_offset = range
+            .as_ref()
+            .filter(|range| range.start > 0)
+            .map(|range| range.start.saturating_sub(LARGE_FILE_WINDOW_SIZE));
+        let next_offset = range
+            .as_ref()
+            .filter(|range| range.end < size)
+            .map(|range| range.end);
+
+        v_flex()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .key_context("LargeFileView")
+            .child(
+                h_flex()
+                    .p_2()
+                    .gap_2()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border_variant)
+                    .child(
+                        Label::new(status)
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+                    .child(div().flex_1())
+                    .child(
+                        Button::new("previous-window", "Previous")
+                            .disabled(previous_offset.is_none())
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                if let Some(offset) = previous_offset {
+                                    this.show_window(offset, cx);
+                                }
+                            })),
+                    )
+                    .child(
+                        Button::new("next-window", "Next")
+                            .disabled(next_offset.is_none())
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                if let Some(offset) = next_offset {
+                                    this.show_window(offset, cx);
+                                }
+                            })),
+                    ),
+            )
+            .child(
+                div()
+                    .id("large-file-contents")
+                    .flex_1()
+                    .p_2()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .font(ThemeSettings::get_global(cx).buffer_font.clone())
+                    .when_some(self.error.clone(), |this, error| {
+                        this.child(Label::new(error).color(Color::Error))
+                    })
+                    .when_some(self.contents.as_ref(), |this, contents| {
+                        this.child(SharedString::from(contents.text.clone()))
+                    }),
+            )
+    }
+}
@@ -28,7 +28,10 @@ use gpui::{
 use itertools::Itertools;
 use language::DiagnosticSeverity;
 use parking_lot::Mutex;
-use project::{DirectoryLister, Project, ProjectEntryId, ProjectPath, WorktreeId};
+use project::{
+    DirectoryLister, Project, ProjectEntryId, ProjectPath, WorktreeId,
+    buffer_store::AutosaveTrigger,
+};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use settings::{Settings, SettingsStore};
@@ -2337,14 +2340,24 @@ impl Pane {
     pub fn autosave_item(
         item: &dyn ItemHandle,
         project: Entity<Project>,
+        trigger: AutosaveTrigger,
         window: &mut Window,
         cx: &mut App,
     ) -> Task<Result<()>> {
-        let format = !matches!(
-            item.workspace_settings(cx).autosave,
-            AutosaveSetting::AfterDelay { .. }
-        );
-        if item.can_autosave(cx) {
+        let autosave = item.workspace_settings(cx).autosave;
+        if !trigger.is_enabled(autosave) {
+            return Task::ready(Ok(()));
+        }
+        let format = !matches!(autosave, AutosaveSetting::AfterDelay { .. });
+        let is_excluded = {
+            let buffer_store = project.read(cx).buffer_store().read(cx);
+            item.project_paths(cx).iter().any(|path| {
+                buffer_store
+                    .get_by_path(path)
+                    .is_some_and(|buffer| buffer_store.is_autosave_excluded(buffer.read(cx), cx))
+            })
+        };
+        if item.can_autosave(cx) && !is_excluded {
             item.save(
                 SaveOptions {
                     format,
@@ -2419,6 +2432,30 @@ impl Pane {
         Some(())
     }
 
+    /// Closes the inactive items of a buffer the project evicted to stay within its memory
+    /// budget, so that the buffer is released. The items can be reopened from the history.
+    pub fn handle_evicted_project_item(
+        &mut self,
+        project_path: &ProjectPath,
+        window: &mut Window,
+        cx: &mut Context<Pane>,
+    ) {
+        let active_item_id = self.active_item().map(|item| item.item_id());
+        let item_ids = self
+            .items()
+            .filter(|item| {
+                Some(item.item_id()) != active_item_id
+                    && item.buffer_kind(cx) == ItemBufferKind::Singleton
+                    && item.project_path(cx).as_ref() == Some(project_path)
+            })
+            .map(|item| item.item_id())
+            .collect::<Vec<_>>();
+        for item_id in item_ids {
+            self.close_item_by_id(item_id, SaveIntent::Close, window, cx)
+                .detach_and_log_err(cx);
+        }
+    }
+
     fn update_toolbar(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         let active_item = self
             .items
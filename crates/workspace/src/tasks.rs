@@ -1,11 +1,22 @@
-use std::process::ExitStatus;
+use std::{process::ExitStatus, time::Instant};
 
 use anyhow::Result;
-use gpui::{AppContext, Context, Entity, Task};
+use futures::{
+    FutureExt as _,
+    future::{LocalBoxFuture, Shared, join_all},
+};
+use gpui::{AppContext, AsyncWindowContext, Context, Entity, Task, WeakEntity};
 use language::Buffer;
-use project::{TaskSourceKind, WorktreeId};
-use task::{DebugScenario, ResolvedTask, SpawnInTerminal, TaskContext, TaskTemplate};
+use project::{
+    ExecutionPlan, TaskSourceKind, WorktreeId,
+    task_store::{BackgroundTaskStatus, TaskStore, TaskStoreEvent},
+};
+use task::{
+    DebugScenario, DependencyFailurePolicy, DependsOrder, ResolvedTask, SpawnInTerminal,
+    TaskContext, TaskTemplate,
+};
 use ui::Window;
+use util::post_inc;
 
 use crate::{Toast, Workspace, notifications::NotificationId};
 
@@ -41,6 +52,21 @@ impl Workspace {
         cx: &mut Context<Workspace>,
     ) {
         let spawn_in_terminal = resolved_task.resolved.clone();
+        let task_to_run = (task_source_kind.clone(), resolved_task.clone());
+        let has_dependencies = !resolved_task.original_task().depends_on.is_empty();
+        let execution_plan = has_dependencies.then(|| {
+            let project = self.project().read(cx);
+            let active_buffer = self
+                .active_item(cx)
+                .and_then(|item| item.project_path(cx))
+                .and_then(|project_path| project.get_open_buffer(&project_path, cx));
+            project.task_store().read(cx).execution_plan(
+                task_source_kind.clone(),
+                resolved_task.clone(),
+                active_buffer,
+                cx,
+            )
+        });
         if !omit_history {
             if let Some(debugger_provider) = self.debugger_provider.as_ref() {
                 debugger_provider.task_scheduled(cx);
@@ -57,11 +83,42 @@ impl Workspace {
             });
         }
 
-        if let Some(terminal_provider) = self.terminal_provider.as_ref() {
-            let task_status = terminal_provider.spawn(spawn_in_terminal, window, cx);
+        if let Some(execution_plan) = execution_plan {
+            let task = cx.spawn_in(window, async move |workspace, cx| {
+                let plan = match execution_plan.await {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        log::error!("Failed to resolve task dependencies: {e:#}");
+                        workspace
+                            .update(cx, |workspace, cx| {
+                                let id = NotificationId::unique::<ResolvedTask>();
+                                workspace.show_toast(
+                                    Toast::new(id, format!("Task dependencies failed: {e}")),
+                                    cx,
+                                );
+                            })
+                            .ok();
+                        return;
+                    }
+                };
+                run_execution_plan(plan, workspace, cx).await;
+            });
+            self.track_scheduled_task(task, cx);
+            return;
+        }
 
-            let task = cx.spawn(async |w, cx| {
-                let res = cx.background_spawn(task_status).await;
+        let (task_source_kind, resolved_task) = task_to_run;
+        if spawn_in_terminal.is_background {
+            self.run_background_task(task_source_kind, resolved_task, window, cx)
+                .detach_and_log_err(cx);
+            return;
+        }
+
+        if self.terminal_provider.is_some() {
+            let task_status = self.spawn_recorded_task(task_source_kind, resolved_task, window, cx);
+
+            let task = cx.spawn(async move |w, cx| {
+                let res = task_status.await;
                 match res {
                     Some(Ok(status)) => {
                         if status.success() {
@@ -80,7 +137,64 @@ impl Workspace {
                     None => log::debug!("Task spawn got cancelled"),
                 };
             });
-            self.scheduled_tasks.push(task);
+            self.track_scheduled_task(task, cx);
+        }
+    }
+
+    /// Keeps a task running for as long as the workspace, and forgets it once it completes.
+    fn track_scheduled_task(&mut self, task: Task<()>, cx: &mut Context<Self>) {
+        let id = post_inc(&mut self.next_scheduled_task_id);
+        let task = cx.spawn(async move |workspace, cx| {
+            task.await;
+            workspace
+                .update(cx, |workspace, _| workspace.scheduled_tasks.remove(&id))
+                .ok();
+        });
+        self.scheduled_tasks.insert(id, task);
+    }
+
+    pub(crate) fn on_task_store_event(
+        &mut self,
+        _: &Entity<TaskStore>,
+        event: &TaskStoreEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            TaskStoreEvent::TaskTriggered {
+                task_source_kind,
+                task,
+                slot,
+            } => {
+                log::debug!(
+                    "Running task `{}` from {task_source_kind:?}, triggered by a save",
+                    task.display_label()
+                );
+                let task_status =
+                    self.spawn_recorded_task(task_source_kind.clone(), task.clone(), window, cx);
+                let task = task.clone();
+                let slot = slot.clone();
+                let task_run = cx.spawn(async move |_, _| {
+                    if let Some(Err(e)) = task_status.await {
+                        log::error!("Task `{}` failed: {e:#}", task.display_label());
+                    }
+                    drop(slot);
+                });
+                self.track_scheduled_task(task_run, cx);
+            }
+            TaskStoreEvent::RerunRequested {
+                task_source_kind,
+                task,
+            } => {
+                self.schedule_resolved_task(
+                    task_source_kind.clone(),
+                    task.clone(),
+                    false,
+                    window,
+                    cx,
+                );
+            }
+            TaskStoreEvent::HeadlessTaskOutput { .. } => {}
         }
     }
 
@@ -105,6 +219,100 @@ impl Workspace {
         }
     }
 
+    /// Runs a background task, restarting it with backoff when it fails, and resolves once it is ready.
+    ///
+    /// A background task that is already running is not started again: this waits until it is ready instead.
+    pub fn run_background_task(
+        &mut self,
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) -> Task<Result<()>> {
+        let spawn_in_terminal = task.resolved.clone();
+        let task_store = self.project().read(cx).task_store().clone();
+        let running = task_store
+            .read(cx)
+            .background_task_status(&spawn_in_terminal.id)
+            .is_some_and(|status| status != BackgroundTaskStatus::Stopped);
+        if running {
+            return task_store.update(cx, |task_store, cx| {
+                task_store.wait_until_ready(&spawn_in_terminal.id, cx)
+            });
+        }
+        let ready = task_store.update(cx, |task_store, cx| {
+            task_store.background_task_started(&spawn_in_terminal, cx);
+            task_store.wait_until_ready(&spawn_in_terminal.id, cx)
+        });
+        let supervisor = cx.spawn_in(window, async move |workspace, cx| {
+            loop {
+                let Ok(task_status) = workspace.update_in(cx, |workspace, window, cx| {
+                    workspace.spawn_recorded_task(
+                        task_source_kind.clone(),
+                        task.clone(),
+                        window,
+                        cx,
+                    )
+                }) else {
+                    break;
+                };
+                let failed = match task_status.await {
+                    Some(Ok(status)) => !status.success(),
+                    Some(Err(e)) => {
+                        log::error!(
+                            "Background task `{}` failed: {e:#}",
+                            spawn_in_terminal.full_label
+                        );
+                        true
+                    }
+                    None => false,
+                };
+                let Ok(Some(restart_delay)) = task_store.update(cx, |task_store, cx| {
+                    task_store.background_task_exited(&spawn_in_terminal.id, failed, cx)
+                }) else {
+                    break;
+                };
+                log::info!(
+                    "Restarting background task `{}` in {restart_delay:?}",
+                    spawn_in_terminal.full_label
+                );
+                cx.background_executor().timer(restart_delay).await;
+            }
+        });
+        self.track_scheduled_task(supervisor, cx);
+        ready
+    }
+
+    /// Spawns a task in a terminal, adding its run to the task history once it exits.
+    fn spawn_recorded_task(
+        &mut self,
+        task_source_kind: TaskSourceKind,
+        task: ResolvedTask,
+        window: &mut Window,
+        cx: &mut Context<Workspace>,
+    ) -> Task<Option<Result<ExitStatus>>> {
+        let task_status = self.spawn_in_terminal(task.resolved.clone(), window, cx);
+        let task_store = self.project().read(cx).task_store().downgrade();
+        let started_at = Instant::now();
+        cx.spawn(async move |_, cx| {
+            let status = task_status.await;
+            if let Some(Ok(exit_status)) = &status {
+                task_store
+                    .update(cx, |task_store, cx| {
+                        task_store.task_run_finished(
+                            &task_source_kind,
+                            &task,
+                            exit_status.code(),
+                            started_at.elapsed(),
+                            cx,
+                        )
+                    })
+                    .ok();
+            }
+            status
+        })
+    }
+
     pub fn spawn_in_terminal(
         self: &mut Workspace,
         spawn_in_terminal: SpawnInTerminal,
@@ -118,3 +326,96 @@ impl Workspace {
         }
     }
 }
+
+/// Runs the steps of the plan, each once its dependencies are done, and returns whether the last one succeeded.
+///
+/// Background tasks are done once they are ready, and keep running afterwards.
+async fn run_execution_plan(
+    plan: ExecutionPlan,
+    workspace: WeakEntity<Workspace>,
+    cx: &mut AsyncWindowContext,
+) -> bool {
+    let mut steps: Vec<Shared<LocalBoxFuture<'static, bool>>> = Vec::new();
+    for step in plan.steps {
+        let dependencies = step
+            .dependencies
+            .iter()
+            .filter_map(|ix| steps.get(*ix).cloned())
+            .collect::<Vec<_>>();
+        let workspace = workspace.clone();
+        let mut cx = cx.clone();
+        let step = async move {
+            let template = step.task.original_task();
+            let abort_on_failure = template.on_dependency_failure == DependencyFailurePolicy::Abort;
+            let dependencies_succeeded = match template.depends_order {
+                DependsOrder::Sequence => {
+                    let mut succeeded = true;
+                    for dependency in dependencies {
+                        if !dependency.await {
+                            succeeded = false;
+                            if abort_on_failure {
+                                break;
+                            }
+                        }
+                    }
+                    succeeded
+                }
+                DependsOrder::Parallel => join_all(dependencies)
+                    .await
+                    .into_iter()
+                    .all(|succeeded| succeeded),
+            };
+            if !dependencies_succeeded && abort_on_failure {
+                log::debug!(
+                    "Not running task `{}`, as one of its dependencies failed",
+                    step.task.display_label()
+                );
+                return false;
+            }
+
+            if step.task.resolved.is_background {
+                let Ok(ready) = workspace.update_in(&mut cx, |workspace, window, cx| {
+                    workspace.run_background_task(
+                        step.task_source_kind.clone(),
+                        step.task.clone(),
+                        window,
+                        cx,
+                    )
+                }) else {
+                    return false;
+                };
+                return match ready.await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::error!("Task `{}` failed: {e:#}", step.task.display_label());
+                        false
+                    }
+                };
+            }
+
+            let Ok(task_status) = workspace.update_in(&mut cx, |workspace, window, cx| {
+                workspace.spawn_recorded_task(
+                    step.task_source_kind.clone(),
+                    step.task.clone(),
+                    window,
+                    cx,
+                )
+            }) else {
+                return false;
+            };
+            match task_status.await {
+                Some(Ok(status)) => status.success(),
+                Some(Err(e)) => {
+                    log::error!("Task `{}` failed: {e:#}", step.task.display_label());
+                    false
+                }
+                None => false,
+            }
+        };
+        steps.push(step.boxed_local().shared());
+    }
+    match steps.pop() {
+        Some(root) => root.await,
+        None => false,
+    }
+}
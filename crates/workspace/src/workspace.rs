@@ -1,7 +1,9 @@
 pub mod dock;
+pub mod hex_buffer_view;
 pub mod history_manager;
 pub mod invalid_item_view;
 pub mod item;
+pub mod large_file_view;
 mod modal_layer;
 pub mod notifications;
 pub mod pane;
@@ -71,6 +73,7 @@ pub use persistence::{
 use project::{
     DirectoryLister, Project, ProjectEntryId, ProjectPath, ResolvedPath, Worktree, WorktreeId,
     WorktreeSettings,
+    buffer_store::AutosaveTrigger,
     debugger::{breakpoint_store::BreakpointStoreEvent, session::ThreadStatus},
     project_settings::ProjectSettings,
     toolchain_store::ToolchainStoreEvent,
@@ -579,6 +582,8 @@ pub fn init(app_state: Arc<AppState>, cx: &mut App) {
     theme_preview::init(cx);
     toast_layer::init(cx);
     history_manager::init(cx);
+    register_project_item::<large_file_view::LargeFileView>(cx);
+    register_project_item::<hex_buffer_view::HexBufferView>(cx);
 
     cx.on_action(|_: &CloseWindow, cx| Workspace::close_global(cx))
         .on_action(|_: &Reload, cx| reload(cx))
@@ -1070,13 +1075,20 @@ pub struct Workspace {
     serializable_items_tx: UnboundedSender<Box<dyn SerializableItemHandle>>,
     _items_serializer: Task<Result<()>>,
     session_id: Option<String>,
-    scheduled_tasks: Vec<Task<()>>,
+    /// The tasks run for the task system, which are dropped with the workspace, and forgotten once they complete.
+    scheduled_tasks: HashMap<usize, Task<()>>,
+    next_scheduled_task_id: usize,
     last_open_dock_positions: Vec<DockPosition>,
     removing: bool,
     utility_panes: UtilityPaneState,
     next_modal_placement: Option<ModalPlacement>,
+    /// When the window of the workspace was last activated, relative to the windows of the other workspaces.
+    window_activation_order: usize,
 }
 
+/// Counts the activations of workspace windows, to order the windows by when they were last activated.
+static WINDOW_ACTIVATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 impl EventEmitter<Event> for Workspace {}
 
 impl Workspace {
@@ -1168,6 +1180,14 @@ impl Workspace {
                     }
                 }
 
+                project::Event::BufferEvicted { project_path, .. } => {
+                    for pane in this.panes.iter() {
+                        pane.update(cx, |pane, cx| {
+                            pane.handle_evicted_project_item(project_path, window, cx)
+                        });
+                    }
+                }
+
                 project::Event::Toast {
                     notification_id,
                     message,
@@ -1367,6 +1387,11 @@ impl Workspace {
                     store.workspaces.remove(&window_handle);
                 })
             }),
+            cx.subscribe_in(
+                &project.read(cx).task_store().clone(),
+                window,
+                Self::on_task_store_event,
+            ),
         ];
 
         cx.defer_in(window, move |this, window, cx| {
@@ -1420,11 +1445,14 @@ impl Workspace {
             _items_serializer,
             session_id: Some(session_id),
 
-            scheduled_tasks: Vec::new(),
+            scheduled_tasks: HashMap::default(),
+            next_scheduled_task_id: 0,
             last_open_dock_positions: Vec::new(),
             removing: false,
             utility_panes: UtilityPaneState::default(),
             next_modal_placement: None,
+            window_activation_order: WINDOW_ACTIVATION_COUNT
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
         }
     }
 
@@ -1448,6 +1476,7 @@ impl Workspace {
             app_state.fs.clone(),
             env,
             true,
+            true,
             cx,
         );
 
@@ -4224,8 +4253,14 @@ impl Workspace {
     ) {
         cx.emit(Event::ActiveItemChanged);
         let active_entry = self.active_project_path(cx);
+        let visible_paths = self
+            .panes
+            .iter()
+            .filter_map(|pane| pane.read(cx).active_item()?.project_path(cx))
+            .collect::<Vec<_>>();
         self.project.update(cx, |project, cx| {
-            project.set_active_path(active_entry.clone(), cx)
+            project.set_active_path(active_entry.clone(), cx);
+            project.set_visible_paths(visible_paths, cx);
         });
 
         if focus_changed && let Some(project_path) = &active_entry {
@@ -4369,6 +4404,8 @@ impl Workspace {
 
     pub fn on_window_activation_changed(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         if window.is_window_active() {
+            self.window_activation_order =
+                WINDOW_ACTIVATION_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             if let Some(database_id) = self.database_id {
                 cx.background_spawn(persistence::DB.update_timestamp(database_id))
                     .detach();
@@ -4384,8 +4421,14 @@ impl Workspace {
                             item.workspace_settings(cx).autosave,
                             AutosaveSetting::OnWindowChange | AutosaveSetting::OnFocusChange
                         ) {
-                            Pane::autosave_item(item.as_ref(), self.project.clone(), window, cx)
-                                .detach_and_log_err(cx);
+                            Pane::autosave_item(
+                                item.as_ref(),
+                                self.project.clone(),
+                                AutosaveTrigger::WindowChange,
+                                window,
+                                cx,
+                            )
+                            .detach_and_log_err(cx);
                         }
                     }
                 });
@@ -6402,6 +6445,25 @@ fn activate_any_workspace_window(cx: &mut AsyncApp) -> Option<WindowHandle<Works
     .flatten()
 }
 
+/// Returns the local workspace windows ordered front to back, by when they were last activated, which is
+/// deterministic even on platforms that don't report the order of the windows.
+pub fn local_workspace_windows_front_to_back(cx: &App) -> Vec<WindowHandle<Workspace>> {
+    let mut windows = local_workspace_windows(cx);
+    windows.sort_by_cached_key(|window| {
+        std::cmp::Reverse(
+            window
+                .read(cx)
+                .map_or(0, |workspace| workspace.window_activation_order),
+        )
+    });
+    windows
+}
+
+/// Returns the local workspace window that was activated last.
+pub fn frontmost_local_workspace_window(cx: &App) -> Option<WindowHandle<Workspace>> {
+    local_workspace_windows_front_to_back(cx).into_iter().next()
+}
+
 pub fn local_workspace_windows(cx: &App) -> Vec<WindowHandle<Workspace>> {
     cx.windows()
         .into_iter()
@@ -6455,7 +6517,7 @@ pub fn open_paths(
                 .collect::<Vec<_>>();
 
             cx.update(|cx| {
-                for window in local_workspace_windows(cx) {
+                for window in local_workspace_windows_front_to_back(cx) {
                     if let Ok(workspace) = window.read(cx) {
                         let m = workspace.project.read(cx).visibility_for_paths(
                             &abs_paths,
@@ -6467,6 +6529,7 @@ pub fn open_paths(
                             existing = Some(window);
                             best_match = m;
                         } else if best_match.is_none()
+                            && existing.is_none()
                             && open_options.open_new_workspace == Some(false)
                         {
                             existing = Some(window)
@@ -6492,7 +6555,7 @@ pub fn open_paths(
                             return;
                         }
                     }
-                    for window in local_workspace_windows(cx) {
+                    for window in local_workspace_windows_front_to_back(cx) {
                         if let Ok(workspace) = window.read(cx) {
                             let project = workspace.project().read(cx);
                             if project.is_via_collab() {
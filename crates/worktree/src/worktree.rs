@@ -33,7 +33,7 @@ use parking_lot::Mutex;
 use paths::{local_settings_folder_name, local_vscode_folder_name};
 use postage::{barrier, prelude::Stream as _, watch};
 pub use settings::WorktreeId;
-use settings::{Settings, SettingsLocation, SettingsStore};
+use settings::{Settings, SettingsLocation, SettingsStore, SymlinkPolicy};
 use smallvec::{SmallVec, smallvec};
 use smol::channel::{self, Sender};
 use std::{
@@ -106,6 +106,18 @@ pub struct LoadedBinaryFile {
     pub content: Vec<u8>,
 }
 
+/// The error of loading a file whose contents look binary as text.
+#[derive(Debug)]
+pub struct BinaryFileError;
+
+impl fmt::Display for BinaryFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Binary files are not supported")
+    }
+}
+
+impl std::error::Error for BinaryFileError {}
+
 impl fmt::Debug for LoadedBinaryFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LoadedBinaryFile")
@@ -119,6 +131,7 @@ pub struct LocalWorktree {
     snapshot: LocalSnapshot,
     scan_requests_tx: channel::Sender<ScanRequest>,
     path_prefixes_to_scan_tx: channel::Sender<PathPrefixScanRequest>,
+    watch_exclusions_tx: channel::Sender<PathMatcher>,
     is_scanning: (watch::Sender<bool>, watch::Receiver<bool>),
     _background_scanner_tasks: Vec<Task<()>>,
     fs: Arc<dyn Fs>,
@@ -128,6 +141,9 @@ pub struct LocalWorktree {
     settings: WorktreeSettings,
     share_private_files: bool,
     scanning_enabled: bool,
+    /// Paths whose ancestor directories are scanned ahead of the rest of the worktree.
+    priority_paths: Arc<Mutex<Vec<Arc<RelPath>>>>,
+    priority_path_requests_tx: channel::Sender<Arc<RelPath>>,
 }
 
 pub struct PathPrefixScanRequest {
@@ -242,6 +258,8 @@ struct BackgroundScannerState {
     removed_entries: HashMap<u64, Entry>,
     changed_paths: Vec<Arc<RelPath>>,
     prev_snapshot: Snapshot,
+    /// Directories matching these globs are scanned, but not watched.
+    watch_exclusions: PathMatcher,
 }
 
 #[derive(Debug, Clone)]
@@ -321,6 +339,18 @@ enum ScanState {
     RootUpdated {
         new_path: Arc<SanitizedPath>,
     },
+    Progress(ScanProgress),
+    SymlinkEscapedRoot {
+        path: Arc<RelPath>,
+        target: Arc<Path>,
+    },
+}
+
+/// How far along the background scan of a local worktree is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub scanned_directories: usize,
+    pub queued_directories: usize,
 }
 
 #[derive(Clone)]
@@ -328,6 +358,12 @@ pub enum Event {
     UpdatedEntries(UpdatedEntriesSet),
     UpdatedGitRepositories(UpdatedGitRepositoriesSet),
     DeletedEntry(ProjectEntryId),
+    ScanProgress(ScanProgress),
+    /// A symlink was found whose target is outside of the worktree root.
+    SymlinkEscapedRoot {
+        path: Arc<RelPath>,
+        target: Arc<Path>,
+    },
 }
 
 impl EventEmitter<Event> for Worktree {}
@@ -400,8 +436,19 @@ impl Worktree {
                 let Self::Local(local_worktree) = this;
                 let settings = WorktreeSettings::get(settings_location, cx).clone();
                 if local_worktree.settings != settings {
+                    let only_watch_exclusions_changed = WorktreeSettings {
+                        watch_exclusions: settings.watch_exclusions.clone(),
+                        ..local_worktree.settings.clone()
+                    } == settings;
                     local_worktree.settings = settings;
-                    local_worktree.restart_background_scanners(cx);
+                    if only_watch_exclusions_changed {
+                        local_worktree
+                            .watch_exclusions_tx
+                            .try_send(local_worktree.settings.watch_exclusions.clone())
+                            .ok();
+                    } else {
+                        local_worktree.restart_background_scanners(cx);
+                    }
                 }
             })
             .detach();
@@ -436,12 +483,15 @@ impl Worktree {
                 is_scanning: watch::channel_with(true),
                 scan_requests_tx,
                 path_prefixes_to_scan_tx,
+                watch_exclusions_tx: channel::unbounded().0,
                 _background_scanner_tasks: Vec::new(),
                 fs,
                 fs_case_sensitive,
                 visible,
                 settings,
                 scanning_enabled,
+                priority_paths: Default::default(),
+                priority_path_requests_tx: channel::unbounded().0,
             };
             worktree.start_background_scanner(scan_requests_rx, path_prefixes_to_scan_rx, cx);
             Worktree::Local(worktree)
@@ -542,7 +592,25 @@ impl Worktree {
     ) -> Task<Result<Arc<File>>> {
         match self {
             Worktree::Local(this) => {
-                this.write_file(path, text, line_ending, encoding, has_bom, cx)
+                this.write_file(path, text, line_ending, encoding, has_bom, false, cx)
+            }
+        }
+    }
+
+    /// Writes a file with elevated privileges, for files that the current user isn't allowed
+    /// to write.
+    pub fn write_file_elevated(
+        &self,
+        path: Arc<RelPath>,
+        text: Rope,
+        line_ending: LineEnding,
+        encoding: &'static Encoding,
+        has_bom: bool,
+        cx: &Context<Worktree>,
+    ) -> Task<Result<Arc<File>>> {
+        match self {
+            Worktree::Local(this) => {
+                this.write_file(path, text, line_ending, encoding, has_bom, true, cx)
             }
         }
     }
@@ -713,6 +781,11 @@ impl LocalWorktree {
         let fs = self.fs.clone();
         let scanning_enabled = self.scanning_enabled;
         let settings = self.settings.clone();
+        let priority_paths = self.priority_paths.clone();
+        let (priority_path_requests_tx, priority_path_requests_rx) = channel::unbounded();
+        self.priority_path_requests_tx = priority_path_requests_tx;
+        let (watch_exclusions_tx, watch_exclusions_rx) = channel::unbounded();
+        self.watch_exclusions_tx = watch_exclusions_tx;
         let (scan_states_tx, mut scan_states_rx) = mpsc::unbounded();
         let background_scanner = cx.background_spawn({
             let abs_path = snapshot.abs_path.as_path().to_path_buf();
@@ -728,6 +801,7 @@ impl LocalWorktree {
                     true
                 });
 
+                let (priority_scan_jobs_tx, priority_scan_jobs_rx) = channel::unbounded();
                 let mut scanner = BackgroundScanner {
                     fs,
                     fs_case_sensitive,
@@ -735,6 +809,7 @@ impl LocalWorktree {
                     executor: background,
                     scan_requests_rx,
                     path_prefixes_to_scan_rx,
+                    watch_exclusions_rx,
                     next_entry_id,
                     state: async_lock::Mutex::new(BackgroundScannerState {
                         prev_snapshot: snapshot.snapshot.clone(),
@@ -744,12 +819,19 @@ impl LocalWorktree {
                         paths_to_scan: Default::default(),
                         removed_entries: Default::default(),
                         changed_paths: Default::default(),
+                        watch_exclusions: settings.watch_exclusions.clone(),
                     }),
                     phase: BackgroundScannerPhase::InitialScan,
                     share_private_files,
                     scanning_enabled,
                     settings,
                     watcher,
+                    priority_paths,
+                    priority_path_requests_rx,
+                    priority_scan_jobs_tx,
+                    priority_scan_jobs_rx,
+                    claimed_dirs: Default::default(),
+                    scanned_dir_count: AtomicUsize::new(0),
                 };
 
                 scanner
@@ -772,12 +854,21 @@ impl LocalWorktree {
                             scanning,
                         } => {
                             *this.is_scanning.0.borrow_mut() = scanning;
+                            if !scanning {
+                                this.priority_paths.lock().clear();
+                            }
                             this.set_snapshot(snapshot, changes, cx);
                             drop(barrier);
                         }
                         ScanState::RootUpdated { new_path } => {
                             this.update_abs_path_and_refresh(new_path, cx);
                         }
+                        ScanState::Progress(progress) => {
+                            cx.emit(Event::ScanProgress(progress));
+                        }
+                        ScanState::SymlinkEscapedRoot { path, target } => {
+                            cx.emit(Event::SymlinkEscapedRoot { path, target });
+                        }
                     }
                 })
                 .ok();
@@ -1114,6 +1205,7 @@ impl LocalWorktree {
         line_ending: LineEnding,
         encoding: &'static Encoding,
         has_bom: bool,
+        elevated: bool,
         cx: &Context<Worktree>,
     ) -> Task<Result<Arc<File>>> {
         let fs = self.fs.clone();
@@ -1126,7 +1218,7 @@ impl LocalWorktree {
             async move {
                 // For UTF-8, use the optimized `fs.save` which writes Rope chunks directly to disk
                 // without allocating a contiguous string.
-                if encoding == encoding_rs::UTF_8 && !has_bom {
+                if encoding == encoding_rs::UTF_8 && !has_bom && !elevated {
                     return fs.save(&abs_path, &text, line_ending).await;
                 }
 
@@ -1183,7 +1275,11 @@ impl LocalWorktree {
                     }
                 };
 
-                fs.write(&abs_path, &bytes).await
+                if elevated {
+                    fs.write_elevated(&abs_path, &bytes).await
+                } else {
+                    fs.write(&abs_path, &bytes).await
+                }
             }
         });
 
@@ -1367,6 +1463,7 @@ impl LocalWorktree {
         cx: &Context<Worktree>,
     ) -> Option<Task<Result<()>>> {
         let path = self.entry_for_id(entry_id)?.path.clone();
+        self.prioritize_path_for_scan(path.clone());
         let mut refresh = self.refresh_entries_for_paths(vec![path]);
         Some(cx.background_spawn(async move {
             refresh.next().await;
@@ -1406,6 +1503,22 @@ impl LocalWorktree {
         self.refresh_entries_for_paths(paths)
     }
 
+    /// Scans the directories leading to `path` ahead of the rest of the worktree, so that
+    /// it becomes available before a long-running scan completes.
+    ///
+    /// Directories that are already queued are moved ahead of the queue together with their
+    /// contents.
+    pub fn prioritize_path_for_scan(&self, path: Arc<RelPath>) {
+        if !*self.is_scanning.1.borrow() {
+            return;
+        }
+        let mut priority_paths = self.priority_paths.lock();
+        if !priority_paths.contains(&path) {
+            priority_paths.push(path.clone());
+            self.priority_path_requests_tx.try_send(path).ok();
+        }
+    }
+
     pub fn add_path_prefix_to_scan(&self, path_prefix: Arc<RelPath>) -> barrier::Receiver {
         let (tx, rx) = barrier::channel();
         self.path_prefixes_to_scan_tx
@@ -1468,6 +1581,20 @@ impl LocalWorktree {
         self.snapshot.update_abs_path(new_path, root_name);
         self.restart_background_scanners(cx);
     }
+
+    /// Like [`Self::update_abs_path_and_refresh`], but also moves the worktree to another
+    /// file system, whose contents at `new_path` must match the current ones.
+    pub fn update_fs_and_abs_path_and_refresh(
+        &mut self,
+        fs: Arc<dyn Fs>,
+        fs_case_sensitive: bool,
+        new_path: Arc<SanitizedPath>,
+        cx: &Context<Worktree>,
+    ) {
+        self.fs = fs;
+        self.fs_case_sensitive = fs_case_sensitive;
+        self.update_abs_path_and_refresh(new_path, cx);
+    }
 }
 
 #[cfg(feature = "collab")]
@@ -2325,6 +2452,11 @@ impl LocalSnapshot {
 }
 
 impl BackgroundScannerState {
+    fn is_path_watch_excluded(&self, path: &RelPath) -> bool {
+        path.ancestors()
+            .any(|ancestor| self.watch_exclusions.is_match(ancestor))
+    }
+
     fn should_scan_directory(&self, entry: &Entry) -> bool {
         (!entry.is_external && (!entry.is_ignored || entry.is_always_included))
             || entry.path.file_name() == Some(DOT_GIT)
@@ -3234,12 +3366,21 @@ struct BackgroundScanner {
     executor: BackgroundExecutor,
     scan_requests_rx: channel::Receiver<ScanRequest>,
     path_prefixes_to_scan_rx: channel::Receiver<PathPrefixScanRequest>,
+    watch_exclusions_rx: channel::Receiver<PathMatcher>,
     next_entry_id: Arc<AtomicUsize>,
     phase: BackgroundScannerPhase,
     watcher: Arc<dyn Watcher>,
     settings: WorktreeSettings,
     share_private_files: bool,
     scanning_enabled: bool,
+    priority_paths: Arc<Mutex<Vec<Arc<RelPath>>>>,
+    priority_path_requests_rx: channel::Receiver<Arc<RelPath>>,
+    priority_scan_jobs_tx: Sender<ScanJob>,
+    priority_scan_jobs_rx: channel::Receiver<ScanJob>,
+    /// The directories scanned by the current `scan_dirs` call, so that a directory queued
+    /// both normally and with priority is only scanned once.
+    claimed_dirs: Mutex<HashSet<Arc<RelPath>>>,
+    scanned_dir_count: AtomicUsize,
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -3434,6 +3575,11 @@ impl BackgroundScanner {
                     self.process_events(paths.into_iter().filter(|e| e.kind.is_some()).map(Into::into).collect()).await;
                 }
 
+                watch_exclusions = self.watch_exclusions_rx.recv().fuse() => {
+                    let Ok(watch_exclusions) = watch_exclusions else { break };
+                    self.update_watch_exclusions(watch_exclusions).await;
+                }
+
                 paths = global_gitignore_events.next().fuse() => {
                     match paths.as_deref() {
                         Some([event, ..]) => {
@@ -3446,6 +3592,42 @@ impl BackgroundScanner {
         }
     }
 
+    /// Starts or stops watching loaded directories whose exclusion status changed, without
+    /// rescanning the worktree. Directories that are watched again are refreshed, since
+    /// changes made while they were excluded were not observed.
+    async fn update_watch_exclusions(&self, watch_exclusions: PathMatcher) {
+        let mut paths_to_refresh = Vec::new();
+        {
+            let mut state = self.state.lock().await;
+            let previous_watch_exclusions =
+                mem::replace(&mut state.watch_exclusions, watch_exclusions);
+            let was_path_watch_excluded = |path: &RelPath| {
+                path.ancestors()
+                    .any(|ancestor| previous_watch_exclusions.is_match(ancestor))
+            };
+            for entry in state.snapshot.entries(true, 0) {
+                if entry.kind != EntryKind::Dir {
+                    continue;
+                }
+                let was_excluded = was_path_watch_excluded(&entry.path);
+                let is_excluded = state.is_path_watch_excluded(&entry.path);
+                if was_excluded == is_excluded {
+                    continue;
+                }
+                let abs_path = state.snapshot.absolutize(&entry.path);
+                if is_excluded {
+                    self.watcher.remove(&abs_path).log_err();
+                } else {
+                    self.watcher.add(&abs_path).log_err();
+                    paths_to_refresh.push(abs_path);
+                }
+            }
+        }
+        if !paths_to_refresh.is_empty() {
+            self.process_events(paths_to_refresh).await;
+        }
+    }
+
     async fn process_scan_request(&self, mut request: ScanRequest, scanning: bool) -> bool {
         log::debug!("rescanning paths {:?}", request.relative_paths);
 
@@ -3760,6 +3942,9 @@ impl BackgroundScanner {
                     if let Some(entry) = state.snapshot.entry_for_path(ancestor)
                         && entry.kind == EntryKind::UnloadedDir
                     {
+                        if self.is_unscanned_symlink(entry) {
+                            break;
+                        }
                         let abs_path = root_path.join(ancestor.as_std_path());
                         state
                             .enqueue_scan_dir(
@@ -3797,6 +3982,8 @@ impl BackgroundScanner {
         }
 
         let progress_update_count = AtomicUsize::new(0);
+        self.scanned_dir_count.store(0, SeqCst);
+        self.claimed_dirs.lock().clear();
         self.executor
             .scoped_priority(Priority::Low, |scope| {
                 for _ in 0..self.executor.num_cpus() {
@@ -3829,6 +4016,12 @@ impl BackgroundScanner {
                                         Ok(_) => {
                                             last_progress_update_count += 1;
                                             self.send_status_update(true, SmallVec::new()).await;
+                                            self.status_updates_tx
+                                                .unbounded_send(ScanState::Progress(ScanProgress {
+                                                    scanned_directories: self.scanned_dir_count.load(SeqCst),
+                                                    queued_directories: scan_jobs_rx.len() + self.priority_scan_jobs_rx.len(),
+                                                }))
+                                                .ok();
                                         }
                                         Err(count) => {
                                             last_progress_update_count = count;
@@ -3837,13 +4030,42 @@ impl BackgroundScanner {
                                     progress_update_timer.set(self.progress_timer(enable_progress_updates).fuse());
                                 }
 
+                                // Move the queued directories leading to newly prioritized paths
+                                // ahead of the rest of the worktree.
+                                path = self.priority_path_requests_rx.recv().fuse() => {
+                                    let Ok(path) = path else { break };
+                                    self.enqueue_priority_dir(&path).await;
+                                }
+
+                                // Load directories leading to prioritized paths before the rest
+                                // of the worktree.
+                                job = self.priority_scan_jobs_rx.recv().fuse() => {
+                                    let Ok(job) = job else { break };
+                                    if !self.claimed_dirs.lock().insert(job.path.clone()) {
+                                        continue;
+                                    }
+                                    self.scan_dir(&job).await.log_err();
+                                    self.scanned_dir_count.fetch_add(1, SeqCst);
+                                }
+
                                 // Recursively load directories from the file system.
                                 job = scan_jobs_rx.recv().fuse() => {
-                                    let Ok(job) = job else { break };
+                                    let Ok(job) = job else {
+                                        // Prioritized directories are queued separately, so keep
+                                        // going until they are scanned too.
+                                        if self.priority_scan_jobs_rx.is_empty() {
+                                            break;
+                                        }
+                                        continue;
+                                    };
+                                    if !self.claimed_dirs.lock().insert(job.path.clone()) {
+                                        continue;
+                                    }
                                     if let Err(err) = self.scan_dir(&job).await
                                         && job.path.is_empty() {
                                             log::error!("error scanning directory {:?}: {}", job.abs_path, err);
                                         }
+                                    self.scanned_dir_count.fetch_add(1, SeqCst);
                                 }
                             }
                         }
@@ -3851,6 +4073,34 @@ impl BackgroundScanner {
                 }
             })
             .await;
+        self.claimed_dirs.lock().clear();
+    }
+
+    /// Queues the deepest loaded ancestor of `path` that is still waiting to be scanned with
+    /// priority, so that its contents are scanned ahead of the rest of the worktree.
+    async fn enqueue_priority_dir(&self, path: &RelPath) {
+        let state = self.state.lock().await;
+        let Some(entry) = path
+            .ancestors()
+            .find_map(|ancestor| state.snapshot.entry_for_path(ancestor))
+        else {
+            return;
+        };
+        if entry.kind != EntryKind::PendingDir
+            || self.claimed_dirs.lock().contains(&entry.path)
+            || self.is_unscanned_symlink(entry)
+        {
+            return;
+        }
+        let abs_path = state.snapshot.abs_path.join(entry.path.as_std_path());
+        state
+            .enqueue_scan_dir(
+                abs_path.into(),
+                entry,
+                &self.priority_scan_jobs_tx,
+                self.fs.as_ref(),
+            )
+            .await;
     }
 
     async fn send_status_update(
@@ -3981,6 +4231,12 @@ impl BackgroundScanner {
                 }
             };
 
+            if child_metadata.is_symlink && self.settings.symlink_policy == SymlinkPolicy::Ignore {
+                log::debug!("skipping symlink {child_path:?}");
+                self.state.lock().await.remove_path(&child_path);
+                continue;
+            }
+
             let mut child_entry = Entry::new(
                 child_path.clone(),
                 &child_metadata,
@@ -3989,6 +4245,7 @@ impl BackgroundScanner {
                 None,
             );
 
+            let mut is_symlink_cycle = false;
             if job.is_external {
                 child_entry.is_external = true;
             } else if child_metadata.is_symlink {
@@ -4015,6 +4272,20 @@ impl BackgroundScanner {
 
                 if !canonical_path.starts_with(root_canonical_path) {
                     child_entry.is_external = true;
+                    self.status_updates_tx
+                        .unbounded_send(ScanState::SymlinkEscapedRoot {
+                            path: child_path.clone(),
+                            target: canonical_path.clone().into(),
+                        })
+                        .ok();
+                }
+
+                if child_metadata.is_dir {
+                    is_symlink_cycle = self
+                        .fs
+                        .canonicalize(&job.abs_path)
+                        .await
+                        .is_ok_and(|parent_path| parent_path.starts_with(&canonical_path));
                 }
 
                 child_entry.canonical_path = Some(canonical_path.into());
@@ -4025,8 +4296,12 @@ impl BackgroundScanner {
                 child_entry.is_always_included =
                     self.settings.is_path_always_included(&child_path, true);
 
-                // Avoid recursing until crash in the case of a recursive symlink
-                if job.ancestor_inodes.contains(&child_entry.inode) {
+                if is_symlink_cycle || self.is_unscanned_symlink(&child_entry) {
+                    log::debug!("not scanning the target of symlink {child_path:?}");
+                    child_entry.kind = EntryKind::UnloadedDir;
+                    new_jobs.push(None);
+                } else if job.ancestor_inodes.contains(&child_entry.inode) {
+                    // Avoid recursing until crash in the case of a recursive symlink
                     new_jobs.push(None);
                 } else {
                     let mut ancestor_inodes = job.ancestor_inodes.clone();
@@ -4092,17 +4367,35 @@ impl BackgroundScanner {
         }
 
         state.populate_dir(job.path.clone(), new_entries, new_ignore);
-        self.watcher.add(job.abs_path.as_ref()).log_err();
+        if !state.is_path_watch_excluded(&job.path) {
+            self.watcher.add(job.abs_path.as_ref()).log_err();
+        }
 
         for new_job in new_jobs.into_iter().flatten() {
-            job.scan_queue
-                .try_send(new_job)
-                .expect("channel is unbounded");
+            let scan_queue = if self.is_priority_scan_path(&new_job.path) {
+                &self.priority_scan_jobs_tx
+            } else {
+                &job.scan_queue
+            };
+            scan_queue.try_send(new_job).expect("channel is unbounded");
         }
 
         Ok(())
     }
 
+    /// Whether the contents of a symlinked directory are left unscanned, so that it's shown as a
+    /// link.
+    fn is_unscanned_symlink(&self, entry: &Entry) -> bool {
+        entry.canonical_path.is_some() && self.settings.symlink_policy == SymlinkPolicy::ShowAsLink
+    }
+
+    fn is_priority_scan_path(&self, path: &RelPath) -> bool {
+        self.priority_paths
+            .lock()
+            .iter()
+            .any(|priority_path| priority_path.starts_with(path))
+    }
+
     /// All list arguments should be sorted before calling this function
     async fn reload_entries_for_paths(
         &self,
@@ -4166,11 +4459,23 @@ impl BackgroundScanner {
             let abs_path: Arc<Path> = root_abs_path.join(path.as_std_path()).into();
             match metadata {
                 Ok(Some((metadata, canonical_path))) => {
+                    if metadata.is_symlink && self.settings.symlink_policy == SymlinkPolicy::Ignore
+                    {
+                        continue;
+                    }
                     let ignore_stack = state
                         .snapshot
                         .ignore_stack_for_abs_path(&abs_path, metadata.is_dir, self.fs.as_ref())
                         .await;
                     let is_external = !canonical_path.starts_with(&root_canonical_path);
+                    if is_external && metadata.is_symlink {
+                        self.status_updates_tx
+                            .unbounded_send(ScanState::SymlinkEscapedRoot {
+                                path: path.clone(),
+                                target: canonical_path.as_path().into(),
+                            })
+                            .ok();
+                    }
                     let entry_id = state.entry_id_for(self.next_entry_id.as_ref(), path, &metadata);
                     let mut fs_entry = Entry::new(
                         path.clone(),
@@ -4193,7 +4498,9 @@ impl BackgroundScanner {
                     fs_entry.is_hidden = self.settings.is_path_hidden(path);
 
                     if let (Some(scan_queue_tx), true) = (&scan_queue_tx, is_dir) {
-                        if state.should_scan_directory(&fs_entry)
+                        if self.is_unscanned_symlink(&fs_entry) {
+                            fs_entry.kind = EntryKind::UnloadedDir;
+                        } else if state.should_scan_directory(&fs_entry)
                             || (fs_entry.path.is_empty()
                                 && abs_path.file_name() == Some(OsStr::new(DOT_GIT)))
                         {
@@ -5406,7 +5713,7 @@ fn decode_byte(bytes: Vec<u8>) -> anyhow::Result<(String, &'static Encoding, boo
             return Ok((cow.into_owned(), encoding, false));
         }
         ByteContent::Binary => {
-            anyhow::bail!("Binary files are not supported");
+            return Err(BinaryFileError.into());
         }
         ByteContent::Unknown => {}
     }
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use anyhow::Context as _;
-use settings::{RegisterSetting, Settings};
+use settings::{RegisterSetting, Settings, SymlinkPolicy};
 use util::{
     ResultExt,
     paths::{PathMatcher, PathStyle},
@@ -20,6 +20,9 @@ pub struct WorktreeSettings {
     pub parent_dir_scan_inclusions: PathMatcher,
     pub private_files: PathMatcher,
     pub hidden_files: PathMatcher,
+    pub watch_exclusions: PathMatcher,
+    pub symlink_policy: SymlinkPolicy,
+    pub autosave_exclusions: PathMatcher,
 }
 
 impl WorktreeSettings {
@@ -41,6 +44,11 @@ impl WorktreeSettings {
         }
     }
 
+    pub fn is_path_autosave_excluded(&self, path: &RelPath) -> bool {
+        path.ancestors()
+            .any(|ancestor| self.autosave_exclusions.is_match(ancestor))
+    }
+
     pub fn is_path_hidden(&self, path: &RelPath) -> bool {
         path.ancestors()
             .any(|ancestor| self.hidden_files.is_match(ancestor))
@@ -54,6 +62,8 @@ impl Settings for WorktreeSettings {
         let file_scan_inclusions = worktree.file_scan_inclusions.unwrap();
         let private_files = worktree.private_files.unwrap().0;
         let hidden_files = worktree.hidden_files.unwrap();
+        let watch_exclusions = worktree.watch_exclusions.unwrap();
+        let autosave_exclusions = worktree.autosave_exclusions.unwrap();
         let parsed_file_scan_inclusions: Vec<String> = file_scan_inclusions
             .iter()
             .flat_map(|glob| {
@@ -84,6 +94,13 @@ impl Settings for WorktreeSettings {
             hidden_files: path_matchers(hidden_files, "hidden_files")
                 .log_err()
                 .unwrap_or_default(),
+            watch_exclusions: path_matchers(watch_exclusions, "watch_exclusions")
+                .log_err()
+                .unwrap_or_default(),
+            symlink_policy: worktree.symlinks.unwrap(),
+            autosave_exclusions: path_matchers(autosave_exclusions, "autosave_exclusions")
+                .log_err()
+                .unwrap_or_default(),
         }
     }
 }
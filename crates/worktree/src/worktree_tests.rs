@@ -14,7 +14,7 @@ use pretty_assertions::assert_eq;
 use rand::prelude::*;
 
 use serde_json::json;
-use settings::SettingsStore;
+use settings::{SettingsStore, SymlinkPolicy};
 use std::{
     mem,
     path::{Path, PathBuf},
@@ -345,6 +345,143 @@ async fn test_symlinks_pointing_outside(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_symlink_policies(cx: &mut TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/root",
+        json!({
+            "dir1": {
+                "deps": {},
+                "src": {
+                    "a.rs": "",
+                },
+            },
+            "dir2": {
+                "b.rs": "",
+            },
+        }),
+    )
+    .await;
+    fs.create_symlink("/root/dir1/src-link".as_ref(), "src".into())
+        .await
+        .unwrap();
+    fs.create_symlink("/root/dir1/deps/up".as_ref(), "..".into())
+        .await
+        .unwrap();
+
+    let tree = Worktree::local(
+        Path::new("/root/dir1"),
+        true,
+        fs.clone(),
+        Default::default(),
+        true,
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+
+    // Symlinks are followed by default, except for the ones pointing to their own ancestors.
+    tree.read_with(cx, |tree, _| {
+        assert_eq!(
+            tree.entries(true, 0)
+                .map(|entry| entry.path.as_ref())
+                .collect::<Vec<_>>(),
+            vec![
+                rel_path(""),
+                rel_path("deps"),
+                rel_path("deps/up"),
+                rel_path("src"),
+                rel_path("src/a.rs"),
+                rel_path("src-link"),
+                rel_path("src-link/a.rs"),
+            ]
+        );
+        assert_eq!(
+            tree.entry_for_path(rel_path("deps/up")).unwrap().kind,
+            EntryKind::UnloadedDir
+        );
+    });
+
+    let escaped_symlinks = Arc::new(Mutex::new(Vec::new()));
+    tree.update(cx, |_, cx| {
+        let escaped_symlinks = escaped_symlinks.clone();
+        cx.subscribe(&tree, move |_, _, event, _| {
+            if let Event::SymlinkEscapedRoot { path, target } = event {
+                escaped_symlinks.lock().push((path.clone(), target.clone()));
+            }
+        })
+        .detach();
+    });
+    fs.create_symlink("/root/dir1/deps/dep-dir2".as_ref(), "../../dir2".into())
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    assert_eq!(
+        mem::take(&mut *escaped_symlinks.lock()),
+        [(
+            rel_path("deps/dep-dir2").into(),
+            Path::new("/root/dir2").into()
+        )]
+    );
+
+    for (policy, expected_paths) in [
+        (
+            SymlinkPolicy::ShowAsLink,
+            vec![
+                rel_path(""),
+                rel_path("deps"),
+                rel_path("deps/dep-dir2"),
+                rel_path("deps/up"),
+                rel_path("src"),
+                rel_path("src/a.rs"),
+                rel_path("src-link"),
+            ],
+        ),
+        (
+            SymlinkPolicy::Ignore,
+            vec![
+                rel_path(""),
+                rel_path("deps"),
+                rel_path("src"),
+                rel_path("src/a.rs"),
+            ],
+        ),
+    ] {
+        cx.update(|cx| {
+            cx.update_global::<SettingsStore, _>(|store, cx| {
+                store.update_user_settings(cx, |settings| {
+                    settings.project.worktree.symlinks = Some(policy);
+                });
+            });
+        });
+        let tree = Worktree::local(
+            Path::new("/root/dir1"),
+            true,
+            fs.clone(),
+            Default::default(),
+            true,
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+        cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+            .await;
+        tree.read_with(cx, |tree, _| {
+            assert_eq!(
+                tree.entries(true, 0)
+                    .map(|entry| entry.path.as_ref())
+                    .collect::<Vec<_>>(),
+                expected_paths,
+                "{policy:?}"
+            );
+        });
+    }
+}
+
 #[cfg(target_os = "macos")]
 #[gpui::test]
 async fn test_renaming_case_only(cx: &mut TestAppContext) {
@@ -1074,6 +1211,73 @@ async fn test_file_scan_exclusions(cx: &mut TestAppContext) {
     });
 }
 
+#[gpui::test]
+async fn test_watch_exclusions(cx: &mut TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/root",
+        json!({
+            "node_modules": {
+                "prettier": {
+                    "index.js": "",
+                },
+            },
+            "target": {
+                "debug": {},
+            },
+            "src": {
+                "lib.rs": "",
+            },
+        }),
+    )
+    .await;
+
+    let tree = Worktree::local(
+        Path::new("/root"),
+        true,
+        fs.clone(),
+        Default::default(),
+        true,
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+    cx.executor().run_until_parked();
+
+    // Only the directories matched by the default exclusions are left unwatched.
+    let watched_dirs = |dirs: &[&str]| {
+        dirs.iter()
+            .map(|dir| PathBuf::from(format!("/root{dir}")))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        fs.watched_dirs(),
+        watched_dirs(&["", "/src", "/target", "/target/debug"])
+    );
+    tree.read_with(cx, |tree, _| {
+        assert!(
+            tree.entry_for_path(rel_path("node_modules/prettier/index.js"))
+                .is_some()
+        );
+    });
+
+    cx.update(|cx| {
+        cx.update_global::<SettingsStore, _>(|store, cx| {
+            store.update_user_settings(cx, |settings| {
+                settings.project.worktree.watch_exclusions = Some(vec!["**/target".to_string()]);
+            });
+        });
+    });
+    cx.executor().run_until_parked();
+    assert_eq!(
+        fs.watched_dirs(),
+        watched_dirs(&["", "/node_modules", "/node_modules/prettier", "/src"])
+    );
+}
+
 #[gpui::test]
 async fn test_hidden_files(cx: &mut TestAppContext) {
     init_test(cx);
@@ -1411,6 +1615,148 @@ async fn test_create_directory_during_initial_scan(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_expanded_directory_scanned_ahead_of_queue(cx: &mut TestAppContext) {
+    init_test(cx);
+    let fs = FakeFs::new(cx.background_executor.clone());
+    let mut root = serde_json::Map::new();
+    for ix in 0..200 {
+        root.insert(format!("dir-{ix:03}"), json!({ "file.txt": "" }));
+    }
+    root.insert("target".into(), json!({ "x": { "y": { "file.txt": "" } } }));
+    fs.insert_tree("/root", serde_json::Value::Object(root))
+        .await;
+
+    let tree = Worktree::local(
+        "/root".as_ref(),
+        true,
+        fs,
+        Default::default(),
+        true,
+        &mut cx.to_async(),
+    )
+    .await
+    .unwrap();
+
+    // Once the root is scanned, `target` is queued behind all of its siblings. Expanding it
+    // moves it ahead of them.
+    let progress_events = Arc::new(Mutex::new(Vec::new()));
+    let pending_dirs_when_loaded = Arc::new(Mutex::new(None));
+    tree.update(cx, |_, cx| {
+        let progress_events = progress_events.clone();
+        let pending_dirs_when_loaded = pending_dirs_when_loaded.clone();
+        let mut expanded = false;
+        cx.subscribe(&cx.entity(), move |tree, _, event, cx| match event {
+            Event::ScanProgress(progress) => progress_events.lock().push(*progress),
+            Event::UpdatedEntries(_) => {
+                if !expanded
+                    && let Some(entry_id) = tree
+                        .entry_for_path(rel_path("target"))
+                        .map(|entry| entry.id)
+                {
+                    expanded = true;
+                    if let Some(task) = tree.expand_entry(entry_id, cx) {
+                        task.detach();
+                    }
+                }
+                let is_loaded = tree
+                    .entry_for_path(rel_path("target/x/y"))
+                    .is_some_and(|entry| entry.kind == EntryKind::Dir);
+                let mut pending_dirs_when_loaded = pending_dirs_when_loaded.lock();
+                if is_loaded && pending_dirs_when_loaded.is_none() {
+                    *pending_dirs_when_loaded = Some(
+                        tree.entries(true, 0)
+                            .filter(|entry| entry.kind == EntryKind::PendingDir)
+                            .count(),
+                    );
+                }
+            }
+            _ => {}
+        })
+        .detach();
+    });
+
+    cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+        .await;
+    cx.executor().run_until_parked();
+
+    let pending_dirs_when_loaded = pending_dirs_when_loaded.lock().unwrap();
+    assert!(
+        pending_dirs_when_loaded > 0,
+        "the expanded directory was only loaded once the whole worktree was"
+    );
+    let progress_events = progress_events.lock();
+    assert!(!progress_events.is_empty());
+    assert!(
+        progress_events
+            .iter()
+            .any(|progress| progress.queued_directories > 0)
+    );
+    tree.read_with(cx, |tree, _| {
+        assert!(
+            tree.entries(true, 0)
+                .all(|entry| entry.kind != EntryKind::PendingDir)
+        );
+    });
+}
+
+#[gpui::test]
+async fn test_directory_queued_with_priority_is_scanned_once(cx: &mut TestAppContext) {
+    init_test(cx);
+    let mut root = serde_json::Map::new();
+    for ix in 0..50 {
+        root.insert(format!("dir-{ix:03}"), json!({ "a": { "file.txt": "" } }));
+    }
+    let root = serde_json::Value::Object(root);
+
+    let mut read_dir_call_counts = Vec::new();
+    for prioritize in [false, true] {
+        let fs = FakeFs::new(cx.background_executor.clone());
+        fs.insert_tree("/root", root.clone()).await;
+        let tree = Worktree::local(
+            "/root".as_ref(),
+            true,
+            fs.clone(),
+            Default::default(),
+            true,
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+
+        // Prioritize every directory as soon as it's known, so that each one is queued both
+        // normally and with priority.
+        if prioritize {
+            tree.update(cx, |_, cx| {
+                cx.subscribe(&cx.entity(), move |tree, _, event, _| {
+                    if let Event::UpdatedEntries(changes) = event {
+                        let tree = tree.as_local().unwrap();
+                        for (path, _, _) in changes.iter() {
+                            tree.prioritize_path_for_scan(path.clone());
+                        }
+                    }
+                })
+                .detach();
+            });
+        }
+
+        cx.read(|cx| tree.read(cx).as_local().unwrap().scan_complete())
+            .await;
+        cx.executor().run_until_parked();
+
+        tree.read_with(cx, |tree, _| {
+            assert_eq!(
+                tree.entries(true, 0)
+                    .filter(|entry| entry.kind == EntryKind::Dir)
+                    .count(),
+                101
+            );
+        });
+        read_dir_call_counts.push(fs.read_dir_call_count());
+    }
+    assert_eq!(read_dir_call_counts[0], read_dir_call_counts[1]);
+}
+
 #[gpui::test]
 async fn test_create_dir_all_on_create_entry(cx: &mut TestAppContext) {
     init_test(cx);